@@ -7,8 +7,8 @@ use std::str::FromStr;
 use std::thread;
 use std::time::Duration;
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
     RawType,
     TaggedSocket,
 };
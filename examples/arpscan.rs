@@ -0,0 +1,78 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use usrnet::stack::repr::Ipv4AddressCidr;
+use usrnet::stack::socket::{
+    RawType,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Lists the hosts in a subnet which reply to ARP requests.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg SUBNET:     +takes_value "Subnet to scan, e.g. 10.0.0.0/24, defaults to the interface's")
+        (@arg TIMEOUT:    +takes_value --timeout "Timeout in milliseconds for replies after each sweep")
+        (@arg RETRIES:    +takes_value --retry "Number of times to retry hosts which did not reply")
+    ).get_matches();
+
+    let mut config = ArpScanConfig::default();
+
+    if let Some(timeout) = matches.value_of("TIMEOUT") {
+        config.timeout = timeout
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .expect("Bad timeout!");
+    }
+
+    if let Some(retries) = matches.value_of("RETRIES") {
+        config.retries = retries.parse::<usize>().expect("Bad retry count!");
+    }
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let cidr = matches
+        .value_of("SUBNET")
+        .map(|subnet| Ipv4AddressCidr::from_str(subnet).expect("Bad subnet!"))
+        .unwrap_or(interface.ipv4_addr);
+
+    let raw_socket = socket_env.raw_socket(RawType::Ethernet);
+    let raw_handle = socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    println!("Starting ARP scan of {}.", cidr);
+
+    let hosts = arpscan(
+        &mut interface,
+        &mut socket_set,
+        raw_handle,
+        cidr,
+        config,
+        |host| {
+            let vendor = match host.vendor {
+                Some(vendor) => vendor,
+                None if host.eth_addr.is_local() => "(Unknown: locally administered)",
+                None => "(Unknown)",
+            };
+            println!(
+                "{}\t{}\t{}\ttime={:.2} ms",
+                host.ipv4_addr,
+                host.eth_addr,
+                vendor,
+                (host.rtt.as_secs() as f64) * 1000.0 + (host.rtt.subsec_nanos() as f64) / 1000000.0,
+            );
+        },
+    );
+
+    println!("{} hosts responded.", hosts.len());
+}
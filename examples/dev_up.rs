@@ -1,6 +1,8 @@
 extern crate env_logger;
 extern crate usrnet;
 
+use std::time::Duration;
+
 use usrnet::examples::*;
 
 /// Opens and brings UP a Linux TAP interface. You should be able to issue ping
@@ -13,5 +15,9 @@ fn main() {
 
     loop {
         env::tick(&mut interface, &mut socket_set);
+        // Wake up periodically to expire state such as IPv4 fragments.
+        interface
+            .poll_wait(Some(Duration::from_millis(100)))
+            .unwrap();
     }
 }
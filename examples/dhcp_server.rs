@@ -0,0 +1,84 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use usrnet::stack::repr::{
+    dhcpv4_ports,
+    Ipv4Address,
+};
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Leases addresses from a pool to DHCP clients on the interface's network.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg POOL_START: +takes_value +required "First IP address of the pool to lease")
+        (@arg POOL_LEN:   +takes_value +required "Number of addresses in the pool")
+        (@arg ROUTER:     -r --router +takes_value "IP address of the router to hand out")
+        (@arg DNS:        -d --dns +takes_value +multiple "IP address of a DNS server to hand out")
+        (@arg LEASE:      -l --lease +takes_value "Lease duration in seconds, 3600 by default")
+    ).get_matches();
+
+    let parse_addr = |addr: &str| Ipv4Address::from_str(addr).expect("Bad IP address!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let subnet_len = interface.ipv4_addr.subnet_len() as u32;
+    let config = DhcpServerConfig {
+        pool_start: parse_addr(matches.value_of("POOL_START").unwrap()),
+        pool_len: matches
+            .value_of("POOL_LEN")
+            .and_then(|len| len.parse::<u32>().ok())
+            .expect("Bad pool length!"),
+        subnet_mask: Ipv4Address::from(
+            (!0u32).checked_shl(32 - subnet_len).unwrap_or(0),
+        ),
+        router: matches.value_of("ROUTER").map(&parse_addr),
+        dns_servers: matches
+            .values_of("DNS")
+            .map(|addrs| addrs.map(&parse_addr).collect())
+            .unwrap_or_default(),
+        lease_duration: Duration::from_secs(
+            matches
+                .value_of("LEASE")
+                .unwrap_or("3600")
+                .parse::<u64>()
+                .expect("Bad lease duration!"),
+        ),
+    };
+
+    let socket_addr = SocketAddr {
+        addr: *interface.ipv4_addr,
+        port: dhcpv4_ports::SERVER,
+    };
+    let mut udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+    udp_socket.set_broadcast(true);
+    let udp_handle = socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    println!(
+        "Leasing {} address(es) from {} on {}.",
+        config.pool_len, config.pool_start, socket_addr
+    );
+
+    let mut server = DhcpServer::new(config);
+    dhcp_server(
+        &mut interface,
+        &mut socket_set,
+        udp_handle,
+        &mut server,
+        || true,
+    );
+}
@@ -0,0 +1,83 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+
+use usrnet::stack::repr::{
+    dns_types,
+    DnsRecordData,
+    Ipv4Address,
+};
+use usrnet::stack::service::dns::Resolver;
+use usrnet::stack::time::SystemEnv;
+use usrnet::examples::*;
+
+/// Looks up DNS records for a name.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg NAME:    +takes_value +required "Name to look up")
+        (@arg TYPE:    +takes_value --type    "Record type to look up, one of A, AAAA, CNAME or PTR")
+        (@arg SERVER:  +takes_value --server  "Address of the DNS server to query")
+        (@arg TIMEOUT: +takes_value --timeout "Timeout in milliseconds for each DNS query")
+    ).get_matches();
+
+    let name = matches.value_of("NAME").unwrap();
+
+    let record_type = match matches.value_of("TYPE").unwrap_or("A") {
+        "A" => dns_types::A,
+        "AAAA" => dns_types::AAAA,
+        "CNAME" => dns_types::CNAME,
+        "PTR" => dns_types::PTR,
+        _ => panic!("Bad record type!"),
+    };
+
+    let server_addr = matches
+        .value_of("SERVER")
+        .or(Some("8.8.8.8"))
+        .and_then(|addr| Ipv4Address::from_str(addr).ok())
+        .expect("Bad IP address!");
+
+    let timeout = matches
+        .value_of("TIMEOUT")
+        .or(Some("1000"))
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .expect("Bad timeout!");
+
+    let mut interface = env::default_interface();
+    interface.dns_servers = vec![server_addr];
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let mut resolver =
+        Resolver::new(&socket_env, &mut socket_set, timeout, SystemEnv::new()).unwrap();
+
+    println!("Looking up {} via {}.", name, server_addr);
+
+    match dns_lookup(
+        &mut interface,
+        &mut socket_set,
+        &mut resolver,
+        name,
+        record_type,
+    ) {
+        Ok(ref records) if records.is_empty() => println!("No records found."),
+        Ok(records) => {
+            for record in records {
+                match record {
+                    DnsRecordData::A(addr) => println!("{} has address {}", name, addr),
+                    DnsRecordData::Aaaa(addr) => println!("{} has IPv6 address {}", name, addr),
+                    DnsRecordData::Cname(alias) => println!("{} is an alias for {}", name, alias),
+                    DnsRecordData::Ptr(ptr) => println!("{} domain name pointer {}", name, ptr),
+                    DnsRecordData::Other(record_type, data) => {
+                        println!("{} has type {} record {:?}", name, record_type, data)
+                    }
+                }
+            }
+        }
+        Err(err) => println!("Lookup failed with {:?}.", err),
+    }
+}
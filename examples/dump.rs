@@ -0,0 +1,59 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+
+use usrnet::stack::socket::{
+    RawType,
+    TaggedSocket,
+};
+use usrnet::examples::dump::Filter;
+use usrnet::examples::*;
+
+/// Prints a one-line summary of each frame received by the interface which
+/// matches a tcpdump-like filter, e.g. 'udp and port 53'.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg FILTER: ... "Filter expression, e.g. 'tcp and port 80 and host 10.0.0.1'")
+        (@arg COUNT: -c --count +takes_value "Exit after this many frames")
+    ).get_matches();
+
+    let filter = matches
+        .values_of("FILTER")
+        .map(|values| values.collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    let filter = Filter::from_str(&filter).expect("Bad filter!");
+
+    let count = matches
+        .value_of("COUNT")
+        .map(|count| count.parse::<usize>().expect("Bad count!"));
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let raw_socket = socket_env.raw_socket(RawType::Ethernet);
+    let raw_handle = socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    let mut captured = 0;
+
+    dump(
+        &mut interface,
+        &mut socket_set,
+        raw_handle,
+        filter,
+        |description| {
+            println!("{}", description);
+            captured += 1;
+            count.is_none_or(|count| captured < count)
+        },
+    );
+
+    println!("{} frames captured.", captured);
+}
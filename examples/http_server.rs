@@ -0,0 +1,62 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+static INDEX_HTML: &str = "<html>\n\
+                           <head><title>usrnet</title></head>\n\
+                           <body><h1>Hello from usrnet!</h1></body>\n\
+                           </html>\n";
+
+/// Serves a static page over HTTP/1.0.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg PORT: +takes_value "TCP port to bind, 80 by default")
+    ).get_matches();
+
+    let port = matches
+        .value_of("PORT")
+        .unwrap_or("80")
+        .parse::<u16>()
+        .expect("Bad TCP port!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let socket_addr = SocketAddr {
+        addr: *interface.ipv4_addr,
+        port,
+    };
+    let tcp_listener = socket_env.tcp_listener(socket_addr, 16, 16).unwrap();
+    let listener_handle = socket_set
+        .add_socket(TaggedSocket::TcpListener(tcp_listener))
+        .unwrap();
+
+    println!(
+        "Running HTTP server; Use 'curl -0 http://{}:{}/' to fetch a page.",
+        socket_addr.addr, socket_addr.port
+    );
+
+    http_server(
+        &mut interface,
+        &mut socket_set,
+        listener_handle,
+        |request| {
+            println!("{} {} {}", request.method, request.path, request.version);
+            match request.path.as_str() {
+                "/" | "/index.html" => HttpResponse::ok("text/html", INDEX_HTML.as_bytes().to_vec()),
+                _ => HttpResponse::error(404, "Not Found"),
+            }
+        },
+        || true,
+    );
+}
@@ -0,0 +1,136 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::io::{
+    self,
+    Read,
+};
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Connects to or listens for a TCP or UDP endpoint, piping stdin to the
+/// endpoint and data received from the endpoint to stdout.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg ADDRESS: +takes_value "IP address to connect or send to, unless listening")
+        (@arg PORT:    -p --port +takes_value +required "Port to connect or send to, or listen on")
+        (@arg LISTEN:  -l --listen "Listen for a connection or datagrams")
+        (@arg UDP:     -u --udp "Use UDP rather than TCP")
+    ).get_matches();
+
+    let port = matches
+        .value_of("PORT")
+        .and_then(|port| port.parse::<u16>().ok())
+        .expect("Bad port!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let (send, recv) = mpsc::channel();
+
+    // Sender is dropped at the end of stdin, which shuts down the connection.
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        loop {
+            match io::stdin().read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(len) => send.send(buf[.. len].to_vec()).unwrap(),
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+
+    let res = if matches.is_present("LISTEN") {
+        let socket_addr = SocketAddr {
+            addr: *interface.ipv4_addr,
+            port,
+        };
+
+        eprintln!("Listening on {}.", socket_addr);
+
+        if matches.is_present("UDP") {
+            let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+            let udp_handle = socket_set
+                .add_socket(TaggedSocket::Udp(udp_socket))
+                .unwrap();
+            nc_udp(
+                &mut interface,
+                &mut socket_set,
+                udp_handle,
+                None,
+                &recv,
+                &mut stdout,
+            )
+        } else {
+            let tcp_listener = socket_env.tcp_listener(socket_addr, 1, 1).unwrap();
+            let listener_handle = socket_set
+                .add_socket(TaggedSocket::TcpListener(tcp_listener))
+                .unwrap();
+            nc_listen(
+                &mut interface,
+                &mut socket_set,
+                listener_handle,
+                &recv,
+                &mut stdout,
+            ).map(|socket_addr| eprintln!("Connection with {} closed.", socket_addr))
+        }
+    } else {
+        let socket_addr = SocketAddr {
+            addr: matches
+                .value_of("ADDRESS")
+                .and_then(|addr| Ipv4Address::from_str(addr).ok())
+                .expect("Bad IP address!"),
+            port,
+        };
+
+        if matches.is_present("UDP") {
+            let udp_socket = socket_env
+                .udp_socket_ephemeral(*interface.ipv4_addr)
+                .unwrap();
+            let udp_handle = socket_set
+                .add_socket(TaggedSocket::Udp(udp_socket))
+                .unwrap();
+            nc_udp(
+                &mut interface,
+                &mut socket_set,
+                udp_handle,
+                Some(socket_addr),
+                &recv,
+                &mut stdout,
+            )
+        } else {
+            let tcp_socket = socket_env
+                .tcp_socket_ephemeral(*interface.ipv4_addr)
+                .unwrap();
+            let tcp_handle = socket_set
+                .add_socket(TaggedSocket::Tcp(tcp_socket))
+                .unwrap();
+            nc_connect(
+                &mut interface,
+                &mut socket_set,
+                tcp_handle,
+                socket_addr,
+                &recv,
+                &mut stdout,
+            )
+        }
+    };
+
+    if let Err(err) = res {
+        eprintln!("Failed with {:?}.", err);
+    }
+}
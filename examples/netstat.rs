@@ -0,0 +1,107 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Accepts TCP connections and UDP packets on a port, discarding any data
+/// received, and periodically prints a table of the sockets along with the
+/// protocol counters of the interface.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg PORT: +takes_value +required "TCP and UDP port to bind")
+        (@arg INTERVAL: -i --interval +takes_value "Seconds between reports")
+    ).get_matches();
+
+    let port = matches
+        .value_of("PORT")
+        .and_then(|port| port.parse::<u16>().ok())
+        .expect("Bad port!");
+
+    let interval = matches
+        .value_of("INTERVAL")
+        .unwrap_or("1")
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .expect("Bad interval!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let socket_addr = SocketAddr {
+        addr: *interface.ipv4_addr,
+        port,
+    };
+    let tcp_listener = socket_env.tcp_listener(socket_addr, 16, 16).unwrap();
+    let tcp_handle = socket_set
+        .add_socket(TaggedSocket::TcpListener(tcp_listener))
+        .unwrap();
+    let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+    socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    println!(
+        "Listening on {}; Use 'ncat {} {}' or 'ncat -u {} {}' to add traffic.",
+        socket_addr, socket_addr.addr, socket_addr.port, socket_addr.addr, socket_addr.port
+    );
+
+    let mut last_report = Instant::now();
+    let mut buffer = [0; 1024];
+
+    loop {
+        env::tick(&mut interface, &mut socket_set);
+
+        let accepted = socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .accept();
+        if let Some((tcp_socket, _)) = accepted {
+            if let Err(err) = socket_set.add_socket(TaggedSocket::Tcp(tcp_socket)) {
+                println!("Dropping connection with {:?}.", err);
+            }
+        }
+
+        // Discard any data received, and forget connections once closed.
+        let mut closed = vec![];
+        for (socket_handle, socket) in socket_set.iter_mut_with_handles() {
+            match *socket {
+                TaggedSocket::Tcp(ref mut socket) => {
+                    while let Ok(buffer_len) = socket.recv(&mut buffer) {
+                        if buffer_len == 0 {
+                            break;
+                        }
+                    }
+                    if socket.is_closed() {
+                        closed.push(socket_handle);
+                    }
+                }
+                TaggedSocket::Udp(ref mut socket) => while socket.recv().is_ok() {},
+                _ => {}
+            }
+        }
+        for socket_handle in closed {
+            socket_set.remove(socket_handle).unwrap();
+        }
+
+        if last_report.elapsed() >= interval {
+            println!("{}", socket_set.dump());
+            println!("{}", interface.stack_stats());
+            last_report = Instant::now();
+        }
+    }
+}
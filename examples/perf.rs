@@ -0,0 +1,115 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+use std::time::Duration;
+
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Measures throughput between a client and a server over TCP or UDP, e.g. run
+/// 'perf -s' on one host and 'perf <server address>' on another.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg ADDRESS: +takes_value "Address of the server to send to, unless running as a server")
+        (@arg SERVER:  -s --server "Run as a server, receiving data from clients")
+        (@arg UDP:     -u --udp "Send UDP datagrams rather than a TCP stream")
+        (@arg PORT:    -p --port +takes_value "Port of the server")
+        (@arg TIME:    -t --time +takes_value "Seconds to send data for")
+        (@arg LENGTH:  -l --length +takes_value "Payload length of UDP datagrams")
+    ).get_matches();
+
+    let port = matches
+        .value_of("PORT")
+        .unwrap_or("5201")
+        .parse::<u16>()
+        .expect("Bad port!");
+
+    let time = matches
+        .value_of("TIME")
+        .unwrap_or("10")
+        .parse::<u64>()
+        .map(Duration::from_secs)
+        .expect("Bad time!");
+
+    let payload_len = matches
+        .value_of("LENGTH")
+        .unwrap_or("1024")
+        .parse::<usize>()
+        .expect("Bad length!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let result = if matches.is_present("SERVER") {
+        let socket_addr = SocketAddr {
+            addr: *interface.ipv4_addr,
+            port,
+        };
+
+        println!("Server listening on {}.", socket_addr);
+
+        if matches.is_present("UDP") {
+            let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+            let udp_handle = socket_set
+                .add_socket(TaggedSocket::Udp(udp_socket))
+                .unwrap();
+            perf_udp_server(&mut interface, &mut socket_set, udp_handle)
+        } else {
+            let tcp_listener = socket_env.tcp_listener(socket_addr, 1, 1).unwrap();
+            let listener_handle = socket_set
+                .add_socket(TaggedSocket::TcpListener(tcp_listener))
+                .unwrap();
+            perf_tcp_server(&mut interface, &mut socket_set, listener_handle)
+        }
+    } else {
+        let server_addr = SocketAddr {
+            addr: matches
+                .value_of("ADDRESS")
+                .and_then(|addr| Ipv4Address::from_str(addr).ok())
+                .expect("Bad IP address!"),
+            port,
+        };
+
+        println!("Sending to {} for {} seconds.", server_addr, time.as_secs());
+
+        if matches.is_present("UDP") {
+            let udp_socket = socket_env
+                .udp_socket_ephemeral(*interface.ipv4_addr)
+                .unwrap();
+            let udp_handle = socket_set
+                .add_socket(TaggedSocket::Udp(udp_socket))
+                .unwrap();
+            perf_udp_client(
+                &mut interface,
+                &mut socket_set,
+                udp_handle,
+                server_addr,
+                time,
+                payload_len,
+            )
+        } else {
+            let tcp_socket = socket_env
+                .tcp_socket_ephemeral(*interface.ipv4_addr)
+                .unwrap();
+            let tcp_handle = socket_set
+                .add_socket(TaggedSocket::Tcp(tcp_socket))
+                .unwrap();
+            perf_tcp_client(&mut interface, &mut socket_set, tcp_handle, server_addr, time)
+        }
+    };
+
+    match result {
+        Ok(result) => println!("{}", result),
+        Err(err) => println!("Failed with {:?}.", err),
+    }
+}
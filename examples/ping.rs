@@ -4,23 +4,27 @@ extern crate env_logger;
 extern crate rand;
 extern crate usrnet;
 
+use std::io::{
+    self,
+    Write,
+};
 use std::str::FromStr;
-use std::thread;
 use std::time::Duration;
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
-    RawType,
-    TaggedSocket,
-};
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::TaggedSocket;
 use usrnet::examples::*;
 
-// Sends an ICMP ping request to a host.
+// Sends ICMP ping requests to a host.
 fn main() {
     env_logger::init();
 
     let matches = clap_app!(app =>
         (@arg ADDRESS:    +takes_value +required "Address to ping")
+        (@arg COUNT:      +takes_value -c --count "Number of ICMP packets to send")
+        (@arg INTERVAL:   +takes_value -i --interval "Interval in milliseconds between ICMP packets")
+        (@arg FLOOD:      -f --flood "Send ICMP packets back to back, printing a dot per timeout")
+        (@arg PATTERN:    +takes_value -p --pattern "Hex bytes to fill the payload with, e.g. ff00")
         (@arg TIMEOUT:    +takes_value --timeout "Timeout in milliseconds for each ICMP packet")
         (@arg PACKET_LEN: +takes_value --len     "Payload size in bytes for each ICMP packet")
     ).get_matches();
@@ -30,60 +34,88 @@ fn main() {
         .and_then(|addr| Ipv4Address::from_str(addr).ok())
         .expect("Bad IP address!");
 
-    let timeout = matches
-        .value_of("TIMEOUT")
-        .or(Some("1000"))
-        .and_then(|timeout| timeout.parse::<u64>().ok())
-        .map(|timeout| Duration::from_millis(timeout))
-        .expect("Bad timeout!");
+    let mut config = PingConfig::default();
+
+    if let Some(count) = matches.value_of("COUNT") {
+        config.count = count.parse::<u16>().expect("Bad count!");
+    }
+
+    if matches.is_present("FLOOD") {
+        config.interval = Duration::from_secs(0);
+    } else if let Some(interval) = matches.value_of("INTERVAL") {
+        config.interval = interval
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .expect("Bad interval!");
+    }
 
-    let packet_len = matches
-        .value_of("PACKET_LEN")
-        .or(Some("64"))
-        .and_then(|packet_len| packet_len.parse::<usize>().ok())
-        .expect("Bad packet length!");
+    if let Some(timeout) = matches.value_of("TIMEOUT") {
+        config.timeout = timeout
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .expect("Bad timeout!");
+    }
+
+    if let Some(packet_len) = matches.value_of("PACKET_LEN") {
+        config.payload_len = packet_len.parse::<usize>().expect("Bad packet length!");
+    }
+
+    if let Some(pattern) = matches.value_of("PATTERN") {
+        config.pattern = parse_pattern(pattern).expect("Bad pattern!");
+    }
 
     let mut interface = env::default_interface();
     let socket_env = env::socket_env(&mut interface);
     let mut socket_set = env::socket_set();
 
-    let raw_socket = socket_env.raw_socket(RawType::Ipv4);
-    let raw_handle = socket_set
-        .add_socket(TaggedSocket::Raw(raw_socket))
+    let icmp_socket = socket_env
+        .icmp_socket(*interface.ipv4_addr, rand::random::<u16>())
+        .unwrap();
+    let icmp_handle = socket_set
+        .add_socket(TaggedSocket::Icmp(icmp_socket))
         .unwrap();
 
     println!(
         "PING {} ({}) {} bytes of data.",
-        ping_addr, ping_addr, packet_len
+        ping_addr, ping_addr, config.payload_len
     );
 
-    for seq in 0 .. 64 {
-        let mut payload = vec![0; packet_len];
+    let flood = matches.is_present("FLOOD");
+    let payload_len = config.payload_len;
+    let mut pinger = Pinger::new(icmp_handle, ping_addr, config);
 
-        for i in 0 .. packet_len {
-            payload[i] = rand::random::<u8>();
-        }
-
-        match ping(
-            &mut interface,
-            &mut socket_set,
-            raw_handle,
+    let stats = pinger.run(&mut interface, &mut socket_set, |seq, rtt| match rtt {
+        Some(_) if flood => {}
+        Some(time) => println!(
+            "{} bytes from {}: icmp_seq={} time={:.2} ms",
+            payload_len,
             ping_addr,
             seq,
-            rand::random::<u16>(),
-            &payload,
-            timeout,
-        ) {
-            Some(time) => println!(
-                "{} bytes from {}: icmp_seq={} time={:.2} ms",
-                payload.len(),
-                ping_addr,
-                seq,
-                (time.as_secs() as f64) * 1000.0 + (time.subsec_nanos() as f64) / 1000000.0,
-            ),
-            None => println!("Request timeout for icmp_seq {}", seq),
+            (time.as_secs() as f64) * 1000.0 + (time.subsec_nanos() as f64) / 1000000.0,
+        ),
+        None if flood => {
+            print!(".");
+            io::stdout().flush().unwrap();
         }
+        None => println!("Request timeout for icmp_seq {}", seq),
+    });
 
-        thread::sleep(Duration::from_secs(1));
+    if flood {
+        println!();
     }
+
+    println!("--- {} ping statistics ---", ping_addr);
+    println!("{}", stats);
+}
+
+/// Parses a pattern of hex bytes such as ff00.
+fn parse_pattern(pattern: &str) -> Option<Vec<u8>> {
+    if pattern.is_empty() || !pattern.len().is_multiple_of(2) || !pattern.is_ascii() {
+        return None;
+    }
+
+    (0 .. pattern.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&pattern[i .. i + 2], 16).ok())
+        .collect()
 }
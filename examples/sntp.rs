@@ -0,0 +1,81 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::str::FromStr;
+use std::time::{
+    Duration,
+    UNIX_EPOCH,
+};
+
+use usrnet::stack::repr::{
+    ntp_ports,
+    Ipv4Address,
+};
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Queries a time server for the offset of the local clock.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg ADDRESS: +takes_value +required "Address of the time server to query")
+        (@arg TIMEOUT: +takes_value --timeout "Timeout in milliseconds to wait for a response")
+    ).get_matches();
+
+    let server_addr = matches
+        .value_of("ADDRESS")
+        .and_then(|addr| Ipv4Address::from_str(addr).ok())
+        .expect("Bad IP address!");
+
+    let timeout = matches
+        .value_of("TIMEOUT")
+        .or(Some("1000"))
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .map(Duration::from_millis)
+        .expect("Bad timeout!");
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    let udp_socket = socket_env
+        .udp_socket_ephemeral(*interface.ipv4_addr)
+        .unwrap();
+    let udp_handle = socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    let server_addr = SocketAddr {
+        addr: server_addr,
+        port: ntp_ports::NTP,
+    };
+
+    match sntp(
+        &mut interface,
+        &mut socket_set,
+        udp_handle,
+        server_addr,
+        timeout,
+    ) {
+        Ok(result) => {
+            let server_time = result.server_time.duration_since(UNIX_EPOCH).unwrap();
+            println!(
+                "{} stratum {}: time={}.{:06} offset={:+.3} ms delay={:.3} ms",
+                server_addr.addr,
+                result.stratum,
+                server_time.as_secs(),
+                server_time.subsec_micros(),
+                (result.offset_nanos as f64) / 1000000.0,
+                (result.delay.as_secs() as f64) * 1000.0
+                    + (result.delay.subsec_nanos() as f64) / 1000000.0,
+            );
+        }
+        Err(err) => println!("Query failed with {:?}.", err),
+    }
+}
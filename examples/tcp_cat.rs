@@ -1,13 +1,12 @@
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
-extern crate rand;
 extern crate usrnet;
 
 use std::str::FromStr;
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
     SocketAddr,
     TaggedSocket,
 };
@@ -39,11 +38,9 @@ fn main() {
     let socket_env = env::socket_env(&mut interface);
     let mut socket_set = env::socket_set();
 
-    let socket_addr = SocketAddr {
-        addr: *interface.ipv4_addr,
-        port: rand::random::<u16>(),
-    };
-    let tcp_socket = socket_env.tcp_socket(socket_addr).unwrap();
+    let tcp_socket = socket_env
+        .tcp_socket_ephemeral(*interface.ipv4_addr)
+        .unwrap();
     let tcp_handle = socket_set
         .add_socket(TaggedSocket::Tcp(tcp_socket))
         .unwrap();
@@ -56,10 +53,12 @@ fn main() {
 
     socket_set
         .socket(tcp_handle)
+        .unwrap()
         .as_tcp_socket()
         .connect(server_addr);
     while socket_set
         .socket(tcp_handle)
+        .unwrap()
         .as_tcp_socket()
         .is_establishing()
     {
@@ -68,7 +67,12 @@ fn main() {
 
     println!("Connection established!");
 
-    if !socket_set.socket(tcp_handle).as_tcp_socket().is_connected() {
+    if !socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .is_connected()
+    {
         panic!("Error connecting to {}!", server_addr);
     }
 
@@ -3,7 +3,7 @@ extern crate clap;
 extern crate env_logger;
 extern crate usrnet;
 
-use usrnet::core::socket::{
+use usrnet::stack::socket::{
     SocketAddr,
     TaggedSocket,
 };
@@ -30,9 +30,9 @@ fn main() {
         addr: *interface.ipv4_addr,
         port,
     };
-    let tcp_socket = socket_env.tcp_socket(socket_addr).unwrap();
+    let tcp_listener = socket_env.tcp_listener(socket_addr, 16, 16).unwrap();
     let tcp_handle = socket_set
-        .add_socket(TaggedSocket::Tcp(tcp_socket))
+        .add_socket(TaggedSocket::TcpListener(tcp_listener))
         .unwrap();
 
     println!(
@@ -0,0 +1,129 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate usrnet;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use usrnet::stack::repr::{
+    tftp_ports,
+    Ipv4Address,
+};
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+
+/// Reads a file from a TFTP server to stdout or writes stdin to one, or serves
+/// files from memory.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(app =>
+        (@arg ADDRESS: +takes_value "IP address of the server, unless serving")
+        (@arg FILE:    +takes_value +multiple "File to read or write, or files to serve")
+        (@arg PUT:     -p --put "Write stdin to the file rather than reading it")
+        (@arg SERVER:  -s --server "Serve files, which clients may also write to")
+        (@arg TIMEOUT: -t --timeout +takes_value "Seconds to wait before retransmitting")
+    ).get_matches();
+
+    let mut config = TftpConfig::default();
+    if let Some(timeout) = matches.value_of("TIMEOUT") {
+        config.timeout = timeout
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .expect("Bad timeout!");
+    }
+
+    let mut interface = env::default_interface();
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    if matches.is_present("SERVER") {
+        let mut files = BTreeMap::new();
+        for path in matches.values_of("FILE").into_iter().flatten() {
+            let name = Path::new(path)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .expect("Bad file name!")
+                .to_string();
+            files.insert(name, fs::read(path).expect("Unable to read file!"));
+        }
+
+        let socket_addr = SocketAddr {
+            addr: *interface.ipv4_addr,
+            port: tftp_ports::TFTP,
+        };
+        let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+        let udp_handle = socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        eprintln!("Serving {} file(s) on {}.", files.len(), socket_addr);
+
+        tftp_server(
+            &mut interface,
+            &mut socket_set,
+            udp_handle,
+            &mut files,
+            config,
+            || true,
+        );
+        return;
+    }
+
+    let server_addr = SocketAddr {
+        addr: matches
+            .value_of("ADDRESS")
+            .and_then(|addr| Ipv4Address::from_str(addr).ok())
+            .expect("Bad IP address!"),
+        port: tftp_ports::TFTP,
+    };
+    let filename = matches.value_of("FILE").expect("Missing file!");
+
+    let udp_socket = socket_env
+        .udp_socket_ephemeral(*interface.ipv4_addr)
+        .unwrap();
+    let udp_handle = socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    let res = if matches.is_present("PUT") {
+        let mut file = Vec::new();
+        io::stdin()
+            .read_to_end(&mut file)
+            .expect("Unable to read stdin!");
+        tftp_put(
+            &mut interface,
+            &mut socket_set,
+            udp_handle,
+            server_addr,
+            filename,
+            &file,
+            config,
+        ).map(|_| eprintln!("Wrote {} bytes to {}.", file.len(), filename))
+    } else {
+        tftp_get(
+            &mut interface,
+            &mut socket_set,
+            udp_handle,
+            server_addr,
+            filename,
+            config,
+        ).map(|file| io::stdout().write_all(&file).unwrap())
+    };
+
+    if let Err(err) = res {
+        eprintln!("Failed with {:?}.", err);
+    }
+}
@@ -7,8 +7,12 @@ extern crate usrnet;
 use std::str::FromStr;
 use std::time::Duration;
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::{
+    ipv4_protocols,
+    Ipv4Address,
+    Ipv4Packet,
+};
+use usrnet::stack::socket::{
     RawType,
     TaggedSocket,
 };
@@ -22,6 +26,10 @@ fn main() {
         (@arg MAX_TTL:    +takes_value --ttl     "Max hops/TTL for each probing packet")
         (@arg TIMEOUT:    +takes_value --timeout "Timeout in milliseconds for each packet")
         (@arg PACKET_LEN: +takes_value --len     "Payload size in bytes for each packet")
+        (@arg QUERIES:    +takes_value -q --queries "Number of probes per hop, 3 by default")
+        (@arg ICMP:       -I --icmp "Probe with ICMP echo requests rather than UDP")
+        (@arg TCP:        -T --tcp "Probe with TCP SYNs rather than UDP")
+        (@arg PORT:       +takes_value -p --port "Port to send TCP SYNs to, 80 by default")
     ).get_matches();
 
     let trace_addr = matches
@@ -29,59 +37,99 @@ fn main() {
         .and_then(|addr| Ipv4Address::from_str(addr).ok())
         .expect("Bad IP address!");
 
-    let max_ttl = matches
-        .value_of("MAX_TTL")
-        .or(Some("64"))
-        .and_then(|ttl| ttl.parse::<u8>().ok())
-        .expect("Bad TTL!");
-
-    let timeout = matches
-        .value_of("TIMEOUT")
-        .or(Some("1000"))
-        .and_then(|timeout| timeout.parse::<u64>().ok())
-        .map(|timeout| Duration::from_millis(timeout))
-        .expect("Bad timeout!");
+    let mode = if matches.is_present("ICMP") {
+        TraceMode::Icmp
+    } else if matches.is_present("TCP") {
+        TraceMode::TcpSyn(
+            matches
+                .value_of("PORT")
+                .or(Some("80"))
+                .and_then(|port| port.parse::<u16>().ok())
+                .expect("Bad port!"),
+        )
+    } else {
+        TraceMode::Udp
+    };
 
-    let packet_len = matches
-        .value_of("PACKET_LEN")
-        .or(Some("64"))
-        .and_then(|packet_len| packet_len.parse::<usize>().ok())
-        .expect("Bad packet length!");
+    let config = TraceConfig {
+        mode,
+        probes: matches
+            .value_of("QUERIES")
+            .or(Some("3"))
+            .and_then(|probes| probes.parse::<usize>().ok())
+            .filter(|probes| *probes > 0)
+            .expect("Bad number of probes!"),
+        payload_len: matches
+            .value_of("PACKET_LEN")
+            .or(Some("64"))
+            .and_then(|packet_len| packet_len.parse::<usize>().ok())
+            .expect("Bad packet length!"),
+        max_ttl: matches
+            .value_of("MAX_TTL")
+            .or(Some("64"))
+            .and_then(|ttl| ttl.parse::<u8>().ok())
+            .expect("Bad TTL!"),
+        timeout: matches
+            .value_of("TIMEOUT")
+            .or(Some("1000"))
+            .and_then(|timeout| timeout.parse::<u64>().ok())
+            .map(Duration::from_millis)
+            .expect("Bad timeout!"),
+    };
 
     let mut interface = env::default_interface();
     let socket_env = env::socket_env(&mut interface);
     let mut socket_set = env::socket_set();
 
-    let raw_socket = socket_env.raw_socket(RawType::Ipv4);
+    // Only responses to probes are of interest, so skip buffering anything
+    // else.
+    let mut raw_socket = socket_env.raw_socket(RawType::Ipv4);
+    raw_socket.set_filter(move |packet| {
+        Ipv4Packet::try_new(packet)
+            .map(|ipv4_packet| match ipv4_packet.protocol() {
+                ipv4_protocols::ICMP => true,
+                ipv4_protocols::TCP => matches!(mode, TraceMode::TcpSyn(_)),
+                _ => false,
+            })
+            .unwrap_or(false)
+    });
     let raw_handle = socket_set
         .add_socket(TaggedSocket::Raw(raw_socket))
         .unwrap();
 
     println!(
         "traceroute to {} ({}), {} hops max, {} byte packets",
-        trace_addr, trace_addr, max_ttl, packet_len
+        trace_addr, trace_addr, config.max_ttl, config.payload_len
     );
 
-    let reached = traceroute(
+    let reached = traceroute_with(
         &mut interface,
         &mut socket_set,
         raw_handle,
         trace_addr,
-        packet_len,
-        max_ttl,
-        timeout,
-        |ttl, hop| {
-            if let Some((time, address)) = hop {
-                println!(
-                    "{:2} {} ({}) {:.3} ms",
-                    ttl,
-                    address,
-                    address,
-                    (time.as_secs() as f64) * 1000.0 + (time.subsec_nanos() as f64) / 1000000.0
-                );
-            } else {
-                println!("{:2} * * * ", ttl);
+        &config,
+        |ttl, hops| {
+            let mut line = format!("{:2}", ttl);
+            let mut last_addr = None;
+
+            for hop in hops {
+                match *hop {
+                    Some((time, address)) => {
+                        if last_addr != Some(address) {
+                            line += &format!(" {} ({})", address, address);
+                            last_addr = Some(address);
+                        }
+                        line += &format!(
+                            " {:.3} ms",
+                            (time.as_secs() as f64) * 1000.0
+                                + (time.subsec_nanos() as f64) / 1000000.0
+                        );
+                    }
+                    None => line += " *",
+                }
             }
+
+            println!("{}", line);
         },
     ).is_some();
 
@@ -1,7 +1,6 @@
 #[macro_use]
 extern crate clap;
 extern crate env_logger;
-extern crate rand;
 extern crate usrnet;
 
 use std::io;
@@ -9,8 +8,8 @@ use std::str::FromStr;
 use std::sync::mpsc;
 use std::thread;
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
     SocketAddr,
     TaggedSocket,
 };
@@ -42,11 +41,9 @@ fn main() {
     let socket_env = env::socket_env(&mut interface);
     let mut socket_set = env::socket_set();
 
-    let socket_addr = SocketAddr {
-        addr: *interface.ipv4_addr,
-        port: rand::random::<u16>(),
-    };
-    let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+    let udp_socket = socket_env
+        .udp_socket_ephemeral(*interface.ipv4_addr)
+        .unwrap();
     let udp_handle = socket_set
         .add_socket(TaggedSocket::Udp(udp_socket))
         .unwrap();
@@ -69,13 +66,19 @@ fn main() {
         if let Ok(buf) = recv.try_recv() {
             socket_set
                 .socket(udp_handle)
+                .unwrap()
                 .as_udp_socket()
                 .send(buf.as_bytes().len(), server_addr)
                 .unwrap()
                 .copy_from_slice(buf.as_bytes());
         }
 
-        if let Ok((buf, _)) = socket_set.socket(udp_handle).as_udp_socket().recv() {
+        if let Ok((buf, _)) = socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .recv()
+        {
             println!("{}", String::from_utf8_lossy(buf));
         }
 
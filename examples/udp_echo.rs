@@ -3,7 +3,7 @@ extern crate clap;
 extern crate env_logger;
 extern crate usrnet;
 
-use usrnet::core::socket::{
+use usrnet::stack::socket::{
     SocketAddr,
     TaggedSocket,
 };
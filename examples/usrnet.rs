@@ -0,0 +1,373 @@
+#[macro_use]
+extern crate clap;
+extern crate env_logger;
+extern crate rand;
+extern crate usrnet;
+
+use std::io::{
+    self,
+    Write,
+};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use usrnet::stack::repr::{
+    ipv4_protocols,
+    EthernetAddress,
+    Ipv4Address,
+    Ipv4AddressCidr,
+    Ipv4Packet,
+};
+use usrnet::stack::service::Interface;
+use usrnet::stack::socket::{
+    RawType,
+    SocketAddr,
+    SocketEnv,
+    SocketSet,
+    TaggedSocket,
+};
+use usrnet::stack::time::SystemEnv;
+use usrnet::examples::*;
+
+/// Runs the sample programs against a device and address of choice.
+fn main() {
+    env_logger::init();
+
+    let matches = clap_app!(usrnet =>
+        (@setting SubcommandRequiredElseHelp)
+        (@arg DEV:     -i --interface +takes_value "Name of the TAP (Linux) or feth (macOS) device, tap0 or feth0 by default")
+        (@arg ADDR:    -a --addr +takes_value "IPv4 address with a subnet mask, 10.0.0.102/24 by default")
+        (@arg GATEWAY: -g --gateway +takes_value "IPv4 address of the default gateway, 10.0.0.101 by default")
+        (@arg MAC:     -m --mac +takes_value "MAC address, 06:11:22:33:44:55 by default")
+        (@subcommand ping =>
+            (about: "Sends ICMP echo requests to a host")
+            (@arg ADDRESS:  +takes_value +required "Address to ping")
+            (@arg COUNT:    -c --count +takes_value "Number of requests to send, 64 by default")
+            (@arg INTERVAL: -i --interval +takes_value "Milliseconds between requests, 1000 by default")
+            (@arg FLOOD:    -f --flood "Send requests back to back, printing a dot per timeout")
+            (@arg PATTERN:  -p --pattern +takes_value "Hex bytes to fill the payload with, e.g. ff00")
+            (@arg TIMEOUT:  --timeout +takes_value "Timeout in milliseconds for each reply, 1000 by default")
+            (@arg LEN:      --len +takes_value "Payload size in bytes for each request, 64 by default")
+        )
+        (@subcommand arping =>
+            (about: "Sends ARP requests for an IPv4 address")
+            (@arg ADDRESS:  +takes_value +required "Address to arping")
+            (@arg COUNT:    -c --count +takes_value "Number of requests to send, 64 by default")
+            (@arg INTERVAL: --interval +takes_value "Milliseconds between requests, 1000 by default")
+            (@arg TIMEOUT:  --timeout +takes_value "Timeout in milliseconds for each reply, 1000 by default")
+        )
+        (@subcommand traceroute =>
+            (about: "Traces the route to a host with UDP, ICMP or TCP SYN probes")
+            (@arg ADDRESS: +takes_value +required "Address to traceroute")
+            (@arg MAX_TTL: --ttl +takes_value "Max hops/TTL for each probe, 64 by default")
+            (@arg TIMEOUT: --timeout +takes_value "Timeout in milliseconds for each probe, 1000 by default")
+            (@arg LEN:     --len +takes_value "Payload size in bytes for each probe, 64 by default")
+            (@arg QUERIES: -q --queries +takes_value "Number of probes per hop, 3 by default")
+            (@arg ICMP:    -I --icmp "Probe with ICMP echo requests rather than UDP")
+            (@arg TCP:     -T --tcp "Probe with TCP SYNs rather than UDP")
+            (@arg PORT:    -p --port +takes_value "Port to send TCP SYNs to, 80 by default")
+        )
+        (@subcommand udp_echo =>
+            (about: "Echoes UDP datagrams to the sender")
+            (@arg PORT: +takes_value +required "UDP port to bind")
+        )
+        (@subcommand tcp_echo =>
+            (about: "Echoes TCP streams to the sender")
+            (@arg PORT: +takes_value +required "TCP port to bind")
+        )
+    ).get_matches();
+
+    let mut interface = env::interface(
+        env::dev(matches.value_of("DEV").unwrap_or(env::DEFAULT_DEV_NAME)),
+        matches
+            .value_of("MAC")
+            .map(|mac| EthernetAddress::from_str(mac).expect("Bad MAC address!"))
+            .unwrap_or(*env::DEFAULT_ETH_ADDR),
+        matches
+            .value_of("ADDR")
+            .map(|addr| Ipv4AddressCidr::from_str(addr).expect("Bad IP address!"))
+            .unwrap_or(*env::DEFAULT_IPV4_ADDR_CIDR),
+    );
+    if let Some(gateway) = matches.value_of("GATEWAY") {
+        interface.default_gateway = Ipv4Address::from_str(gateway).expect("Bad gateway!");
+    }
+
+    println!(
+        "Interface: (MTU = {}, MAC = {}, IPv4 = {}, Gateway: {})",
+        interface.dev.max_transmission_unit(),
+        interface.ethernet_addr,
+        interface.ipv4_addr,
+        interface.default_gateway,
+    );
+
+    let socket_env = env::socket_env(&mut interface);
+    let mut socket_set = env::socket_set();
+
+    match matches.subcommand() {
+        ("ping", Some(matches)) => run_ping(&mut interface, &socket_env, &mut socket_set, matches),
+        ("arping", Some(matches)) => {
+            run_arping(&mut interface, &socket_env, &mut socket_set, matches)
+        }
+        ("traceroute", Some(matches)) => {
+            run_traceroute(&mut interface, &socket_env, &mut socket_set, matches)
+        }
+        ("udp_echo", Some(matches)) => {
+            run_udp_echo(&mut interface, &socket_env, &mut socket_set, matches)
+        }
+        ("tcp_echo", Some(matches)) => {
+            run_tcp_echo(&mut interface, &socket_env, &mut socket_set, matches)
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Parses the value of an argument, falling back to a default if absent.
+fn value<T: FromStr>(matches: &ArgMatches, name: &str, default: &str) -> T {
+    matches
+        .value_of(name)
+        .unwrap_or(default)
+        .parse::<T>()
+        .unwrap_or_else(|_| panic!("Bad {}!", name.to_lowercase().replace('_', " ")))
+}
+
+fn millis(matches: &ArgMatches, name: &str) -> Duration {
+    Duration::from_millis(value(matches, name, "1000"))
+}
+
+fn as_millis(time: Duration) -> f64 {
+    (time.as_secs() as f64) * 1000.0 + (time.subsec_nanos() as f64) / 1000000.0
+}
+
+fn run_ping(
+    interface: &mut Interface,
+    socket_env: &SocketEnv<SystemEnv>,
+    socket_set: &mut SocketSet,
+    matches: &ArgMatches,
+) {
+    let ping_addr: Ipv4Address = value(matches, "ADDRESS", "");
+    let flood = matches.is_present("FLOOD");
+    let config = PingConfig {
+        count: value(matches, "COUNT", "64"),
+        interval: if flood {
+            Duration::from_secs(0)
+        } else {
+            millis(matches, "INTERVAL")
+        },
+        timeout: millis(matches, "TIMEOUT"),
+        payload_len: value(matches, "LEN", "64"),
+        pattern: matches
+            .value_of("PATTERN")
+            .map(|pattern| parse_pattern(pattern).expect("Bad pattern!"))
+            .unwrap_or_default(),
+    };
+
+    let icmp_socket = socket_env
+        .icmp_socket(*interface.ipv4_addr, rand::random::<u16>())
+        .unwrap();
+    let icmp_handle = socket_set
+        .add_socket(TaggedSocket::Icmp(icmp_socket))
+        .unwrap();
+
+    println!(
+        "PING {} ({}) {} bytes of data.",
+        ping_addr, ping_addr, config.payload_len
+    );
+
+    let payload_len = config.payload_len;
+    let mut pinger = Pinger::new(icmp_handle, ping_addr, config);
+
+    let stats = pinger.run(interface, socket_set, |seq, rtt| match rtt {
+        Some(_) if flood => {}
+        Some(time) => println!(
+            "{} bytes from {}: icmp_seq={} time={:.2} ms",
+            payload_len,
+            ping_addr,
+            seq,
+            as_millis(time),
+        ),
+        None if flood => {
+            print!(".");
+            io::stdout().flush().unwrap();
+        }
+        None => println!("Request timeout for icmp_seq {}", seq),
+    });
+
+    if flood {
+        println!();
+    }
+
+    println!("--- {} ping statistics ---", ping_addr);
+    println!("{}", stats);
+}
+
+/// Parses a pattern of hex bytes such as ff00.
+fn parse_pattern(pattern: &str) -> Option<Vec<u8>> {
+    if pattern.is_empty() || !pattern.len().is_multiple_of(2) || !pattern.is_ascii() {
+        return None;
+    }
+
+    (0 .. pattern.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&pattern[i .. i + 2], 16).ok())
+        .collect()
+}
+
+fn run_arping(
+    interface: &mut Interface,
+    socket_env: &SocketEnv<SystemEnv>,
+    socket_set: &mut SocketSet,
+    matches: &ArgMatches,
+) {
+    let arping_addr: Ipv4Address = value(matches, "ADDRESS", "");
+    let count: usize = value(matches, "COUNT", "64");
+    let interval = millis(matches, "INTERVAL");
+    let timeout = millis(matches, "TIMEOUT");
+
+    let raw_socket = socket_env.raw_socket(RawType::Ethernet);
+    let raw_handle = socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    println!("ARPING {}.", arping_addr);
+
+    for i in 0 .. count {
+        match arping(interface, socket_set, raw_handle, arping_addr, timeout) {
+            Some((time, eth_addr)) => println!(
+                "28 bytes from {} ({}) index={} time={:.2} ms",
+                eth_addr,
+                arping_addr,
+                i,
+                as_millis(time),
+            ),
+            None => println!("Timeout"),
+        }
+
+        thread::sleep(interval);
+    }
+}
+
+fn run_traceroute(
+    interface: &mut Interface,
+    socket_env: &SocketEnv<SystemEnv>,
+    socket_set: &mut SocketSet,
+    matches: &ArgMatches,
+) {
+    let trace_addr: Ipv4Address = value(matches, "ADDRESS", "");
+    let mode = if matches.is_present("ICMP") {
+        TraceMode::Icmp
+    } else if matches.is_present("TCP") {
+        TraceMode::TcpSyn(value(matches, "PORT", "80"))
+    } else {
+        TraceMode::Udp
+    };
+    let config = TraceConfig {
+        mode,
+        probes: value(matches, "QUERIES", "3"),
+        payload_len: value(matches, "LEN", "64"),
+        max_ttl: value(matches, "MAX_TTL", "64"),
+        timeout: millis(matches, "TIMEOUT"),
+    };
+
+    // Only responses to probes are of interest, so skip buffering anything
+    // else.
+    let mut raw_socket = socket_env.raw_socket(RawType::Ipv4);
+    raw_socket.set_filter(move |packet| {
+        Ipv4Packet::try_new(packet)
+            .map(|ipv4_packet| match ipv4_packet.protocol() {
+                ipv4_protocols::ICMP => true,
+                ipv4_protocols::TCP => matches!(mode, TraceMode::TcpSyn(_)),
+                _ => false,
+            })
+            .unwrap_or(false)
+    });
+    let raw_handle = socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    println!(
+        "traceroute to {} ({}), {} hops max, {} byte packets",
+        trace_addr, trace_addr, config.max_ttl, config.payload_len
+    );
+
+    let reached = traceroute_with(
+        interface,
+        socket_set,
+        raw_handle,
+        trace_addr,
+        &config,
+        |ttl, hops| {
+            let mut line = format!("{:2}", ttl);
+            let mut last_addr = None;
+
+            for hop in hops {
+                match *hop {
+                    Some((time, address)) => {
+                        if last_addr != Some(address) {
+                            line += &format!(" {} ({})", address, address);
+                            last_addr = Some(address);
+                        }
+                        line += &format!(" {:.3} ms", as_millis(time));
+                    }
+                    None => line += " *",
+                }
+            }
+
+            println!("{}", line);
+        },
+    ).is_some();
+
+    if !reached {
+        std::process::exit(1);
+    }
+}
+
+fn run_udp_echo(
+    interface: &mut Interface,
+    socket_env: &SocketEnv<SystemEnv>,
+    socket_set: &mut SocketSet,
+    matches: &ArgMatches,
+) {
+    let socket_addr = SocketAddr {
+        addr: *interface.ipv4_addr,
+        port: value(matches, "PORT", ""),
+    };
+    let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+    let udp_handle = socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    println!(
+        "Running UDP echo server; Use 'ncat -u {} {}' to send packets.",
+        socket_addr.addr, socket_addr.port
+    );
+
+    udp_echo(interface, socket_set, udp_handle, || true);
+}
+
+fn run_tcp_echo(
+    interface: &mut Interface,
+    socket_env: &SocketEnv<SystemEnv>,
+    socket_set: &mut SocketSet,
+    matches: &ArgMatches,
+) {
+    let socket_addr = SocketAddr {
+        addr: *interface.ipv4_addr,
+        port: value(matches, "PORT", ""),
+    };
+    let tcp_listener = socket_env.tcp_listener(socket_addr, 16, 16).unwrap();
+    let tcp_handle = socket_set
+        .add_socket(TaggedSocket::TcpListener(tcp_listener))
+        .unwrap();
+
+    println!(
+        "Running TCP echo server; Use 'ncat {} {}' to send messages.",
+        socket_addr.addr, socket_addr.port
+    );
+
+    loop {
+        tcp_echo(interface, socket_set, tcp_handle, || true);
+    }
+}
@@ -0,0 +1,8 @@
+#![no_main]
+#[macro_use]
+extern crate libfuzzer_sys;
+extern crate usrnet;
+
+fuzz_target!(|data: &[u8]| {
+    usrnet::fuzz::arp(data);
+});
@@ -1,19 +0,0 @@
-mod closed;
-mod established;
-mod listen;
-mod socket;
-mod state;
-mod syn_recv;
-mod syn_sent;
-
-pub use self::closed::TcpClosed;
-pub use self::established::TcpEstablished;
-pub use self::listen::TcpListen;
-pub use self::socket::TcpSocket;
-pub use self::state::{
-    Tcp,
-    TcpContext,
-    TcpState,
-};
-pub use self::syn_recv::TcpSynRecv;
-pub use self::syn_sent::TcpSynSent;
@@ -0,0 +1,337 @@
+//! Errors returned by the stack.
+//!
+//! Errors are grouped into categories by their cause, e.g. parsing a packet
+//! or using a socket, so callers can match on a whole category or on a
+//! specific failure within one.
+
+use core::error::Error as StdError;
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use core::result::Result as StdResult;
+#[cfg(feature = "std")]
+use std::io::Error as IOError;
+
+use stack::repr::{
+    EthernetAddress,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv6Address,
+};
+use stack::socket::SocketAddr;
+
+#[derive(Debug)]
+pub enum Error {
+    /// Indicates an error where a packet or frame could not be parsed.
+    Parse(ParseError),
+    /// Indicates an error where a buffer or queue could not hold or provide
+    /// data.
+    Buffer(BufferError),
+    /// Indicates an error with a socket operation.
+    Socket(SocketError),
+    /// Indicates an error with an address, such as resolving it.
+    Address(AddressError),
+    /// Indicates an error with a device/interface.
+    Device(DeviceError),
+    /// Indicates an error reported by a router or remote host.
+    Remote(RemoteError),
+    /// Indicates an error where an operation did not complete in time, such
+    /// as establishing a connection.
+    Timeout,
+    /// Indicates an error where a an incoming packet was ignored.
+    Ignored,
+}
+
+/// Errors parsing packets or frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Indicates an error where a buffer is too short to hold the packet or
+    /// frame it should contain.
+    Truncated,
+    /// Indicates an error where a packet or frame is malformed.
+    Malformed,
+    /// Indicates an error where a checksum is invalid.
+    Checksum,
+}
+
+/// Errors with buffers and queues, such as socket buffers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferError {
+    /// Indicates an error where a buffer or queue is full, e.g. when enqueueing
+    /// a packet for sending.
+    Full,
+    /// Indicates an error where a buffer or queue is empty, e.g. when there
+    /// is nothing to receive or send.
+    Empty,
+    /// Indicates an error where data is larger than a buffer can ever hold,
+    /// such as a payload exceeding the slots of a socket buffer or a packet
+    /// exceeding the MTU.
+    TooSmall,
+}
+
+/// Errors with socket operations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketError {
+    /// Indicates an error where a socket binding has already been assigned.
+    BindingInUse(SocketAddr),
+    /// Indicates an error where every ephemeral port is in use.
+    PortsExhausted,
+    /// Indicates an error where a socket operation requires a connection, but
+    /// the socket is not connected.
+    NotConnected,
+    /// Indicates an error where a connection was closed by a RST from the
+    /// remote endpoint.
+    ConnectionReset,
+    /// Indicates an error where a socket handle is not in use, e.g. because
+    /// the socket was removed from the socket set.
+    InvalidHandle,
+    /// Indicates an error where a socket set is full and cannot grow to hold
+    /// another socket.
+    SetFull,
+    /// Indicates an error where a socket operation requires buffers which
+    /// were split off into halves of the socket.
+    Split,
+}
+
+/// Errors with addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressError {
+    /// Indicates an error where a MAC address could not be resolved for an IPV4
+    /// address.
+    MacResolution(Ipv4Address),
+    /// Indicates an error where a MAC address could not be resolved for an
+    /// IPv6 address via Neighbor Discovery.
+    NeighborResolution(Ipv6Address),
+    /// Indicates an error where an address is not valid for the operation
+    /// being performed, such as joining a non-multicast group.
+    InvalidAddress(Ipv4Address),
+    /// Indicates an error where another host, identified by its Ethernet
+    /// address, is using the IPv4 address of the interface.
+    AddressConflict(EthernetAddress),
+}
+
+/// Errors with devices.
+#[derive(Debug)]
+pub enum DeviceError {
+    /// Indicates an error where the device can not send or receive a frame
+    /// right now, such as writes to a busy device or reads on a device with no
+    /// frames.
+    WouldBlock,
+    /// Indicates an error where no interface is available to send a packet.
+    NoInterface,
+    /// Indicates an error from the OS or the underlying IO of a device.
+    #[cfg(feature = "std")]
+    Io(IOError),
+}
+
+/// Errors reported by routers or remote hosts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RemoteError {
+    /// Indicates an error reported via an ICMP message, such as a destination
+    /// or port being unreachable.
+    Icmp(Icmpv4Repr),
+    /// Indicates an error response from a DNS server with the response code,
+    /// such as a name not existing.
+    Dns(u8),
+    /// Indicates an error message from a TFTP peer with the error code, such
+    /// as a file not being found.
+    Tftp(u16),
+}
+
+pub type Result<T> = StdResult<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            Error::Parse(ref err) => write!(f, "parse error: {}", err),
+            Error::Buffer(ref err) => write!(f, "buffer error: {}", err),
+            Error::Socket(ref err) => write!(f, "socket error: {}", err),
+            Error::Address(ref err) => write!(f, "address error: {}", err),
+            Error::Device(ref err) => write!(f, "device error: {}", err),
+            Error::Remote(ref err) => write!(f, "remote error: {}", err),
+            Error::Timeout => write!(f, "timed out"),
+            Error::Ignored => write!(f, "packet ignored"),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            ParseError::Truncated => write!(f, "truncated packet"),
+            ParseError::Malformed => write!(f, "malformed packet"),
+            ParseError::Checksum => write!(f, "invalid checksum"),
+        }
+    }
+}
+
+impl Display for BufferError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            BufferError::Full => write!(f, "buffer full"),
+            BufferError::Empty => write!(f, "buffer empty"),
+            BufferError::TooSmall => write!(f, "buffer too small"),
+        }
+    }
+}
+
+impl Display for SocketError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            SocketError::BindingInUse(ref socket_addr) => {
+                write!(f, "binding {} in use", socket_addr)
+            }
+            SocketError::PortsExhausted => write!(f, "no ephemeral ports available"),
+            SocketError::NotConnected => write!(f, "not connected"),
+            SocketError::ConnectionReset => write!(f, "connection reset"),
+            SocketError::InvalidHandle => write!(f, "invalid socket handle"),
+            SocketError::SetFull => write!(f, "socket set full"),
+            SocketError::Split => write!(f, "socket split"),
+        }
+    }
+}
+
+impl Display for AddressError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            AddressError::MacResolution(ref addr) => {
+                write!(f, "could not resolve MAC address for {}", addr)
+            }
+            AddressError::NeighborResolution(ref addr) => {
+                write!(f, "could not resolve MAC address for {}", addr)
+            }
+            AddressError::InvalidAddress(ref addr) => write!(f, "invalid address {}", addr),
+            AddressError::AddressConflict(ref eth_addr) => {
+                write!(f, "address in use by {}", eth_addr)
+            }
+        }
+    }
+}
+
+impl Display for DeviceError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            DeviceError::WouldBlock => write!(f, "device would block"),
+            DeviceError::NoInterface => write!(f, "no interface available"),
+            #[cfg(feature = "std")]
+            DeviceError::Io(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            RemoteError::Icmp(ref icmp_repr) => write!(f, "ICMP {:?}", icmp_repr.message),
+            RemoteError::Dns(rcode) => write!(f, "DNS response code {}", rcode),
+            RemoteError::Tftp(code) => write!(f, "TFTP error code {}", code),
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Parse(ref err) => Some(err),
+            Error::Buffer(ref err) => Some(err),
+            Error::Socket(ref err) => Some(err),
+            Error::Address(ref err) => Some(err),
+            Error::Device(ref err) => Some(err),
+            Error::Remote(ref err) => Some(err),
+            Error::Timeout | Error::Ignored => None,
+        }
+    }
+}
+
+impl StdError for ParseError {}
+
+impl StdError for BufferError {}
+
+impl StdError for SocketError {}
+
+impl StdError for AddressError {}
+
+impl StdError for DeviceError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            #[cfg(feature = "std")]
+            DeviceError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl StdError for RemoteError {}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Error {
+        Error::Parse(err)
+    }
+}
+
+impl From<BufferError> for Error {
+    fn from(err: BufferError) -> Error {
+        Error::Buffer(err)
+    }
+}
+
+impl From<SocketError> for Error {
+    fn from(err: SocketError) -> Error {
+        Error::Socket(err)
+    }
+}
+
+impl From<AddressError> for Error {
+    fn from(err: AddressError) -> Error {
+        Error::Address(err)
+    }
+}
+
+impl From<DeviceError> for Error {
+    fn from(err: DeviceError) -> Error {
+        Error::Device(err)
+    }
+}
+
+impl From<RemoteError> for Error {
+    fn from(err: RemoteError) -> Error {
+        Error::Remote(err)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<IOError> for Error {
+    fn from(err: IOError) -> Error {
+        Error::Device(DeviceError::Io(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind as IOErrorKind;
+
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        let err = Error::Buffer(BufferError::Full);
+        assert_eq!(err.to_string(), "buffer error: buffer full");
+        assert!(err.source().is_some());
+        assert!(Error::Timeout.source().is_none());
+    }
+
+    #[test]
+    fn test_io_error_preserved() {
+        let err = Error::from(IOError::new(IOErrorKind::InvalidInput, "bad MTU"));
+        assert_eq!(err.to_string(), "device error: bad MTU");
+
+        let io_err = err
+            .source()
+            .and_then(|err| err.source())
+            .and_then(|err| err.downcast_ref::<IOError>())
+            .unwrap();
+        assert_eq!(io_err.kind(), IOErrorKind::InvalidInput);
+    }
+}
@@ -3,7 +3,7 @@ use std::time::{
     Instant,
 };
 
-use core::repr::{
+use stack::repr::{
     eth_types,
     Arp,
     ArpOp,
@@ -11,8 +11,11 @@ use core::repr::{
     EthernetFrame,
     Ipv4Address,
 };
-use core::service::Interface;
-use core::socket::SocketSet;
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
 use examples::env;
 use Error;
 
@@ -20,7 +23,7 @@ use Error;
 pub fn arping(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    raw_handle: usize,
+    raw_handle: SocketHandle,
     arping_addr: Ipv4Address,
     timeout: Duration,
 ) -> Option<(Duration, EthernetAddress)> {
@@ -37,6 +40,7 @@ pub fn arping(
     // Socket may have a full send buffer!
     while let Err(_) = socket_set
         .socket(raw_handle)
+        .unwrap()
         .as_raw_socket()
         .send(eth_frame_len)
         .map(|eth_buffer| {
@@ -58,6 +62,7 @@ pub fn arping(
             return None;
         } else if let Ok(eth_addr) = socket_set
             .socket(raw_handle)
+            .unwrap()
             .as_raw_socket()
             .recv()
             .and_then(|eth_buffer| {
@@ -0,0 +1,252 @@
+use std::collections::BTreeMap;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use stack::repr::{
+    eth_types,
+    Arp,
+    ArpOp,
+    EthernetAddress,
+    EthernetFrame,
+    Ipv4Address,
+    Ipv4AddressCidr,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
+use examples::env;
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Vendors of some common organizationally unique identifiers (OUIs), i.e.
+/// the first 3 bytes of an Ethernet address.
+static OUI_VENDORS: &[([u8; 3], &str)] = &[
+    ([0x00, 0x00, 0x0C], "Cisco Systems"),
+    ([0x00, 0x03, 0x93], "Apple"),
+    ([0x00, 0x0C, 0x29], "VMware"),
+    ([0x00, 0x15, 0x5D], "Microsoft"),
+    ([0x00, 0x16, 0x3E], "Xensource"),
+    ([0x00, 0x1C, 0x42], "Parallels"),
+    ([0x00, 0x50, 0x56], "VMware"),
+    ([0x08, 0x00, 0x27], "PCS Systemtechnik (VirtualBox)"),
+    ([0x52, 0x54, 0x00], "QEMU"),
+    ([0xB8, 0x27, 0xEB], "Raspberry Pi Foundation"),
+    ([0xDC, 0xA6, 0x32], "Raspberry Pi Trading"),
+];
+
+/// Settings for an ARP scan.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArpScanConfig {
+    /// How long to wait for replies after each sweep of the range.
+    pub timeout: Duration,
+    /// Number of times addresses which did not reply are swept again.
+    pub retries: usize,
+}
+
+impl Default for ArpScanConfig {
+    fn default() -> ArpScanConfig {
+        ArpScanConfig {
+            timeout: Duration::from_millis(500),
+            retries: 1,
+        }
+    }
+}
+
+/// A host which replied to an ARP scan.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArpScanHost {
+    pub ipv4_addr: Ipv4Address,
+    pub eth_addr: EthernetAddress,
+    /// Vendor of the host's network card, if the OUI is known.
+    pub vendor: Option<&'static str>,
+    /// Time between sending the last ARP request to the host and its reply.
+    pub rtt: Duration,
+}
+
+/// Looks up the vendor of an Ethernet address by its OUI.
+pub fn vendor(eth_addr: EthernetAddress) -> Option<&'static str> {
+    let oui = &eth_addr.as_bytes()[.. 3];
+    OUI_VENDORS
+        .iter()
+        .find(|&&(vendor_oui, _)| &vendor_oui[..] == oui)
+        .map(|&(_, vendor)| vendor)
+}
+
+/// Sweeps a subnet with ARP requests via a raw Ethernet socket, calling f for
+/// each host as it replies and returning all hosts ordered by address.
+///
+/// Requests are queued on the socket as fast as it accepts them, so the
+/// interface sends them to the device in bursts.
+pub fn arpscan<F>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    raw_handle: SocketHandle,
+    cidr: Ipv4AddressCidr,
+    config: ArpScanConfig,
+    mut f: F,
+) -> Vec<ArpScanHost>
+where
+    F: FnMut(&ArpScanHost),
+{
+    let targets: Vec<Ipv4Address> = scan_addrs(cidr)
+        .into_iter()
+        .filter(|&addr| addr != *interface.ipv4_addr)
+        .collect();
+
+    let mut send_at = BTreeMap::new();
+    let mut hosts = BTreeMap::new();
+
+    for _ in 0 .. config.retries + 1 {
+        let pending: Vec<Ipv4Address> = targets
+            .iter()
+            .cloned()
+            .filter(|addr| !hosts.contains_key(addr))
+            .collect();
+
+        if pending.is_empty() {
+            break;
+        }
+
+        for addr in pending {
+            // Socket may have a full send buffer!
+            while let Err(Error::Buffer(BufferError::Full)) =
+                send_request(interface, socket_set, raw_handle, addr)
+            {
+                env::tick(interface, socket_set);
+                recv_replies(interface, socket_set, raw_handle, &send_at, &mut hosts, &mut f);
+            }
+
+            send_at.insert(addr, Instant::now());
+        }
+
+        let sweep_at = Instant::now();
+        while Instant::now().duration_since(sweep_at) < config.timeout {
+            env::tick(interface, socket_set);
+            recv_replies(interface, socket_set, raw_handle, &send_at, &mut hosts, &mut f);
+        }
+    }
+
+    hosts.into_values().collect()
+}
+
+/// Returns the addresses to scan in a subnet, excluding the network and
+/// broadcast addresses unless the subnet is too small to have hosts otherwise.
+fn scan_addrs(cidr: Ipv4AddressCidr) -> Vec<Ipv4Address> {
+    let network = cidr.network().as_int();
+    let broadcast = cidr.broadcast().as_int();
+
+    if cidr.subnet_len() >= 31 {
+        (network ..= broadcast).map(Ipv4Address::from).collect()
+    } else {
+        (network + 1 .. broadcast).map(Ipv4Address::from).collect()
+    }
+}
+
+/// Enqueues a broadcast ARP request for an address on a raw Ethernet socket.
+fn send_request(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    raw_handle: SocketHandle,
+    addr: Ipv4Address,
+) -> Result<()> {
+    let arp_repr = Arp {
+        op: ArpOp::Request,
+        source_hw_addr: interface.ethernet_addr,
+        source_proto_addr: *interface.ipv4_addr,
+        target_hw_addr: EthernetAddress::BROADCAST,
+        target_proto_addr: addr,
+    };
+
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(arp_repr.buffer_len());
+    let eth_buffer = socket_set
+        .socket(raw_handle)
+        .unwrap()
+        .as_raw_socket()
+        .send(eth_frame_len)?;
+
+    let mut eth_frame = EthernetFrame::try_new(eth_buffer)?;
+    eth_frame.set_src_addr(interface.ethernet_addr);
+    eth_frame.set_dst_addr(EthernetAddress::BROADCAST);
+    eth_frame.set_payload_type(eth_types::ARP);
+    arp_repr.serialize(eth_frame.payload_mut())
+}
+
+/// Records the first reply from each address a request was sent to.
+fn recv_replies<F>(
+    interface: &Interface,
+    socket_set: &mut SocketSet,
+    raw_handle: SocketHandle,
+    send_at: &BTreeMap<Ipv4Address, Instant>,
+    hosts: &mut BTreeMap<Ipv4Address, ArpScanHost>,
+    f: &mut F,
+) where
+    F: FnMut(&ArpScanHost),
+{
+    let raw_socket = socket_set.socket(raw_handle).unwrap().as_raw_socket();
+
+    while let Ok(eth_buffer) = raw_socket.recv() {
+        let arp_repr = match EthernetFrame::try_new(eth_buffer) {
+            Ok(ref eth_frame) if eth_frame.payload_type() == eth_types::ARP => {
+                match Arp::deserialize(eth_frame.payload()) {
+                    Ok(arp_repr) => arp_repr,
+                    Err(_) => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        if arp_repr.op != ArpOp::Reply
+            || arp_repr.target_proto_addr != *interface.ipv4_addr
+            || hosts.contains_key(&arp_repr.source_proto_addr)
+        {
+            continue;
+        }
+
+        if let Some(&sent) = send_at.get(&arp_repr.source_proto_addr) {
+            let host = ArpScanHost {
+                ipv4_addr: arp_repr.source_proto_addr,
+                eth_addr: arp_repr.source_hw_addr,
+                vendor: vendor(arp_repr.source_hw_addr),
+                rtt: Instant::now().duration_since(sent),
+            };
+            f(&host);
+            hosts.insert(host.ipv4_addr, host);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vendor() {
+        let eth_addr = EthernetAddress::new([0x08, 0x00, 0x27, 0x12, 0x34, 0x56]);
+        assert_eq!(vendor(eth_addr), Some("PCS Systemtechnik (VirtualBox)"));
+
+        let eth_addr = EthernetAddress::new([0x06, 0x00, 0x00, 0x00, 0x00, 0x01]);
+        assert_eq!(vendor(eth_addr), None);
+    }
+
+    #[test]
+    fn test_scan_addrs() {
+        let cidr = Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 5]), 30);
+        assert_eq!(
+            scan_addrs(cidr),
+            vec![
+                Ipv4Address::new([10, 0, 0, 5]),
+                Ipv4Address::new([10, 0, 0, 6]),
+            ]
+        );
+
+        let cidr = Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 5]), 32);
+        assert_eq!(scan_addrs(cidr), vec![Ipv4Address::new([10, 0, 0, 5])]);
+    }
+}
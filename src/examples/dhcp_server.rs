@@ -0,0 +1,447 @@
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use stack::repr::{
+    dhcpv4_ports,
+    Dhcpv4MessageType,
+    Dhcpv4Packet,
+    Dhcpv4Repr,
+    EthernetAddress,
+    Ipv4Address,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+};
+use examples::env;
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// How long an offered address is reserved for a client which has not
+/// requested it yet.
+pub static OFFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Addresses and configuration handed out by a DHCP server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhcpServerConfig {
+    /// First address of the pool of addresses leased to clients.
+    pub pool_start: Ipv4Address,
+    /// Number of addresses in the pool.
+    pub pool_len: u32,
+    pub subnet_mask: Ipv4Address,
+    pub router: Option<Ipv4Address>,
+    pub dns_servers: Vec<Ipv4Address>,
+    pub lease_duration: Duration,
+}
+
+/// An address bound to, or offered to, a client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DhcpLease {
+    pub client_hw_addr: EthernetAddress,
+    pub ipv4_addr: Ipv4Address,
+    /// Whether the client has requested the address rather than only been
+    /// offered it.
+    pub bound: bool,
+    pub expires_at: Instant,
+}
+
+/// The lease table of a DHCP server, which assigns addresses from a pool.
+///
+/// Clients are offered the address they held before if possible, so a client
+/// restarting discovery keeps its address.
+#[derive(Debug)]
+pub struct DhcpServer {
+    config: DhcpServerConfig,
+    leases: Vec<DhcpLease>,
+}
+
+impl DhcpServer {
+    /// Creates a server with no leases.
+    pub fn new(config: DhcpServerConfig) -> DhcpServer {
+        DhcpServer {
+            config,
+            leases: Vec::new(),
+        }
+    }
+
+    /// Returns the leases and offers which have not expired by now.
+    pub fn leases(&self, now: Instant) -> Vec<&DhcpLease> {
+        self.leases
+            .iter()
+            .filter(|lease| lease.expires_at > now)
+            .collect()
+    }
+
+    /// Handles a message from a client, returning the reply to send if any.
+    ///
+    /// The server_id is the address of the server, which clients use to tell
+    /// offers from different servers apart.
+    pub fn handle(
+        &mut self,
+        request: &Dhcpv4Repr,
+        server_id: Ipv4Address,
+        now: Instant,
+    ) -> Option<Dhcpv4Repr> {
+        let client_hw_addr = request.client_hw_addr;
+
+        match request.message_type {
+            Dhcpv4MessageType::Discover => {
+                let ipv4_addr = self.choose_addr(client_hw_addr, request.requested_ip, now)?;
+                self.bind(client_hw_addr, ipv4_addr, false, now + OFFER_TIMEOUT);
+                Some(self.reply(request, Dhcpv4MessageType::Offer, ipv4_addr, server_id))
+            }
+            Dhcpv4MessageType::Request => {
+                match request.server_identifier {
+                    // The client chose an offer from another server.
+                    Some(addr) if addr != server_id => {
+                        self.release(client_hw_addr);
+                        return None;
+                    }
+                    _ => {}
+                }
+
+                let ipv4_addr = if request.client_ip.is_unspecified() {
+                    request.requested_ip?
+                } else {
+                    request.client_ip
+                };
+
+                if self.is_available(ipv4_addr, client_hw_addr, now) {
+                    let expires_at = now + self.config.lease_duration;
+                    self.bind(client_hw_addr, ipv4_addr, true, expires_at);
+                    Some(self.reply(request, Dhcpv4MessageType::Ack, ipv4_addr, server_id))
+                } else {
+                    let mut nak = self.reply(
+                        request,
+                        Dhcpv4MessageType::Nak,
+                        Ipv4Address::UNSPECIFIED,
+                        server_id,
+                    );
+                    nak.subnet_mask = None;
+                    nak.router = None;
+                    nak.dns_servers.clear();
+                    nak.lease_duration = None;
+                    Some(nak)
+                }
+            }
+            Dhcpv4MessageType::Release => {
+                self.release(client_hw_addr);
+                None
+            }
+            Dhcpv4MessageType::Decline => {
+                // Another host is using the address, so keep it out of the
+                // pool for a lease duration.
+                if let Some(ipv4_addr) = request.requested_ip {
+                    self.release(client_hw_addr);
+                    let expires_at = now + self.config.lease_duration;
+                    self.bind(EthernetAddress::BROADCAST, ipv4_addr, true, expires_at);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Chooses an address to offer a client, preferring one it already holds
+    /// or asked for.
+    fn choose_addr(
+        &self,
+        client_hw_addr: EthernetAddress,
+        requested_ip: Option<Ipv4Address>,
+        now: Instant,
+    ) -> Option<Ipv4Address> {
+        let held = self
+            .leases
+            .iter()
+            .find(|lease| lease.client_hw_addr == client_hw_addr)
+            .map(|lease| lease.ipv4_addr);
+
+        held.into_iter()
+            .chain(requested_ip)
+            .chain((0 .. self.config.pool_len).map(|i| {
+                Ipv4Address::from(self.config.pool_start.as_int().wrapping_add(i))
+            }))
+            .find(|addr| self.is_available(*addr, client_hw_addr, now))
+    }
+
+    /// Checks if an address is in the pool and not leased to another client.
+    fn is_available(
+        &self,
+        ipv4_addr: Ipv4Address,
+        client_hw_addr: EthernetAddress,
+        now: Instant,
+    ) -> bool {
+        let offset = ipv4_addr
+            .as_int()
+            .wrapping_sub(self.config.pool_start.as_int());
+
+        offset < self.config.pool_len && !self.leases.iter().any(|lease| {
+            lease.ipv4_addr == ipv4_addr
+                && lease.client_hw_addr != client_hw_addr
+                && lease.expires_at > now
+        })
+    }
+
+    fn bind(
+        &mut self,
+        client_hw_addr: EthernetAddress,
+        ipv4_addr: Ipv4Address,
+        bound: bool,
+        expires_at: Instant,
+    ) {
+        self.leases.retain(|lease| {
+            lease.client_hw_addr != client_hw_addr && lease.ipv4_addr != ipv4_addr
+        });
+        self.leases.push(DhcpLease {
+            client_hw_addr,
+            ipv4_addr,
+            bound,
+            expires_at,
+        });
+    }
+
+    fn release(&mut self, client_hw_addr: EthernetAddress) {
+        self.leases
+            .retain(|lease| lease.client_hw_addr != client_hw_addr);
+    }
+
+    fn reply(
+        &self,
+        request: &Dhcpv4Repr,
+        message_type: Dhcpv4MessageType,
+        your_ip: Ipv4Address,
+        server_id: Ipv4Address,
+    ) -> Dhcpv4Repr {
+        let mut reply = Dhcpv4Repr::new(
+            message_type,
+            request.transaction_id,
+            request.client_hw_addr,
+        );
+        reply.broadcast = request.broadcast;
+        reply.your_ip = your_ip;
+        reply.server_identifier = Some(server_id);
+        reply.subnet_mask = Some(self.config.subnet_mask);
+        reply.router = self.config.router;
+        reply.dns_servers = self.config.dns_servers.clone();
+        reply.lease_duration = Some(self.config.lease_duration.as_secs() as u32);
+        reply
+    }
+}
+
+/// Runs a DHCP server leasing addresses via a UDP socket as long as f returns
+/// true.
+///
+/// The socket should be bound to the server port and have broadcast enabled,
+/// since clients without an address broadcast their messages and expect
+/// broadcast replies.
+pub fn dhcp_server<F: FnMut() -> bool>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    server: &mut DhcpServer,
+    mut f: F,
+) {
+    while f() {
+        env::tick(interface, socket_set);
+
+        let server_id = *interface.ipv4_addr;
+        let udp_socket = socket_set.socket(udp_handle).unwrap().as_udp_socket();
+
+        while let Ok((payload, _)) = udp_socket.recv() {
+            let request = match parse(payload) {
+                Ok(request) => request,
+                Err(err) => {
+                    debug!("Ignoring DHCP message with {:?}.", err);
+                    continue;
+                }
+            };
+
+            let reply = match server.handle(&request, server_id, Instant::now()) {
+                Some(reply) => reply,
+                None => continue,
+            };
+
+            debug!(
+                "Sending DHCP {:?} of {} to {}.",
+                reply.message_type, reply.your_ip, reply.client_hw_addr
+            );
+
+            // Clients without an address can only receive broadcasts.
+            let addr = if request.broadcast || request.client_ip.is_unspecified() {
+                Ipv4Address::BROADCAST
+            } else {
+                request.client_ip
+            };
+
+            let res = udp_socket
+                .send(
+                    reply.buffer_len(),
+                    SocketAddr {
+                        addr,
+                        port: dhcpv4_ports::CLIENT,
+                    },
+                )
+                .and_then(|buffer| reply.serialize(&mut Dhcpv4Packet::try_new(buffer)?));
+
+            match res {
+                Ok(_) => {}
+                Err(Error::Buffer(BufferError::Full)) => {
+                    debug!("Dropping DHCP reply, send buffer is full.");
+                }
+                Err(err) => debug!("Error sending DHCP reply with {:?}.", err),
+            }
+        }
+    }
+}
+
+fn parse(payload: &[u8]) -> Result<Dhcpv4Repr> {
+    let dhcp_packet = Dhcpv4Packet::try_new(payload)?;
+    dhcp_packet.check_encoding()?;
+    let dhcp_repr = Dhcpv4Repr::deserialize(&dhcp_packet)?;
+
+    if dhcp_repr.message_type.is_reply() {
+        Err(Error::Ignored)
+    } else {
+        Ok(dhcp_repr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> DhcpServer {
+        DhcpServer::new(DhcpServerConfig {
+            pool_start: Ipv4Address::new([10, 0, 0, 100]),
+            pool_len: 2,
+            subnet_mask: Ipv4Address::new([255, 255, 255, 0]),
+            router: Some(server_id()),
+            dns_servers: vec![server_id()],
+            lease_duration: Duration::from_secs(3600),
+        })
+    }
+
+    fn server_id() -> Ipv4Address {
+        Ipv4Address::new([10, 0, 0, 1])
+    }
+
+    fn hw_addr(host: u8) -> EthernetAddress {
+        EthernetAddress::new([0x06, 0, 0, 0, 0, host])
+    }
+
+    fn discover(host: u8) -> Dhcpv4Repr {
+        let mut discover = Dhcpv4Repr::new(Dhcpv4MessageType::Discover, 7, hw_addr(host));
+        discover.broadcast = true;
+        discover
+    }
+
+    fn request(host: u8, ipv4_addr: Ipv4Address) -> Dhcpv4Repr {
+        let mut request = Dhcpv4Repr::new(Dhcpv4MessageType::Request, 7, hw_addr(host));
+        request.requested_ip = Some(ipv4_addr);
+        request.server_identifier = Some(server_id());
+        request
+    }
+
+    #[test]
+    fn test_offer_and_ack() {
+        let mut server = server();
+        let now = Instant::now();
+
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        assert_eq!(offer.message_type, Dhcpv4MessageType::Offer);
+        assert_eq!(offer.transaction_id, 7);
+        assert_eq!(offer.your_ip, Ipv4Address::new([10, 0, 0, 100]));
+        assert_eq!(offer.server_identifier, Some(server_id()));
+        assert_eq!(offer.lease_duration, Some(3600));
+        assert!(!server.leases(now)[0].bound);
+
+        let ack = server
+            .handle(&request(1, offer.your_ip), server_id(), now)
+            .unwrap();
+        assert_eq!(ack.message_type, Dhcpv4MessageType::Ack);
+        assert_eq!(ack.your_ip, offer.your_ip);
+        assert_eq!(server.leases(now).len(), 1);
+        assert!(server.leases(now)[0].bound);
+
+        // A client restarting discovery is offered the same address.
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        assert_eq!(offer.your_ip, ack.your_ip);
+    }
+
+    #[test]
+    fn test_pool_exhausted() {
+        let mut server = server();
+        let now = Instant::now();
+
+        assert!(server.handle(&discover(1), server_id(), now).is_some());
+        assert!(server.handle(&discover(2), server_id(), now).is_some());
+        assert!(server.handle(&discover(3), server_id(), now).is_none());
+
+        // Offers expire if not requested.
+        let later = now + OFFER_TIMEOUT;
+        let offer = server.handle(&discover(3), server_id(), later).unwrap();
+        assert_eq!(offer.your_ip, Ipv4Address::new([10, 0, 0, 100]));
+    }
+
+    #[test]
+    fn test_nak_address_of_other_client() {
+        let mut server = server();
+        let now = Instant::now();
+
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        let nak = server
+            .handle(&request(2, offer.your_ip), server_id(), now)
+            .unwrap();
+        assert_eq!(nak.message_type, Dhcpv4MessageType::Nak);
+
+        let nak = server
+            .handle(
+                &request(2, Ipv4Address::new([10, 0, 0, 50])),
+                server_id(),
+                now,
+            )
+            .unwrap();
+        assert_eq!(nak.message_type, Dhcpv4MessageType::Nak);
+    }
+
+    #[test]
+    fn test_request_for_other_server_releases_offer() {
+        let mut server = server();
+        let now = Instant::now();
+
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        let mut request = request(1, offer.your_ip);
+        request.server_identifier = Some(Ipv4Address::new([10, 0, 0, 2]));
+        assert!(server.handle(&request, server_id(), now).is_none());
+        assert!(server.leases(now).is_empty());
+    }
+
+    #[test]
+    fn test_release_and_decline() {
+        let mut server = server();
+        let now = Instant::now();
+
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        server.handle(&request(1, offer.your_ip), server_id(), now);
+
+        let mut release = Dhcpv4Repr::new(Dhcpv4MessageType::Release, 8, hw_addr(1));
+        release.client_ip = offer.your_ip;
+        assert!(server.handle(&release, server_id(), now).is_none());
+        assert!(server.leases(now).is_empty());
+
+        let mut decline = Dhcpv4Repr::new(Dhcpv4MessageType::Decline, 9, hw_addr(1));
+        decline.requested_ip = Some(offer.your_ip);
+        assert!(server.handle(&decline, server_id(), now).is_none());
+
+        // The declined address is skipped.
+        let offer = server.handle(&discover(1), server_id(), now).unwrap();
+        assert_eq!(offer.your_ip, Ipv4Address::new([10, 0, 0, 101]));
+    }
+}
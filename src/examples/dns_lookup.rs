@@ -0,0 +1,31 @@
+use stack::repr::DnsRecordData;
+use stack::service::dns::Resolver;
+use stack::service::Interface;
+use stack::socket::SocketSet;
+use examples::env;
+use Result;
+
+/// Resolves records of a type for a name via a resolver, blocking until the
+/// query completes.
+pub fn dns_lookup(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    resolver: &mut Resolver,
+    name: &str,
+    record_type: u16,
+) -> Result<Vec<DnsRecordData>> {
+    let handle = resolver.query(name, record_type)?;
+
+    loop {
+        if let Err(err) = resolver.poll(interface, socket_set) {
+            resolver.cancel(handle);
+            return Err(err);
+        }
+
+        if let Some(result) = resolver.take_result(handle) {
+            return result;
+        }
+
+        env::tick(interface, socket_set);
+    }
+}
@@ -0,0 +1,289 @@
+use std::result::Result as StdResult;
+use std::str::FromStr;
+
+use stack::capture::describe_frame;
+use stack::repr::{
+    eth_types,
+    ipv4_protocols,
+    Arp,
+    EthernetFrame,
+    Ipv4Address,
+    Ipv4Packet,
+    TcpPacket,
+    UdpPacket,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
+use examples::env;
+
+/// A protocol a dump filter can select.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Arp,
+    Ip,
+    Icmp,
+    Udp,
+    Tcp,
+}
+
+/// A tcpdump-like filter expression over Ethernet frames.
+///
+/// Expressions are a sequence of terms which must all match, optionally
+/// joined by 'and', e.g. "tcp and port 80 and host 10.0.0.1". Supported
+/// terms are the protocols 'arp', 'ip', 'icmp', 'udp' and 'tcp', 'host
+/// <IPv4 address>' and 'port <number>'.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub protocol: Option<Protocol>,
+    pub host: Option<Ipv4Address>,
+    pub port: Option<u16>,
+}
+
+impl Filter {
+    /// Checks if an Ethernet frame matches every term of the filter.
+    pub fn matches(&self, frame: &[u8]) -> bool {
+        let eth_frame = match EthernetFrame::try_new(frame) {
+            Ok(eth_frame) => eth_frame,
+            Err(_) => return false,
+        };
+
+        match eth_frame.payload_type() {
+            eth_types::ARP => {
+                let arp = match Arp::deserialize(eth_frame.payload()) {
+                    Ok(arp) => arp,
+                    Err(_) => return false,
+                };
+
+                let host_matches = match self.host {
+                    Some(host) => host == arp.source_proto_addr || host == arp.target_proto_addr,
+                    None => true,
+                };
+
+                match self.protocol {
+                    None | Some(Protocol::Arp) => host_matches && self.port.is_none(),
+                    _ => false,
+                }
+            }
+            eth_types::IPV4 => {
+                let ipv4_packet = match Ipv4Packet::try_new(eth_frame.payload()) {
+                    Ok(ipv4_packet) => ipv4_packet,
+                    Err(_) => return false,
+                };
+
+                if ipv4_packet.check_encoding().is_err() {
+                    return false;
+                }
+
+                let host_matches = match self.host {
+                    Some(host) => host == ipv4_packet.src_addr() || host == ipv4_packet.dst_addr(),
+                    None => true,
+                };
+
+                // Only the first fragment carries the transport header.
+                let ports = if ipv4_packet.fragment_offset() != 0 {
+                    None
+                } else {
+                    let payload = ipv4_packet.payload();
+                    match ipv4_packet.protocol() {
+                        ipv4_protocols::UDP => UdpPacket::try_new(payload)
+                            .ok()
+                            .map(|udp_packet| (udp_packet.src_port(), udp_packet.dst_port())),
+                        ipv4_protocols::TCP => TcpPacket::try_new(payload)
+                            .ok()
+                            .map(|tcp_packet| (tcp_packet.src_port(), tcp_packet.dst_port())),
+                        _ => None,
+                    }
+                };
+
+                let port_matches = match (self.port, ports) {
+                    (Some(port), Some((src_port, dst_port))) => {
+                        port == src_port || port == dst_port
+                    }
+                    (Some(_), None) => false,
+                    (None, _) => true,
+                };
+
+                let protocol_matches = match self.protocol {
+                    None | Some(Protocol::Ip) => true,
+                    Some(Protocol::Icmp) => ipv4_packet.protocol() == ipv4_protocols::ICMP,
+                    Some(Protocol::Udp) => ipv4_packet.protocol() == ipv4_protocols::UDP,
+                    Some(Protocol::Tcp) => ipv4_packet.protocol() == ipv4_protocols::TCP,
+                    Some(Protocol::Arp) => false,
+                };
+
+                protocol_matches && host_matches && port_matches
+            }
+            _ => self.protocol.is_none() && self.host.is_none() && self.port.is_none(),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = ();
+
+    /// Parses a filter from an expression such as "udp and port 53".
+    fn from_str(expr: &str) -> StdResult<Filter, Self::Err> {
+        let mut filter = Filter::default();
+        let mut tokens = expr.split_whitespace().filter(|token| *token != "and");
+
+        while let Some(token) = tokens.next() {
+            let protocol = match token {
+                "arp" => Protocol::Arp,
+                "ip" => Protocol::Ip,
+                "icmp" => Protocol::Icmp,
+                "udp" => Protocol::Udp,
+                "tcp" => Protocol::Tcp,
+                "host" if filter.host.is_none() => {
+                    let host = tokens.next().ok_or(())?;
+                    filter.host = Some(Ipv4Address::from_str(host)?);
+                    continue;
+                }
+                "port" if filter.port.is_none() => {
+                    let port = tokens.next().ok_or(())?;
+                    filter.port = Some(port.parse::<u16>().map_err(|_| ())?);
+                    continue;
+                }
+                _ => return Err(()),
+            };
+
+            if filter.protocol.is_some() {
+                return Err(());
+            }
+
+            filter.protocol = Some(protocol);
+        }
+
+        Ok(filter)
+    }
+}
+
+/// Captures frames received via a raw Ethernet socket which match a filter,
+/// passing a tcpdump-like description of each to f until it returns false.
+pub fn dump<F: FnMut(&str) -> bool>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    raw_handle: SocketHandle,
+    filter: Filter,
+    mut f: F,
+) {
+    // Filter on enqueue so uninteresting traffic doesn't fill the buffer.
+    socket_set
+        .socket(raw_handle)
+        .unwrap()
+        .as_raw_socket()
+        .set_filter(move |frame| filter.matches(frame));
+
+    loop {
+        env::tick(interface, socket_set);
+
+        while let Ok(frame) = socket_set
+            .socket(raw_handle)
+            .unwrap()
+            .as_raw_socket()
+            .recv()
+        {
+            if !f(&describe_frame(frame)) {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stack::repr::{
+        ArpOp,
+        EthernetAddress,
+        Ipv4Options,
+        Ipv4Protocol,
+        Ipv4Repr,
+        UdpRepr,
+    };
+
+    use super::*;
+
+    fn arp_frame() -> Vec<u8> {
+        let arp = Arp {
+            op: ArpOp::Request,
+            source_hw_addr: EthernetAddress::new([0, 1, 2, 3, 4, 5]),
+            source_proto_addr: Ipv4Address::new([10, 0, 0, 1]),
+            target_hw_addr: EthernetAddress::new([0; 6]),
+            target_proto_addr: Ipv4Address::new([10, 0, 0, 2]),
+        };
+        let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(arp.buffer_len())];
+        {
+            let mut eth_frame = EthernetFrame::try_new(&mut buffer[..]).unwrap();
+            eth_frame.set_payload_type(eth_types::ARP);
+            arp.serialize(eth_frame.payload_mut()).unwrap();
+        }
+        buffer
+    }
+
+    fn udp_frame(src_port: u16, dst_port: u16) -> Vec<u8> {
+        let udp_repr = UdpRepr {
+            src_port,
+            dst_port,
+            length: 8,
+            checksum: true,
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new([10, 0, 0, 1]),
+            dst_addr: Ipv4Address::new([10, 0, 0, 3]),
+            protocol: Ipv4Protocol::UDP,
+            payload_len: udp_repr.length,
+            ttl: 64,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        };
+        let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(ipv4_repr.buffer_len())];
+        {
+            let mut eth_frame = EthernetFrame::try_new(&mut buffer[..]).unwrap();
+            eth_frame.set_payload_type(eth_types::IPV4);
+            let mut ipv4_packet = Ipv4Packet::try_new(eth_frame.payload_mut()).unwrap();
+            ipv4_repr.serialize(&mut ipv4_packet);
+            let mut udp_packet = UdpPacket::try_new(ipv4_packet.payload_mut()).unwrap();
+            udp_repr.serialize(&mut udp_packet, &ipv4_repr);
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        assert_eq!(Filter::from_str(""), Ok(Filter::default()));
+        assert_eq!(
+            Filter::from_str("udp and port 53 and host 10.0.0.1"),
+            Ok(Filter {
+                protocol: Some(Protocol::Udp),
+                host: Some(Ipv4Address::new([10, 0, 0, 1])),
+                port: Some(53),
+            })
+        );
+        assert_eq!(Filter::from_str("tcp udp"), Err(()));
+        assert_eq!(Filter::from_str("port"), Err(()));
+        assert_eq!(Filter::from_str("host 10.0.0"), Err(()));
+        assert_eq!(Filter::from_str("vlan"), Err(()));
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let arp_frame = arp_frame();
+        let udp_frame = udp_frame(1024, 53);
+
+        let filter = |expr| Filter::from_str(expr).unwrap();
+
+        assert!(filter("").matches(&arp_frame));
+        assert!(filter("").matches(&udp_frame));
+        assert!(filter("arp host 10.0.0.2").matches(&arp_frame));
+        assert!(!filter("arp host 10.0.0.3").matches(&arp_frame));
+        assert!(!filter("arp").matches(&udp_frame));
+        assert!(filter("ip").matches(&udp_frame));
+        assert!(filter("udp port 53 host 10.0.0.3").matches(&udp_frame));
+        assert!(!filter("udp port 80").matches(&udp_frame));
+        assert!(!filter("tcp").matches(&udp_frame));
+        assert!(!filter("port 53").matches(&arp_frame));
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::net::{
     IpAddr as StdIpAddr,
     Ipv4Addr as StdIpv4Addr,
@@ -5,22 +6,32 @@ use std::net::{
 
 use get_if_addrs;
 
-use core::arp_cache::ArpCache;
-use core::dev::Device;
-use core::repr::{
+use stack::arp_cache::ArpCache;
+use stack::dev::Device;
+use stack::neighbor_cache::NeighborCache;
+use stack::repr::{
     EthernetAddress,
     Ipv4Address,
     Ipv4AddressCidr,
+    Ipv6Address,
+    Ipv6AddressCidr,
 };
-use core::service::{
+use stack::service::routing::RoutingTable;
+use stack::service::{
+    arp,
+    icmpv4,
+    ipv4,
     socket,
     Interface,
+    InterfaceStats,
+    StackStats,
 };
-use core::socket::{
+use stack::socket::{
     SocketEnv,
     SocketSet,
 };
-use core::time::SystemEnv;
+use stack::storage::Pool;
+use stack::time::SystemEnv;
 
 /// Default capacity of a socket set.
 pub static SOCKET_SET_HANDLES: usize = 64;
@@ -52,15 +63,35 @@ lazy_static! {
     };
 }
 
+/// Name of the device examples run on by default. See tap.sh for more info.
 #[cfg(target_os = "linux")]
-pub fn default_dev() -> Box<Device> {
+pub static DEFAULT_DEV_NAME: &str = "tap0";
+
+/// Name of the device examples run on by default.
+#[cfg(not(target_os = "linux"))]
+pub static DEFAULT_DEV_NAME: &str = "feth0";
+
+pub fn default_dev() -> Box<dyn Device> {
+    dev(DEFAULT_DEV_NAME)
+}
+
+/// Opens a TAP device by name.
+#[cfg(target_os = "linux")]
+pub fn dev(ifr_name: &str) -> Box<dyn Device> {
     use linux::tap::Tap;
-    Box::new(Tap::new("tap0"))
+    Box::new(Tap::new(ifr_name))
 }
 
-#[cfg(not(target_os = "linux"))]
-pub fn default_dev() -> Box<Device> {
-    panic!("Sorry, examples are only supported on Linux.");
+/// Opens a BPF device attached to an interface by name.
+#[cfg(target_os = "macos")]
+pub fn dev(ifr_name: &str) -> Box<dyn Device> {
+    use macos::bpf::Bpf;
+    Box::new(Bpf::new(ifr_name))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn dev(_ifr_name: &str) -> Box<dyn Device> {
+    panic!("Sorry, examples are only supported on Linux and macOS.");
 }
 
 /// Get's the IPv4 address for an interface. See tap.sh for more info.
@@ -76,15 +107,45 @@ pub fn ifr_addr(ifr_name: &str) -> StdIpv4Addr {
     panic!("IPv4 address for '{}' not found!", ifr_name);
 }
 
-/// Creates a network interface.
-pub fn default_interface() -> Interface {
-    let interface = Interface {
-        dev: default_dev(),
+/// Creates a network interface for a device with an Ethernet and IPv4
+/// address, using the default gateway.
+pub fn interface(
+    dev: Box<dyn Device>,
+    ethernet_addr: EthernetAddress,
+    ipv4_addr: Ipv4AddressCidr,
+) -> Interface {
+    Interface {
+        dev,
         arp_cache: ArpCache::new(60, SystemEnv::new()),
-        ethernet_addr: *DEFAULT_ETH_ADDR,
-        ipv4_addr: *DEFAULT_IPV4_ADDR_CIDR,
+        arp_pending: arp::PendingQueue::new(100, SystemEnv::new()),
+        ethernet_addr,
+        ipv4_addr,
         default_gateway: *DEFAULT_IPV4_GATEWAY,
-    };
+        dns_servers: Vec::new(),
+        routing_table: RoutingTable::new(),
+        ipv4_multicast_groups: BTreeSet::new(),
+        ipv4_reassembler: ipv4::Reassembler::new(30, SystemEnv::new()),
+        icmpv4_rate_limiter: icmpv4::RateLimiter::new(1000, 50, SystemEnv::new()),
+        arp_conflict_handler: None,
+        arp_claim: None,
+        capture: None,
+        tx_batch: None,
+        shaper: None,
+        filter: None,
+        conntrack: None,
+        pppoe_session: None,
+        shim: None,
+        pool: Pool::new(),
+        stats: InterfaceStats::default(),
+        stack_stats: StackStats::default(),
+        ipv6_addr: Ipv6AddressCidr::new(Ipv6Address::link_local(ethernet_addr), 64),
+        neighbor_cache: NeighborCache::new(60, SystemEnv::new()),
+    }
+}
+
+/// Creates a network interface.
+pub fn default_interface() -> Interface {
+    let interface = interface(default_dev(), *DEFAULT_ETH_ADDR, *DEFAULT_IPV4_ADDR_CIDR);
 
     println!(
         "Interface: (MTU = {}, MAC = {}, IPv4 = {}, Gateway: {})",
@@ -0,0 +1,289 @@
+use std::net::Shutdown;
+use std::str;
+
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+    TcpStateKind,
+};
+use examples::env;
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Maximum length of the head of a request, i.e. the request line and headers.
+pub static MAX_REQUEST_LEN: usize = 4096;
+
+/// The request line of an HTTP request. Headers are ignored.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub version: String,
+}
+
+impl HttpRequest {
+    /// Tries to parse the head of a request, returning None if the blank line
+    /// ending the head has not been received yet.
+    pub fn parse(buffer: &[u8]) -> Result<Option<HttpRequest>> {
+        let head_len = match buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            Some(head_len) => head_len,
+            None if buffer.len() > MAX_REQUEST_LEN => {
+                return Err(Error::Parse(ParseError::Malformed))
+            }
+            None => return Ok(None),
+        };
+
+        let head =
+            str::from_utf8(&buffer[.. head_len]).map_err(|_| Error::Parse(ParseError::Malformed))?;
+        let request_line = head.split("\r\n").next().unwrap();
+        let tokens: Vec<_> = request_line.split(' ').collect();
+
+        if tokens.len() != 3 || !tokens[1].starts_with('/') || !tokens[2].starts_with("HTTP/1.")
+        {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        Ok(Some(HttpRequest {
+            method: tokens[0].to_string(),
+            path: tokens[1].to_string(),
+            version: tokens[2].to_string(),
+        }))
+    }
+}
+
+/// A response to an HTTP request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub reason: &'static str,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    /// Creates a 200 OK response with a body.
+    pub fn ok(content_type: &'static str, body: Vec<u8>) -> HttpResponse {
+        HttpResponse {
+            status: 200,
+            reason: "OK",
+            content_type,
+            body,
+        }
+    }
+
+    /// Creates an error response with the reason as a plain text body.
+    pub fn error(status: u16, reason: &'static str) -> HttpResponse {
+        HttpResponse {
+            status,
+            reason,
+            content_type: "text/plain",
+            body: format!("{} {}\n", status, reason).into_bytes(),
+        }
+    }
+
+    /// Serializes the status line, headers and body of the response.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buffer = format!(
+            "HTTP/1.0 {} {}\r\n\
+             Content-Type: {}\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\
+             \r\n",
+            self.status,
+            self.reason,
+            self.content_type,
+            self.body.len()
+        ).into_bytes();
+        buffer.extend_from_slice(&self.body);
+        buffer
+    }
+}
+
+/// A connection accepted by the server.
+struct HttpConnection {
+    tcp_handle: SocketHandle,
+    request: Vec<u8>,
+    /// Bytes of the response which have not been enqueued yet, once the
+    /// request has been handled.
+    response: Option<Vec<u8>>,
+    peer_done: bool,
+}
+
+/// Runs an HTTP/1.0 server as long as f returns true.
+///
+/// GET requests are answered with the response returned by the handler, and
+/// other methods with 501 Not Implemented. As in HTTP/1.0, the connection is
+/// closed after each response.
+pub fn http_server<F, H>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    listener_handle: SocketHandle,
+    mut handler: H,
+    mut f: F,
+) where
+    F: FnMut() -> bool,
+    H: FnMut(&HttpRequest) -> HttpResponse,
+{
+    let mut connections: Vec<HttpConnection> = Vec::new();
+    let mut buffer = [0; 1024];
+
+    while f() {
+        env::tick(interface, socket_set);
+
+        while let Some((tcp_socket, socket_addr)) = socket_set
+            .socket(listener_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .accept()
+        {
+            match socket_set.add_socket(TaggedSocket::Tcp(tcp_socket)) {
+                Ok(tcp_handle) => {
+                    debug!("Got a connection from {}!", socket_addr);
+                    connections.push(HttpConnection {
+                        tcp_handle,
+                        request: Vec::new(),
+                        response: None,
+                        peer_done: false,
+                    });
+                }
+                Err(err) => debug!("Dropping connection from {} with {:?}.", socket_addr, err),
+            }
+        }
+
+        let mut i = 0;
+        while i < connections.len() {
+            if serve(socket_set, &mut connections[i], &mut handler, &mut buffer) {
+                let connection = connections.swap_remove(i);
+                socket_set.remove(connection.tcp_handle).unwrap();
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    for connection in connections {
+        socket_set.remove(connection.tcp_handle).unwrap();
+    }
+}
+
+/// Makes progress on a connection, returning true once it can be removed.
+fn serve<H>(
+    socket_set: &mut SocketSet,
+    connection: &mut HttpConnection,
+    handler: &mut H,
+    buffer: &mut [u8],
+) -> bool
+where
+    H: FnMut(&HttpRequest) -> HttpResponse,
+{
+    let tcp_socket = socket_set
+        .socket(connection.tcp_handle)
+        .unwrap()
+        .as_tcp_socket();
+
+    loop {
+        match tcp_socket.recv(buffer) {
+            Ok(0) => {
+                connection.peer_done = true;
+                break;
+            }
+            Ok(recv_len) if connection.response.is_none() => {
+                connection.request.extend_from_slice(&buffer[.. recv_len]);
+            }
+            Ok(_) => {}
+            Err(Error::Buffer(BufferError::Empty)) => break,
+            Err(_) => return true,
+        }
+    }
+
+    if connection.response.is_none() {
+        let response = match HttpRequest::parse(&connection.request) {
+            Ok(Some(ref request)) if request.method == "GET" => handler(request),
+            Ok(Some(_)) => HttpResponse::error(501, "Not Implemented"),
+            Ok(None) if connection.peer_done => HttpResponse::error(400, "Bad Request"),
+            Ok(None) => return false,
+            Err(_) => HttpResponse::error(400, "Bad Request"),
+        };
+        connection.response = Some(response.serialize());
+    }
+
+    let response = connection.response.as_mut().unwrap();
+    if !response.is_empty() {
+        match tcp_socket.send(response) {
+            Ok(send_len) => {
+                response.drain(.. send_len);
+                if response.is_empty() {
+                    tcp_socket.shutdown(Shutdown::Write);
+                }
+            }
+            Err(Error::Buffer(BufferError::Full)) => {}
+            Err(_) => return true,
+        }
+    }
+
+    // Rather than lingering in TIME_WAIT, forget the connection once both
+    // ends have closed it.
+    tcp_socket.is_closed() || tcp_socket.state() == TcpStateKind::TimeWait
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!(
+            HttpRequest::parse(b"GET /index.html HTTP/1.0\r\nHost: usrnet\r\n\r\n").unwrap(),
+            Some(HttpRequest {
+                method: "GET".to_string(),
+                path: "/index.html".to_string(),
+                version: "HTTP/1.0".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_incomplete_request() {
+        assert_eq!(HttpRequest::parse(b"GET / HTTP/1.0\r\n").unwrap(), None);
+        assert_matches!(
+            HttpRequest::parse(&vec![b'a'; MAX_REQUEST_LEN + 1]),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_parse_malformed_request() {
+        for request in [
+            &b"GET\r\n\r\n"[..],
+            &b"GET index.html HTTP/1.0\r\n\r\n"[..],
+            &b"GET / SPDY/3\r\n\r\n"[..],
+            &b"GET / HTTP/1.0 extra\r\n\r\n"[..],
+        ].iter()
+        {
+            assert_matches!(
+                HttpRequest::parse(request),
+                Err(Error::Parse(ParseError::Malformed))
+            );
+        }
+    }
+
+    #[test]
+    fn test_serialize_response() {
+        assert_eq!(
+            HttpResponse::ok("text/plain", b"hi".to_vec()).serialize(),
+            b"HTTP/1.0 200 OK\r\n\
+              Content-Type: text/plain\r\n\
+              Content-Length: 2\r\n\
+              Connection: close\r\n\
+              \r\n\
+              hi"
+                .to_vec()
+        );
+    }
+}
@@ -1,14 +1,73 @@
 //! Sample programs.
 
 pub mod arping;
+pub mod arpscan;
+pub mod dhcp_server;
+pub mod dns_lookup;
+pub mod dump;
 pub mod env;
+pub mod http_server;
+pub mod nc;
+pub mod perf;
 pub mod ping;
+pub mod sntp;
 pub mod tcp_echo;
+pub mod tftp;
 pub mod traceroute;
 pub mod udp_echo;
 
 pub use self::arping::arping;
-pub use self::ping::ping;
+pub use self::arpscan::{
+    arpscan,
+    ArpScanConfig,
+    ArpScanHost,
+};
+pub use self::dhcp_server::{
+    dhcp_server,
+    DhcpLease,
+    DhcpServer,
+    DhcpServerConfig,
+};
+pub use self::dns_lookup::dns_lookup;
+pub use self::dump::dump;
+pub use self::http_server::{
+    http_server,
+    HttpRequest,
+    HttpResponse,
+};
+pub use self::nc::{
+    nc_connect,
+    nc_listen,
+    nc_udp,
+};
+pub use self::perf::{
+    perf_tcp_client,
+    perf_tcp_server,
+    perf_udp_client,
+    perf_udp_server,
+    PerfResult,
+};
+pub use self::ping::{
+    ping,
+    PingConfig,
+    PingStats,
+    Pinger,
+};
+pub use self::sntp::{
+    sntp,
+    SntpResult,
+};
 pub use self::tcp_echo::tcp_echo;
-pub use self::traceroute::traceroute;
+pub use self::tftp::{
+    tftp_get,
+    tftp_put,
+    tftp_server,
+    TftpConfig,
+};
+pub use self::traceroute::{
+    traceroute,
+    traceroute_with,
+    TraceConfig,
+    TraceMode,
+};
 pub use self::udp_echo::udp_echo;
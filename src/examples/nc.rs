@@ -0,0 +1,224 @@
+use std::io::Write;
+use std::net::Shutdown;
+use std::sync::mpsc::{
+    Receiver,
+    TryRecvError,
+};
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+    TcpStateKind,
+};
+use examples::env;
+use {
+    BufferError,
+    Error,
+    Result,
+    SocketError,
+};
+
+/// How long to keep servicing a TCP connection after both ends have finished
+/// sending, so the final FIN and ACK make it onto the link.
+static TCP_LINGER: Duration = Duration::from_secs(1);
+
+/// Connects a TCP socket to an endpoint and pipes data from input to the
+/// connection and from the connection to output.
+///
+/// The sending half of the connection is shut down once input disconnects,
+/// and the function returns once both ends have finished sending.
+pub fn nc_connect<W: Write>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    tcp_handle: SocketHandle,
+    socket_addr: SocketAddr,
+    input: &Receiver<Vec<u8>>,
+    output: &mut W,
+) -> Result<()> {
+    socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .connect(socket_addr);
+
+    while socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .is_establishing()
+    {
+        env::tick(interface, socket_set);
+    }
+
+    pipe_tcp(interface, socket_set, tcp_handle, input, output)
+}
+
+/// Accepts a single connection via a TCP listener and pipes data as in
+/// nc_connect(...), returning the address of the remote endpoint.
+pub fn nc_listen<W: Write>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    listener_handle: SocketHandle,
+    input: &Receiver<Vec<u8>>,
+    output: &mut W,
+) -> Result<SocketAddr> {
+    let (tcp_socket, socket_addr) = loop {
+        env::tick(interface, socket_set);
+
+        if let Some(accepted) = socket_set
+            .socket(listener_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .accept()
+        {
+            break accepted;
+        }
+    };
+
+    debug!("Got a connection from {}!", socket_addr);
+
+    let tcp_handle = socket_set.add_socket(TaggedSocket::Tcp(tcp_socket))?;
+    let res = pipe_tcp(interface, socket_set, tcp_handle, input, output);
+    socket_set.remove(tcp_handle)?;
+    res.map(|_| socket_addr)
+}
+
+/// Pipes data between a connected TCP socket, input and output.
+fn pipe_tcp<W: Write>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    tcp_handle: SocketHandle,
+    input: &Receiver<Vec<u8>>,
+    output: &mut W,
+) -> Result<()> {
+    let mut pending = Vec::new();
+    let mut input_done = false;
+    let mut remote_done = false;
+    let mut buffer = [0; 1024];
+
+    loop {
+        env::tick(interface, socket_set);
+
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+
+        if !input_done && pending.is_empty() {
+            match input.try_recv() {
+                Ok(data) => pending = data,
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => {
+                    input_done = true;
+                    tcp_socket.shutdown(Shutdown::Write);
+                }
+            }
+        }
+
+        if !pending.is_empty() {
+            match tcp_socket.send(&pending) {
+                Ok(send_len) => {
+                    pending.drain(.. send_len);
+                }
+                Err(Error::Buffer(BufferError::Full)) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        loop {
+            match tcp_socket.recv(&mut buffer) {
+                Ok(0) => {
+                    remote_done = true;
+                    break;
+                }
+                Ok(recv_len) => output.write_all(&buffer[.. recv_len])?,
+                Err(Error::Buffer(BufferError::Empty)) => break,
+                // Both ends may finish and close the connection in one tick.
+                Err(Error::Socket(SocketError::NotConnected)) if remote_done && input_done => {
+                    break
+                }
+                Err(Error::Socket(SocketError::NotConnected)) => {
+                    return Err(tcp_socket
+                        .take_error()
+                        .unwrap_or(Error::Socket(SocketError::NotConnected)));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if remote_done && input_done && tcp_socket.send_queued() == 0 {
+            break;
+        }
+    }
+
+    output.flush()?;
+
+    // TIME_WAIT is only entered once the FIN of the remote endpoint has been
+    // acknowledged, so there is nothing left to linger for.
+    let linger_start = Instant::now();
+    while Instant::now().duration_since(linger_start) < TCP_LINGER {
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+        if tcp_socket.is_closed() || tcp_socket.state() == TcpStateKind::TimeWait {
+            break;
+        }
+
+        env::tick(interface, socket_set);
+    }
+
+    Ok(())
+}
+
+/// Sends each chunk of data from input as a datagram via a UDP socket and
+/// writes the payload of datagrams received to output.
+///
+/// Without a peer address, data is sent to the source of the first datagram
+/// received. Returns once input disconnects and all datagrams are sent.
+pub fn nc_udp<W: Write>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    mut peer_addr: Option<SocketAddr>,
+    input: &Receiver<Vec<u8>>,
+    output: &mut W,
+) -> Result<()> {
+    let mut pending = None;
+    let mut input_done = false;
+
+    loop {
+        env::tick(interface, socket_set);
+
+        let udp_socket = socket_set.socket(udp_handle).unwrap().as_udp_socket();
+
+        while let Ok((payload, addr)) = udp_socket.recv() {
+            output.write_all(payload)?;
+            output.flush()?;
+            peer_addr.get_or_insert(addr);
+        }
+
+        if !input_done && pending.is_none() {
+            match input.try_recv() {
+                Ok(data) => pending = Some(data),
+                Err(TryRecvError::Empty) => {}
+                Err(TryRecvError::Disconnected) => input_done = true,
+            }
+        }
+
+        if let Some(addr) = peer_addr {
+            if let Some(data) = pending.take() {
+                match udp_socket.send(data.len(), addr) {
+                    Ok(payload) => payload.copy_from_slice(&data),
+                    Err(Error::Buffer(BufferError::Full)) => pending = Some(data),
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+
+        if input_done && pending.is_none() && udp_socket.send_enqueued() == 0 {
+            return Ok(());
+        }
+    }
+}
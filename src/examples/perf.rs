@@ -0,0 +1,367 @@
+use std::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use std::net::Shutdown;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+};
+use examples::env;
+use {
+    Error,
+    Result,
+    SocketError,
+};
+
+/// Size of the chunks of data sent and received over TCP.
+static TCP_CHUNK_LEN: usize = 4096;
+
+/// How long a TCP server waits for its FIN to be acknowledged.
+static TCP_LINGER: Duration = Duration::from_secs(1);
+
+/// Number of times the end of a UDP stream is signalled, in case some of the
+/// empty datagrams marking it are lost.
+static UDP_END_MARKERS: usize = 3;
+
+/// How long a UDP server waits for more datagrams before ending the stream.
+static UDP_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The result of a throughput measurement.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PerfResult {
+    /// Number of payload bytes transferred.
+    pub bytes: u64,
+    /// Time taken to transfer the payload.
+    pub duration: Duration,
+    /// Number of TCP segments retransmitted by the socket.
+    pub retransmits: u64,
+    /// Number of UDP datagrams sent but never received.
+    pub lost: u64,
+}
+
+impl PerfResult {
+    /// Returns the goodput in bits per second.
+    pub fn bits_per_sec(&self) -> f64 {
+        let secs = self.duration.as_secs() as f64 + self.duration.subsec_nanos() as f64 / 1e9;
+        if secs == 0.0 {
+            0.0
+        } else {
+            (self.bytes * 8) as f64 / secs
+        }
+    }
+}
+
+impl Display for PerfResult {
+    /// Formats an iperf-like summary of the measurement.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} bytes in {:.2} s, {:.2} Mbit/s, {} retransmits, {} lost",
+            self.bytes,
+            self.duration.as_secs() as f64 + self.duration.subsec_nanos() as f64 / 1e9,
+            self.bits_per_sec() / 1e6,
+            self.retransmits,
+            self.lost
+        )
+    }
+}
+
+/// Connects a TCP socket to a server and sends data as fast as possible for
+/// a duration, then waits for the server to receive everything and close its
+/// end of the connection.
+pub fn perf_tcp_client(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    tcp_handle: SocketHandle,
+    server_addr: SocketAddr,
+    duration: Duration,
+) -> Result<PerfResult> {
+    socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .connect(server_addr);
+
+    while socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .is_establishing()
+    {
+        env::tick(interface, socket_set);
+    }
+
+    let chunk = vec![0; TCP_CHUNK_LEN];
+    let mut buffer = vec![0; TCP_CHUNK_LEN];
+    let mut bytes = 0;
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < duration {
+        env::tick(interface, socket_set);
+
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+        if !tcp_socket.is_connected() {
+            return Err(tcp_socket
+                .take_error()
+                .unwrap_or(Error::Socket(SocketError::NotConnected)));
+        }
+
+        while let Ok(send_len) = tcp_socket.send(&chunk) {
+            bytes += send_len as u64;
+        }
+    }
+
+    socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .shutdown(Shutdown::Write);
+
+    // The server closes its end once it has received all the data.
+    loop {
+        env::tick(interface, socket_set);
+
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+        match tcp_socket.recv(&mut buffer) {
+            Ok(0) => break,
+            Err(Error::Socket(SocketError::NotConnected)) => {
+                return Err(tcp_socket
+                    .take_error()
+                    .unwrap_or(Error::Socket(SocketError::NotConnected)));
+            }
+            _ => {}
+        }
+    }
+
+    let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+
+    Ok(PerfResult {
+        bytes,
+        duration: Instant::now().duration_since(start),
+        retransmits: tcp_socket.stats().retransmits,
+        lost: 0,
+    })
+}
+
+/// Accepts a connection via a TCP listener and receives data until the client
+/// closes its end of the connection.
+pub fn perf_tcp_server(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    listener_handle: SocketHandle,
+) -> Result<PerfResult> {
+    let tcp_socket = loop {
+        env::tick(interface, socket_set);
+
+        if let Some((tcp_socket, socket_addr)) = socket_set
+            .socket(listener_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .accept()
+        {
+            debug!("Got a connection from {}!", socket_addr);
+            break tcp_socket;
+        }
+    };
+
+    let tcp_handle = socket_set.add_socket(TaggedSocket::Tcp(tcp_socket))?;
+    let mut buffer = vec![0; TCP_CHUNK_LEN];
+    let mut bytes = 0;
+    let start = Instant::now();
+
+    loop {
+        env::tick(interface, socket_set);
+
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+        match tcp_socket.recv(&mut buffer) {
+            Ok(0) => break,
+            Ok(recv_len) => bytes += recv_len as u64,
+            Err(Error::Socket(SocketError::NotConnected)) => {
+                let err = tcp_socket
+                    .take_error()
+                    .unwrap_or(Error::Socket(SocketError::NotConnected));
+                socket_set.remove(tcp_handle)?;
+                return Err(err);
+            }
+            Err(_) => {}
+        }
+    }
+
+    let duration = Instant::now().duration_since(start);
+
+    socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket()
+        .shutdown(Shutdown::Write);
+
+    let linger_start = Instant::now();
+    while Instant::now().duration_since(linger_start) < TCP_LINGER
+        && !socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket()
+            .is_closed()
+    {
+        env::tick(interface, socket_set);
+    }
+
+    let retransmits = socket_set
+        .remove(tcp_handle)?
+        .as_tcp_socket()
+        .stats()
+        .retransmits;
+
+    Ok(PerfResult {
+        bytes,
+        duration,
+        retransmits,
+        lost: 0,
+    })
+}
+
+/// Sends datagrams with payload_len bytes of payload to a server as fast as
+/// possible for a duration.
+///
+/// Each datagram starts with a 4 byte sequence number so the server can count
+/// lost datagrams, and the end of the stream is marked with empty datagrams.
+pub fn perf_udp_client(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    server_addr: SocketAddr,
+    duration: Duration,
+    payload_len: usize,
+) -> Result<PerfResult> {
+    let payload_len = payload_len.max(4);
+    let mut seq_num: u32 = 0;
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < duration {
+        env::tick(interface, socket_set);
+
+        while let Ok(payload) = socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(payload_len, server_addr)
+        {
+            NetworkEndian::write_u32(&mut payload[.. 4], seq_num);
+            seq_num = seq_num.wrapping_add(1);
+        }
+    }
+
+    let duration = Instant::now().duration_since(start);
+
+    // Drain so the end of the stream isn't dropped behind a burst of data.
+    drain_udp(interface, socket_set, udp_handle);
+
+    for _ in 0 .. UDP_END_MARKERS {
+        // Socket may have a full send buffer!
+        while socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(0, server_addr)
+            .is_err()
+        {
+            env::tick(interface, socket_set);
+        }
+    }
+
+    drain_udp(interface, socket_set, udp_handle);
+
+    Ok(PerfResult {
+        bytes: seq_num as u64 * payload_len as u64,
+        duration,
+        retransmits: 0,
+        lost: 0,
+    })
+}
+
+/// Ticks until all datagrams enqueued by a UDP socket are on the link.
+fn drain_udp(interface: &mut Interface, socket_set: &mut SocketSet, udp_handle: SocketHandle) {
+    while socket_set
+        .socket(udp_handle)
+        .unwrap()
+        .as_udp_socket()
+        .send_enqueued() > 0
+    {
+        env::tick(interface, socket_set);
+    }
+}
+
+/// Receives datagrams sent by perf_udp_client(...) until the end of the
+/// stream, timing from the first datagram received to the last.
+///
+/// The stream also ends if no datagrams are received for UDP_IDLE_TIMEOUT,
+/// in case every datagram marking the end was lost.
+pub fn perf_udp_server(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+) -> Result<PerfResult> {
+    let mut start = None;
+    let mut last_recv = Instant::now();
+    let mut bytes = 0;
+    let mut received: u64 = 0;
+    let mut expected: u64 = 0;
+
+    loop {
+        env::tick(interface, socket_set);
+
+        if let Some(start) = start {
+            if Instant::now().duration_since(last_recv) >= UDP_IDLE_TIMEOUT {
+                return Ok(PerfResult {
+                    bytes,
+                    duration: last_recv.duration_since(start),
+                    retransmits: 0,
+                    lost: expected.saturating_sub(received),
+                });
+            }
+        }
+
+        while let Ok((payload, _)) = socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .recv()
+        {
+            if payload.is_empty() {
+                // Ignore markers left over from a previous client.
+                if let Some(start) = start {
+                    return Ok(PerfResult {
+                        bytes,
+                        duration: last_recv.duration_since(start),
+                        retransmits: 0,
+                        lost: expected.saturating_sub(received),
+                    });
+                }
+                continue;
+            } else if payload.len() < 4 {
+                continue;
+            }
+
+            last_recv = Instant::now();
+            start.get_or_insert(last_recv);
+            bytes += payload.len() as u64;
+            received += 1;
+            expected = expected.max(NetworkEndian::read_u32(&payload[.. 4]) as u64 + 1);
+        }
+    }
+}
@@ -1,60 +1,45 @@
+use std::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
 use std::time::{
     Duration,
     Instant,
 };
 
-use core::repr::{
-    ipv4_protocols,
+use stack::repr::{
     Icmpv4Message,
-    Icmpv4Packet,
-    Icmpv4Repr,
     Ipv4Address,
-    Ipv4Packet,
-    Ipv4Protocol,
-    Ipv4Repr,
 };
-use core::service::Interface;
-use core::socket::SocketSet;
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
 use examples::env;
 use Error;
 
-/// Sends an ICMP ping request to a host via a raw IP socket.
+/// Sends an ICMP ping request to a host via an ICMP socket and waits for the
+/// echo reply, returning the round trip time.
 pub fn ping(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    raw_handle: usize,
+    icmp_handle: SocketHandle,
     ping_addr: Ipv4Address,
-    id: u16,
     seq: u16,
     payload: &[u8],
     timeout: Duration,
 ) -> Option<Duration> {
-    let icmp_repr = Icmpv4Repr {
-        message: Icmpv4Message::EchoRequest { id, seq },
-        payload_len: payload.len(),
-    };
-
-    let ipv4_repr = Ipv4Repr {
-        src_addr: *interface.ipv4_addr,
-        dst_addr: ping_addr,
-        protocol: Ipv4Protocol::ICMP,
-        payload_len: icmp_repr.buffer_len() as u16,
-    };
-
     // Socket may have a full send buffer!
-    while let Err(_) = socket_set
-        .socket(raw_handle)
-        .as_raw_socket()
-        .send(ipv4_repr.buffer_len())
-        .map(|ip_buffer| {
-            let mut ipv4_packet = Ipv4Packet::try_new(ip_buffer).unwrap();
-            ipv4_repr.serialize(&mut ipv4_packet);
-
-            let mut icmp_packet = Icmpv4Packet::try_new(ipv4_packet.payload_mut()).unwrap();
-            icmp_repr.serialize(&mut icmp_packet).unwrap();
-            icmp_packet.payload_mut().copy_from_slice(payload);
-            icmp_packet.fill_checksum();
-        }) {
+    while socket_set
+        .socket(icmp_handle)
+        .unwrap()
+        .as_icmp_socket()
+        .send_echo(payload.len(), ping_addr, seq)
+        .map(|buffer| buffer.copy_from_slice(payload))
+        .is_err()
+    {
         env::tick(interface, socket_set);
     }
 
@@ -65,40 +50,298 @@ pub fn ping(
 
         if waiting >= timeout {
             return None;
-        } else if let Ok(_) = socket_set
-            .socket(raw_handle)
-            .as_raw_socket()
+        } else if socket_set
+            .socket(icmp_handle)
+            .unwrap()
+            .as_icmp_socket()
             .recv()
-            .and_then(|ip_buffer| {
-                let ipv4_packet = Ipv4Packet::try_new(ip_buffer)?;
-                if ipv4_packet.protocol() != ipv4_protocols::ICMP
-                    || ipv4_packet.src_addr() != ping_addr
-                    || ipv4_packet.dst_addr() != *interface.ipv4_addr
-                {
-                    return Err(Error::Ignored);
-                }
-
-                let icmp_packet = Icmpv4Packet::try_new(ipv4_packet.payload())?;
-                icmp_packet.check_encoding()?;
-                let icmp_repr = Icmpv4Repr::deserialize(&icmp_packet)?;
-
-                match icmp_repr.message {
-                    Icmpv4Message::EchoReply {
-                        id: id_reply,
-                        seq: seq_reply,
-                    } => {
-                        if id_reply == id && seq_reply == seq && icmp_packet.payload() == payload {
-                            Ok(())
-                        } else {
-                            Err(Error::Ignored)
-                        }
+            .and_then(|(payload_reply, addr, icmp_repr)| match icmp_repr.message {
+                Icmpv4Message::EchoReply { seq: seq_reply, .. } => {
+                    if addr == ping_addr && seq_reply == seq && payload_reply == payload {
+                        Ok(())
+                    } else {
+                        Err(Error::Ignored)
                     }
-                    _ => Err(Error::Ignored),
                 }
-            }) {
+                _ => Err(Error::Ignored),
+            })
+            .is_ok()
+        {
             return Some(waiting);
         }
 
         env::tick(interface, socket_set);
     }
 }
+
+/// Settings for a series of pings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PingConfig {
+    /// Number of echo requests to send.
+    pub count: u16,
+    /// Time between sending echo requests. With an interval of zero, requests
+    /// are sent back to back as soon as a reply arrives, i.e. flood mode.
+    pub interval: Duration,
+    /// How long to wait for each echo reply.
+    pub timeout: Duration,
+    /// Number of payload bytes in each echo request.
+    pub payload_len: usize,
+    /// Bytes repeated to fill the payload, or incrementing bytes if empty.
+    pub pattern: Vec<u8>,
+}
+
+impl Default for PingConfig {
+    fn default() -> PingConfig {
+        PingConfig {
+            count: 64,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(1),
+            payload_len: 64,
+            pattern: Vec::new(),
+        }
+    }
+}
+
+impl PingConfig {
+    /// Creates a payload of payload_len bytes filled with the pattern.
+    pub fn payload(&self) -> Vec<u8> {
+        if self.pattern.is_empty() {
+            (0 .. self.payload_len).map(|i| i as u8).collect()
+        } else {
+            self.pattern
+                .iter()
+                .cloned()
+                .cycle()
+                .take(self.payload_len)
+                .collect()
+        }
+    }
+}
+
+/// Round trip times of a series of pings, with None for requests which timed
+/// out.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PingStats {
+    pub rtts: Vec<Option<Duration>>,
+}
+
+impl PingStats {
+    /// Returns the number of echo requests sent.
+    pub fn transmitted(&self) -> usize {
+        self.rtts.len()
+    }
+
+    /// Returns the number of echo replies received.
+    pub fn received(&self) -> usize {
+        self.rtts.iter().filter(|rtt| rtt.is_some()).count()
+    }
+
+    /// Returns the percentage of echo requests which went unanswered.
+    pub fn loss_percent(&self) -> f64 {
+        if self.rtts.is_empty() {
+            0.0
+        } else {
+            100.0 * (self.transmitted() - self.received()) as f64 / self.transmitted() as f64
+        }
+    }
+
+    /// Returns the shortest round trip time, if any replies were received.
+    pub fn min(&self) -> Option<Duration> {
+        self.rtts.iter().filter_map(|rtt| *rtt).min()
+    }
+
+    /// Returns the mean round trip time, if any replies were received.
+    pub fn avg(&self) -> Option<Duration> {
+        self.moments().map(|(mean, _)| from_secs_f64(mean))
+    }
+
+    /// Returns the longest round trip time, if any replies were received.
+    pub fn max(&self) -> Option<Duration> {
+        self.rtts.iter().filter_map(|rtt| *rtt).max()
+    }
+
+    /// Returns the standard deviation of the round trip times, if any replies
+    /// were received.
+    pub fn stddev(&self) -> Option<Duration> {
+        self.moments()
+            .map(|(mean, mean_sq)| from_secs_f64((mean_sq - mean * mean).max(0.0).sqrt()))
+    }
+
+    /// Returns the mean of the round trip times and of their squares, in
+    /// seconds.
+    fn moments(&self) -> Option<(f64, f64)> {
+        let received = self.received();
+        if received == 0 {
+            return None;
+        }
+
+        let (sum, sum_sq) = self
+            .rtts
+            .iter()
+            .filter_map(|rtt| rtt.map(as_secs_f64))
+            .fold((0.0, 0.0), |(sum, sum_sq), secs| {
+                (sum + secs, sum_sq + secs * secs)
+            });
+
+        Some((sum / received as f64, sum_sq / received as f64))
+    }
+}
+
+impl Display for PingStats {
+    /// Formats a ping-like summary of the series.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} packets transmitted, {} received, {:.1}% packet loss",
+            self.transmitted(),
+            self.received(),
+            self.loss_percent()
+        )?;
+
+        if let (Some(min), Some(avg), Some(max), Some(stddev)) =
+            (self.min(), self.avg(), self.max(), self.stddev())
+        {
+            write!(
+                f,
+                "\nrtt min/avg/max/stddev = {:.3}/{:.3}/{:.3}/{:.3} ms",
+                as_secs_f64(min) * 1e3,
+                as_secs_f64(avg) * 1e3,
+                as_secs_f64(max) * 1e3,
+                as_secs_f64(stddev) * 1e3
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+fn as_secs_f64(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + duration.subsec_nanos() as f64 / 1e9
+}
+
+fn from_secs_f64(secs: f64) -> Duration {
+    Duration::new(secs as u64, (secs.fract() * 1e9) as u32)
+}
+
+/// Sends a series of ICMP echo requests to a host via an ICMP socket, keeping
+/// track of round trip times.
+pub struct Pinger {
+    icmp_handle: SocketHandle,
+    ping_addr: Ipv4Address,
+    config: PingConfig,
+    payload: Vec<u8>,
+}
+
+impl Pinger {
+    /// Creates a pinger for a host.
+    pub fn new(icmp_handle: SocketHandle, ping_addr: Ipv4Address, config: PingConfig) -> Pinger {
+        let payload = config.payload();
+        Pinger {
+            icmp_handle,
+            ping_addr,
+            config,
+            payload,
+        }
+    }
+
+    /// Returns the settings of the pinger.
+    pub fn config(&self) -> &PingConfig {
+        &self.config
+    }
+
+    /// Sends config.count echo requests, calling f with the sequence number
+    /// and round trip time of each as it completes.
+    ///
+    /// The stack is serviced while waiting out the interval between requests.
+    pub fn run<F>(
+        &mut self,
+        interface: &mut Interface,
+        socket_set: &mut SocketSet,
+        mut f: F,
+    ) -> PingStats
+    where
+        F: FnMut(u16, Option<Duration>),
+    {
+        let mut stats = PingStats::default();
+
+        for seq in 0 .. self.config.count {
+            let send_at = Instant::now();
+
+            let rtt = ping(
+                interface,
+                socket_set,
+                self.icmp_handle,
+                self.ping_addr,
+                seq,
+                &self.payload,
+                self.config.timeout,
+            );
+            stats.rtts.push(rtt);
+            f(seq, rtt);
+
+            if seq + 1 < self.config.count {
+                while Instant::now().duration_since(send_at) < self.config.interval {
+                    env::tick(interface, socket_set);
+                }
+            }
+        }
+
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn millis(rtts: &[Option<u64>]) -> PingStats {
+        PingStats {
+            rtts: rtts
+                .iter()
+                .map(|rtt| rtt.map(Duration::from_millis))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = millis(&[Some(2), None, Some(4), Some(6), None]);
+        assert_eq!(stats.transmitted(), 5);
+        assert_eq!(stats.received(), 3);
+        assert_eq!(stats.loss_percent(), 40.0);
+        assert_eq!(stats.min(), Some(Duration::from_millis(2)));
+        assert_eq!(stats.max(), Some(Duration::from_millis(6)));
+
+        let avg = as_secs_f64(stats.avg().unwrap());
+        assert!((avg - 0.004).abs() < 1e-6);
+
+        // sqrt(((2 - 4)^2 + 0 + (6 - 4)^2) / 3) ms
+        let stddev = as_secs_f64(stats.stddev().unwrap());
+        assert!((stddev - 0.001633).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_stats_no_replies() {
+        let stats = millis(&[None, None]);
+        assert_eq!(stats.loss_percent(), 100.0);
+        assert_eq!(stats.min(), None);
+        assert_eq!(stats.avg(), None);
+        assert_eq!(stats.stddev(), None);
+        assert_eq!(
+            stats.to_string(),
+            "2 packets transmitted, 0 received, 100.0% packet loss"
+        );
+    }
+
+    #[test]
+    fn test_payload_pattern() {
+        let mut config = PingConfig {
+            payload_len: 5,
+            ..PingConfig::default()
+        };
+        assert_eq!(config.payload(), vec![0, 1, 2, 3, 4]);
+
+        config.pattern = vec![0xAB, 0xCD];
+        assert_eq!(config.payload(), vec![0xAB, 0xCD, 0xAB, 0xCD, 0xAB]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::{
+    Duration,
+    Instant,
+    SystemTime,
+};
+
+use stack::repr::{
+    ntp_modes,
+    NtpPacket,
+    NtpRepr,
+    NtpTimestamp,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+};
+use examples::env;
+use {
+    Error,
+    Result,
+};
+
+/// The result of querying a time server.
+#[derive(Clone, Copy, Debug)]
+pub struct SntpResult {
+    /// Time of the server when the response was received, i.e. the local time
+    /// corrected by the offset.
+    pub server_time: SystemTime,
+    /// Signed offset in nanoseconds of the server clock relative to the local
+    /// clock.
+    pub offset_nanos: i64,
+    /// Round trip delay excluding the time spent by the server.
+    pub delay: Duration,
+    pub stratum: u8,
+}
+
+/// Queries a time server via a UDP socket and computes the offset and round
+/// trip delay per RFC 4330, waiting up to timeout for a response.
+///
+/// Responses which do not echo the request's transmit timestamp, or are
+/// kiss-o'-death messages from a server refusing service, are ignored.
+pub fn sntp(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    server_addr: SocketAddr,
+    timeout: Duration,
+) -> Result<SntpResult> {
+    let request_repr = NtpRepr::request(NtpTimestamp::from_system_time(SystemTime::now()));
+
+    // Socket may have a full send buffer!
+    while socket_set
+        .socket(udp_handle)
+        .unwrap()
+        .as_udp_socket()
+        .send(request_repr.buffer_len(), server_addr)
+        .and_then(NtpPacket::try_new)
+        .map(|mut packet| request_repr.serialize(&mut packet))
+        .is_err()
+    {
+        env::tick(interface, socket_set);
+    }
+
+    let send_at = Instant::now();
+
+    while Instant::now().duration_since(send_at) < timeout {
+        env::tick(interface, socket_set);
+
+        let response_repr = match socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .recv()
+        {
+            Ok((payload, addr)) if addr == server_addr => {
+                match NtpPacket::try_new(payload).and_then(|packet| NtpRepr::deserialize(&packet)) {
+                    Ok(response_repr) => response_repr,
+                    Err(err) => {
+                        debug!("Ignoring NTP message from {} with {:?}.", addr, err);
+                        continue;
+                    }
+                }
+            }
+            _ => continue,
+        };
+
+        if response_repr.mode != ntp_modes::SERVER
+            || response_repr.stratum == 0
+            || response_repr.transmit_timestamp.is_zero()
+            || response_repr.originate_timestamp != request_repr.transmit_timestamp
+        {
+            debug!("Ignoring NTP response {:?}.", response_repr);
+            continue;
+        }
+
+        let recv_at = SystemTime::now();
+        let t1 = request_repr.transmit_timestamp;
+        let t2 = response_repr.receive_timestamp;
+        let t3 = response_repr.transmit_timestamp;
+        let t4 = NtpTimestamp::from_system_time(recv_at);
+
+        let offset_nanos = (t2.nanos_since(t1) + t3.nanos_since(t4)) / 2;
+        let delay_nanos = t4.nanos_since(t1) - t3.nanos_since(t2);
+
+        let server_time = if offset_nanos >= 0 {
+            recv_at + Duration::from_nanos(offset_nanos as u64)
+        } else {
+            recv_at - Duration::from_nanos(offset_nanos.unsigned_abs())
+        };
+
+        return Ok(SntpResult {
+            server_time,
+            offset_nanos,
+            delay: Duration::from_nanos(delay_nanos.max(0) as u64),
+            stratum: response_repr.stratum,
+        });
+    }
+
+    Err(Error::Timeout)
+}
@@ -1,21 +1,27 @@
-use core::service::Interface;
-use core::socket::SocketSet;
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
 use examples::env;
 
 /// Runs a TCP echo server as long as f returns true.
 pub fn tcp_echo<F: FnMut() -> bool>(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    tcp_handle: usize,
+    tcp_handle: SocketHandle,
     mut f: F,
 ) {
-    socket_set.socket(tcp_handle).as_tcp_socket().listen(16, 16);
-
     while f() {
         env::tick(interface, socket_set);
 
-        if let Some(_) = socket_set.socket(tcp_handle).as_tcp_socket().accept() {
-            debug!("Got a connection!");
+        if let Some((_, socket_addr)) = socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .accept()
+        {
+            debug!("Got a connection from {}!", socket_addr);
         }
     }
 }
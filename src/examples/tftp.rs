@@ -0,0 +1,397 @@
+use std::collections::BTreeMap;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use stack::repr::tftp::BLOCK_LEN;
+use stack::repr::{
+    tftp_error_codes,
+    tftp_modes,
+    TftpRepr,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+};
+use examples::env;
+use {
+    BufferError,
+    Error,
+    RemoteError,
+    Result,
+};
+
+/// Retransmission settings for TFTP transfers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TftpConfig {
+    /// How long to wait for a reply before retransmitting.
+    pub timeout: Duration,
+    /// Number of retransmissions before a transfer fails with Error::Timeout.
+    pub retries: usize,
+}
+
+impl Default for TftpConfig {
+    fn default() -> TftpConfig {
+        TftpConfig {
+            timeout: Duration::from_secs(1),
+            retries: 5,
+        }
+    }
+}
+
+/// Reads a file from a server via a UDP socket.
+pub fn tftp_get(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    server_addr: SocketAddr,
+    filename: &str,
+    config: TftpConfig,
+) -> Result<Vec<u8>> {
+    let mut transfer = Transfer::new(udp_handle, server_addr, config, false);
+    let mut request = TftpRepr::ReadRequest {
+        filename: filename.to_string(),
+        mode: tftp_modes::OCTET.to_string(),
+    };
+    let mut file = Vec::new();
+    let mut block: u16 = 1;
+
+    loop {
+        let data = match transfer.exchange(interface, socket_set, &request, |reply| match *reply {
+            TftpRepr::Data { block: reply_block, .. } => reply_block == block,
+            _ => false,
+        })? {
+            TftpRepr::Data { data, .. } => data,
+            _ => unreachable!(),
+        };
+
+        file.extend_from_slice(&data);
+        request = TftpRepr::Ack { block };
+
+        if data.len() < BLOCK_LEN {
+            transfer.send(interface, socket_set, &request)?;
+            transfer.drain(interface, socket_set);
+            return Ok(file);
+        }
+
+        block = block.wrapping_add(1);
+    }
+}
+
+/// Writes a file to a server via a UDP socket.
+pub fn tftp_put(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    server_addr: SocketAddr,
+    filename: &str,
+    file: &[u8],
+    config: TftpConfig,
+) -> Result<()> {
+    let mut transfer = Transfer::new(udp_handle, server_addr, config, false);
+    let request = TftpRepr::WriteRequest {
+        filename: filename.to_string(),
+        mode: tftp_modes::OCTET.to_string(),
+    };
+
+    transfer.exchange(interface, socket_set, &request, |reply| {
+        *reply == TftpRepr::Ack { block: 0 }
+    })?;
+
+    send_blocks(interface, socket_set, &mut transfer, file)
+}
+
+/// Runs a TFTP server serving and storing files in memory as long as f
+/// returns true.
+///
+/// Transfers are served one at a time from the socket the server is bound
+/// to, and requests from other clients during a transfer are rejected.
+pub fn tftp_server<F: FnMut() -> bool>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    files: &mut BTreeMap<String, Vec<u8>>,
+    config: TftpConfig,
+    mut f: F,
+) {
+    // The final ACK of the last write, in case it is lost and the client
+    // retransmits the final DATA.
+    let mut last_ack: Option<(SocketAddr, TftpRepr)> = None;
+
+    while f() {
+        env::tick(interface, socket_set);
+
+        let (request, client_addr) = match recv(socket_set, udp_handle) {
+            Some(received) => received,
+            None => continue,
+        };
+
+        let mut transfer = Transfer::new(udp_handle, client_addr, config, true);
+
+        let res = match request {
+            TftpRepr::ReadRequest { .. } | TftpRepr::WriteRequest { .. }
+                if !is_octet(&request) =>
+            {
+                transfer.send_error(
+                    interface,
+                    socket_set,
+                    tftp_error_codes::NOT_DEFINED,
+                    "Only octet mode is supported",
+                )
+            }
+            TftpRepr::ReadRequest { ref filename, .. } => match files.get(filename) {
+                Some(file) => send_blocks(interface, socket_set, &mut transfer, file),
+                None => transfer.send_error(
+                    interface,
+                    socket_set,
+                    tftp_error_codes::FILE_NOT_FOUND,
+                    "File not found",
+                ),
+            },
+            TftpRepr::WriteRequest { ref filename, .. } => {
+                recv_blocks(interface, socket_set, &mut transfer).map(|(file, ack)| {
+                    files.insert(filename.clone(), file);
+                    last_ack = Some((client_addr, ack));
+                })
+            }
+            TftpRepr::Data { .. } => match last_ack {
+                Some((addr, ref ack)) if addr == client_addr => {
+                    transfer.send(interface, socket_set, ack)
+                }
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        };
+
+        if let Err(err) = res {
+            debug!("TFTP transfer with {} failed with {:?}.", client_addr, err);
+        }
+    }
+}
+
+fn is_octet(request: &TftpRepr) -> bool {
+    match *request {
+        TftpRepr::ReadRequest { ref mode, .. } | TftpRepr::WriteRequest { ref mode, .. } => {
+            mode.eq_ignore_ascii_case(tftp_modes::OCTET)
+        }
+        _ => false,
+    }
+}
+
+/// Sends a file as DATA messages, each acknowledged before the next is sent.
+fn send_blocks(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    transfer: &mut Transfer,
+    file: &[u8],
+) -> Result<()> {
+    let mut chunks: Vec<&[u8]> = file.chunks(BLOCK_LEN).collect();
+    // A file which fills its last block is terminated by an empty one.
+    match chunks.last() {
+        Some(chunk) if chunk.len() < BLOCK_LEN => {}
+        _ => chunks.push(&[]),
+    }
+
+    for (i, chunk) in chunks.into_iter().enumerate() {
+        let block = (i + 1) as u16;
+        let data = TftpRepr::Data {
+            block,
+            data: chunk.to_vec(),
+        };
+        transfer.exchange(interface, socket_set, &data, |reply| {
+            *reply == TftpRepr::Ack { block }
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Receives a file as DATA messages in reply to a WRQ, returning the file and
+/// the final ACK sent.
+fn recv_blocks(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    transfer: &mut Transfer,
+) -> Result<(Vec<u8>, TftpRepr)> {
+    let mut ack = TftpRepr::Ack { block: 0 };
+    let mut file = Vec::new();
+    let mut block: u16 = 1;
+
+    loop {
+        let data = match transfer.exchange(interface, socket_set, &ack, |reply| match *reply {
+            TftpRepr::Data { block: reply_block, .. } => reply_block == block,
+            _ => false,
+        })? {
+            TftpRepr::Data { data, .. } => data,
+            _ => unreachable!(),
+        };
+
+        file.extend_from_slice(&data);
+        ack = TftpRepr::Ack { block };
+
+        if data.len() < BLOCK_LEN {
+            transfer.send(interface, socket_set, &ack)?;
+            transfer.drain(interface, socket_set);
+            return Ok((file, ack));
+        }
+
+        block = block.wrapping_add(1);
+    }
+}
+
+/// Receives and parses the next TFTP message, ignoring malformed ones.
+fn recv(socket_set: &mut SocketSet, udp_handle: SocketHandle) -> Option<(TftpRepr, SocketAddr)> {
+    let udp_socket = socket_set.socket(udp_handle).unwrap().as_udp_socket();
+
+    while let Ok((payload, addr)) = udp_socket.recv() {
+        match TftpRepr::deserialize(payload) {
+            Ok(repr) => return Some((repr, addr)),
+            Err(err) => debug!("Ignoring TFTP message from {} with {:?}.", addr, err),
+        }
+    }
+
+    None
+}
+
+/// The lock-step exchange of messages with a peer during a transfer.
+struct Transfer {
+    udp_handle: SocketHandle,
+    /// Address the first message is sent to, i.e. the server's well known
+    /// port for a client.
+    addr: SocketAddr,
+    /// Address of the peer once known, i.e. its transfer identifier (TID).
+    peer_addr: Option<SocketAddr>,
+    config: TftpConfig,
+}
+
+impl Transfer {
+    fn new(
+        udp_handle: SocketHandle,
+        addr: SocketAddr,
+        config: TftpConfig,
+        is_server: bool,
+    ) -> Transfer {
+        Transfer {
+            udp_handle,
+            addr,
+            // A server replies from a port of its choosing, which the client
+            // learns from the first reply.
+            peer_addr: if is_server { Some(addr) } else { None },
+            config,
+        }
+    }
+
+    /// Sends a message and waits for a reply accepted by f, retransmitting
+    /// the message if no reply is received in time.
+    ///
+    /// Fails with Error::Timeout once out of retries, and with
+    /// RemoteError::Tftp if the peer replies with an ERROR.
+    fn exchange<F>(
+        &mut self,
+        interface: &mut Interface,
+        socket_set: &mut SocketSet,
+        repr: &TftpRepr,
+        mut f: F,
+    ) -> Result<TftpRepr>
+    where
+        F: FnMut(&TftpRepr) -> bool,
+    {
+        for _ in 0 .. self.config.retries + 1 {
+            self.send(interface, socket_set, repr)?;
+
+            let send_at = Instant::now();
+            while Instant::now().duration_since(send_at) < self.config.timeout {
+                env::tick(interface, socket_set);
+
+                while let Some((reply, addr)) = recv(socket_set, self.udp_handle) {
+                    match self.peer_addr {
+                        Some(peer_addr) if peer_addr != addr => {
+                            let error = TftpRepr::Error {
+                                code: tftp_error_codes::UNKNOWN_TID,
+                                message: "Unknown transfer ID".to_string(),
+                            };
+                            send_to(interface, socket_set, self.udp_handle, &error, addr)?;
+                            continue;
+                        }
+                        None if addr.addr != self.addr.addr => continue,
+                        _ => {}
+                    }
+
+                    if let TftpRepr::Error { code, .. } = reply {
+                        return Err(Error::Remote(RemoteError::Tftp(code)));
+                    } else if f(&reply) {
+                        self.peer_addr = Some(addr);
+                        return Ok(reply);
+                    }
+                }
+            }
+        }
+
+        Err(Error::Timeout)
+    }
+
+    /// Sends a message to the peer without waiting for a reply.
+    fn send(
+        &self,
+        interface: &mut Interface,
+        socket_set: &mut SocketSet,
+        repr: &TftpRepr,
+    ) -> Result<()> {
+        let addr = self.peer_addr.unwrap_or(self.addr);
+        send_to(interface, socket_set, self.udp_handle, repr, addr)
+    }
+
+    /// Terminates the transfer with an ERROR message.
+    fn send_error(
+        &self,
+        interface: &mut Interface,
+        socket_set: &mut SocketSet,
+        code: u16,
+        message: &str,
+    ) -> Result<()> {
+        let error = TftpRepr::Error {
+            code,
+            message: message.to_string(),
+        };
+        self.send(interface, socket_set, &error)?;
+        self.drain(interface, socket_set);
+        Ok(())
+    }
+
+    /// Ticks until all messages sent are on the link.
+    fn drain(&self, interface: &mut Interface, socket_set: &mut SocketSet) {
+        while socket_set
+            .socket(self.udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send_enqueued() > 0
+        {
+            env::tick(interface, socket_set);
+        }
+    }
+}
+
+fn send_to(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    udp_handle: SocketHandle,
+    repr: &TftpRepr,
+    addr: SocketAddr,
+) -> Result<()> {
+    loop {
+        // Socket may have a full send buffer!
+        match socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(repr.buffer_len(), addr)
+        {
+            Ok(buffer) => return repr.serialize(buffer),
+            Err(Error::Buffer(BufferError::Full)) => env::tick(interface, socket_set),
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -3,9 +3,14 @@ use std::time::{
     Instant,
 };
 
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
 use rand;
 
-use core::repr::{
+use stack::repr::{
+    ipv4_flags,
     ipv4_protocols,
     Icmpv4DestinationUnreachable,
     Icmpv4Message,
@@ -13,24 +18,70 @@ use core::repr::{
     Icmpv4Repr,
     Icmpv4TimeExceeded,
     Ipv4Address,
+    Ipv4Options,
     Ipv4Packet,
     Ipv4Protocol,
     Ipv4Repr,
+    PacketBuilder,
+    TcpPacket,
+    TcpRepr,
     UdpPacket,
-    UdpRepr,
 };
-use core::service::Interface;
-use core::socket::{
-    SocketAddr,
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
     SocketSet,
 };
 use examples::env;
-use Error;
+use {
+    Error,
+    ParseError,
+    Result,
+};
 
 const PORT_MIN: u16 = 33434;
 
 const PORT_MAX: u16 = 33534;
 
+/// The kind of packet sent to probe each hop.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceMode {
+    /// UDP packets to an unused port, which the destination answers with an
+    /// ICMP Port Unreachable.
+    Udp,
+    /// ICMP echo requests, which the destination answers with echo replies.
+    Icmp,
+    /// TCP SYNs to a port, which the destination answers with a SYN-ACK if the
+    /// port is open or a RST otherwise.
+    TcpSyn(u16),
+}
+
+/// Settings for a traceroute.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceConfig {
+    pub mode: TraceMode,
+    /// Number of probes sent per hop.
+    pub probes: usize,
+    /// Payload size in bytes for each UDP or ICMP probe. TCP SYNs carry no
+    /// payload.
+    pub payload_len: usize,
+    pub max_ttl: u8,
+    /// How long to wait for a response to each probe.
+    pub timeout: Duration,
+}
+
+impl Default for TraceConfig {
+    fn default() -> TraceConfig {
+        TraceConfig {
+            mode: TraceMode::Udp,
+            probes: 3,
+            payload_len: 64,
+            max_ttl: 64,
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
 /// Performs a traceroute via UDP packets.
 ///
 /// Up until the max TTL is reached (starting at 1) or we receive a reply from
@@ -40,10 +91,11 @@ const PORT_MAX: u16 = 33534;
 ///
 /// 2. Wait for an ICMP Time Exceeded or Destination Unreachable response until
 ///    the specified timeout.
+#[allow(clippy::too_many_arguments)]
 pub fn traceroute<F>(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    raw_handle: usize,
+    raw_handle: SocketHandle,
     addr: Ipv4Address,
     payload_len: usize,
     max_ttl: u8,
@@ -53,90 +105,238 @@ pub fn traceroute<F>(
 where
     F: FnMut(u8, Option<(Duration, Ipv4Address)>),
 {
-    // Send UDP packet to a random port.
-    let port = PORT_MIN + rand::random::<u16>() % (PORT_MAX - PORT_MIN + 1);
-    let socket_addr = SocketAddr { addr, port };
-
-    for ttl in 1 .. (max_ttl + 1) {
-        send(
-            interface,
-            socket_set,
-            raw_handle,
-            socket_addr,
-            payload_len,
-            ttl,
-        );
-        let response = recv(interface, socket_set, raw_handle, socket_addr, timeout);
-        f(ttl, response);
-        if let Some((_, endpoint)) = response {
-            if endpoint == addr {
-                return Some(());
-            }
+    let config = TraceConfig {
+        mode: TraceMode::Udp,
+        probes: 1,
+        payload_len,
+        max_ttl,
+        timeout,
+    };
+
+    traceroute_with(
+        interface,
+        socket_set,
+        raw_handle,
+        addr,
+        &config,
+        |ttl, hops| f(ttl, hops[0]),
+    )
+}
+
+/// Performs a traceroute with config.probes probes of a kind per hop, calling
+/// f with the responses to the probes for each hop.
+///
+/// The raw socket must receive ICMP packets, and TCP packets when probing with
+/// TCP SYNs.
+pub fn traceroute_with<F>(
+    interface: &mut Interface,
+    socket_set: &mut SocketSet,
+    raw_handle: SocketHandle,
+    addr: Ipv4Address,
+    config: &TraceConfig,
+    mut f: F,
+) -> Option<()>
+where
+    F: FnMut(u8, &[Option<(Duration, Ipv4Address)>]),
+{
+    // Identifies the probes of this traceroute, as the UDP port, ICMP echo
+    // identifier or TCP source port.
+    let id = rand::random::<u16>();
+    let mut seq: u16 = 0;
+
+    for ttl in 1 .. (config.max_ttl + 1) {
+        let mut hops = Vec::with_capacity(config.probes);
+
+        for _ in 0 .. config.probes {
+            let probe = Probe::new(config.mode, addr, id, seq);
+            seq = seq.wrapping_add(1);
+
+            send(
+                interface,
+                socket_set,
+                raw_handle,
+                &probe,
+                config.payload_len,
+                ttl,
+            );
+            hops.push(recv(
+                interface,
+                socket_set,
+                raw_handle,
+                &probe,
+                config.timeout,
+            ));
+        }
+
+        f(ttl, &hops);
+
+        let reached = hops.iter().any(|hop| match *hop {
+            Some((_, endpoint)) => endpoint == addr,
+            None => false,
+        });
+        if reached {
+            return Some(());
         }
     }
 
     None
 }
 
-/// Sends a UDP packet to the specified (addr, port).
+/// A single probe, identified by the fields echoed in responses to it.
+struct Probe {
+    mode: TraceMode,
+    addr: Ipv4Address,
+    /// UDP or TCP source port, or ICMP echo identifier.
+    src_port: u16,
+    /// UDP or TCP destination port, or ICMP echo sequence number.
+    dst_port: u16,
+    /// TCP sequence number.
+    seq_num: u32,
+}
+
+impl Probe {
+    fn new(mode: TraceMode, addr: Ipv4Address, id: u16, seq: u16) -> Probe {
+        // Ports change with each probe so a probe is not mistaken for part of
+        // the connection a previous TCP probe started.
+        let port = PORT_MIN + id.wrapping_add(seq) % (PORT_MAX - PORT_MIN + 1);
+        let (src_port, dst_port) = match mode {
+            TraceMode::Udp => (port, port),
+            TraceMode::Icmp => (id, seq),
+            TraceMode::TcpSyn(dst_port) => (port, dst_port),
+        };
+
+        Probe {
+            mode,
+            addr,
+            src_port,
+            dst_port,
+            seq_num: seq as u32,
+        }
+    }
+
+    fn protocol(&self) -> Ipv4Protocol {
+        match self.mode {
+            TraceMode::Udp => Ipv4Protocol::UDP,
+            TraceMode::Icmp => Ipv4Protocol::ICMP,
+            TraceMode::TcpSyn(_) => Ipv4Protocol::TCP,
+        }
+    }
+
+    /// Checks if the start of a packet, as embedded in an ICMP error, is this
+    /// probe.
+    fn is_embedded(&self, ipv4_packet: &Ipv4Packet<&[u8]>) -> Result<bool> {
+        // So I'm not 100% sure about this, but let's check the (1) destination address
+        // and (2) transport protocol only since source address, checksum, etc. can get
+        // modified by a NAT.
+        if ipv4_packet.dst_addr() != self.addr || ipv4_packet.protocol() != self.protocol() as u8 {
+            return Ok(false);
+        }
+
+        // We only have a portion of the original IP packet, so let's be careful parsing
+        // the payload...
+        let ip_header_len = (ipv4_packet.header_len() * 4) as usize;
+        let ip_payload = &ipv4_packet.as_ref()[ip_header_len ..];
+
+        match self.mode {
+            // Likewise, let's inspect the destination port only since the source port might
+            // have gotten modified by a NAT.
+            TraceMode::Udp => {
+                Ok(UdpPacket::try_new(ip_payload)?.dst_port() == self.dst_port)
+            }
+            TraceMode::Icmp => {
+                let icmp_packet = Icmpv4Packet::try_new(ip_payload)?;
+                match Icmpv4Repr::deserialize(&icmp_packet)?.message {
+                    Icmpv4Message::EchoRequest { id, seq } => {
+                        Ok(id == self.src_port && seq == self.dst_port)
+                    }
+                    _ => Ok(false),
+                }
+            }
+            // Only the ports and sequence number are guaranteed to be quoted.
+            TraceMode::TcpSyn(_) => {
+                if ip_payload.len() < 8 {
+                    return Err(Error::Parse(ParseError::Truncated));
+                }
+                Ok(NetworkEndian::read_u16(&ip_payload[2 .. 4]) == self.dst_port
+                    && NetworkEndian::read_u32(&ip_payload[4 .. 8]) == self.seq_num)
+            }
+        }
+    }
+}
+
+/// Sends a probe with the specified TTL.
 ///
-/// The UDP will be enqueued on a socket, not necessarily forwarded onto the
+/// The probe will be enqueued on a socket, not necessarily forwarded onto the
 /// link.
 fn send(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    raw_handle: usize,
-    socket_addr: SocketAddr,
+    raw_handle: SocketHandle,
+    probe: &Probe,
     payload_len: usize,
     ttl: u8,
 ) {
-    // Assuming 5 word/20 byte IP header!
-    let udp_repr = UdpRepr {
-        src_port: socket_addr.port,
-        dst_port: socket_addr.port,
-        length: (8 + payload_len) as u16,
-    };
+    let payload: Vec<u8> = (0 .. payload_len).map(|_| rand::random::<u8>()).collect();
 
     let ipv4_repr = Ipv4Repr {
         src_addr: *interface.ipv4_addr,
-        dst_addr: socket_addr.addr,
-        protocol: Ipv4Protocol::UDP,
-        payload_len: udp_repr.buffer_len() as u16,
+        dst_addr: probe.addr,
+        protocol: probe.protocol(),
+        payload_len: 0,
+        ttl,
+        dscp: 0,
+        flags: ipv4_flags::DONT_FRAGMENT,
+        options: Ipv4Options::new(),
+    };
+
+    let builder = PacketBuilder::new().ipv4(ipv4_repr);
+    let builder = match probe.mode {
+        TraceMode::Udp => builder
+            .udp(probe.src_port, probe.dst_port)
+            .payload(&payload),
+        TraceMode::Icmp => builder
+            .icmpv4(Icmpv4Message::EchoRequest {
+                id: probe.src_port,
+                seq: probe.dst_port,
+            })
+            .payload(&payload),
+        TraceMode::TcpSyn(_) => {
+            let mut flags = [false; 9];
+            flags[TcpRepr::FLAG_SYN] = true;
+            builder.tcp(TcpRepr {
+                src_port: probe.src_port,
+                dst_port: probe.dst_port,
+                seq_num: probe.seq_num,
+                ack_num: 0,
+                flags,
+                window_size: 1024,
+                urgent_pointer: 0,
+                max_segment_size: None,
+            })
+        }
     };
 
     // Socket may have a full send buffer!
-    while let Err(_) = socket_set
+    while socket_set
         .socket(raw_handle)
+        .unwrap()
         .as_raw_socket()
-        .send(ipv4_repr.buffer_len())
-        .map(|ip_buffer| {
-            let mut ipv4_packet = Ipv4Packet::try_new(ip_buffer).unwrap();
-            ipv4_repr.serialize(&mut ipv4_packet);
-
-            // We need to update the checksum manually if we set a custom TTL,
-            // or any header field.
-            ipv4_packet.set_ttl(ttl as u8);
-            ipv4_packet.set_header_checksum(0);
-            let checksum = ipv4_packet.gen_header_checksum();
-            ipv4_packet.set_header_checksum(checksum);
-
-            let mut udp_packet = UdpPacket::try_new(ipv4_packet.payload_mut()).unwrap();
-            for i in 0 .. payload_len {
-                udp_packet.payload_mut()[i] = rand::random::<u8>();
-            }
-            udp_repr.serialize(&mut udp_packet, &ipv4_repr);
-        }) {
+        .send(builder.buffer_len())
+        .map(|ip_buffer| builder.build(ip_buffer).unwrap())
+        .is_err()
+    {
         env::tick(interface, socket_set);
     }
 }
 
-/// Waits for a Time Exceeded or Destination Unreachable ICMP error in response
-/// to a UDP packet up until the specified timeout.
+/// Waits for a response to a probe up until the specified timeout, i.e. an
+/// ICMP Time Exceeded from a router or the final response from the
+/// destination for the kind of probe.
 fn recv(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    raw_handle: usize,
-    socket_addr: SocketAddr,
+    raw_handle: SocketHandle,
+    probe: &Probe,
     timeout: Duration,
 ) -> Option<(Duration, Ipv4Address)> {
     let wait_at = Instant::now();
@@ -148,58 +348,19 @@ fn recv(
             return None;
         } else if let Ok(response_addr) = socket_set
             .socket(raw_handle)
+            .unwrap()
             .as_raw_socket()
             .recv()
             .and_then(|ip_buffer| {
                 let ipv4_packet = Ipv4Packet::try_new(ip_buffer)?;
-                if ipv4_packet.protocol() != ipv4_protocols::ICMP
-                    || ipv4_packet.dst_addr() != *interface.ipv4_addr
-                {
+                if ipv4_packet.dst_addr() != *interface.ipv4_addr {
                     return Err(Error::Ignored);
                 }
 
-                let response_addr = ipv4_packet.src_addr();
-
-                // We care only about two cases of ICMP messages:
-                //
-                // 1. Destination Unreachable => If the UDP packet reached the final host.
-                // 2. Time Exceeded           => If the UDP packet was dropped by a router.
-                let icmp_packet = Icmpv4Packet::try_new(ipv4_packet.payload())?;
-                icmp_packet.check_encoding()?;
-                let icmp_repr = Icmpv4Repr::deserialize(&icmp_packet)?;
-                let ipv4_packet = match icmp_repr.message {
-                    Icmpv4Message::DestinationUnreachable(
-                        Icmpv4DestinationUnreachable::PortUnreachable,
-                    ) => Ipv4Packet::try_new(icmp_packet.payload())?,
-                    Icmpv4Message::TimeExceeded(Icmpv4TimeExceeded::TTLExpired) => {
-                        Ipv4Packet::try_new(icmp_packet.payload())?
-                    }
-                    _ => {
-                        return Err(Error::Ignored);
-                    }
-                };
-
-                // So I'm not 100% sure about this, but let's check the (1) destination address
-                // and (2) transport protocol only since source address, checksum, etc. can get
-                // modified by a NAT.
-                if ipv4_packet.dst_addr() != socket_addr.addr
-                    || ipv4_packet.protocol() != ipv4_protocols::UDP
-                {
-                    return Err(Error::Ignored);
-                }
-
-                // We only have a portion of the original IP packet, so let's be careful parsing
-                // the payload...
-                let ip_header_len = (ipv4_packet.header_len() * 4) as usize;
-                let ip_payload = &ipv4_packet.as_ref()[ip_header_len ..];
-                let udp_packet = UdpPacket::try_new(ip_payload)?;
-
-                // Likewise, let's inspect the destination port only since the source port might
-                // have gotten modified by a NAT.
-                if udp_packet.dst_port() != socket_addr.port {
-                    Err(Error::Ignored)
+                if is_response(probe, &ipv4_packet)? {
+                    Ok(ipv4_packet.src_addr())
                 } else {
-                    Ok(response_addr)
+                    Err(Error::Ignored)
                 }
             }) {
             return Some((waiting, response_addr));
@@ -208,3 +369,42 @@ fn recv(
         env::tick(interface, socket_set);
     }
 }
+
+/// Checks if a packet received is a response to a probe.
+fn is_response(probe: &Probe, ipv4_packet: &Ipv4Packet<&[u8]>) -> Result<bool> {
+    match (ipv4_packet.protocol(), probe.mode) {
+        (ipv4_protocols::ICMP, _) => {}
+        // The destination answers a SYN with a SYN-ACK or RST, acknowledging
+        // the SYN.
+        (ipv4_protocols::TCP, TraceMode::TcpSyn(_)) => {
+            let tcp_packet = TcpPacket::try_new(ipv4_packet.payload())?;
+            return Ok(ipv4_packet.src_addr() == probe.addr
+                && tcp_packet.src_port() == probe.dst_port
+                && tcp_packet.dst_port() == probe.src_port
+                && (tcp_packet.syn() || tcp_packet.rst())
+                && tcp_packet.ack_num() == probe.seq_num.wrapping_add(1));
+        }
+        _ => return Ok(false),
+    }
+
+    let icmp_packet = Icmpv4Packet::try_new(ipv4_packet.payload())?;
+    icmp_packet.check_encoding()?;
+    let icmp_repr = Icmpv4Repr::deserialize(&icmp_packet)?;
+
+    // We care only about three cases of ICMP messages:
+    //
+    // 1. Time Exceeded           => If the probe was dropped by a router.
+    // 2. Destination Unreachable => If a UDP probe reached the final host.
+    // 3. Echo Reply              => If an ICMP probe reached the final host.
+    match (icmp_repr.message, probe.mode) {
+        (Icmpv4Message::TimeExceeded(Icmpv4TimeExceeded::TTLExpired), _)
+        | (
+            Icmpv4Message::DestinationUnreachable(Icmpv4DestinationUnreachable::PortUnreachable),
+            TraceMode::Udp,
+        ) => probe.is_embedded(&Ipv4Packet::try_new(icmp_packet.payload())?),
+        (Icmpv4Message::EchoReply { id, seq }, TraceMode::Icmp) => {
+            Ok(ipv4_packet.src_addr() == probe.addr && id == probe.src_port && seq == probe.dst_port)
+        }
+        _ => Ok(false),
+    }
+}
@@ -1,12 +1,15 @@
-use core::service::Interface;
-use core::socket::SocketSet;
+use stack::service::Interface;
+use stack::socket::{
+    SocketHandle,
+    SocketSet,
+};
 use examples::env;
 
 /// Runs a UDP echo server as long as f returns true.
 pub fn udp_echo<F: FnMut() -> bool>(
     interface: &mut Interface,
     socket_set: &mut SocketSet,
-    udp_handle: usize,
+    udp_handle: SocketHandle,
     mut f: F,
 ) {
     let mut buf = vec![];
@@ -14,7 +17,12 @@ pub fn udp_echo<F: FnMut() -> bool>(
     while f() {
         env::tick(interface, socket_set);
 
-        let addr = match socket_set.socket(udp_handle).as_udp_socket().recv() {
+        let addr = match socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .recv()
+        {
             Ok((payload, addr)) => {
                 buf.resize(payload.len(), 0);
                 buf.copy_from_slice(payload);
@@ -26,6 +34,7 @@ pub fn udp_echo<F: FnMut() -> bool>(
         // Write response, socket may have a full send buffer!
         while let Err(_) = socket_set
             .socket(udp_handle)
+            .unwrap()
             .as_udp_socket()
             .send(buf.len(), addr)
             .map(|buffer| buffer.copy_from_slice(&buf))
@@ -37,6 +46,7 @@ pub fn udp_echo<F: FnMut() -> bool>(
     // Now drain to ensure UDP responses make it onto the link.
     while socket_set
         .socket(udp_handle)
+        .unwrap()
         .as_udp_socket()
         .send_enqueued() > 0
     {
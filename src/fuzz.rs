@@ -0,0 +1,259 @@
+//! Entry points for fuzzing the packet parsers, see the fuzz directory.
+//!
+//! Each function parses a buffer as a packet of some type and calls every
+//! getter, deserializer and formatter on it, none of which should panic no
+//! matter how malformed the buffer is.
+
+use alloc::string::ToString;
+
+use stack::capture::describe_frame;
+use stack::repr::tcp::TcpOptionIter;
+use stack::repr::{
+    Arp,
+    EthernetFrame,
+    Icmpv4Packet,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    Ipv4Options,
+    Ipv6Packet,
+    Ipv6Repr,
+    PppoeDiscoveryRepr,
+    PppoePacket,
+    PppoeSessionRepr,
+    TcpPacket,
+    TcpRepr,
+    UdpPacket,
+    UdpRepr,
+};
+
+/// Parses an Ethernet frame, including any ARP message or IPv4 packet it
+/// carries.
+pub fn ethernet_frame(data: &[u8]) {
+    let _ = describe_frame(data);
+
+    if let Ok(eth_frame) = EthernetFrame::try_new(data) {
+        let _ = eth_frame.dst_addr();
+        let _ = eth_frame.src_addr();
+        let _ = eth_frame.payload_type();
+        let _ = eth_frame.payload();
+        let _ = eth_frame.to_string();
+    }
+}
+
+/// Parses an ARP message.
+pub fn arp(data: &[u8]) {
+    if let Ok(arp) = Arp::deserialize(data) {
+        let _ = arp.to_string();
+    }
+}
+
+/// Parses an IPv4 packet, without checking its encoding first.
+pub fn ipv4_packet(data: &[u8]) {
+    let ipv4_packet = match Ipv4Packet::try_new(data) {
+        Ok(ipv4_packet) => ipv4_packet,
+        Err(_) => return,
+    };
+
+    let _ = ipv4_packet.check_encoding();
+    let _ = ipv4_packet.gen_header_checksum();
+    let _ = ipv4_packet.ip_version();
+    let _ = ipv4_packet.dscp();
+    let _ = ipv4_packet.ecn();
+    let _ = ipv4_packet.identification();
+    let _ = ipv4_packet.fragment_offset();
+    let _ = ipv4_packet.header_checksum();
+    let _ = ipv4_packet.payload();
+    let _ = ipv4_packet.to_string();
+
+    for option in Ipv4Options::try_new(ipv4_packet.options()).iter().flat_map(|options| options.iter()) {
+        let _ = option;
+    }
+
+    if let Ok(ipv4_repr) = Ipv4Repr::deserialize(&ipv4_packet) {
+        let _ = ipv4_repr.gen_checksum_with_pseudo_header(ipv4_packet.payload());
+    }
+}
+
+/// Parses an IPv6 packet and its extension headers, without checking its
+/// encoding first.
+pub fn ipv6_packet(data: &[u8]) {
+    let ipv6_packet = match Ipv6Packet::try_new(data) {
+        Ok(ipv6_packet) => ipv6_packet,
+        Err(_) => return,
+    };
+
+    let _ = ipv6_packet.check_encoding();
+    let _ = ipv6_packet.payload();
+    let _ = ipv6_packet.ext_headers().count();
+    let _ = ipv6_packet.upper_layer();
+    let _ = Ipv6Repr::deserialize(&ipv6_packet);
+}
+
+/// Parses a PPPoE packet as both a discovery and session packet, without
+/// checking its encoding first.
+pub fn pppoe_packet(data: &[u8]) {
+    let pppoe_packet = match PppoePacket::try_new(data) {
+        Ok(pppoe_packet) => pppoe_packet,
+        Err(_) => return,
+    };
+
+    let _ = pppoe_packet.check_encoding();
+    let _ = pppoe_packet.payload();
+    let _ = pppoe_packet.ppp_payload();
+    let _ = pppoe_packet.tags().count();
+    let _ = PppoeDiscoveryRepr::deserialize(&pppoe_packet);
+    let _ = PppoeSessionRepr::deserialize(&pppoe_packet);
+}
+
+/// Parses an ICMPv4 packet, without checking its encoding first.
+pub fn icmpv4_packet(data: &[u8]) {
+    let icmp_packet = match Icmpv4Packet::try_new(data) {
+        Ok(icmp_packet) => icmp_packet,
+        Err(_) => return,
+    };
+
+    let _ = icmp_packet.check_encoding();
+    let _ = icmp_packet.checksum();
+    let _ = icmp_packet.payload();
+    let _ = icmp_packet.to_string();
+    let _ = Icmpv4Repr::deserialize(&icmp_packet);
+}
+
+/// Parses a UDP packet, without checking its encoding first.
+pub fn udp_packet(data: &[u8]) {
+    let udp_packet = match UdpPacket::try_new(data) {
+        Ok(udp_packet) => udp_packet,
+        Err(_) => return,
+    };
+
+    let _ = udp_packet.check_encoding(&ipv4_repr(Ipv4Protocol::UDP, data.len()));
+    let _ = udp_packet.payload();
+    let _ = udp_packet.to_string();
+    let _ = UdpRepr::deserialize(&udp_packet);
+}
+
+/// Parses a TCP segment, without checking its encoding first.
+pub fn tcp_packet(data: &[u8]) {
+    let tcp_packet = match TcpPacket::try_new(data) {
+        Ok(tcp_packet) => tcp_packet,
+        Err(_) => return,
+    };
+
+    let _ = tcp_packet.check_encoding(&ipv4_repr(Ipv4Protocol::TCP, data.len()));
+    let _ = tcp_packet.checksum();
+    let _ = tcp_packet.payload();
+    let _ = tcp_packet.to_string();
+    let _ = TcpRepr::deserialize(&tcp_packet);
+    tcp_options(tcp_packet.options());
+}
+
+/// Parses TCP options.
+pub fn tcp_options(data: &[u8]) {
+    // Options are at least a byte long, so the iterator must end by then.
+    assert!(TcpOptionIter::new(data).take(data.len() + 1).count() <= data.len());
+}
+
+/// Returns an IPv4 header to checksum transport packets with.
+fn ipv4_repr(protocol: Ipv4Protocol, payload_len: usize) -> Ipv4Repr {
+    Ipv4Repr {
+        src_addr: Ipv4Address::new([10, 0, 0, 1]),
+        dst_addr: Ipv4Address::new([10, 0, 0, 2]),
+        protocol,
+        payload_len: payload_len as u16,
+        ttl: Ipv4Repr::DEFAULT_TTL,
+        dscp: 0,
+        flags: 0,
+        options: Ipv4Options::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{
+        Rng,
+        SeedableRng,
+        XorShiftRng,
+    };
+
+    use super::*;
+
+    const ENTRY_POINTS: [fn(&[u8]); 9] = [
+        ethernet_frame,
+        arp,
+        ipv4_packet,
+        ipv6_packet,
+        pppoe_packet,
+        icmpv4_packet,
+        udp_packet,
+        tcp_packet,
+        tcp_options,
+    ];
+
+    #[test]
+    fn test_random_buffers() {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        for _ in 0 .. 2000 {
+            let len = rng.gen_range(0, 128);
+            let data: Vec<u8> = rng.gen_iter().take(len).collect();
+            for entry_point in ENTRY_POINTS.iter() {
+                entry_point(&data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ipv4_header_len_exceeds_buffer() {
+        let mut data = [0; 20];
+        data[0] = 0x4F;
+        ipv4_packet(&data);
+
+        let packet = Ipv4Packet::try_new(&data[..]).unwrap();
+        assert!(packet.options().is_empty());
+        assert!(packet.payload().is_empty());
+    }
+
+    #[test]
+    fn test_ipv4_packet_len_less_than_header_len() {
+        let mut data = [0; 28];
+        data[0] = 0x46;
+        data[3] = 20;
+        ipv4_packet(&data);
+
+        let packet = Ipv4Packet::try_new(&data[..]).unwrap();
+        assert_eq!(packet.options().len(), 4);
+        assert!(packet.payload().is_empty());
+    }
+
+    #[test]
+    fn test_ipv6_payload_len_exceeds_buffer() {
+        let mut data = [0; 48];
+        data[0] = 0x60;
+        data[4] = 0xFF;
+        ipv6_packet(&data);
+
+        let packet = Ipv6Packet::try_new(&data[..]).unwrap();
+        assert_eq!(packet.payload().len(), 8);
+    }
+
+    #[test]
+    fn test_tcp_data_offset_out_of_range() {
+        for &data_offset in [0x00, 0xF0].iter() {
+            let mut data = [0; 24];
+            data[12] = data_offset;
+            tcp_packet(&data);
+
+            let packet = TcpPacket::try_new(&data[..]).unwrap();
+            assert!(packet.options().len() <= 4);
+        }
+    }
+
+    #[test]
+    fn test_tcp_option_len_too_short() {
+        tcp_options(&[2, 0, 1, 1]);
+        tcp_options(&[2, 1]);
+    }
+}
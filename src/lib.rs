@@ -1,47 +1,49 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[macro_use]
+extern crate alloc;
 #[cfg(test)]
 #[macro_use]
 extern crate assert_matches;
 extern crate byteorder;
+#[cfg(feature = "std")]
+extern crate core;
+#[cfg(feature = "std")]
 extern crate get_if_addrs;
+#[cfg(feature = "std")]
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "std")]
 extern crate libc;
 #[macro_use]
 extern crate log;
 extern crate rand;
 
-pub mod core;
+pub mod stack;
+pub mod error;
+#[doc(hidden)]
+pub mod fuzz;
+#[cfg(feature = "std")]
 pub mod examples;
+#[cfg(feature = "std")]
+pub mod netapi;
+#[cfg(feature = "std")]
+pub mod reactor;
+pub mod testing;
 
-#[cfg(target_os = "linux")]
+#[cfg(all(feature = "std", target_os = "linux"))]
 pub mod linux;
 
-use std::io::Error as IOError;
-use std::result::Result as StdResult;
-
-use core::repr::Ipv4Address;
-use core::socket::SocketAddr;
-
-#[derive(Debug)]
-pub enum Error {
-    /// Indicates an error where a MAC address could not be resolved for an IPV4
-    /// address.
-    MacResolution(Ipv4Address),
-    /// Indicates an error where a socket binding has already been assigned.
-    BindingInUse(SocketAddr),
-    /// Indicates an error where a socket buffer is full or empty, depending on
-    /// the operation being performed.
-    Exhausted,
-    /// Indicates an error where a an incoming packet was ignored.
-    Ignored,
-    /// Indicates an error with a device/interface. This includes situations
-    /// such as writes to a busy device or attempting reads on a device
-    /// with no Ethernet frames.
-    Device(Option<IOError>),
-    /// Indicates an error where a packet or frame is malformed.
-    Malformed,
-    /// Indicates an error where a checksum is invalid.
-    Checksum,
-}
+#[cfg(all(feature = "std", target_os = "macos"))]
+pub mod macos;
 
-pub type Result<T> = StdResult<T, Error>;
+pub use error::{
+    AddressError,
+    BufferError,
+    DeviceError,
+    Error,
+    ParseError,
+    RemoteError,
+    Result,
+    SocketError,
+};
@@ -1,5 +1,14 @@
+use std::io::Error as IOError;
+use std::time::Duration;
+
 use libc;
 
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
 // https://github.com/torvalds/linux/blob/master/include/uapi/linux/if_tun.h
 pub const IFF_TAP: libc::c_short = 0x0002;
 
@@ -12,6 +21,21 @@ pub const TUNSETIFF: libc::c_ulong = 0x400454CA;
 // https://github.com/torvalds/linux/blob/master/include/uapi/linux/sockios.h
 pub const SIOCGIFMTU: libc::c_ulong = 0x8921;
 
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/sockios.h
+pub const SIOCGIFINDEX: libc::c_ulong = 0x8933;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/if_ether.h
+pub const ETH_P_ALL: u16 = 0x0003;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/if_packet.h
+pub const PACKET_ADD_MEMBERSHIP: libc::c_int = 1;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/if_packet.h
+pub const PACKET_MR_PROMISC: libc::c_ushort = 1;
+
+// https://github.com/torvalds/linux/blob/master/include/uapi/linux/if_packet.h
+pub const PACKET_OUTGOING: libc::c_uchar = 4;
+
 #[repr(C)]
 #[derive(Clone, Copy)]
 // https://linux.die.net/man/7/netdevice
@@ -42,6 +66,17 @@ impl c_ifreq {
 pub union c_ifreq_ifru {
     pub ifr_flags: libc::c_short,
     pub ifr_mtu: libc::c_int,
+    pub ifr_ifindex: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+// https://man7.org/linux/man-pages/man7/packet.7.html
+pub struct c_packet_mreq {
+    pub mr_ifindex: libc::c_int,
+    pub mr_type: libc::c_ushort,
+    pub mr_alen: libc::c_ushort,
+    pub mr_address: [libc::c_uchar; 8],
 }
 
 pub fn errno() -> libc::c_int {
@@ -50,3 +85,32 @@ pub fn errno() -> libc::c_int {
         *errno
     }
 }
+
+/// Blocks until a file descriptor is readable or the timeout elapses, see
+/// [poll(2)](https://man7.org/linux/man-pages/man2/poll.2.html).
+pub fn poll_readable(fd: libc::c_int, timeout: Option<Duration>) -> Result<()> {
+    // Round up so short timeouts do not turn into busy polling.
+    let timeout_ms = match timeout {
+        Some(timeout) => {
+            let millis =
+                timeout.as_secs() * 1000 + u64::from(timeout.subsec_nanos() + 999_999) / 1_000_000;
+            millis.min(libc::c_int::MAX as u64) as libc::c_int
+        }
+        None => -1,
+    };
+
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    unsafe {
+        if libc::poll(&mut pollfd as *mut libc::pollfd, 1, timeout_ms) < 0 && errno() != libc::EINTR
+        {
+            Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -1,4 +1,5 @@
 //! Linux specific features.
 
 pub mod libc;
+pub mod raw_socket;
 pub mod tap;
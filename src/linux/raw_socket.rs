@@ -0,0 +1,251 @@
+use std::io::{
+    Error as IOError,
+    ErrorKind as IOErrorKind,
+};
+use std::mem;
+use std::os::unix::io::{
+    AsRawFd,
+    RawFd,
+};
+use std::time::Duration;
+
+use libc;
+
+use stack::dev::Device;
+use linux::libc as _libc;
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// [AF_PACKET socket](https://man7.org/linux/man-pages/man7/packet.7.html)
+/// bound to an existing interface, such as a physical NIC, for sending and
+/// receiving raw ethernet frames.
+///
+/// Unlike a TAP, frames are shared with the host's own network stack, so
+/// usrnet should use an IPv4 address not assigned to the host.
+pub struct RawSocketDev {
+    sockfd: libc::c_int,
+    max_transmission_unit: usize,
+}
+
+impl RawSocketDev {
+    /// Binds to an existing interface, optionally in promiscuous mode to
+    /// receive frames addressed to other hosts, which requires CAP_NET_RAW.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if creating or binding the socket runs into an error,
+    /// such as the interface not existing, see try_new(...) for a
+    /// non-panicking alternative.
+    pub fn new(ifr_name: &str, promiscuous: bool) -> RawSocketDev {
+        match RawSocketDev::try_new(ifr_name, promiscuous) {
+            Ok(raw_dev) => raw_dev,
+            Err(Error::Device(DeviceError::Io(err))) => {
+                panic!("Opening AF_PACKET socket on {}: {}.", ifr_name, err)
+            }
+            Err(err) => panic!("Opening AF_PACKET socket on {}: {:?}.", ifr_name, err),
+        }
+    }
+
+    /// Binds to an existing interface like new(...), returning any IO error
+    /// encountered while creating, binding or configuring the socket as
+    /// DeviceError::Io(...).
+    pub fn try_new(ifr_name: &str, promiscuous: bool) -> Result<RawSocketDev> {
+        if ifr_name.len() > libc::IF_NAMESIZE {
+            return Err(Error::Device(DeviceError::Io(IOError::new(
+                IOErrorKind::InvalidInput,
+                "Interface name is too long.",
+            ))));
+        }
+
+        unsafe {
+            let ifreq = _libc::c_ifreq::with_name(ifr_name);
+            let protocol = _libc::ETH_P_ALL.to_be();
+
+            // Create the socket...
+            let sockfd = libc::socket(
+                libc::AF_PACKET,
+                libc::SOCK_RAW | libc::SOCK_NONBLOCK,
+                libc::c_int::from(protocol),
+            );
+
+            if sockfd == -1 {
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+            }
+
+            // Closes the socket if anything after this fails...
+            let mut raw_dev = RawSocketDev {
+                sockfd,
+                max_transmission_unit: 0,
+            };
+
+            // Bind it to the interface...
+            let mut _ifreq = ifreq;
+            if libc::ioctl(
+                sockfd,
+                _libc::SIOCGIFINDEX,
+                &mut _ifreq as *mut _libc::c_ifreq,
+            ) == -1
+            {
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+            }
+
+            let ifindex = _ifreq.ifr_ifru.ifr_ifindex;
+
+            let mut sockaddr: libc::sockaddr_ll = mem::zeroed();
+            sockaddr.sll_family = libc::AF_PACKET as libc::c_ushort;
+            sockaddr.sll_protocol = protocol;
+            sockaddr.sll_ifindex = ifindex;
+
+            if libc::bind(
+                sockfd,
+                &sockaddr as *const libc::sockaddr_ll as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+            ) == -1
+            {
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+            }
+
+            if promiscuous {
+                let mreq = _libc::c_packet_mreq {
+                    mr_ifindex: ifindex,
+                    mr_type: _libc::PACKET_MR_PROMISC,
+                    mr_alen: 0,
+                    mr_address: [0; 8],
+                };
+
+                if libc::setsockopt(
+                    sockfd,
+                    libc::SOL_PACKET,
+                    _libc::PACKET_ADD_MEMBERSHIP,
+                    &mreq as *const _libc::c_packet_mreq as *const libc::c_void,
+                    mem::size_of::<_libc::c_packet_mreq>() as libc::socklen_t,
+                ) == -1
+                {
+                    return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+                }
+            }
+
+            // Query the MTU...
+            let mut _ifreq = ifreq;
+            if libc::ioctl(
+                sockfd,
+                _libc::SIOCGIFMTU,
+                &mut _ifreq as *mut _libc::c_ifreq,
+            ) == -1
+            {
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+            }
+
+            raw_dev.max_transmission_unit = _ifreq.ifr_ifru.ifr_mtu as usize;
+
+            // Now we're done!
+            Ok(raw_dev)
+        }
+    }
+}
+
+impl Device for RawSocketDev {
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        unsafe {
+            let wrote = libc::send(
+                self.sockfd,
+                buffer.as_ptr() as *const libc::c_void,
+                buffer.len(),
+                0,
+            );
+
+            if wrote < 0 && _libc::errno() == libc::EAGAIN {
+                Err(Error::Device(DeviceError::WouldBlock))
+            } else if wrote < 0 {
+                Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        loop {
+            unsafe {
+                let mut sockaddr: libc::sockaddr_ll = mem::zeroed();
+                let mut sockaddr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+
+                let read = libc::recvfrom(
+                    self.sockfd,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                    &mut sockaddr as *mut libc::sockaddr_ll as *mut libc::sockaddr,
+                    &mut sockaddr_len,
+                );
+
+                if read < 0 && _libc::errno() == libc::EAGAIN {
+                    return Err(Error::Device(DeviceError::WouldBlock));
+                } else if read < 0 {
+                    return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+                } else if sockaddr.sll_pkttype == _libc::PACKET_OUTGOING {
+                    // Skip frames sent via the interface, including our own.
+                    continue;
+                } else {
+                    return Ok(read as usize);
+                }
+            }
+        }
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        _libc::poll_readable(self.sockfd, timeout)
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.sockfd)
+    }
+
+    fn max_transmission_unit(&self) -> usize {
+        self.max_transmission_unit
+    }
+}
+
+impl AsRawFd for RawSocketDev {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sockfd
+    }
+}
+
+impl Drop for RawSocketDev {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.sockfd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_new_name_too_long() {
+        match RawSocketDev::try_new("eth0123456789abcdef", false) {
+            Err(Error::Device(DeviceError::Io(err))) => {
+                assert_eq!(err.kind(), IOErrorKind::InvalidInput)
+            }
+            Err(err) => panic!("Unexpected error {:?}.", err),
+            Ok(_) => panic!("Opened AF_PACKET socket with invalid name."),
+        }
+    }
+
+    #[test]
+    fn test_try_new_missing_interface() {
+        // Fails with EPERM without CAP_NET_RAW, or ENODEV with it, either way
+        // without leaking the socket.
+        match RawSocketDev::try_new("usrnetmissing0", false) {
+            Err(Error::Device(DeviceError::Io(_))) => {}
+            Err(err) => panic!("Unexpected error {:?}.", err),
+            Ok(_) => panic!("Opened AF_PACKET socket on a missing interface."),
+        }
+    }
+}
@@ -1,10 +1,19 @@
-use std::io::Error as IOError;
+use std::io::{
+    Error as IOError,
+    ErrorKind as IOErrorKind,
+};
+use std::os::unix::io::{
+    AsRawFd,
+    RawFd,
+};
+use std::time::Duration;
 
 use libc;
 
-use core::dev::Device;
+use stack::dev::Device;
 use linux::libc as _libc;
 use {
+    DeviceError,
     Error,
     Result,
 };
@@ -23,8 +32,28 @@ impl Tap {
     /// # Panics
     ///
     /// Causes a panic if [tun_alloc(...)](https://www.kernel.org/doc/Documentation/networking/tuntap.txt)
-    /// runs into an error.
+    /// runs into an error, see try_new(...) for a non-panicking alternative.
     pub fn new(ifr_name: &str) -> Tap {
+        match Tap::try_new(ifr_name) {
+            Ok(tap) => tap,
+            Err(Error::Device(DeviceError::Io(err))) => {
+                panic!("Opening TAP {}: {}.", ifr_name, err)
+            }
+            Err(err) => panic!("Opening TAP {}: {:?}.", ifr_name, err),
+        }
+    }
+
+    /// Creates or binds to an existing TAP interface, returning any IO error
+    /// encountered by [tun_alloc(...)](https://www.kernel.org/doc/Documentation/networking/tuntap.txt)
+    /// or querying the MTU as DeviceError::Io(...).
+    pub fn try_new(ifr_name: &str) -> Result<Tap> {
+        if ifr_name.len() > libc::IF_NAMESIZE {
+            return Err(Error::Device(DeviceError::Io(IOError::new(
+                IOErrorKind::InvalidInput,
+                "Interface name is too long.",
+            ))));
+        }
+
         unsafe {
             let ifreq = _libc::c_ifreq::with_name(ifr_name);
 
@@ -35,43 +64,52 @@ impl Tap {
             );
 
             if tapfd < 0 {
-                panic!("Opening TAP: {}.", IOError::last_os_error());
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
             }
 
+            // Closes the TAP if anything after this fails...
+            let mut tap = Tap {
+                tapfd,
+                max_transmission_unit: 0,
+            };
+
             let mut _ifreq = ifreq.clone();
             _ifreq.ifr_ifru.ifr_flags = _libc::IFF_TAP | _libc::IFF_NO_PI;
             if libc::ioctl(tapfd, _libc::TUNSETIFF, &mut _ifreq as *mut _libc::c_ifreq) == -1 {
-                panic!("TUNSETIFF TAP: {}.", IOError::last_os_error());
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
             }
 
-            // Query the MTU...
-            let sockfd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+            tap.max_transmission_unit = query_mtu(&ifreq)?;
 
-            if sockfd == -1 {
-                panic!("Opening socket: {}.", IOError::last_os_error());
-            }
-
-            let mut _ifreq = ifreq.clone();
-
-            if libc::ioctl(
-                sockfd,
-                _libc::SIOCGIFMTU,
-                &mut _ifreq as *mut _libc::c_ifreq,
-            ) == -1
-            {
-                panic!("IOCTL socket: {}.", IOError::last_os_error());
-            }
+            // Now we're done!
+            Ok(tap)
+        }
+    }
+}
 
-            libc::close(sockfd);
+/// Queries the MTU of an interface via an ioctl(...) on a throwaway socket.
+unsafe fn query_mtu(ifreq: &_libc::c_ifreq) -> Result<usize> {
+    let sockfd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
 
-            let max_transmission_unit = _ifreq.ifr_ifru.ifr_mtu as usize;
+    if sockfd == -1 {
+        return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+    }
 
-            // Now we're done!
-            Tap {
-                tapfd,
-                max_transmission_unit,
-            }
-        }
+    let mut _ifreq = *ifreq;
+    let res = libc::ioctl(
+        sockfd,
+        _libc::SIOCGIFMTU,
+        &mut _ifreq as *mut _libc::c_ifreq,
+    );
+    // Capture the error before close(...) can clobber errno.
+    let err = IOError::last_os_error();
+
+    libc::close(sockfd);
+
+    if res == -1 {
+        Err(Error::Device(DeviceError::Io(err)))
+    } else {
+        Ok(_ifreq.ifr_ifru.ifr_mtu as usize)
     }
 }
 
@@ -85,9 +123,9 @@ impl Device for Tap {
             );
 
             if wrote < 0 && _libc::errno() == libc::EAGAIN {
-                Err(Error::Device(None))
+                Err(Error::Device(DeviceError::WouldBlock))
             } else if wrote < 0 {
-                Err(Error::Device(Some(IOError::last_os_error())))
+                Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
             } else {
                 Ok(())
             }
@@ -103,20 +141,34 @@ impl Device for Tap {
             );
 
             if read < 0 && _libc::errno() == libc::EAGAIN {
-                Err(Error::Device(None))
+                Err(Error::Device(DeviceError::WouldBlock))
             } else if read < 0 {
-                Err(Error::Device(Some(IOError::last_os_error())))
+                Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
             } else {
                 Ok(read as usize)
             }
         }
     }
 
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        _libc::poll_readable(self.tapfd, timeout)
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.tapfd)
+    }
+
     fn max_transmission_unit(&self) -> usize {
         self.max_transmission_unit
     }
 }
 
+impl AsRawFd for Tap {
+    fn as_raw_fd(&self) -> RawFd {
+        self.tapfd
+    }
+}
+
 impl Drop for Tap {
     fn drop(&mut self) {
         unsafe {
@@ -124,3 +176,19 @@ impl Drop for Tap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_new_name_too_long() {
+        match Tap::try_new("tap0123456789abcdef") {
+            Err(Error::Device(DeviceError::Io(err))) => {
+                assert_eq!(err.kind(), IOErrorKind::InvalidInput)
+            }
+            Err(err) => panic!("Unexpected error {:?}.", err),
+            Ok(_) => panic!("Opened TAP with invalid name."),
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use std::io::Error as IOError;
+use std::os::unix::io::{
+    AsRawFd,
+    RawFd,
+};
+use std::time::Duration;
+
+use byteorder::{
+    NativeEndian,
+    ReadBytesExt,
+};
+use libc;
+
+use stack::dev::Device;
+use macos::libc as _libc;
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// Offsets of fields within a struct bpf_hdr preceding each captured frame.
+mod fields {
+    use std::ops::Range;
+
+    pub const CAPLEN: Range<usize> = 8 .. 12;
+
+    pub const HDRLEN: Range<usize> = 16 .. 18;
+}
+
+/// [BPF device](https://www.freebsd.org/cgi/man.cgi?bpf(4)) attached to an
+/// interface for sending and receiving raw ethernet frames, typically one
+/// side of a feth pair since macOS lacks TAP interfaces.
+///
+/// The device runs in promiscuous mode so frames addressed to the usrnet
+/// Ethernet address, rather than the interface's own, are received.
+pub struct Bpf {
+    bpffd: libc::c_int,
+    max_transmission_unit: usize,
+    read_buffer: Vec<u8>,
+    read_len: usize,
+    read_offset: usize,
+}
+
+impl Bpf {
+    /// Opens the first free BPF device and attaches it to an existing
+    /// interface.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if no BPF device is free, or configuring it runs into
+    /// an error such as the interface not existing.
+    pub fn new(ifr_name: &str) -> Bpf {
+        unsafe {
+            let ifreq = _libc::c_ifreq::with_name(ifr_name);
+
+            // Find a free BPF device...
+            let bpffd = (0 .. 256)
+                .map(|i| {
+                    let path = format!("/dev/bpf{}\0", i);
+                    libc::open(path.as_ptr() as *const libc::c_char, libc::O_RDWR)
+                })
+                .find(|&bpffd| bpffd >= 0 || _libc::errno() != libc::EBUSY)
+                .unwrap_or(-1);
+
+            if bpffd < 0 {
+                panic!("Opening BPF: {}.", IOError::last_os_error());
+            }
+
+            // Attach it to the interface...
+            let mut _ifreq = ifreq;
+            if libc::ioctl(bpffd, _libc::BIOCSETIF, &mut _ifreq as *mut _libc::c_ifreq) == -1 {
+                panic!("BIOCSETIF BPF: {}.", IOError::last_os_error());
+            }
+
+            // Return frames as they arrive, leave source addresses as is, skip
+            // frames we send, and accept frames for the usrnet address...
+            let enable: libc::c_uint = 1;
+            let disable: libc::c_uint = 0;
+            for &(request, value) in [
+                (_libc::BIOCIMMEDIATE, &enable),
+                (_libc::BIOCSHDRCMPLT, &enable),
+                (_libc::BIOCSSEESENT, &disable),
+            ]
+            .iter()
+            {
+                if libc::ioctl(bpffd, request, value as *const libc::c_uint) == -1 {
+                    panic!("IOCTL BPF: {}.", IOError::last_os_error());
+                }
+            }
+
+            if libc::ioctl(bpffd, _libc::BIOCPROMISC) == -1 {
+                panic!("BIOCPROMISC BPF: {}.", IOError::last_os_error());
+            }
+
+            if libc::fcntl(bpffd, libc::F_SETFL, libc::O_NONBLOCK) == -1 {
+                panic!("O_NONBLOCK BPF: {}.", IOError::last_os_error());
+            }
+
+            // Reads must use a buffer of exactly the BPF buffer length...
+            let mut buffer_len: libc::c_uint = 0;
+            if libc::ioctl(
+                bpffd,
+                _libc::BIOCGBLEN,
+                &mut buffer_len as *mut libc::c_uint,
+            ) == -1
+            {
+                panic!("BIOCGBLEN BPF: {}.", IOError::last_os_error());
+            }
+
+            // Query the MTU...
+            let sockfd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0);
+
+            if sockfd == -1 {
+                panic!("Opening socket: {}.", IOError::last_os_error());
+            }
+
+            let mut _ifreq = ifreq;
+            if libc::ioctl(
+                sockfd,
+                _libc::SIOCGIFMTU,
+                &mut _ifreq as *mut _libc::c_ifreq,
+            ) == -1
+            {
+                panic!("IOCTL socket: {}.", IOError::last_os_error());
+            }
+
+            libc::close(sockfd);
+
+            let max_transmission_unit = _ifreq.ifr_ifru.ifr_mtu as usize;
+
+            // Now we're done!
+            Bpf {
+                bpffd,
+                max_transmission_unit,
+                read_buffer: vec![0; buffer_len as usize],
+                read_len: 0,
+                read_offset: 0,
+            }
+        }
+    }
+
+    /// Copies the next frame buffered from the last read, if any.
+    fn next_buffered(&mut self, buffer: &mut [u8]) -> Option<usize> {
+        // The last record may not be padded to alignment.
+        let record = self
+            .read_buffer
+            .get(self.read_offset .. self.read_len)
+            .unwrap_or(&[]);
+        if record.len() < fields::HDRLEN.end {
+            return None;
+        }
+
+        let caplen = (&record[fields::CAPLEN])
+            .read_u32::<NativeEndian>()
+            .unwrap() as usize;
+        let hdrlen = (&record[fields::HDRLEN])
+            .read_u16::<NativeEndian>()
+            .unwrap() as usize;
+
+        let frame = record.get(hdrlen .. hdrlen + caplen)?;
+        let len = frame.len().min(buffer.len());
+        buffer[.. len].copy_from_slice(&frame[.. len]);
+
+        self.read_offset += _libc::bpf_wordalign(hdrlen + caplen);
+        Some(len)
+    }
+}
+
+impl Device for Bpf {
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        unsafe {
+            let wrote = libc::write(
+                self.bpffd,
+                buffer.as_ptr() as *const libc::c_void,
+                buffer.len(),
+            );
+
+            if wrote < 0 && _libc::errno() == libc::EAGAIN {
+                Err(Error::Device(DeviceError::WouldBlock))
+            } else if wrote < 0 {
+                Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        // A single read may return several frames, each with a BPF header.
+        if let Some(len) = self.next_buffered(buffer) {
+            return Ok(len);
+        }
+
+        unsafe {
+            let read = libc::read(
+                self.bpffd,
+                self.read_buffer.as_mut_ptr() as *mut libc::c_void,
+                self.read_buffer.len(),
+            );
+
+            if read < 0 && _libc::errno() == libc::EAGAIN {
+                return Err(Error::Device(DeviceError::WouldBlock));
+            } else if read < 0 {
+                return Err(Error::Device(DeviceError::Io(IOError::last_os_error())));
+            }
+
+            self.read_len = read as usize;
+            self.read_offset = 0;
+        }
+
+        self.next_buffered(buffer)
+            .ok_or(Error::Device(DeviceError::WouldBlock))
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        if self.read_offset < self.read_len {
+            Ok(())
+        } else {
+            _libc::poll_readable(self.bpffd, timeout)
+        }
+    }
+
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.bpffd)
+    }
+
+    fn max_transmission_unit(&self) -> usize {
+        self.max_transmission_unit
+    }
+}
+
+impl AsRawFd for Bpf {
+    fn as_raw_fd(&self) -> RawFd {
+        self.bpffd
+    }
+}
+
+impl Drop for Bpf {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.bpffd);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bpf(records: &[u8]) -> Bpf {
+        Bpf {
+            bpffd: -1,
+            max_transmission_unit: 1500,
+            read_buffer: records.to_vec(),
+            read_len: records.len(),
+            read_offset: 0,
+        }
+    }
+
+    #[test]
+    fn test_next_buffered() {
+        let mut records = vec![];
+        for &(caplen, byte) in [(3u32, 0xAAu8), (4, 0xBB)].iter() {
+            let mut header = [0; 18];
+            header[fields::CAPLEN].copy_from_slice(&caplen.to_ne_bytes());
+            header[fields::HDRLEN].copy_from_slice(&18u16.to_ne_bytes());
+            records.extend_from_slice(&header);
+            records.extend(vec![byte; caplen as usize]);
+            while records.len() % _libc::BPF_ALIGNMENT != 0 {
+                records.push(0);
+            }
+        }
+
+        let mut bpf = bpf(&records);
+        let mut buffer = [0; 8];
+        assert_eq!(bpf.next_buffered(&mut buffer), Some(3));
+        assert_eq!(&buffer[.. 3], &[0xAA; 3]);
+        assert_eq!(bpf.next_buffered(&mut buffer), Some(4));
+        assert_eq!(&buffer[.. 4], &[0xBB; 4]);
+        assert_eq!(bpf.next_buffered(&mut buffer), None);
+    }
+}
@@ -0,0 +1,108 @@
+use std::io::Error as IOError;
+use std::time::Duration;
+
+use libc;
+
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCGBLEN: libc::c_ulong = 0x40044266;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCSETIF: libc::c_ulong = 0x8020426C;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCPROMISC: libc::c_ulong = 0x20004269;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCIMMEDIATE: libc::c_ulong = 0x80044270;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCSHDRCMPLT: libc::c_ulong = 0x80044275;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BIOCSSEESENT: libc::c_ulong = 0x80044277;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/sys/sockio.h
+pub const SIOCGIFMTU: libc::c_ulong = 0xC0206933;
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub const BPF_ALIGNMENT: usize = 4;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/if.h
+pub struct c_ifreq {
+    pub ifr_name: [libc::c_char; libc::IF_NAMESIZE],
+    pub ifr_ifru: c_ifreq_ifru,
+}
+
+impl c_ifreq {
+    pub fn with_name(ifr_name: &str) -> c_ifreq {
+        assert!(ifr_name.len() < libc::IF_NAMESIZE);
+
+        let mut ifreq = c_ifreq {
+            ifr_name: [0; libc::IF_NAMESIZE],
+            ifr_ifru: c_ifreq_ifru { ifr_addr: [0; 16] },
+        };
+
+        for (i, c) in ifr_name.as_bytes().iter().enumerate() {
+            ifreq.ifr_name[i] = *c as libc::c_char;
+        }
+
+        ifreq
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union c_ifreq_ifru {
+    // Pads the union to the size of struct sockaddr, which the kernel copies.
+    pub ifr_addr: [u8; 16],
+    pub ifr_mtu: libc::c_int,
+}
+
+pub fn errno() -> libc::c_int {
+    unsafe {
+        let errno = libc::__error();
+        *errno
+    }
+}
+
+// https://opensource.apple.com/source/xnu/xnu-4570.1.46/bsd/net/bpf.h
+pub fn bpf_wordalign(len: usize) -> usize {
+    (len + BPF_ALIGNMENT - 1) & !(BPF_ALIGNMENT - 1)
+}
+
+/// Blocks until a file descriptor is readable or the timeout elapses, see
+/// [poll(2)](https://man7.org/linux/man-pages/man2/poll.2.html).
+pub fn poll_readable(fd: libc::c_int, timeout: Option<Duration>) -> Result<()> {
+    // Round up so short timeouts do not turn into busy polling.
+    let timeout_ms = match timeout {
+        Some(timeout) => {
+            let millis =
+                timeout.as_secs() * 1000 + u64::from(timeout.subsec_nanos() + 999_999) / 1_000_000;
+            millis.min(libc::c_int::MAX as u64) as libc::c_int
+        }
+        None => -1,
+    };
+
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+
+    unsafe {
+        if libc::poll(&mut pollfd as *mut libc::pollfd, 1, timeout_ms) < 0 && errno() != libc::EINTR
+        {
+            Err(Error::Device(DeviceError::Io(IOError::last_os_error())))
+        } else {
+            Ok(())
+        }
+    }
+}
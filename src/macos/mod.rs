@@ -0,0 +1,4 @@
+//! macOS specific features.
+
+pub mod bpf;
+pub mod libc;
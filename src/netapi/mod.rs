@@ -0,0 +1,580 @@
+//! Blocking sockets in the style of std::net.
+//!
+//! A Stack owns an interface and a socket set, and the TcpStream, TcpListener
+//! and UdpSocket types drive the stack while they block, so applications
+//! written against std::net can be ported with few changes. Stacks are single
+//! threaded, and sockets block without servicing other stacks. See sync for
+//! sockets which may be used from several threads.
+
+#[cfg(feature = "sync")]
+pub mod sync;
+
+use std::cell::RefCell;
+use std::cmp;
+use std::io::{
+    Error as IOError,
+    ErrorKind as IOErrorKind,
+    Read,
+    Result as IOResult,
+    Write,
+};
+use std::net::Shutdown;
+use std::rc::Rc;
+use std::time::Duration;
+
+use stack::repr::{
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Icmpv4Repr,
+};
+use stack::service::{
+    socket,
+    Interface,
+};
+use stack::socket::{
+    SocketAddr,
+    SocketEnv,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+    TcpSocket,
+};
+use stack::time::{
+    Instant,
+    SystemEnv,
+};
+use {
+    AddressError,
+    BufferError,
+    DeviceError,
+    Error,
+    RemoteError,
+    SocketError,
+};
+
+/// Length of the SYN and ESTABLISHED queues of TCP listeners.
+pub static TCP_LISTENER_BACKLOG: usize = 32;
+
+struct Inner {
+    interface: Interface,
+    socket_set: SocketSet,
+    socket_env: SocketEnv<SystemEnv>,
+    closing: Vec<SocketHandle>,
+}
+
+impl Inner {
+    fn new(interface: Interface) -> Inner {
+        let socket_env = SocketEnv::new(&interface, SystemEnv::new());
+        Inner {
+            interface,
+            socket_set: SocketSet::with_capacity(16),
+            socket_env,
+            closing: Vec::new(),
+        }
+    }
+
+    fn tick(&mut self) {
+        let Inner {
+            ref mut interface,
+            ref mut socket_set,
+            ref mut closing,
+            ..
+        } = *self;
+
+        socket::recv(interface, socket_set);
+        socket::send(interface, socket_set);
+
+        // Remove dropped TCP streams once their connections finish closing.
+        closing.retain(|handle| {
+            let is_closed = match socket_set.socket(*handle) {
+                Ok(&mut TaggedSocket::Tcp(ref socket)) => socket.is_closed(),
+                _ => true,
+            };
+
+            if is_closed {
+                socket_set.remove(*handle).ok();
+            }
+
+            !is_closed
+        });
+    }
+}
+
+/// An interface and the sockets serviced via it, shared by blocking sockets.
+#[derive(Clone)]
+pub struct Stack {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Stack {
+    /// Creates a stack which services sockets via an interface.
+    pub fn new(interface: Interface) -> Stack {
+        Stack {
+            inner: Rc::new(RefCell::new(Inner::new(interface))),
+        }
+    }
+
+    /// Calls f with the interface of the stack, e.g. to change its
+    /// configuration.
+    pub fn with_interface<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Interface) -> R,
+    {
+        f(&mut self.inner.borrow_mut().interface)
+    }
+
+    /// Sends and receives packets from/to sockets and the interface.
+    ///
+    /// Sockets tick the stack while blocking, but applications which do not
+    /// block on a socket for a while should tick the stack themselves.
+    pub fn tick(&self) {
+        self.inner.borrow_mut().tick();
+    }
+
+    fn add_socket(&self, socket: TaggedSocket) -> IOResult<SocketHandle> {
+        let mut inner = self.inner.borrow_mut();
+        inner.socket_set.add_socket(socket).map_err(io_error)
+    }
+
+    fn remove_socket(&self, handle: SocketHandle) {
+        self.inner.borrow_mut().socket_set.remove(handle).ok();
+    }
+
+    /// Ticks the stack until f returns a result for the socket, or the timeout
+    /// elapses.
+    fn block_on<F, R>(
+        &self,
+        handle: SocketHandle,
+        timeout: Option<Duration>,
+        mut f: F,
+    ) -> IOResult<R>
+    where
+        F: FnMut(&mut TaggedSocket) -> Option<IOResult<R>>,
+    {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            {
+                let mut inner = self.inner.borrow_mut();
+                let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+                if let Some(res) = f(socket) {
+                    return res;
+                }
+            }
+
+            // Sleep until a frame arrives or a timer such as a TCP retransmission
+            // is due.
+            let mut wait = {
+                let inner = self.inner.borrow();
+                inner.interface.poll_delay(&inner.socket_set)
+            };
+            if let Some(deadline) = deadline {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(IOError::new(IOErrorKind::TimedOut, "Operation timed out."));
+                }
+                let remaining = deadline - now;
+                wait = Some(wait.map_or(remaining, |wait| cmp::min(wait, remaining)));
+            }
+
+            if let Err(err) = self.inner.borrow_mut().interface.poll_wait(wait) {
+                warn!("Error waiting for frames with {:?}.", err);
+            }
+
+            self.tick();
+        }
+    }
+}
+
+/// A TCP connection which blocks while sending and receiving, like
+/// std::net::TcpStream.
+pub struct TcpStream {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl TcpStream {
+    /// Opens a connection to a remote endpoint from an ephemeral port,
+    /// blocking until the connection is established or fails.
+    pub fn connect(stack: &Stack, addr: SocketAddr) -> IOResult<TcpStream> {
+        TcpStream::connect_with(stack, addr, |socket| socket.connect(addr))
+    }
+
+    /// Opens a connection to a remote endpoint like connect(...), failing if
+    /// the connection is not established within the timeout.
+    pub fn connect_timeout(
+        stack: &Stack,
+        addr: SocketAddr,
+        timeout: Duration,
+    ) -> IOResult<TcpStream> {
+        TcpStream::connect_with(stack, addr, |socket| {
+            socket.connect_with_timeout(addr, timeout)
+        })
+    }
+
+    fn connect_with<F>(stack: &Stack, addr: SocketAddr, connect: F) -> IOResult<TcpStream>
+    where
+        F: FnOnce(&mut TcpSocket),
+    {
+        let mut socket = {
+            let inner = stack.inner.borrow();
+            let ipv4_addr = *inner.interface.ipv4_addr;
+            inner
+                .socket_env
+                .tcp_socket_ephemeral(ipv4_addr)
+                .map_err(io_error)?
+        };
+        let local_addr = **socket.binding();
+        connect(&mut socket);
+
+        let stream = TcpStream {
+            stack: stack.clone(),
+            handle: stack.add_socket(TaggedSocket::Tcp(socket))?,
+            local_addr,
+            peer_addr: addr,
+            read_timeout: None,
+            write_timeout: None,
+        };
+
+        stack.block_on(stream.handle, None, |socket| {
+            let socket = socket.as_tcp_socket();
+            if socket.is_establishing() {
+                None
+            } else if socket.is_connected() {
+                Some(Ok(()))
+            } else {
+                Some(Err(take_error(socket, IOErrorKind::ConnectionRefused)))
+            }
+        })?;
+
+        Ok(stream)
+    }
+
+    /// Returns the local address of the connection.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the address of the remote endpoint of the connection.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Sets the timeout for reads, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for reads.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Sets the timeout for writes, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for writes.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Shuts down the reading and/or writing halves of the connection.
+    pub fn shutdown(&self, how: Shutdown) -> IOResult<()> {
+        let mut inner = self.stack.inner.borrow_mut();
+        let socket = inner.socket_set.socket(self.handle).map_err(io_error)?;
+        socket.as_tcp_socket().shutdown(how);
+        Ok(())
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buffer: &mut [u8]) -> IOResult<usize> {
+        self.stack
+            .block_on(self.handle, self.read_timeout, |socket| {
+                let socket = socket.as_tcp_socket();
+                if socket.can_recv() {
+                    Some(socket.recv(buffer).map_err(io_error))
+                } else if socket.is_closed() {
+                    Some(socket.take_error().map(io_error).map_or(Ok(0), Err))
+                } else {
+                    None
+                }
+            })
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buffer: &[u8]) -> IOResult<usize> {
+        self.stack
+            .block_on(self.handle, self.write_timeout, |socket| {
+                let socket = socket.as_tcp_socket();
+                if socket.can_send() {
+                    Some(socket.send(buffer).map_err(io_error))
+                } else if !socket.is_connected() {
+                    Some(Err(take_error(socket, IOErrorKind::BrokenPipe)))
+                } else {
+                    None
+                }
+            })
+    }
+
+    /// Sends as much written data as possible. Unlike a std::net::TcpStream,
+    /// data is only sent while the stack is ticked.
+    fn flush(&mut self) -> IOResult<()> {
+        self.stack.tick();
+        Ok(())
+    }
+}
+
+impl Drop for TcpStream {
+    /// Closes the connection, which finishes closing while the stack is ticked.
+    fn drop(&mut self) {
+        let mut inner = self.stack.inner.borrow_mut();
+        if let Ok(socket) = inner.socket_set.socket(self.handle) {
+            socket.as_tcp_socket().shutdown(Shutdown::Both);
+        }
+        inner.closing.push(self.handle);
+    }
+}
+
+/// A TCP listener which blocks while accepting connections, like
+/// std::net::TcpListener.
+pub struct TcpListener {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+}
+
+impl TcpListener {
+    /// Creates a listener bound to an address.
+    pub fn bind(stack: &Stack, addr: SocketAddr) -> IOResult<TcpListener> {
+        let listener = stack
+            .inner
+            .borrow()
+            .socket_env
+            .tcp_listener(addr, TCP_LISTENER_BACKLOG, TCP_LISTENER_BACKLOG)
+            .map_err(io_error)?;
+
+        Ok(TcpListener {
+            stack: stack.clone(),
+            handle: stack.add_socket(TaggedSocket::TcpListener(listener))?,
+            local_addr: addr,
+        })
+    }
+
+    /// Blocks until a connection is established and returns it along with the
+    /// address of the remote endpoint.
+    pub fn accept(&self) -> IOResult<(TcpStream, SocketAddr)> {
+        let (socket, peer_addr) = self.stack.block_on(self.handle, None, |socket| {
+            socket.as_tcp_listener().accept().map(Ok)
+        })?;
+
+        let stream = TcpStream {
+            stack: self.stack.clone(),
+            handle: self.stack.add_socket(TaggedSocket::Tcp(socket))?,
+            local_addr: self.local_addr,
+            peer_addr,
+            read_timeout: None,
+            write_timeout: None,
+        };
+
+        Ok((stream, peer_addr))
+    }
+
+    /// Returns the local address of the listener.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        self.stack.remove_socket(self.handle);
+    }
+}
+
+/// A UDP socket which blocks while sending and receiving, like
+/// std::net::UdpSocket.
+pub struct UdpSocket {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl UdpSocket {
+    /// Creates a socket bound to an address, or to an ephemeral port if the
+    /// port of the address is 0.
+    pub fn bind(stack: &Stack, addr: SocketAddr) -> IOResult<UdpSocket> {
+        let socket = {
+            let inner = stack.inner.borrow();
+            if addr.port == 0 {
+                inner.socket_env.udp_socket_ephemeral(addr.addr)
+            } else {
+                inner.socket_env.udp_socket(addr)
+            }
+            .map_err(io_error)?
+        };
+        let local_addr = **socket.binding();
+
+        Ok(UdpSocket {
+            stack: stack.clone(),
+            handle: stack.add_socket(TaggedSocket::Udp(socket))?,
+            local_addr,
+            read_timeout: None,
+            write_timeout: None,
+        })
+    }
+
+    /// Returns the local address of the socket.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Connects the socket to a remote endpoint, so send(...) and recv(...)
+    /// send to and receive from only the endpoint.
+    pub fn connect(&self, addr: SocketAddr) -> IOResult<()> {
+        let mut inner = self.stack.inner.borrow_mut();
+        let socket = inner.socket_set.socket(self.handle).map_err(io_error)?;
+        socket.as_udp_socket().connect(addr);
+        Ok(())
+    }
+
+    /// Returns the address of the remote endpoint the socket is connected to.
+    pub fn peer_addr(&self) -> IOResult<SocketAddr> {
+        let mut inner = self.stack.inner.borrow_mut();
+        let socket = inner.socket_set.socket(self.handle).map_err(io_error)?;
+        socket.as_udp_socket().peer_addr().map_err(io_error)
+    }
+
+    /// Sends a datagram to an address, blocking while the send buffer is full.
+    pub fn send_to(&self, buffer: &[u8], addr: SocketAddr) -> IOResult<usize> {
+        self.stack
+            .block_on(self.handle, self.write_timeout, |socket| {
+                match socket.as_udp_socket().send(buffer.len(), addr) {
+                    Ok(payload) => {
+                        payload.copy_from_slice(buffer);
+                        Some(Ok(buffer.len()))
+                    }
+                    Err(Error::Buffer(BufferError::Full)) => None,
+                    Err(err) => Some(Err(io_error(err))),
+                }
+            })
+    }
+
+    /// Sends a datagram to the endpoint the socket is connected to.
+    pub fn send(&self, buffer: &[u8]) -> IOResult<usize> {
+        let addr = self.peer_addr()?;
+        self.send_to(buffer, addr)
+    }
+
+    /// Blocks until a datagram is received and returns the number of bytes
+    /// copied to the buffer along with the address of the sender. Datagrams
+    /// larger than the buffer are truncated.
+    pub fn recv_from(&self, buffer: &mut [u8]) -> IOResult<(usize, SocketAddr)> {
+        self.stack
+            .block_on(self.handle, self.read_timeout, |socket| {
+                match socket.as_udp_socket().recv() {
+                    Ok((payload, addr)) => {
+                        let len = cmp::min(payload.len(), buffer.len());
+                        buffer[.. len].copy_from_slice(&payload[.. len]);
+                        Some(Ok((len, addr)))
+                    }
+                    Err(Error::Buffer(BufferError::Empty)) => None,
+                    Err(err) => Some(Err(io_error(err))),
+                }
+            })
+    }
+
+    /// Blocks until a datagram is received from the endpoint the socket is
+    /// connected to.
+    pub fn recv(&self, buffer: &mut [u8]) -> IOResult<usize> {
+        self.recv_from(buffer).map(|(len, _)| len)
+    }
+
+    /// Sets the timeout for receiving, or None to block indefinitely. Fails
+    /// with ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for receiving.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Sets the timeout for sending, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for sending.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+}
+
+impl Drop for UdpSocket {
+    /// Sends as many enqueued datagrams as possible before closing the socket.
+    fn drop(&mut self) {
+        self.stack.tick();
+        self.stack.remove_socket(self.handle);
+    }
+}
+
+fn check_timeout(timeout: Option<Duration>) -> IOResult<Option<Duration>> {
+    if timeout == Some(Duration::from_secs(0)) {
+        Err(IOError::new(
+            IOErrorKind::InvalidInput,
+            "Zero timeouts are not supported.",
+        ))
+    } else {
+        Ok(timeout)
+    }
+}
+
+/// Returns the error which closed a TCP socket, or an error of a particular
+/// kind if there is none, e.g. because the connection was reset.
+fn take_error(socket: &mut TcpSocket, kind: IOErrorKind) -> IOError {
+    match socket.take_error() {
+        Some(err) => io_error(err),
+        None => IOError::new(kind, "Connection closed."),
+    }
+}
+
+/// Converts an error to the closest std::io::Error.
+fn io_error(err: Error) -> IOError {
+    let kind = match err {
+        Error::Device(DeviceError::Io(err)) => return err,
+        Error::Device(DeviceError::WouldBlock)
+        | Error::Buffer(BufferError::Full)
+        | Error::Buffer(BufferError::Empty) => IOErrorKind::WouldBlock,
+        Error::Socket(SocketError::BindingInUse(_)) => IOErrorKind::AddrInUse,
+        Error::Address(AddressError::InvalidAddress(_)) => IOErrorKind::AddrNotAvailable,
+        Error::Socket(SocketError::NotConnected) => IOErrorKind::NotConnected,
+        Error::Socket(SocketError::ConnectionReset) => IOErrorKind::ConnectionReset,
+        Error::Timeout => IOErrorKind::TimedOut,
+        Error::Remote(RemoteError::Icmp(Icmpv4Repr {
+            message:
+                Icmpv4Message::DestinationUnreachable(Icmpv4DestinationUnreachable::PortUnreachable),
+            ..
+        })) => IOErrorKind::ConnectionRefused,
+        _ => IOErrorKind::Other,
+    };
+
+    IOError::new(kind, format!("{:?}", err))
+}
@@ -0,0 +1,603 @@
+//! Blocking sockets in the style of std::net which may be used from several
+//! threads.
+//!
+//! Interfaces and sockets are single threaded, so a Stack runs them on a
+//! thread of their own and the TcpStream, TcpListener and UdpSocket types are
+//! handles which send requests to that thread and block on the replies. Unlike
+//! the handles in netapi, these are Send + Sync, so application threads may
+//! queue data while the stack keeps servicing the interface.
+
+use std::cmp;
+use std::io::{
+    Error as IOError,
+    ErrorKind as IOErrorKind,
+    Read,
+    Result as IOResult,
+    Write,
+};
+use std::net::Shutdown;
+use std::sync::mpsc::{
+    self,
+    Sender,
+    TryRecvError,
+};
+use std::sync::{
+    Arc,
+    Mutex,
+};
+use std::thread;
+use std::time::Duration;
+
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    TaggedSocket,
+};
+use stack::time::Instant;
+use netapi::{
+    check_timeout,
+    io_error,
+    take_error,
+    Inner,
+    TCP_LISTENER_BACKLOG,
+};
+use {
+    BufferError,
+    Error,
+};
+
+/// Longest the stack thread waits for frames before servicing new requests.
+pub static POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A request run on the stack thread each time it is ticked until it returns
+/// true.
+type Request = Box<dyn FnMut(&mut Inner) -> bool + Send>;
+
+/// A handle to an interface and the sockets serviced via it on a thread of
+/// their own.
+///
+/// The thread exits once all handles to the stack, including sockets, are
+/// dropped and closing TCP connections finish closing.
+#[derive(Clone)]
+pub struct Stack {
+    requests: Arc<Mutex<Sender<Request>>>,
+}
+
+impl Stack {
+    /// Spawns a thread which creates an interface with f and services sockets
+    /// via it.
+    ///
+    /// Interfaces are not Send, so they are created on the stack thread
+    /// rather than moved to it.
+    pub fn spawn<F>(f: F) -> Stack
+    where
+        F: FnOnce() -> Interface + Send + 'static,
+    {
+        let (requests_tx, requests_rx) = mpsc::channel::<Request>();
+
+        thread::spawn(move || {
+            let mut inner = Inner::new(f());
+            let mut pending = Vec::new();
+            let mut is_dropped = false;
+
+            loop {
+                loop {
+                    match requests_rx.try_recv() {
+                        Ok(request) => pending.push(request),
+                        Err(TryRecvError::Empty) => break,
+                        Err(TryRecvError::Disconnected) => {
+                            is_dropped = true;
+                            break;
+                        }
+                    }
+                }
+
+                pending.retain_mut(|request| !request(&mut inner));
+                inner.tick();
+
+                if is_dropped && pending.is_empty() && inner.closing.is_empty() {
+                    return;
+                }
+
+                // Wake up regularly to service new requests, which cannot wake
+                // the device.
+                let wait = inner
+                    .interface
+                    .poll_delay(&inner.socket_set)
+                    .map_or(POLL_INTERVAL, |wait| cmp::min(wait, POLL_INTERVAL));
+                if let Err(err) = inner.interface.poll_wait(Some(wait)) {
+                    warn!("Error waiting for frames with {:?}.", err);
+                }
+            }
+        });
+
+        Stack {
+            requests: Arc::new(Mutex::new(requests_tx)),
+        }
+    }
+
+    /// Calls f with the interface of the stack on the stack thread, e.g. to
+    /// change its configuration, and returns the result.
+    pub fn with_interface<F, R>(&self, f: F) -> IOResult<R>
+    where
+        F: FnOnce(&mut Interface) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.call(move |inner| Ok(f(&mut inner.interface)))
+    }
+
+    /// Queues a request without waiting for it to finish.
+    fn request(&self, request: Request) -> IOResult<()> {
+        self.requests
+            .lock()
+            .unwrap()
+            .send(request)
+            .map_err(|_| stack_exited())
+    }
+
+    /// Runs f once on the stack thread and returns the result.
+    fn call<F, R>(&self, f: F) -> IOResult<R>
+    where
+        F: FnOnce(&mut Inner) -> IOResult<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let mut f = Some(f);
+        self.block_on(None, move |inner| match f.take() {
+            Some(f) => f(inner).map(Some),
+            None => Ok(None),
+        })
+    }
+
+    /// Runs f on the stack thread each time the stack is ticked until it
+    /// returns a result, or the timeout elapses.
+    fn block_on<F, R>(&self, timeout: Option<Duration>, mut f: F) -> IOResult<R>
+    where
+        F: FnMut(&mut Inner) -> IOResult<Option<R>> + Send + 'static,
+        R: Send + 'static,
+    {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let (reply_tx, reply_rx) = mpsc::channel();
+
+        self.request(Box::new(move |inner| {
+            let res = match f(inner) {
+                Ok(Some(res)) => Ok(res),
+                Err(err) => Err(err),
+                Ok(None) => match deadline {
+                    Some(deadline) if Instant::now() >= deadline => Err(IOError::new(
+                        IOErrorKind::TimedOut,
+                        "Operation timed out.",
+                    )),
+                    _ => return false,
+                },
+            };
+
+            reply_tx.send(res).ok();
+            true
+        }))?;
+
+        reply_rx.recv().unwrap_or_else(|_| Err(stack_exited()))
+    }
+}
+
+/// A TCP connection which blocks while sending and receiving, like
+/// std::net::TcpStream.
+pub struct TcpStream {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+    peer_addr: SocketAddr,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl TcpStream {
+    /// Opens a connection to a remote endpoint from an ephemeral port,
+    /// blocking until the connection is established or fails.
+    pub fn connect(stack: &Stack, addr: SocketAddr) -> IOResult<TcpStream> {
+        TcpStream::connect_with(stack, addr, None)
+    }
+
+    /// Opens a connection to a remote endpoint like connect(...), failing if
+    /// the connection is not established within the timeout.
+    pub fn connect_timeout(
+        stack: &Stack,
+        addr: SocketAddr,
+        timeout: Duration,
+    ) -> IOResult<TcpStream> {
+        TcpStream::connect_with(stack, addr, Some(timeout))
+    }
+
+    fn connect_with(
+        stack: &Stack,
+        addr: SocketAddr,
+        timeout: Option<Duration>,
+    ) -> IOResult<TcpStream> {
+        let (handle, local_addr) = stack.call(move |inner| {
+            let ipv4_addr = *inner.interface.ipv4_addr;
+            let mut socket = inner
+                .socket_env
+                .tcp_socket_ephemeral(ipv4_addr)
+                .map_err(io_error)?;
+            let local_addr = **socket.binding();
+            match timeout {
+                Some(timeout) => socket.connect_with_timeout(addr, timeout),
+                None => socket.connect(addr),
+            }
+
+            let handle = inner
+                .socket_set
+                .add_socket(TaggedSocket::Tcp(socket))
+                .map_err(io_error)?;
+            Ok((handle, local_addr))
+        })?;
+
+        let stream = TcpStream {
+            stack: stack.clone(),
+            handle,
+            local_addr,
+            peer_addr: addr,
+            read_timeout: None,
+            write_timeout: None,
+        };
+
+        stack.block_on(None, move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            let socket = socket.as_tcp_socket();
+            if socket.is_establishing() {
+                Ok(None)
+            } else if socket.is_connected() {
+                Ok(Some(()))
+            } else {
+                Err(take_error(socket, IOErrorKind::ConnectionRefused))
+            }
+        })?;
+
+        Ok(stream)
+    }
+
+    /// Returns the local address of the connection.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Returns the address of the remote endpoint of the connection.
+    pub fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    /// Sets the timeout for reads, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for reads.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Sets the timeout for writes, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for writes.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Shuts down the reading and/or writing halves of the connection.
+    pub fn shutdown(&self, how: Shutdown) -> IOResult<()> {
+        let handle = self.handle;
+        self.stack.call(move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            socket.as_tcp_socket().shutdown(how);
+            Ok(())
+        })
+    }
+}
+
+impl Read for TcpStream {
+    fn read(&mut self, buffer: &mut [u8]) -> IOResult<usize> {
+        let handle = self.handle;
+        let len = buffer.len();
+        let payload = self.stack.block_on(self.read_timeout, move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            let socket = socket.as_tcp_socket();
+            if socket.can_recv() {
+                let mut payload = vec![0; len];
+                let len = socket.recv(&mut payload).map_err(io_error)?;
+                payload.truncate(len);
+                Ok(Some(payload))
+            } else if socket.is_closed() {
+                socket.take_error().map(io_error).map_or(Ok(Some(vec![])), Err)
+            } else {
+                Ok(None)
+            }
+        })?;
+
+        buffer[.. payload.len()].copy_from_slice(&payload);
+        Ok(payload.len())
+    }
+}
+
+impl Write for TcpStream {
+    fn write(&mut self, buffer: &[u8]) -> IOResult<usize> {
+        let handle = self.handle;
+        let payload = buffer.to_vec();
+        self.stack.block_on(self.write_timeout, move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            let socket = socket.as_tcp_socket();
+            if socket.can_send() {
+                socket.send(&payload).map(Some).map_err(io_error)
+            } else if !socket.is_connected() {
+                Err(take_error(socket, IOErrorKind::BrokenPipe))
+            } else {
+                Ok(None)
+            }
+        })
+    }
+
+    /// Does nothing, as the stack thread sends written data as soon as
+    /// possible.
+    fn flush(&mut self) -> IOResult<()> {
+        Ok(())
+    }
+}
+
+impl Drop for TcpStream {
+    /// Closes the connection, which finishes closing on the stack thread.
+    fn drop(&mut self) {
+        let handle = self.handle;
+        let request = Box::new(move |inner: &mut Inner| {
+            if let Ok(socket) = inner.socket_set.socket(handle) {
+                socket.as_tcp_socket().shutdown(Shutdown::Both);
+            }
+            inner.closing.push(handle);
+            true
+        });
+        self.stack.request(request).ok();
+    }
+}
+
+/// A TCP listener which blocks while accepting connections, like
+/// std::net::TcpListener.
+pub struct TcpListener {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+}
+
+impl TcpListener {
+    /// Creates a listener bound to an address.
+    pub fn bind(stack: &Stack, addr: SocketAddr) -> IOResult<TcpListener> {
+        let handle = stack.call(move |inner| {
+            let listener = inner
+                .socket_env
+                .tcp_listener(addr, TCP_LISTENER_BACKLOG, TCP_LISTENER_BACKLOG)
+                .map_err(io_error)?;
+            inner
+                .socket_set
+                .add_socket(TaggedSocket::TcpListener(listener))
+                .map_err(io_error)
+        })?;
+
+        Ok(TcpListener {
+            stack: stack.clone(),
+            handle,
+            local_addr: addr,
+        })
+    }
+
+    /// Blocks until a connection is established and returns it along with the
+    /// address of the remote endpoint.
+    pub fn accept(&self) -> IOResult<(TcpStream, SocketAddr)> {
+        let listener_handle = self.handle;
+        let (handle, peer_addr) = self.stack.block_on(None, move |inner| {
+            let (socket, peer_addr) = {
+                let listener = inner
+                    .socket_set
+                    .socket(listener_handle)
+                    .map_err(io_error)?;
+                match listener.as_tcp_listener().accept() {
+                    Some(accepted) => accepted,
+                    None => return Ok(None),
+                }
+            };
+
+            let handle = inner
+                .socket_set
+                .add_socket(TaggedSocket::Tcp(socket))
+                .map_err(io_error)?;
+            Ok(Some((handle, peer_addr)))
+        })?;
+
+        let stream = TcpStream {
+            stack: self.stack.clone(),
+            handle,
+            local_addr: self.local_addr,
+            peer_addr,
+            read_timeout: None,
+            write_timeout: None,
+        };
+
+        Ok((stream, peer_addr))
+    }
+
+    /// Returns the local address of the listener.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for TcpListener {
+    fn drop(&mut self) {
+        let handle = self.handle;
+        let request = Box::new(move |inner: &mut Inner| {
+            inner.socket_set.remove(handle).ok();
+            true
+        });
+        self.stack.request(request).ok();
+    }
+}
+
+/// A UDP socket which blocks while sending and receiving, like
+/// std::net::UdpSocket.
+pub struct UdpSocket {
+    stack: Stack,
+    handle: SocketHandle,
+    local_addr: SocketAddr,
+    read_timeout: Option<Duration>,
+    write_timeout: Option<Duration>,
+}
+
+impl UdpSocket {
+    /// Creates a socket bound to an address, or to an ephemeral port if the
+    /// port of the address is 0.
+    pub fn bind(stack: &Stack, addr: SocketAddr) -> IOResult<UdpSocket> {
+        let (handle, local_addr) = stack.call(move |inner| {
+            let socket = if addr.port == 0 {
+                inner.socket_env.udp_socket_ephemeral(addr.addr)
+            } else {
+                inner.socket_env.udp_socket(addr)
+            }
+            .map_err(io_error)?;
+            let local_addr = **socket.binding();
+
+            let handle = inner
+                .socket_set
+                .add_socket(TaggedSocket::Udp(socket))
+                .map_err(io_error)?;
+            Ok((handle, local_addr))
+        })?;
+
+        Ok(UdpSocket {
+            stack: stack.clone(),
+            handle,
+            local_addr,
+            read_timeout: None,
+            write_timeout: None,
+        })
+    }
+
+    /// Returns the local address of the socket.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Connects the socket to a remote endpoint, so send(...) and recv(...)
+    /// send to and receive from only the endpoint.
+    pub fn connect(&self, addr: SocketAddr) -> IOResult<()> {
+        let handle = self.handle;
+        self.stack.call(move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            socket.as_udp_socket().connect(addr);
+            Ok(())
+        })
+    }
+
+    /// Returns the address of the remote endpoint the socket is connected to.
+    pub fn peer_addr(&self) -> IOResult<SocketAddr> {
+        let handle = self.handle;
+        self.stack.call(move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            socket.as_udp_socket().peer_addr().map_err(io_error)
+        })
+    }
+
+    /// Sends a datagram to an address, blocking while the send buffer is full.
+    pub fn send_to(&self, buffer: &[u8], addr: SocketAddr) -> IOResult<usize> {
+        let handle = self.handle;
+        let datagram = buffer.to_vec();
+        self.stack.block_on(self.write_timeout, move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            match socket.as_udp_socket().send(datagram.len(), addr) {
+                Ok(payload) => {
+                    payload.copy_from_slice(&datagram);
+                    Ok(Some(datagram.len()))
+                }
+                Err(Error::Buffer(BufferError::Full)) => Ok(None),
+                Err(err) => Err(io_error(err)),
+            }
+        })
+    }
+
+    /// Sends a datagram to the endpoint the socket is connected to.
+    pub fn send(&self, buffer: &[u8]) -> IOResult<usize> {
+        let addr = self.peer_addr()?;
+        self.send_to(buffer, addr)
+    }
+
+    /// Blocks until a datagram is received and returns the number of bytes
+    /// copied to the buffer along with the address of the sender. Datagrams
+    /// larger than the buffer are truncated.
+    pub fn recv_from(&self, buffer: &mut [u8]) -> IOResult<(usize, SocketAddr)> {
+        let handle = self.handle;
+        let len = buffer.len();
+        let (datagram, addr) = self.stack.block_on(self.read_timeout, move |inner| {
+            let socket = inner.socket_set.socket(handle).map_err(io_error)?;
+            match socket.as_udp_socket().recv() {
+                Ok((payload, addr)) => {
+                    let len = cmp::min(payload.len(), len);
+                    Ok(Some((payload[.. len].to_vec(), addr)))
+                }
+                Err(Error::Buffer(BufferError::Empty)) => Ok(None),
+                Err(err) => Err(io_error(err)),
+            }
+        })?;
+
+        buffer[.. datagram.len()].copy_from_slice(&datagram);
+        Ok((datagram.len(), addr))
+    }
+
+    /// Blocks until a datagram is received from the endpoint the socket is
+    /// connected to.
+    pub fn recv(&self, buffer: &mut [u8]) -> IOResult<usize> {
+        self.recv_from(buffer).map(|(len, _)| len)
+    }
+
+    /// Sets the timeout for receiving, or None to block indefinitely. Fails
+    /// with ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.read_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for receiving.
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Sets the timeout for sending, or None to block indefinitely. Fails with
+    /// ErrorKind::InvalidInput for a zero timeout.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> IOResult<()> {
+        self.write_timeout = check_timeout(timeout)?;
+        Ok(())
+    }
+
+    /// Returns the timeout for sending.
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+}
+
+impl Drop for UdpSocket {
+    /// Closes the socket once enqueued datagrams are sent as far as possible.
+    fn drop(&mut self) {
+        let handle = self.handle;
+        let request = Box::new(move |inner: &mut Inner| {
+            inner.tick();
+            inner.socket_set.remove(handle).ok();
+            true
+        });
+        self.stack.request(request).ok();
+    }
+}
+
+fn stack_exited() -> IOError {
+    IOError::new(IOErrorKind::BrokenPipe, "Stack thread exited.")
+}
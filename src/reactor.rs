@@ -0,0 +1,355 @@
+//! Awaiting sockets from futures.
+//!
+//! A Reactor owns an interface and a socket set, and returns futures which
+//! poll sockets via their poll_* functions. Sockets wake the tasks awaiting
+//! them when packets are sent or received, so tasks only run when they can
+//! make progress. Executors should call Reactor::poll() when raw_fd() is
+//! readable and after poll_delay() for timers, or use block_on(...) which
+//! does so.
+
+use std::cell::RefCell;
+use std::future::Future;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{
+    AtomicBool,
+    Ordering,
+};
+use std::sync::Arc;
+use std::task::{
+    Context,
+    Poll,
+    Wake,
+    Waker,
+};
+use std::time::Duration;
+
+use stack::service::{
+    socket,
+    Interface,
+};
+use stack::socket::{
+    SocketAddr,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+    TcpSocket,
+};
+use Result;
+
+struct Inner {
+    interface: Interface,
+    socket_set: SocketSet,
+}
+
+/// An interface and the sockets serviced via it, shared by futures.
+#[derive(Clone)]
+pub struct Reactor {
+    inner: Rc<RefCell<Inner>>,
+}
+
+impl Reactor {
+    /// Creates a reactor which services sockets via an interface.
+    pub fn new(interface: Interface, socket_set: SocketSet) -> Reactor {
+        let inner = Inner {
+            interface,
+            socket_set,
+        };
+
+        Reactor {
+            inner: Rc::new(RefCell::new(inner)),
+        }
+    }
+
+    /// Returns a file descriptor which is readable when the interface has
+    /// frames to receive, if the device is backed by one.
+    pub fn raw_fd(&self) -> Option<RawFd> {
+        self.inner.borrow().interface.dev.raw_fd()
+    }
+
+    /// Sends and receives packets from/to sockets and the interface, waking
+    /// tasks awaiting sockets which can make progress.
+    pub fn poll(&self) {
+        let mut inner = self.inner.borrow_mut();
+        let Inner {
+            ref mut interface,
+            ref mut socket_set,
+        } = *inner;
+
+        socket::recv(interface, socket_set);
+        socket::send(interface, socket_set);
+    }
+
+    /// Returns the time until poll() next needs to be called to service timers,
+    /// or None if nothing is due until raw_fd() is readable.
+    pub fn poll_delay(&self) -> Option<Duration> {
+        let inner = self.inner.borrow();
+        inner.interface.poll_delay(&inner.socket_set)
+    }
+
+    /// Calls f with the interface of the reactor.
+    pub fn with_interface<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Interface) -> R,
+    {
+        f(&mut self.inner.borrow_mut().interface)
+    }
+
+    /// Calls f with the socket set of the reactor.
+    pub fn with_socket_set<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut SocketSet) -> R,
+    {
+        f(&mut self.inner.borrow_mut().socket_set)
+    }
+
+    /// Adds a socket to the socket set of the reactor.
+    pub fn add_socket(&self, socket: TaggedSocket) -> Result<SocketHandle> {
+        self.inner.borrow_mut().socket_set.add_socket(socket)
+    }
+
+    /// Removes a socket from the socket set of the reactor.
+    pub fn remove_socket(&self, handle: SocketHandle) -> Result<TaggedSocket> {
+        self.inner.borrow_mut().socket_set.remove(handle)
+    }
+
+    /// Returns a future which resolves once a TCP socket finishes connecting
+    /// to an endpoint passed to TcpSocket::connect(...).
+    pub fn connect(&self, handle: SocketHandle) -> TcpConnect {
+        TcpConnect {
+            reactor: self.clone(),
+            handle,
+        }
+    }
+
+    /// Returns a future which resolves to the next connection established by
+    /// a TCP listener, along with the address of the remote endpoint.
+    pub fn accept(&self, handle: SocketHandle) -> TcpAccept {
+        TcpAccept {
+            reactor: self.clone(),
+            handle,
+        }
+    }
+
+    /// Returns a future which enqueues data for sending via a TCP socket and
+    /// resolves to the number of bytes enqueued.
+    pub fn send<'a>(&self, handle: SocketHandle, buffer: &'a [u8]) -> TcpSend<'a> {
+        TcpSend {
+            reactor: self.clone(),
+            handle,
+            buffer,
+        }
+    }
+
+    /// Returns a future which dequeues data received via a TCP socket and
+    /// resolves to the number of bytes dequeued, or 0 at the end of the
+    /// stream.
+    pub fn recv<'a>(&self, handle: SocketHandle, buffer: &'a mut [u8]) -> TcpRecv<'a> {
+        TcpRecv {
+            reactor: self.clone(),
+            handle,
+            buffer,
+        }
+    }
+
+    /// Returns a future which enqueues a packet for sending to an address via
+    /// a UDP socket and resolves to the length of the payload.
+    pub fn send_to<'a>(
+        &self,
+        handle: SocketHandle,
+        buffer: &'a [u8],
+        addr: SocketAddr,
+    ) -> UdpSendTo<'a> {
+        UdpSendTo {
+            reactor: self.clone(),
+            handle,
+            buffer,
+            addr,
+        }
+    }
+
+    /// Returns a future which dequeues a packet received via a UDP socket and
+    /// resolves to the number of bytes copied to the buffer along with the
+    /// address of the sender. Packets larger than the buffer are truncated.
+    pub fn recv_from<'a>(&self, handle: SocketHandle, buffer: &'a mut [u8]) -> UdpRecvFrom<'a> {
+        UdpRecvFrom {
+            reactor: self.clone(),
+            handle,
+            buffer,
+        }
+    }
+
+    /// Runs a future to completion on the current thread, polling the reactor
+    /// while the future is pending.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        let woken = Arc::new(Woken(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+
+        loop {
+            let ready = future.as_mut().poll(&mut cx);
+
+            // Send whatever the future enqueued.
+            self.poll();
+
+            if let Poll::Ready(output) = ready {
+                return output;
+            }
+
+            if woken.0.swap(false, Ordering::SeqCst) {
+                continue;
+            }
+
+            // Sleep until a frame arrives or a timer such as a TCP retransmission
+            // is due.
+            let timeout = self.poll_delay();
+            if let Err(err) = self.inner.borrow_mut().interface.poll_wait(timeout) {
+                warn!("Error waiting for frames with {:?}.", err);
+            }
+
+            self.poll();
+        }
+    }
+
+    fn poll_socket<F, R>(&self, handle: SocketHandle, f: F) -> Poll<Result<R>>
+    where
+        F: FnOnce(&mut TaggedSocket) -> Poll<Result<R>>,
+    {
+        match self.inner.borrow_mut().socket_set.socket(handle) {
+            Ok(socket) => f(socket),
+            Err(err) => Poll::Ready(Err(err)),
+        }
+    }
+}
+
+/// Flags the task run by block_on(...) as woken.
+struct Woken(AtomicBool);
+
+impl Wake for Woken {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Future returned by Reactor::connect(...).
+pub struct TcpConnect {
+    reactor: Reactor,
+    handle: SocketHandle,
+}
+
+impl Future for TcpConnect {
+    type Output = Result<()>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.reactor.poll_socket(self.handle, |socket| {
+            socket.as_tcp_socket().poll_connect(cx)
+        })
+    }
+}
+
+/// Future returned by Reactor::accept(...).
+pub struct TcpAccept {
+    reactor: Reactor,
+    handle: SocketHandle,
+}
+
+impl Future for TcpAccept {
+    type Output = Result<(TcpSocket, SocketAddr)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.reactor.poll_socket(self.handle, |socket| {
+            socket.as_tcp_listener().poll_accept(cx).map(Ok)
+        })
+    }
+}
+
+/// Future returned by Reactor::send(...).
+pub struct TcpSend<'a> {
+    reactor: Reactor,
+    handle: SocketHandle,
+    buffer: &'a [u8],
+}
+
+impl<'a> Future for TcpSend<'a> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let buffer = self.buffer;
+        self.reactor.poll_socket(self.handle, |socket| {
+            socket.as_tcp_socket().poll_send(cx, buffer)
+        })
+    }
+}
+
+/// Future returned by Reactor::recv(...).
+pub struct TcpRecv<'a> {
+    reactor: Reactor,
+    handle: SocketHandle,
+    buffer: &'a mut [u8],
+}
+
+impl<'a> Future for TcpRecv<'a> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let recv = self.get_mut();
+        let buffer = &mut recv.buffer;
+        recv.reactor.poll_socket(recv.handle, |socket| {
+            socket.as_tcp_socket().poll_recv(cx, buffer)
+        })
+    }
+}
+
+/// Future returned by Reactor::send_to(...).
+pub struct UdpSendTo<'a> {
+    reactor: Reactor,
+    handle: SocketHandle,
+    buffer: &'a [u8],
+    addr: SocketAddr,
+}
+
+impl<'a> Future for UdpSendTo<'a> {
+    type Output = Result<usize>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let (buffer, addr) = (self.buffer, self.addr);
+        self.reactor.poll_socket(self.handle, |socket| {
+            socket
+                .as_udp_socket()
+                .poll_send(cx, buffer.len(), addr)
+                .map(|res| {
+                    res.map(|payload| {
+                        payload.copy_from_slice(buffer);
+                        buffer.len()
+                    })
+                })
+        })
+    }
+}
+
+/// Future returned by Reactor::recv_from(...).
+pub struct UdpRecvFrom<'a> {
+    reactor: Reactor,
+    handle: SocketHandle,
+    buffer: &'a mut [u8],
+}
+
+impl<'a> Future for UdpRecvFrom<'a> {
+    type Output = Result<(usize, SocketAddr)>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let recv_from = self.get_mut();
+        let buffer = &mut recv_from.buffer;
+        recv_from.reactor.poll_socket(recv_from.handle, |socket| {
+            socket.as_udp_socket().poll_recv(cx).map(|res| {
+                res.map(|(payload, addr)| {
+                    let len = payload.len().min(buffer.len());
+                    buffer[.. len].copy_from_slice(&payload[.. len]);
+                    (len, addr)
+                })
+            })
+        })
+    }
+}
@@ -0,0 +1,387 @@
+//! Management and caching of Ethernet/IP address mappings.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use stack::repr::{
+    EthernetAddress,
+    Ipv4Address,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+};
+
+struct Entry {
+    eth_addr: EthernetAddress,
+    in_cache_since: Instant,
+    used_at: Instant,
+    permanent: bool,
+}
+
+/// A snapshot of an ARP cache entry, see `ArpCache::dump()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArpEntry {
+    pub ipv4_addr: Ipv4Address,
+    pub eth_addr: EthernetAddress,
+    /// Permanent entries never expire and are not overwritten by learned
+    /// mappings.
+    pub permanent: bool,
+    /// Time since the mapping was created or last updated.
+    pub age: Duration,
+}
+
+/// Maintains an expiring set of IPv4 -> ethernet address mappings.
+///
+/// The cache may optionally be bounded, in which case the least recently used
+/// non-permanent entry is evicted to make room for new mappings.
+pub struct ArpCache<T = DefaultEnv>
+where
+    T: Env,
+{
+    entries: BTreeMap<Ipv4Address, Entry>,
+    expiration: Duration,
+    in_cache_since_min: Instant,
+    max_entries: Option<usize>,
+    time_env: T,
+}
+
+impl<T: Env> ArpCache<T> {
+    /// Creates an ARP cache where ethernet address mappings expire after
+    /// expiration_in_secs seconds.
+    pub fn new(expiration_in_secs: u64, time_env: T) -> ArpCache<T> {
+        ArpCache {
+            entries: BTreeMap::new(),
+            expiration: Duration::from_secs(expiration_in_secs),
+            in_cache_since_min: time_env.now_instant(),
+            max_entries: None,
+            time_env: time_env,
+        }
+    }
+
+    /// Bounds the number of entries in the cache, or removes the bound if
+    /// max_entries is None. Excess non-permanent entries are evicted in least
+    /// recently used order.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+
+        if let Some(max_entries) = max_entries {
+            while self.entries.len() > max_entries && self.evict_lru() {}
+        }
+    }
+
+    /// Returns the maximum number of entries in the cache, if bounded.
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Lookup the ethernet address for an IPv4 address.
+    pub fn eth_addr_for_ip(&mut self, ipv4_addr: Ipv4Address) -> Option<EthernetAddress> {
+        self.expire_eth_addr();
+
+        let now = self.time_env.now_instant();
+
+        match self.entries.get_mut(&ipv4_addr) {
+            Some(entry) => {
+                entry.used_at = now;
+                Some(entry.eth_addr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Create or update the ethernet address mapping for an IPv4 address.
+    ///
+    /// Permanent mappings for the IPv4 address are left untouched.
+    pub fn set_eth_addr_for_ip(&mut self, ipv4_addr: Ipv4Address, eth_addr: EthernetAddress) {
+        self.expire_eth_addr();
+
+        if let Some(entry) = self.entries.get(&ipv4_addr) {
+            if entry.permanent {
+                return;
+            }
+        }
+
+        self.insert(ipv4_addr, eth_addr, false);
+    }
+
+    /// Create or update a permanent ethernet address mapping for an IPv4
+    /// address which never expires.
+    pub fn set_permanent_eth_addr_for_ip(
+        &mut self,
+        ipv4_addr: Ipv4Address,
+        eth_addr: EthernetAddress,
+    ) {
+        self.expire_eth_addr();
+        self.insert(ipv4_addr, eth_addr, true);
+    }
+
+    /// Removes the mapping for an IPv4 address, permanent or not, returning the
+    /// ethernet address if one existed.
+    pub fn remove(&mut self, ipv4_addr: Ipv4Address) -> Option<EthernetAddress> {
+        self.entries.remove(&ipv4_addr).map(|entry| entry.eth_addr)
+    }
+
+    /// Removes all non-permanent mappings.
+    pub fn flush(&mut self) {
+        self.entries.retain(|_, entry| entry.permanent);
+    }
+
+    /// Returns a snapshot of the mappings in the cache, ordered by IPv4
+    /// address.
+    pub fn dump(&mut self) -> Vec<ArpEntry> {
+        self.expire_eth_addr();
+
+        let now = self.time_env.now_instant();
+
+        let mut entries: Vec<_> = self
+            .entries
+            .iter()
+            .map(|(ipv4_addr, entry)| ArpEntry {
+                ipv4_addr: *ipv4_addr,
+                eth_addr: entry.eth_addr,
+                permanent: entry.permanent,
+                age: now.duration_since(entry.in_cache_since),
+            })
+            .collect();
+
+        entries.sort_by_key(|entry| entry.ipv4_addr.as_int());
+        entries
+    }
+
+    fn insert(&mut self, ipv4_addr: Ipv4Address, eth_addr: EthernetAddress, permanent: bool) {
+        if !self.entries.contains_key(&ipv4_addr) {
+            if let Some(max_entries) = self.max_entries {
+                if self.entries.len() >= max_entries && !self.evict_lru() && !permanent {
+                    debug!(
+                        "ARP cache is full, dropping mapping from {} to {}.",
+                        ipv4_addr, eth_addr
+                    );
+                    return;
+                }
+            }
+        }
+
+        let in_cache_since = self.time_env.now_instant();
+
+        if !permanent && !self.entries.values().any(|entry| !entry.permanent) {
+            self.in_cache_since_min = in_cache_since;
+        }
+
+        self.entries.insert(
+            ipv4_addr,
+            Entry {
+                eth_addr,
+                in_cache_since,
+                used_at: in_cache_since,
+                permanent,
+            },
+        );
+    }
+
+    /// Evicts the least recently used non-permanent entry, returning false if
+    /// there are none.
+    fn evict_lru(&mut self) -> bool {
+        let lru = self
+            .entries
+            .iter()
+            .filter(|&(_, entry)| !entry.permanent)
+            .min_by_key(|&(_, entry)| entry.used_at)
+            .map(|(ipv4_addr, _)| *ipv4_addr);
+
+        match lru {
+            Some(ipv4_addr) => {
+                debug!("Evicting {} from the ARP cache.", ipv4_addr);
+                self.entries.remove(&ipv4_addr);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Purge Ethernet address entries translations that have expired.
+    fn expire_eth_addr(&mut self) {
+        let now = self.time_env.now_instant();
+
+        if now > self.in_cache_since_min + self.expiration {
+            // Purge expired entries...
+            let expiration = self.expiration;
+            self.entries.retain(|_, entry| {
+                entry.permanent || now.duration_since(entry.in_cache_since) <= expiration
+            });
+
+            // Update timestamp of the oldest entry...
+            let in_cache_since = self
+                .entries
+                .iter()
+                .filter(|&(_, entry)| !entry.permanent)
+                .map(|(_, entry)| entry.in_cache_since);
+            self.in_cache_since_min = match in_cache_since.min() {
+                Some(in_cache_since) => in_cache_since,
+                None => now,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn time_env(&mut self) -> &mut T {
+        &mut self.time_env
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    fn arp_cache() -> ArpCache<MockEnv> {
+        ArpCache::new(60, MockEnv::new())
+    }
+
+    fn ipv4(i: u8) -> Ipv4Address {
+        Ipv4Address::new([0, 0, 0, i])
+    }
+
+    fn eth(i: u8) -> EthernetAddress {
+        EthernetAddress::new([0, 0, 0, 0, 0, i])
+    }
+
+    #[test]
+    fn test_lookup_ip_with_no_mapping() {
+        let mut arp_cache = arp_cache();
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+    }
+
+    #[test]
+    fn test_lookup_ip_with_mapping() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(0));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.time_env().now += Duration::from_secs(60);
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+    }
+
+    #[test]
+    fn test_lookup_ip_after_expiring() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(0));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.time_env().now += Duration::from_secs(61);
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+    }
+
+    #[test]
+    fn test_push_back_expiration() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(0));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.time_env().now += Duration::from_secs(60);
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(0));
+        arp_cache.time_env().now += Duration::from_secs(60);
+
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.time_env().now += Duration::from_secs(1);
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+    }
+
+    #[test]
+    fn test_chained_expiration() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(0));
+        arp_cache.time_env().now += Duration::from_secs(30);
+        arp_cache.set_eth_addr_for_ip(ipv4(1), eth(1));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(1)).unwrap(), eth(1));
+
+        arp_cache.time_env().now += Duration::from_secs(31);
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(1)).unwrap(), eth(1));
+
+        arp_cache.time_env().now += Duration::from_secs(30);
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(1)), None);
+    }
+
+    #[test]
+    fn test_permanent_entries() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_permanent_eth_addr_for_ip(ipv4(0), eth(0));
+        arp_cache.set_eth_addr_for_ip(ipv4(0), eth(1));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.time_env().now += Duration::from_secs(61);
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        arp_cache.flush();
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+
+        assert_eq!(arp_cache.remove(ipv4(0)).unwrap(), eth(0));
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(0)), None);
+    }
+
+    #[test]
+    fn test_dump() {
+        let mut arp_cache = arp_cache();
+
+        arp_cache.set_eth_addr_for_ip(ipv4(2), eth(2));
+        arp_cache.time_env().now += Duration::from_secs(10);
+        arp_cache.set_permanent_eth_addr_for_ip(ipv4(1), eth(1));
+
+        assert_eq!(
+            arp_cache.dump(),
+            vec![
+                ArpEntry {
+                    ipv4_addr: ipv4(1),
+                    eth_addr: eth(1),
+                    permanent: true,
+                    age: Duration::from_secs(0),
+                },
+                ArpEntry {
+                    ipv4_addr: ipv4(2),
+                    eth_addr: eth(2),
+                    permanent: false,
+                    age: Duration::from_secs(10),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut arp_cache = arp_cache();
+        arp_cache.set_max_entries(Some(3));
+
+        arp_cache.set_permanent_eth_addr_for_ip(ipv4(0), eth(0));
+        arp_cache.set_eth_addr_for_ip(ipv4(1), eth(1));
+        arp_cache.time_env().now += Duration::from_secs(1);
+        arp_cache.set_eth_addr_for_ip(ipv4(2), eth(2));
+        arp_cache.time_env().now += Duration::from_secs(1);
+
+        // Use the older entry so the newer one is evicted.
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(1)).unwrap(), eth(1));
+        arp_cache.set_eth_addr_for_ip(ipv4(3), eth(3));
+
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(0)).unwrap(), eth(0));
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(1)).unwrap(), eth(1));
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(2)), None);
+        assert_eq!(arp_cache.eth_addr_for_ip(ipv4(3)).unwrap(), eth(3));
+
+        arp_cache.set_max_entries(Some(1));
+        assert_eq!(arp_cache.dump().len(), 1);
+        arp_cache.set_eth_addr_for_ip(ipv4(4), eth(4));
+        assert_matches!(arp_cache.eth_addr_for_ip(ipv4(4)), None);
+    }
+}
@@ -0,0 +1,252 @@
+//! Forwarding of Ethernet frames between devices at layer 2.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use stack::dev::Device;
+use stack::repr::{
+    EthernetAddress,
+    EthernetFrame,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+};
+use Error;
+
+/// One of the two devices connected by a bridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Port {
+    A,
+    B,
+}
+
+impl Port {
+    /// Returns the port on the other side of the bridge.
+    pub fn other(self) -> Port {
+        match self {
+            Port::A => Port::B,
+            Port::B => Port::A,
+        }
+    }
+}
+
+/// Maintains an expiring set of Ethernet address -> port mappings, learned
+/// from the source addresses of frames arriving on each port.
+pub struct MacTable<T = DefaultEnv>
+where
+    T: Env,
+{
+    entries: BTreeMap<EthernetAddress, (Port, Instant)>,
+    aging: Duration,
+    time_env: T,
+}
+
+impl<T: Env> MacTable<T> {
+    /// Creates a MAC table where mappings expire after aging_in_secs seconds
+    /// without a frame from the address.
+    pub fn new(aging_in_secs: u64, time_env: T) -> MacTable<T> {
+        MacTable {
+            entries: BTreeMap::new(),
+            aging: Duration::from_secs(aging_in_secs),
+            time_env,
+        }
+    }
+
+    /// Records that frames from an address arrived on a port, e.g. after a
+    /// host moves between ports.
+    pub fn learn(&mut self, eth_addr: EthernetAddress, port: Port) {
+        let now = self.time_env.now_instant();
+        self.entries.insert(eth_addr, (port, now));
+    }
+
+    /// Returns the port an address was last seen on, if the mapping has not
+    /// expired.
+    pub fn lookup(&self, eth_addr: EthernetAddress) -> Option<Port> {
+        let now = self.time_env.now_instant();
+
+        match self.entries.get(&eth_addr) {
+            Some(&(port, learned_at)) if now.duration_since(learned_at) < self.aging => Some(port),
+            _ => None,
+        }
+    }
+
+    /// Removes expired mappings and returns the number of mappings remaining.
+    pub fn expire(&mut self) -> usize {
+        let (now, aging) = (self.time_env.now_instant(), self.aging);
+        self.entries
+            .retain(|_, &mut (_, learned_at)| now.duration_since(learned_at) < aging);
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    fn time_env(&mut self) -> &mut T {
+        &mut self.time_env
+    }
+}
+
+/// A learning bridge which forwards Ethernet frames between two devices, e.g.
+/// to connect a ChannelDevice topology to a TAP.
+///
+/// Frames to addresses last seen on the port they arrived on are filtered,
+/// and all other frames are forwarded to the other port.
+pub struct Bridge<T = DefaultEnv>
+where
+    T: Env,
+{
+    a: Box<dyn Device>,
+    b: Box<dyn Device>,
+    mac_table: MacTable<T>,
+    buffer: Vec<u8>,
+}
+
+impl<T: Env> Bridge<T> {
+    /// Creates a bridge between two devices.
+    pub fn new(a: Box<dyn Device>, b: Box<dyn Device>, mac_table: MacTable<T>) -> Bridge<T> {
+        let buffer_len = a.max_transmission_unit().max(b.max_transmission_unit())
+            + EthernetFrame::<&[u8]>::HEADER_LEN;
+
+        Bridge {
+            a,
+            b,
+            mac_table,
+            buffer: vec![0; buffer_len],
+        }
+    }
+
+    /// Returns the device connected to a port.
+    pub fn dev(&mut self, port: Port) -> &mut dyn Device {
+        match port {
+            Port::A => &mut *self.a,
+            Port::B => &mut *self.b,
+        }
+    }
+
+    /// Returns the MAC table of the bridge.
+    pub fn mac_table(&mut self) -> &mut MacTable<T> {
+        &mut self.mac_table
+    }
+
+    /// Forwards frames received by either device until both are exhausted,
+    /// returning the number of frames forwarded.
+    ///
+    /// Frames which the receiving device is too busy to send are dropped, as
+    /// a switch with full buffers would.
+    pub fn poll(&mut self) -> usize {
+        self.mac_table.expire();
+        self.forward(Port::A) + self.forward(Port::B)
+    }
+
+    fn forward(&mut self, port: Port) -> usize {
+        let Bridge {
+            ref mut a,
+            ref mut b,
+            ref mut mac_table,
+            ref mut buffer,
+        } = *self;
+
+        let (rx, tx) = match port {
+            Port::A => (a, b),
+            Port::B => (b, a),
+        };
+
+        let mut forwarded = 0;
+
+        loop {
+            let buffer_len = match rx.recv(&mut buffer[..]) {
+                Ok(buffer_len) => buffer_len,
+                Err(Error::Device(_)) => break,
+                Err(err) => {
+                    warn!("Error receiving Ethernet frame with {:?}.", err);
+                    break;
+                }
+            };
+
+            let (src_addr, dst_addr) = match EthernetFrame::try_new(&buffer[.. buffer_len]) {
+                Ok(eth_frame) => (eth_frame.src_addr(), eth_frame.dst_addr()),
+                Err(_) => continue,
+            };
+
+            if src_addr.is_unicast() {
+                mac_table.learn(src_addr, port);
+            }
+
+            if dst_addr.is_unicast() && mac_table.lookup(dst_addr) == Some(port) {
+                debug!(
+                    "Filtering Ethernet frame to {} on port {:?}.",
+                    dst_addr, port
+                );
+                continue;
+            }
+
+            match tx.send(&buffer[.. buffer_len]) {
+                Ok(_) => forwarded += 1,
+                Err(err) => debug!("Dropping Ethernet frame to {} with {:?}.", dst_addr, err),
+            }
+        }
+
+        forwarded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::dev::ChannelDevice;
+    use stack::time::MockEnv;
+
+    fn eth(i: u8) -> EthernetAddress {
+        EthernetAddress::new([0x06, 0, 0, 0, 0, i])
+    }
+
+    fn frame(src_addr: EthernetAddress, dst_addr: EthernetAddress) -> Vec<u8> {
+        let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(4)];
+        {
+            let mut eth_frame = EthernetFrame::try_new(&mut buffer[..]).unwrap();
+            eth_frame.set_src_addr(src_addr);
+            eth_frame.set_dst_addr(dst_addr);
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_mac_table_aging() {
+        let mut mac_table = MacTable::new(60, MockEnv::new());
+        mac_table.learn(eth(1), Port::A);
+        assert_eq!(mac_table.lookup(eth(1)), Some(Port::A));
+        assert_eq!(mac_table.lookup(eth(2)), None);
+
+        mac_table.time_env().now += Duration::from_secs(30);
+        mac_table.learn(eth(2), Port::B);
+        mac_table.time_env().now += Duration::from_secs(30);
+        assert_eq!(mac_table.lookup(eth(1)), None);
+        assert_eq!(mac_table.lookup(eth(2)), Some(Port::B));
+        assert_eq!(mac_table.expire(), 1);
+    }
+
+    #[test]
+    fn test_bridge_learns_and_filters() {
+        let (a, mut host_a) = ChannelDevice::pair();
+        let (b, mut host_b) = ChannelDevice::pair();
+        let mut bridge = Bridge::new(Box::new(a), Box::new(b), MacTable::new(60, MockEnv::new()));
+
+        // Unknown and broadcast destinations are forwarded...
+        host_a.send(&frame(eth(1), eth(2))).unwrap();
+        host_a
+            .send(&frame(eth(1), EthernetAddress::BROADCAST))
+            .unwrap();
+        assert_eq!(bridge.poll(), 2);
+        assert_eq!(host_b.pending(), 2);
+        assert_eq!(bridge.mac_table().lookup(eth(1)), Some(Port::A));
+
+        // But not frames to hosts on the same port.
+        host_a.send(&frame(eth(3), eth(1))).unwrap();
+        host_b.send(&frame(eth(2), eth(1))).unwrap();
+        assert_eq!(bridge.poll(), 1);
+        assert_eq!(host_a.pending(), 1);
+        assert_eq!(host_b.pending(), 2);
+    }
+}
@@ -0,0 +1,275 @@
+//! Mirroring of Ethernet frames sent and received by an interface.
+
+use alloc::boxed::Box;
+use alloc::string::{
+    String,
+    ToString,
+};
+#[cfg(feature = "std")]
+use std::io::{
+    Result as IOResult,
+    Write,
+};
+#[cfg(feature = "std")]
+use std::time::{
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+#[cfg(feature = "std")]
+use byteorder::{
+    LittleEndian,
+    WriteBytesExt,
+};
+
+use stack::repr::{
+    eth_types,
+    ipv4_protocols,
+    Arp,
+    EthernetFrame,
+    Icmpv4Packet,
+    Ipv4Packet,
+    TcpPacket,
+    UdpPacket,
+};
+
+/// Whether a captured frame was sent or received by the interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Callback invoked with each captured frame.
+pub type CaptureHandler = Box<dyn FnMut(Direction, &[u8])>;
+
+/// A hook which mirrors frames to a callback while enabled, independently of
+/// the device an interface uses.
+pub struct Capture {
+    handler: CaptureHandler,
+    enabled: bool,
+}
+
+impl Capture {
+    /// Creates an enabled capture which mirrors frames to a callback.
+    pub fn new(handler: CaptureHandler) -> Capture {
+        Capture {
+            handler,
+            enabled: true,
+        }
+    }
+
+    /// Creates an enabled capture which writes frames to a pcap file, e.g. for
+    /// inspection with Wireshark or tcpdump.
+    #[cfg(feature = "std")]
+    pub fn pcap<W: 'static + Write>(writer: W) -> IOResult<Capture> {
+        let mut pcap_writer = PcapWriter::new(writer)?;
+
+        Ok(Capture::new(Box::new(move |_, frame: &[u8]| {
+            if let Err(err) = pcap_writer.write_frame(SystemTime::now(), frame) {
+                warn!("Error writing frame to pcap with {:?}.", err);
+            }
+        })))
+    }
+
+    /// Checks if frames are being mirrored.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Starts or stops mirroring frames, e.g. to only capture around an
+    /// operation being debugged.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Mirrors a frame to the callback if the capture is enabled.
+    pub fn capture(&mut self, direction: Direction, frame: &[u8]) {
+        if self.enabled {
+            (self.handler)(direction, frame);
+        }
+    }
+}
+
+/// Writes Ethernet frames in the [pcap](https://wiki.wireshark.org/Development/LibpcapFileFormat)
+/// file format.
+#[cfg(feature = "std")]
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> PcapWriter<W> {
+    /// Maximum number of bytes recorded per frame.
+    pub const SNAP_LEN: u32 = 65535;
+
+    /// Creates a writer, writing the pcap file header.
+    pub fn new(mut writer: W) -> IOResult<PcapWriter<W>> {
+        writer.write_u32::<LittleEndian>(0xA1B2_C3D4)?; // Magic
+        writer.write_u16::<LittleEndian>(2)?; // Major version
+        writer.write_u16::<LittleEndian>(4)?; // Minor version
+        writer.write_i32::<LittleEndian>(0)?; // UTC offset
+        writer.write_u32::<LittleEndian>(0)?; // Timestamp accuracy
+        writer.write_u32::<LittleEndian>(Self::SNAP_LEN)?;
+        writer.write_u32::<LittleEndian>(1)?; // Ethernet link type
+        writer.flush()?;
+
+        Ok(PcapWriter { writer })
+    }
+
+    /// Writes a frame captured at a time, flushing it so the file can be
+    /// followed live.
+    pub fn write_frame(&mut self, time: SystemTime, frame: &[u8]) -> IOResult<()> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let captured_len = frame.len().min(Self::SNAP_LEN as usize);
+
+        self.writer
+            .write_u32::<LittleEndian>(since_epoch.as_secs() as u32)?;
+        self.writer
+            .write_u32::<LittleEndian>(since_epoch.subsec_micros())?;
+        self.writer.write_u32::<LittleEndian>(captured_len as u32)?;
+        self.writer.write_u32::<LittleEndian>(frame.len() as u32)?;
+        self.writer.write_all(&frame[.. captured_len])?;
+        self.writer.flush()
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Decodes a frame into a tcpdump-like one-liner, with a summary of each
+/// layer that could be parsed separated by a colon.
+///
+/// Decoding stops at the first layer which is malformed or unsupported, so
+/// any frame may be described including those received from a link.
+pub fn describe_frame(frame: &[u8]) -> String {
+    let eth_frame = match EthernetFrame::try_new(frame) {
+        Ok(eth_frame) => eth_frame,
+        Err(_) => return format!("Ethernet malformed, length {}", frame.len()),
+    };
+
+    let mut description = eth_frame.to_string();
+
+    match eth_frame.payload_type() {
+        eth_types::ARP => {
+            if let Ok(arp) = Arp::deserialize(eth_frame.payload()) {
+                description += &format!(": {}", arp);
+            }
+        }
+        eth_types::IPV4 => {
+            let ipv4_packet = match Ipv4Packet::try_new(eth_frame.payload()) {
+                Ok(ipv4_packet) => ipv4_packet,
+                Err(_) => return description,
+            };
+
+            if ipv4_packet.check_encoding().is_err() {
+                return description;
+            }
+
+            description += &format!(": {}", ipv4_packet);
+
+            if ipv4_packet.fragment_offset() != 0 {
+                return description;
+            }
+
+            let payload = ipv4_packet.payload();
+            let transport = match ipv4_packet.protocol() {
+                ipv4_protocols::ICMP => Icmpv4Packet::try_new(payload).map(|p| p.to_string()),
+                ipv4_protocols::UDP => UdpPacket::try_new(payload).map(|p| p.to_string()),
+                ipv4_protocols::TCP => TcpPacket::try_new(payload).map(|p| p.to_string()),
+                _ => return description,
+            };
+
+            if let Ok(transport) = transport {
+                description += &format!(": {}", transport);
+            }
+        }
+        _ => {}
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+    use alloc::rc::Rc;
+    use core::time::Duration;
+
+    use stack::repr::{
+        arp::Op,
+        EthernetAddress,
+        Ipv4Address,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_capture_toggle() {
+        let frames = Rc::new(RefCell::new(vec![]));
+        let frames_clone = frames.clone();
+        let mut capture = Capture::new(Box::new(move |direction, frame: &[u8]| {
+            frames_clone.borrow_mut().push((direction, frame.to_vec()));
+        }));
+
+        capture.capture(Direction::Sent, &[1]);
+        capture.set_enabled(false);
+        capture.capture(Direction::Received, &[2]);
+        capture.set_enabled(true);
+        capture.capture(Direction::Received, &[3]);
+
+        assert_eq!(
+            *frames.borrow(),
+            vec![(Direction::Sent, vec![1]), (Direction::Received, vec![3])]
+        );
+    }
+
+    #[test]
+    fn test_pcap_writer() {
+        let mut pcap_writer = PcapWriter::new(vec![]).unwrap();
+        let time = UNIX_EPOCH + Duration::new(0x0102_0304, 5_000);
+        pcap_writer.write_frame(time, &[0xAA, 0xBB]).unwrap();
+
+        assert_eq!(
+            pcap_writer.into_inner(),
+            vec![
+                0xD4, 0xC3, 0xB2, 0xA1, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0xFF, 0xFF, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, // Header
+                0x04, 0x03, 0x02, 0x01, 0x05, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00,
+                0x00, 0x00, 0xAA, 0xBB, // Frame
+            ]
+        );
+    }
+
+    #[test]
+    fn test_describe_arp_frame() {
+        let arp = Arp {
+            op: Op::Request,
+            source_hw_addr: EthernetAddress::new([0, 1, 2, 3, 4, 5]),
+            source_proto_addr: Ipv4Address::new([10, 0, 0, 1]),
+            target_hw_addr: EthernetAddress::new([0; 6]),
+            target_proto_addr: Ipv4Address::new([10, 0, 0, 2]),
+        };
+        let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(arp.buffer_len())];
+        {
+            let mut eth_frame = EthernetFrame::try_new(&mut buffer[..]).unwrap();
+            eth_frame.set_src_addr(arp.source_hw_addr);
+            eth_frame.set_dst_addr(EthernetAddress::BROADCAST);
+            eth_frame.set_payload_type(eth_types::ARP);
+            arp.serialize(eth_frame.payload_mut()).unwrap();
+        }
+
+        assert_eq!(
+            describe_frame(&buffer),
+            "Ethernet 00:01:02:03:04:05 > FF:FF:FF:FF:FF:FF, type 0x0806, length 42: \
+             ARP who-has 10.0.0.2 tell 10.0.0.1"
+        );
+    }
+
+    #[test]
+    fn test_describe_malformed_frame() {
+        assert_eq!(describe_frame(&[0; 4]), "Ethernet malformed, length 4");
+    }
+}
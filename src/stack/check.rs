@@ -0,0 +1,231 @@
+//! Compute internet checksums.
+
+use core::iter::Cloned;
+use core::slice::Iter as SliceIter;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+/// An iterator that inteprets a sequence of bytes as a sequence of network
+/// byte order u16's.
+pub struct ByteOrderIter<I: Iterator<Item = u8>> {
+    iter: I,
+}
+
+impl<I: Iterator<Item = u8>> From<I> for ByteOrderIter<I> {
+    fn from(iter: I) -> Self {
+        ByteOrderIter { iter }
+    }
+}
+
+impl<'a> From<&'a [u8]> for ByteOrderIter<Cloned<SliceIter<'a, u8>>> {
+    fn from(slice: &'a [u8]) -> Self {
+        ByteOrderIter {
+            iter: slice.iter().cloned(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for ByteOrderIter<I> {
+    type Item = u16;
+
+    fn next(&mut self) -> Option<u16> {
+        match self.iter.next() {
+            None => None,
+            Some(x) => {
+                if let Some(y) = self.iter.next() {
+                    let buffer = [x, y];
+                    Some(NetworkEndian::read_u16(&buffer[..]))
+                } else {
+                    Some((x as u16) << 8)
+                }
+            }
+        }
+    }
+}
+
+/// Calculates the Internet Checksum from [RFC1071](https://tools.ietf.org/html/rfc107).
+///
+/// Prefer internet_checksum_slices(...) for data which is already in slices,
+/// which sums several bytes at a time rather than one u16 at a time.
+pub fn internet_checksum<T, I>(iterable: T) -> u16
+where
+    T: Into<ByteOrderIter<I>>,
+    I: Iterator<Item = u8>,
+{
+    let mut iter = iterable.into();
+    let mut acc: u64 = 0;
+
+    while let Some(i) = iter.next() {
+        acc += i as u64;
+    }
+
+    !fold(acc)
+}
+
+/// Calculates the Internet Checksum of several slices as if they were
+/// concatenated, e.g. a pseudo-header followed by a packet.
+pub fn internet_checksum_slices(slices: &[&[u8]]) -> u16 {
+    let mut acc: u64 = 0;
+    let mut offset = 0;
+
+    for slice in slices {
+        let sum = fold(sum_words(slice));
+        // A slice starting at an odd offset has its bytes in the opposite half
+        // of each u16, and byte swapping commutes with one's complement sums.
+        acc += if offset % 2 == 0 {
+            sum as u64
+        } else {
+            sum.swap_bytes() as u64
+        };
+        offset += slice.len();
+    }
+
+    !fold(acc)
+}
+
+/// Updates a checksum after a u16 it covers changes from old to new, without
+/// recomputing the checksum over all of the data, see RFC 1624.
+pub fn update_checksum(checksum: u16, old: u16, new: u16) -> u16 {
+    let acc = (!checksum) as u64 + (!old) as u64 + new as u64;
+    !fold(acc)
+}
+
+/// Updates a checksum after bytes it covers change from old_bytes to
+/// new_bytes, see RFC 1624. The bytes must start at an even offset within the
+/// checksummed data, e.g. a whole field of a header.
+///
+/// Panics if old_bytes and new_bytes differ in length.
+pub fn checksum_adjust(checksum: u16, old_bytes: &[u8], new_bytes: &[u8]) -> u16 {
+    assert_eq!(old_bytes.len(), new_bytes.len());
+    let acc = (!checksum) as u64 + (!fold(sum_words(old_bytes))) as u64 + sum_words(new_bytes);
+    !fold(acc)
+}
+
+/// Sums the bytes of data as network byte order u16's, padding data of odd
+/// length with a zero byte.
+///
+/// Bytes are summed as u32's, which folds to the same one's complement sum.
+fn sum_words(data: &[u8]) -> u64 {
+    let mut acc: u64 = 0;
+    let mut chunks = data.chunks_exact(4);
+
+    for chunk in &mut chunks {
+        acc += NetworkEndian::read_u32(chunk) as u64;
+    }
+
+    for word in chunks.remainder().chunks(2) {
+        acc += match *word {
+            [x, y] => ((x as u64) << 8) | y as u64,
+            [x] => (x as u64) << 8,
+            _ => 0,
+        };
+    }
+
+    acc
+}
+
+/// Folds a sum into a u16 by adding carries back in.
+fn fold(mut acc: u64) -> u16 {
+    while acc > 0xFFFF {
+        acc = (acc & 0xFFFF) + (acc >> 16);
+    }
+
+    acc as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_order_iter_empty() {
+        let buffer: [u8; 0] = [];
+        let mut iter = ByteOrderIter::from(&buffer[..]);
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_byte_order_iter_even() {
+        let buffer: [u8; 6] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        let mut iter = ByteOrderIter::from(&buffer[..]);
+        assert_matches!(iter.next(), Some(258));
+        assert_matches!(iter.next(), Some(772));
+        assert_matches!(iter.next(), Some(1286));
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_byte_order_iter_odd() {
+        let buffer: [u8; 5] = [0x01, 0x02, 0x03, 0x04, 0x05];
+        let mut iter = ByteOrderIter::from(&buffer[..]);
+        assert_matches!(iter.next(), Some(258));
+        assert_matches!(iter.next(), Some(772));
+        assert_matches!(iter.next(), Some(1280));
+        assert_matches!(iter.next(), None);
+    }
+
+    #[test]
+    fn test_internet_checksum() {
+        let buffer: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        let iter = ByteOrderIter::from(&buffer[..]);
+        assert_eq!(0xB861, internet_checksum(iter));
+    }
+
+    #[test]
+    fn test_internet_checksum_slices() {
+        let buffer: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        assert_eq!(0xB861, internet_checksum_slices(&[&buffer[..]]));
+        assert_eq!(
+            0xB861,
+            internet_checksum_slices(&[&buffer[.. 3], &buffer[3 .. 10], &buffer[10 ..]])
+        );
+
+        let odd: Vec<u8> = (0 .. 37).map(|i| (i * 97) as u8).collect();
+        assert_eq!(
+            internet_checksum(&odd[..]),
+            internet_checksum_slices(&[&odd[.. 5], &odd[5 ..]])
+        );
+    }
+
+    #[test]
+    fn test_update_checksum() {
+        let mut buffer: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xB8, 0x61, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        // Decrement the TTL, which shares a u16 with the protocol.
+        buffer[8] = 0x3F;
+        let checksum = update_checksum(0xB861, 0x4011, 0x3F11);
+        buffer[10] = 0;
+        buffer[11] = 0;
+        assert_eq!(checksum, internet_checksum_slices(&[&buffer[..]]));
+    }
+
+    #[test]
+    fn test_checksum_adjust() {
+        let mut buffer: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x73, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0xB8, 0x61, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        // Rewrite the source address, as NAT would.
+        let checksum = checksum_adjust(0xB861, &buffer[12 .. 16], &[10, 0, 0, 1]);
+        buffer[12 .. 16].copy_from_slice(&[10, 0, 0, 1]);
+        buffer[10] = 0;
+        buffer[11] = 0;
+        assert_eq!(checksum, internet_checksum_slices(&[&buffer[..]]));
+
+        // Odd length fields are padded like the checksummed data.
+        let data: [u8; 3] = [0x12, 0x34, 0x56];
+        let checksum = checksum_adjust(internet_checksum_slices(&[&data[..]]), &data[2 ..], &[0x78]);
+        assert_eq!(checksum, internet_checksum_slices(&[&[0x12, 0x34, 0x78]]));
+    }
+}
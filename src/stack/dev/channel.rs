@@ -0,0 +1,432 @@
+use std::collections::VecDeque;
+use std::io::{
+    Error as IOError,
+    ErrorKind as IOErrorKind,
+};
+use std::sync::{
+    Arc,
+    Condvar,
+    Mutex,
+};
+use std::time::Duration;
+
+use rand::{
+    Rng,
+    SeedableRng,
+    XorShiftRng,
+};
+
+use stack::dev::{
+    Device,
+    DeviceCapabilities,
+};
+use stack::repr::{
+    eth_types,
+    ipv4_flags,
+    EthernetFrame,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpPacket,
+    UdpPacket,
+    UdpRepr,
+};
+use stack::time::{
+    Env,
+    Instant,
+    SystemEnv,
+};
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// Impairments applied to frames sent over a ChannelDevice.
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelConfig {
+    /// MTU reported by the devices, frames with larger payloads are rejected.
+    pub mtu: usize,
+    /// Probability in [0, 1] of a frame being dropped.
+    pub loss: f64,
+    /// Probability in [0, 1] of a frame being delivered before the frame sent
+    /// prior to it.
+    pub reorder: f64,
+    /// Time a frame spends in flight before it can be received.
+    pub latency: Duration,
+    /// Seed for choosing which frames are dropped or reordered, so runs with
+    /// the same seed impair the same frames.
+    pub seed: [u32; 4],
+    /// Offloads reported by the devices. Checksums the devices claim to
+    /// offload are filled in as frames are sent, like a NIC would.
+    pub capabilities: DeviceCapabilities,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> ChannelConfig {
+        ChannelConfig {
+            mtu: 1500,
+            loss: 0.0,
+            reorder: 0.0,
+            latency: Duration::from_secs(0),
+            seed: [0x193A_6754, 0xA8A7_D469, 0x9783_0E05, 0x113B_A7BB],
+            capabilities: DeviceCapabilities::default(),
+        }
+    }
+}
+
+struct Frame {
+    deliver_at: Instant,
+    buffer: Vec<u8>,
+}
+
+/// Frames in flight to a device, and a condition signalled when frames are
+/// added.
+type Queue = Arc<(Mutex<VecDeque<Frame>>, Condvar)>;
+
+/// An in-memory device connected to a peer device, which exchange frames
+/// through queues rather than a link.
+///
+/// Devices may be moved to different threads, allowing two full stacks to
+/// run against each other without root or TAP access.
+pub struct ChannelDevice<T = SystemEnv>
+where
+    T: Env,
+{
+    tx: Queue,
+    rx: Queue,
+    config: ChannelConfig,
+    rng: XorShiftRng,
+    time_env: T,
+}
+
+impl ChannelDevice {
+    /// Creates a pair of connected devices which deliver frames immediately
+    /// and without loss.
+    pub fn pair() -> (ChannelDevice, ChannelDevice) {
+        ChannelDevice::pair_with_config(ChannelConfig::default(), SystemEnv::new())
+    }
+}
+
+impl<T: Env + Clone> ChannelDevice<T> {
+    /// Creates a pair of connected devices which impair frames sent in either
+    /// direction according to config.
+    pub fn pair_with_config(
+        config: ChannelConfig,
+        time_env: T,
+    ) -> (ChannelDevice<T>, ChannelDevice<T>) {
+        let a_to_b: Queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+        let b_to_a: Queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+
+        // Impair each direction independently, but still deterministically.
+        let mut seed_b = config.seed;
+        seed_b[0] = !seed_b[0];
+
+        let a = ChannelDevice {
+            tx: a_to_b.clone(),
+            rx: b_to_a.clone(),
+            config,
+            rng: XorShiftRng::from_seed(config.seed),
+            time_env: time_env.clone(),
+        };
+
+        let b = ChannelDevice {
+            tx: b_to_a,
+            rx: a_to_b,
+            config,
+            rng: XorShiftRng::from_seed(seed_b),
+            time_env,
+        };
+
+        (a, b)
+    }
+}
+
+impl<T: Env> ChannelDevice<T> {
+    /// Returns the number of frames sent by the peer which have not been
+    /// received yet, including frames still in flight.
+    pub fn pending(&self) -> usize {
+        self.rx.0.lock().unwrap().len()
+    }
+}
+
+impl<T: Env> Device for ChannelDevice<T> {
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        if buffer.len() > self.config.mtu + EthernetFrame::<&[u8]>::HEADER_LEN {
+            return Err(Error::Device(DeviceError::Io(IOError::new(
+                IOErrorKind::InvalidInput,
+                "Frame exceeds MTU.",
+            ))));
+        }
+
+        if self.rng.gen::<f64>() < self.config.loss {
+            debug!("Dropping frame of {} bytes.", buffer.len());
+            return Ok(());
+        }
+
+        let reorder = self.rng.gen::<f64>() < self.config.reorder;
+        let mut frame = buffer.to_vec();
+        fill_checksums(&mut frame, &self.config.capabilities);
+
+        let mut tx = self.tx.0.lock().unwrap();
+        tx.push_back(Frame {
+            deliver_at: self.time_env.now_instant() + self.config.latency,
+            buffer: frame,
+        });
+
+        // Swap the frame with the one ahead, keeping delivery times in order.
+        let len = tx.len();
+        if reorder && len >= 2 {
+            debug!("Reordering frame of {} bytes.", buffer.len());
+            let last = tx.pop_back().unwrap();
+            let prior = tx.pop_back().unwrap();
+            tx.push_back(Frame {
+                deliver_at: prior.deliver_at,
+                buffer: last.buffer,
+            });
+            tx.push_back(Frame {
+                deliver_at: last.deliver_at,
+                buffer: prior.buffer,
+            });
+        }
+
+        self.tx.1.notify_all();
+        Ok(())
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        let mut rx = self.rx.0.lock().unwrap();
+
+        let ready = rx
+            .front()
+            .is_some_and(|frame| frame.deliver_at <= self.time_env.now_instant());
+
+        if !ready {
+            return Err(Error::Device(DeviceError::WouldBlock));
+        }
+
+        // Like reading from a socket, frames are truncated to fit the buffer.
+        let frame = rx.pop_front().unwrap();
+        let len = frame.buffer.len().min(buffer.len());
+        buffer[.. len].copy_from_slice(&frame.buffer[.. len]);
+
+        Ok(len)
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        let (ref queue, ref condvar) = *self.rx;
+        let rx = queue.lock().unwrap();
+
+        // Sleep until the next frame in flight arrives, else until one is sent.
+        let now = self.time_env.now_instant();
+        let timeout = match rx.front() {
+            Some(frame) if frame.deliver_at <= now => return Ok(()),
+            Some(frame) => {
+                let until_delivery = frame.deliver_at.duration_since(now);
+                Some(timeout.map_or(until_delivery, |timeout| timeout.min(until_delivery)))
+            }
+            None => timeout,
+        };
+
+        match timeout {
+            Some(timeout) => drop(condvar.wait_timeout(rx, timeout).unwrap()),
+            None => drop(condvar.wait(rx).unwrap()),
+        }
+
+        Ok(())
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.config.capabilities
+    }
+
+    fn max_transmission_unit(&self) -> usize {
+        self.config.mtu
+    }
+}
+
+/// Fills in the checksums of an Ethernet frame which a device offloads.
+fn fill_checksums(eth_buffer: &mut [u8], capabilities: &DeviceCapabilities) {
+    let checksum = capabilities.checksum;
+    if !(checksum.ipv4 || checksum.tcp || checksum.udp) {
+        return;
+    }
+
+    let mut eth_frame = match EthernetFrame::try_new(eth_buffer) {
+        Ok(ref eth_frame) if eth_frame.payload_type() != eth_types::IPV4 => return,
+        Ok(eth_frame) => eth_frame,
+        Err(_) => return,
+    };
+
+    let mut ipv4_packet = match Ipv4Packet::try_new(eth_frame.payload_mut()) {
+        Ok(ipv4_packet) => ipv4_packet,
+        Err(_) => return,
+    };
+
+    if checksum.ipv4 {
+        ipv4_packet.fill_checksum();
+    }
+
+    // Transport checksums are only offloaded for unfragmented packets.
+    if ipv4_packet.flags() & ipv4_flags::NOT_LAST != 0 || ipv4_packet.fragment_offset() != 0 {
+        return;
+    }
+
+    let ipv4_repr = match Ipv4Repr::deserialize(&ipv4_packet) {
+        Ok(ipv4_repr) => ipv4_repr,
+        Err(_) => return,
+    };
+
+    match ipv4_repr.protocol {
+        Ipv4Protocol::TCP if checksum.tcp => {
+            if let Ok(mut tcp_packet) = TcpPacket::try_new(ipv4_packet.payload_mut()) {
+                tcp_packet.fill_checksum(&ipv4_repr);
+            }
+        }
+        Ipv4Protocol::UDP if checksum.udp => {
+            if let Ok(mut udp_packet) = UdpPacket::try_new(ipv4_packet.payload_mut()) {
+                let udp_repr = UdpRepr {
+                    checksum: true,
+                    ..UdpRepr::deserialize(&udp_packet)
+                };
+                udp_repr.serialize(&mut udp_packet, &ipv4_repr);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use stack::time::MockEnv;
+
+    use super::*;
+
+    fn recv(dev: &mut ChannelDevice<MockEnv>) -> Result<Vec<u8>> {
+        let mut buffer = [0; 64];
+        let len = dev.recv(&mut buffer)?;
+        Ok(buffer[.. len].to_vec())
+    }
+
+    #[test]
+    fn test_send_and_recv() {
+        let (mut a, mut b) =
+            ChannelDevice::pair_with_config(ChannelConfig::default(), MockEnv::new());
+        a.send(&[1, 2, 3]).unwrap();
+        b.send(&[4, 5]).unwrap();
+        assert_eq!(a.pending(), 1);
+        assert_eq!(recv(&mut b).unwrap(), vec![1, 2, 3]);
+        assert_eq!(recv(&mut a).unwrap(), vec![4, 5]);
+        assert_matches!(recv(&mut a), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+    }
+
+    #[test]
+    fn test_send_exceeds_mtu() {
+        let config = ChannelConfig {
+            mtu: 4,
+            ..ChannelConfig::default()
+        };
+        let (mut a, b) = ChannelDevice::pair_with_config(config, MockEnv::new());
+        assert!(a.send(&[0; 18]).is_ok());
+        assert_matches!(a.send(&[0; 19]), Err(Error::Device(DeviceError::Io(_))));
+        assert_eq!(b.pending(), 1);
+    }
+
+    #[test]
+    fn test_loss() {
+        let config = ChannelConfig {
+            loss: 1.0,
+            ..ChannelConfig::default()
+        };
+        let (mut a, mut b) = ChannelDevice::pair_with_config(config, MockEnv::new());
+        a.send(&[1]).unwrap();
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+    }
+
+    #[test]
+    fn test_reorder() {
+        let config = ChannelConfig {
+            reorder: 1.0,
+            ..ChannelConfig::default()
+        };
+        let (mut a, mut b) = ChannelDevice::pair_with_config(config, MockEnv::new());
+        a.send(&[1]).unwrap();
+        a.send(&[2]).unwrap();
+        a.send(&[3]).unwrap();
+        assert_eq!(recv(&mut b).unwrap(), vec![2]);
+        assert_eq!(recv(&mut b).unwrap(), vec![3]);
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_latency() {
+        let config = ChannelConfig {
+            latency: Duration::from_millis(10),
+            ..ChannelConfig::default()
+        };
+        let (mut a, mut b) = ChannelDevice::pair_with_config(config, MockEnv::new());
+        a.send(&[1]).unwrap();
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+        b.time_env.now += Duration::from_millis(10);
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_wait_timeout() {
+        let (mut a, _b) = ChannelDevice::pair_with_config(ChannelConfig::default(), MockEnv::new());
+        let start = Instant::now();
+        a.wait(Some(Duration::from_millis(20))).unwrap();
+        assert!(Instant::now().duration_since(start) >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_wait_for_frame() {
+        let (mut a, mut b) = ChannelDevice::pair();
+        let sender = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            b.send(&[1]).unwrap();
+        });
+
+        a.wait(None).unwrap();
+        sender.join().unwrap();
+        let mut buffer = [0; 1];
+        assert_eq!(a.recv(&mut buffer).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_wait_frame_ready() {
+        let (mut a, mut b) =
+            ChannelDevice::pair_with_config(ChannelConfig::default(), MockEnv::new());
+        b.send(&[1]).unwrap();
+        let start = Instant::now();
+        a.wait(Some(Duration::from_secs(10))).unwrap();
+        assert!(Instant::now().duration_since(start) < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_same_seed_impairs_same_frames() {
+        let config = ChannelConfig {
+            loss: 0.5,
+            ..ChannelConfig::default()
+        };
+
+        let delivered = || {
+            let (mut a, mut b) = ChannelDevice::pair_with_config(config, MockEnv::new());
+            for i in 0 .. 32 {
+                a.send(&[i]).unwrap();
+            }
+            let mut frames = vec![];
+            while let Ok(frame) = recv(&mut b) {
+                frames.push(frame[0]);
+            }
+            frames
+        };
+
+        let frames = delivered();
+        assert!(!frames.is_empty() && frames.len() < 32);
+        assert_eq!(frames, delivered());
+    }
+}
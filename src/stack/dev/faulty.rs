@@ -0,0 +1,452 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use rand::{
+    Rng,
+    SeedableRng,
+    XorShiftRng,
+};
+
+use stack::dev::{
+    Device,
+    DeviceCapabilities,
+};
+use stack::repr::EthernetFrame;
+use stack::time::{
+    Env,
+    Instant,
+    SystemEnv,
+};
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// Longest a reordered frame waits for a frame to overtake it before it is
+/// delivered anyway.
+pub const REORDER_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// Faults injected into frames passing through a FaultyDevice.
+///
+/// Each probability is in [0, 1] and applied independently to every frame
+/// sent and received.
+#[derive(Clone, Copy, Debug)]
+pub struct FaultConfig {
+    /// Probability of a frame being dropped.
+    pub loss: f64,
+    /// Probability of a frame being delivered twice.
+    pub duplicate: f64,
+    /// Probability of a frame being delivered after the frame following it.
+    pub reorder: f64,
+    /// Probability of a random bit of a frame being flipped.
+    pub corrupt: f64,
+    /// Time a frame is held before it is delivered.
+    pub latency: Duration,
+    /// Seed for choosing which frames are faulted, so runs with the same
+    /// seed fault the same frames.
+    pub seed: [u32; 4],
+}
+
+impl Default for FaultConfig {
+    fn default() -> FaultConfig {
+        FaultConfig {
+            loss: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            corrupt: 0.0,
+            latency: Duration::from_secs(0),
+            seed: [0x5DEE_CE66, 0x0B0B_A5E1, 0x2545_F491, 0x9E37_79B9],
+        }
+    }
+}
+
+/// Counts of faults injected by a FaultyDevice.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub dropped: u64,
+    pub duplicated: u64,
+    pub reordered: u64,
+    pub corrupted: u64,
+}
+
+struct Frame {
+    deliver_at: Instant,
+    buffer: Vec<u8>,
+}
+
+/// Frames held by a FaultyDevice in one direction.
+struct Faults {
+    config: FaultConfig,
+    rng: XorShiftRng,
+    queue: VecDeque<Frame>,
+    /// A reordered frame waiting for the next frame to overtake it.
+    held: Option<Frame>,
+}
+
+impl Faults {
+    fn new(config: FaultConfig, seed: [u32; 4]) -> Faults {
+        Faults {
+            config,
+            rng: XorShiftRng::from_seed(seed),
+            queue: VecDeque::new(),
+            held: None,
+        }
+    }
+
+    /// Injects faults into a frame and queues what remains of it for delivery.
+    fn push(&mut self, buffer: &[u8], now: Instant, stats: &mut FaultStats) {
+        if self.rng.gen::<f64>() < self.config.loss {
+            debug!("Dropping frame of {} bytes.", buffer.len());
+            stats.dropped += 1;
+            return;
+        }
+
+        let mut frame = Frame {
+            deliver_at: now + self.config.latency,
+            buffer: buffer.to_vec(),
+        };
+
+        if !frame.buffer.is_empty() && self.rng.gen::<f64>() < self.config.corrupt {
+            let bit = self.rng.gen_range(0, frame.buffer.len() * 8);
+            debug!("Corrupting bit {} of frame of {} bytes.", bit, buffer.len());
+            frame.buffer[bit / 8] ^= 1 << (bit % 8);
+            stats.corrupted += 1;
+        }
+
+        let copies = if self.rng.gen::<f64>() < self.config.duplicate {
+            debug!("Duplicating frame of {} bytes.", buffer.len());
+            stats.duplicated += 1;
+            2
+        } else {
+            1
+        };
+
+        let reorder = self.held.is_none() && self.rng.gen::<f64>() < self.config.reorder;
+
+        for _ in 0 .. copies {
+            self.queue.push_back(Frame {
+                deliver_at: frame.deliver_at,
+                buffer: frame.buffer.clone(),
+            });
+        }
+
+        // Let this frame overtake the held one, keeping delivery times in order.
+        if let Some(mut held) = self.held.take() {
+            held.deliver_at = frame.deliver_at;
+            self.queue.push_back(held);
+        }
+
+        if reorder {
+            debug!("Reordering frame of {} bytes.", buffer.len());
+            stats.reordered += 1;
+            let mut held = self.queue.pop_back().unwrap();
+            held.deliver_at += REORDER_TIMEOUT;
+            self.held = Some(held);
+        }
+    }
+
+    /// Dequeues the next frame due for delivery.
+    fn pop(&mut self, now: Instant) -> Option<Vec<u8>> {
+        if self.held.as_ref().is_some_and(|held| held.deliver_at <= now) {
+            self.queue.push_back(self.held.take().unwrap());
+        }
+
+        match self.queue.front() {
+            Some(frame) if frame.deliver_at <= now => {}
+            _ => return None,
+        }
+
+        self.queue.pop_front().map(|frame| frame.buffer)
+    }
+
+    /// Puts back a frame which could not be delivered.
+    fn unpop(&mut self, buffer: Vec<u8>, now: Instant) {
+        self.queue.push_front(Frame {
+            deliver_at: now,
+            buffer,
+        });
+    }
+
+    /// Returns when the next frame is due for delivery.
+    fn poll_at(&self) -> Option<Instant> {
+        let queued = self.queue.front().map(|frame| frame.deliver_at);
+        let held = self.held.as_ref().map(|held| held.deliver_at);
+        match (queued, held) {
+            (Some(queued), Some(held)) => Some(queued.min(held)),
+            (queued, held) => queued.or(held),
+        }
+    }
+}
+
+/// A device which injects faults such as loss and corruption into frames
+/// sent and received via an inner device, e.g. to test TCP retransmission
+/// and IPv4 reassembly over a TAP.
+///
+/// Frames held back for latency or reordering are sent the next time the
+/// device is used, so it should be polled regularly like any other device.
+pub struct FaultyDevice<D, T = SystemEnv>
+where
+    D: Device,
+    T: Env,
+{
+    inner: D,
+    tx: Faults,
+    rx: Faults,
+    stats: FaultStats,
+    recv_buffer: Vec<u8>,
+    time_env: T,
+}
+
+impl<D: Device> FaultyDevice<D> {
+    /// Creates a device which faults frames according to config.
+    pub fn new(inner: D, config: FaultConfig) -> FaultyDevice<D> {
+        FaultyDevice::with_env(inner, config, SystemEnv::new())
+    }
+}
+
+impl<D: Device, T: Env> FaultyDevice<D, T> {
+    /// Creates a device which faults frames according to config, measuring
+    /// latency with time_env.
+    pub fn with_env(inner: D, config: FaultConfig, time_env: T) -> FaultyDevice<D, T> {
+        // Fault each direction independently, but still deterministically.
+        let mut rx_seed = config.seed;
+        rx_seed[0] = !rx_seed[0];
+
+        let recv_buffer_len = inner.max_transmission_unit() + EthernetFrame::<&[u8]>::HEADER_LEN;
+
+        FaultyDevice {
+            inner,
+            tx: Faults::new(config, config.seed),
+            rx: Faults::new(config, rx_seed),
+            stats: FaultStats::default(),
+            recv_buffer: vec![0; recv_buffer_len],
+            time_env,
+        }
+    }
+
+    /// Returns the device frames are faulted for.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Returns the device frames are faulted for.
+    pub fn inner_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Returns counts of the faults injected so far.
+    pub fn stats(&self) -> FaultStats {
+        self.stats
+    }
+
+    /// Sends frames which are due to the inner device, stopping at the first
+    /// frame it can not send right now.
+    fn flush(&mut self) -> Result<()> {
+        let now = self.time_env.now_instant();
+
+        while let Some(buffer) = self.tx.pop(now) {
+            match self.inner.send(&buffer) {
+                Ok(_) => {}
+                Err(Error::Device(DeviceError::WouldBlock)) => {
+                    self.tx.unpop(buffer, now);
+                    break;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<D: Device, T: Env> Device for FaultyDevice<D, T> {
+    fn send(&mut self, buffer: &[u8]) -> Result<()> {
+        let now = self.time_env.now_instant();
+        self.tx.push(buffer, now, &mut self.stats);
+        self.flush()
+    }
+
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if let Err(err) = self.flush() {
+            debug!("Error sending delayed frame with {:?}.", err);
+        }
+
+        let now = self.time_env.now_instant();
+        let recv_err = loop {
+            match self.inner.recv(&mut self.recv_buffer) {
+                Ok(len) => self.rx.push(&self.recv_buffer[.. len], now, &mut self.stats),
+                Err(err) => break err,
+            }
+        };
+
+        match self.rx.pop(now) {
+            Some(frame) => {
+                // Like reading from a socket, frames are truncated to fit the buffer.
+                let len = frame.len().min(buffer.len());
+                buffer[.. len].copy_from_slice(&frame[.. len]);
+                Ok(len)
+            }
+            None => Err(recv_err),
+        }
+    }
+
+    fn wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        // Wake up in time to deliver frames held back in either direction.
+        let now = self.time_env.now_instant();
+        let poll_at = match (self.tx.poll_at(), self.rx.poll_at()) {
+            (Some(tx), Some(rx)) => Some(tx.min(rx)),
+            (tx, rx) => tx.or(rx),
+        };
+
+        let timeout = match poll_at {
+            Some(poll_at) if poll_at <= now => return Ok(()),
+            Some(poll_at) => {
+                let until_due = poll_at.duration_since(now);
+                Some(timeout.map_or(until_due, |timeout| timeout.min(until_due)))
+            }
+            None => timeout,
+        };
+
+        self.inner.wait(timeout)
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.inner.capabilities()
+    }
+
+    fn max_transmission_unit(&self) -> usize {
+        self.inner.max_transmission_unit()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stack::dev::{
+        ChannelConfig,
+        ChannelDevice,
+    };
+    use stack::time::MockEnv;
+
+    use super::*;
+
+    fn faulty_pair(
+        config: FaultConfig,
+    ) -> (
+        FaultyDevice<ChannelDevice<MockEnv>, MockEnv>,
+        ChannelDevice<MockEnv>,
+    ) {
+        let (a, b) = ChannelDevice::pair_with_config(ChannelConfig::default(), MockEnv::new());
+        (FaultyDevice::with_env(a, config, MockEnv::new()), b)
+    }
+
+    fn recv<D: Device>(dev: &mut D) -> Result<Vec<u8>> {
+        let mut buffer = [0; 64];
+        let len = dev.recv(&mut buffer)?;
+        Ok(buffer[.. len].to_vec())
+    }
+
+    #[test]
+    fn test_no_faults() {
+        let (mut a, mut b) = faulty_pair(FaultConfig::default());
+        a.send(&[1, 2, 3]).unwrap();
+        b.send(&[4, 5]).unwrap();
+        assert_eq!(recv(&mut b).unwrap(), vec![1, 2, 3]);
+        assert_eq!(recv(&mut a).unwrap(), vec![4, 5]);
+        assert_matches!(recv(&mut a), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_eq!(a.stats(), FaultStats::default());
+    }
+
+    #[test]
+    fn test_loss() {
+        let config = FaultConfig {
+            loss: 1.0,
+            ..FaultConfig::default()
+        };
+        let (mut a, mut b) = faulty_pair(config);
+        a.send(&[1]).unwrap();
+        b.send(&[2]).unwrap();
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_matches!(recv(&mut a), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_eq!(a.stats().dropped, 2);
+    }
+
+    #[test]
+    fn test_duplicate() {
+        let config = FaultConfig {
+            duplicate: 1.0,
+            ..FaultConfig::default()
+        };
+        let (mut a, mut b) = faulty_pair(config);
+        a.send(&[1]).unwrap();
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_eq!(a.stats().duplicated, 1);
+    }
+
+    #[test]
+    fn test_corrupt() {
+        let config = FaultConfig {
+            corrupt: 1.0,
+            ..FaultConfig::default()
+        };
+        let (mut a, mut b) = faulty_pair(config);
+        let frame = [0xA5; 16];
+        a.send(&frame).unwrap();
+
+        let corrupted = recv(&mut b).unwrap();
+        let flipped: u32 = frame
+            .iter()
+            .zip(corrupted.iter())
+            .map(|(x, y)| (x ^ y).count_ones())
+            .sum();
+        assert_eq!(flipped, 1);
+        assert_eq!(a.stats().corrupted, 1);
+    }
+
+    #[test]
+    fn test_reorder() {
+        let config = FaultConfig {
+            reorder: 1.0,
+            ..FaultConfig::default()
+        };
+        let (mut a, mut b) = faulty_pair(config);
+        a.send(&[1]).unwrap();
+        a.send(&[2]).unwrap();
+        a.send(&[3]).unwrap();
+        assert_eq!(recv(&mut b).unwrap(), vec![2]);
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+
+        // The last frame is delivered even though no frame overtakes it.
+        a.time_env.now += REORDER_TIMEOUT;
+        a.recv(&mut [0; 64]).unwrap_err();
+        assert_eq!(recv(&mut b).unwrap(), vec![3]);
+        assert_eq!(a.stats().reordered, 2);
+    }
+
+    #[test]
+    fn test_latency() {
+        let config = FaultConfig {
+            latency: Duration::from_millis(10),
+            ..FaultConfig::default()
+        };
+        let (mut a, mut b) = faulty_pair(config);
+        a.send(&[1]).unwrap();
+        b.send(&[2]).unwrap();
+        assert_matches!(recv(&mut b), Err(Error::Device(DeviceError::WouldBlock)));
+        assert_matches!(recv(&mut a), Err(Error::Device(DeviceError::WouldBlock)));
+
+        // Frames held in either direction are delivered once due.
+        a.time_env.now += Duration::from_millis(10);
+        assert_eq!(recv(&mut a).unwrap(), vec![2]);
+        assert_eq!(recv(&mut b).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_send_error() {
+        let (mut a, _b) = faulty_pair(FaultConfig::default());
+        assert_matches!(a.send(&[0; 1515]), Err(Error::Device(DeviceError::Io(_))));
+    }
+}
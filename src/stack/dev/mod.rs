@@ -0,0 +1,184 @@
+//! Sending/receiving raw Ethernet frames.
+
+#[cfg(feature = "std")]
+pub mod channel;
+#[cfg(feature = "std")]
+pub mod faulty;
+
+#[cfg(feature = "std")]
+pub use self::channel::{
+    ChannelConfig,
+    ChannelDevice,
+};
+#[cfg(feature = "std")]
+pub use self::faulty::{
+    FaultConfig,
+    FaultStats,
+    FaultyDevice,
+};
+
+use core::time::Duration;
+#[cfg(all(feature = "std", unix))]
+use std::os::unix::io::RawFd;
+
+use Result;
+
+/// Checksums a device computes in hardware for frames it sends, leaving the
+/// corresponding checksum fields to be zeroed rather than filled in software.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChecksumCapabilities {
+    /// IPv4 header checksums.
+    pub ipv4: bool,
+    /// TCP checksums of unfragmented packets.
+    pub tcp: bool,
+    /// UDP checksums of unfragmented packets.
+    pub udp: bool,
+}
+
+/// Offloads supported by a device. Offloads such as TCP segmentation may be
+/// added in the future, so construct these with ..Default::default().
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DeviceCapabilities {
+    /// Checksums computed by the device.
+    pub checksum: ChecksumCapabilities,
+}
+
+/// A low level interface for sending frames.
+pub trait Device {
+    /// Sends a frame across the link.
+    fn send(&mut self, buffer: &[u8]) -> Result<()>;
+
+    /// Reads a frame from the link and returns the size of frame.
+    ///
+    /// The buffer should be at least max_transmission_unit() bytes long to
+    /// avoid errors.
+    fn recv(&mut self, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Sends several frames across the link, returning how many of the
+    /// leading frames were sent.
+    ///
+    /// An error is only returned if no frame could be sent. Devices such as
+    /// those with TX rings may override this to amortize syscalls, the default
+    /// implementation calls send() for each frame.
+    fn send_batch(&mut self, buffers: &[&[u8]]) -> Result<usize> {
+        for (i, buffer) in buffers.iter().enumerate() {
+            if let Err(err) = self.send(buffer) {
+                return if i == 0 { Err(err) } else { Ok(i) };
+            }
+        }
+
+        Ok(buffers.len())
+    }
+
+    /// Reads up to buffers.len() frames from the link, returning the number
+    /// of frames read and storing the size of each in lens.
+    ///
+    /// Like recv(), an error is returned if no frame could be read. Devices
+    /// such as those with RX rings may override this to amortize syscalls, the
+    /// default implementation calls recv() for each buffer.
+    fn recv_batch(&mut self, buffers: &mut [&mut [u8]], lens: &mut [usize]) -> Result<usize> {
+        for (i, (buffer, len)) in buffers.iter_mut().zip(lens.iter_mut()).enumerate() {
+            match self.recv(buffer) {
+                Ok(buffer_len) => *len = buffer_len,
+                Err(err) => return if i == 0 { Err(err) } else { Ok(i) },
+            }
+        }
+
+        Ok(buffers.len().min(lens.len()))
+    }
+
+    /// Blocks until a frame may be ready to recv(), or the timeout elapses if
+    /// one is provided.
+    ///
+    /// Wake ups may be spurious, so callers should be prepared for recv() to
+    /// find no frames. The default implementation returns immediately for
+    /// devices which can not wait.
+    fn wait(&mut self, _timeout: Option<Duration>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns a file descriptor which becomes readable when frames arrive,
+    /// if the device has one, for registering with external event loops such
+    /// as epoll or mio.
+    ///
+    /// The descriptor remains owned by the device and must not be read from
+    /// or closed directly.
+    #[cfg(all(feature = "std", unix))]
+    fn raw_fd(&self) -> Option<RawFd> {
+        None
+    }
+
+    /// Returns the offloads supported by the device. The default
+    /// implementation reports none, so all checksums are computed in software.
+    fn capabilities(&self) -> DeviceCapabilities {
+        DeviceCapabilities::default()
+    }
+
+    /// Returns the [MTU](https://en.wikipedia.org/wiki/Maximum_transmission_unit)
+    /// of the link.
+    fn max_transmission_unit(&self) -> usize;
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::collections::VecDeque;
+
+    use super::*;
+    use {
+        DeviceError,
+        Error,
+    };
+
+    /// A device which echoes frames back, accepting at most two at a time.
+    struct Loopback {
+        frames: VecDeque<Vec<u8>>,
+    }
+
+    impl Device for Loopback {
+        fn send(&mut self, buffer: &[u8]) -> Result<()> {
+            if self.frames.len() >= 2 {
+                return Err(Error::Device(DeviceError::WouldBlock));
+            }
+            self.frames.push_back(buffer.to_vec());
+            Ok(())
+        }
+
+        fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+            let frame = self
+                .frames
+                .pop_front()
+                .ok_or(Error::Device(DeviceError::WouldBlock))?;
+            buffer[.. frame.len()].copy_from_slice(&frame);
+            Ok(frame.len())
+        }
+
+        fn max_transmission_unit(&self) -> usize {
+            1500
+        }
+    }
+
+    #[test]
+    fn test_default_batches() {
+        let mut dev = Loopback {
+            frames: VecDeque::new(),
+        };
+        assert_eq!(dev.send_batch(&[&[1], &[2, 2], &[3]]).unwrap(), 2);
+        assert_matches!(
+            dev.send_batch(&[&[3]]),
+            Err(Error::Device(DeviceError::WouldBlock))
+        );
+
+        let mut buffer = [0; 12];
+        let mut lens = [0; 3];
+        {
+            let mut buffers: Vec<&mut [u8]> = buffer.chunks_mut(4).collect();
+            assert_eq!(dev.recv_batch(&mut buffers, &mut lens).unwrap(), 2);
+            assert_matches!(
+                dev.recv_batch(&mut buffers, &mut lens),
+                Err(Error::Device(DeviceError::WouldBlock))
+            );
+        }
+        assert_eq!(&lens[.. 2], &[1, 2]);
+        assert_eq!(&buffer[.. 6], &[1, 0, 0, 0, 2, 2]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Keyed hashing of flows and connections.
+//!
+//! std's DefaultHasher is not available without the std feature, so the
+//! stack brings its own SipHash, which is also what DefaultHasher uses.
+
+use core::hash::Hasher;
+
+use byteorder::{
+    ByteOrder,
+    LittleEndian,
+};
+
+/// A [SipHash-2-4](https://www.aumasson.jp/siphash/siphash.pdf) hasher.
+///
+/// Hashes are hard to predict without the key, so the hasher is suitable for
+/// hashing data chosen by remote hosts, e.g. for TCP initial sequence
+/// numbers.
+#[derive(Clone, Debug)]
+pub struct SipHasher {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    // Bytes written since the last full word, little endian.
+    tail: u64,
+    tail_len: usize,
+    len: usize,
+}
+
+impl Default for SipHasher {
+    fn default() -> SipHasher {
+        SipHasher::new()
+    }
+}
+
+impl SipHasher {
+    /// Creates a hasher with a zero key.
+    pub fn new() -> SipHasher {
+        SipHasher::with_keys(0, 0)
+    }
+
+    /// Creates a hasher with a 128 bit key.
+    pub fn with_key(key: &[u8; 16]) -> SipHasher {
+        SipHasher::with_keys(
+            LittleEndian::read_u64(&key[0 .. 8]),
+            LittleEndian::read_u64(&key[8 .. 16]),
+        )
+    }
+
+    /// Creates a hasher with a 128 bit key split into two words.
+    pub fn with_keys(k0: u64, k1: u64) -> SipHasher {
+        SipHasher {
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            tail_len: 0,
+            len: 0,
+        }
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+}
+
+impl Hasher for SipHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.tail |= u64::from(byte) << (8 * self.tail_len);
+            self.tail_len += 1;
+
+            if self.tail_len == 8 {
+                let word = self.tail;
+                self.compress(word);
+                self.tail = 0;
+                self.tail_len = 0;
+            }
+        }
+
+        self.len += bytes.len();
+    }
+
+    fn finish(&self) -> u64 {
+        let mut hasher = self.clone();
+        hasher.compress(((self.len as u64) << 56) | self.tail);
+
+        hasher.v2 ^= 0xFF;
+        for _ in 0 .. 4 {
+            hasher.round();
+        }
+
+        hasher.v0 ^ hasher.v1 ^ hasher.v2 ^ hasher.v3
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_siphash_reference_vector() {
+        // Appendix A of the SipHash paper.
+        let mut key = [0; 16];
+        for (i, byte) in key.iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let message: Vec<u8> = (0 .. 15).collect();
+
+        let mut hasher = SipHasher::with_key(&key);
+        hasher.write(&message);
+        assert_eq!(hasher.finish(), 0xa129_ca61_49be_45e5);
+    }
+
+    #[test]
+    fn test_siphash_split_writes() {
+        let mut hasher = SipHasher::with_keys(1, 2);
+        hasher.write(b"hello, world");
+
+        let mut split_hasher = SipHasher::with_keys(1, 2);
+        split_hasher.write(b"hello");
+        split_hasher.write(b", world");
+        assert_eq!(hasher.finish(), split_hasher.finish());
+
+        assert_ne!(hasher.finish(), SipHasher::new().finish());
+    }
+}
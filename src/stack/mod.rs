@@ -1,8 +1,13 @@
 //! Core, platform independent networking code.
 
 pub mod arp_cache;
+pub mod bridge;
+pub mod capture;
 pub mod check;
 pub mod dev;
+pub mod hash;
+pub mod neighbor_cache;
+pub mod random;
 pub mod repr;
 pub mod service;
 pub mod socket;
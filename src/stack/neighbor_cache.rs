@@ -0,0 +1,107 @@
+//! Management and caching of Ethernet/IPv6 address mappings learned via
+//! Neighbor Discovery.
+
+use alloc::collections::BTreeMap;
+use core::time::Duration;
+
+use stack::repr::{
+    EthernetAddress,
+    Ipv6Address,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+};
+
+struct Entry {
+    eth_addr: EthernetAddress,
+    in_cache_since: Instant,
+}
+
+/// Maintains an expiring set of IPv6 -> ethernet address mappings, the IPv6
+/// analogue of an ArpCache.
+pub struct NeighborCache<T = DefaultEnv>
+where
+    T: Env,
+{
+    entries: BTreeMap<Ipv6Address, Entry>,
+    expiration: Duration,
+    time_env: T,
+}
+
+impl<T: Env> NeighborCache<T> {
+    /// Creates a neighbor cache where ethernet address mappings expire after
+    /// expiration_in_secs seconds.
+    pub fn new(expiration_in_secs: u64, time_env: T) -> NeighborCache<T> {
+        NeighborCache {
+            entries: BTreeMap::new(),
+            expiration: Duration::from_secs(expiration_in_secs),
+            time_env,
+        }
+    }
+
+    /// Lookup the ethernet address for an IPv6 address.
+    pub fn eth_addr_for_ip(&mut self, ipv6_addr: Ipv6Address) -> Option<EthernetAddress> {
+        self.expire_eth_addr();
+        self.entries.get(&ipv6_addr).map(|entry| entry.eth_addr)
+    }
+
+    /// Create or update the ethernet address mapping for an IPv6 address.
+    pub fn set_eth_addr_for_ip(&mut self, ipv6_addr: Ipv6Address, eth_addr: EthernetAddress) {
+        let in_cache_since = self.time_env.now_instant();
+        self.entries.insert(
+            ipv6_addr,
+            Entry {
+                eth_addr,
+                in_cache_since,
+            },
+        );
+    }
+
+    /// Removes the mapping for an IPv6 address, returning the ethernet address
+    /// if one existed.
+    pub fn remove(&mut self, ipv6_addr: Ipv6Address) -> Option<EthernetAddress> {
+        self.entries.remove(&ipv6_addr).map(|entry| entry.eth_addr)
+    }
+
+    /// Purge Ethernet address entries translations that have expired.
+    fn expire_eth_addr(&mut self) {
+        let now = self.time_env.now_instant();
+        let expiration = self.expiration;
+        self.entries
+            .retain(|_, entry| now.duration_since(entry.in_cache_since) <= expiration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    fn ipv6(i: u8) -> Ipv6Address {
+        Ipv6Address::new([0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, i])
+    }
+
+    fn eth(i: u8) -> EthernetAddress {
+        EthernetAddress::new([0, 0, 0, 0, 0, i])
+    }
+
+    #[test]
+    fn test_lookup_and_expire() {
+        let mut neighbor_cache = NeighborCache::new(30, MockEnv::new());
+        assert_matches!(neighbor_cache.eth_addr_for_ip(ipv6(1)), None);
+
+        neighbor_cache.set_eth_addr_for_ip(ipv6(1), eth(1));
+        neighbor_cache.time_env.now += Duration::from_secs(20);
+        neighbor_cache.set_eth_addr_for_ip(ipv6(2), eth(2));
+        assert_eq!(neighbor_cache.eth_addr_for_ip(ipv6(1)).unwrap(), eth(1));
+
+        neighbor_cache.time_env.now += Duration::from_secs(11);
+        assert_matches!(neighbor_cache.eth_addr_for_ip(ipv6(1)), None);
+        assert_eq!(neighbor_cache.eth_addr_for_ip(ipv6(2)).unwrap(), eth(2));
+
+        assert_eq!(neighbor_cache.remove(ipv6(2)).unwrap(), eth(2));
+        assert_matches!(neighbor_cache.eth_addr_for_ip(ipv6(2)), None);
+    }
+}
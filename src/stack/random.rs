@@ -0,0 +1,60 @@
+//! Random numbers for values which should be hard to guess, such as
+//! ephemeral ports, IPv4 identifications and DHCP transaction IDs.
+//!
+//! With the std feature numbers come from the OS seeded rand::random().
+//! Targets without an OS have no entropy the stack knows of, so numbers come
+//! from a generator the application seeds from whatever entropy it has, e.g.
+//! a hardware RNG, via seed(...).
+
+use core::sync::atomic::{
+    AtomicU32,
+    Ordering,
+};
+
+#[cfg(feature = "std")]
+use rand;
+use rand::Rand;
+#[cfg(not(feature = "std"))]
+use rand::Rng;
+
+/// State of the xorshift generator used without the std feature, which must
+/// never be zero.
+static STATE: AtomicU32 = AtomicU32::new(0x9E37_79B9);
+
+/// Seeds the generator used without the std feature, which otherwise yields
+/// the same numbers after every boot. Has no effect with the std feature.
+pub fn seed(seed: u32) {
+    if seed != 0 {
+        STATE.store(seed, Ordering::Relaxed);
+    }
+}
+
+/// Returns a random value.
+#[cfg(feature = "std")]
+pub fn random<T: Rand>() -> T {
+    rand::random()
+}
+
+/// Returns a random value.
+#[cfg(not(feature = "std"))]
+pub fn random<T: Rand>() -> T {
+    XorShiftRng.gen()
+}
+
+#[cfg(not(feature = "std"))]
+struct XorShiftRng;
+
+#[cfg(not(feature = "std"))]
+impl Rng for XorShiftRng {
+    fn next_u32(&mut self) -> u32 {
+        // Plain loads and stores rather than a compare and swap keep this
+        // available on targets without atomic read-modify-write operations.
+        // Racing callers may get the same number, like two unlucky draws.
+        let mut x = STATE.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        STATE.store(x, Ordering::Relaxed);
+        x
+    }
+}
@@ -1,20 +1,22 @@
-use std::io::{
-    Cursor,
-    Write,
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
 };
 
 use byteorder::{
+    ByteOrder,
     NetworkEndian,
-    ReadBytesExt,
-    WriteBytesExt,
 };
 
-use core::repr::{
+use stack::repr::{
     EthernetAddress,
     Ipv4Address,
 };
 use {
+    BufferError,
     Error,
+    ParseError,
     Result,
 };
 
@@ -56,15 +58,15 @@ impl Arp {
     /// Tries to deserialize a packet into an ARP message.
     pub fn deserialize(buffer: &[u8]) -> Result<Arp> {
         if buffer.len() < 28 {
-            return Err(Error::Malformed);
+            return Err(Error::Parse(ParseError::Malformed));
         }
 
-        let hw_type = (&buffer[0 .. 2]).read_u16::<NetworkEndian>().unwrap();
-        let proto_type = (&buffer[2 .. 4]).read_u16::<NetworkEndian>().unwrap();
-        let op = (&buffer[6 .. 8]).read_u16::<NetworkEndian>().unwrap();
+        let hw_type = NetworkEndian::read_u16(&buffer[0 .. 2]);
+        let proto_type = NetworkEndian::read_u16(&buffer[2 .. 4]);
+        let op = NetworkEndian::read_u16(&buffer[6 .. 8]);
 
         if hw_type != hw_types::ETHERNET || proto_type != proto_types::IPV4 || op == 0 || op > 2 {
-            return Err(Error::Malformed);
+            return Err(Error::Parse(ParseError::Malformed));
         }
 
         Ok(Arp {
@@ -79,24 +81,37 @@ impl Arp {
     /// Serializes the ARP message into a packet.
     pub fn serialize(&self, buffer: &mut [u8]) -> Result<()> {
         if self.buffer_len() > buffer.len() {
-            return Err(Error::Exhausted);
+            return Err(Error::Buffer(BufferError::TooSmall));
         }
 
-        let mut writer = Cursor::new(buffer);
-        writer
-            .write_u16::<NetworkEndian>(hw_types::ETHERNET)
-            .unwrap();
-        writer
-            .write_u16::<NetworkEndian>(proto_types::IPV4)
-            .unwrap();
-        writer.write_u8(6).unwrap();
-        writer.write_u8(4).unwrap();
-        writer.write_u16::<NetworkEndian>(self.op as u16).unwrap();
-        writer.write(self.source_hw_addr.as_bytes()).unwrap();
-        writer.write(self.source_proto_addr.as_bytes()).unwrap();
-        writer.write(self.target_hw_addr.as_bytes()).unwrap();
-        writer.write(self.target_proto_addr.as_bytes()).unwrap();
+        NetworkEndian::write_u16(&mut buffer[0 .. 2], hw_types::ETHERNET);
+        NetworkEndian::write_u16(&mut buffer[2 .. 4], proto_types::IPV4);
+        buffer[4] = 6;
+        buffer[5] = 4;
+        NetworkEndian::write_u16(&mut buffer[6 .. 8], self.op as u16);
+        buffer[8 .. 14].copy_from_slice(self.source_hw_addr.as_bytes());
+        buffer[14 .. 18].copy_from_slice(self.source_proto_addr.as_bytes());
+        buffer[18 .. 24].copy_from_slice(self.target_hw_addr.as_bytes());
+        buffer[24 .. 28].copy_from_slice(self.target_proto_addr.as_bytes());
 
         Ok(())
     }
 }
+
+impl Display for Arp {
+    /// Formats a tcpdump-like summary of the ARP message.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.op {
+            Op::Request => write!(
+                f,
+                "ARP who-has {} tell {}",
+                self.target_proto_addr, self.source_proto_addr
+            ),
+            Op::Reply => write!(
+                f,
+                "ARP reply {} is-at {}",
+                self.source_proto_addr, self.source_hw_addr
+            ),
+        }
+    }
+}
@@ -0,0 +1,405 @@
+use stack::repr::{
+    eth_types,
+    Arp,
+    EthernetAddress,
+    EthernetFrame,
+    Icmpv4Message,
+    Icmpv4Packet,
+    Icmpv4Repr,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpPacket,
+    TcpRepr,
+    UdpPacket,
+    UdpRepr,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+#[derive(Clone, Copy, Debug)]
+enum Network {
+    Ipv4(Ipv4Repr),
+    Arp(Arp),
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Transport {
+    Udp { src_port: u16, dst_port: u16 },
+    Tcp(TcpRepr),
+    Icmpv4(Icmpv4Message),
+}
+
+/// Composes a frame or packet layer by layer, e.g.
+/// PacketBuilder::ethernet(..).ipv4(..).udp(..).payload(..), and writes it
+/// into a buffer.
+///
+/// Length, protocol and payload type fields of each layer are computed from
+/// the layers above it, overriding those of the provided headers, and
+/// checksums are filled after everything else is written.
+///
+/// Layers must be added top down, and composing layers which can't carry
+/// each other, such as UDP without IPv4, panics.
+#[derive(Clone, Copy, Debug)]
+pub struct PacketBuilder<'a> {
+    ethernet: Option<(EthernetAddress, EthernetAddress)>,
+    network: Option<Network>,
+    transport: Option<Transport>,
+    payload: &'a [u8],
+}
+
+impl<'a> PacketBuilder<'a> {
+    /// Creates a builder without a link layer, e.g. for packets sent via a
+    /// raw IPv4 socket.
+    pub fn new() -> PacketBuilder<'a> {
+        PacketBuilder {
+            ethernet: None,
+            network: None,
+            transport: None,
+            payload: &[],
+        }
+    }
+
+    /// Creates a builder for an Ethernet frame.
+    pub fn ethernet(src_addr: EthernetAddress, dst_addr: EthernetAddress) -> PacketBuilder<'a> {
+        PacketBuilder {
+            ethernet: Some((src_addr, dst_addr)),
+            ..PacketBuilder::new()
+        }
+    }
+
+    /// Adds an IPv4 header, whose protocol is kept only if no transport
+    /// layer is added.
+    pub fn ipv4(mut self, ipv4_repr: Ipv4Repr) -> PacketBuilder<'a> {
+        assert!(self.network.is_none(), "Network layer already added!");
+        self.network = Some(Network::Ipv4(ipv4_repr));
+        self
+    }
+
+    /// Adds an ARP message to an Ethernet frame.
+    pub fn arp(mut self, arp: Arp) -> PacketBuilder<'a> {
+        assert!(self.ethernet.is_some(), "ARP requires an Ethernet frame!");
+        assert!(self.network.is_none(), "Network layer already added!");
+        self.network = Some(Network::Arp(arp));
+        self
+    }
+
+    /// Adds a UDP header with a checksum.
+    pub fn udp(self, src_port: u16, dst_port: u16) -> PacketBuilder<'a> {
+        self.transport(Transport::Udp { src_port, dst_port })
+    }
+
+    /// Adds a TCP header.
+    pub fn tcp(self, tcp_repr: TcpRepr) -> PacketBuilder<'a> {
+        self.transport(Transport::Tcp(tcp_repr))
+    }
+
+    /// Adds an ICMP header.
+    pub fn icmpv4(self, message: Icmpv4Message) -> PacketBuilder<'a> {
+        self.transport(Transport::Icmpv4(message))
+    }
+
+    /// Sets the payload of the innermost layer.
+    pub fn payload(mut self, payload: &'a [u8]) -> PacketBuilder<'a> {
+        self.payload = payload;
+        self
+    }
+
+    /// Returns the buffer size needed to build the frame or packet.
+    pub fn buffer_len(&self) -> usize {
+        let len = match self.network {
+            Some(Network::Ipv4(ipv4_repr)) => ipv4_repr.header_len() + self.transport_len(),
+            Some(Network::Arp(arp)) => arp.buffer_len(),
+            None => panic!("Missing network layer!"),
+        };
+
+        match self.ethernet {
+            Some(_) => EthernetFrame::<&[u8]>::buffer_len(len),
+            None => len,
+        }
+    }
+
+    /// Writes the frame or packet to the start of a buffer, returning the
+    /// number of bytes written.
+    pub fn build(&self, buffer: &mut [u8]) -> Result<usize> {
+        let buffer_len = self.buffer_len();
+        if buffer.len() < buffer_len {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        let buffer = &mut buffer[.. buffer_len];
+
+        match self.ethernet {
+            Some((src_addr, dst_addr)) => {
+                let mut eth_frame = EthernetFrame::try_new(buffer)?;
+                eth_frame.set_src_addr(src_addr);
+                eth_frame.set_dst_addr(dst_addr);
+                eth_frame.set_payload_type(match self.network {
+                    Some(Network::Arp(_)) => eth_types::ARP,
+                    _ => eth_types::IPV4,
+                });
+                self.build_network(eth_frame.payload_mut())?;
+            }
+            None => self.build_network(buffer)?,
+        }
+
+        Ok(buffer_len)
+    }
+
+    fn transport(mut self, transport: Transport) -> PacketBuilder<'a> {
+        match self.network {
+            Some(Network::Ipv4(_)) => {}
+            _ => panic!("Transport layer requires IPv4!"),
+        }
+        assert!(self.transport.is_none(), "Transport layer already added!");
+        self.transport = Some(transport);
+        self
+    }
+
+    fn transport_len(&self) -> usize {
+        let header_len = match self.transport {
+            Some(Transport::Udp { .. }) => UdpPacket::<&[u8]>::buffer_len(0),
+            Some(Transport::Tcp(tcp_repr)) => tcp_repr.header_len(),
+            Some(Transport::Icmpv4(_)) => Icmpv4Packet::<&[u8]>::buffer_len(0),
+            None => 0,
+        };
+        header_len + self.payload.len()
+    }
+
+    fn build_network(&self, buffer: &mut [u8]) -> Result<()> {
+        let ipv4_repr = match self.network {
+            Some(Network::Ipv4(ipv4_repr)) => ipv4_repr,
+            Some(Network::Arp(arp)) => return arp.serialize(buffer),
+            None => unreachable!(),
+        };
+
+        let ipv4_repr = Ipv4Repr {
+            protocol: match self.transport {
+                Some(Transport::Udp { .. }) => Ipv4Protocol::UDP,
+                Some(Transport::Tcp(_)) => Ipv4Protocol::TCP,
+                Some(Transport::Icmpv4(_)) => Ipv4Protocol::ICMP,
+                None => ipv4_repr.protocol,
+            },
+            payload_len: self.transport_len() as u16,
+            ..ipv4_repr
+        };
+
+        let mut ipv4_packet = Ipv4Packet::try_new(buffer)?;
+        ipv4_repr.serialize(&mut ipv4_packet);
+        self.build_transport(&ipv4_repr, ipv4_packet.payload_mut())
+    }
+
+    fn build_transport(&self, ipv4_repr: &Ipv4Repr, buffer: &mut [u8]) -> Result<()> {
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => {
+                buffer.copy_from_slice(self.payload);
+                return Ok(());
+            }
+        };
+
+        // Headers may write into the payload, e.g. ICMP timestamps, so the
+        // payload goes first.
+        let payload_at = buffer.len() - self.payload.len();
+        buffer[payload_at ..].copy_from_slice(self.payload);
+
+        match transport {
+            Transport::Udp { src_port, dst_port } => {
+                let udp_repr = UdpRepr {
+                    src_port,
+                    dst_port,
+                    length: buffer.len() as u16,
+                    checksum: true,
+                };
+                udp_repr.serialize(&mut UdpPacket::try_new(buffer)?, ipv4_repr);
+            }
+            Transport::Tcp(tcp_repr) => {
+                let mut tcp_packet = TcpPacket::try_new(buffer)?;
+                tcp_repr.serialize(&mut tcp_packet)?;
+                tcp_packet.fill_checksum(ipv4_repr);
+            }
+            Transport::Icmpv4(message) => {
+                let icmp_repr = Icmpv4Repr {
+                    message,
+                    payload_len: self.payload.len(),
+                };
+                let mut icmp_packet = Icmpv4Packet::try_new(buffer)?;
+                icmp_repr.serialize(&mut icmp_packet)?;
+                icmp_packet.fill_checksum();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> Default for PacketBuilder<'a> {
+    fn default() -> PacketBuilder<'a> {
+        PacketBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::repr::{
+        ArpOp,
+        Ipv4Address,
+        Ipv4Options,
+    };
+
+    fn ipv4_repr() -> Ipv4Repr {
+        Ipv4Repr {
+            src_addr: Ipv4Address::new([10, 0, 0, 1]),
+            dst_addr: Ipv4Address::new([10, 0, 0, 2]),
+            protocol: Ipv4Protocol::ICMP,
+            payload_len: 0,
+            ttl: 3,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        }
+    }
+
+    fn eth_addrs() -> (EthernetAddress, EthernetAddress) {
+        (
+            EthernetAddress::new([0, 1, 2, 3, 4, 5]),
+            EthernetAddress::new([6, 7, 8, 9, 10, 11]),
+        )
+    }
+
+    #[test]
+    fn test_build_ethernet_ipv4_udp() {
+        let (src_addr, dst_addr) = eth_addrs();
+        let builder = PacketBuilder::ethernet(src_addr, dst_addr)
+            .ipv4(ipv4_repr())
+            .udp(1024, 53)
+            .payload(&[1, 2, 3]);
+        assert_eq!(builder.buffer_len(), 14 + 20 + 8 + 3);
+
+        let mut buffer = [0xFF; 64];
+        assert_eq!(builder.build(&mut buffer).unwrap(), 45);
+
+        let eth_frame = EthernetFrame::try_new(&buffer[.. 45]).unwrap();
+        assert_eq!(eth_frame.src_addr(), src_addr);
+        assert_eq!(eth_frame.dst_addr(), dst_addr);
+        assert_eq!(eth_frame.payload_type(), eth_types::IPV4);
+
+        let ipv4_packet = Ipv4Packet::try_new(eth_frame.payload()).unwrap();
+        assert_matches!(ipv4_packet.check_encoding(), Ok(_));
+        let ipv4_repr = Ipv4Repr::deserialize(&ipv4_packet).unwrap();
+        assert_eq!(ipv4_repr.protocol, Ipv4Protocol::UDP);
+        assert_eq!(ipv4_repr.payload_len, 11);
+        assert_eq!(ipv4_repr.ttl, 3);
+
+        let udp_packet = UdpPacket::try_new(ipv4_packet.payload()).unwrap();
+        assert_matches!(udp_packet.check_encoding(&ipv4_repr), Ok(_));
+        assert_ne!(udp_packet.checksum(), 0);
+        assert_eq!(udp_packet.src_port(), 1024);
+        assert_eq!(udp_packet.dst_port(), 53);
+        assert_eq!(udp_packet.payload(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn test_build_ipv4_tcp() {
+        let mut flags = [false; 9];
+        flags[TcpRepr::FLAG_SYN] = true;
+        let tcp_repr = TcpRepr {
+            src_port: 1024,
+            dst_port: 80,
+            seq_num: 1,
+            ack_num: 0,
+            flags,
+            window_size: 1024,
+            urgent_pointer: 0,
+            max_segment_size: Some(1460),
+        };
+
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr())
+            .tcp(tcp_repr)
+            .payload(&[1, 2]);
+
+        let mut buffer = [0; 46];
+        assert_eq!(builder.build(&mut buffer).unwrap(), 46);
+
+        let ipv4_packet = Ipv4Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(ipv4_packet.check_encoding(), Ok(_));
+        let ipv4_repr = Ipv4Repr::deserialize(&ipv4_packet).unwrap();
+        assert_eq!(ipv4_repr.protocol, Ipv4Protocol::TCP);
+
+        let tcp_packet = TcpPacket::try_new(ipv4_packet.payload()).unwrap();
+        assert_matches!(tcp_packet.check_encoding(&ipv4_repr), Ok(_));
+        assert_eq!(TcpRepr::deserialize(&tcp_packet), tcp_repr);
+        assert_eq!(tcp_packet.payload(), &[1, 2]);
+    }
+
+    #[test]
+    fn test_build_ipv4_icmpv4() {
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr())
+            .icmpv4(Icmpv4Message::EchoRequest { id: 1, seq: 2 })
+            .payload(&[1, 2, 3, 4]);
+
+        let mut buffer = [0; 32];
+        builder.build(&mut buffer).unwrap();
+
+        let ipv4_packet = Ipv4Packet::try_new(&buffer[..]).unwrap();
+        let icmp_packet = Icmpv4Packet::try_new(ipv4_packet.payload()).unwrap();
+        assert_matches!(icmp_packet.check_encoding(), Ok(_));
+        assert_eq!(
+            Icmpv4Repr::deserialize(&icmp_packet).unwrap(),
+            Icmpv4Repr {
+                message: Icmpv4Message::EchoRequest { id: 1, seq: 2 },
+                payload_len: 4,
+            }
+        );
+        assert_eq!(icmp_packet.payload(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_build_ipv4_raw_payload() {
+        let builder = PacketBuilder::new().ipv4(ipv4_repr()).payload(&[1, 2]);
+
+        let mut buffer = [0; 22];
+        builder.build(&mut buffer).unwrap();
+
+        let ipv4_packet = Ipv4Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(ipv4_packet.check_encoding(), Ok(_));
+        assert_eq!(ipv4_packet.protocol(), Ipv4Protocol::ICMP as u8);
+        assert_eq!(ipv4_packet.payload(), &[1, 2]);
+    }
+
+    #[test]
+    fn test_build_ethernet_arp() {
+        let (src_addr, dst_addr) = eth_addrs();
+        let arp = Arp {
+            op: ArpOp::Request,
+            source_hw_addr: src_addr,
+            source_proto_addr: Ipv4Address::new([10, 0, 0, 1]),
+            target_hw_addr: EthernetAddress::BROADCAST,
+            target_proto_addr: Ipv4Address::new([10, 0, 0, 2]),
+        };
+
+        let builder = PacketBuilder::ethernet(src_addr, dst_addr).arp(arp);
+        let mut buffer = [0; 42];
+        assert_eq!(builder.build(&mut buffer).unwrap(), 42);
+
+        let eth_frame = EthernetFrame::try_new(&buffer[..]).unwrap();
+        assert_eq!(eth_frame.payload_type(), eth_types::ARP);
+        assert_eq!(Arp::deserialize(eth_frame.payload()).unwrap(), arp);
+    }
+
+    #[test]
+    fn test_build_with_buffer_too_small() {
+        let builder = PacketBuilder::new().ipv4(ipv4_repr()).udp(1, 2);
+        let mut buffer = [0; 27];
+        assert_matches!(
+            builder.build(&mut buffer),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+    }
+}
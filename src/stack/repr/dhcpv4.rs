@@ -0,0 +1,781 @@
+use alloc::vec::Vec;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::repr::{
+    EthernetAddress,
+    Ipv4Address,
+};
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// The type of a DHCP message, carried in the DHCP message type option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageType {
+    Discover,
+    Offer,
+    Request,
+    Decline,
+    Ack,
+    Nak,
+    Release,
+    Inform,
+}
+
+impl MessageType {
+    fn try_from(message_type: u8) -> Result<MessageType> {
+        match message_type {
+            message_types::DISCOVER => Ok(MessageType::Discover),
+            message_types::OFFER => Ok(MessageType::Offer),
+            message_types::REQUEST => Ok(MessageType::Request),
+            message_types::DECLINE => Ok(MessageType::Decline),
+            message_types::ACK => Ok(MessageType::Ack),
+            message_types::NAK => Ok(MessageType::Nak),
+            message_types::RELEASE => Ok(MessageType::Release),
+            message_types::INFORM => Ok(MessageType::Inform),
+            _ => Err(Error::Parse(ParseError::Malformed)),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            MessageType::Discover => message_types::DISCOVER,
+            MessageType::Offer => message_types::OFFER,
+            MessageType::Request => message_types::REQUEST,
+            MessageType::Decline => message_types::DECLINE,
+            MessageType::Ack => message_types::ACK,
+            MessageType::Nak => message_types::NAK,
+            MessageType::Release => message_types::RELEASE,
+            MessageType::Inform => message_types::INFORM,
+        }
+    }
+
+    /// Checks if the message is sent by servers rather than clients.
+    pub fn is_reply(self) -> bool {
+        matches!(
+            self,
+            MessageType::Offer | MessageType::Ack | MessageType::Nak
+        )
+    }
+}
+
+/// A DHCP message over Ethernet, along with the options relevant to
+/// configuring an IPv4 interface.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub message_type: MessageType,
+    /// Transaction ID chosen by the client to match replies to requests.
+    pub transaction_id: u32,
+    /// Seconds elapsed since the client began acquiring or renewing a lease.
+    pub secs: u16,
+    /// Asks servers to broadcast replies since the client can not receive
+    /// unicast packets before it is configured.
+    pub broadcast: bool,
+    pub client_hw_addr: EthernetAddress,
+    /// Address of a client in the BOUND, RENEWING or REBINDING states.
+    pub client_ip: Ipv4Address,
+    /// Address offered to or assigned to the client.
+    pub your_ip: Ipv4Address,
+    /// Address of the next server used in bootstrap.
+    pub server_ip: Ipv4Address,
+    /// Address of a relay agent forwarding the message.
+    pub relay_agent_ip: Ipv4Address,
+    pub requested_ip: Option<Ipv4Address>,
+    pub server_identifier: Option<Ipv4Address>,
+    pub subnet_mask: Option<Ipv4Address>,
+    pub router: Option<Ipv4Address>,
+    pub dns_servers: Vec<Ipv4Address>,
+    /// Lease duration in seconds.
+    pub lease_duration: Option<u32>,
+    /// Seconds after which the client should renew its lease (T1).
+    pub renewal_duration: Option<u32>,
+    /// Seconds after which the client should rebind its lease (T2).
+    pub rebinding_duration: Option<u32>,
+    /// Option codes the client is interested in.
+    pub parameter_request_list: Vec<u8>,
+}
+
+impl Repr {
+    /// Minimum length of a message, since BOOTP relay agents may discard
+    /// shorter messages, see RFC 1542.
+    pub const MIN_BUFFER_LEN: usize = 300;
+
+    /// Creates a message with only the type, transaction ID and client
+    /// hardware address set.
+    pub fn new(
+        message_type: MessageType,
+        transaction_id: u32,
+        client_hw_addr: EthernetAddress,
+    ) -> Repr {
+        Repr {
+            message_type,
+            transaction_id,
+            secs: 0,
+            broadcast: false,
+            client_hw_addr,
+            client_ip: Ipv4Address::UNSPECIFIED,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            requested_ip: None,
+            server_identifier: None,
+            subnet_mask: None,
+            router: None,
+            dns_servers: Vec::new(),
+            lease_duration: None,
+            renewal_duration: None,
+            rebinding_duration: None,
+            parameter_request_list: Vec::new(),
+        }
+    }
+
+    /// Returns the buffer size needed to serialize the DHCP message.
+    pub fn buffer_len(&self) -> usize {
+        let addr_option_len = |addr: Option<Ipv4Address>| addr.map_or(0, |_| 6);
+        let u32_option_len = |value: Option<u32>| value.map_or(0, |_| 6);
+        let list_option_len = |len: usize| if len > 0 { 2 + len } else { 0 };
+
+        let options_len = 3
+            + addr_option_len(self.requested_ip)
+            + addr_option_len(self.server_identifier)
+            + addr_option_len(self.subnet_mask)
+            + addr_option_len(self.router)
+            + list_option_len(self.dns_servers.len() * 4)
+            + u32_option_len(self.lease_duration)
+            + u32_option_len(self.renewal_duration)
+            + u32_option_len(self.rebinding_duration)
+            + list_option_len(self.parameter_request_list.len())
+            + 1;
+
+        let buffer_len = Packet::<&[u8]>::OPTIONS_OFFSET + options_len;
+        if buffer_len < Self::MIN_BUFFER_LEN {
+            Self::MIN_BUFFER_LEN
+        } else {
+            buffer_len
+        }
+    }
+
+    /// Tries to deserialize a packet into a DHCP message.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        if packet.hw_type() != hw_types::ETHERNET
+            || packet.hw_len() != 6
+            || packet.magic_cookie() != Packet::<&[u8]>::MAGIC_COOKIE
+        {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let mut repr = Repr::new(
+            MessageType::Discover,
+            packet.transaction_id(),
+            packet.client_hw_addr(),
+        );
+        repr.secs = packet.secs();
+        repr.broadcast = packet.flags() & flags::BROADCAST != 0;
+        repr.client_ip = packet.client_ip();
+        repr.your_ip = packet.your_ip();
+        repr.server_ip = packet.server_ip();
+        repr.relay_agent_ip = packet.relay_agent_ip();
+
+        let addr_option = |data: &[u8]| {
+            Ipv4Address::try_new(data).map_err(|_| Error::Parse(ParseError::Malformed))
+        };
+        let u32_option = |data: &[u8]| {
+            if data.len() != 4 {
+                Err(Error::Parse(ParseError::Malformed))
+            } else {
+                Ok(NetworkEndian::read_u32(data))
+            }
+        };
+
+        let mut message_type = None;
+
+        for option in packet.options() {
+            let (code, data) = option?;
+            match code {
+                option_codes::MESSAGE_TYPE if data.len() == 1 => {
+                    message_type = Some(MessageType::try_from(data[0])?);
+                }
+                option_codes::REQUESTED_IP => repr.requested_ip = Some(addr_option(data)?),
+                option_codes::SERVER_IDENTIFIER => {
+                    repr.server_identifier = Some(addr_option(data)?)
+                }
+                option_codes::SUBNET_MASK => repr.subnet_mask = Some(addr_option(data)?),
+                option_codes::ROUTER if data.len() >= 4 => {
+                    repr.router = Some(addr_option(&data[.. 4])?)
+                }
+                option_codes::DNS_SERVERS if data.len() % 4 == 0 => {
+                    repr.dns_servers = data
+                        .chunks(4)
+                        .map(|addr| Ipv4Address::try_new(addr).unwrap())
+                        .collect();
+                }
+                option_codes::LEASE_TIME => repr.lease_duration = Some(u32_option(data)?),
+                option_codes::RENEWAL_TIME => repr.renewal_duration = Some(u32_option(data)?),
+                option_codes::REBINDING_TIME => repr.rebinding_duration = Some(u32_option(data)?),
+                option_codes::PARAMETER_REQUEST_LIST => {
+                    repr.parameter_request_list = data.to_vec();
+                }
+                option_codes::MESSAGE_TYPE | option_codes::ROUTER | option_codes::DNS_SERVERS => {
+                    return Err(Error::Parse(ParseError::Malformed))
+                }
+                _ => {}
+            }
+        }
+
+        match message_type {
+            Some(message_type) => {
+                repr.message_type = message_type;
+                Ok(repr)
+            }
+            None => Err(Error::Parse(ParseError::Malformed)),
+        }
+    }
+
+    /// Serializes the DHCP message into a packet, which should be at least
+    /// buffer_len() bytes.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        if packet.as_ref().len() < self.buffer_len() {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        let op = if self.message_type.is_reply() {
+            ops::REPLY
+        } else {
+            ops::REQUEST
+        };
+
+        packet.set_op(op);
+        packet.set_hw_type(hw_types::ETHERNET);
+        packet.set_hw_len(6);
+        packet.set_hops(0);
+        packet.set_transaction_id(self.transaction_id);
+        packet.set_secs(self.secs);
+        packet.set_flags(if self.broadcast { flags::BROADCAST } else { 0 });
+        packet.set_client_ip(self.client_ip);
+        packet.set_your_ip(self.your_ip);
+        packet.set_server_ip(self.server_ip);
+        packet.set_relay_agent_ip(self.relay_agent_ip);
+        packet.set_client_hw_addr(self.client_hw_addr);
+        packet.set_magic_cookie(Packet::<&[u8]>::MAGIC_COOKIE);
+
+        let options = packet.options_mut();
+        for byte in options.iter_mut() {
+            *byte = option_codes::PAD;
+        }
+
+        let mut offset = 0;
+        let mut write_option = |code: u8, data: &[u8]| {
+            options[offset] = code;
+            options[offset + 1] = data.len() as u8;
+            options[offset + 2 .. offset + 2 + data.len()].copy_from_slice(data);
+            offset += 2 + data.len();
+        };
+
+        write_option(option_codes::MESSAGE_TYPE, &[self.message_type.as_u8()]);
+
+        let addr_options = [
+            (option_codes::REQUESTED_IP, self.requested_ip),
+            (option_codes::SERVER_IDENTIFIER, self.server_identifier),
+            (option_codes::SUBNET_MASK, self.subnet_mask),
+            (option_codes::ROUTER, self.router),
+        ];
+        for &(code, addr) in addr_options.iter() {
+            if let Some(addr) = addr {
+                write_option(code, addr.as_bytes());
+            }
+        }
+
+        if !self.dns_servers.is_empty() {
+            let dns_servers: Vec<u8> = self
+                .dns_servers
+                .iter()
+                .flat_map(|addr| addr.as_bytes().to_vec())
+                .collect();
+            write_option(option_codes::DNS_SERVERS, &dns_servers);
+        }
+
+        let u32_options = [
+            (option_codes::LEASE_TIME, self.lease_duration),
+            (option_codes::RENEWAL_TIME, self.renewal_duration),
+            (option_codes::REBINDING_TIME, self.rebinding_duration),
+        ];
+        for &(code, value) in u32_options.iter() {
+            if let Some(value) = value {
+                let mut data = [0; 4];
+                NetworkEndian::write_u32(&mut data[..], value);
+                write_option(code, &data);
+            }
+        }
+
+        if !self.parameter_request_list.is_empty() {
+            write_option(
+                option_codes::PARAMETER_REQUEST_LIST,
+                &self.parameter_request_list,
+            );
+        }
+
+        options[offset] = option_codes::END;
+
+        Ok(())
+    }
+}
+
+/// [https://tools.ietf.org/html/rfc2131#section-3](https://tools.ietf.org/html/rfc2131#section-3)
+pub mod ports {
+    pub const SERVER: u16 = 67;
+
+    pub const CLIENT: u16 = 68;
+}
+
+/// [https://tools.ietf.org/html/rfc2131#section-2](https://tools.ietf.org/html/rfc2131#section-2)
+pub mod ops {
+    pub const REQUEST: u8 = 1;
+
+    pub const REPLY: u8 = 2;
+}
+
+/// [https://tools.ietf.org/html/rfc2131#section-2](https://tools.ietf.org/html/rfc2131#section-2)
+pub mod flags {
+    pub const BROADCAST: u16 = 0x8000;
+}
+
+/// [https://tools.ietf.org/html/rfc1700](https://tools.ietf.org/html/rfc1700)
+pub mod hw_types {
+    pub const ETHERNET: u8 = 1;
+}
+
+/// [https://tools.ietf.org/html/rfc2132#section-9.6](https://tools.ietf.org/html/rfc2132#section-9.6)
+pub mod message_types {
+    pub const DISCOVER: u8 = 1;
+
+    pub const OFFER: u8 = 2;
+
+    pub const REQUEST: u8 = 3;
+
+    pub const DECLINE: u8 = 4;
+
+    pub const ACK: u8 = 5;
+
+    pub const NAK: u8 = 6;
+
+    pub const RELEASE: u8 = 7;
+
+    pub const INFORM: u8 = 8;
+}
+
+/// [https://tools.ietf.org/html/rfc2132](https://tools.ietf.org/html/rfc2132)
+pub mod option_codes {
+    pub const PAD: u8 = 0;
+
+    pub const SUBNET_MASK: u8 = 1;
+
+    pub const ROUTER: u8 = 3;
+
+    pub const DNS_SERVERS: u8 = 6;
+
+    pub const REQUESTED_IP: u8 = 50;
+
+    pub const LEASE_TIME: u8 = 51;
+
+    pub const MESSAGE_TYPE: u8 = 53;
+
+    pub const SERVER_IDENTIFIER: u8 = 54;
+
+    pub const PARAMETER_REQUEST_LIST: u8 = 55;
+
+    pub const RENEWAL_TIME: u8 = 58;
+
+    pub const REBINDING_TIME: u8 = 59;
+
+    pub const END: u8 = 255;
+}
+
+/// [https://tools.ietf.org/html/rfc2131#section-2](https://tools.ietf.org/html/rfc2131#section-2)
+mod fields {
+    use core::ops::Range;
+
+    pub const OP: usize = 0;
+
+    pub const HW_TYPE: usize = 1;
+
+    pub const HW_LEN: usize = 2;
+
+    pub const HOPS: usize = 3;
+
+    pub const TRANSACTION_ID: Range<usize> = 4 .. 8;
+
+    pub const SECS: Range<usize> = 8 .. 10;
+
+    pub const FLAGS: Range<usize> = 10 .. 12;
+
+    pub const CLIENT_IP: Range<usize> = 12 .. 16;
+
+    pub const YOUR_IP: Range<usize> = 16 .. 20;
+
+    pub const SERVER_IP: Range<usize> = 20 .. 24;
+
+    pub const RELAY_AGENT_IP: Range<usize> = 24 .. 28;
+
+    pub const CLIENT_HW_ADDR: Range<usize> = 28 .. 34;
+
+    pub const MAGIC_COOKIE: Range<usize> = 236 .. 240;
+}
+
+/// An iterator over the (code, data) pairs of the options in a DHCP message.
+pub struct OptionIter<'a> {
+    options: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = Result<(u8, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.position < self.options.len() {
+            let code = self.options[self.position];
+            match code {
+                option_codes::PAD => self.position += 1,
+                option_codes::END => {
+                    self.position = self.options.len();
+                    return None;
+                }
+                _ => {
+                    let start = self.position + 2;
+                    if start > self.options.len()
+                        || start + self.options[self.position + 1] as usize > self.options.len()
+                    {
+                        self.position = self.options.len();
+                        return Some(Err(Error::Parse(ParseError::Malformed)));
+                    }
+
+                    let end = start + self.options[self.position + 1] as usize;
+                    self.position = end;
+                    return Some(Ok((code, &self.options[start .. end])));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// View of a byte buffer as a DHCP message.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    /// Offset of the options following the fixed BOOTP fields and magic
+    /// cookie.
+    pub const OPTIONS_OFFSET: usize = 240;
+
+    pub const MAGIC_COOKIE: u32 = 0x63825363;
+
+    /// Tries to create a DHCP packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::OPTIONS_OFFSET {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Checks if the packet has a valid encoding, i.e. carries the DHCP magic
+    /// cookie and well formed options.
+    pub fn check_encoding(&self) -> Result<()> {
+        if self.magic_cookie() != Self::MAGIC_COOKIE {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        for option in self.options() {
+            option?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns an iterator over the options in the packet.
+    pub fn options(&self) -> OptionIter<'_> {
+        OptionIter {
+            options: &self.buffer.as_ref()[Self::OPTIONS_OFFSET ..],
+            position: 0,
+        }
+    }
+
+    pub fn op(&self) -> u8 {
+        self.buffer.as_ref()[fields::OP]
+    }
+
+    pub fn hw_type(&self) -> u8 {
+        self.buffer.as_ref()[fields::HW_TYPE]
+    }
+
+    pub fn hw_len(&self) -> u8 {
+        self.buffer.as_ref()[fields::HW_LEN]
+    }
+
+    pub fn hops(&self) -> u8 {
+        self.buffer.as_ref()[fields::HOPS]
+    }
+
+    pub fn transaction_id(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buffer.as_ref()[fields::TRANSACTION_ID])
+    }
+
+    pub fn secs(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::SECS])
+    }
+
+    pub fn flags(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::FLAGS])
+    }
+
+    pub fn client_ip(&self) -> Ipv4Address {
+        Ipv4Address::try_new(&self.buffer.as_ref()[fields::CLIENT_IP]).unwrap()
+    }
+
+    pub fn your_ip(&self) -> Ipv4Address {
+        Ipv4Address::try_new(&self.buffer.as_ref()[fields::YOUR_IP]).unwrap()
+    }
+
+    pub fn server_ip(&self) -> Ipv4Address {
+        Ipv4Address::try_new(&self.buffer.as_ref()[fields::SERVER_IP]).unwrap()
+    }
+
+    pub fn relay_agent_ip(&self) -> Ipv4Address {
+        Ipv4Address::try_new(&self.buffer.as_ref()[fields::RELAY_AGENT_IP]).unwrap()
+    }
+
+    pub fn client_hw_addr(&self) -> EthernetAddress {
+        EthernetAddress::try_new(&self.buffer.as_ref()[fields::CLIENT_HW_ADDR]).unwrap()
+    }
+
+    pub fn magic_cookie(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buffer.as_ref()[fields::MAGIC_COOKIE])
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[Self::OPTIONS_OFFSET ..]
+    }
+
+    pub fn set_op(&mut self, op: u8) {
+        self.buffer.as_mut()[fields::OP] = op;
+    }
+
+    pub fn set_hw_type(&mut self, hw_type: u8) {
+        self.buffer.as_mut()[fields::HW_TYPE] = hw_type;
+    }
+
+    pub fn set_hw_len(&mut self, hw_len: u8) {
+        self.buffer.as_mut()[fields::HW_LEN] = hw_len;
+    }
+
+    pub fn set_hops(&mut self, hops: u8) {
+        self.buffer.as_mut()[fields::HOPS] = hops;
+    }
+
+    pub fn set_transaction_id(&mut self, transaction_id: u32) {
+        NetworkEndian::write_u32(&mut self.buffer.as_mut()[fields::TRANSACTION_ID], transaction_id)
+    }
+
+    pub fn set_secs(&mut self, secs: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::SECS], secs)
+    }
+
+    pub fn set_flags(&mut self, flags: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::FLAGS], flags)
+    }
+
+    pub fn set_client_ip(&mut self, client_ip: Ipv4Address) {
+        self.buffer.as_mut()[fields::CLIENT_IP].copy_from_slice(client_ip.as_bytes());
+    }
+
+    pub fn set_your_ip(&mut self, your_ip: Ipv4Address) {
+        self.buffer.as_mut()[fields::YOUR_IP].copy_from_slice(your_ip.as_bytes());
+    }
+
+    pub fn set_server_ip(&mut self, server_ip: Ipv4Address) {
+        self.buffer.as_mut()[fields::SERVER_IP].copy_from_slice(server_ip.as_bytes());
+    }
+
+    pub fn set_relay_agent_ip(&mut self, relay_agent_ip: Ipv4Address) {
+        self.buffer.as_mut()[fields::RELAY_AGENT_IP].copy_from_slice(relay_agent_ip.as_bytes());
+    }
+
+    pub fn set_client_hw_addr(&mut self, client_hw_addr: EthernetAddress) {
+        self.buffer.as_mut()[fields::CLIENT_HW_ADDR].copy_from_slice(client_hw_addr.as_bytes());
+    }
+
+    pub fn set_magic_cookie(&mut self, magic_cookie: u32) {
+        NetworkEndian::write_u32(&mut self.buffer.as_mut()[fields::MAGIC_COOKIE], magic_cookie)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer_buffer() -> Vec<u8> {
+        let mut buffer = vec![0; Packet::<&[u8]>::OPTIONS_OFFSET];
+        buffer[.. 4].copy_from_slice(&[0x02, 0x01, 0x06, 0x00]);
+        buffer[4 .. 8].copy_from_slice(&[0xDE, 0xAD, 0xBE, 0xEF]);
+        buffer[10] = 0x80;
+        buffer[16 .. 20].copy_from_slice(&[10, 0, 0, 150]);
+        buffer[28 .. 34].copy_from_slice(&[0x06, 0x11, 0x22, 0x33, 0x44, 0x55]);
+        buffer[236 .. 240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+        buffer.extend_from_slice(&[
+            0x35, 0x01, 0x02, // Message type
+            0x00, // Pad
+            0x36, 0x04, 0x0A, 0x00, 0x00, 0x01, // Server identifier
+            0x01, 0x04, 0xFF, 0xFF, 0xFF, 0x00, // Subnet mask
+            0x03, 0x08, 0x0A, 0x00, 0x00, 0x01, 0x0A, 0x00, 0x00, 0x02, // Routers
+            0x06, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x04, 0x04, // DNS servers
+            0x33, 0x04, 0x00, 0x00, 0x0E, 0x10, // Lease time
+            0x0C, 0x03, 0x66, 0x6F, 0x6F, // Host name
+            0xFF, // End
+        ]);
+        buffer
+    }
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer = vec![0; Packet::<&[u8]>::OPTIONS_OFFSET - 1];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_packet_getters() {
+        let buffer = offer_buffer();
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet.op(), ops::REPLY);
+        assert_eq!(packet.hw_type(), hw_types::ETHERNET);
+        assert_eq!(packet.hw_len(), 6);
+        assert_eq!(packet.hops(), 0);
+        assert_eq!(packet.transaction_id(), 0xDEADBEEF);
+        assert_eq!(packet.flags(), flags::BROADCAST);
+        assert_eq!(packet.your_ip(), Ipv4Address::new([10, 0, 0, 150]));
+        assert_eq!(
+            packet.client_hw_addr(),
+            EthernetAddress::new([0x06, 0x11, 0x22, 0x33, 0x44, 0x55])
+        );
+        assert_eq!(packet.options().count(), 7);
+
+        let repr = Repr::deserialize(&packet).unwrap();
+        assert_eq!(repr.message_type, MessageType::Offer);
+        assert!(repr.broadcast);
+        assert_eq!(
+            repr.server_identifier,
+            Some(Ipv4Address::new([10, 0, 0, 1]))
+        );
+        assert_eq!(repr.subnet_mask, Some(Ipv4Address::new([255, 255, 255, 0])));
+        assert_eq!(repr.router, Some(Ipv4Address::new([10, 0, 0, 1])));
+        assert_eq!(
+            repr.dns_servers,
+            vec![
+                Ipv4Address::new([8, 8, 8, 8]),
+                Ipv4Address::new([8, 8, 4, 4]),
+            ]
+        );
+        assert_eq!(repr.lease_duration, Some(3600));
+        assert_eq!(repr.renewal_duration, None);
+    }
+
+    #[test]
+    fn test_check_encoding_truncated_option() {
+        let mut buffer = offer_buffer();
+        let len = buffer.len();
+        buffer.truncate(len - 3);
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_without_message_type() {
+        let mut buffer = offer_buffer();
+        buffer[Packet::<&[u8]>::OPTIONS_OFFSET] = option_codes::PAD;
+        buffer[Packet::<&[u8]>::OPTIONS_OFFSET + 1] = option_codes::PAD;
+        buffer[Packet::<&[u8]>::OPTIONS_OFFSET + 2] = option_codes::PAD;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let mut repr = Repr::new(
+            MessageType::Request,
+            0x12345678,
+            EthernetAddress::new([0x06, 0x11, 0x22, 0x33, 0x44, 0x55]),
+        );
+        repr.broadcast = true;
+        repr.requested_ip = Some(Ipv4Address::new([10, 0, 0, 150]));
+        repr.server_identifier = Some(Ipv4Address::new([10, 0, 0, 1]));
+        repr.dns_servers = vec![Ipv4Address::new([10, 0, 0, 53])];
+        repr.renewal_duration = Some(1800);
+        repr.parameter_request_list = vec![option_codes::SUBNET_MASK, option_codes::ROUTER];
+
+        let buffer_len = repr.buffer_len();
+        assert_eq!(buffer_len, Repr::MIN_BUFFER_LEN);
+
+        let mut buffer = vec![0xFF; buffer_len];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+        }
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet.op(), ops::REQUEST);
+        assert_eq!(packet.client_ip(), Ipv4Address::UNSPECIFIED);
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_serialize_buffer_too_small() {
+        let repr = Repr::new(
+            MessageType::Discover,
+            0,
+            EthernetAddress::new([0x06, 0x11, 0x22, 0x33, 0x44, 0x55]),
+        );
+        let mut buffer = vec![0; Repr::MIN_BUFFER_LEN - 1];
+        let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+        assert_matches!(
+            repr.serialize(&mut packet),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+    }
+}
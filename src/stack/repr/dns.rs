@@ -0,0 +1,683 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::repr::{
+    Ipv4Address,
+    Ipv6Address,
+};
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Maximum length of an encoded domain name, see RFC 1035.
+const MAX_NAME_LEN: usize = 255;
+
+/// Maximum length of a single label within a domain name, see RFC 1035.
+const MAX_LABEL_LEN: usize = 63;
+
+/// A DNS question, asking for records of a type and class for a name.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Question {
+    pub name: String,
+    pub record_type: u16,
+    pub class: u16,
+}
+
+/// The data of a resource record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordData {
+    A(Ipv4Address),
+    Aaaa(Ipv6Address),
+    Cname(String),
+    Ptr(String),
+    /// Data of any other record type, which is carried uninterpreted.
+    Other(u16, Vec<u8>),
+}
+
+impl RecordData {
+    /// Returns the record type of the data.
+    pub fn record_type(&self) -> u16 {
+        match *self {
+            RecordData::A(_) => types::A,
+            RecordData::Aaaa(_) => types::AAAA,
+            RecordData::Cname(_) => types::CNAME,
+            RecordData::Ptr(_) => types::PTR,
+            RecordData::Other(record_type, _) => record_type,
+        }
+    }
+}
+
+/// A DNS resource record.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub class: u16,
+    /// Time in seconds the record may be cached for.
+    pub ttl: u32,
+    pub data: RecordData,
+}
+
+/// A DNS message.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub id: u16,
+    /// A combination of the QR, AA, TC, RD and RA flags.
+    pub flags: u16,
+    pub opcode: u8,
+    pub rcode: u8,
+    pub questions: Vec<Question>,
+    pub answers: Vec<Record>,
+    pub authorities: Vec<Record>,
+    pub additionals: Vec<Record>,
+}
+
+impl Repr {
+    /// Creates a standard query for records of a type with recursion desired.
+    pub fn query(id: u16, name: &str, record_type: u16) -> Repr {
+        Repr {
+            id,
+            flags: flags::RECURSION_DESIRED,
+            opcode: opcodes::QUERY,
+            rcode: rcodes::NO_ERROR,
+            questions: vec![Question {
+                name: name.to_string(),
+                record_type,
+                class: classes::IN,
+            }],
+            answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        }
+    }
+
+    /// Checks if the message is a response rather than a query.
+    pub fn is_response(&self) -> bool {
+        self.flags & flags::RESPONSE != 0
+    }
+
+    /// Returns the buffer size needed to serialize the DNS message, with names
+    /// compressed.
+    ///
+    /// Fails with ParseError::Malformed if a name is not a valid domain name.
+    pub fn buffer_len(&self) -> Result<usize> {
+        self.encode().map(|buffer| buffer.len())
+    }
+
+    /// Tries to deserialize a packet into a DNS message, expanding compressed
+    /// names.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let message = packet.as_ref();
+        let mut offset = Packet::<&[u8]>::HEADER_LEN;
+
+        let mut questions = Vec::new();
+        for _ in 0 .. packet.question_count() {
+            let (name, next) = read_name(message, offset)?;
+            let fields = message
+                .get(next .. next + 4)
+                .ok_or(Error::Parse(ParseError::Malformed))?;
+            questions.push(Question {
+                name,
+                record_type: NetworkEndian::read_u16(&fields[0 .. 2]),
+                class: NetworkEndian::read_u16(&fields[2 .. 4]),
+            });
+            offset = next + 4;
+        }
+
+        let mut read_records = |count: u16| -> Result<Vec<Record>> {
+            let mut records = Vec::new();
+            for _ in 0 .. count {
+                let (record, next) = read_record(message, offset)?;
+                records.push(record);
+                offset = next;
+            }
+            Ok(records)
+        };
+
+        let answers = read_records(packet.answer_count())?;
+        let authorities = read_records(packet.authority_count())?;
+        let additionals = read_records(packet.additional_count())?;
+
+        let packet_flags = packet.flags();
+
+        Ok(Repr {
+            id: packet.id(),
+            flags: packet_flags & flags::MASK,
+            opcode: ((packet_flags >> 11) & 0x0F) as u8,
+            rcode: (packet_flags & 0x0F) as u8,
+            questions,
+            answers,
+            authorities,
+            additionals,
+        })
+    }
+
+    /// Serializes the DNS message into a packet, which should be at least
+    /// buffer_len() bytes.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let buffer = self.encode()?;
+        if packet.as_ref().len() < buffer.len() {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        packet.as_mut()[.. buffer.len()].copy_from_slice(&buffer);
+        Ok(())
+    }
+
+    fn encode(&self) -> Result<Vec<u8>> {
+        let mut buffer = vec![0; Packet::<&[u8]>::HEADER_LEN];
+        let mut names = BTreeMap::new();
+
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            packet.set_id(self.id);
+            packet.set_flags(
+                (self.flags & flags::MASK)
+                    | ((self.opcode as u16 & 0x0F) << 11)
+                    | (self.rcode as u16 & 0x0F),
+            );
+            packet.set_question_count(self.questions.len() as u16);
+            packet.set_answer_count(self.answers.len() as u16);
+            packet.set_authority_count(self.authorities.len() as u16);
+            packet.set_additional_count(self.additionals.len() as u16);
+        }
+
+        for question in self.questions.iter() {
+            write_name(&mut buffer, &mut names, &question.name)?;
+            buffer.extend_from_slice(&question.record_type.to_be_bytes());
+            buffer.extend_from_slice(&question.class.to_be_bytes());
+        }
+
+        for record in self
+            .answers
+            .iter()
+            .chain(self.authorities.iter())
+            .chain(self.additionals.iter())
+        {
+            write_record(&mut buffer, &mut names, record)?;
+        }
+
+        Ok(buffer)
+    }
+}
+
+/// Reads a possibly compressed name at an offset in a message, returning the
+/// name and the offset following it.
+fn read_name(message: &[u8], offset: usize) -> Result<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut name_len = 0;
+    let mut position = offset;
+    let mut next = None;
+
+    loop {
+        let len = *message
+            .get(position)
+            .ok_or(Error::Parse(ParseError::Malformed))? as usize;
+
+        match len & 0xC0 {
+            0xC0 => {
+                let low = *message
+                    .get(position + 1)
+                    .ok_or(Error::Parse(ParseError::Malformed))? as usize;
+                let pointer = ((len & 0x3F) << 8) | low;
+                // Only follow pointers to earlier names to rule out loops.
+                if pointer >= position {
+                    return Err(Error::Parse(ParseError::Malformed));
+                }
+                if next.is_none() {
+                    next = Some(position + 2);
+                }
+                position = pointer;
+            }
+            0x00 if len == 0 => {
+                if next.is_none() {
+                    next = Some(position + 1);
+                }
+                break;
+            }
+            0x00 => {
+                let label = message
+                    .get(position + 1 .. position + 1 + len)
+                    .ok_or(Error::Parse(ParseError::Malformed))?;
+                name_len += 1 + len;
+                if name_len > MAX_NAME_LEN {
+                    return Err(Error::Parse(ParseError::Malformed));
+                }
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position += 1 + len;
+            }
+            _ => return Err(Error::Parse(ParseError::Malformed)),
+        }
+    }
+
+    Ok((labels.join("."), next.unwrap()))
+}
+
+/// Writes a name to the end of a message, compressing it against names
+/// written previously.
+fn write_name(buffer: &mut Vec<u8>, names: &mut BTreeMap<String, usize>, name: &str) -> Result<()> {
+    let name = name.trim_end_matches('.');
+    if name.len() + 1 > MAX_NAME_LEN {
+        return Err(Error::Parse(ParseError::Malformed));
+    }
+
+    let labels: Vec<&str> = if name.is_empty() {
+        Vec::new()
+    } else {
+        name.split('.').collect()
+    };
+
+    for i in 0 .. labels.len() {
+        let suffix = labels[i ..].join(".").to_lowercase();
+        if let Some(&pointer) = names.get(&suffix) {
+            buffer.extend_from_slice(&(0xC000 | pointer as u16).to_be_bytes());
+            return Ok(());
+        }
+
+        let label = labels[i].as_bytes();
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        // Pointers only have 14 bits for the offset.
+        if buffer.len() < 0x4000 {
+            names.insert(suffix, buffer.len());
+        }
+
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label);
+    }
+
+    buffer.push(0);
+    Ok(())
+}
+
+fn read_record(message: &[u8], offset: usize) -> Result<(Record, usize)> {
+    let (name, next) = read_name(message, offset)?;
+    let fields = message
+        .get(next .. next + 10)
+        .ok_or(Error::Parse(ParseError::Malformed))?;
+    let record_type = NetworkEndian::read_u16(&fields[0 .. 2]);
+    let class = NetworkEndian::read_u16(&fields[2 .. 4]);
+    let ttl = NetworkEndian::read_u32(&fields[4 .. 8]);
+    let data_len = NetworkEndian::read_u16(&fields[8 .. 10]) as usize;
+
+    let data_offset = next + 10;
+    let data = message
+        .get(data_offset .. data_offset + data_len)
+        .ok_or(Error::Parse(ParseError::Malformed))?;
+
+    let data = match record_type {
+        types::A => RecordData::A(
+            Ipv4Address::try_new(data).map_err(|_| Error::Parse(ParseError::Malformed))?,
+        ),
+        types::AAAA => RecordData::Aaaa(
+            Ipv6Address::try_new(data).map_err(|_| Error::Parse(ParseError::Malformed))?,
+        ),
+        types::CNAME => RecordData::Cname(read_name(message, data_offset)?.0),
+        types::PTR => RecordData::Ptr(read_name(message, data_offset)?.0),
+        _ => RecordData::Other(record_type, data.to_vec()),
+    };
+
+    let record = Record {
+        name,
+        class,
+        ttl,
+        data,
+    };
+
+    Ok((record, data_offset + data_len))
+}
+
+fn write_record(
+    buffer: &mut Vec<u8>,
+    names: &mut BTreeMap<String, usize>,
+    record: &Record,
+) -> Result<()> {
+    write_name(buffer, names, &record.name)?;
+    buffer.extend_from_slice(&record.data.record_type().to_be_bytes());
+    buffer.extend_from_slice(&record.class.to_be_bytes());
+    buffer.extend_from_slice(&record.ttl.to_be_bytes());
+
+    // Fill in the data length once the data, which may be compressed, is
+    // written.
+    let data_len_offset = buffer.len();
+    buffer.extend_from_slice(&[0; 2]);
+
+    match record.data {
+        RecordData::A(addr) => buffer.extend_from_slice(addr.as_bytes()),
+        RecordData::Aaaa(addr) => buffer.extend_from_slice(addr.as_bytes()),
+        RecordData::Cname(ref name) | RecordData::Ptr(ref name) => write_name(buffer, names, name)?,
+        RecordData::Other(_, ref data) => buffer.extend_from_slice(data),
+    }
+
+    let data_len = buffer.len() - data_len_offset - 2;
+    if data_len > u16::MAX as usize {
+        return Err(Error::Parse(ParseError::Malformed));
+    }
+
+    NetworkEndian::write_u16(&mut buffer[data_len_offset .. data_len_offset + 2], data_len as u16);
+
+    Ok(())
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-4.2.1](https://tools.ietf.org/html/rfc1035#section-4.2.1)
+pub mod ports {
+    pub const DNS: u16 = 53;
+
+    pub const MDNS: u16 = 5353;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-4.1.1](https://tools.ietf.org/html/rfc1035#section-4.1.1)
+pub mod flags {
+    pub const RESPONSE: u16 = 0x8000;
+
+    pub const AUTHORITATIVE: u16 = 0x0400;
+
+    pub const TRUNCATED: u16 = 0x0200;
+
+    pub const RECURSION_DESIRED: u16 = 0x0100;
+
+    pub const RECURSION_AVAILABLE: u16 = 0x0080;
+
+    /// All of the above flags, excluding the opcode and rcode.
+    pub const MASK: u16 = 0x8780;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-4.1.1](https://tools.ietf.org/html/rfc1035#section-4.1.1)
+pub mod opcodes {
+    pub const QUERY: u8 = 0;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-4.1.1](https://tools.ietf.org/html/rfc1035#section-4.1.1)
+pub mod rcodes {
+    pub const NO_ERROR: u8 = 0;
+
+    pub const FORMAT_ERROR: u8 = 1;
+
+    pub const SERVER_FAILURE: u8 = 2;
+
+    pub const NAME_ERROR: u8 = 3;
+
+    pub const NOT_IMPLEMENTED: u8 = 4;
+
+    pub const REFUSED: u8 = 5;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-3.2.2](https://tools.ietf.org/html/rfc1035#section-3.2.2)
+pub mod types {
+    pub const A: u16 = 1;
+
+    pub const NS: u16 = 2;
+
+    pub const CNAME: u16 = 5;
+
+    pub const PTR: u16 = 12;
+
+    pub const AAAA: u16 = 28;
+
+    pub const ANY: u16 = 255;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-3.2.4](https://tools.ietf.org/html/rfc1035#section-3.2.4)
+pub mod classes {
+    pub const IN: u16 = 1;
+
+    /// Set on mDNS questions to request a unicast response, see RFC 6762.
+    pub const UNICAST_RESPONSE: u16 = 0x8000;
+
+    /// Set on mDNS records which replace rather than add to cached records
+    /// for the name, see RFC 6762.
+    pub const CACHE_FLUSH: u16 = 0x8000;
+}
+
+/// [https://tools.ietf.org/html/rfc1035#section-4.1.1](https://tools.ietf.org/html/rfc1035#section-4.1.1)
+mod fields {
+    use core::ops::Range;
+
+    pub const ID: Range<usize> = 0 .. 2;
+
+    pub const FLAGS: Range<usize> = 2 .. 4;
+
+    pub const QUESTION_COUNT: Range<usize> = 4 .. 6;
+
+    pub const ANSWER_COUNT: Range<usize> = 6 .. 8;
+
+    pub const AUTHORITY_COUNT: Range<usize> = 8 .. 10;
+
+    pub const ADDITIONAL_COUNT: Range<usize> = 10 .. 12;
+}
+
+/// View of a byte buffer as a DNS message.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 12;
+
+    /// Tries to create a DNS packet from a byte buffer.
+    ///
+    /// NOTE: Use Repr::deserialize() to validate the rest of the message if
+    /// the provided buffer originates from a untrusted source such as a link.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    pub fn id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::ID])
+    }
+
+    pub fn flags(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::FLAGS])
+    }
+
+    pub fn question_count(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::QUESTION_COUNT])
+    }
+
+    pub fn answer_count(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::ANSWER_COUNT])
+    }
+
+    pub fn authority_count(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::AUTHORITY_COUNT])
+    }
+
+    pub fn additional_count(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::ADDITIONAL_COUNT])
+    }
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> AsMut<[u8]> for Packet<T> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_id(&mut self, id: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::ID], id)
+    }
+
+    pub fn set_flags(&mut self, flags: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::FLAGS], flags)
+    }
+
+    pub fn set_question_count(&mut self, question_count: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::QUESTION_COUNT], question_count)
+    }
+
+    pub fn set_answer_count(&mut self, answer_count: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::ANSWER_COUNT], answer_count)
+    }
+
+    pub fn set_authority_count(&mut self, authority_count: u16) {
+        NetworkEndian::write_u16(
+            &mut self.buffer.as_mut()[fields::AUTHORITY_COUNT],
+            authority_count,
+        )
+    }
+
+    pub fn set_additional_count(&mut self, additional_count: u16) {
+        NetworkEndian::write_u16(
+            &mut self.buffer.as_mut()[fields::ADDITIONAL_COUNT],
+            additional_count,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Response to an A query for www.example.com, answered via a CNAME with
+    // compressed names.
+    static RESPONSE: [u8; 78] = [
+        0xBE, 0xEF, 0x81, 0x80, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // Header
+        0x03, b'w', b'w', b'w', 0x07, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0x03, b'c', b'o',
+        b'm', 0x00, 0x00, 0x01, 0x00, 0x01, // Question
+        0xC0, 0x0C, 0x00, 0x05, 0x00, 0x01, 0x00, 0x00, 0x0E, 0x10, 0x00, 0x07, 0x04, b'e', b'd',
+        b'g', b'e', 0xC0, 0x10, // CNAME
+        0xC0, 0x2D, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x3C, 0x00, 0x04, 0x5D, 0xB8, 0xD8,
+        0x22, // A
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Padding
+    ];
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer: [u8; 11] = [0; 11];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_compressed() {
+        let packet = Packet::try_new(&RESPONSE[..]).unwrap();
+        assert_eq!(packet.id(), 0xBEEF);
+        assert_eq!(packet.question_count(), 1);
+        assert_eq!(packet.answer_count(), 2);
+
+        let repr = Repr::deserialize(&packet).unwrap();
+        assert!(repr.is_response());
+        assert_eq!(
+            repr.flags,
+            flags::RESPONSE | flags::RECURSION_DESIRED | flags::RECURSION_AVAILABLE
+        );
+        assert_eq!(repr.rcode, rcodes::NO_ERROR);
+        assert_eq!(repr.questions[0].name, "www.example.com");
+        assert_eq!(repr.questions[0].record_type, types::A);
+        assert_eq!(
+            repr.answers,
+            vec![
+                Record {
+                    name: "www.example.com".to_string(),
+                    class: classes::IN,
+                    ttl: 3600,
+                    data: RecordData::Cname("edge.example.com".to_string()),
+                },
+                Record {
+                    name: "edge.example.com".to_string(),
+                    class: classes::IN,
+                    ttl: 60,
+                    data: RecordData::A(Ipv4Address::new([93, 184, 216, 34])),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_pointer_loop() {
+        let mut buffer = RESPONSE.to_vec();
+        // Point the question name at itself.
+        buffer[12] = 0xC0;
+        buffer[13] = 0x0C;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_truncated() {
+        let packet = Packet::try_new(&RESPONSE[.. 60]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_serialize_compressed() {
+        let packet = Packet::try_new(&RESPONSE[..]).unwrap();
+        let repr = Repr::deserialize(&packet).unwrap();
+
+        let buffer_len = repr.buffer_len().unwrap();
+        assert_eq!(buffer_len, 68);
+
+        let mut buffer = vec![0; buffer_len];
+        repr.serialize(&mut Packet::try_new(&mut buffer[..]).unwrap())
+            .unwrap();
+        assert_eq!(&buffer[..], &RESPONSE[.. 68]);
+    }
+
+    #[test]
+    fn test_serialize_deserialize_query() {
+        let mut repr = Repr::query(7, "example.com.", types::AAAA);
+        repr.additionals.push(Record {
+            name: "host.example.com".to_string(),
+            class: classes::IN,
+            ttl: 120,
+            data: RecordData::Aaaa(Ipv6Address::LOOPBACK),
+        });
+
+        let mut buffer = vec![0; repr.buffer_len().unwrap()];
+        repr.serialize(&mut Packet::try_new(&mut buffer[..]).unwrap())
+            .unwrap();
+
+        repr.questions[0].name = "example.com".to_string();
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_serialize_bad_label() {
+        let repr = Repr::query(7, "example..com", types::A);
+        assert_matches!(repr.buffer_len(), Err(Error::Parse(ParseError::Malformed)));
+
+        let label = "a".repeat(64);
+        let repr = Repr::query(7, &label, types::A);
+        assert_matches!(repr.buffer_len(), Err(Error::Parse(ParseError::Malformed)));
+    }
+}
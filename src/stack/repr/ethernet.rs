@@ -1,25 +1,25 @@
-use std::fmt::{
+use alloc::vec::Vec;
+use core::fmt::{
     Display,
     Formatter,
     Result as FmtResult,
 };
-use std::io::Write;
-use std::result::Result as StdResult;
-use std::str::FromStr;
+use core::result::Result as StdResult;
+use core::str::FromStr;
 
 use byteorder::{
+    ByteOrder,
     NetworkEndian,
-    ReadBytesExt,
-    WriteBytesExt,
 };
 
 use {
     Error,
+    ParseError,
     Result,
 };
 
 /// [MAC address](https://en.wikipedia.org/wiki/MAC_address) in network byte order.
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Address([u8; 6]);
 
 impl Address {
@@ -33,7 +33,7 @@ impl Address {
     /// Tries to creates a MAC address from a network byte order slice.
     pub fn try_new(addr: &[u8]) -> Result<Address> {
         if addr.len() != 6 {
-            return Err(Error::Exhausted);
+            return Err(Error::Parse(ParseError::Malformed));
         }
 
         let mut _addr: [u8; 6] = [0; 6];
@@ -105,10 +105,16 @@ pub mod eth_types {
     pub const IPV4: u16 = 0x800;
 
     pub const ARP: u16 = 0x806;
+
+    pub const IPV6: u16 = 0x86DD;
+
+    pub const PPPOE_DISCOVERY: u16 = 0x8863;
+
+    pub const PPPOE_SESSION: u16 = 0x8864;
 }
 
 mod fields {
-    use std::ops::{
+    use core::ops::{
         Range,
         RangeFrom,
     };
@@ -147,8 +153,10 @@ impl<T: AsRef<[u8]>> Frame<T> {
 
     /// Tries to create an Ethernet frame from a byte buffer.
     pub fn try_new(buffer: T) -> Result<Frame<T>> {
-        if buffer.as_ref().len() < Self::HEADER_LEN || buffer.as_ref().len() > Self::MAX_FRAME_LEN {
-            Err(Error::Exhausted)
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else if buffer.as_ref().len() > Self::MAX_FRAME_LEN {
+            Err(Error::Parse(ParseError::Malformed))
         } else {
             Ok(Frame { buffer })
         }
@@ -168,9 +176,7 @@ impl<T: AsRef<[u8]>> Frame<T> {
     }
 
     pub fn payload_type(&self) -> u16 {
-        (&self.buffer.as_ref()[fields::PAYLOAD_TYPE])
-            .read_u16::<NetworkEndian>()
-            .unwrap()
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::PAYLOAD_TYPE])
     }
 
     pub fn payload(&self) -> &[u8] {
@@ -180,21 +186,15 @@ impl<T: AsRef<[u8]>> Frame<T> {
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
     pub fn set_dst_addr(&mut self, addr: Address) {
-        (&mut self.buffer.as_mut()[fields::DST_ADDR])
-            .write(addr.as_bytes())
-            .unwrap();
+        self.buffer.as_mut()[fields::DST_ADDR].copy_from_slice(addr.as_bytes());
     }
 
     pub fn set_src_addr(&mut self, addr: Address) {
-        (&mut self.buffer.as_mut()[fields::SRC_ADDR])
-            .write(addr.as_bytes())
-            .unwrap();
+        self.buffer.as_mut()[fields::SRC_ADDR].copy_from_slice(addr.as_bytes());
     }
 
     pub fn set_payload_type(&mut self, payload_type: u16) {
-        (&mut self.buffer.as_mut()[fields::PAYLOAD_TYPE])
-            .write_u16::<NetworkEndian>(payload_type)
-            .unwrap();
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::PAYLOAD_TYPE], payload_type);
     }
 
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -202,6 +202,20 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Frame<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> Display for Frame<T> {
+    /// Formats a tcpdump-like summary of the frame header.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Ethernet {} > {}, type 0x{:04X}, length {}",
+            self.src_addr(),
+            self.dst_addr(),
+            self.payload_type(),
+            self.buffer.as_ref().len()
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,667 @@
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::check::internet_checksum_slices;
+use stack::repr::Ipv4Address;
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// [https://tools.ietf.org/html/rfc1812#section-5.2.7.1](https://tools.ietf.org/html/rfc1812#section-5.2.7.1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DestinationUnreachable {
+    NetUnreachable,
+    HostUnreachable,
+    ProtocolUnreachable,
+    PortUnreachable,
+    /// A packet with DF set exceeds the MTU of the next hop.
+    FragmentationNeeded {
+        next_hop_mtu: u16,
+    },
+    SourceRouteFailed,
+    NetUnknown,
+    HostUnknown,
+    SourceHostIsolated,
+    NetProhibited,
+    HostProhibited,
+    NetUnreachableForTos,
+    HostUnreachableForTos,
+    CommunicationProhibited,
+    HostPrecedenceViolation,
+    PrecedenceCutoff,
+    #[doc(hidden)]
+    ___Exhaustive,
+}
+
+impl DestinationUnreachable {
+    fn from_code(code: u8, next_hop_mtu: u16) -> Option<DestinationUnreachable> {
+        let message = match code {
+            0 => DestinationUnreachable::NetUnreachable,
+            1 => DestinationUnreachable::HostUnreachable,
+            2 => DestinationUnreachable::ProtocolUnreachable,
+            3 => DestinationUnreachable::PortUnreachable,
+            4 => DestinationUnreachable::FragmentationNeeded { next_hop_mtu },
+            5 => DestinationUnreachable::SourceRouteFailed,
+            6 => DestinationUnreachable::NetUnknown,
+            7 => DestinationUnreachable::HostUnknown,
+            8 => DestinationUnreachable::SourceHostIsolated,
+            9 => DestinationUnreachable::NetProhibited,
+            10 => DestinationUnreachable::HostProhibited,
+            11 => DestinationUnreachable::NetUnreachableForTos,
+            12 => DestinationUnreachable::HostUnreachableForTos,
+            13 => DestinationUnreachable::CommunicationProhibited,
+            14 => DestinationUnreachable::HostPrecedenceViolation,
+            15 => DestinationUnreachable::PrecedenceCutoff,
+            _ => return None,
+        };
+
+        Some(message)
+    }
+
+    fn code(&self) -> u8 {
+        match *self {
+            DestinationUnreachable::NetUnreachable => 0,
+            DestinationUnreachable::HostUnreachable => 1,
+            DestinationUnreachable::ProtocolUnreachable => 2,
+            DestinationUnreachable::PortUnreachable => 3,
+            DestinationUnreachable::FragmentationNeeded { .. } => 4,
+            DestinationUnreachable::SourceRouteFailed => 5,
+            DestinationUnreachable::NetUnknown => 6,
+            DestinationUnreachable::HostUnknown => 7,
+            DestinationUnreachable::SourceHostIsolated => 8,
+            DestinationUnreachable::NetProhibited => 9,
+            DestinationUnreachable::HostProhibited => 10,
+            DestinationUnreachable::NetUnreachableForTos => 11,
+            DestinationUnreachable::HostUnreachableForTos => 12,
+            DestinationUnreachable::CommunicationProhibited => 13,
+            DestinationUnreachable::HostPrecedenceViolation => 14,
+            DestinationUnreachable::PrecedenceCutoff => 15,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeExceeded {
+    TTLExpired,
+    /// Not all fragments of a packet arrived before the reassembly timeout.
+    FragmentReassembly,
+    #[doc(hidden)]
+    ___Exhaustive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Redirect {
+    Net,
+    Host,
+    NetForTos,
+    HostForTos,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    EchoReply {
+        id: u16,
+        seq: u16,
+    },
+    EchoRequest {
+        id: u16,
+        seq: u16,
+    },
+    DestinationUnreachable(DestinationUnreachable),
+    /// Asks the sender to reduce its rate of sending. Deprecated by RFC 6633,
+    /// but still decoded for diagnostics.
+    SourceQuench,
+    /// Advises the sender to route packets for the destination of the
+    /// original packet through a different gateway on the same network.
+    Redirect {
+        reason: Redirect,
+        gateway_addr: Ipv4Address,
+    },
+    TimeExceeded(TimeExceeded),
+    /// A header field of the original packet is invalid, where pointer is
+    /// the offset of the offending byte.
+    ParameterProblem {
+        pointer: u8,
+    },
+    /// A timestamp request or reply, with timestamps in milliseconds since
+    /// midnight UT. The timestamps occupy the first 12 bytes of the payload.
+    TimestampRequest {
+        id: u16,
+        seq: u16,
+        originate: u32,
+        receive: u32,
+        transmit: u32,
+    },
+    TimestampReply {
+        id: u16,
+        seq: u16,
+        originate: u32,
+        receive: u32,
+        transmit: u32,
+    },
+    #[doc(hidden)]
+    ___Exhaustive,
+}
+
+/// An ICMP header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub message: Message,
+    pub payload_len: usize,
+}
+
+impl Repr {
+    /// Length of the timestamps at the start of timestamp request and reply
+    /// payloads.
+    pub const TIMESTAMPS_LEN: usize = 12;
+
+    /// Returns the buffer size needed to serialize the ICMP header and
+    /// associated payload.
+    pub fn buffer_len(&self) -> usize {
+        8 + self.payload_len
+    }
+
+    /// Tries to deserialize a packet into an ICMP header.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let (id, seq) = (
+            NetworkEndian::read_u16(&packet.header()[0 .. 2]),
+            NetworkEndian::read_u16(&packet.header()[2 .. 4]),
+        );
+
+        let payload_len = packet.payload().len();
+
+        let timestamps = || -> Result<(u32, u32, u32)> {
+            if payload_len < Self::TIMESTAMPS_LEN {
+                return Err(Error::Parse(ParseError::Malformed));
+            }
+            let payload = packet.payload();
+            Ok((
+                NetworkEndian::read_u32(&payload[0 .. 4]),
+                NetworkEndian::read_u32(&payload[4 .. 8]),
+                NetworkEndian::read_u32(&payload[8 .. 12]),
+            ))
+        };
+
+        let message = match (packet._type(), packet.code()) {
+            (0, 0) => Message::EchoReply { id, seq },
+            (8, 0) => Message::EchoRequest { id, seq },
+            (3, code) => match DestinationUnreachable::from_code(code, seq) {
+                Some(message) => Message::DestinationUnreachable(message),
+                None => return Err(Error::Parse(ParseError::Malformed)),
+            },
+            (4, 0) => Message::SourceQuench,
+            (5, code) if code <= 3 => Message::Redirect {
+                reason: match code {
+                    0 => Redirect::Net,
+                    1 => Redirect::Host,
+                    2 => Redirect::NetForTos,
+                    _ => Redirect::HostForTos,
+                },
+                gateway_addr: Ipv4Address::try_new(packet.header()).unwrap(),
+            },
+            (11, 0) => Message::TimeExceeded(TimeExceeded::TTLExpired),
+            (11, 1) => Message::TimeExceeded(TimeExceeded::FragmentReassembly),
+            (12, 0) => Message::ParameterProblem {
+                pointer: packet.header()[0],
+            },
+            (13, 0) => {
+                let (originate, receive, transmit) = timestamps()?;
+                Message::TimestampRequest {
+                    id,
+                    seq,
+                    originate,
+                    receive,
+                    transmit,
+                }
+            }
+            (14, 0) => {
+                let (originate, receive, transmit) = timestamps()?;
+                Message::TimestampReply {
+                    id,
+                    seq,
+                    originate,
+                    receive,
+                    transmit,
+                }
+            }
+            _ => return Err(Error::Parse(ParseError::Malformed)),
+        };
+
+        Ok(Repr {
+            message,
+            payload_len,
+        })
+    }
+
+    /// Serializes the ICMP header into a packet.
+    ///
+    /// NOTE: Use fill_checksum() on the packet before sending over the wire!
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        fn echo<T>(packet: &mut Packet<T>, type_of: u8, id: u16, seq: u16)
+        where
+            T: AsRef<[u8]> + AsMut<[u8]>,
+        {
+            packet.set_type(type_of);
+            packet.set_code(0);
+
+            NetworkEndian::write_u16(&mut packet.header_mut()[0 .. 2], id);
+            NetworkEndian::write_u16(&mut packet.header_mut()[2 .. 4], seq);
+        };
+
+        fn error<T>(packet: &mut Packet<T>, type_of: u8, code: u8)
+        where
+            T: AsRef<[u8]> + AsMut<[u8]>,
+        {
+            packet.set_type(type_of);
+            packet.set_code(code);
+            let zeros = [0; 4];
+            packet.header_mut().copy_from_slice(&zeros[..]);
+        };
+
+        fn timestamps<T>(packet: &mut Packet<T>, timestamps: [u32; 3]) -> Result<()>
+        where
+            T: AsRef<[u8]> + AsMut<[u8]>,
+        {
+            if packet.payload().len() < Repr::TIMESTAMPS_LEN {
+                return Err(Error::Buffer(BufferError::TooSmall));
+            }
+            let payload = packet.payload_mut();
+            for (chunk, timestamp) in payload.chunks_mut(4).zip(timestamps.iter()) {
+                NetworkEndian::write_u32(chunk, *timestamp);
+            }
+            Ok(())
+        }
+
+        match self.message {
+            Message::EchoReply { id, seq } => echo(packet, 0, id, seq),
+            Message::EchoRequest { id, seq } => echo(packet, 8, id, seq),
+            Message::DestinationUnreachable(message) => {
+                error(packet, 3, message.code());
+                if let DestinationUnreachable::FragmentationNeeded { next_hop_mtu } = message {
+                    NetworkEndian::write_u16(&mut packet.header_mut()[2 .. 4], next_hop_mtu);
+                }
+            }
+            Message::SourceQuench => error(packet, 4, 0),
+            Message::Redirect {
+                reason,
+                gateway_addr,
+            } => {
+                let code = match reason {
+                    Redirect::Net => 0,
+                    Redirect::Host => 1,
+                    Redirect::NetForTos => 2,
+                    Redirect::HostForTos => 3,
+                };
+                error(packet, 5, code);
+                packet.header_mut().copy_from_slice(gateway_addr.as_bytes());
+            }
+            Message::TimeExceeded(message) => {
+                let code = match message {
+                    TimeExceeded::TTLExpired => 0,
+                    TimeExceeded::FragmentReassembly => 1,
+                    _ => unreachable!(),
+                };
+                error(packet, 11, code);
+            }
+            Message::ParameterProblem { pointer } => {
+                error(packet, 12, 0);
+                packet.header_mut()[0] = pointer;
+            }
+            Message::TimestampRequest {
+                id,
+                seq,
+                originate,
+                receive,
+                transmit,
+            } => {
+                echo(packet, 13, id, seq);
+                timestamps(packet, [originate, receive, transmit])?;
+            }
+            Message::TimestampReply {
+                id,
+                seq,
+                originate,
+                receive,
+                transmit,
+            } => {
+                echo(packet, 14, id, seq);
+                timestamps(packet, [originate, receive, transmit])?;
+            }
+            _ => unreachable!(),
+        };
+
+        Ok(())
+    }
+}
+
+/// [https://en.wikipedia.org/wiki/Internet_Control_Message_Protocol](https://en.wikipedia.org/wiki/Internet_Control_Message_Protocol)
+mod fields {
+    use core::ops::{
+        Range,
+        RangeFrom,
+    };
+
+    pub const TYPE: usize = 0;
+
+    pub const CODE: usize = 1;
+
+    pub const CHECKSUM: Range<usize> = 2 .. 4;
+
+    pub const HEADER: Range<usize> = 4 .. 8;
+
+    pub const PAYLOAD: RangeFrom<usize> = 8 ..;
+}
+
+/// View of a byte buffer as an ICMP packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 8;
+
+    pub const MAX_PACKET_LEN: usize = 65535;
+
+    /// Tries to create an ICMP packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else if buffer.as_ref().len() > Self::MAX_PACKET_LEN {
+            Err(Error::Parse(ParseError::Malformed))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Returns the length of an ICMP packet with the specified payload size.
+    pub fn buffer_len(payload_len: usize) -> usize {
+        Self::HEADER_LEN + payload_len
+    }
+
+    /// Checks if the packet has a valid encoding. This may include checksum,
+    /// field consistency, etc. checks.
+    pub fn check_encoding(&self) -> Result<()> {
+        if self.gen_packet_checksum() != 0 {
+            Err(Error::Parse(ParseError::Checksum))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calculates the packet checksum.
+    pub fn gen_packet_checksum(&self) -> u16 {
+        internet_checksum_slices(&[self.buffer.as_ref()])
+    }
+
+    pub fn _type(&self) -> u8 {
+        self.buffer.as_ref()[fields::TYPE]
+    }
+
+    pub fn code(&self) -> u8 {
+        self.buffer.as_ref()[fields::CODE]
+    }
+
+    pub fn checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::CHECKSUM])
+    }
+
+    pub fn header(&self) -> &[u8] {
+        &self.buffer.as_ref()[fields::HEADER]
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[fields::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_type(&mut self, type_of: u8) {
+        self.buffer.as_mut()[fields::TYPE] = type_of
+    }
+
+    pub fn set_code(&mut self, code: u8) {
+        self.buffer.as_mut()[fields::CODE] = code;
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::CHECKSUM], checksum)
+    }
+
+    pub fn header_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[fields::HEADER]
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[fields::PAYLOAD]
+    }
+
+    pub fn fill_checksum(&mut self) {
+        self.set_checksum(0);
+        let checksum = self.gen_packet_checksum();
+        self.set_checksum(checksum);
+    }
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    /// Formats a tcpdump-like summary of the packet header.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let header = self.header();
+        let id = NetworkEndian::read_u16(&header[0 .. 2]);
+        let seq = NetworkEndian::read_u16(&header[2 .. 4]);
+
+        match (self._type(), self.code()) {
+            (0, 0) => write!(f, "ICMPv4 echo reply, id {}, seq {}", id, seq)?,
+            (8, 0) => write!(f, "ICMPv4 echo request, id {}, seq {}", id, seq)?,
+            (3, code) => write!(f, "ICMPv4 destination unreachable, code {}", code)?,
+            (5, code) => write!(f, "ICMPv4 redirect, code {}", code)?,
+            (11, code) => write!(f, "ICMPv4 time exceeded, code {}", code)?,
+            (type_of, code) => write!(f, "ICMPv4 type {}, code {}", type_of, code)?,
+        }
+
+        write!(f, ", length {}", self.buffer.as_ref().len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer: [u8; 7] = [0; 7];
+        assert!(match Packet::try_new(&buffer[..]) {
+            Err(Error::Parse(ParseError::Truncated)) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_packet_with_empty_payload() {
+        let buffer: [u8; 8] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_eq!(packet.payload().len(), 0);
+    }
+
+    #[test]
+    fn test_packet_with_invalid_checksum() {
+        let buffer: [u8; 9] = [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Checksum))
+        );
+    }
+
+    #[test]
+    fn test_packet_getters() {
+        let buffer: [u8; 9] = [0x01, 0x02, 0xE9, 0xEf, 0x05, 0x06, 0x07, 0x08, 0x09];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet._type(), 1);
+        assert_eq!(packet.code(), 2);
+        assert_eq!(packet.checksum(), 59887);
+        assert_eq!(packet.header(), [0x05, 0x06, 0x07, 0x08]);
+        assert_eq!(packet.payload(), [0x09]);
+    }
+
+    #[test]
+    fn test_packet_setters() {
+        let mut buffer: [u8; 9] = [0; 9];
+
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            packet.set_type(1);
+            packet.set_code(2);
+            packet.set_checksum(772);
+            packet
+                .header_mut()
+                .copy_from_slice(&[0x05, 0x06, 0x07, 0x08]);
+            packet.payload_mut()[0] = 0x09;
+        }
+
+        assert_eq!(
+            &buffer[..],
+            &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09][..]
+        );
+    }
+
+    #[test]
+    fn test_fragmentation_needed_serialize_deserialize() {
+        let repr = Repr {
+            message: Message::DestinationUnreachable(DestinationUnreachable::FragmentationNeeded {
+                next_hop_mtu: 1400,
+            }),
+            payload_len: 1,
+        };
+
+        let mut buffer: [u8; 9] = [0; 9];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum();
+        }
+
+        assert_eq!(&buffer[.. 2], &[0x03, 0x04]);
+        assert_eq!(&buffer[4 .. 8], &[0x00, 0x00, 0x05, 0x78]);
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_parameter_problem_serialize_deserialize() {
+        let repr = Repr {
+            message: Message::ParameterProblem { pointer: 2 },
+            payload_len: 1,
+        };
+
+        let mut buffer: [u8; 9] = [0; 9];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum();
+        }
+
+        assert_eq!(&buffer[.. 2], &[0x0C, 0x00]);
+        assert_eq!(&buffer[4 .. 8], &[0x02, 0x00, 0x00, 0x00]);
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_destination_unreachable_codes() {
+        for code in 0 .. 16 {
+            let message = DestinationUnreachable::from_code(code, 0).unwrap();
+            assert_eq!(message.code(), code);
+        }
+        assert_eq!(DestinationUnreachable::from_code(16, 0), None);
+    }
+
+    #[test]
+    fn test_redirect_serialize_deserialize() {
+        let repr = Repr {
+            message: Message::Redirect {
+                reason: Redirect::Host,
+                gateway_addr: Ipv4Address::new([10, 0, 0, 1]),
+            },
+            payload_len: 0,
+        };
+
+        let mut buffer: [u8; 8] = [0; 8];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum();
+        }
+
+        assert_eq!(&buffer[.. 2], &[0x05, 0x01]);
+        assert_eq!(&buffer[4 ..], &[0x0A, 0x00, 0x00, 0x01]);
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_timestamp_serialize_deserialize() {
+        let repr = Repr {
+            message: Message::TimestampReply {
+                id: 1,
+                seq: 2,
+                originate: 3,
+                receive: 4,
+                transmit: 5,
+            },
+            payload_len: Repr::TIMESTAMPS_LEN,
+        };
+
+        let mut buffer: [u8; 20] = [0; 20];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum();
+        }
+
+        assert_eq!(&buffer[.. 2], &[0x0E, 0x00]);
+        assert_eq!(&buffer[4 .. 8], &[0x00, 0x01, 0x00, 0x02]);
+        assert_eq!(
+            &buffer[8 ..],
+            &[0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x05]
+        );
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+
+        let packet = Packet::try_new(&buffer[.. 19]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+}
@@ -0,0 +1,769 @@
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::repr::{
+    EthernetAddress,
+    Ipv6Address,
+    Ipv6Repr,
+};
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// [https://tools.ietf.org/html/rfc4443#section-3.1](https://tools.ietf.org/html/rfc4443#section-3.1)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DestinationUnreachable {
+    NoRoute,
+    Prohibited,
+    BeyondScope,
+    AddressUnreachable,
+    PortUnreachable,
+    SourcePolicy,
+    RejectRoute,
+}
+
+impl DestinationUnreachable {
+    fn from_code(code: u8) -> Option<DestinationUnreachable> {
+        let message = match code {
+            0 => DestinationUnreachable::NoRoute,
+            1 => DestinationUnreachable::Prohibited,
+            2 => DestinationUnreachable::BeyondScope,
+            3 => DestinationUnreachable::AddressUnreachable,
+            4 => DestinationUnreachable::PortUnreachable,
+            5 => DestinationUnreachable::SourcePolicy,
+            6 => DestinationUnreachable::RejectRoute,
+            _ => return None,
+        };
+
+        Some(message)
+    }
+
+    fn code(&self) -> u8 {
+        match *self {
+            DestinationUnreachable::NoRoute => 0,
+            DestinationUnreachable::Prohibited => 1,
+            DestinationUnreachable::BeyondScope => 2,
+            DestinationUnreachable::AddressUnreachable => 3,
+            DestinationUnreachable::PortUnreachable => 4,
+            DestinationUnreachable::SourcePolicy => 5,
+            DestinationUnreachable::RejectRoute => 6,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeExceeded {
+    HopLimitExceeded,
+    FragmentReassembly,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParameterProblem {
+    ErroneousHeader,
+    UnrecognizedNextHeader,
+    UnrecognizedOption,
+}
+
+/// Prefix information advertised by a router.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrefixInfo {
+    pub prefix_len: u8,
+    /// A combination of prefix_flags.
+    pub flags: u8,
+    pub valid_lifetime: u32,
+    pub preferred_lifetime: u32,
+    pub prefix: Ipv6Address,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    DestinationUnreachable(DestinationUnreachable),
+    /// A packet exceeds the MTU of the next hop.
+    PacketTooBig {
+        mtu: u32,
+    },
+    TimeExceeded(TimeExceeded),
+    /// A field of the original packet is invalid, where pointer is the offset
+    /// of the offending byte.
+    ParameterProblem {
+        reason: ParameterProblem,
+        pointer: u32,
+    },
+    EchoRequest {
+        id: u16,
+        seq: u16,
+    },
+    EchoReply {
+        id: u16,
+        seq: u16,
+    },
+    RouterSolicit {
+        source_lladdr: Option<EthernetAddress>,
+    },
+    RouterAdvert {
+        hop_limit: u8,
+        /// A combination of router_flags.
+        flags: u8,
+        router_lifetime: u16,
+        reachable_time: u32,
+        retrans_time: u32,
+        source_lladdr: Option<EthernetAddress>,
+        mtu: Option<u32>,
+        prefix_info: Option<PrefixInfo>,
+    },
+    NeighborSolicit {
+        target_addr: Ipv6Address,
+        source_lladdr: Option<EthernetAddress>,
+    },
+    NeighborAdvert {
+        /// A combination of neighbor_flags.
+        flags: u8,
+        target_addr: Ipv6Address,
+        target_lladdr: Option<EthernetAddress>,
+    },
+}
+
+impl Message {
+    /// Checks if this is a Neighbor Discovery message.
+    pub fn is_ndp(&self) -> bool {
+        matches!(
+            *self,
+            Message::RouterSolicit { .. }
+                | Message::RouterAdvert { .. }
+                | Message::NeighborSolicit { .. }
+                | Message::NeighborAdvert { .. }
+        )
+    }
+}
+
+/// An ICMPv6 header.
+///
+/// For Neighbor Discovery messages, the payload holds the message body and
+/// options which are (de)serialized with the header. Use Repr::ndp(...) to
+/// create a header with the corresponding payload length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub message: Message,
+    pub payload_len: usize,
+}
+
+impl Repr {
+    /// Creates a Neighbor Discovery header with a payload length matching the
+    /// message body and options.
+    pub fn ndp(message: Message) -> Repr {
+        let option = |present: bool, len: usize| if present { len } else { 0 };
+
+        let payload_len = match message {
+            Message::RouterSolicit { source_lladdr } => option(source_lladdr.is_some(), 8),
+            Message::RouterAdvert {
+                source_lladdr,
+                mtu,
+                prefix_info,
+                ..
+            } => {
+                8 + option(source_lladdr.is_some(), 8)
+                    + option(mtu.is_some(), 8)
+                    + option(prefix_info.is_some(), 32)
+            }
+            Message::NeighborSolicit { source_lladdr, .. } => {
+                16 + option(source_lladdr.is_some(), 8)
+            }
+            Message::NeighborAdvert { target_lladdr, .. } => {
+                16 + option(target_lladdr.is_some(), 8)
+            }
+            _ => 0,
+        };
+
+        Repr {
+            message,
+            payload_len,
+        }
+    }
+
+    /// Returns the buffer size needed to serialize the ICMPv6 header and
+    /// associated payload.
+    pub fn buffer_len(&self) -> usize {
+        8 + self.payload_len
+    }
+
+    /// Tries to deserialize a packet into an ICMPv6 header.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let header = packet.header();
+        let payload = packet.payload();
+        let (id, seq) = (
+            NetworkEndian::read_u16(&header[0 .. 2]),
+            NetworkEndian::read_u16(&header[2 .. 4]),
+        );
+        let word = NetworkEndian::read_u32(header);
+
+        let target_addr = || -> Result<Ipv6Address> {
+            if payload.len() < 16 {
+                return Err(Error::Parse(ParseError::Malformed));
+            }
+            Ipv6Address::try_new(&payload[.. 16])
+        };
+
+        let message = match (packet._type(), packet.code()) {
+            (1, code) => match DestinationUnreachable::from_code(code) {
+                Some(message) => Message::DestinationUnreachable(message),
+                None => return Err(Error::Parse(ParseError::Malformed)),
+            },
+            (2, 0) => Message::PacketTooBig { mtu: word },
+            (3, 0) => Message::TimeExceeded(TimeExceeded::HopLimitExceeded),
+            (3, 1) => Message::TimeExceeded(TimeExceeded::FragmentReassembly),
+            (4, code) if code <= 2 => Message::ParameterProblem {
+                reason: match code {
+                    0 => ParameterProblem::ErroneousHeader,
+                    1 => ParameterProblem::UnrecognizedNextHeader,
+                    _ => ParameterProblem::UnrecognizedOption,
+                },
+                pointer: word,
+            },
+            (128, 0) => Message::EchoRequest { id, seq },
+            (129, 0) => Message::EchoReply { id, seq },
+            (133, 0) => {
+                let options = NdpOptions::parse(payload)?;
+                Message::RouterSolicit {
+                    source_lladdr: options.source_lladdr,
+                }
+            }
+            (134, 0) => {
+                if payload.len() < 8 {
+                    return Err(Error::Parse(ParseError::Malformed));
+                }
+                let options = NdpOptions::parse(&payload[8 ..])?;
+                Message::RouterAdvert {
+                    hop_limit: header[0],
+                    flags: header[1],
+                    router_lifetime: seq,
+                    reachable_time: NetworkEndian::read_u32(&payload[0 .. 4]),
+                    retrans_time: NetworkEndian::read_u32(&payload[4 .. 8]),
+                    source_lladdr: options.source_lladdr,
+                    mtu: options.mtu,
+                    prefix_info: options.prefix_info,
+                }
+            }
+            (135, 0) => {
+                let target_addr = target_addr()?;
+                let options = NdpOptions::parse(&payload[16 ..])?;
+                Message::NeighborSolicit {
+                    target_addr,
+                    source_lladdr: options.source_lladdr,
+                }
+            }
+            (136, 0) => {
+                let target_addr = target_addr()?;
+                let options = NdpOptions::parse(&payload[16 ..])?;
+                Message::NeighborAdvert {
+                    flags: header[0],
+                    target_addr,
+                    target_lladdr: options.target_lladdr,
+                }
+            }
+            _ => return Err(Error::Parse(ParseError::Malformed)),
+        };
+
+        Ok(Repr {
+            message,
+            payload_len: payload.len(),
+        })
+    }
+
+    /// Serializes the ICMPv6 header into a packet.
+    ///
+    /// NOTE: Use fill_checksum() on the packet before sending over the wire!
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        fn header<T>(packet: &mut Packet<T>, type_of: u8, code: u8, word: u32)
+        where
+            T: AsRef<[u8]> + AsMut<[u8]>,
+        {
+            packet.set_type(type_of);
+            packet.set_code(code);
+            NetworkEndian::write_u32(packet.header_mut(), word);
+        }
+
+        fn echo<T>(packet: &mut Packet<T>, type_of: u8, id: u16, seq: u16)
+        where
+            T: AsRef<[u8]> + AsMut<[u8]>,
+        {
+            header(packet, type_of, 0, ((id as u32) << 16) | (seq as u32));
+        }
+
+        if self.message.is_ndp() && packet.payload().len() < Repr::ndp(self.message).payload_len {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        match self.message {
+            Message::DestinationUnreachable(message) => header(packet, 1, message.code(), 0),
+            Message::PacketTooBig { mtu } => header(packet, 2, 0, mtu),
+            Message::TimeExceeded(message) => {
+                let code = match message {
+                    TimeExceeded::HopLimitExceeded => 0,
+                    TimeExceeded::FragmentReassembly => 1,
+                };
+                header(packet, 3, code, 0);
+            }
+            Message::ParameterProblem { reason, pointer } => {
+                let code = match reason {
+                    ParameterProblem::ErroneousHeader => 0,
+                    ParameterProblem::UnrecognizedNextHeader => 1,
+                    ParameterProblem::UnrecognizedOption => 2,
+                };
+                header(packet, 4, code, pointer);
+            }
+            Message::EchoRequest { id, seq } => echo(packet, 128, id, seq),
+            Message::EchoReply { id, seq } => echo(packet, 129, id, seq),
+            Message::RouterSolicit { source_lladdr } => {
+                header(packet, 133, 0, 0);
+                let payload = packet.payload_mut();
+                write_lladdr_option(payload, ndp_options::SOURCE_LLADDR, source_lladdr);
+            }
+            Message::RouterAdvert {
+                hop_limit,
+                flags,
+                router_lifetime,
+                reachable_time,
+                retrans_time,
+                source_lladdr,
+                mtu,
+                prefix_info,
+            } => {
+                let word = ((hop_limit as u32) << 24) | ((flags as u32) << 16);
+                header(packet, 134, 0, word | router_lifetime as u32);
+                let payload = packet.payload_mut();
+                NetworkEndian::write_u32(&mut payload[0 .. 4], reachable_time);
+                NetworkEndian::write_u32(&mut payload[4 .. 8], retrans_time);
+                let mut offset = 8;
+                offset += write_lladdr_option(
+                    &mut payload[offset ..],
+                    ndp_options::SOURCE_LLADDR,
+                    source_lladdr,
+                );
+                if let Some(mtu) = mtu {
+                    let option = &mut payload[offset .. offset + 8];
+                    option[.. 4].copy_from_slice(&[ndp_options::MTU, 1, 0, 0]);
+                    NetworkEndian::write_u32(&mut option[4 .. 8], mtu);
+                    offset += 8;
+                }
+                if let Some(prefix_info) = prefix_info {
+                    let payload = &mut payload[offset .. offset + 32];
+                    payload[.. 4].copy_from_slice(&[
+                        ndp_options::PREFIX_INFO,
+                        4,
+                        prefix_info.prefix_len,
+                        prefix_info.flags,
+                    ]);
+                    NetworkEndian::write_u32(&mut payload[4 .. 8], prefix_info.valid_lifetime);
+                    NetworkEndian::write_u32(&mut payload[8 .. 12], prefix_info.preferred_lifetime);
+                    payload[12 .. 16].copy_from_slice(&[0; 4]);
+                    payload[16 .. 32].copy_from_slice(prefix_info.prefix.as_bytes());
+                }
+            }
+            Message::NeighborSolicit {
+                target_addr,
+                source_lladdr,
+            } => {
+                header(packet, 135, 0, 0);
+                let payload = packet.payload_mut();
+                payload[.. 16].copy_from_slice(target_addr.as_bytes());
+                write_lladdr_option(
+                    &mut payload[16 ..],
+                    ndp_options::SOURCE_LLADDR,
+                    source_lladdr,
+                );
+            }
+            Message::NeighborAdvert {
+                flags,
+                target_addr,
+                target_lladdr,
+            } => {
+                header(packet, 136, 0, (flags as u32) << 24);
+                let payload = packet.payload_mut();
+                payload[.. 16].copy_from_slice(target_addr.as_bytes());
+                write_lladdr_option(
+                    &mut payload[16 ..],
+                    ndp_options::TARGET_LLADDR,
+                    target_lladdr,
+                );
+            }
+        };
+
+        Ok(())
+    }
+}
+
+/// Writes a link-layer address option (if present) to the front of a buffer,
+/// returning the number of bytes written.
+fn write_lladdr_option(buffer: &mut [u8], kind: u8, lladdr: Option<EthernetAddress>) -> usize {
+    match lladdr {
+        Some(lladdr) => {
+            buffer[0] = kind;
+            buffer[1] = 1;
+            buffer[2 .. 8].copy_from_slice(lladdr.as_bytes());
+            8
+        }
+        None => 0,
+    }
+}
+
+/// The Neighbor Discovery options understood by the stack.
+#[derive(Default)]
+struct NdpOptions {
+    source_lladdr: Option<EthernetAddress>,
+    target_lladdr: Option<EthernetAddress>,
+    mtu: Option<u32>,
+    prefix_info: Option<PrefixInfo>,
+}
+
+impl NdpOptions {
+    /// Parses a sequence of options, ignoring unknown options, see RFC 4861
+    /// section 4.6.
+    fn parse(mut buffer: &[u8]) -> Result<NdpOptions> {
+        let mut options = NdpOptions::default();
+
+        while !buffer.is_empty() {
+            if buffer.len() < 8 || buffer[1] == 0 || buffer.len() < (buffer[1] as usize) * 8 {
+                return Err(Error::Parse(ParseError::Malformed));
+            }
+
+            let (option, rest) = buffer.split_at((buffer[1] as usize) * 8);
+            buffer = rest;
+
+            match option[0] {
+                ndp_options::SOURCE_LLADDR => {
+                    options.source_lladdr = Some(EthernetAddress::try_new(&option[2 .. 8])?);
+                }
+                ndp_options::TARGET_LLADDR => {
+                    options.target_lladdr = Some(EthernetAddress::try_new(&option[2 .. 8])?);
+                }
+                ndp_options::MTU => {
+                    options.mtu = Some(NetworkEndian::read_u32(&option[4 .. 8]));
+                }
+                ndp_options::PREFIX_INFO if option.len() == 32 => {
+                    options.prefix_info = Some(PrefixInfo {
+                        prefix_len: option[2],
+                        flags: option[3],
+                        valid_lifetime: NetworkEndian::read_u32(&option[4 .. 8]),
+                        preferred_lifetime: NetworkEndian::read_u32(&option[8 .. 12]),
+                        prefix: Ipv6Address::try_new(&option[16 .. 32])?,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/// [https://tools.ietf.org/html/rfc4861#section-4.6](https://tools.ietf.org/html/rfc4861#section-4.6)
+pub mod ndp_options {
+    pub const SOURCE_LLADDR: u8 = 1;
+
+    pub const TARGET_LLADDR: u8 = 2;
+
+    pub const PREFIX_INFO: u8 = 3;
+
+    pub const MTU: u8 = 5;
+}
+
+/// Flags of router advertisements.
+pub mod router_flags {
+    /// Addresses are available via DHCPv6.
+    pub const MANAGED: u8 = 0x80;
+
+    /// Other configuration is available via DHCPv6.
+    pub const OTHER: u8 = 0x40;
+}
+
+/// Flags of neighbor advertisements.
+pub mod neighbor_flags {
+    /// The sender is a router.
+    pub const ROUTER: u8 = 0x80;
+
+    /// The advertisement was sent in response to a solicitation.
+    pub const SOLICITED: u8 = 0x40;
+
+    /// The advertisement should override an existing cache entry.
+    pub const OVERRIDE: u8 = 0x20;
+}
+
+/// Flags of prefix information options.
+pub mod prefix_flags {
+    /// The prefix can be used for on-link determination.
+    pub const ON_LINK: u8 = 0x80;
+
+    /// The prefix can be used for stateless address autoconfiguration.
+    pub const AUTONOMOUS: u8 = 0x40;
+}
+
+/// [https://tools.ietf.org/html/rfc4443#section-2.1](https://tools.ietf.org/html/rfc4443#section-2.1)
+mod fields {
+    use core::ops::{
+        Range,
+        RangeFrom,
+    };
+
+    pub const TYPE: usize = 0;
+
+    pub const CODE: usize = 1;
+
+    pub const CHECKSUM: Range<usize> = 2 .. 4;
+
+    pub const HEADER: Range<usize> = 4 .. 8;
+
+    pub const PAYLOAD: RangeFrom<usize> = 8 ..;
+}
+
+/// View of a byte buffer as an ICMPv6 packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 8;
+
+    /// Tries to create an ICMPv6 packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Returns the length of an ICMPv6 packet with the specified payload size.
+    pub fn buffer_len(payload_len: usize) -> usize {
+        Self::HEADER_LEN + payload_len
+    }
+
+    /// Checks if the packet has a valid encoding. Unlike ICMPv4, the checksum
+    /// covers a pseudo-header from the IPv6 header.
+    pub fn check_encoding(&self, ipv6_repr: &Ipv6Repr) -> Result<()> {
+        if self.gen_packet_checksum(ipv6_repr) != 0 {
+            Err(Error::Parse(ParseError::Checksum))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calculates the packet checksum.
+    pub fn gen_packet_checksum(&self, ipv6_repr: &Ipv6Repr) -> u16 {
+        ipv6_repr.gen_checksum_with_pseudo_header(self.buffer.as_ref())
+    }
+
+    pub fn _type(&self) -> u8 {
+        self.buffer.as_ref()[fields::TYPE]
+    }
+
+    pub fn code(&self) -> u8 {
+        self.buffer.as_ref()[fields::CODE]
+    }
+
+    pub fn checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::CHECKSUM])
+    }
+
+    pub fn header(&self) -> &[u8] {
+        &self.buffer.as_ref()[fields::HEADER]
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[fields::PAYLOAD]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_type(&mut self, type_of: u8) {
+        self.buffer.as_mut()[fields::TYPE] = type_of
+    }
+
+    pub fn set_code(&mut self, code: u8) {
+        self.buffer.as_mut()[fields::CODE] = code;
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::CHECKSUM], checksum)
+    }
+
+    pub fn header_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[fields::HEADER]
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer.as_mut()[fields::PAYLOAD]
+    }
+
+    pub fn fill_checksum(&mut self, ipv6_repr: &Ipv6Repr) {
+        self.set_checksum(0);
+        let checksum = self.gen_packet_checksum(ipv6_repr);
+        self.set_checksum(checksum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::repr::Ipv6Protocol;
+
+    fn addr(s: &str) -> Ipv6Address {
+        s.parse().unwrap()
+    }
+
+    fn ipv6_repr(payload_len: usize) -> Ipv6Repr {
+        Ipv6Repr {
+            src_addr: addr("fe80::1"),
+            dst_addr: addr("ff02::1:ff00:2"),
+            protocol: Ipv6Protocol::ICMPv6,
+            payload_len: payload_len as u16,
+            hop_limit: 255,
+            traffic_class: 0,
+            flow_label: 0,
+        }
+    }
+
+    fn round_trip(repr: Repr) -> Vec<u8> {
+        let mut buffer = vec![0; repr.buffer_len()];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum(&ipv6_repr(repr.buffer_len()));
+        }
+
+        {
+            let packet = Packet::try_new(&buffer[..]).unwrap();
+            assert_matches!(packet.check_encoding(&ipv6_repr(repr.buffer_len())), Ok(_));
+            assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+        }
+
+        buffer
+    }
+
+    #[test]
+    fn test_packet_with_invalid_checksum() {
+        let buffer: [u8; 8] = [128, 0, 0, 0, 0, 1, 0, 2];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(&ipv6_repr(8)),
+            Err(Error::Parse(ParseError::Checksum))
+        );
+    }
+
+    #[test]
+    fn test_echo_and_errors() {
+        let buffer = round_trip(Repr {
+            message: Message::EchoRequest { id: 1, seq: 2 },
+            payload_len: 4,
+        });
+        assert_eq!(&buffer[4 .. 8], &[0, 1, 0, 2]);
+
+        let buffer = round_trip(Repr {
+            message: Message::PacketTooBig { mtu: 1280 },
+            payload_len: 0,
+        });
+        assert_eq!(&buffer[.. 2], &[2, 0]);
+        assert_eq!(&buffer[4 .. 8], &[0, 0, 5, 0]);
+
+        round_trip(Repr {
+            message: Message::DestinationUnreachable(DestinationUnreachable::PortUnreachable),
+            payload_len: 0,
+        });
+        round_trip(Repr {
+            message: Message::TimeExceeded(TimeExceeded::FragmentReassembly),
+            payload_len: 0,
+        });
+        round_trip(Repr {
+            message: Message::ParameterProblem {
+                reason: ParameterProblem::UnrecognizedNextHeader,
+                pointer: 6,
+            },
+            payload_len: 0,
+        });
+    }
+
+    #[test]
+    fn test_neighbor_solicit_and_advert() {
+        let buffer = round_trip(Repr::ndp(Message::NeighborSolicit {
+            target_addr: addr("fe80::2"),
+            source_lladdr: Some(EthernetAddress::new([1, 2, 3, 4, 5, 6])),
+        }));
+        assert_eq!(buffer.len(), 32);
+        assert_eq!(&buffer[24 ..], &[1, 1, 1, 2, 3, 4, 5, 6]);
+
+        let buffer = round_trip(Repr::ndp(Message::NeighborAdvert {
+            flags: neighbor_flags::SOLICITED | neighbor_flags::OVERRIDE,
+            target_addr: addr("fe80::2"),
+            target_lladdr: Some(EthernetAddress::new([1, 2, 3, 4, 5, 6])),
+        }));
+        assert_eq!(buffer[4], 0x60);
+
+        round_trip(Repr::ndp(Message::NeighborSolicit {
+            target_addr: addr("fe80::2"),
+            source_lladdr: None,
+        }));
+    }
+
+    #[test]
+    fn test_router_solicit_and_advert() {
+        round_trip(Repr::ndp(Message::RouterSolicit {
+            source_lladdr: Some(EthernetAddress::new([1, 2, 3, 4, 5, 6])),
+        }));
+
+        let buffer = round_trip(Repr::ndp(Message::RouterAdvert {
+            hop_limit: 64,
+            flags: router_flags::OTHER,
+            router_lifetime: 1800,
+            reachable_time: 0,
+            retrans_time: 0,
+            source_lladdr: Some(EthernetAddress::new([1, 2, 3, 4, 5, 6])),
+            mtu: Some(1500),
+            prefix_info: Some(PrefixInfo {
+                prefix_len: 64,
+                flags: prefix_flags::ON_LINK | prefix_flags::AUTONOMOUS,
+                valid_lifetime: 86400,
+                preferred_lifetime: 14400,
+                prefix: addr("2001:db8::"),
+            }),
+        }));
+        assert_eq!(buffer.len(), 64);
+    }
+
+    #[test]
+    fn test_ndp_options_malformed() {
+        let mut buffer = [0; 32];
+        buffer[0] = 135;
+        buffer[24] = ndp_options::SOURCE_LLADDR;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+
+        // Unknown options are skipped.
+        buffer[24] = 0xFF;
+        buffer[25] = 1;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_eq!(
+            Repr::deserialize(&packet).unwrap().message,
+            Message::NeighborSolicit {
+                target_addr: Ipv6Address::UNSPECIFIED,
+                source_lladdr: None,
+            }
+        );
+    }
+}
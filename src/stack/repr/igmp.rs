@@ -0,0 +1,248 @@
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::check::internet_checksum_slices;
+use stack::repr::Ipv4Address;
+use {
+    Error,
+    ParseError,
+    Result,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// A general (group_addr is 0.0.0.0) or group specific membership query
+    /// with the max response time in units of 1/10 second.
+    MembershipQuery {
+        max_resp_time: u8,
+        group_addr: Ipv4Address,
+    },
+    MembershipReport {
+        group_addr: Ipv4Address,
+    },
+    LeaveGroup {
+        group_addr: Ipv4Address,
+    },
+}
+
+/// An IGMPv2 message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub message: Message,
+}
+
+impl Repr {
+    /// Returns the buffer size needed to serialize the IGMP message.
+    pub fn buffer_len(&self) -> usize {
+        Packet::<&[u8]>::PACKET_LEN
+    }
+
+    /// Tries to deserialize a packet into an IGMP message.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let group_addr = packet.group_addr();
+
+        let message = match packet._type() {
+            types::MEMBERSHIP_QUERY => Message::MembershipQuery {
+                max_resp_time: packet.max_resp_time(),
+                group_addr,
+            },
+            types::MEMBERSHIP_REPORT_V1 | types::MEMBERSHIP_REPORT_V2 => {
+                Message::MembershipReport { group_addr }
+            }
+            types::LEAVE_GROUP => Message::LeaveGroup { group_addr },
+            _ => return Err(Error::Parse(ParseError::Malformed)),
+        };
+
+        Ok(Repr { message })
+    }
+
+    /// Serializes the IGMP message into a packet.
+    ///
+    /// NOTE: Use fill_checksum() on the packet before sending over the wire!
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let (type_of, max_resp_time, group_addr) = match self.message {
+            Message::MembershipQuery {
+                max_resp_time,
+                group_addr,
+            } => (types::MEMBERSHIP_QUERY, max_resp_time, group_addr),
+            Message::MembershipReport { group_addr } => {
+                (types::MEMBERSHIP_REPORT_V2, 0, group_addr)
+            }
+            Message::LeaveGroup { group_addr } => (types::LEAVE_GROUP, 0, group_addr),
+        };
+
+        packet.set_type(type_of);
+        packet.set_max_resp_time(max_resp_time);
+        packet.set_group_addr(group_addr);
+
+        Ok(())
+    }
+}
+
+/// [https://tools.ietf.org/html/rfc2236#section-2](https://tools.ietf.org/html/rfc2236#section-2)
+pub mod types {
+    pub const MEMBERSHIP_QUERY: u8 = 0x11;
+
+    pub const MEMBERSHIP_REPORT_V1: u8 = 0x12;
+
+    pub const MEMBERSHIP_REPORT_V2: u8 = 0x16;
+
+    pub const LEAVE_GROUP: u8 = 0x17;
+}
+
+/// [https://tools.ietf.org/html/rfc2236#section-2](https://tools.ietf.org/html/rfc2236#section-2)
+mod fields {
+    use core::ops::Range;
+
+    pub const TYPE: usize = 0;
+
+    pub const MAX_RESP_TIME: usize = 1;
+
+    pub const CHECKSUM: Range<usize> = 2 .. 4;
+
+    pub const GROUP_ADDR: Range<usize> = 4 .. 8;
+}
+
+/// View of a byte buffer as an IGMP packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const PACKET_LEN: usize = 8;
+
+    /// Tries to create an IGMP packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::PACKET_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Checks if the packet has a valid encoding. This may include checksum,
+    /// field consistency, etc. checks.
+    pub fn check_encoding(&self) -> Result<()> {
+        if self.gen_packet_checksum() != 0 {
+            Err(Error::Parse(ParseError::Checksum))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calculates the packet checksum.
+    pub fn gen_packet_checksum(&self) -> u16 {
+        internet_checksum_slices(&[self.buffer.as_ref()])
+    }
+
+    pub fn _type(&self) -> u8 {
+        self.buffer.as_ref()[fields::TYPE]
+    }
+
+    pub fn max_resp_time(&self) -> u8 {
+        self.buffer.as_ref()[fields::MAX_RESP_TIME]
+    }
+
+    pub fn checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::CHECKSUM])
+    }
+
+    pub fn group_addr(&self) -> Ipv4Address {
+        Ipv4Address::try_new(&self.buffer.as_ref()[fields::GROUP_ADDR]).unwrap()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_type(&mut self, type_of: u8) {
+        self.buffer.as_mut()[fields::TYPE] = type_of;
+    }
+
+    pub fn set_max_resp_time(&mut self, max_resp_time: u8) {
+        self.buffer.as_mut()[fields::MAX_RESP_TIME] = max_resp_time;
+    }
+
+    pub fn set_checksum(&mut self, checksum: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::CHECKSUM], checksum)
+    }
+
+    pub fn set_group_addr(&mut self, group_addr: Ipv4Address) {
+        self.buffer.as_mut()[fields::GROUP_ADDR].copy_from_slice(group_addr.as_bytes());
+    }
+
+    pub fn fill_checksum(&mut self) {
+        self.set_checksum(0);
+        let checksum = self.gen_packet_checksum();
+        self.set_checksum(checksum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer: [u8; 7] = [0; 7];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_packet_getters() {
+        let buffer: [u8; 8] = [0x11, 0x64, 0xEE, 0x9B, 0x00, 0x00, 0x00, 0x00];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet._type(), types::MEMBERSHIP_QUERY);
+        assert_eq!(packet.max_resp_time(), 100);
+        assert_eq!(packet.checksum(), 0xEE9B);
+        assert_eq!(packet.group_addr(), Ipv4Address::new([0, 0, 0, 0]));
+        assert_eq!(
+            Repr::deserialize(&packet).unwrap(),
+            Repr {
+                message: Message::MembershipQuery {
+                    max_resp_time: 100,
+                    group_addr: Ipv4Address::new([0, 0, 0, 0]),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_report_serialize_deserialize() {
+        let repr = Repr {
+            message: Message::MembershipReport {
+                group_addr: Ipv4Address::new([224, 0, 0, 251]),
+            },
+        };
+
+        let mut buffer: [u8; 8] = [0; 8];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.fill_checksum();
+        }
+
+        assert_eq!(
+            &buffer[..],
+            &[0x16, 0x00, 0x09, 0x04, 0xE0, 0x00, 0x00, 0xFB][..]
+        );
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+}
@@ -0,0 +1,1365 @@
+use alloc::vec::Vec;
+use core::fmt::{
+    Debug,
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use core::net::Ipv4Addr as StdIpv4Addr;
+use core::ops::Deref;
+use core::result::Result as StdResult;
+use core::str::FromStr;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::check::{
+    checksum_adjust,
+    internet_checksum_slices,
+};
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// [IPv4 address](https://en.wikipedia.org/wiki/IPv4) in network byte order.
+/// See [this](https://en.wikipedia.org/wiki/Classful_network) for a description
+/// of IPv4 address classes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address([u8; 4]);
+
+impl Address {
+    /// The unspecified address, i.e. 0.0.0.0.
+    pub const UNSPECIFIED: Address = Address([0; 4]);
+
+    /// The limited broadcast address, i.e. 255.255.255.255.
+    pub const BROADCAST: Address = Address([0xFF; 4]);
+
+    /// The all systems multicast group, i.e. 224.0.0.1.
+    pub const ALL_HOSTS: Address = Address([224, 0, 0, 1]);
+
+    /// The all routers multicast group, i.e. 224.0.0.2.
+    pub const ALL_ROUTERS: Address = Address([224, 0, 0, 2]);
+
+    /// The multicast DNS group, i.e. 224.0.0.251.
+    pub const MDNS: Address = Address([224, 0, 0, 251]);
+
+    /// Creates an IPv4 address from a network byte order buffer.
+    pub fn new(addr: [u8; 4]) -> Address {
+        Address(addr)
+    }
+
+    /// Tries to creates an IPv4 address from a network byte order slice.
+    pub fn try_new(addr: &[u8]) -> Result<Address> {
+        if addr.len() != 4 {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let mut _addr: [u8; 4] = [0; 4];
+        _addr.clone_from_slice(addr);
+        Ok(Address(_addr))
+    }
+
+    /// Returns a reference to the network byte order representation of the
+    /// address.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Returns an integer representation of the address in host byte order.
+    pub fn as_int(&self) -> u32 {
+        NetworkEndian::read_u32(&self.0[..])
+    }
+
+    // Checks if this is the unspecified address.
+    pub fn is_unspecified(&self) -> bool {
+        self.0 == [0; 4]
+    }
+
+    // Checks if this is a unicast address.
+    pub fn is_unicast(&self) -> bool {
+        !(self.is_multicast() || self.is_reserved())
+    }
+
+    // Checks if this is a multicast address.
+    pub fn is_multicast(&self) -> bool {
+        (self.0[0] & 0b11110000) == 0b11100000
+    }
+
+    // Checks if this is a reserved address.
+    pub fn is_reserved(&self) -> bool {
+        (self.0[0] & 0b11110000) == 0b11110000
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}.{}.{}.{}", self.0[0], self.0[1], self.0[2], self.0[3])
+    }
+}
+
+impl From<u32> for Address {
+    fn from(addr: u32) -> Address {
+        let mut bytes = [0; 4];
+        NetworkEndian::write_u32(&mut bytes[..], addr);
+        Address(bytes)
+    }
+}
+
+impl<'a> From<&'a StdIpv4Addr> for Address {
+    fn from(addr: &'a StdIpv4Addr) -> Address {
+        Address(addr.octets())
+    }
+}
+
+impl Into<StdIpv4Addr> for Address {
+    fn into(self) -> StdIpv4Addr {
+        StdIpv4Addr::new(self.0[0], self.0[1], self.0[2], self.0[3])
+    }
+}
+
+impl FromStr for Address {
+    type Err = ();
+
+    /// Parses an Ipv4 address from an A.B.C.D style string.
+    fn from_str(addr: &str) -> StdResult<Address, Self::Err> {
+        let (bytes, unknown): (Vec<_>, Vec<_>) = addr.split(".")
+            .map(|token| token.parse::<u8>())
+            .partition(|byte| !byte.is_err());
+
+        if bytes.len() != 4 || unknown.len() > 0 {
+            return Err(());
+        }
+
+        let bytes: Vec<_> = bytes.into_iter().map(|byte| byte.unwrap()).collect();
+
+        let mut ipv4: [u8; 4] = [0; 4];
+        ipv4.clone_from_slice(&bytes);
+
+        Ok(Address::new(ipv4))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AddressCidr {
+    address: Address,
+    subnet_len: u32,
+}
+
+impl AddressCidr {
+    /// Creates an IPv4 address with a subnet mask.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the subnet mask is longer than 32 bits.
+    pub fn new(address: Address, subnet_len: usize) -> AddressCidr {
+        assert!(subnet_len <= 32);
+
+        AddressCidr {
+            address,
+            subnet_len: subnet_len as u32,
+        }
+    }
+
+    /// Returns the length of the subnet mask in bits.
+    pub fn subnet_len(&self) -> usize {
+        self.subnet_len as usize
+    }
+
+    /// Returns the network address of the subnet, i.e. the address with all
+    /// host bits cleared.
+    pub fn network(&self) -> Address {
+        Address::from(self.address.as_int() & self.mask())
+    }
+
+    /// Checks if the address is a member of the subnet.
+    pub fn is_member(&self, address: Address) -> bool {
+        let mask = self.mask();
+        (address.as_int() & mask) == (self.address.as_int() & mask)
+    }
+
+    /// Checks if the address is a broadcast address for the subnet.
+    pub fn is_broadcast(&self, address: Address) -> bool {
+        address == self.broadcast()
+    }
+
+    /// Creates an IPv4 broadcast address for the subnet.
+    pub fn broadcast(&self) -> Address {
+        let mask = self.mask();
+        let addr = (self.address.as_int() & mask) | (!mask);
+        Address::from(addr)
+    }
+
+    fn mask(&self) -> u32 {
+        !(0xFFFFFFFFu32.checked_shr(self.subnet_len).unwrap_or(0))
+    }
+}
+
+impl Deref for AddressCidr {
+    type Target = Address;
+
+    fn deref(&self) -> &Address {
+        &self.address
+    }
+}
+
+impl Display for AddressCidr {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}/{}", self.address, self.subnet_len)
+    }
+}
+
+impl FromStr for AddressCidr {
+    type Err = ();
+
+    /// Parses an IPv4 address with a subnet mask from an A.B.C.D/N style
+    /// string.
+    fn from_str(addr: &str) -> StdResult<AddressCidr, Self::Err> {
+        let mut tokens = addr.splitn(2, '/');
+        let address = Address::from_str(tokens.next().unwrap())?;
+        let subnet_len = tokens
+            .next()
+            .and_then(|subnet_len| subnet_len.parse::<usize>().ok())
+            .ok_or(())?;
+
+        if subnet_len > 32 {
+            return Err(());
+        }
+
+        Ok(AddressCidr::new(address, subnet_len))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+/// A set of supported protocols over IPv4.
+pub enum Protocol {
+    ICMP = protocols::ICMP,
+    IGMP = protocols::IGMP,
+    UDP = protocols::UDP,
+    TCP = protocols::TCP,
+    #[doc(hidden)]
+    __Nonexhaustive,
+}
+
+/// An IPv4 header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub src_addr: Address,
+    pub dst_addr: Address,
+    pub protocol: Protocol,
+    pub payload_len: u16,
+    pub ttl: u8,
+    /// Differentiated services code point.
+    pub dscp: u8,
+    /// A combination of DONT_FRAGMENT and NOT_LAST flags.
+    pub flags: u8,
+    pub options: Options,
+}
+
+impl Repr {
+    /// A sensible default TTL for outgoing packets.
+    pub const DEFAULT_TTL: u8 = 64;
+
+    /// Returns the length of the IPv4 header (including options!) when
+    /// serialized to a buffer.
+    pub fn header_len(&self) -> usize {
+        Packet::<&[u8]>::MIN_HEADER_LEN + self.options.padded_len()
+    }
+
+    /// Returns the buffer size needed to serialize the IPv4 header and
+    /// associated payload.
+    pub fn buffer_len(&self) -> usize {
+        self.header_len() + (self.payload_len as usize)
+    }
+
+    /// Tries to deserialize a packet into an IPv4 header.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        Ok(Repr {
+            src_addr: packet.src_addr(),
+            dst_addr: packet.dst_addr(),
+            protocol: match packet.protocol() {
+                protocols::ICMP => Protocol::ICMP,
+                protocols::IGMP => Protocol::IGMP,
+                protocols::TCP => Protocol::TCP,
+                protocols::UDP => Protocol::UDP,
+                _ => return Err(Error::Parse(ParseError::Malformed)),
+            },
+            payload_len: packet.payload().len() as u16,
+            ttl: packet.ttl(),
+            dscp: packet.dscp(),
+            flags: packet.flags(),
+            options: Options::try_new(packet.options())?,
+        })
+    }
+
+    /// Serializes the IPv4 header into a packet and performs a checksum update.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        self.serialize_with_checksum(packet, true);
+    }
+
+    /// Serializes the IPv4 header into a packet, leaving the checksum zeroed
+    /// unless fill_checksum is set, e.g. for devices which offload checksums.
+    pub fn serialize_with_checksum<T>(&self, packet: &mut Packet<T>, fill_checksum: bool)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        let header_len = self.header_len();
+
+        packet.set_ip_version(4);
+        packet.set_header_len((header_len / 4) as u8);
+        packet.set_dscp(self.dscp);
+        packet.set_ecn(0);
+        packet.set_packet_len((header_len + self.payload_len as usize) as u16);
+        packet.set_identification(0);
+        packet.set_fragment_offset(0);
+        packet.set_flags(self.flags);
+        packet.set_ttl(self.ttl);
+        packet.set_protocol(self.protocol as u8);
+        packet.set_header_checksum(0);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+
+        // Pad options with EOL (zeros) to a multiple of 32 bits.
+        let options = packet.options_mut();
+        let options_len = self.options.as_bytes().len();
+        options[.. options_len].copy_from_slice(self.options.as_bytes());
+        for byte in options[options_len ..].iter_mut() {
+            *byte = 0;
+        }
+
+        if fill_checksum {
+            let checksum = packet.gen_header_checksum();
+            packet.set_header_checksum(checksum);
+        }
+    }
+
+    /// Generates a checksum for the byte buffer, using a pseudo-header
+    /// corresponding to this IP header.
+    pub fn gen_checksum_with_pseudo_header(&self, buffer: &[u8]) -> u16 {
+        let mut ip_pseudo_header = [0; 12];
+        (&mut ip_pseudo_header[0 .. 4]).copy_from_slice(self.src_addr.as_bytes());
+        (&mut ip_pseudo_header[4 .. 8]).copy_from_slice(self.dst_addr.as_bytes());
+        ip_pseudo_header[9] = self.protocol as u8;
+        NetworkEndian::write_u16(&mut ip_pseudo_header[10 .. 12], self.payload_len);
+
+        internet_checksum_slices(&[&ip_pseudo_header[..], buffer])
+    }
+}
+
+/// An IPv4 option.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Ipv4Option<'a> {
+    EOL,
+    NoOp,
+    /// Addresses of routers the packet passed through, where pointer is the
+    /// 1-based offset (from the start of the option) of the next free slot.
+    RecordRoute {
+        pointer: u8,
+        route: &'a [u8],
+    },
+    /// Timestamps and optionally addresses of routers the packet passed
+    /// through, see RFC 791.
+    Timestamp {
+        pointer: u8,
+        overflow: u8,
+        flag: u8,
+        data: &'a [u8],
+    },
+    /// Asks routers to examine the packet more closely, see RFC 2113.
+    RouterAlert(u16),
+    Unknown {
+        kind: u8,
+        payload: &'a [u8],
+    },
+}
+
+/// [https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml](https://www.iana.org/assignments/ip-parameters/ip-parameters.xhtml)
+pub mod option_kinds {
+    pub const EOL: u8 = 0;
+
+    pub const NO_OP: u8 = 1;
+
+    pub const RECORD_ROUTE: u8 = 7;
+
+    pub const TIMESTAMP: u8 = 68;
+
+    pub const ROUTER_ALERT: u8 = 148;
+}
+
+impl<'a> Ipv4Option<'a> {
+    /// Returns the number of bytes needed to serialize the option.
+    pub fn buffer_len(&self) -> usize {
+        match *self {
+            Ipv4Option::EOL | Ipv4Option::NoOp => 1,
+            Ipv4Option::RecordRoute { route, .. } => 3 + route.len(),
+            Ipv4Option::Timestamp { data, .. } => 4 + data.len(),
+            Ipv4Option::RouterAlert(_) => 4,
+            Ipv4Option::Unknown { payload, .. } => 2 + payload.len(),
+        }
+    }
+
+    /// Checks if the option is copied into every fragment of a packet rather
+    /// than only the first.
+    pub fn is_copied(&self) -> bool {
+        let kind = match *self {
+            Ipv4Option::EOL => option_kinds::EOL,
+            Ipv4Option::NoOp => option_kinds::NO_OP,
+            Ipv4Option::RecordRoute { .. } => option_kinds::RECORD_ROUTE,
+            Ipv4Option::Timestamp { .. } => option_kinds::TIMESTAMP,
+            Ipv4Option::RouterAlert(_) => option_kinds::ROUTER_ALERT,
+            Ipv4Option::Unknown { kind, .. } => kind,
+        };
+        kind & 0x80 != 0
+    }
+
+    /// Serializes the option into a buffer, which must be at least
+    /// buffer_len() bytes.
+    pub fn serialize(&self, buffer: &mut [u8]) {
+        let len = self.buffer_len() as u8;
+        match *self {
+            Ipv4Option::EOL => buffer[0] = option_kinds::EOL,
+            Ipv4Option::NoOp => buffer[0] = option_kinds::NO_OP,
+            Ipv4Option::RecordRoute { pointer, route } => {
+                buffer[.. 3].copy_from_slice(&[option_kinds::RECORD_ROUTE, len, pointer]);
+                buffer[3 .. len as usize].copy_from_slice(route);
+            }
+            Ipv4Option::Timestamp {
+                pointer,
+                overflow,
+                flag,
+                data,
+            } => {
+                buffer[.. 4].copy_from_slice(&[
+                    option_kinds::TIMESTAMP,
+                    len,
+                    pointer,
+                    (overflow << 4) | (flag & 0x0F),
+                ]);
+                buffer[4 .. len as usize].copy_from_slice(data);
+            }
+            Ipv4Option::RouterAlert(value) => {
+                buffer[.. 2].copy_from_slice(&[option_kinds::ROUTER_ALERT, len]);
+                NetworkEndian::write_u16(&mut buffer[2 .. 4], value);
+            }
+            Ipv4Option::Unknown { kind, payload } => {
+                buffer[.. 2].copy_from_slice(&[kind, len]);
+                buffer[2 .. len as usize].copy_from_slice(payload);
+            }
+        }
+    }
+}
+
+/// An iterator that produces Ipv4Options from a buffer.
+pub struct Ipv4OptionIter<'a> {
+    options: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for Ipv4OptionIter<'a> {
+    type Item = Ipv4Option<'a>;
+
+    fn next(&mut self) -> Option<Ipv4Option<'a>> {
+        if self.position == self.options.len() {
+            return None;
+        }
+
+        let kind = self.options[self.position];
+        let (option, len) = match kind {
+            option_kinds::EOL => (Ipv4Option::EOL, 1),
+            option_kinds::NO_OP => (Ipv4Option::NoOp, 1),
+            _ => {
+                if self.position + 2 > self.options.len() {
+                    // No space for length field!
+                    return None;
+                }
+
+                let len = self.options[self.position + 1] as usize;
+
+                if len < 2 || self.position + len > self.options.len() {
+                    // Length exceeds buffer!
+                    return None;
+                }
+
+                let payload = &self.options[self.position + 2 .. self.position + len];
+
+                match (kind, len) {
+                    (option_kinds::RECORD_ROUTE, _) if len >= 3 => (
+                        Ipv4Option::RecordRoute {
+                            pointer: payload[0],
+                            route: &payload[1 ..],
+                        },
+                        len,
+                    ),
+                    (option_kinds::TIMESTAMP, _) if len >= 4 => (
+                        Ipv4Option::Timestamp {
+                            pointer: payload[0],
+                            overflow: payload[1] >> 4,
+                            flag: payload[1] & 0x0F,
+                            data: &payload[2 ..],
+                        },
+                        len,
+                    ),
+                    (option_kinds::ROUTER_ALERT, 4) => (
+                        Ipv4Option::RouterAlert(
+                            NetworkEndian::read_u16(payload),
+                        ),
+                        len,
+                    ),
+                    _ => (Ipv4Option::Unknown { kind, payload }, len),
+                }
+            }
+        };
+
+        self.position += len;
+
+        Some(option)
+    }
+}
+
+impl<'a> Ipv4OptionIter<'a> {
+    /// Creates a new IPv4 options iterator from a buffer.
+    pub fn new(options: &'a [u8]) -> Ipv4OptionIter<'a> {
+        Ipv4OptionIter {
+            options,
+            position: 0,
+        }
+    }
+}
+
+/// Serialized IPv4 options of a header.
+///
+/// Options are stored inline (rather than as a list) so the Repr stays Copy.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    buffer: [u8; 40],
+    len: usize,
+}
+
+impl Options {
+    /// Maximum length of options in an IPv4 header.
+    pub const MAX_LEN: usize = 40;
+
+    /// Creates an empty set of options.
+    pub fn new() -> Options {
+        Options {
+            buffer: [0; 40],
+            len: 0,
+        }
+    }
+
+    /// Tries to create options from serialized options.
+    pub fn try_new(options: &[u8]) -> Result<Options> {
+        if options.len() > Self::MAX_LEN {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let mut buffer = [0; 40];
+        buffer[.. options.len()].copy_from_slice(options);
+        Ok(Options {
+            buffer,
+            len: options.len(),
+        })
+    }
+
+    /// Appends an option, failing with BufferError::Full if the options would
+    /// exceed MAX_LEN bytes.
+    pub fn push(&mut self, option: &Ipv4Option) -> Result<()> {
+        let option_len = option.buffer_len();
+        if self.len + option_len > Self::MAX_LEN {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        option.serialize(&mut self.buffer[self.len .. self.len + option_len]);
+        self.len += option_len;
+        Ok(())
+    }
+
+    /// Returns the options which must be copied into every fragment.
+    pub fn copied(&self) -> Options {
+        let mut options = Options::new();
+        for option in self.iter().filter(|option| option.is_copied()) {
+            options.push(&option).unwrap();
+        }
+        options
+    }
+
+    /// Returns the length of the options padded to a multiple of 32 bits.
+    pub fn padded_len(&self) -> usize {
+        (self.len + 3) & !0x03
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[.. self.len]
+    }
+
+    pub fn iter(&self) -> Ipv4OptionIter<'_> {
+        Ipv4OptionIter::new(self.as_bytes())
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options::new()
+    }
+}
+
+impl Debug for Options {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// [https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml]
+/// (https://www.iana.org/assignments/protocol-numbers/protocol-numbers.xhtml)
+pub mod protocols {
+    pub const ICMP: u8 = 1;
+
+    pub const IGMP: u8 = 2;
+
+    pub const TCP: u8 = 6;
+
+    pub const UDP: u8 = 17;
+}
+
+pub mod flags {
+    pub const DONT_FRAGMENT: u8 = 0b00000010;
+
+    pub const NOT_LAST: u8 = 0b00000001;
+}
+
+/// [https://en.wikipedia.org/wiki/IPv4](https://en.wikipedia.org/wiki/IPv4)
+mod fields {
+    use core::ops::Range;
+
+    pub const IP_VERSION_AND_HEADER_LEN: usize = 0;
+
+    pub const DSCP_AND_ECN: usize = 1;
+
+    pub const PACKET_LEN: Range<usize> = 2 .. 4;
+
+    pub const IDENTIFICATION: Range<usize> = 4 .. 6;
+
+    pub const FLAGS: usize = 6;
+
+    pub const FRAG_OFFSET: Range<usize> = 6 .. 8;
+
+    pub const TTL: usize = 8;
+
+    pub const PROTOCOL: usize = 9;
+
+    pub const CHECKSUM: Range<usize> = 10 .. 12;
+
+    pub const SRC_ADDR: Range<usize> = 12 .. 16;
+
+    pub const DST_ADDR: Range<usize> = 16 .. 20;
+}
+
+/// View of a byte buffer as an IPv4 packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> AsMut<[u8]> for Packet<T> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut()
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const MIN_HEADER_LEN: usize = 20;
+
+    /// Tries to create an IPv4 packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    ///
+    /// Getters clamp the header and packet lengths to the buffer, so they
+    /// don't panic if it's skipped, but may return truncated options or
+    /// payload.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::MIN_HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Returns the length of an IPv4 packet with the specified payload size.
+    pub fn buffer_len(payload_len: usize) -> usize {
+        20 + payload_len
+    }
+
+    /// Checks if the packet has a valid encoding. This may include checksum,
+    /// field consistency, etc. checks.
+    pub fn check_encoding(&self) -> Result<()> {
+        if (self.packet_len() as usize) > self.buffer.as_ref().len()
+            || self.packet_len() < (self.header_len() * 4) as u16
+            || ((self.header_len() * 4) as usize) < Self::MIN_HEADER_LEN
+            || ((self.header_len() * 4) as usize) > self.buffer.as_ref().len()
+            || self.ip_version() != 4
+        {
+            Err(Error::Parse(ParseError::Malformed))
+        } else if self.gen_header_checksum() != 0 {
+            Err(Error::Parse(ParseError::Checksum))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Calculates the header checksum.
+    pub fn gen_header_checksum(&self) -> u16 {
+        internet_checksum_slices(&[&self.buffer.as_ref()[.. self.header_end()]])
+    }
+
+    /// Returns the offset of the payload, clamped to the buffer so getters
+    /// don't panic on packets which fail check_encoding().
+    fn header_end(&self) -> usize {
+        let header_len = (self.header_len() * 4) as usize;
+        header_len
+            .max(Self::MIN_HEADER_LEN)
+            .min(self.buffer.as_ref().len())
+    }
+
+    /// Returns the offset following the payload, clamped like header_end().
+    fn packet_end(&self) -> usize {
+        (self.packet_len() as usize)
+            .min(self.buffer.as_ref().len())
+            .max(self.header_end())
+    }
+
+    pub fn ip_version(&self) -> u8 {
+        (self.buffer.as_ref()[fields::IP_VERSION_AND_HEADER_LEN] & 0xF0) >> 4
+    }
+
+    pub fn header_len(&self) -> u8 {
+        (self.buffer.as_ref()[fields::IP_VERSION_AND_HEADER_LEN] & 0x0F)
+    }
+
+    pub fn dscp(&self) -> u8 {
+        (self.buffer.as_ref()[fields::DSCP_AND_ECN] & 0xFC) >> 2
+    }
+
+    pub fn ecn(&self) -> u8 {
+        self.buffer.as_ref()[fields::DSCP_AND_ECN] & 0x03
+    }
+
+    pub fn packet_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::PACKET_LEN])
+    }
+
+    pub fn identification(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::IDENTIFICATION])
+    }
+
+    pub fn flags(&self) -> u8 {
+        (self.buffer.as_ref()[fields::FLAGS] & 0xE0) >> 5
+    }
+
+    pub fn fragment_offset(&self) -> u16 {
+        let frag_offset_slice = &self.buffer.as_ref()[fields::FRAG_OFFSET];
+        let mut frag_offset_only: [u8; 2] = [0; 2];
+        frag_offset_only[0] = frag_offset_slice[0] & 0x1F; // Clear flags!
+        frag_offset_only[1] = frag_offset_slice[1];
+        NetworkEndian::read_u16(&frag_offset_only[..])
+    }
+
+    pub fn ttl(&self) -> u8 {
+        self.buffer.as_ref()[fields::TTL]
+    }
+
+    pub fn protocol(&self) -> u8 {
+        self.buffer.as_ref()[fields::PROTOCOL]
+    }
+
+    pub fn header_checksum(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::CHECKSUM])
+    }
+
+    pub fn src_addr(&self) -> Address {
+        Address::try_new(&self.buffer.as_ref()[fields::SRC_ADDR]).unwrap()
+    }
+
+    pub fn dst_addr(&self) -> Address {
+        Address::try_new(&self.buffer.as_ref()[fields::DST_ADDR]).unwrap()
+    }
+
+    pub fn options(&self) -> &[u8] {
+        &self.buffer.as_ref()[Self::MIN_HEADER_LEN .. self.header_end()]
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[self.header_end() .. self.packet_end()]
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_ip_version(&mut self, version: u8) {
+        self.buffer.as_mut()[fields::IP_VERSION_AND_HEADER_LEN] &= !0xF0;
+        self.buffer.as_mut()[fields::IP_VERSION_AND_HEADER_LEN] |= version << 4;
+    }
+
+    pub fn set_header_len(&mut self, header_len: u8) {
+        self.buffer.as_mut()[fields::IP_VERSION_AND_HEADER_LEN] &= !0x0F;
+        self.buffer.as_mut()[fields::IP_VERSION_AND_HEADER_LEN] |= header_len & 0x0F;
+    }
+
+    pub fn set_dscp(&mut self, dscp: u8) {
+        self.buffer.as_mut()[fields::DSCP_AND_ECN] &= !0xFC;
+        self.buffer.as_mut()[fields::DSCP_AND_ECN] |= dscp << 2;
+    }
+
+    pub fn set_ecn(&mut self, ecn: u8) {
+        self.buffer.as_mut()[fields::DSCP_AND_ECN] &= !0x03;
+        self.buffer.as_mut()[fields::DSCP_AND_ECN] |= ecn & 0x03;
+    }
+
+    pub fn set_packet_len(&mut self, packet_len: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::PACKET_LEN], packet_len)
+    }
+
+    pub fn set_identification(&mut self, id: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::IDENTIFICATION], id)
+    }
+
+    pub fn set_flags(&mut self, flags: u8) {
+        self.buffer.as_mut()[fields::FLAGS] &= 0x1F;
+        self.buffer.as_mut()[fields::FLAGS] |= flags << 5
+    }
+
+    pub fn set_fragment_offset(&mut self, frag_offset: u16) {
+        let flags = self.flags();
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::FRAG_OFFSET], frag_offset);
+        self.set_flags(flags);
+    }
+
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.buffer.as_mut()[fields::TTL] = ttl;
+    }
+
+    /// Sets the TTL and incrementally updates the header checksum, rather
+    /// than recomputing it over the whole header.
+    pub fn set_ttl_with_checksum(&mut self, ttl: u8) {
+        self.update_field_with_checksum(fields::TTL, &[ttl]);
+    }
+
+    pub fn set_protocol(&mut self, protocol: u8) {
+        self.buffer.as_mut()[fields::PROTOCOL] = protocol;
+    }
+
+    pub fn set_header_checksum(&mut self, header_checksum: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::CHECKSUM], header_checksum)
+    }
+
+    pub fn set_src_addr(&mut self, addr: Address) {
+        self.buffer.as_mut()[fields::SRC_ADDR].copy_from_slice(addr.as_bytes());
+    }
+
+    pub fn set_dst_addr(&mut self, addr: Address) {
+        self.buffer.as_mut()[fields::DST_ADDR].copy_from_slice(addr.as_bytes());
+    }
+
+    pub fn options_mut(&mut self) -> &mut [u8] {
+        let header_end = self.header_end();
+        &mut self.buffer.as_mut()[Self::MIN_HEADER_LEN .. header_end]
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let (header_end, packet_end) = (self.header_end(), self.packet_end());
+        &mut self.buffer.as_mut()[header_end .. packet_end]
+    }
+
+    pub fn fill_checksum(&mut self) {
+        self.set_header_checksum(0);
+        let checksum = self.gen_header_checksum();
+        self.set_header_checksum(checksum);
+    }
+
+    /// Sets the source address and incrementally updates the header checksum.
+    pub fn set_src_addr_with_checksum(&mut self, addr: Address) {
+        self.update_field_with_checksum(fields::SRC_ADDR.start, addr.as_bytes());
+    }
+
+    /// Sets the destination address and incrementally updates the header
+    /// checksum.
+    pub fn set_dst_addr_with_checksum(&mut self, addr: Address) {
+        self.update_field_with_checksum(fields::DST_ADDR.start, addr.as_bytes());
+    }
+
+    /// Overwrites header bytes at an offset and incrementally updates the
+    /// header checksum, rather than recomputing it over the whole header, see
+    /// RFC 1624.
+    ///
+    /// Panics if the bytes overlap the checksum or extend past the header.
+    pub fn update_field_with_checksum(&mut self, offset: usize, bytes: &[u8]) {
+        let end = offset + bytes.len();
+        assert!(end <= fields::CHECKSUM.start || offset >= fields::CHECKSUM.end);
+        assert!(end <= self.header_end());
+
+        // Adjust whole u16's so a field at an odd offset, like the TTL, stays
+        // aligned with the checksummed words.
+        // Bytes past the end of the header are summed as zeros.
+        let (start, padded_end) = (offset & !1, (end + 1) & !1);
+        let len = padded_end - start;
+        let header = &self.buffer.as_ref()[start .. self.header_end().min(padded_end)];
+        let mut old = [0; 60];
+        old[.. header.len()].copy_from_slice(header);
+        let mut new = old;
+        new[offset - start .. end - start].copy_from_slice(bytes);
+
+        let checksum = checksum_adjust(self.header_checksum(), &old[.. len], &new[.. len]);
+        self.buffer.as_mut()[offset .. end].copy_from_slice(bytes);
+        self.set_header_checksum(checksum);
+    }
+}
+
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    /// Formats a tcpdump-like summary of the packet header.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "IPv4 {} > {}, proto {}, ttl {}, id {}, length {}",
+            self.src_addr(),
+            self.dst_addr(),
+            self.protocol(),
+            self.ttl(),
+            self.identification(),
+            self.packet_len()
+        )?;
+
+        if self.flags() & flags::DONT_FRAGMENT != 0 {
+            write!(f, ", DF")?;
+        }
+
+        if self.flags() & flags::NOT_LAST != 0 || self.fragment_offset() != 0 {
+            write!(f, ", frag offset {}", self.fragment_offset() * 8)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unicast() {
+        let addr = Address::new([0x00, 0x00, 0x00, 0x00]);
+        assert!(addr.is_unicast());
+        let addr = Address::new([0x80, 0x00, 0x00, 0x00]);
+        assert!(addr.is_unicast());
+        let addr = Address::new([0xC0, 0x00, 0x00, 0x00]);
+        assert!(addr.is_unicast());
+    }
+
+    #[test]
+    fn test_is_multicast() {
+        let addr = Address::new([0xE0, 0x00, 0x00, 0x00]);
+        assert!(addr.is_multicast());
+        let addr = Address::new([0xF0, 0x00, 0x00, 0x00]);
+        assert!(!addr.is_multicast());
+    }
+
+    #[test]
+    fn test_is_reserved() {
+        let addr = Address::new([0xF0, 0x00, 0x00, 0x00]);
+        assert!(addr.is_reserved());
+    }
+
+    #[test]
+    fn test_addr_cidr_is_member() {
+        let addr = AddressCidr::new(Address::new([0x12, 0x30, 0x00, 0x00]), 4);
+
+        assert!(!addr.is_member(Address::new([0x00, 0x00, 0x00, 0x00])));
+        assert!(addr.is_member(Address::new([0x10, 0x00, 0x00, 0x00])));
+        assert!(addr.is_member(Address::new([0x12, 0x30, 0x00, 0x00])));
+        assert!(addr.is_member(Address::new([0x12, 0x34, 0x00, 0x00])));
+        assert!(addr.is_member(Address::new([0x1F, 0xFF, 0xFF, 0xFF])));
+    }
+
+    #[test]
+    fn test_addr_cidr_network() {
+        let addr = AddressCidr::new(Address::new([10, 1, 2, 3]), 16);
+        assert_eq!(addr.network(), Address::new([10, 1, 0, 0]));
+        let addr = AddressCidr::new(Address::new([10, 1, 2, 3]), 32);
+        assert_eq!(addr.network(), Address::new([10, 1, 2, 3]));
+        assert!(addr.is_member(Address::new([10, 1, 2, 3])));
+        assert!(!addr.is_member(Address::new([10, 1, 2, 4])));
+    }
+
+    #[test]
+    fn test_addr_cidr_from_str() {
+        assert_eq!(
+            AddressCidr::from_str("10.0.0.102/24"),
+            Ok(AddressCidr::new(Address::new([10, 0, 0, 102]), 24))
+        );
+        assert_eq!(AddressCidr::from_str("10.0.0.102"), Err(()));
+        assert_eq!(AddressCidr::from_str("10.0.0.102/33"), Err(()));
+        assert_eq!(AddressCidr::from_str("10.0.0/24"), Err(()));
+    }
+
+    #[test]
+    fn test_addr_broadcast() {
+        let addr = AddressCidr::new(Address::new([0x12, 0x30, 0x00, 0x00]), 4);
+        assert_eq!(addr.broadcast(), Address::new([0x1F, 0xFF, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn test_addr_is_broadcast() {
+        let addr = AddressCidr::new(Address::new([0x12, 0x30, 0x00, 0x00]), 4);
+
+        assert!(!addr.is_broadcast(Address::new([0x0F, 0xFF, 0xFF, 0xFF])));
+        assert!(addr.is_broadcast(Address::new([0x1F, 0xFF, 0xFF, 0xFF])));
+    }
+
+    #[test]
+    fn test_packet_with_buffer_less_than_min_header() {
+        let buffer: [u8; 19] = [0; 19];
+        let packet = Packet::try_new(&buffer[..]);
+        assert_matches!(packet, Err(Error::Parse(ParseError::Truncated)));
+    }
+
+    #[test]
+    fn test_packet_with_invalid_packet_len() {
+        let buffer: [u8; 42] = [
+            0x41, 0x11, 0x00, 0x13, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert!(packet.options().is_empty());
+        assert!(packet.payload().is_empty());
+
+        let buffer: [u8; 42] = [
+            0x41, 0x11, 0x00, 0xFF, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert_eq!(packet.payload().len(), 22);
+    }
+
+    #[test]
+    fn test_packet_with_invalid_header_len() {
+        let buffer: [u8; 42] = [
+            0x41, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert_eq!(packet.payload().len(), 20);
+
+        let buffer: [u8; 42] = [
+            0x4F, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert_eq!(packet.options().len(), 22);
+        assert!(packet.payload().is_empty());
+    }
+
+    #[test]
+    fn test_packet_with_invalid_ip_version() {
+        let buffer: [u8; 42] = [
+            0x41, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_packet_with_invalid_checksum() {
+        let buffer: [u8; 42] = [
+            0x46, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x00, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Checksum))
+        );
+    }
+
+    #[test]
+    fn test_packet_getters() {
+        let buffer: [u8; 42] = [
+            0x46, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0xC6, 0xAD, 0x01, 0x02,
+            0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(4, packet.ip_version());
+        assert_eq!(6, packet.header_len());
+        assert_eq!(4, packet.dscp());
+        assert_eq!(1, packet.ecn());
+        assert_eq!(40, packet.packet_len());
+        assert_eq!(65535, packet.identification());
+        assert_eq!(7, packet.flags());
+        assert_eq!(257, packet.fragment_offset());
+        assert_eq!(2, packet.ttl());
+        assert_eq!(3, packet.protocol());
+        assert_eq!(50861, packet.header_checksum());
+        assert_eq!(Address([1, 2, 3, 4]), packet.src_addr());
+        assert_eq!(Address([5, 6, 7, 8]), packet.dst_addr());
+        assert_eq!(16, packet.payload().len());
+        assert_eq!(9, packet.payload()[0]);
+    }
+
+    #[test]
+    fn test_packet_setters() {
+        let mut buffer: [u8; 42] = [0; 42];
+
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            packet.set_ip_version(4);
+            packet.set_header_len(6);
+            packet.set_dscp(4);
+            packet.set_ecn(1);
+            packet.set_packet_len(40);
+            packet.set_identification(65535);
+            packet.set_flags(7);
+            packet.set_fragment_offset(257);
+            packet.set_ttl(2);
+            packet.set_protocol(3);
+            packet.set_header_checksum(4);
+            packet.set_src_addr(Address([1, 2, 3, 4]));
+            packet.set_dst_addr(Address([5, 6, 7, 8]));
+            packet.payload_mut()[0] = 0x09;
+        }
+
+        assert_eq!(
+            &buffer[..],
+            &[
+                0x46, 0x11, 0x00, 0x28, 0xFF, 0xFF, 0xE1, 0x01, 0x02, 0x03, 0x00, 0x04, 0x01, 0x02,
+                0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x00, 0x00, 0x00, 0x00, 0x09, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            ][..]
+        );
+    }
+
+    #[test]
+    fn test_set_ttl_with_checksum() {
+        let mut buffer: [u8; 20] = [
+            0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7,
+        ];
+        let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+        packet.fill_checksum();
+
+        for ttl in (0 .. 64).rev() {
+            packet.set_ttl_with_checksum(ttl);
+            assert_eq!(packet.ttl(), ttl);
+            assert_matches!(packet.check_encoding(), Ok(_));
+        }
+    }
+
+    #[test]
+    fn test_update_field_with_checksum() {
+        let mut buffer: [u8; 24] = [
+            0x46, 0x00, 0x00, 0x18, 0x00, 0x00, 0x40, 0x00, 0x40, 0x11, 0x00, 0x00, 0xc0, 0xa8,
+            0x00, 0x01, 0xc0, 0xa8, 0x00, 0xc7, 0x94, 0x04, 0x00, 0x00,
+        ];
+        let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+        packet.fill_checksum();
+
+        packet.set_src_addr_with_checksum(Address([10, 0, 0, 1]));
+        packet.set_dst_addr_with_checksum(Address([10, 0, 0, 2]));
+        assert_eq!(packet.src_addr(), Address([10, 0, 0, 1]));
+        assert_eq!(packet.dst_addr(), Address([10, 0, 0, 2]));
+        assert_matches!(packet.check_encoding(), Ok(_));
+
+        // Fields at odd offsets, including in the options.
+        packet.update_field_with_checksum(fields::DSCP_AND_ECN, &[0xB8]);
+        packet.update_field_with_checksum(21, &[0x04, 0x00, 0x01]);
+        assert_eq!(packet.dscp(), 46);
+        assert_eq!(packet.options(), &[0x94, 0x04, 0x00, 0x01]);
+        assert_matches!(packet.check_encoding(), Ok(_));
+    }
+
+    #[test]
+    fn test_repr_serialize_deserialize() {
+        let repr = Repr {
+            src_addr: Address([1, 2, 3, 4]),
+            dst_addr: Address([5, 6, 7, 8]),
+            protocol: Protocol::UDP,
+            payload_len: 1,
+            ttl: 3,
+            dscp: 46,
+            flags: 0,
+            options: Options::new(),
+        };
+
+        let mut buffer: [u8; 21] = [0; 21];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet);
+        }
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet.ttl(), 3);
+        assert_eq!(packet.dscp(), 46);
+        assert_eq!(packet.flags(), 0);
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_repr_serialize_without_checksum() {
+        let repr = Repr {
+            src_addr: Address([1, 2, 3, 4]),
+            dst_addr: Address([5, 6, 7, 8]),
+            protocol: Protocol::UDP,
+            payload_len: 0,
+            ttl: 3,
+            dscp: 0,
+            flags: 0,
+            options: Options::new(),
+        };
+
+        let mut buffer: [u8; 20] = [0xFF; 20];
+        let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+        repr.serialize_with_checksum(&mut packet, false);
+        assert_eq!(packet.header_checksum(), 0);
+        packet.fill_checksum();
+        assert_matches!(packet.check_encoding(), Ok(_));
+    }
+
+    #[test]
+    fn test_option_iter() {
+        let buffer: [u8; 16] = [
+            0x01, 0x94, 0x04, 0x00, 0x00, 0x07, 0x07, 0x04, 0x0A, 0x00, 0x00, 0x01, 0x63, 0x02,
+            0x00, 0x00,
+        ];
+        let options: Vec<_> = Ipv4OptionIter::new(&buffer[..]).collect();
+        assert_eq!(
+            options,
+            vec![
+                Ipv4Option::NoOp,
+                Ipv4Option::RouterAlert(0),
+                Ipv4Option::RecordRoute {
+                    pointer: 4,
+                    route: &[0x0A, 0x00, 0x00, 0x01],
+                },
+                Ipv4Option::Unknown {
+                    kind: 0x63,
+                    payload: &[],
+                },
+                Ipv4Option::EOL,
+                Ipv4Option::EOL,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repr_serialize_deserialize_with_options() {
+        let mut options = Options::new();
+        options.push(&Ipv4Option::RouterAlert(0)).unwrap();
+        options
+            .push(&Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &[0; 8],
+            })
+            .unwrap();
+        assert_eq!(options.as_bytes().len(), 15);
+        assert_eq!(options.padded_len(), 16);
+        assert_eq!(options.copied().as_bytes(), &[0x94, 0x04, 0x00, 0x00]);
+
+        let repr = Repr {
+            src_addr: Address([1, 2, 3, 4]),
+            dst_addr: Address([5, 6, 7, 8]),
+            protocol: Protocol::UDP,
+            payload_len: 1,
+            ttl: 3,
+            dscp: 0,
+            flags: 0,
+            options,
+        };
+        assert_eq!(repr.header_len(), 36);
+
+        let mut buffer: [u8; 37] = [0xFF; 37];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet);
+            packet.payload_mut()[0] = 9;
+        }
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet.header_len(), 9);
+        assert_eq!(packet.payload(), &[9]);
+        assert_eq!(packet.options()[15], 0);
+
+        // Padding is parsed as an EOL option.
+        let mut padded = options;
+        padded.push(&Ipv4Option::EOL).unwrap();
+        assert_eq!(
+            Repr::deserialize(&packet).unwrap(),
+            Repr {
+                options: padded,
+                ..repr
+            }
+        );
+    }
+
+    #[test]
+    fn test_options_exhausted() {
+        let mut options = Options::new();
+        options
+            .push(&Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &[0; 37],
+            })
+            .unwrap();
+        assert_matches!(
+            options.push(&Ipv4Option::NoOp),
+            Err(Error::Buffer(BufferError::Full))
+        );
+    }
+}
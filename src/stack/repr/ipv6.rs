@@ -0,0 +1,748 @@
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use core::net::Ipv6Addr as StdIpv6Addr;
+use core::ops::Deref;
+use core::result::Result as StdResult;
+use core::str::FromStr;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::check::internet_checksum_slices;
+use stack::repr::EthernetAddress;
+use {
+    Error,
+    ParseError,
+    Result,
+};
+
+/// [IPv6 address](https://en.wikipedia.org/wiki/IPv6_address) in network byte
+/// order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Address([u8; 16]);
+
+impl Address {
+    /// The unspecified address, i.e. ::.
+    pub const UNSPECIFIED: Address = Address([0; 16]);
+
+    /// The loopback address, i.e. ::1.
+    pub const LOOPBACK: Address = Address([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    /// The link local all nodes multicast group, i.e. ff02::1.
+    pub const ALL_NODES: Address = Address([0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+
+    /// The link local all routers multicast group, i.e. ff02::2.
+    pub const ALL_ROUTERS: Address =
+        Address([0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+
+    /// Creates an IPv6 address from a network byte order buffer.
+    pub fn new(addr: [u8; 16]) -> Address {
+        Address(addr)
+    }
+
+    /// Tries to creates an IPv6 address from a network byte order slice.
+    pub fn try_new(addr: &[u8]) -> Result<Address> {
+        if addr.len() != 16 {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let mut _addr: [u8; 16] = [0; 16];
+        _addr.clone_from_slice(addr);
+        Ok(Address(_addr))
+    }
+
+    /// Returns a reference to the network byte order representation of the
+    /// address.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Checks if this is the unspecified address.
+    pub fn is_unspecified(&self) -> bool {
+        *self == Self::UNSPECIFIED
+    }
+
+    /// Checks if this is the loopback address.
+    pub fn is_loopback(&self) -> bool {
+        *self == Self::LOOPBACK
+    }
+
+    /// Checks if this is a multicast address, i.e. in ff00::/8.
+    pub fn is_multicast(&self) -> bool {
+        self.0[0] == 0xFF
+    }
+
+    /// Checks if this is a link local unicast address, i.e. in fe80::/10.
+    pub fn is_link_local(&self) -> bool {
+        self.0[0] == 0xFE && (self.0[1] & 0xC0) == 0x80
+    }
+
+    /// Checks if this is a unicast address.
+    pub fn is_unicast(&self) -> bool {
+        !(self.is_multicast() || self.is_unspecified())
+    }
+
+    /// Creates a link local address with an interface identifier derived from
+    /// an Ethernet address (modified EUI-64), see RFC 4291 appendix A.
+    pub fn link_local(eth_addr: EthernetAddress) -> Address {
+        let eth_bytes = eth_addr.as_bytes();
+        let mut addr = [0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xFF, 0xFE, 0, 0, 0];
+        addr[8 .. 11].copy_from_slice(&eth_bytes[0 .. 3]);
+        addr[8] ^= 0x02;
+        addr[13 ..].copy_from_slice(&eth_bytes[3 ..]);
+        Address(addr)
+    }
+
+    /// Returns the solicited node multicast group for the address, i.e.
+    /// ff02::1:ffXX:XXXX with the low 24 bits of the address.
+    pub fn solicited_node(&self) -> Address {
+        let mut addr = [0xFF, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xFF, 0, 0, 0];
+        addr[13 ..].copy_from_slice(&self.0[13 ..]);
+        Address(addr)
+    }
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", StdIpv6Addr::from(self.0))
+    }
+}
+
+impl<'a> From<&'a StdIpv6Addr> for Address {
+    fn from(addr: &'a StdIpv6Addr) -> Address {
+        Address(addr.octets())
+    }
+}
+
+impl From<Address> for StdIpv6Addr {
+    fn from(addr: Address) -> StdIpv6Addr {
+        StdIpv6Addr::from(addr.0)
+    }
+}
+
+impl FromStr for Address {
+    type Err = ();
+
+    /// Parses an IPv6 address from a colon separated string such as fe80::1.
+    fn from_str(addr: &str) -> StdResult<Address, Self::Err> {
+        addr.parse::<StdIpv6Addr>()
+            .map(|addr| Address::from(&addr))
+            .map_err(|_| ())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AddressCidr {
+    address: Address,
+    prefix_len: u32,
+}
+
+impl AddressCidr {
+    /// Creates an IPv6 address with a prefix length.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the prefix is longer than 128 bits.
+    pub fn new(address: Address, prefix_len: usize) -> AddressCidr {
+        assert!(prefix_len <= 128);
+
+        AddressCidr {
+            address,
+            prefix_len: prefix_len as u32,
+        }
+    }
+
+    /// Returns the length of the prefix in bits.
+    pub fn prefix_len(&self) -> usize {
+        self.prefix_len as usize
+    }
+
+    /// Returns the network prefix, i.e. the address with all interface
+    /// identifier bits cleared.
+    pub fn network(&self) -> Address {
+        let mut addr = self.address.0;
+        for (i, byte) in addr.iter_mut().enumerate() {
+            *byte &= self.mask(i);
+        }
+        Address(addr)
+    }
+
+    /// Checks if the address is a member of the prefix.
+    pub fn is_member(&self, address: Address) -> bool {
+        (0 .. 16).all(|i| (address.0[i] & self.mask(i)) == (self.address.0[i] & self.mask(i)))
+    }
+
+    /// Returns the mask for the i'th byte of an address.
+    fn mask(&self, i: usize) -> u8 {
+        let bits = (self.prefix_len as usize).saturating_sub(i * 8);
+        if bits >= 8 {
+            0xFF
+        } else {
+            !(0xFFu8 >> bits)
+        }
+    }
+}
+
+impl Deref for AddressCidr {
+    type Target = Address;
+
+    fn deref(&self) -> &Address {
+        &self.address
+    }
+}
+
+impl Display for AddressCidr {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// A set of supported upper layer protocols over IPv6.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Protocol {
+    TCP = next_headers::TCP,
+    UDP = next_headers::UDP,
+    ICMPv6 = next_headers::ICMPV6,
+}
+
+/// An IPv6 header.
+///
+/// Extension headers are skipped when deserializing, so the protocol and
+/// payload length describe the upper layer payload. No extension headers are
+/// written when serializing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub src_addr: Address,
+    pub dst_addr: Address,
+    pub protocol: Protocol,
+    pub payload_len: u16,
+    pub hop_limit: u8,
+    pub traffic_class: u8,
+    /// 20 bit flow label.
+    pub flow_label: u32,
+}
+
+impl Repr {
+    /// A sensible default hop limit for outgoing packets.
+    pub const DEFAULT_HOP_LIMIT: u8 = 64;
+
+    /// Returns the buffer size needed to serialize the IPv6 header and
+    /// associated payload.
+    pub fn buffer_len(&self) -> usize {
+        Packet::<&[u8]>::buffer_len(self.payload_len as usize)
+    }
+
+    /// Tries to deserialize a packet into an IPv6 header.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let (protocol, payload) = packet.upper_layer()?;
+
+        Ok(Repr {
+            src_addr: packet.src_addr(),
+            dst_addr: packet.dst_addr(),
+            protocol: match protocol {
+                next_headers::TCP => Protocol::TCP,
+                next_headers::UDP => Protocol::UDP,
+                next_headers::ICMPV6 => Protocol::ICMPv6,
+                _ => return Err(Error::Parse(ParseError::Malformed)),
+            },
+            payload_len: payload.len() as u16,
+            hop_limit: packet.hop_limit(),
+            traffic_class: packet.traffic_class(),
+            flow_label: packet.flow_label(),
+        })
+    }
+
+    /// Serializes the IPv6 header into a packet.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        packet.set_ip_version(6);
+        packet.set_traffic_class(self.traffic_class);
+        packet.set_flow_label(self.flow_label);
+        packet.set_payload_len(self.payload_len);
+        packet.set_next_header(self.protocol as u8);
+        packet.set_hop_limit(self.hop_limit);
+        packet.set_src_addr(self.src_addr);
+        packet.set_dst_addr(self.dst_addr);
+    }
+
+    /// Generates a checksum for the byte buffer, using a pseudo-header
+    /// corresponding to this IP header, see RFC 8200 section 8.1.
+    pub fn gen_checksum_with_pseudo_header(&self, buffer: &[u8]) -> u16 {
+        let mut ip_pseudo_header = [0; 40];
+        ip_pseudo_header[0 .. 16].copy_from_slice(self.src_addr.as_bytes());
+        ip_pseudo_header[16 .. 32].copy_from_slice(self.dst_addr.as_bytes());
+        NetworkEndian::write_u32(&mut ip_pseudo_header[32 .. 36], self.payload_len as u32);
+        ip_pseudo_header[39] = self.protocol as u8;
+
+        internet_checksum_slices(&[&ip_pseudo_header[..], buffer])
+    }
+}
+
+/// An iterator over the extension headers of an IPv6 packet.
+///
+/// Each item is the type of the extension header and its raw bytes. Iteration
+/// stops at the first header which is not a known extension header, which
+/// can then be retrieved via upper_layer().
+pub struct ExtHeaderIter<'a> {
+    next_header: u8,
+    buffer: &'a [u8],
+    malformed: bool,
+}
+
+impl<'a> ExtHeaderIter<'a> {
+    /// Creates an iterator over a chain of headers, starting with a header
+    /// of type next_header.
+    pub fn new(next_header: u8, buffer: &'a [u8]) -> ExtHeaderIter<'a> {
+        ExtHeaderIter {
+            next_header,
+            buffer,
+            malformed: false,
+        }
+    }
+
+    /// Walks the remaining extension headers, returning the upper layer
+    /// protocol number and payload.
+    pub fn upper_layer(mut self) -> Result<(u8, &'a [u8])> {
+        for ext_header in self.by_ref() {
+            ext_header?;
+        }
+
+        Ok((self.next_header, self.buffer))
+    }
+
+    /// Checks if a header type is an extension header.
+    pub fn is_ext_header(next_header: u8) -> bool {
+        matches!(
+            next_header,
+            next_headers::HOP_BY_HOP
+                | next_headers::ROUTING
+                | next_headers::FRAGMENT
+                | next_headers::AUTH
+                | next_headers::DST_OPTS
+        )
+    }
+}
+
+impl<'a> Iterator for ExtHeaderIter<'a> {
+    type Item = Result<(u8, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.malformed || !Self::is_ext_header(self.next_header) {
+            return None;
+        }
+
+        if self.buffer.len() < 8 {
+            self.malformed = true;
+            return Some(Err(Error::Parse(ParseError::Malformed)));
+        }
+
+        let header_len = match self.next_header {
+            next_headers::FRAGMENT => 8,
+            next_headers::AUTH => (self.buffer[1] as usize + 2) * 4,
+            _ => (self.buffer[1] as usize + 1) * 8,
+        };
+
+        if self.buffer.len() < header_len {
+            self.malformed = true;
+            return Some(Err(Error::Parse(ParseError::Malformed)));
+        }
+
+        let kind = self.next_header;
+        let (header, buffer) = self.buffer.split_at(header_len);
+        self.next_header = header[0];
+        self.buffer = buffer;
+
+        Some(Ok((kind, header)))
+    }
+}
+
+/// [https://www.iana.org/assignments/protocol-numbers](https://www.iana.org/assignments/protocol-numbers)
+pub mod next_headers {
+    pub const HOP_BY_HOP: u8 = 0;
+
+    pub const TCP: u8 = 6;
+
+    pub const UDP: u8 = 17;
+
+    pub const ROUTING: u8 = 43;
+
+    pub const FRAGMENT: u8 = 44;
+
+    pub const AUTH: u8 = 51;
+
+    pub const ICMPV6: u8 = 58;
+
+    pub const NO_NEXT: u8 = 59;
+
+    pub const DST_OPTS: u8 = 60;
+}
+
+mod fields {
+    use core::ops::Range;
+
+    pub const VERSION_TC_AND_FLOW_LABEL: Range<usize> = 0 .. 4;
+
+    pub const PAYLOAD_LEN: Range<usize> = 4 .. 6;
+
+    pub const NEXT_HEADER: usize = 6;
+
+    pub const HOP_LIMIT: usize = 7;
+
+    pub const SRC_ADDR: Range<usize> = 8 .. 24;
+
+    pub const DST_ADDR: Range<usize> = 24 .. 40;
+}
+
+/// View of a byte buffer as an IPv6 packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> AsMut<[u8]> for Packet<T> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut()
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 40;
+
+    /// Tries to create an IPv6 packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    ///
+    /// Getters clamp the payload length to the buffer, so they don't panic
+    /// if it's skipped, but may return a truncated payload.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Returns the length of an IPv6 packet with the specified payload size.
+    pub fn buffer_len(payload_len: usize) -> usize {
+        Self::HEADER_LEN + payload_len
+    }
+
+    /// Checks if the packet has a valid encoding. This may include field
+    /// consistency checks, etc.
+    pub fn check_encoding(&self) -> Result<()> {
+        if Self::HEADER_LEN + (self.payload_len() as usize) > self.buffer.as_ref().len()
+            || self.ip_version() != 6
+        {
+            Err(Error::Parse(ParseError::Malformed))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Walks the chain of extension headers, returning the upper layer
+    /// protocol number and payload.
+    pub fn upper_layer(&self) -> Result<(u8, &[u8])> {
+        self.ext_headers().upper_layer()
+    }
+
+    /// Returns an iterator over the extension headers of the packet.
+    pub fn ext_headers(&self) -> ExtHeaderIter<'_> {
+        ExtHeaderIter::new(self.next_header(), self.payload())
+    }
+
+    pub fn ip_version(&self) -> u8 {
+        self.buffer.as_ref()[fields::VERSION_TC_AND_FLOW_LABEL.start] >> 4
+    }
+
+    pub fn traffic_class(&self) -> u8 {
+        (self.version_tc_and_flow_label() >> 20) as u8
+    }
+
+    pub fn flow_label(&self) -> u32 {
+        self.version_tc_and_flow_label() & 0x000F_FFFF
+    }
+
+    pub fn payload_len(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::PAYLOAD_LEN])
+    }
+
+    pub fn next_header(&self) -> u8 {
+        self.buffer.as_ref()[fields::NEXT_HEADER]
+    }
+
+    pub fn hop_limit(&self) -> u8 {
+        self.buffer.as_ref()[fields::HOP_LIMIT]
+    }
+
+    pub fn src_addr(&self) -> Address {
+        Address::try_new(&self.buffer.as_ref()[fields::SRC_ADDR]).unwrap()
+    }
+
+    pub fn dst_addr(&self) -> Address {
+        Address::try_new(&self.buffer.as_ref()[fields::DST_ADDR]).unwrap()
+    }
+
+    /// Returns the payload following the fixed header, including any
+    /// extension headers.
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[Self::HEADER_LEN .. self.payload_end()]
+    }
+
+    /// Returns the offset following the payload, clamped to the buffer so
+    /// getters don't panic on packets which fail check_encoding().
+    fn payload_end(&self) -> usize {
+        (Self::HEADER_LEN + self.payload_len() as usize).min(self.buffer.as_ref().len())
+    }
+
+    fn version_tc_and_flow_label(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buffer.as_ref()[fields::VERSION_TC_AND_FLOW_LABEL])
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_ip_version(&mut self, version: u8) {
+        self.buffer.as_mut()[fields::VERSION_TC_AND_FLOW_LABEL.start] &= !0xF0;
+        self.buffer.as_mut()[fields::VERSION_TC_AND_FLOW_LABEL.start] |= version << 4;
+    }
+
+    pub fn set_traffic_class(&mut self, traffic_class: u8) {
+        let word =
+            (self.version_tc_and_flow_label() & 0xF00F_FFFF) | ((traffic_class as u32) << 20);
+        self.set_version_tc_and_flow_label(word);
+    }
+
+    pub fn set_flow_label(&mut self, flow_label: u32) {
+        let word = (self.version_tc_and_flow_label() & 0xFFF0_0000) | (flow_label & 0x000F_FFFF);
+        self.set_version_tc_and_flow_label(word);
+    }
+
+    pub fn set_payload_len(&mut self, payload_len: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::PAYLOAD_LEN], payload_len)
+    }
+
+    pub fn set_next_header(&mut self, next_header: u8) {
+        self.buffer.as_mut()[fields::NEXT_HEADER] = next_header;
+    }
+
+    pub fn set_hop_limit(&mut self, hop_limit: u8) {
+        self.buffer.as_mut()[fields::HOP_LIMIT] = hop_limit;
+    }
+
+    pub fn set_src_addr(&mut self, addr: Address) {
+        self.buffer.as_mut()[fields::SRC_ADDR].copy_from_slice(addr.as_bytes());
+    }
+
+    pub fn set_dst_addr(&mut self, addr: Address) {
+        self.buffer.as_mut()[fields::DST_ADDR].copy_from_slice(addr.as_bytes());
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let payload_end = self.payload_end();
+        &mut self.buffer.as_mut()[Self::HEADER_LEN .. payload_end]
+    }
+
+    fn set_version_tc_and_flow_label(&mut self, word: u32) {
+        NetworkEndian::write_u32(&mut self.buffer.as_mut()[fields::VERSION_TC_AND_FLOW_LABEL], word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> Address {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_addr_parse_and_display() {
+        let a = addr("fe80::1:2");
+        assert_eq!(
+            a.as_bytes(),
+            &[0xFE, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 2]
+        );
+        assert_eq!(format!("{}", a), "fe80::1:2");
+        assert_eq!(format!("{}", Address::ALL_NODES), "ff02::1");
+        assert!("fe80::1::2".parse::<Address>().is_err());
+    }
+
+    #[test]
+    fn test_addr_classes() {
+        assert!(addr("fe80::1").is_link_local());
+        assert!(addr("fe80::1").is_unicast());
+        assert!(!addr("fec0::1").is_link_local());
+        assert!(addr("ff02::1").is_multicast());
+        assert!(!addr("ff02::1").is_unicast());
+        assert!(Address::UNSPECIFIED.is_unspecified());
+        assert!(Address::LOOPBACK.is_loopback());
+        assert_eq!(
+            Address::link_local(EthernetAddress::new([0x06, 0x11, 0x22, 0x33, 0x44, 0x55])),
+            addr("fe80::411:22ff:fe33:4455")
+        );
+        assert_eq!(
+            addr("2001:db8::aabb:ccdd").solicited_node(),
+            addr("ff02::1:ffbb:ccdd")
+        );
+    }
+
+    #[test]
+    fn test_addr_cidr() {
+        let cidr = AddressCidr::new(addr("2001:db8:ab::1"), 36);
+        assert_eq!(cidr.network(), addr("2001:db8::"));
+        assert!(cidr.is_member(addr("2001:db8:fff::ff")));
+        assert!(!cidr.is_member(addr("2001:db8:1000::1")));
+        assert_eq!(format!("{}", cidr), "2001:db8:ab::1/36");
+
+        let cidr = AddressCidr::new(addr("2001:db8::1"), 0);
+        assert!(cidr.is_member(addr("::")));
+    }
+
+    #[test]
+    fn test_packet_with_buffer_less_than_header() {
+        let buffer: [u8; 39] = [0; 39];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_packet_with_invalid_encoding() {
+        let mut buffer: [u8; 41] = [0; 41];
+        buffer[0] = 0x60;
+        buffer[5] = 2;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert_eq!(packet.payload().len(), 1);
+
+        buffer[0] = 0x40;
+        buffer[5] = 1;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_repr_serialize_deserialize() {
+        let repr = Repr {
+            src_addr: addr("fe80::1"),
+            dst_addr: addr("ff02::1"),
+            protocol: Protocol::UDP,
+            payload_len: 1,
+            hop_limit: 255,
+            traffic_class: 0xB8,
+            flow_label: 0xABCDE,
+        };
+
+        let mut buffer: [u8; 41] = [0; 41];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet);
+            packet.payload_mut()[0] = 9;
+        }
+
+        assert_eq!(
+            &buffer[.. 8],
+            &[0x6B, 0x8A, 0xBC, 0xDE, 0x00, 0x01, 0x11, 0xFF]
+        );
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(packet.ip_version(), 6);
+        assert_eq!(packet.traffic_class(), 0xB8);
+        assert_eq!(packet.flow_label(), 0xABCDE);
+        assert_eq!(packet.payload(), &[9]);
+        assert_eq!(Repr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_ext_headers() {
+        let mut buffer = vec![0; 40 + 8 + 16 + 8 + 2];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            packet.set_ip_version(6);
+            packet.set_payload_len(34);
+            packet.set_next_header(next_headers::HOP_BY_HOP);
+        }
+        // Hop-by-hop options -> destination options (16 bytes) -> fragment -> UDP.
+        buffer[40] = next_headers::DST_OPTS;
+        buffer[48] = next_headers::FRAGMENT;
+        buffer[49] = 1;
+        buffer[64] = next_headers::UDP;
+        buffer[72] = 0xAA;
+        buffer[73] = 0xBB;
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        let ext_headers: Vec<_> = packet
+            .ext_headers()
+            .map(|ext_header| {
+                ext_header
+                    .map(|(kind, header)| (kind, header.len()))
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(
+            ext_headers,
+            vec![
+                (next_headers::HOP_BY_HOP, 8),
+                (next_headers::DST_OPTS, 16),
+                (next_headers::FRAGMENT, 8),
+            ]
+        );
+        assert_eq!(
+            packet.upper_layer().unwrap(),
+            (next_headers::UDP, &[0xAA, 0xBB][..])
+        );
+        assert_eq!(Repr::deserialize(&packet).unwrap().payload_len, 2);
+
+        // Truncated destination options header.
+        let mut buffer = buffer.clone();
+        buffer[49] = 4;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.upper_layer(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_gen_checksum_with_pseudo_header() {
+        let repr = Repr {
+            src_addr: addr("fe80::1"),
+            dst_addr: addr("fe80::2"),
+            protocol: Protocol::UDP,
+            payload_len: 10,
+            hop_limit: 64,
+            traffic_class: 0,
+            flow_label: 0,
+        };
+
+        // UDP header from port 1234 to 5678, with a zeroed checksum and 2 bytes
+        // of payload.
+        let udp: [u8; 10] = [0x04, 0xD2, 0x16, 0x2E, 0x00, 0x0A, 0x00, 0x00, 0x01, 0x02];
+        assert_eq!(repr.gen_checksum_with_pseudo_header(&udp[..]), 0xE6D3);
+    }
+}
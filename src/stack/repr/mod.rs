@@ -0,0 +1,135 @@
+//! Serialization and deserialization of network packets.
+//!
+//! The `repr` module provides abstractions for serialization and deserializing
+//! packets and frames at different network layers to/from byte buffers.
+
+pub mod arp;
+pub mod builder;
+pub mod dhcpv4;
+pub mod dns;
+pub mod ethernet;
+pub mod icmpv4;
+pub mod icmpv6;
+pub mod igmp;
+pub mod ipv4;
+pub mod ipv6;
+pub mod ntp;
+pub mod pppoe;
+pub mod tcp;
+pub mod tftp;
+pub mod udp;
+
+pub use self::arp::{
+    hw_types as arp_hw_types,
+    proto_types as arp_proto_types,
+    Arp,
+    Op as ArpOp,
+};
+pub use self::builder::PacketBuilder;
+pub use self::dhcpv4::{
+    option_codes as dhcpv4_option_codes,
+    ports as dhcpv4_ports,
+    MessageType as Dhcpv4MessageType,
+    OptionIter as Dhcpv4OptionIter,
+    Packet as Dhcpv4Packet,
+    Repr as Dhcpv4Repr,
+};
+pub use self::dns::{
+    classes as dns_classes,
+    flags as dns_flags,
+    opcodes as dns_opcodes,
+    ports as dns_ports,
+    rcodes as dns_rcodes,
+    types as dns_types,
+    Packet as DnsPacket,
+    Question as DnsQuestion,
+    Record as DnsRecord,
+    RecordData as DnsRecordData,
+    Repr as DnsRepr,
+};
+pub use self::ethernet::{
+    eth_types,
+    Address as EthernetAddress,
+    Frame as EthernetFrame,
+};
+pub use self::icmpv4::{
+    DestinationUnreachable as Icmpv4DestinationUnreachable,
+    Message as Icmpv4Message,
+    Packet as Icmpv4Packet,
+    Redirect as Icmpv4Redirect,
+    Repr as Icmpv4Repr,
+    TimeExceeded as Icmpv4TimeExceeded,
+};
+pub use self::icmpv6::{
+    ndp_options,
+    neighbor_flags,
+    prefix_flags,
+    router_flags,
+    DestinationUnreachable as Icmpv6DestinationUnreachable,
+    Message as Icmpv6Message,
+    Packet as Icmpv6Packet,
+    ParameterProblem as Icmpv6ParameterProblem,
+    PrefixInfo as NdpPrefixInfo,
+    Repr as Icmpv6Repr,
+    TimeExceeded as Icmpv6TimeExceeded,
+};
+pub use self::igmp::{
+    types as igmp_types,
+    Message as IgmpMessage,
+    Packet as IgmpPacket,
+    Repr as IgmpRepr,
+};
+pub use self::ipv4::{
+    flags as ipv4_flags,
+    option_kinds as ipv4_option_kinds,
+    protocols as ipv4_protocols,
+    Address as Ipv4Address,
+    AddressCidr as Ipv4AddressCidr,
+    Ipv4Option,
+    Ipv4OptionIter,
+    Options as Ipv4Options,
+    Packet as Ipv4Packet,
+    Protocol as Ipv4Protocol,
+    Repr as Ipv4Repr,
+};
+pub use self::ipv6::{
+    next_headers as ipv6_next_headers,
+    Address as Ipv6Address,
+    AddressCidr as Ipv6AddressCidr,
+    ExtHeaderIter as Ipv6ExtHeaderIter,
+    Packet as Ipv6Packet,
+    Protocol as Ipv6Protocol,
+    Repr as Ipv6Repr,
+};
+pub use self::ntp::{
+    leap_indicators as ntp_leap_indicators,
+    modes as ntp_modes,
+    ports as ntp_ports,
+    Packet as NtpPacket,
+    Repr as NtpRepr,
+    Timestamp as NtpTimestamp,
+};
+pub use self::pppoe::{
+    codes as pppoe_codes,
+    ppp_protocols,
+    tag_types as pppoe_tag_types,
+    Code as PppoeCode,
+    DiscoveryRepr as PppoeDiscoveryRepr,
+    Packet as PppoePacket,
+    SessionRepr as PppoeSessionRepr,
+    TagIter as PppoeTagIter,
+};
+pub use self::tcp::{
+    Packet as TcpPacket,
+    Repr as TcpRepr,
+};
+pub use self::tftp::{
+    error_codes as tftp_error_codes,
+    modes as tftp_modes,
+    ports as tftp_ports,
+    Repr as TftpRepr,
+};
+pub use self::udp::{
+    Packet as UdpPacket,
+    Repr as UdpRepr,
+};
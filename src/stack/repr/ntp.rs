@@ -0,0 +1,488 @@
+#[cfg(feature = "std")]
+use std::time::{
+    Duration,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use {
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Seconds between the NTP epoch, 1900-01-01, and the Unix epoch.
+const UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+
+/// An NTP timestamp, in seconds and fractions of a second since the NTP
+/// epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timestamp {
+    pub seconds: u32,
+    /// Fraction of a second in units of 2^-32 seconds.
+    pub fraction: u32,
+}
+
+impl Timestamp {
+    /// Creates a timestamp from the system time, which is assumed to fall
+    /// within the current NTP era ending in 2036.
+    #[cfg(feature = "std")]
+    pub fn from_system_time(time: SystemTime) -> Timestamp {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let fraction = (u64::from(since_epoch.subsec_nanos()) << 32) / 1_000_000_000;
+
+        Timestamp {
+            seconds: (since_epoch.as_secs() + UNIX_EPOCH_OFFSET) as u32,
+            fraction: fraction as u32,
+        }
+    }
+
+    /// Converts the timestamp to a system time, assuming it falls within the
+    /// current NTP era.
+    #[cfg(feature = "std")]
+    pub fn to_system_time(self) -> SystemTime {
+        let nanos = (u64::from(self.fraction) * 1_000_000_000) >> 32;
+        let since_ntp_epoch = Duration::new(u64::from(self.seconds), nanos as u32);
+        let unix_epoch_offset = Duration::from_secs(UNIX_EPOCH_OFFSET);
+
+        if since_ntp_epoch >= unix_epoch_offset {
+            UNIX_EPOCH + (since_ntp_epoch - unix_epoch_offset)
+        } else {
+            UNIX_EPOCH - (unix_epoch_offset - since_ntp_epoch)
+        }
+    }
+
+    /// Checks if the timestamp is 0, which indicates an unknown time.
+    pub fn is_zero(self) -> bool {
+        self.seconds == 0 && self.fraction == 0
+    }
+
+    /// Returns the signed difference in nanoseconds between the timestamp
+    /// and an earlier one, handling wrap around between NTP eras.
+    pub fn nanos_since(self, earlier: Timestamp) -> i64 {
+        let diff = self.as_u64().wrapping_sub(earlier.as_u64()) as i64;
+        ((i128::from(diff) * 1_000_000_000) >> 32) as i64
+    }
+
+    fn as_u64(self) -> u64 {
+        (u64::from(self.seconds) << 32) | u64::from(self.fraction)
+    }
+}
+
+/// An NTP message, see RFC 5905. SNTP clients only need to fill in the
+/// version, mode and transmit timestamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repr {
+    pub leap_indicator: u8,
+    pub version: u8,
+    pub mode: u8,
+    /// Distance of the sender from a reference clock, where 0 indicates a
+    /// kiss-o'-death message.
+    pub stratum: u8,
+    /// Log2 of the maximum interval in seconds between messages.
+    pub poll: i8,
+    /// Log2 of the precision in seconds of the sender's clock.
+    pub precision: i8,
+    /// Round trip delay to the reference clock, in NTP short format.
+    pub root_delay: u32,
+    /// Dispersion relative to the reference clock, in NTP short format.
+    pub root_dispersion: u32,
+    /// Identifies the reference clock, or carries the kiss code of
+    /// kiss-o'-death messages.
+    pub reference_id: [u8; 4],
+    /// Time the sender's clock was last set.
+    pub reference_timestamp: Timestamp,
+    /// Time the request was sent by the client, as echoed by the server.
+    pub originate_timestamp: Timestamp,
+    /// Time the request arrived at the server.
+    pub receive_timestamp: Timestamp,
+    /// Time the message departed the sender.
+    pub transmit_timestamp: Timestamp,
+}
+
+impl Repr {
+    /// Creates an SNTP client request sent at a time.
+    pub fn request(transmit_timestamp: Timestamp) -> Repr {
+        Repr {
+            leap_indicator: leap_indicators::UNSYNCHRONIZED,
+            version: VERSION,
+            mode: modes::CLIENT,
+            stratum: 0,
+            poll: 0,
+            precision: 0,
+            root_delay: 0,
+            root_dispersion: 0,
+            reference_id: [0; 4],
+            reference_timestamp: Timestamp::default(),
+            originate_timestamp: Timestamp::default(),
+            receive_timestamp: Timestamp::default(),
+            transmit_timestamp,
+        }
+    }
+
+    /// Returns the buffer size needed to serialize the message.
+    pub fn buffer_len(&self) -> usize {
+        Packet::<&[u8]>::HEADER_LEN
+    }
+
+    /// Deserializes a packet into an NTP message, ignoring any extension
+    /// fields or MAC.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<Repr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let version = packet.version();
+        if version == 0 || version > VERSION {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        Ok(Repr {
+            leap_indicator: packet.leap_indicator(),
+            version,
+            mode: packet.mode(),
+            stratum: packet.stratum(),
+            poll: packet.poll(),
+            precision: packet.precision(),
+            root_delay: packet.root_delay(),
+            root_dispersion: packet.root_dispersion(),
+            reference_id: packet.reference_id(),
+            reference_timestamp: packet.reference_timestamp(),
+            originate_timestamp: packet.originate_timestamp(),
+            receive_timestamp: packet.receive_timestamp(),
+            transmit_timestamp: packet.transmit_timestamp(),
+        })
+    }
+
+    /// Serializes the NTP message into a packet.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>)
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        packet.set_leap_indicator_version_mode(self.leap_indicator, self.version, self.mode);
+        packet.set_stratum(self.stratum);
+        packet.set_poll(self.poll);
+        packet.set_precision(self.precision);
+        packet.set_root_delay(self.root_delay);
+        packet.set_root_dispersion(self.root_dispersion);
+        packet.set_reference_id(self.reference_id);
+        packet.set_reference_timestamp(self.reference_timestamp);
+        packet.set_originate_timestamp(self.originate_timestamp);
+        packet.set_receive_timestamp(self.receive_timestamp);
+        packet.set_transmit_timestamp(self.transmit_timestamp);
+    }
+}
+
+/// NTP version sent in requests, and the highest understood.
+pub const VERSION: u8 = 4;
+
+/// [https://tools.ietf.org/html/rfc5905#section-7.3](https://tools.ietf.org/html/rfc5905#section-7.3)
+mod fields {
+    use core::ops::Range;
+
+    pub const LI_VN_MODE: usize = 0;
+
+    pub const STRATUM: usize = 1;
+
+    pub const POLL: usize = 2;
+
+    pub const PRECISION: usize = 3;
+
+    pub const ROOT_DELAY: Range<usize> = 4 .. 8;
+
+    pub const ROOT_DISPERSION: Range<usize> = 8 .. 12;
+
+    pub const REFERENCE_ID: Range<usize> = 12 .. 16;
+
+    pub const REFERENCE_TIMESTAMP: usize = 16;
+
+    pub const ORIGINATE_TIMESTAMP: usize = 24;
+
+    pub const RECEIVE_TIMESTAMP: usize = 32;
+
+    pub const TRANSMIT_TIMESTAMP: usize = 40;
+}
+
+/// [https://tools.ietf.org/html/rfc5905#section-7.3](https://tools.ietf.org/html/rfc5905#section-7.3)
+pub mod ports {
+    pub const NTP: u16 = 123;
+}
+
+/// [https://tools.ietf.org/html/rfc5905#section-7.3](https://tools.ietf.org/html/rfc5905#section-7.3)
+pub mod leap_indicators {
+    pub const NO_WARNING: u8 = 0;
+
+    pub const LAST_MINUTE_61: u8 = 1;
+
+    pub const LAST_MINUTE_59: u8 = 2;
+
+    pub const UNSYNCHRONIZED: u8 = 3;
+}
+
+/// [https://tools.ietf.org/html/rfc5905#section-7.3](https://tools.ietf.org/html/rfc5905#section-7.3)
+pub mod modes {
+    pub const SYMMETRIC_ACTIVE: u8 = 1;
+
+    pub const SYMMETRIC_PASSIVE: u8 = 2;
+
+    pub const CLIENT: u8 = 3;
+
+    pub const SERVER: u8 = 4;
+
+    pub const BROADCAST: u8 = 5;
+}
+
+/// View of a byte buffer as an NTP packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> AsMut<[u8]> for Packet<T> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut()
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 48;
+
+    /// Tries to create an NTP packet from a byte buffer.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    pub fn leap_indicator(&self) -> u8 {
+        self.buffer.as_ref()[fields::LI_VN_MODE] >> 6
+    }
+
+    pub fn version(&self) -> u8 {
+        (self.buffer.as_ref()[fields::LI_VN_MODE] >> 3) & 0x07
+    }
+
+    pub fn mode(&self) -> u8 {
+        self.buffer.as_ref()[fields::LI_VN_MODE] & 0x07
+    }
+
+    pub fn stratum(&self) -> u8 {
+        self.buffer.as_ref()[fields::STRATUM]
+    }
+
+    pub fn poll(&self) -> i8 {
+        self.buffer.as_ref()[fields::POLL] as i8
+    }
+
+    pub fn precision(&self) -> i8 {
+        self.buffer.as_ref()[fields::PRECISION] as i8
+    }
+
+    pub fn root_delay(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buffer.as_ref()[fields::ROOT_DELAY])
+    }
+
+    pub fn root_dispersion(&self) -> u32 {
+        NetworkEndian::read_u32(&self.buffer.as_ref()[fields::ROOT_DISPERSION])
+    }
+
+    pub fn reference_id(&self) -> [u8; 4] {
+        let mut reference_id = [0; 4];
+        reference_id.copy_from_slice(&self.buffer.as_ref()[fields::REFERENCE_ID]);
+        reference_id
+    }
+
+    pub fn reference_timestamp(&self) -> Timestamp {
+        self.timestamp(fields::REFERENCE_TIMESTAMP)
+    }
+
+    pub fn originate_timestamp(&self) -> Timestamp {
+        self.timestamp(fields::ORIGINATE_TIMESTAMP)
+    }
+
+    pub fn receive_timestamp(&self) -> Timestamp {
+        self.timestamp(fields::RECEIVE_TIMESTAMP)
+    }
+
+    pub fn transmit_timestamp(&self) -> Timestamp {
+        self.timestamp(fields::TRANSMIT_TIMESTAMP)
+    }
+
+    fn timestamp(&self, offset: usize) -> Timestamp {
+        let buffer = &self.buffer.as_ref()[offset .. offset + 8];
+        Timestamp {
+            seconds: NetworkEndian::read_u32(&buffer[0 .. 4]),
+            fraction: NetworkEndian::read_u32(&buffer[4 .. 8]),
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_leap_indicator_version_mode(&mut self, leap_indicator: u8, version: u8, mode: u8) {
+        self.buffer.as_mut()[fields::LI_VN_MODE] =
+            ((leap_indicator & 0x03) << 6) | ((version & 0x07) << 3) | (mode & 0x07);
+    }
+
+    pub fn set_stratum(&mut self, stratum: u8) {
+        self.buffer.as_mut()[fields::STRATUM] = stratum;
+    }
+
+    pub fn set_poll(&mut self, poll: i8) {
+        self.buffer.as_mut()[fields::POLL] = poll as u8;
+    }
+
+    pub fn set_precision(&mut self, precision: i8) {
+        self.buffer.as_mut()[fields::PRECISION] = precision as u8;
+    }
+
+    pub fn set_root_delay(&mut self, root_delay: u32) {
+        NetworkEndian::write_u32(&mut self.buffer.as_mut()[fields::ROOT_DELAY], root_delay)
+    }
+
+    pub fn set_root_dispersion(&mut self, root_dispersion: u32) {
+        NetworkEndian::write_u32(
+            &mut self.buffer.as_mut()[fields::ROOT_DISPERSION],
+            root_dispersion,
+        )
+    }
+
+    pub fn set_reference_id(&mut self, reference_id: [u8; 4]) {
+        self.buffer.as_mut()[fields::REFERENCE_ID].copy_from_slice(&reference_id);
+    }
+
+    pub fn set_reference_timestamp(&mut self, timestamp: Timestamp) {
+        self.set_timestamp(fields::REFERENCE_TIMESTAMP, timestamp);
+    }
+
+    pub fn set_originate_timestamp(&mut self, timestamp: Timestamp) {
+        self.set_timestamp(fields::ORIGINATE_TIMESTAMP, timestamp);
+    }
+
+    pub fn set_receive_timestamp(&mut self, timestamp: Timestamp) {
+        self.set_timestamp(fields::RECEIVE_TIMESTAMP, timestamp);
+    }
+
+    pub fn set_transmit_timestamp(&mut self, timestamp: Timestamp) {
+        self.set_timestamp(fields::TRANSMIT_TIMESTAMP, timestamp);
+    }
+
+    fn set_timestamp(&mut self, offset: usize, timestamp: Timestamp) {
+        let buffer = &mut self.buffer.as_mut()[offset .. offset + 8];
+        NetworkEndian::write_u32(&mut buffer[0 .. 4], timestamp.seconds);
+        NetworkEndian::write_u32(&mut buffer[4 .. 8], timestamp.fraction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Server response with stratum 2, originate timestamp
+    // 0xE1_2E_D3_80.80000000 and receive/transmit timestamps half a second
+    // and one second later.
+    static RESPONSE: [u8; 48] = [
+        0x24, 0x02, 0x06, 0xE9, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x20, 0x0A, 0x00, 0x00,
+        0x01, 0xE1, 0x2E, 0xD3, 0x00, 0x00, 0x00, 0x00, 0x00, 0xE1, 0x2E, 0xD3, 0x80, 0x80, 0x00,
+        0x00, 0x00, 0xE1, 0x2E, 0xD3, 0x81, 0x00, 0x00, 0x00, 0x00, 0xE1, 0x2E, 0xD3, 0x81, 0x80,
+        0x00, 0x00, 0x00,
+    ];
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer: [u8; 47] = [0; 47];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let packet = Packet::try_new(&RESPONSE[..]).unwrap();
+        let repr = Repr::deserialize(&packet).unwrap();
+        assert_eq!(repr.leap_indicator, leap_indicators::NO_WARNING);
+        assert_eq!(repr.version, 4);
+        assert_eq!(repr.mode, modes::SERVER);
+        assert_eq!(repr.stratum, 2);
+        assert_eq!(repr.poll, 6);
+        assert_eq!(repr.precision, -23);
+        assert_eq!(repr.root_delay, 0x10);
+        assert_eq!(repr.root_dispersion, 0x20);
+        assert_eq!(repr.reference_id, [10, 0, 0, 1]);
+        assert_eq!(
+            repr.originate_timestamp,
+            Timestamp {
+                seconds: 0xE12ED380,
+                fraction: 0x80000000,
+            }
+        );
+        assert_eq!(
+            repr.receive_timestamp.nanos_since(repr.originate_timestamp),
+            500_000_000
+        );
+        assert_eq!(
+            repr.transmit_timestamp
+                .nanos_since(repr.originate_timestamp),
+            1_000_000_000
+        );
+    }
+
+    #[test]
+    fn test_deserialize_bad_version() {
+        let mut buffer = RESPONSE;
+        buffer[0] = 0x3C;
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            Repr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let repr = Repr::deserialize(&Packet::try_new(&RESPONSE[..]).unwrap()).unwrap();
+        let mut buffer = [0; 48];
+        repr.serialize(&mut Packet::try_new(&mut buffer[..]).unwrap());
+        assert_eq!(&buffer[..], &RESPONSE[..]);
+    }
+
+    #[test]
+    fn test_timestamp_system_time() {
+        let time = UNIX_EPOCH + Duration::new(1_500_000_000, 250_000_000);
+        let timestamp = Timestamp::from_system_time(time);
+        assert_eq!(
+            timestamp,
+            Timestamp {
+                seconds: 3_708_988_800,
+                fraction: 0x40000000,
+            }
+        );
+        assert_eq!(timestamp.to_system_time(), time);
+    }
+
+    #[test]
+    fn test_timestamp_nanos_since_across_eras() {
+        let earlier = Timestamp {
+            seconds: 0xFFFFFFFF,
+            fraction: 0,
+        };
+        let later = Timestamp {
+            seconds: 1,
+            fraction: 0,
+        };
+        assert_eq!(later.nanos_since(earlier), 2_000_000_000);
+        assert_eq!(earlier.nanos_since(later), -2_000_000_000);
+    }
+}
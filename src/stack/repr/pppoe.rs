@@ -0,0 +1,574 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// The stage of PPPoE a packet belongs to, see RFC 2516 section 5.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Code {
+    /// A PPP frame carried by an established session.
+    Session,
+    /// Active Discovery Initiation, broadcast by hosts looking for an access
+    /// concentrator.
+    Padi,
+    /// Active Discovery Offer, sent by access concentrators in response to a
+    /// PADI.
+    Pado,
+    /// Active Discovery Request, sent by hosts to the access concentrator
+    /// they chose.
+    Padr,
+    /// Active Discovery Session-confirmation, which assigns the session ID.
+    Pads,
+    /// Active Discovery Terminate, sent by either side to end a session.
+    Padt,
+}
+
+impl Code {
+    fn try_from(code: u8) -> Result<Code> {
+        match code {
+            codes::SESSION => Ok(Code::Session),
+            codes::PADI => Ok(Code::Padi),
+            codes::PADO => Ok(Code::Pado),
+            codes::PADR => Ok(Code::Padr),
+            codes::PADS => Ok(Code::Pads),
+            codes::PADT => Ok(Code::Padt),
+            _ => Err(Error::Parse(ParseError::Malformed)),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            Code::Session => codes::SESSION,
+            Code::Padi => codes::PADI,
+            Code::Pado => codes::PADO,
+            Code::Padr => codes::PADR,
+            Code::Pads => codes::PADS,
+            Code::Padt => codes::PADT,
+        }
+    }
+}
+
+/// A PPPoE discovery packet, along with the tags relevant to establishing a
+/// session. Tags are omitted if None.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveryRepr {
+    pub code: Code,
+    /// Zero until assigned by the PADS.
+    pub session_id: u16,
+    /// Service requested by the host or offered by the access concentrator,
+    /// where an empty name means any service.
+    pub service_name: Option<Vec<u8>>,
+    pub ac_name: Option<Vec<u8>>,
+    /// Value chosen by the host to match replies to requests, which access
+    /// concentrators echo back.
+    pub host_uniq: Option<Vec<u8>>,
+    /// Value from an access concentrator's PADO which the host echoes back in
+    /// its PADR.
+    pub ac_cookie: Option<Vec<u8>>,
+    /// Value added by relays which must be echoed back.
+    pub relay_session_id: Option<Vec<u8>>,
+    /// Type and contents of an error tag, e.g. a Service-Name-Error.
+    pub error: Option<(u16, Vec<u8>)>,
+}
+
+impl DiscoveryRepr {
+    /// Creates a discovery packet without tags.
+    pub fn new(code: Code, session_id: u16) -> DiscoveryRepr {
+        DiscoveryRepr {
+            code,
+            session_id,
+            service_name: None,
+            ac_name: None,
+            host_uniq: None,
+            ac_cookie: None,
+            relay_session_id: None,
+            error: None,
+        }
+    }
+
+    /// Returns the length of the tags in the packet.
+    fn tags_len(&self) -> usize {
+        let tag_len = |value: &Option<Vec<u8>>| value.as_ref().map_or(0, |value| 4 + value.len());
+
+        tag_len(&self.service_name)
+            + tag_len(&self.ac_name)
+            + tag_len(&self.host_uniq)
+            + tag_len(&self.ac_cookie)
+            + tag_len(&self.relay_session_id)
+            + self.error.as_ref().map_or(0, |(_, value)| 4 + value.len())
+    }
+
+    /// Returns the buffer size needed to serialize the packet.
+    pub fn buffer_len(&self) -> usize {
+        Packet::<&[u8]>::buffer_len(self.tags_len())
+    }
+
+    /// Tries to deserialize a packet into a discovery packet.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<DiscoveryRepr>
+    where
+        T: AsRef<[u8]>,
+    {
+        let code = Code::try_from(packet.code())?;
+        if code == Code::Session {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let mut repr = DiscoveryRepr::new(code, packet.session_id());
+
+        for tag in packet.tags() {
+            let (tag_type, value) = tag?;
+            let value = Some(value.to_vec());
+            match tag_type {
+                tag_types::SERVICE_NAME => repr.service_name = value,
+                tag_types::AC_NAME => repr.ac_name = value,
+                tag_types::HOST_UNIQ => repr.host_uniq = value,
+                tag_types::AC_COOKIE => repr.ac_cookie = value,
+                tag_types::RELAY_SESSION_ID => repr.relay_session_id = value,
+                tag_types::SERVICE_NAME_ERROR
+                | tag_types::AC_SYSTEM_ERROR
+                | tag_types::GENERIC_ERROR => repr.error = value.map(|value| (tag_type, value)),
+                _ => {}
+            }
+        }
+
+        Ok(repr)
+    }
+
+    /// Serializes the discovery packet into a packet.
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        if packet.as_ref().len() < self.buffer_len() {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        packet.set_ver_type(Packet::<&[u8]>::VER_TYPE);
+        packet.set_code(self.code.as_u8());
+        packet.set_session_id(self.session_id);
+        packet.set_length(self.tags_len() as u16);
+
+        let tags = [
+            (tag_types::SERVICE_NAME, self.service_name.as_ref()),
+            (tag_types::AC_NAME, self.ac_name.as_ref()),
+            (tag_types::HOST_UNIQ, self.host_uniq.as_ref()),
+            (tag_types::AC_COOKIE, self.ac_cookie.as_ref()),
+            (tag_types::RELAY_SESSION_ID, self.relay_session_id.as_ref()),
+        ];
+
+        let mut writer = packet.payload_mut();
+        for &(tag_type, value) in tags.iter() {
+            if let Some(value) = value {
+                write_tag(&mut writer, tag_type, value);
+            }
+        }
+        if let Some((tag_type, ref value)) = self.error {
+            write_tag(&mut writer, tag_type, value);
+        }
+
+        Ok(())
+    }
+}
+
+fn write_tag(writer: &mut &mut [u8], tag_type: u16, value: &[u8]) {
+    let (header, rest) = ::core::mem::take(writer).split_at_mut(4);
+    NetworkEndian::write_u16(&mut header[0 .. 2], tag_type);
+    NetworkEndian::write_u16(&mut header[2 .. 4], value.len() as u16);
+    let (tag, rest) = rest.split_at_mut(value.len());
+    tag.copy_from_slice(value);
+    *writer = rest;
+}
+
+/// A PPPoE session packet carrying a PPP frame of some protocol, see RFC 2516
+/// section 6.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionRepr {
+    pub session_id: u16,
+    /// PPP protocol of the payload, e.g. ppp_protocols::IPV4.
+    pub protocol: u16,
+    pub payload_len: usize,
+}
+
+impl SessionRepr {
+    /// Length of the PPP protocol field preceding the payload.
+    pub const PPP_HEADER_LEN: usize = 2;
+
+    /// Returns the buffer size needed to serialize the packet and payload.
+    pub fn buffer_len(&self) -> usize {
+        Packet::<&[u8]>::buffer_len(Self::PPP_HEADER_LEN + self.payload_len)
+    }
+
+    /// Tries to deserialize a packet into a session packet.
+    pub fn deserialize<T>(packet: &Packet<T>) -> Result<SessionRepr>
+    where
+        T: AsRef<[u8]>,
+    {
+        if packet.code() != codes::SESSION {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let payload = packet.payload();
+        if payload.len() < Self::PPP_HEADER_LEN {
+            return Err(Error::Parse(ParseError::Truncated));
+        }
+
+        Ok(SessionRepr {
+            session_id: packet.session_id(),
+            protocol: NetworkEndian::read_u16(&payload[.. 2]),
+            payload_len: payload.len() - Self::PPP_HEADER_LEN,
+        })
+    }
+
+    /// Serializes the session packet header and PPP protocol into a packet,
+    /// leaving the payload to be filled in via ppp_payload_mut().
+    pub fn serialize<T>(&self, packet: &mut Packet<T>) -> Result<()>
+    where
+        T: AsRef<[u8]> + AsMut<[u8]>,
+    {
+        if packet.as_ref().len() < self.buffer_len() {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        packet.set_ver_type(Packet::<&[u8]>::VER_TYPE);
+        packet.set_code(codes::SESSION);
+        packet.set_session_id(self.session_id);
+        packet.set_length((Self::PPP_HEADER_LEN + self.payload_len) as u16);
+        NetworkEndian::write_u16(&mut packet.payload_mut()[.. 2], self.protocol);
+
+        Ok(())
+    }
+}
+
+/// [https://tools.ietf.org/html/rfc2516#section-5](https://tools.ietf.org/html/rfc2516#section-5)
+pub mod codes {
+    pub const SESSION: u8 = 0x00;
+
+    pub const PADO: u8 = 0x07;
+
+    pub const PADI: u8 = 0x09;
+
+    pub const PADR: u8 = 0x19;
+
+    pub const PADS: u8 = 0x65;
+
+    pub const PADT: u8 = 0xA7;
+}
+
+/// [https://tools.ietf.org/html/rfc2516#appendix-A](https://tools.ietf.org/html/rfc2516#appendix-A)
+pub mod tag_types {
+    pub const END_OF_LIST: u16 = 0x0000;
+
+    pub const SERVICE_NAME: u16 = 0x0101;
+
+    pub const AC_NAME: u16 = 0x0102;
+
+    pub const HOST_UNIQ: u16 = 0x0103;
+
+    pub const AC_COOKIE: u16 = 0x0104;
+
+    pub const RELAY_SESSION_ID: u16 = 0x0110;
+
+    pub const SERVICE_NAME_ERROR: u16 = 0x0201;
+
+    pub const AC_SYSTEM_ERROR: u16 = 0x0202;
+
+    pub const GENERIC_ERROR: u16 = 0x0203;
+}
+
+/// PPP protocols carried by session packets, see RFC 1661.
+pub mod ppp_protocols {
+    pub const IPV4: u16 = 0x0021;
+
+    pub const IPCP: u16 = 0x8021;
+
+    pub const LCP: u16 = 0xC021;
+}
+
+/// [https://tools.ietf.org/html/rfc2516#section-4](https://tools.ietf.org/html/rfc2516#section-4)
+mod fields {
+    use core::ops::Range;
+
+    pub const VER_TYPE: usize = 0;
+
+    pub const CODE: usize = 1;
+
+    pub const SESSION_ID: Range<usize> = 2 .. 4;
+
+    pub const LENGTH: Range<usize> = 4 .. 6;
+}
+
+/// An iterator over the (type, value) pairs of the tags in a PPPoE discovery
+/// packet.
+pub struct TagIter<'a> {
+    tags: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Iterator for TagIter<'a> {
+    type Item = Result<(u16, &'a [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position + 4 > self.tags.len() {
+            return None;
+        }
+
+        let header = &self.tags[self.position .. self.position + 4];
+        let tag_type = NetworkEndian::read_u16(&header[.. 2]);
+        let len = NetworkEndian::read_u16(&header[2 ..]) as usize;
+
+        let start = self.position + 4;
+        if tag_type == tag_types::END_OF_LIST {
+            self.position = self.tags.len();
+            return None;
+        } else if start + len > self.tags.len() {
+            self.position = self.tags.len();
+            return Some(Err(Error::Parse(ParseError::Malformed)));
+        }
+
+        self.position = start + len;
+        Some(Ok((tag_type, &self.tags[start .. start + len])))
+    }
+}
+
+/// View of a byte buffer as a PPPoE packet.
+#[derive(Debug)]
+pub struct Packet<T: AsRef<[u8]>> {
+    buffer: T,
+}
+
+impl<T: AsRef<[u8]>> AsRef<[u8]> for Packet<T> {
+    fn as_ref(&self) -> &[u8] {
+        self.buffer.as_ref()
+    }
+}
+
+impl<T: AsRef<[u8]>> Packet<T> {
+    pub const HEADER_LEN: usize = 6;
+
+    /// Version 1 and type 1, the only combination defined by RFC 2516.
+    pub const VER_TYPE: u8 = 0x11;
+
+    /// Tries to create a PPPoE packet from a byte buffer.
+    ///
+    /// NOTE: Use check_encoding() before operating on the packet if the
+    /// provided buffer originates from a untrusted source such as a link.
+    /// Getters never panic regardless, and the payload is clamped to the
+    /// buffer if the length field exceeds it.
+    pub fn try_new(buffer: T) -> Result<Packet<T>> {
+        if buffer.as_ref().len() < Self::HEADER_LEN {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(Packet { buffer })
+        }
+    }
+
+    /// Returns the length of a PPPoE packet with the specified payload size.
+    pub fn buffer_len(payload_len: usize) -> usize {
+        Self::HEADER_LEN + payload_len
+    }
+
+    /// Checks if the packet has a valid encoding, i.e. a supported version and
+    /// type and a length which fits in the buffer.
+    pub fn check_encoding(&self) -> Result<()> {
+        if self.ver_type() != Self::VER_TYPE {
+            Err(Error::Parse(ParseError::Malformed))
+        } else if Self::buffer_len(self.length() as usize) > self.buffer.as_ref().len() {
+            Err(Error::Parse(ParseError::Truncated))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn ver_type(&self) -> u8 {
+        self.buffer.as_ref()[fields::VER_TYPE]
+    }
+
+    pub fn code(&self) -> u8 {
+        self.buffer.as_ref()[fields::CODE]
+    }
+
+    pub fn session_id(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::SESSION_ID])
+    }
+
+    pub fn length(&self) -> u16 {
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::LENGTH])
+    }
+
+    /// Returns the offset at which the payload ends, clamped to the buffer
+    /// since Ethernet pads short frames.
+    fn payload_end(&self) -> usize {
+        min(
+            Self::buffer_len(self.length() as usize),
+            self.buffer.as_ref().len(),
+        )
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.buffer.as_ref()[Self::HEADER_LEN .. self.payload_end()]
+    }
+
+    /// Returns the PPP frame of a session packet without its protocol field.
+    pub fn ppp_payload(&self) -> &[u8] {
+        let payload = self.payload();
+        &payload[min(SessionRepr::PPP_HEADER_LEN, payload.len()) ..]
+    }
+
+    /// Returns an iterator over the tags of a discovery packet.
+    pub fn tags(&self) -> TagIter<'_> {
+        TagIter {
+            tags: self.payload(),
+            position: 0,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
+    pub fn set_ver_type(&mut self, ver_type: u8) {
+        self.buffer.as_mut()[fields::VER_TYPE] = ver_type;
+    }
+
+    pub fn set_code(&mut self, code: u8) {
+        self.buffer.as_mut()[fields::CODE] = code;
+    }
+
+    pub fn set_session_id(&mut self, session_id: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::SESSION_ID], session_id)
+    }
+
+    pub fn set_length(&mut self, length: u16) {
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::LENGTH], length)
+    }
+
+    pub fn payload_mut(&mut self) -> &mut [u8] {
+        let payload_end = self.payload_end();
+        &mut self.buffer.as_mut()[Self::HEADER_LEN .. payload_end]
+    }
+
+    pub fn ppp_payload_mut(&mut self) -> &mut [u8] {
+        let payload = self.payload_mut();
+        let start = min(SessionRepr::PPP_HEADER_LEN, payload.len());
+        &mut payload[start ..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packet_buffer_too_small() {
+        let buffer: [u8; 5] = [0; 5];
+        assert_matches!(
+            Packet::try_new(&buffer[..]),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+    }
+
+    #[test]
+    fn test_packet_invalid_encoding() {
+        let buffer: [u8; 6] = [0x21, 0x09, 0x00, 0x00, 0x00, 0x00];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+
+        let buffer: [u8; 8] = [0x11, 0x09, 0x00, 0x00, 0x00, 0x04, 0x01, 0x01];
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(),
+            Err(Error::Parse(ParseError::Truncated))
+        );
+        assert_eq!(packet.payload(), &[0x01, 0x01]);
+        assert!(packet.tags().next().is_none());
+    }
+
+    #[test]
+    fn test_discovery_serialize_deserialize() {
+        let repr = DiscoveryRepr {
+            service_name: Some(Vec::new()),
+            host_uniq: Some(vec![0xAB, 0xCD]),
+            ..DiscoveryRepr::new(Code::Padi, 0)
+        };
+
+        let mut buffer = vec![0; repr.buffer_len()];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+        }
+
+        assert_eq!(
+            &buffer[..],
+            &[
+                0x11, 0x09, 0x00, 0x00, 0x00, 0x0A, 0x01, 0x01, 0x00, 0x00, 0x01, 0x03, 0x00,
+                0x02, 0xAB, 0xCD,
+            ][..]
+        );
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(DiscoveryRepr::deserialize(&packet).unwrap(), repr);
+    }
+
+    #[test]
+    fn test_discovery_deserialize_with_padding() {
+        // A PADO padded to the minimum Ethernet payload, with an error tag and
+        // an unknown tag.
+        let mut buffer = [0; 46];
+        buffer[.. 26].copy_from_slice(&[
+            0x11, 0x07, 0x00, 0x00, 0x00, 0x14, 0x01, 0x02, 0x00, 0x02, 0x61, 0x63, 0x01, 0x05,
+            0x00, 0x00, 0x02, 0x01, 0x00, 0x04, 0x6E, 0x6F, 0x70, 0x65, 0xFF, 0xFF,
+        ]);
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+
+        let repr = DiscoveryRepr::deserialize(&packet).unwrap();
+        assert_eq!(repr.code, Code::Pado);
+        assert_eq!(repr.ac_name, Some(b"ac".to_vec()));
+        assert_eq!(
+            repr.error,
+            Some((tag_types::SERVICE_NAME_ERROR, b"nope".to_vec()))
+        );
+    }
+
+    #[test]
+    fn test_session_serialize_deserialize() {
+        let repr = SessionRepr {
+            session_id: 0x1234,
+            protocol: ppp_protocols::IPV4,
+            payload_len: 3,
+        };
+
+        let mut buffer = vec![0; repr.buffer_len()];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet).unwrap();
+            packet.ppp_payload_mut().copy_from_slice(&[1, 2, 3]);
+        }
+
+        assert_eq!(
+            &buffer[..],
+            &[0x11, 0x00, 0x12, 0x34, 0x00, 0x05, 0x00, 0x21, 0x01, 0x02, 0x03][..]
+        );
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(packet.check_encoding(), Ok(_));
+        assert_eq!(SessionRepr::deserialize(&packet).unwrap(), repr);
+        assert_eq!(packet.ppp_payload(), &[1, 2, 3]);
+        assert_matches!(
+            DiscoveryRepr::deserialize(&packet),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+}
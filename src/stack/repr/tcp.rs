@@ -1,11 +1,20 @@
+use alloc::string::String;
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+
 use byteorder::{
     ByteOrder,
     NetworkEndian,
 };
 
-use core::repr::Ipv4Repr;
+use stack::repr::Ipv4Repr;
 use {
+    BufferError,
     Error,
+    ParseError,
     Result,
 };
 
@@ -94,7 +103,7 @@ impl Repr {
         T: AsRef<[u8]> + AsMut<[u8]>,
     {
         if self.header_len() > packet.as_ref().len() {
-            return Err(Error::Exhausted);
+            return Err(Error::Buffer(BufferError::TooSmall));
         }
 
         packet.set_src_port(self.src_port);
@@ -142,7 +151,7 @@ impl Repr {
 
 /// [https://en.wikipedia.org/wiki/Transmission_Control_Protocol#TCP_segment_structure](https://en.wikipedia.org/wiki/Transmission_Control_Protocol#TCP_segment_structure)
 mod fields {
-    use std::ops::Range;
+    use core::ops::Range;
 
     pub const SRC_PORT: Range<usize> = 0 .. 2;
 
@@ -196,7 +205,7 @@ impl<'a> Iterator for TcpOptionIter<'a> {
 
                 let len = self.options[self.position + 1] as usize;
 
-                if self.position + len > self.options.len() {
+                if len < 2 || self.position + len > self.options.len() {
                     // Length exceeds buffer!
                     return None;
                 }
@@ -255,9 +264,12 @@ impl<T: AsRef<[u8]>> Packet<T> {
     /// NOTE: Use check_encoding() before operating on the packet if
     /// constructing a packet via a buffer originating from an untrusted
     /// source like a link.
+    ///
+    /// Getters clamp the data offset to the buffer, so they don't panic if
+    /// it's skipped, but may return truncated options.
     pub fn try_new(buffer: T) -> Result<Packet<T>> {
         if buffer.as_ref().len() < Self::MIN_HEADER_LEN {
-            Err(Error::Exhausted)
+            Err(Error::Parse(ParseError::Truncated))
         } else {
             Ok(Packet { buffer })
         }
@@ -273,11 +285,11 @@ impl<T: AsRef<[u8]>> Packet<T> {
     /// field consistency, etc. checks.
     pub fn check_encoding(&self, ipv4_repr: &Ipv4Repr) -> Result<()> {
         if self.gen_packet_checksum(ipv4_repr) != 0 {
-            Err(Error::Checksum)
+            Err(Error::Parse(ParseError::Checksum))
         } else if ((self.data_offset() * 4) as usize) < Self::MIN_HEADER_LEN
             || (self.data_offset() as usize) * 4 > self.as_ref().len()
         {
-            Err(Error::Malformed)
+            Err(Error::Parse(ParseError::Malformed))
         } else {
             Ok(())
         }
@@ -362,13 +374,20 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     pub fn options(&self) -> &[u8] {
-        let data_offset = (self.data_offset() * 4) as usize;
-        &self.as_ref()[Self::MIN_HEADER_LEN .. data_offset]
+        &self.as_ref()[Self::MIN_HEADER_LEN .. self.header_end()]
     }
 
     pub fn payload(&self) -> &[u8] {
+        &self.as_ref()[self.header_end() ..]
+    }
+
+    /// Returns the offset of the payload, clamped to the buffer so getters
+    /// don't panic on segments which fail check_encoding().
+    fn header_end(&self) -> usize {
         let data_offset = (self.data_offset() * 4) as usize;
-        &self.as_ref()[data_offset ..]
+        data_offset
+            .max(Self::MIN_HEADER_LEN)
+            .min(self.as_ref().len())
     }
 }
 
@@ -455,13 +474,13 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     }
 
     pub fn options_mut(&mut self) -> &mut [u8] {
-        let data_offset = (self.data_offset() * 4) as usize;
-        &mut self.as_mut()[Self::MIN_HEADER_LEN .. data_offset]
+        let header_end = self.header_end();
+        &mut self.as_mut()[Self::MIN_HEADER_LEN .. header_end]
     }
 
     pub fn payload_mut(&mut self) -> &mut [u8] {
-        let data_offset = (self.data_offset() * 4) as usize;
-        &mut self.as_mut()[data_offset ..]
+        let header_end = self.header_end();
+        &mut self.as_mut()[header_end ..]
     }
 
     pub fn fill_checksum(&mut self, ipv4_repr: &Ipv4Repr) {
@@ -471,10 +490,50 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    /// Formats a tcpdump-like summary of the segment header, with flags
+    /// abbreviated as in tcpdump, e.g. [S.] for a SYN + ACK.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut flags = String::new();
+        for &(set, abbrev) in [
+            (self.syn(), 'S'),
+            (self.fin(), 'F'),
+            (self.rst(), 'R'),
+            (self.psh(), 'P'),
+            (self.urg(), 'U'),
+            (self.ece(), 'E'),
+            (self.cwr(), 'W'),
+            (self.ack(), '.'),
+        ].iter()
+        {
+            if set {
+                flags.push(abbrev);
+            }
+        }
+
+        let header_len = (self.data_offset() as usize) * 4;
+        let payload_len = self.as_ref().len().saturating_sub(header_len);
+
+        write!(
+            f,
+            "TCP {} > {}, flags [{}], seq {}, ack {}, win {}, length {}",
+            self.src_port(),
+            self.dst_port(),
+            flags,
+            self.seq_num(),
+            self.ack_num(),
+            self.window_size(),
+            payload_len
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use core::repr::{
+    use stack::repr::{
+        ipv4_flags,
         Ipv4Address,
+        Ipv4Options,
         Ipv4Protocol,
     };
 
@@ -486,6 +545,10 @@ mod tests {
             dst_addr: Ipv4Address::new([4, 5, 6, 7]),
             protocol: Ipv4Protocol::TCP,
             payload_len: payload_len as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
         }
     }
 
@@ -493,7 +556,7 @@ mod tests {
     fn test_packet_with_buffer_less_than_min_header() {
         let buffer: [u8; 19] = [0; 19];
         let packet = Packet::try_new(&buffer[..]);
-        assert_matches!(packet, Err(Error::Exhausted));
+        assert_matches!(packet, Err(Error::Parse(ParseError::Truncated)));
     }
 
     #[test]
@@ -504,7 +567,10 @@ mod tests {
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         ];
         let packet = Packet::try_new(&buffer[..]).unwrap();
-        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Err(Error::Checksum));
+        assert_matches!(
+            packet.check_encoding(&ipv4_repr(16)),
+            Err(Error::Parse(ParseError::Checksum))
+        );
     }
 
     #[test]
@@ -515,7 +581,23 @@ mod tests {
             0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         ];
         let packet = Packet::try_new(&buffer[..]).unwrap();
-        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Err(Error::Malformed));
+        assert_matches!(
+            packet.check_encoding(&ipv4_repr(16)),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert!(packet.options().is_empty());
+        assert_eq!(packet.payload().len(), 16);
+
+        let mut buffer = buffer;
+        buffer[12] = 0xF0;
+        buffer[16 .. 18].copy_from_slice(&[0xAC, 0x90]);
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(
+            packet.check_encoding(&ipv4_repr(16)),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+        assert_eq!(packet.options().len(), 16);
+        assert!(packet.payload().is_empty());
     }
 
     #[test]
@@ -618,4 +700,15 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_options_iterator_with_invalid_len() {
+        let buffer: [u8; 6] = [1, 2, 0, 1, 1, 1];
+        let options: Vec<_> = TcpOptionIter::new(&buffer).collect();
+        assert_eq!(options, vec![TcpOption::NoOp]);
+
+        let buffer: [u8; 3] = [1, 99, 1];
+        let options: Vec<_> = TcpOptionIter::new(&buffer).collect();
+        assert_eq!(options, vec![TcpOption::NoOp]);
+    }
 }
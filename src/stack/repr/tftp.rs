@@ -0,0 +1,280 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Number of bytes of data in every DATA message but the last of a transfer.
+pub const BLOCK_LEN: usize = 512;
+
+/// A TFTP message, see RFC 1350.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Repr {
+    /// Requests to read a file from the server.
+    ReadRequest { filename: String, mode: String },
+    /// Requests to write a file to the server.
+    WriteRequest { filename: String, mode: String },
+    /// Carries up to BLOCK_LEN bytes of a file, with blocks numbered from 1.
+    Data { block: u16, data: Vec<u8> },
+    /// Acknowledges a DATA message, or a WRQ with block 0.
+    Ack { block: u16 },
+    /// Terminates a transfer.
+    Error { code: u16, message: String },
+}
+
+impl Repr {
+    /// Returns the buffer size needed to serialize the TFTP message.
+    pub fn buffer_len(&self) -> usize {
+        match *self {
+            Repr::ReadRequest {
+                ref filename,
+                ref mode,
+            }
+            | Repr::WriteRequest {
+                ref filename,
+                ref mode,
+            } => 2 + filename.len() + 1 + mode.len() + 1,
+            Repr::Data { ref data, .. } => 4 + data.len(),
+            Repr::Ack { .. } => 4,
+            Repr::Error { ref message, .. } => 4 + message.len() + 1,
+        }
+    }
+
+    /// Tries to deserialize a packet into a TFTP message.
+    pub fn deserialize(buffer: &[u8]) -> Result<Repr> {
+        if buffer.len() < 4 {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+
+        let opcode = NetworkEndian::read_u16(&buffer[0 .. 2]);
+
+        match opcode {
+            opcodes::RRQ | opcodes::WRQ => {
+                let (filename, next) = read_string(buffer, 2)?;
+                let (mode, _) = read_string(buffer, next)?;
+                if filename.is_empty() {
+                    return Err(Error::Parse(ParseError::Malformed));
+                }
+
+                if opcode == opcodes::RRQ {
+                    Ok(Repr::ReadRequest { filename, mode })
+                } else {
+                    Ok(Repr::WriteRequest { filename, mode })
+                }
+            }
+            opcodes::DATA => {
+                if buffer.len() > 4 + BLOCK_LEN {
+                    return Err(Error::Parse(ParseError::Malformed));
+                }
+
+                Ok(Repr::Data {
+                    block: NetworkEndian::read_u16(&buffer[2 .. 4]),
+                    data: buffer[4 ..].to_vec(),
+                })
+            }
+            opcodes::ACK => Ok(Repr::Ack {
+                block: NetworkEndian::read_u16(&buffer[2 .. 4]),
+            }),
+            opcodes::ERROR => Ok(Repr::Error {
+                code: NetworkEndian::read_u16(&buffer[2 .. 4]),
+                message: read_string(buffer, 4)?.0,
+            }),
+            _ => Err(Error::Parse(ParseError::Malformed)),
+        }
+    }
+
+    /// Serializes the TFTP message into a packet.
+    pub fn serialize(&self, buffer: &mut [u8]) -> Result<()> {
+        if self.buffer_len() > buffer.len() {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        match *self {
+            Repr::ReadRequest {
+                ref filename,
+                ref mode,
+            }
+            | Repr::WriteRequest {
+                ref filename,
+                ref mode,
+            } => {
+                let opcode = match *self {
+                    Repr::ReadRequest { .. } => opcodes::RRQ,
+                    _ => opcodes::WRQ,
+                };
+                NetworkEndian::write_u16(&mut buffer[0 .. 2], opcode);
+                let next = write_string(buffer, 2, filename);
+                write_string(buffer, next, mode);
+            }
+            Repr::Data { block, ref data } => {
+                NetworkEndian::write_u16(&mut buffer[0 .. 2], opcodes::DATA);
+                NetworkEndian::write_u16(&mut buffer[2 .. 4], block);
+                buffer[4 .. 4 + data.len()].copy_from_slice(data);
+            }
+            Repr::Ack { block } => {
+                NetworkEndian::write_u16(&mut buffer[0 .. 2], opcodes::ACK);
+                NetworkEndian::write_u16(&mut buffer[2 .. 4], block);
+            }
+            Repr::Error { code, ref message } => {
+                NetworkEndian::write_u16(&mut buffer[0 .. 2], opcodes::ERROR);
+                NetworkEndian::write_u16(&mut buffer[2 .. 4], code);
+                write_string(buffer, 4, message);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads a NUL terminated string at an offset, returning the string and the
+/// offset following the terminator.
+fn read_string(buffer: &[u8], offset: usize) -> Result<(String, usize)> {
+    let len = buffer
+        .get(offset ..)
+        .and_then(|rest| rest.iter().position(|&byte| byte == 0))
+        .ok_or(Error::Parse(ParseError::Malformed))?;
+
+    let string = String::from_utf8(buffer[offset .. offset + len].to_vec())
+        .map_err(|_| Error::Parse(ParseError::Malformed))?;
+
+    Ok((string, offset + len + 1))
+}
+
+/// Writes a NUL terminated string at an offset, returning the offset
+/// following the terminator.
+fn write_string(buffer: &mut [u8], offset: usize, string: &str) -> usize {
+    buffer[offset .. offset + string.len()].copy_from_slice(string.as_bytes());
+    buffer[offset + string.len()] = 0;
+    offset + string.len() + 1
+}
+
+/// [https://tools.ietf.org/html/rfc1350#section-4](https://tools.ietf.org/html/rfc1350#section-4)
+pub mod ports {
+    pub const TFTP: u16 = 69;
+}
+
+/// [https://tools.ietf.org/html/rfc1350#section-5](https://tools.ietf.org/html/rfc1350#section-5)
+pub mod opcodes {
+    pub const RRQ: u16 = 1;
+
+    pub const WRQ: u16 = 2;
+
+    pub const DATA: u16 = 3;
+
+    pub const ACK: u16 = 4;
+
+    pub const ERROR: u16 = 5;
+}
+
+/// [https://tools.ietf.org/html/rfc1350#appendix-I](https://tools.ietf.org/html/rfc1350#appendix-I)
+pub mod error_codes {
+    pub const NOT_DEFINED: u16 = 0;
+
+    pub const FILE_NOT_FOUND: u16 = 1;
+
+    pub const ACCESS_VIOLATION: u16 = 2;
+
+    pub const DISK_FULL: u16 = 3;
+
+    pub const ILLEGAL_OPERATION: u16 = 4;
+
+    pub const UNKNOWN_TID: u16 = 5;
+
+    pub const FILE_EXISTS: u16 = 6;
+
+    pub const NO_SUCH_USER: u16 = 7;
+}
+
+/// Transfer modes, of which only octet is supported.
+pub mod modes {
+    pub const NETASCII: &str = "netascii";
+
+    pub const OCTET: &str = "octet";
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_request() {
+        let buffer = b"\x00\x01boot.img\x00octet\x00";
+        assert_eq!(
+            Repr::deserialize(&buffer[..]).unwrap(),
+            Repr::ReadRequest {
+                filename: "boot.img".to_string(),
+                mode: "octet".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_unterminated_request() {
+        let buffer = b"\x00\x02boot.img\x00octet";
+        assert_matches!(
+            Repr::deserialize(&buffer[..]),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_data_too_long() {
+        let mut buffer = vec![0; 4 + BLOCK_LEN + 1];
+        buffer[1] = opcodes::DATA as u8;
+        assert_matches!(
+            Repr::deserialize(&buffer),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_unknown_opcode() {
+        assert_matches!(
+            Repr::deserialize(&[0, 6, 0, 0]),
+            Err(Error::Parse(ParseError::Malformed))
+        );
+    }
+
+    #[test]
+    fn test_serialize_and_deserialize() {
+        let reprs = [
+            Repr::WriteRequest {
+                filename: "a.txt".to_string(),
+                mode: modes::OCTET.to_string(),
+            },
+            Repr::Data {
+                block: 7,
+                data: vec![1, 2, 3],
+            },
+            Repr::Ack { block: 0xFFFF },
+            Repr::Error {
+                code: error_codes::FILE_NOT_FOUND,
+                message: "no such file".to_string(),
+            },
+        ];
+
+        for repr in reprs.iter() {
+            let mut buffer = vec![0; repr.buffer_len()];
+            repr.serialize(&mut buffer).unwrap();
+            assert_eq!(Repr::deserialize(&buffer).unwrap(), *repr);
+        }
+    }
+
+    #[test]
+    fn test_serialize_buffer_too_small() {
+        let repr = Repr::Ack { block: 1 };
+        assert_matches!(
+            repr.serialize(&mut [0; 3]),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+    }
+}
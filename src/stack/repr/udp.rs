@@ -1,12 +1,18 @@
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+
 use byteorder::{
+    ByteOrder,
     NetworkEndian,
-    ReadBytesExt,
-    WriteBytesExt,
 };
 
-use core::repr::Ipv4Repr;
+use stack::repr::Ipv4Repr;
 use {
     Error,
+    ParseError,
     Result,
 };
 
@@ -16,6 +22,9 @@ pub struct Repr {
     pub src_port: u16,
     pub dst_port: u16,
     pub length: u16,
+    /// Indicates if the packet carries a checksum. UDP over IPv4 allows a
+    /// checksum of 0 to indicate the sender did not compute one.
+    pub checksum: bool,
 }
 
 impl Repr {
@@ -34,10 +43,12 @@ impl Repr {
             src_port: packet.src_port(),
             dst_port: packet.dst_port(),
             length: packet.length(),
+            checksum: packet.checksum() != 0,
         }
     }
 
-    /// Serializes the UDP header into a packet and performs a checksum update.
+    /// Serializes the UDP header into a packet and performs a checksum update,
+    /// unless checksum is false in which case the checksum is left as 0.
     pub fn serialize<T>(&self, packet: &mut Packet<T>, ipv4_repr: &Ipv4Repr)
     where
         T: AsRef<[u8]> + AsMut<[u8]>,
@@ -47,14 +58,23 @@ impl Repr {
         packet.set_length(self.length);
         packet.set_checksum(0);
 
-        let checksum = packet.gen_packet_checksum(ipv4_repr);
+        if !self.checksum {
+            return;
+        }
+
+        // A computed checksum of 0 is sent as all ones since 0 means no
+        // checksum, see RFC 768.
+        let checksum = match packet.gen_packet_checksum(ipv4_repr) {
+            0 => 0xFFFF,
+            checksum => checksum,
+        };
         packet.set_checksum(checksum);
     }
 }
 
 /// [https://en.wikipedia.org/wiki/User_Datagram_Protocol](https://en.wikipedia.org/wiki/User_Datagram_Protocol)
 mod fields {
-    use std::ops::Range;
+    use core::ops::Range;
 
     pub const SRC_PORT: Range<usize> = 0 .. 2;
 
@@ -95,8 +115,10 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn try_new(buffer: T) -> Result<Packet<T>> {
         let buffer_len = buffer.as_ref().len();
 
-        if buffer_len < Self::buffer_len(0) || buffer_len > Self::MAX_PACKET_LEN {
-            Err(Error::Exhausted)
+        if buffer_len < Self::buffer_len(0) {
+            Err(Error::Parse(ParseError::Truncated))
+        } else if buffer_len > Self::MAX_PACKET_LEN {
+            Err(Error::Parse(ParseError::Malformed))
         } else {
             Ok(Packet { buffer })
         }
@@ -112,9 +134,9 @@ impl<T: AsRef<[u8]>> Packet<T> {
     pub fn check_encoding(&self, ipv4_repr: &Ipv4Repr) -> Result<()> {
         // NOTE: Should enforce checksum if using IPv6, optional for IPv4.
         if self.checksum() != 0 && self.gen_packet_checksum(ipv4_repr) != 0 {
-            Err(Error::Checksum)
+            Err(Error::Parse(ParseError::Checksum))
         } else if self.length() as usize != self.buffer.as_ref().len() {
-            Err(Error::Malformed)
+            Err(Error::Parse(ParseError::Malformed))
         } else {
             Ok(())
         }
@@ -126,27 +148,19 @@ impl<T: AsRef<[u8]>> Packet<T> {
     }
 
     pub fn src_port(&self) -> u16 {
-        (&self.buffer.as_ref()[fields::SRC_PORT])
-            .read_u16::<NetworkEndian>()
-            .unwrap()
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::SRC_PORT])
     }
 
     pub fn dst_port(&self) -> u16 {
-        (&self.buffer.as_ref()[fields::DST_PORT])
-            .read_u16::<NetworkEndian>()
-            .unwrap()
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::DST_PORT])
     }
 
     pub fn length(&self) -> u16 {
-        (&self.buffer.as_ref()[fields::LENGTH])
-            .read_u16::<NetworkEndian>()
-            .unwrap()
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::LENGTH])
     }
 
     pub fn checksum(&self) -> u16 {
-        (&self.buffer.as_ref()[fields::CHECKSUM])
-            .read_u16::<NetworkEndian>()
-            .unwrap()
+        NetworkEndian::read_u16(&self.buffer.as_ref()[fields::CHECKSUM])
     }
 
     pub fn payload(&self) -> &[u8] {
@@ -156,27 +170,19 @@ impl<T: AsRef<[u8]>> Packet<T> {
 
 impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     pub fn set_src_port(&mut self, port: u16) {
-        (&mut self.buffer.as_mut()[fields::SRC_PORT])
-            .write_u16::<NetworkEndian>(port)
-            .unwrap()
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::SRC_PORT], port)
     }
 
     pub fn set_dst_port(&mut self, port: u16) {
-        (&mut self.buffer.as_mut()[fields::DST_PORT])
-            .write_u16::<NetworkEndian>(port)
-            .unwrap()
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::DST_PORT], port)
     }
 
     pub fn set_length(&mut self, length: u16) {
-        (&mut self.buffer.as_mut()[fields::LENGTH])
-            .write_u16::<NetworkEndian>(length)
-            .unwrap()
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::LENGTH], length)
     }
 
     pub fn set_checksum(&mut self, checksum: u16) {
-        (&mut self.buffer.as_mut()[fields::CHECKSUM])
-            .write_u16::<NetworkEndian>(checksum)
-            .unwrap()
+        NetworkEndian::write_u16(&mut self.buffer.as_mut()[fields::CHECKSUM], checksum)
     }
 
     pub fn payload_mut(&mut self) -> &mut [u8] {
@@ -184,10 +190,25 @@ impl<T: AsRef<[u8]> + AsMut<[u8]>> Packet<T> {
     }
 }
 
+impl<T: AsRef<[u8]>> Display for Packet<T> {
+    /// Formats a tcpdump-like summary of the packet header.
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "UDP {} > {}, length {}",
+            self.src_port(),
+            self.dst_port(),
+            self.length()
+        )
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use core::repr::{
+    use stack::repr::{
+        ipv4_flags,
         Ipv4Address,
+        Ipv4Options,
         Ipv4Protocol,
     };
 
@@ -199,6 +220,10 @@ mod tests {
             dst_addr: Ipv4Address::new([4, 5, 6, 7]),
             protocol: Ipv4Protocol::UDP,
             payload_len: payload_len as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
         }
     }
 
@@ -206,7 +231,7 @@ mod tests {
     fn test_packet_with_buffer_less_than_min_header() {
         let buffer: [u8; 4] = [0; 4];
         let packet = Packet::try_new(&buffer[..]);
-        assert_matches!(packet, Err(Error::Exhausted));
+        assert_matches!(packet, Err(Error::Parse(ParseError::Truncated)));
     }
 
     #[test]
@@ -216,7 +241,10 @@ mod tests {
             0x00, 0x00,
         ];
         let packet = Packet::try_new(&buffer[..]).unwrap();
-        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Err(Error::Checksum));
+        assert_matches!(
+            packet.check_encoding(&ipv4_repr(16)),
+            Err(Error::Parse(ParseError::Checksum))
+        );
     }
 
     #[test]
@@ -226,7 +254,10 @@ mod tests {
             0x00, 0x00,
         ];
         let packet = Packet::try_new(&buffer[..]).unwrap();
-        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Err(Error::Malformed));
+        assert_matches!(
+            packet.check_encoding(&ipv4_repr(16)),
+            Err(Error::Parse(ParseError::Malformed))
+        );
     }
 
     #[test]
@@ -265,4 +296,37 @@ mod tests {
             ][..]
         );
     }
+
+    #[test]
+    fn test_repr_serialize_without_checksum() {
+        let mut repr = Repr {
+            src_port: 1024,
+            dst_port: 2048,
+            length: 16,
+            checksum: false,
+        };
+
+        let mut buffer: [u8; 16] = [0; 16];
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            packet.payload_mut()[0] = 9;
+            repr.serialize(&mut packet, &ipv4_repr(16));
+        }
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_eq!(packet.checksum(), 0);
+        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Ok(_));
+        assert_eq!(Repr::deserialize(&packet), repr);
+
+        repr.checksum = true;
+        {
+            let mut packet = Packet::try_new(&mut buffer[..]).unwrap();
+            repr.serialize(&mut packet, &ipv4_repr(16));
+        }
+
+        let packet = Packet::try_new(&buffer[..]).unwrap();
+        assert_ne!(packet.checksum(), 0);
+        assert_matches!(packet.check_encoding(&ipv4_repr(16)), Ok(_));
+        assert_eq!(Repr::deserialize(&packet), repr);
+    }
 }
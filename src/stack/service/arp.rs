@@ -0,0 +1,720 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use stack::repr::{
+    eth_types,
+    Arp,
+    ArpOp,
+    EthernetAddress,
+    EthernetFrame,
+    Ipv4Address,
+};
+use stack::service::{
+    ethernet,
+    Interface,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    TimerQueue,
+};
+use {
+    AddressError,
+    Error,
+    Result,
+};
+
+/// Sends an ARP packet via an interface.
+pub fn send_packet(
+    interface: &mut Interface,
+    arp_repr: &Arp,
+    dst_addr: EthernetAddress,
+) -> Result<()> {
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(arp_repr.buffer_len());
+
+    ethernet::send_frame(interface, eth_frame_len, |eth_frame| {
+        eth_frame.set_dst_addr(dst_addr);
+        eth_frame.set_payload_type(eth_types::ARP);
+        arp_repr.serialize(eth_frame.payload_mut()).unwrap();
+    })
+}
+
+/// Receives an ARP packet from an interface.
+///
+/// This may result in a response to ARP requests, updating the ARP cache, etc.
+pub fn recv_packet(interface: &mut Interface, eth_frame: &EthernetFrame<&[u8]>) -> Result<()> {
+    let arp_repr = Arp::deserialize(eth_frame.payload())?;
+
+    let eth_addr = interface.ethernet_addr;
+    if let Some(conflict_addr) = interface
+        .arp_claim
+        .as_mut()
+        .and_then(|claim| claim.recv(&arp_repr, eth_addr))
+    {
+        warn!(
+            "Address conflict while probing for {} with {}.",
+            interface.ipv4_addr, conflict_addr
+        );
+        notify_conflict(interface, conflict_addr);
+        return Err(Error::Address(AddressError::AddressConflict(conflict_addr)));
+    }
+
+    if is_conflict(&arp_repr, *interface.ipv4_addr, interface.ethernet_addr) {
+        warn!(
+            "Address conflict, {} is also claimed by {}.",
+            arp_repr.source_proto_addr, arp_repr.source_hw_addr
+        );
+        notify_conflict(interface, arp_repr.source_hw_addr);
+        return Err(Error::Address(AddressError::AddressConflict(
+            arp_repr.source_hw_addr,
+        )));
+    }
+
+    // Like RFC 826's merge step, existing mappings are updated by any ARP from
+    // the sender, and gratuitous ARPs from the subnet are learned, so hosts
+    // which change their addresses are picked up without waiting for expiry.
+    if arp_repr.target_proto_addr != *interface.ipv4_addr {
+        let source_proto_addr = arp_repr.source_proto_addr;
+        if interface
+            .arp_cache
+            .eth_addr_for_ip(source_proto_addr)
+            .is_some()
+            || (is_gratuitous(&arp_repr) && interface.ipv4_addr.is_member(source_proto_addr))
+        {
+            debug!(
+                "Updating mapping from {} to {}.",
+                source_proto_addr, arp_repr.source_hw_addr
+            );
+            interface
+                .arp_cache
+                .set_eth_addr_for_ip(source_proto_addr, arp_repr.source_hw_addr);
+            flush_pending(interface, source_proto_addr);
+        }
+
+        debug!(
+            "Ignoring ARP with target IPv4 address {}.",
+            arp_repr.target_proto_addr
+        );
+        return Err(Error::Ignored);
+    }
+
+    debug!(
+        "Received ARP, adding mapping from {} to {}.",
+        arp_repr.source_proto_addr, arp_repr.source_hw_addr
+    );
+    interface
+        .arp_cache
+        .set_eth_addr_for_ip(arp_repr.source_proto_addr, arp_repr.source_hw_addr);
+
+    flush_pending(interface, arp_repr.source_proto_addr);
+
+    match arp_repr.op {
+        // The address is not ours to defend until probing succeeds.
+        ArpOp::Request if is_probing(interface) => Ok(()),
+        ArpOp::Request => {
+            let arp_reply = Arp {
+                op: ArpOp::Reply,
+                source_hw_addr: interface.ethernet_addr,
+                source_proto_addr: *interface.ipv4_addr,
+                target_hw_addr: arp_repr.source_hw_addr,
+                target_proto_addr: arp_repr.source_proto_addr,
+            };
+
+            debug!(
+                "Sending ARP reply to {}/{}.",
+                arp_reply.target_proto_addr, arp_reply.target_hw_addr
+            );
+
+            send_packet(interface, &arp_reply, arp_reply.target_hw_addr)
+        }
+        _ => Ok(()),
+    }
+}
+/// Tries to retrieve the Ethernet address for an IPv4 address.
+///
+/// The IP address may not have an Ethernet mapping yet, in which case an ARP
+/// request is dispatched (unless one is already outstanding) and an error
+/// returned. The ARP response (if the IP address exists on the network) will
+/// be processed by `recv_packet(...)` and update the ARP cache, while
+/// `poll(...)` retries the request until then.
+pub fn eth_addr_for_ip(
+    interface: &mut Interface,
+    ipv4_addr: Ipv4Address,
+) -> Result<EthernetAddress> {
+    match interface.arp_cache.eth_addr_for_ip(ipv4_addr) {
+        Some(eth_addr) => Ok(eth_addr),
+        None => {
+            if interface.arp_pending.start(ipv4_addr) {
+                send_request(interface, ipv4_addr)?;
+            }
+            Err(Error::Address(AddressError::MacResolution(ipv4_addr)))
+        }
+    }
+}
+
+/// Buffers an outgoing IPv4 packet until the Ethernet address of the next
+/// hop, ipv4_addr, is resolved.
+pub fn enqueue_packet(
+    interface: &mut Interface,
+    ipv4_addr: Ipv4Address,
+    ipv4_buffer: Vec<u8>,
+) -> Result<()> {
+    debug!("Buffering IPv4 packet for {} pending ARP.", ipv4_addr);
+    interface.arp_pending.enqueue(ipv4_addr, ipv4_buffer)
+}
+
+/// Retries outstanding ARP requests, sends buffered packets for addresses
+/// which have been resolved, and sends any probe or announcement which is due
+/// while claiming the interface address.
+pub fn poll(interface: &mut Interface) {
+    let eth_addr = interface.ethernet_addr;
+    if let Some(arp_repr) = interface
+        .arp_claim
+        .as_mut()
+        .and_then(|claim| claim.poll(eth_addr))
+    {
+        if let Err(err) = send_packet(interface, &arp_repr, EthernetAddress::BROADCAST) {
+            debug!(
+                "Error sending ARP for {} with {:?}.",
+                arp_repr.target_proto_addr, err
+            );
+        }
+    }
+
+    for ipv4_addr in interface.arp_pending.addrs() {
+        flush_pending(interface, ipv4_addr);
+    }
+
+    for ipv4_addr in interface.arp_pending.poll() {
+        if let Err(err) = send_request(interface, ipv4_addr) {
+            debug!(
+                "Error resending ARP request for {} with {:?}.",
+                ipv4_addr, err
+            );
+        }
+    }
+}
+
+fn send_request(interface: &mut Interface, ipv4_addr: Ipv4Address) -> Result<()> {
+    let arp_repr = Arp {
+        op: ArpOp::Request,
+        source_hw_addr: interface.ethernet_addr,
+        source_proto_addr: *interface.ipv4_addr,
+        target_hw_addr: EthernetAddress::BROADCAST,
+        target_proto_addr: ipv4_addr,
+    };
+
+    debug!("Sending ARP request for {}.", ipv4_addr);
+    send_packet(interface, &arp_repr, EthernetAddress::BROADCAST)
+}
+
+/// Sends any packets buffered for an IPv4 address if it has been resolved.
+fn flush_pending(interface: &mut Interface, ipv4_addr: Ipv4Address) {
+    let eth_addr = match interface.arp_cache.eth_addr_for_ip(ipv4_addr) {
+        Some(eth_addr) => eth_addr,
+        None => return,
+    };
+
+    let ipv4_buffers = match interface.arp_pending.resolve(ipv4_addr) {
+        Some(ipv4_buffers) => ipv4_buffers,
+        None => return,
+    };
+
+    debug!(
+        "Sending {} buffered IPv4 packets to {}/{}.",
+        ipv4_buffers.len(),
+        ipv4_addr,
+        eth_addr
+    );
+
+    for ipv4_buffer in ipv4_buffers {
+        let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(ipv4_buffer.len());
+        if let Err(err) = ethernet::send_frame(interface, eth_frame_len, |eth_frame| {
+            eth_frame.set_dst_addr(eth_addr);
+            eth_frame.set_payload_type(eth_types::IPV4);
+            eth_frame.payload_mut().copy_from_slice(&ipv4_buffer[..]);
+        }) {
+            warn!("Error sending buffered IPv4 packet with {:?}.", err);
+        }
+    }
+}
+
+/// Timing of address probes and announcements, see RFC 5227.
+#[derive(Clone, Copy, Debug)]
+pub struct AnnounceConfig {
+    /// Number of probes sent before claiming the address.
+    pub probe_num: u32,
+    /// Delay between probes.
+    pub probe_interval: Duration,
+    /// Delay after the last probe before the address is claimed.
+    pub announce_wait: Duration,
+    /// Number of gratuitous ARP announcements sent.
+    pub announce_num: u32,
+    /// Delay between announcements.
+    pub announce_interval: Duration,
+}
+
+impl Default for AnnounceConfig {
+    fn default() -> AnnounceConfig {
+        AnnounceConfig {
+            probe_num: 3,
+            probe_interval: Duration::from_secs(1),
+            announce_wait: Duration::from_secs(2),
+            announce_num: 2,
+            announce_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Starts claiming the IPv4 address of an interface via an AddressClaim
+/// stored in interface.arp_claim, and sends the first probe.
+///
+/// Later probes and announcements are sent by poll(...), i.e. via
+/// socket::send(...), and conflicts are detected as ARP packets are received
+/// via socket::recv(...), so other traffic keeps flowing while the address is
+/// claimed. Conflicts are also reported to the interface conflict handler.
+pub fn announce(
+    interface: &mut Interface,
+    config: &AnnounceConfig,
+    time_env: DefaultEnv,
+) -> Result<()> {
+    let mut claim = AddressClaim::new(*interface.ipv4_addr, *config, time_env);
+    let arp_repr = claim.poll(interface.ethernet_addr);
+    interface.arp_claim = Some(claim);
+
+    match arp_repr {
+        Some(arp_repr) => send_packet(interface, &arp_repr, EthernetAddress::BROADCAST),
+        None => Ok(()),
+    }
+}
+
+/// Sends a gratuitous ARP announcing the IPv4 and Ethernet addresses of an
+/// interface, so other hosts update their ARP caches.
+pub fn send_gratuitous(interface: &mut Interface) -> Result<()> {
+    let ipv4_addr = *interface.ipv4_addr;
+    let arp_repr = Arp {
+        op: ArpOp::Request,
+        source_hw_addr: interface.ethernet_addr,
+        source_proto_addr: ipv4_addr,
+        target_hw_addr: EthernetAddress::new([0; 6]),
+        target_proto_addr: ipv4_addr,
+    };
+
+    debug!("Sending gratuitous ARP for {}.", ipv4_addr);
+    send_packet(interface, &arp_repr, EthernetAddress::BROADCAST)
+}
+
+/// Checks if an ARP packet was sent by another host using our IPv4 address.
+fn is_conflict(arp_repr: &Arp, ipv4_addr: Ipv4Address, eth_addr: EthernetAddress) -> bool {
+    !ipv4_addr.is_unspecified()
+        && arp_repr.source_proto_addr == ipv4_addr
+        && arp_repr.source_hw_addr != eth_addr
+}
+
+fn is_gratuitous(arp_repr: &Arp) -> bool {
+    !arp_repr.source_proto_addr.is_unspecified()
+        && arp_repr.source_proto_addr == arp_repr.target_proto_addr
+}
+
+fn is_probing(interface: &Interface) -> bool {
+    interface
+        .arp_claim
+        .as_ref()
+        .is_some_and(|claim| claim.state() == ClaimState::Probing)
+}
+
+fn notify_conflict(interface: &mut Interface, eth_addr: EthernetAddress) {
+    let ipv4_addr = *interface.ipv4_addr;
+    if let Some(ref mut handler) = interface.arp_conflict_handler {
+        handler(ipv4_addr, eth_addr);
+    }
+}
+
+/// Progress of an AddressClaim.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimState {
+    /// Sending probes and listening for other hosts using or probing for the
+    /// address.
+    Probing,
+    /// Sending gratuitous ARPs announcing the address.
+    Announcing,
+    /// The address has been announced.
+    Claimed,
+    /// A host with the Ethernet address uses, or is probing for, the address.
+    Conflict(EthernetAddress),
+}
+
+/// Claims an IPv4 address by sending ARP probes for it and, if no other host
+/// replies or probes for the same address, announcing it with gratuitous
+/// ARPs, see RFC 5227.
+///
+/// The claim does not block, packets are sent as they fall due when polled,
+/// and event loops can sleep until poll_at().
+pub struct AddressClaim<T = DefaultEnv>
+where
+    T: Env,
+{
+    ipv4_addr: Ipv4Address,
+    config: AnnounceConfig,
+    state: ClaimState,
+    /// Number of probes or announcements sent in the current state.
+    sent: u32,
+    send_at: Instant,
+    time_env: T,
+}
+
+impl<T: Env> AddressClaim<T> {
+    /// Creates a claim for an IPv4 address whose first probe is due now.
+    pub fn new(ipv4_addr: Ipv4Address, config: AnnounceConfig, time_env: T) -> AddressClaim<T> {
+        let send_at = time_env.now_instant();
+        AddressClaim {
+            ipv4_addr,
+            config,
+            state: ClaimState::Probing,
+            sent: 0,
+            send_at,
+            time_env,
+        }
+    }
+
+    /// Returns the address being claimed.
+    pub fn ipv4_addr(&self) -> Ipv4Address {
+        self.ipv4_addr
+    }
+
+    /// Returns the progress of the claim.
+    pub fn state(&self) -> ClaimState {
+        self.state
+    }
+
+    /// Returns when the next probe or announcement is due, or when probing
+    /// ends, if the claim is in progress.
+    pub fn poll_at(&self) -> Option<Instant> {
+        match self.state {
+            ClaimState::Probing | ClaimState::Announcing => Some(self.send_at),
+            _ => None,
+        }
+    }
+
+    /// Returns a probe or announcement from an Ethernet address if one is
+    /// due to be sent, advancing the claim.
+    pub fn poll(&mut self, eth_addr: EthernetAddress) -> Option<Arp> {
+        let now = self.time_env.now_instant();
+        if now < self.send_at {
+            return None;
+        }
+
+        if self.state == ClaimState::Probing && self.sent >= self.config.probe_num {
+            debug!("No conflicts while probing for {}.", self.ipv4_addr);
+            self.state = ClaimState::Announcing;
+            self.sent = 0;
+        }
+
+        if self.state == ClaimState::Announcing && self.sent >= self.config.announce_num {
+            debug!("Claimed {}.", self.ipv4_addr);
+            self.state = ClaimState::Claimed;
+        }
+
+        let (source_proto_addr, wait) = match self.state {
+            ClaimState::Probing if self.sent + 1 == self.config.probe_num => {
+                (Ipv4Address::UNSPECIFIED, self.config.announce_wait)
+            }
+            ClaimState::Probing => (Ipv4Address::UNSPECIFIED, self.config.probe_interval),
+            ClaimState::Announcing => (self.ipv4_addr, self.config.announce_interval),
+            _ => return None,
+        };
+
+        self.sent += 1;
+        self.send_at = now + wait;
+
+        debug!(
+            "Sending ARP {} for {}.",
+            if self.state == ClaimState::Probing {
+                "probe"
+            } else {
+                "announcement"
+            },
+            self.ipv4_addr
+        );
+
+        Some(Arp {
+            op: ArpOp::Request,
+            source_hw_addr: eth_addr,
+            source_proto_addr,
+            target_hw_addr: EthernetAddress::new([0; 6]),
+            target_proto_addr: self.ipv4_addr,
+        })
+    }
+
+    /// Checks an ARP packet received while probing for one from another host
+    /// using or probing for the address, returning the Ethernet address of
+    /// the host on a conflict.
+    pub fn recv(&mut self, arp_repr: &Arp, eth_addr: EthernetAddress) -> Option<EthernetAddress> {
+        if self.state != ClaimState::Probing || arp_repr.source_hw_addr == eth_addr {
+            return None;
+        }
+
+        let is_probe = arp_repr.source_proto_addr.is_unspecified()
+            && arp_repr.target_proto_addr == self.ipv4_addr;
+
+        if is_probe || arp_repr.source_proto_addr == self.ipv4_addr {
+            self.state = ClaimState::Conflict(arp_repr.source_hw_addr);
+            Some(arp_repr.source_hw_addr)
+        } else {
+            None
+        }
+    }
+}
+
+/// Maximum number of packets buffered per unresolved IPv4 address. The oldest
+/// packet is dropped when a new packet would exceed this.
+pub static PENDING_MAX_PACKETS: usize = 4;
+
+/// Number of ARP requests sent for an IPv4 address before giving up.
+pub static PENDING_MAX_REQUESTS: u32 = 5;
+
+struct PendingResolution {
+    ipv4_buffers: Vec<Vec<u8>>,
+    requests: u32,
+}
+
+/// Tracks outstanding ARP requests and buffers outgoing packets until the
+/// Ethernet address of their next hop is resolved.
+///
+/// Requests are retried with exponential backoff, starting at a configurable
+/// timeout, and abandoned (along with any buffered packets) after
+/// PENDING_MAX_REQUESTS requests.
+pub struct PendingQueue<T = DefaultEnv>
+where
+    T: Env,
+{
+    pending: BTreeMap<Ipv4Address, PendingResolution>,
+    retries: TimerQueue<Ipv4Address>,
+    retry_timeout: Duration,
+    time_env: T,
+}
+
+impl<T: Env> PendingQueue<T> {
+    /// Creates a queue which first retries ARP requests after
+    /// retry_timeout_in_millis milliseconds.
+    pub fn new(retry_timeout_in_millis: u64, time_env: T) -> PendingQueue<T> {
+        PendingQueue {
+            pending: BTreeMap::new(),
+            retries: TimerQueue::new(),
+            retry_timeout: Duration::from_millis(retry_timeout_in_millis),
+            time_env,
+        }
+    }
+
+    /// Records an ARP request being sent for an IPv4 address, returning
+    /// false if a request is already outstanding.
+    pub fn start(&mut self, ipv4_addr: Ipv4Address) -> bool {
+        if self.pending.contains_key(&ipv4_addr) {
+            return false;
+        }
+
+        self.pending.insert(
+            ipv4_addr,
+            PendingResolution {
+                ipv4_buffers: Vec::new(),
+                requests: 1,
+            },
+        );
+        self.retries
+            .schedule(&self.time_env, ipv4_addr, self.retry_timeout);
+        true
+    }
+
+    /// Buffers a packet for an IPv4 address with an outstanding ARP request.
+    pub fn enqueue(&mut self, ipv4_addr: Ipv4Address, ipv4_buffer: Vec<u8>) -> Result<()> {
+        let pending = self.pending.get_mut(&ipv4_addr).ok_or(Error::Ignored)?;
+
+        if pending.ipv4_buffers.len() >= PENDING_MAX_PACKETS {
+            debug!("Dropping oldest IPv4 packet buffered for {}.", ipv4_addr);
+            pending.ipv4_buffers.remove(0);
+        }
+
+        pending.ipv4_buffers.push(ipv4_buffer);
+        Ok(())
+    }
+
+    /// Removes the outstanding request for an IPv4 address, returning any
+    /// buffered packets.
+    pub fn resolve(&mut self, ipv4_addr: Ipv4Address) -> Option<Vec<Vec<u8>>> {
+        self.retries.cancel(&ipv4_addr);
+        self.pending
+            .remove(&ipv4_addr)
+            .map(|pending| pending.ipv4_buffers)
+    }
+
+    /// Returns the IPv4 addresses with outstanding requests.
+    pub fn addrs(&self) -> Vec<Ipv4Address> {
+        self.pending.keys().cloned().collect()
+    }
+
+    /// Returns when the next outstanding request should be retried, if any.
+    pub fn poll_at(&self) -> Option<Instant> {
+        self.retries.poll_at()
+    }
+
+    /// Returns the IPv4 addresses whose requests should be retried now, and
+    /// abandons requests which have been retried too many times.
+    pub fn poll(&mut self) -> Vec<Ipv4Address> {
+        let mut retries = Vec::new();
+
+        for ipv4_addr in self.retries.expire(&self.time_env) {
+            let requests = {
+                let pending = self.pending.get_mut(&ipv4_addr).unwrap();
+                if pending.requests >= PENDING_MAX_REQUESTS {
+                    debug!(
+                        "Giving up resolving {}, dropping {} buffered IPv4 packets.",
+                        ipv4_addr,
+                        pending.ipv4_buffers.len()
+                    );
+                    None
+                } else {
+                    pending.requests += 1;
+                    Some(pending.requests)
+                }
+            };
+
+            match requests {
+                Some(requests) => {
+                    let timeout = self.retry_timeout * (1 << (requests - 1));
+                    self.retries.schedule(&self.time_env, ipv4_addr, timeout);
+                    retries.push(ipv4_addr);
+                }
+                None => {
+                    self.pending.remove(&ipv4_addr);
+                }
+            }
+        }
+
+        retries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    fn ipv4(i: u8) -> Ipv4Address {
+        Ipv4Address::new([0, 0, 0, i])
+    }
+
+    #[test]
+    fn test_is_conflict() {
+        let eth = |i| EthernetAddress::new([0, 0, 0, 0, 0, i]);
+        let arp_repr = Arp {
+            op: ArpOp::Reply,
+            source_hw_addr: eth(1),
+            source_proto_addr: ipv4(1),
+            target_hw_addr: eth(2),
+            target_proto_addr: ipv4(2),
+        };
+
+        assert!(is_conflict(&arp_repr, ipv4(1), eth(2)));
+        assert!(!is_conflict(&arp_repr, ipv4(1), eth(1)));
+        assert!(!is_conflict(&arp_repr, ipv4(2), eth(2)));
+    }
+
+    #[test]
+    fn test_claim_probe_and_announce() {
+        let eth_addr = EthernetAddress::new([0, 0, 0, 0, 0, 1]);
+        let mut claim = AddressClaim::new(ipv4(1), AnnounceConfig::default(), MockEnv::new());
+        let start = claim.time_env.now;
+
+        for i in 0 .. 3 {
+            let probe = claim.poll(eth_addr).unwrap();
+            assert_eq!(probe.source_proto_addr, Ipv4Address::UNSPECIFIED);
+            assert_eq!(probe.target_proto_addr, ipv4(1));
+            assert_eq!(claim.state(), ClaimState::Probing);
+            assert_matches!(claim.poll(eth_addr), None);
+            assert_eq!(
+                claim.poll_at(),
+                Some(start + Duration::from_secs(if i < 2 { i + 1 } else { 4 }))
+            );
+            claim.time_env.now = claim.poll_at().unwrap();
+        }
+
+        for _ in 0 .. 2 {
+            let announcement = claim.poll(eth_addr).unwrap();
+            assert_eq!(announcement.source_proto_addr, ipv4(1));
+            assert_eq!(announcement.target_proto_addr, ipv4(1));
+            assert_eq!(claim.state(), ClaimState::Announcing);
+            claim.time_env.now += Duration::from_secs(2);
+        }
+
+        assert_matches!(claim.poll(eth_addr), None);
+        assert_eq!(claim.state(), ClaimState::Claimed);
+        assert_eq!(claim.poll_at(), None);
+    }
+
+    #[test]
+    fn test_claim_conflict() {
+        let eth = |i| EthernetAddress::new([0, 0, 0, 0, 0, i]);
+        let mut claim = AddressClaim::new(ipv4(1), AnnounceConfig::default(), MockEnv::new());
+        let mut probe = claim.poll(eth(1)).unwrap();
+
+        // Our own probe, and ARPs for other addresses, are not conflicts.
+        assert_eq!(claim.recv(&probe, eth(1)), None);
+        let other = Arp {
+            source_proto_addr: ipv4(2),
+            target_proto_addr: ipv4(3),
+            ..probe
+        };
+        assert_eq!(claim.recv(&other, eth(1)), None);
+        assert_eq!(claim.state(), ClaimState::Probing);
+
+        probe.source_hw_addr = eth(2);
+        assert_eq!(claim.recv(&probe, eth(1)), Some(eth(2)));
+        assert_eq!(claim.state(), ClaimState::Conflict(eth(2)));
+        assert_eq!(claim.poll_at(), None);
+
+        claim.time_env.now += Duration::from_secs(60);
+        assert_matches!(claim.poll(eth(1)), None);
+    }
+
+    #[test]
+    fn test_pending_enqueue_and_resolve() {
+        let mut pending_queue = PendingQueue::new(1000, MockEnv::new());
+        assert_matches!(pending_queue.enqueue(ipv4(1), vec![0]), Err(Error::Ignored));
+
+        assert!(pending_queue.start(ipv4(1)));
+        assert!(!pending_queue.start(ipv4(1)));
+
+        for i in 0 .. (PENDING_MAX_PACKETS + 1) {
+            pending_queue.enqueue(ipv4(1), vec![i as u8]).unwrap();
+        }
+
+        let ipv4_buffers = pending_queue.resolve(ipv4(1)).unwrap();
+        assert_eq!(ipv4_buffers.len(), PENDING_MAX_PACKETS);
+        assert_eq!(ipv4_buffers[0], vec![1]);
+        assert_matches!(pending_queue.resolve(ipv4(1)), None);
+    }
+
+    #[test]
+    fn test_pending_retry_backoff() {
+        let mut pending_queue = PendingQueue::new(1000, MockEnv::new());
+        pending_queue.start(ipv4(1));
+
+        // Retries happen after 1, 2, 4 and 8 seconds.
+        for i in 0 .. (PENDING_MAX_REQUESTS - 1) {
+            let timeout = Duration::from_secs(1 << i);
+            let retry_at = pending_queue.time_env.now + timeout;
+            assert_eq!(pending_queue.poll_at(), Some(retry_at));
+            pending_queue.time_env.now += timeout - Duration::from_millis(1);
+            assert_eq!(pending_queue.poll(), vec![]);
+            pending_queue.time_env.now += Duration::from_millis(1);
+            assert_eq!(pending_queue.poll(), vec![ipv4(1)]);
+        }
+
+        pending_queue.time_env.now += Duration::from_secs(1 << (PENDING_MAX_REQUESTS - 1));
+        assert_eq!(pending_queue.poll(), vec![]);
+        assert_eq!(pending_queue.addrs(), vec![]);
+        assert_eq!(pending_queue.poll_at(), None);
+    }
+}
@@ -0,0 +1,586 @@
+use alloc::collections::{
+    BTreeMap,
+    BTreeSet,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::capture::Direction;
+use stack::repr::{
+    ipv4_protocols,
+    Ipv4Address,
+    Ipv4Packet,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    TimerQueue,
+};
+
+/// The protocol, addresses and ports identifying the packets of a connection
+/// in one direction.
+///
+/// Protocols without ports have zero ports, except ICMP echo messages which
+/// use their identifier as both ports so requests and replies pair up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tuple {
+    pub protocol: u8,
+    pub src_addr: Ipv4Address,
+    pub src_port: u16,
+    pub dst_addr: Ipv4Address,
+    pub dst_port: u16,
+}
+
+impl Tuple {
+    /// Returns the tuple of a packet, or None if the packet is a fragment
+    /// after the first or too short to carry the ports.
+    pub fn from_packet(ipv4_packet: &Ipv4Packet<&[u8]>) -> Option<Tuple> {
+        if ipv4_packet.fragment_offset() != 0 {
+            return None;
+        }
+
+        let transport = ipv4_packet.payload();
+        let (src_port, dst_port) = match ipv4_packet.protocol() {
+            ipv4_protocols::TCP | ipv4_protocols::UDP if transport.len() >= 4 => (
+                NetworkEndian::read_u16(&transport[0 .. 2]),
+                NetworkEndian::read_u16(&transport[2 .. 4]),
+            ),
+            ipv4_protocols::TCP | ipv4_protocols::UDP => return None,
+            // Echo requests and replies carry an identifier after the type,
+            // code and checksum.
+            ipv4_protocols::ICMP
+                if transport.len() >= 8 && (transport[0] == 0 || transport[0] == 8) =>
+            {
+                let ident = NetworkEndian::read_u16(&transport[4 .. 6]);
+                (ident, ident)
+            }
+            _ => (0, 0),
+        };
+
+        Some(Tuple {
+            protocol: ipv4_packet.protocol(),
+            src_addr: ipv4_packet.src_addr(),
+            src_port,
+            dst_addr: ipv4_packet.dst_addr(),
+            dst_port,
+        })
+    }
+
+    /// Returns the tuple of packets flowing in the other direction.
+    pub fn reverse(&self) -> Tuple {
+        Tuple {
+            protocol: self.protocol,
+            src_addr: self.dst_addr,
+            src_port: self.dst_port,
+            dst_addr: self.src_addr,
+            dst_port: self.src_port,
+        }
+    }
+}
+
+/// The state of a tracked connection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Packets have only been seen from the initiator.
+    New,
+    /// Packets have been seen in both directions.
+    Established,
+    /// A TCP FIN has been seen from one side.
+    Closing,
+    /// A TCP RST has been seen, or FINs from both sides.
+    Closed,
+}
+
+/// Counters for the packets of a connection in one direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+/// A tracked connection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    /// Tuple of the first packet seen, i.e. the initiator's packets.
+    pub tuple: Tuple,
+    /// Whether the connection was initiated by the interface (Sent) or a
+    /// remote host (Received).
+    pub origin: Direction,
+    pub state: State,
+    /// Counters for packets matching tuple.
+    pub original: Counters,
+    /// Counters for packets matching the reversed tuple.
+    pub reply: Counters,
+    pub first_seen: Instant,
+    pub last_seen: Instant,
+    fin: [bool; 2],
+}
+
+/// How long connections in each state are tracked without seeing packets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeouts {
+    pub new: Duration,
+    pub established: Duration,
+    /// Used instead of established for TCP connections.
+    pub tcp_established: Duration,
+    /// Used for Closing and Closed connections.
+    pub closing: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Timeouts {
+        Timeouts {
+            new: Duration::from_secs(30),
+            established: Duration::from_secs(180),
+            tcp_established: Duration::from_secs(7200),
+            closing: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Timeouts {
+    fn timeout(&self, entry: &Entry) -> Duration {
+        match entry.state {
+            State::New => self.new,
+            State::Established if entry.tuple.protocol == ipv4_protocols::TCP => {
+                self.tcp_established
+            }
+            State::Established => self.established,
+            State::Closing | State::Closed => self.closing,
+        }
+    }
+}
+
+/// A table of connections seen in IPv4 packets sent and received via an
+/// interface, keyed by the tuple of their initiator.
+///
+/// The table may optionally be bounded, in which case the least recently seen
+/// connection is evicted to make room for new ones.
+///
+/// Connections expire lazily as packets are tracked, so tracking a packet
+/// costs O(log n) plus the number of connections which expired since.
+#[derive(Debug)]
+pub struct Conntrack<T = DefaultEnv>
+where
+    T: Env,
+{
+    entries: BTreeMap<Tuple, Entry>,
+    // When each connection expires unless another packet is seen.
+    expiry_timers: TimerQueue<Tuple>,
+    // Connections ordered by when they were last seen, for eviction.
+    lru: BTreeSet<(Instant, Tuple)>,
+    timeouts: Timeouts,
+    max_entries: Option<usize>,
+    time_env: T,
+}
+
+impl<T: Env> Conntrack<T> {
+    /// Creates an empty, unbounded table with the default timeouts.
+    pub fn new(time_env: T) -> Conntrack<T> {
+        Conntrack {
+            entries: BTreeMap::new(),
+            expiry_timers: TimerQueue::new(),
+            lru: BTreeSet::new(),
+            timeouts: Timeouts::default(),
+            max_entries: None,
+            time_env,
+        }
+    }
+
+    /// Returns how long connections in each state are tracked without seeing
+    /// packets.
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    /// Sets how long connections in each state are tracked without seeing
+    /// packets, which also applies to connections already in the table.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+
+        for (tuple, entry) in self.entries.iter() {
+            self.expiry_timers
+                .schedule_at(*tuple, entry.last_seen + timeouts.timeout(entry));
+        }
+    }
+
+    /// Returns the maximum number of entries in the table, if bounded.
+    pub fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    /// Bounds the number of entries in the table, or removes the bound if
+    /// max_entries is None. Excess entries are evicted in least recently seen
+    /// order.
+    pub fn set_max_entries(&mut self, max_entries: Option<usize>) {
+        self.max_entries = max_entries;
+
+        if let Some(max_entries) = max_entries {
+            while self.entries.len() > max_entries && self.evict_lru() {}
+        }
+    }
+
+    /// Returns the connection a tuple belongs to in either direction, if it
+    /// has not expired.
+    pub fn get(&self, tuple: &Tuple) -> Option<&Entry> {
+        let now = self.time_env.now_instant();
+
+        self.entries
+            .get(tuple)
+            .or_else(|| self.entries.get(&tuple.reverse()))
+            .filter(|entry| !self.is_expired(entry, now))
+    }
+
+    /// Returns the state a packet would put its connection in if tracked, or
+    /// None if the packet can't be tracked.
+    ///
+    /// This is New for packets of unknown connections and Established for
+    /// the first reply to a New connection.
+    pub fn state(&self, ipv4_packet: &Ipv4Packet<&[u8]>) -> Option<State> {
+        let tuple = Tuple::from_packet(ipv4_packet)?;
+
+        match self.get(&tuple) {
+            Some(entry) if entry.state == State::New && entry.tuple != tuple => {
+                Some(State::Established)
+            }
+            Some(entry) => Some(entry.state),
+            None => Some(State::New),
+        }
+    }
+
+    /// Records a packet sent or received via the interface, creating an entry
+    /// for its connection if needed, and returns the entry.
+    pub fn track(
+        &mut self,
+        direction: Direction,
+        ipv4_packet: &Ipv4Packet<&[u8]>,
+    ) -> Option<&Entry> {
+        let tuple = Tuple::from_packet(ipv4_packet)?;
+        let now = self.time_env.now_instant();
+        self.expire();
+
+        let key = if self.entries.contains_key(&tuple) {
+            tuple
+        } else if self.entries.contains_key(&tuple.reverse()) {
+            tuple.reverse()
+        } else {
+            let full = self
+                .max_entries
+                .is_some_and(|max_entries| self.entries.len() >= max_entries);
+            if full && !self.evict_lru() {
+                return None;
+            }
+
+            let entry = Entry {
+                tuple,
+                origin: direction,
+                state: State::New,
+                original: Counters::default(),
+                reply: Counters::default(),
+                first_seen: now,
+                last_seen: now,
+                fin: [false; 2],
+            };
+            self.entries.insert(tuple, entry);
+            self.lru.insert((now, tuple));
+            tuple
+        };
+
+        let entry = self.entries.get_mut(&key).unwrap();
+        let side = if entry.tuple == tuple { 0 } else { 1 };
+
+        let counters = if side == 0 {
+            &mut entry.original
+        } else {
+            &mut entry.reply
+        };
+        counters.packets += 1;
+        counters.bytes += ipv4_packet.as_ref().len() as u64;
+        self.lru.remove(&(entry.last_seen, key));
+        self.lru.insert((now, key));
+        entry.last_seen = now;
+
+        let transport = ipv4_packet.payload();
+        if tuple.protocol == ipv4_protocols::TCP && transport.len() >= 14 {
+            let flags = transport[13];
+            if flags & 0x04 != 0 {
+                entry.state = State::Closed;
+            } else if flags & 0x01 != 0 {
+                entry.fin[side] = true;
+                entry.state = if entry.fin[0] && entry.fin[1] {
+                    State::Closed
+                } else {
+                    State::Closing
+                };
+            }
+        }
+
+        if entry.state == State::New && side == 1 {
+            entry.state = State::Established;
+        }
+
+        self.expiry_timers
+            .schedule_at(key, now + self.timeouts.timeout(entry));
+
+        Some(entry)
+    }
+
+    /// Removes the connection a tuple belongs to in either direction,
+    /// returning its entry if one existed.
+    pub fn remove(&mut self, tuple: &Tuple) -> Option<Entry> {
+        if self.entries.contains_key(tuple) {
+            self.remove_entry(tuple)
+        } else {
+            self.remove_entry(&tuple.reverse())
+        }
+    }
+
+    /// Removes all connections.
+    pub fn flush(&mut self) {
+        self.entries.clear();
+        self.expiry_timers.clear();
+        self.lru.clear();
+    }
+
+    /// Returns the number of connections, including expired ones not yet
+    /// removed.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns a snapshot of the connections in the table, ordered by the
+    /// tuple of their initiator.
+    pub fn dump(&mut self) -> Vec<Entry> {
+        self.expire();
+        self.entries.values().cloned().collect()
+    }
+
+    fn is_expired(&self, entry: &Entry, now: Instant) -> bool {
+        now.saturating_duration_since(entry.last_seen) >= self.timeouts.timeout(entry)
+    }
+
+    fn expire(&mut self) {
+        for tuple in self.expiry_timers.expire(&self.time_env) {
+            self.remove_entry(&tuple);
+        }
+    }
+
+    fn evict_lru(&mut self) -> bool {
+        match self.lru.iter().next() {
+            Some(&(_, tuple)) => {
+                self.remove_entry(&tuple);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn remove_entry(&mut self, tuple: &Tuple) -> Option<Entry> {
+        let entry = self.entries.remove(tuple)?;
+        self.expiry_timers.cancel(tuple);
+        self.lru.remove(&(entry.last_seen, *tuple));
+        Some(entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::repr::{
+        Ipv4Options,
+        Ipv4Protocol,
+        Ipv4Repr,
+        PacketBuilder,
+        TcpRepr,
+    };
+    use stack::time::MockEnv;
+
+    const A: [u8; 4] = [10, 0, 0, 1];
+
+    const B: [u8; 4] = [10, 0, 0, 2];
+
+    fn ipv4_repr(src_addr: [u8; 4], dst_addr: [u8; 4]) -> Ipv4Repr {
+        Ipv4Repr {
+            src_addr: Ipv4Address::new(src_addr),
+            dst_addr: Ipv4Address::new(dst_addr),
+            protocol: Ipv4Protocol::UDP,
+            payload_len: 0,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        }
+    }
+
+    fn udp(src: ([u8; 4], u16), dst: ([u8; 4], u16)) -> Vec<u8> {
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr(src.0, dst.0))
+            .udp(src.1, dst.1)
+            .payload(&[0; 8]);
+        let mut buffer = vec![0; builder.buffer_len()];
+        builder.build(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn tcp(src: ([u8; 4], u16), dst: ([u8; 4], u16), flags_set: &[usize]) -> Vec<u8> {
+        let mut flags = [false; 9];
+        for &flag in flags_set {
+            flags[flag] = true;
+        }
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr(src.0, dst.0))
+            .tcp(TcpRepr {
+                src_port: src.1,
+                dst_port: dst.1,
+                seq_num: 0,
+                ack_num: 0,
+                flags,
+                window_size: 0,
+                urgent_pointer: 0,
+                max_segment_size: None,
+            });
+        let mut buffer = vec![0; builder.buffer_len()];
+        builder.build(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn track(
+        conntrack: &mut Conntrack<MockEnv>,
+        direction: Direction,
+        buffer: &[u8],
+    ) -> Option<Entry> {
+        let ipv4_packet = Ipv4Packet::try_new(buffer).unwrap();
+        conntrack.track(direction, &ipv4_packet).cloned()
+    }
+
+    fn state(conntrack: &Conntrack<MockEnv>, buffer: &[u8]) -> Option<State> {
+        conntrack.state(&Ipv4Packet::try_new(buffer).unwrap())
+    }
+
+    #[test]
+    fn test_track_udp() {
+        let mut conntrack = Conntrack::new(MockEnv::new());
+        let request = udp((A, 1024), (B, 53));
+        let reply = udp((B, 53), (A, 1024));
+
+        assert_eq!(state(&conntrack, &request), Some(State::New));
+        let entry = track(&mut conntrack, Direction::Sent, &request).unwrap();
+        assert_eq!(entry.state, State::New);
+        assert_eq!(entry.origin, Direction::Sent);
+        assert_eq!(entry.tuple.src_port, 1024);
+
+        assert_eq!(state(&conntrack, &request), Some(State::New));
+        assert_eq!(state(&conntrack, &reply), Some(State::Established));
+        let entry = track(&mut conntrack, Direction::Received, &reply).unwrap();
+        assert_eq!(entry.state, State::Established);
+        assert_eq!(entry.original.packets, 1);
+        assert_eq!(entry.reply.packets, 1);
+        assert_eq!(entry.reply.bytes, reply.len() as u64);
+
+        track(&mut conntrack, Direction::Sent, &request);
+        let dump = conntrack.dump();
+        assert_eq!(dump.len(), 1);
+        assert_eq!(dump[0].original.packets, 2);
+        assert_eq!(conntrack.get(&dump[0].tuple.reverse()), Some(&dump[0]));
+    }
+
+    #[test]
+    fn test_track_tcp_close() {
+        let mut conntrack = Conntrack::new(MockEnv::new());
+        let (a, b) = ((A, 1024), (B, 80));
+
+        track(
+            &mut conntrack,
+            Direction::Received,
+            &tcp(a, b, &[TcpRepr::FLAG_SYN]),
+        );
+        track(
+            &mut conntrack,
+            Direction::Sent,
+            &tcp(b, a, &[TcpRepr::FLAG_SYN, TcpRepr::FLAG_ACK]),
+        );
+        let entry = track(
+            &mut conntrack,
+            Direction::Received,
+            &tcp(a, b, &[TcpRepr::FLAG_FIN]),
+        )
+        .unwrap();
+        assert_eq!(entry.state, State::Closing);
+        assert_eq!(entry.origin, Direction::Received);
+        let entry = track(
+            &mut conntrack,
+            Direction::Sent,
+            &tcp(b, a, &[TcpRepr::FLAG_FIN]),
+        )
+        .unwrap();
+        assert_eq!(entry.state, State::Closed);
+
+        let entry = track(
+            &mut conntrack,
+            Direction::Sent,
+            &tcp((A, 1025), b, &[TcpRepr::FLAG_RST]),
+        )
+        .unwrap();
+        assert_eq!(entry.state, State::Closed);
+    }
+
+    #[test]
+    fn test_track_expiration_and_eviction() {
+        let mut conntrack = Conntrack::new(MockEnv::new());
+        track(&mut conntrack, Direction::Sent, &udp((A, 1024), (B, 53)));
+
+        conntrack.time_env.now += Duration::from_secs(29);
+        track(&mut conntrack, Direction::Sent, &udp((A, 1025), (B, 53)));
+        assert_eq!(conntrack.dump().len(), 2);
+
+        conntrack.time_env.now += Duration::from_secs(1);
+        let dump = conntrack.dump();
+        assert_eq!(dump.len(), 1);
+        assert_eq!(dump[0].tuple.src_port, 1025);
+
+        conntrack.set_max_entries(Some(1));
+        conntrack.time_env.now += Duration::from_secs(1);
+        track(&mut conntrack, Direction::Sent, &udp((A, 1026), (B, 53)));
+        let dump = conntrack.dump();
+        assert_eq!(dump.len(), 1);
+        assert_eq!(dump[0].tuple.src_port, 1026);
+    }
+
+    #[test]
+    fn test_set_timeouts() {
+        let mut conntrack = Conntrack::new(MockEnv::new());
+        let request = udp((A, 1024), (B, 53));
+        track(&mut conntrack, Direction::Sent, &request);
+        track(&mut conntrack, Direction::Sent, &udp((A, 1025), (B, 53)));
+
+        // Shorter timeouts apply to connections already in the table...
+        conntrack.set_timeouts(Timeouts {
+            new: Duration::from_secs(5),
+            ..Timeouts::default()
+        });
+        conntrack.time_env.now += Duration::from_secs(4);
+        track(&mut conntrack, Direction::Sent, &request);
+        assert_eq!(conntrack.len(), 2);
+
+        // ...and seeing a packet pushes back only its connection's expiry.
+        conntrack.time_env.now += Duration::from_secs(1);
+        let dump = conntrack.dump();
+        assert_eq!(dump.len(), 1);
+        assert_eq!(dump[0].tuple.src_port, 1024);
+
+        assert!(conntrack.remove(&dump[0].tuple.reverse()).is_some());
+        assert!(conntrack.is_empty());
+        conntrack.time_env.now += Duration::from_secs(5);
+        assert!(conntrack.dump().is_empty());
+    }
+}
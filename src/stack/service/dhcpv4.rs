@@ -0,0 +1,730 @@
+use alloc::vec::Vec;
+use core::cmp;
+use core::time::Duration;
+
+use stack::random::random;
+use stack::repr::{
+    dhcpv4_option_codes,
+    dhcpv4_ports,
+    Dhcpv4MessageType,
+    Dhcpv4Packet,
+    Dhcpv4Repr,
+    EthernetAddress,
+    Ipv4Address,
+    Ipv4AddressCidr,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketEnv,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    PollAt,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Initial timeout before retransmitting a DISCOVER or REQUEST, which doubles
+/// after each transmission up to MAX_RETRANSMIT_TIMEOUT_SECS, see RFC 2131.
+pub static RETRANSMIT_TIMEOUT_SECS: u64 = 4;
+
+/// Maximum timeout before retransmitting a DISCOVER or REQUEST.
+pub static MAX_RETRANSMIT_TIMEOUT_SECS: u64 = 64;
+
+/// Number of REQUESTs sent for an offer before restarting discovery.
+pub static MAX_REQUESTS: u32 = 4;
+
+/// Minimum timeout before retransmitting a REQUEST while renewing or
+/// rebinding a lease.
+pub static MIN_RENEW_TIMEOUT_SECS: u64 = 60;
+
+/// Options requested from DHCP servers.
+static PARAMETER_REQUEST_LIST: [u8; 6] = [
+    dhcpv4_option_codes::SUBNET_MASK,
+    dhcpv4_option_codes::ROUTER,
+    dhcpv4_option_codes::DNS_SERVERS,
+    dhcpv4_option_codes::LEASE_TIME,
+    dhcpv4_option_codes::RENEWAL_TIME,
+    dhcpv4_option_codes::REBINDING_TIME,
+];
+
+/// States of a DHCP client, see RFC 2131 section 4.4.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Waiting to start discovery, after which the interface is unconfigured.
+    Init,
+    /// Broadcasting DISCOVERs and waiting for an OFFER.
+    Selecting,
+    /// Broadcasting REQUESTs for an offered address and waiting for an ACK.
+    Requesting,
+    /// Holding a lease which has been applied to the interface.
+    Bound,
+    /// Asking the server which granted the lease to extend it.
+    Renewing,
+    /// Asking any server to extend the lease.
+    Rebinding,
+}
+
+/// An IPv4 address lease and the configuration that came with it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lease {
+    pub ipv4_addr: Ipv4AddressCidr,
+    pub router: Option<Ipv4Address>,
+    pub dns_servers: Vec<Ipv4Address>,
+    /// Identifier of the server which granted the lease.
+    pub server_id: Ipv4Address,
+    pub acquired_at: Instant,
+    pub duration: Duration,
+    /// Time after acquired_at when renewal starts (T1).
+    pub renew_after: Duration,
+    /// Time after acquired_at when rebinding starts (T2).
+    pub rebind_after: Duration,
+}
+
+impl Lease {
+    /// Creates a lease from an ACK, falling back to server_id if the ACK does
+    /// not identify the server.
+    fn from_ack(
+        dhcp_repr: &Dhcpv4Repr,
+        server_id: Option<Ipv4Address>,
+        acquired_at: Instant,
+    ) -> Option<Lease> {
+        let addr = dhcp_repr.your_ip;
+        if addr.is_unspecified() || !addr.is_unicast() {
+            return None;
+        }
+
+        let server_id = dhcp_repr.server_identifier.or(server_id)?;
+
+        // Fall back to the classful mask if the server does not provide one.
+        let subnet_len = match dhcp_repr.subnet_mask {
+            Some(mask) => (!mask.as_int()).leading_zeros() as usize,
+            None if addr.as_bytes()[0] < 128 => 8,
+            None if addr.as_bytes()[0] < 192 => 16,
+            None => 24,
+        };
+
+        // A missing lease time is treated as an infinite lease.
+        let duration = dhcp_repr.lease_duration.unwrap_or(u32::MAX) as u64;
+        let renew_after = dhcp_repr
+            .renewal_duration
+            .map_or(duration / 2, |secs| secs as u64);
+        let rebind_after = dhcp_repr
+            .rebinding_duration
+            .map_or(duration * 7 / 8, |secs| secs as u64);
+
+        Some(Lease {
+            ipv4_addr: Ipv4AddressCidr::new(addr, subnet_len),
+            router: dhcp_repr.router,
+            dns_servers: dhcp_repr.dns_servers.clone(),
+            server_id,
+            acquired_at,
+            duration: Duration::from_secs(duration),
+            renew_after: Duration::from_secs(renew_after),
+            rebind_after: Duration::from_secs(rebind_after),
+        })
+    }
+
+    /// Returns the time at which renewal starts.
+    pub fn renew_at(&self) -> Instant {
+        self.acquired_at + self.renew_after
+    }
+
+    /// Returns the time at which rebinding starts.
+    pub fn rebind_at(&self) -> Instant {
+        self.acquired_at + self.rebind_after
+    }
+
+    /// Returns the time at which the lease expires.
+    pub fn expires_at(&self) -> Instant {
+        self.acquired_at + self.duration
+    }
+}
+
+#[derive(Debug)]
+struct Offer {
+    server_id: Ipv4Address,
+    ipv4_addr: Ipv4Address,
+}
+
+/// Changes to apply to the interface configuration.
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    Configure,
+    Deconfigure,
+}
+
+/// A DHCP client which acquires an IPv4 address lease over a UDP socket,
+/// applies it to an interface, and keeps it renewed.
+///
+/// The interface address is cleared when the client starts discovery, so
+/// messages are sent from 0.0.0.0 until a lease is bound. Replies are expected
+/// to be broadcast until then since the interface can not receive unicast
+/// packets without an address.
+pub struct Dhcpv4Client<T = DefaultEnv>
+where
+    T: Env,
+{
+    socket_handle: SocketHandle,
+    state: State,
+    transaction_id: u32,
+    offer: Option<Offer>,
+    lease: Option<Lease>,
+    transmit_at: Instant,
+    transmissions: u32,
+    time_env: T,
+}
+
+impl<T: Env> Dhcpv4Client<T> {
+    /// Creates a client and adds a UDP socket bound to the DHCP client port
+    /// to the socket set.
+    pub fn new<E>(
+        socket_env: &SocketEnv<E>,
+        socket_set: &mut SocketSet,
+        time_env: T,
+    ) -> Result<Dhcpv4Client<T>>
+    where
+        E: 'static + Env + Clone,
+    {
+        let mut socket = socket_env.udp_socket(SocketAddr {
+            addr: Ipv4Address::UNSPECIFIED,
+            port: dhcpv4_ports::CLIENT,
+        })?;
+        socket.set_broadcast(true);
+
+        let socket_handle = socket_set.add_socket(TaggedSocket::Udp(socket))?;
+
+        let transmit_at = time_env.now_instant();
+
+        Ok(Dhcpv4Client {
+            socket_handle,
+            state: State::Init,
+            transaction_id: 0,
+            offer: None,
+            lease: None,
+            transmit_at,
+            transmissions: 0,
+            time_env,
+        })
+    }
+
+    /// Returns the handle of the socket used by the client.
+    pub fn socket_handle(&self) -> SocketHandle {
+        self.socket_handle
+    }
+
+    /// Returns the current state of the client.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Returns the current lease, if any.
+    pub fn lease(&self) -> Option<&Lease> {
+        self.lease.as_ref()
+    }
+
+    /// Abandons any lease and restarts discovery on the next poll.
+    pub fn reset(&mut self) {
+        self.state = State::Init;
+    }
+
+    /// Returns when poll(...) next needs to be called to send or retransmit a
+    /// message, or to renew, rebind or expire the lease.
+    pub fn poll_at(&self) -> PollAt {
+        let deadline = match (self.state, self.lease.as_ref()) {
+            (State::Init, _) => return PollAt::Now,
+            (State::Bound, Some(lease)) => lease.renew_at(),
+            (State::Renewing, Some(lease)) => cmp::min(self.transmit_at, lease.rebind_at()),
+            (State::Rebinding, Some(lease)) => cmp::min(self.transmit_at, lease.expires_at()),
+            _ => self.transmit_at,
+        };
+
+        PollAt::Time(deadline)
+    }
+
+    /// Processes DHCP replies received by the socket, advances lease timers,
+    /// and enqueues any DHCP message due to be sent. The interface is
+    /// configured when a lease is bound and unconfigured when it is lost.
+    ///
+    /// This should be called regularly, before sending packets via the socket
+    /// set.
+    pub fn poll(&mut self, interface: &mut Interface, socket_set: &mut SocketSet) -> Result<()> {
+        let eth_addr = interface.ethernet_addr;
+        let socket = socket_set.socket(self.socket_handle)?.as_udp_socket();
+
+        loop {
+            let dhcp_repr = match socket.recv() {
+                Ok((payload, _)) => match parse(payload) {
+                    Ok(dhcp_repr) => dhcp_repr,
+                    Err(err) => {
+                        debug!("Ignoring DHCP message with {:?}.", err);
+                        continue;
+                    }
+                },
+                Err(Error::Buffer(BufferError::Empty)) => break,
+                Err(err) => {
+                    debug!("Error receiving DHCP message with {:?}.", err);
+                    continue;
+                }
+            };
+
+            if let Some(change) = self.recv(&dhcp_repr, eth_addr) {
+                self.apply(change, interface);
+            }
+        }
+
+        if let Some(change) = self.update() {
+            self.apply(change, interface);
+        }
+
+        if let Some((dhcp_repr, dst_addr)) = self.transmit(eth_addr) {
+            debug!(
+                "Sending DHCP {:?} to {} in state {:?}.",
+                dhcp_repr.message_type, dst_addr, self.state
+            );
+            let buffer = socket.send(
+                dhcp_repr.buffer_len(),
+                SocketAddr {
+                    addr: dst_addr,
+                    port: dhcpv4_ports::SERVER,
+                },
+            )?;
+            dhcp_repr.serialize(&mut Dhcpv4Packet::try_new(buffer)?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a reply from a DHCP server.
+    fn recv(&mut self, dhcp_repr: &Dhcpv4Repr, eth_addr: EthernetAddress) -> Option<Change> {
+        if dhcp_repr.transaction_id != self.transaction_id || dhcp_repr.client_hw_addr != eth_addr {
+            return None;
+        }
+
+        let now = self.time_env.now_instant();
+
+        match (self.state, dhcp_repr.message_type) {
+            (State::Selecting, Dhcpv4MessageType::Offer) => {
+                match dhcp_repr.server_identifier {
+                    Some(server_id) if dhcp_repr.your_ip.is_unicast() => {
+                        debug!(
+                            "Received DHCP offer of {} from {}.",
+                            dhcp_repr.your_ip, server_id
+                        );
+                        self.offer = Some(Offer {
+                            server_id,
+                            ipv4_addr: dhcp_repr.your_ip,
+                        });
+                        self.start_transaction(State::Requesting, now);
+                    }
+                    _ => debug!("Ignoring DHCP offer {:?}.", dhcp_repr),
+                }
+                None
+            }
+            (State::Requesting, Dhcpv4MessageType::Ack)
+            | (State::Renewing, Dhcpv4MessageType::Ack)
+            | (State::Rebinding, Dhcpv4MessageType::Ack) => {
+                let server_id = match (self.offer.as_ref(), self.lease.as_ref()) {
+                    (Some(offer), _) => Some(offer.server_id),
+                    (_, Some(lease)) => Some(lease.server_id),
+                    _ => None,
+                };
+
+                match Lease::from_ack(dhcp_repr, server_id, now) {
+                    Some(lease) => {
+                        debug!("Bound DHCP lease {:?}.", lease);
+                        self.lease = Some(lease);
+                        self.offer = None;
+                        self.state = State::Bound;
+                        Some(Change::Configure)
+                    }
+                    None => {
+                        debug!("Ignoring DHCP ACK {:?}.", dhcp_repr);
+                        None
+                    }
+                }
+            }
+            (State::Requesting, Dhcpv4MessageType::Nak)
+            | (State::Renewing, Dhcpv4MessageType::Nak)
+            | (State::Rebinding, Dhcpv4MessageType::Nak) => {
+                debug!("Received DHCP NAK, restarting discovery.");
+                self.state = State::Init;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Advances the client through states driven by timers.
+    fn update(&mut self) -> Option<Change> {
+        let now = self.time_env.now_instant();
+
+        match self.state {
+            State::Requesting if self.transmissions >= MAX_REQUESTS && now >= self.transmit_at => {
+                debug!("DHCP request timed out, restarting discovery.");
+                self.state = State::Init;
+            }
+            State::Bound | State::Renewing | State::Rebinding => {
+                let (expires_at, rebind_at, renew_at) = match self.lease {
+                    Some(ref lease) => (lease.expires_at(), lease.rebind_at(), lease.renew_at()),
+                    None => (now, now, now),
+                };
+
+                if now >= expires_at {
+                    debug!("DHCP lease expired, restarting discovery.");
+                    self.state = State::Init;
+                } else if now >= rebind_at && self.state != State::Rebinding {
+                    self.start_transaction(State::Rebinding, now);
+                } else if now >= renew_at && self.state == State::Bound {
+                    self.start_transaction(State::Renewing, now);
+                }
+            }
+            _ => {}
+        }
+
+        if self.state == State::Init {
+            self.offer = None;
+            self.lease = None;
+            self.start_transaction(State::Selecting, now);
+            Some(Change::Deconfigure)
+        } else {
+            None
+        }
+    }
+
+    /// Returns a DHCP message and destination address if a message is due to
+    /// be sent in the current state.
+    fn transmit(&mut self, eth_addr: EthernetAddress) -> Option<(Dhcpv4Repr, Ipv4Address)> {
+        let now = self.time_env.now_instant();
+        if now < self.transmit_at {
+            return None;
+        }
+
+        let mut dhcp_repr =
+            Dhcpv4Repr::new(Dhcpv4MessageType::Request, self.transaction_id, eth_addr);
+        dhcp_repr.parameter_request_list = PARAMETER_REQUEST_LIST.to_vec();
+
+        let (dst_addr, timeout) = match (self.state, self.offer.as_ref(), self.lease.as_ref()) {
+            (State::Selecting, _, _) => {
+                dhcp_repr.message_type = Dhcpv4MessageType::Discover;
+                dhcp_repr.broadcast = true;
+                (Ipv4Address::BROADCAST, self.retransmit_timeout())
+            }
+            (State::Requesting, Some(offer), _) => {
+                dhcp_repr.broadcast = true;
+                dhcp_repr.requested_ip = Some(offer.ipv4_addr);
+                dhcp_repr.server_identifier = Some(offer.server_id);
+                (Ipv4Address::BROADCAST, self.retransmit_timeout())
+            }
+            (State::Renewing, _, Some(lease)) => {
+                dhcp_repr.client_ip = *lease.ipv4_addr;
+                (lease.server_id, renew_timeout(now, lease.rebind_at()))
+            }
+            (State::Rebinding, _, Some(lease)) => {
+                dhcp_repr.client_ip = *lease.ipv4_addr;
+                (
+                    Ipv4Address::BROADCAST,
+                    renew_timeout(now, lease.expires_at()),
+                )
+            }
+            _ => return None,
+        };
+
+        self.transmissions += 1;
+        self.transmit_at = now + timeout;
+
+        Some((dhcp_repr, dst_addr))
+    }
+
+    /// Moves to a state which sends messages under a new transaction ID,
+    /// starting immediately.
+    fn start_transaction(&mut self, state: State, now: Instant) {
+        self.state = state;
+        self.transaction_id = random::<u32>();
+        self.transmissions = 0;
+        self.transmit_at = now;
+    }
+
+    /// Returns the timeout before retransmitting a DISCOVER or REQUEST.
+    fn retransmit_timeout(&self) -> Duration {
+        let timeout = RETRANSMIT_TIMEOUT_SECS << cmp::min(self.transmissions, 4);
+        Duration::from_secs(cmp::min(timeout, MAX_RETRANSMIT_TIMEOUT_SECS))
+    }
+
+    fn apply(&self, change: Change, interface: &mut Interface) {
+        match (change, self.lease.as_ref()) {
+            (Change::Configure, Some(lease)) => {
+                interface.ipv4_addr = lease.ipv4_addr;
+                if let Some(router) = lease.router {
+                    interface.default_gateway = router;
+                }
+                interface.dns_servers = lease.dns_servers.clone();
+            }
+            _ => {
+                interface.ipv4_addr = Ipv4AddressCidr::new(Ipv4Address::UNSPECIFIED, 0);
+                interface.dns_servers.clear();
+            }
+        }
+    }
+}
+
+/// Returns the timeout before retransmitting a REQUEST while renewing or
+/// rebinding, i.e. half the time remaining until deadline, see RFC 2131.
+fn renew_timeout(now: Instant, deadline: Instant) -> Duration {
+    let remaining = deadline.duration_since(now) / 2;
+    cmp::max(remaining, Duration::from_secs(MIN_RENEW_TIMEOUT_SECS))
+}
+
+fn parse(payload: &[u8]) -> Result<Dhcpv4Repr> {
+    let dhcp_packet = Dhcpv4Packet::try_new(payload)?;
+    dhcp_packet.check_encoding()?;
+    let dhcp_repr = Dhcpv4Repr::deserialize(&dhcp_packet)?;
+
+    if dhcp_repr.message_type.is_reply() {
+        Ok(dhcp_repr)
+    } else {
+        Err(Error::Ignored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    fn eth_addr() -> EthernetAddress {
+        EthernetAddress::new([0x06, 0x11, 0x22, 0x33, 0x44, 0x55])
+    }
+
+    fn server_id() -> Ipv4Address {
+        Ipv4Address::new([10, 0, 0, 1])
+    }
+
+    fn client() -> Dhcpv4Client<MockEnv> {
+        let time_env = MockEnv::new();
+        Dhcpv4Client {
+            socket_handle: SocketHandle::new(0, 0),
+            state: State::Init,
+            transaction_id: 0,
+            offer: None,
+            lease: None,
+            transmit_at: time_env.now,
+            transmissions: 0,
+            time_env,
+        }
+    }
+
+    fn reply(client: &Dhcpv4Client<MockEnv>, message_type: Dhcpv4MessageType) -> Dhcpv4Repr {
+        let mut dhcp_repr = Dhcpv4Repr::new(message_type, client.transaction_id, eth_addr());
+        dhcp_repr.your_ip = Ipv4Address::new([10, 0, 0, 150]);
+        dhcp_repr.server_identifier = Some(server_id());
+        dhcp_repr.subnet_mask = Some(Ipv4Address::new([255, 255, 255, 0]));
+        dhcp_repr.router = Some(server_id());
+        dhcp_repr.dns_servers = vec![Ipv4Address::new([10, 0, 0, 53])];
+        dhcp_repr.lease_duration = Some(3600);
+        dhcp_repr
+    }
+
+    fn bound_client() -> Dhcpv4Client<MockEnv> {
+        let mut client = client();
+        client.update();
+        client.transmit(eth_addr()).unwrap();
+        let offer = reply(&client, Dhcpv4MessageType::Offer);
+        client.recv(&offer, eth_addr());
+        client.transmit(eth_addr()).unwrap();
+        let ack = reply(&client, Dhcpv4MessageType::Ack);
+        assert_eq!(client.recv(&ack, eth_addr()), Some(Change::Configure));
+        client
+    }
+
+    #[test]
+    fn test_acquire_lease() {
+        let mut client = client();
+        assert_eq!(client.update(), Some(Change::Deconfigure));
+        assert_eq!(client.state(), State::Selecting);
+
+        let (discover, dst_addr) = client.transmit(eth_addr()).unwrap();
+        assert_eq!(discover.message_type, Dhcpv4MessageType::Discover);
+        assert!(discover.broadcast);
+        assert_eq!(dst_addr, Ipv4Address::BROADCAST);
+        assert_matches!(client.transmit(eth_addr()), None);
+
+        let offer = reply(&client, Dhcpv4MessageType::Offer);
+        assert_eq!(client.recv(&offer, eth_addr()), None);
+        assert_eq!(client.state(), State::Requesting);
+
+        let (request, dst_addr) = client.transmit(eth_addr()).unwrap();
+        assert_eq!(request.message_type, Dhcpv4MessageType::Request);
+        assert_eq!(request.requested_ip, Some(offer.your_ip));
+        assert_eq!(request.server_identifier, Some(server_id()));
+        assert_eq!(request.client_ip, Ipv4Address::UNSPECIFIED);
+        assert_eq!(dst_addr, Ipv4Address::BROADCAST);
+
+        let ack = reply(&client, Dhcpv4MessageType::Ack);
+        assert_eq!(client.recv(&ack, eth_addr()), Some(Change::Configure));
+        assert_eq!(client.state(), State::Bound);
+
+        let lease = client.lease().unwrap();
+        assert_eq!(
+            lease.ipv4_addr,
+            Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 150]), 24)
+        );
+        assert_eq!(lease.router, Some(server_id()));
+        assert_eq!(lease.dns_servers, vec![Ipv4Address::new([10, 0, 0, 53])]);
+        assert_eq!(lease.renew_after, Duration::from_secs(1800));
+        assert_eq!(lease.rebind_after, Duration::from_secs(3150));
+        assert_matches!(client.transmit(eth_addr()), None);
+    }
+
+    #[test]
+    fn test_ignores_other_transactions() {
+        let mut client = client();
+        client.update();
+
+        let mut offer = reply(&client, Dhcpv4MessageType::Offer);
+        offer.transaction_id = client.transaction_id.wrapping_add(1);
+        client.recv(&offer, eth_addr());
+        assert_eq!(client.state(), State::Selecting);
+
+        let offer = reply(&client, Dhcpv4MessageType::Offer);
+        client.recv(&offer, EthernetAddress::new([0x06, 0, 0, 0, 0, 1]));
+        assert_eq!(client.state(), State::Selecting);
+    }
+
+    #[test]
+    fn test_retransmit_backoff() {
+        let mut client = client();
+        client.update();
+
+        for timeout in [4, 8, 16, 32, 64, 64].iter() {
+            assert!(client.transmit(eth_addr()).is_some());
+            client.time_env.now += Duration::from_secs(timeout - 1);
+            assert_matches!(client.transmit(eth_addr()), None);
+            client.time_env.now += Duration::from_secs(1);
+        }
+    }
+
+    #[test]
+    fn test_poll_at() {
+        let mut client = client();
+        assert_eq!(client.poll_at(), PollAt::Now);
+
+        client.update();
+        let start = client.time_env.now;
+        assert_eq!(client.poll_at(), PollAt::Time(start));
+
+        client.transmit(eth_addr()).unwrap();
+        assert_eq!(
+            client.poll_at(),
+            PollAt::Time(start + Duration::from_secs(RETRANSMIT_TIMEOUT_SECS))
+        );
+
+        let mut client = bound_client();
+        let renew_at = client.lease().unwrap().renew_at();
+        assert_eq!(client.poll_at(), PollAt::Time(renew_at));
+
+        client.time_env.now = renew_at;
+        client.update();
+        client.transmit(eth_addr()).unwrap();
+        assert_eq!(
+            client.poll_at(),
+            PollAt::Time(renew_at + Duration::from_secs(675))
+        );
+    }
+
+    #[test]
+    fn test_request_timeout_restarts_discovery() {
+        let mut client = client();
+        client.update();
+        client.transmit(eth_addr()).unwrap();
+        let offer = reply(&client, Dhcpv4MessageType::Offer);
+        client.recv(&offer, eth_addr());
+
+        for _ in 0 .. MAX_REQUESTS {
+            assert_eq!(client.update(), None);
+            assert!(client.transmit(eth_addr()).is_some());
+            client.time_env.now += Duration::from_secs(MAX_RETRANSMIT_TIMEOUT_SECS);
+        }
+
+        assert_eq!(client.update(), Some(Change::Deconfigure));
+        assert_eq!(client.state(), State::Selecting);
+    }
+
+    #[test]
+    fn test_nak_restarts_discovery() {
+        let mut client = bound_client();
+        client.time_env.now += Duration::from_secs(1800);
+        client.update();
+        assert_eq!(client.state(), State::Renewing);
+
+        let nak = reply(&client, Dhcpv4MessageType::Nak);
+        assert_eq!(client.recv(&nak, eth_addr()), None);
+        assert_eq!(client.update(), Some(Change::Deconfigure));
+        assert_eq!(client.state(), State::Selecting);
+        assert_matches!(client.lease(), None);
+    }
+
+    #[test]
+    fn test_renew_rebind_expire() {
+        let mut client = bound_client();
+
+        client.time_env.now += Duration::from_secs(1799);
+        assert_eq!(client.update(), None);
+        assert_eq!(client.state(), State::Bound);
+
+        client.time_env.now += Duration::from_secs(1);
+        assert_eq!(client.update(), None);
+        assert_eq!(client.state(), State::Renewing);
+
+        let (request, dst_addr) = client.transmit(eth_addr()).unwrap();
+        assert_eq!(request.message_type, Dhcpv4MessageType::Request);
+        assert_eq!(request.client_ip, Ipv4Address::new([10, 0, 0, 150]));
+        assert_eq!(request.requested_ip, None);
+        assert_eq!(dst_addr, server_id());
+
+        // Retransmits after half the time remaining until rebinding.
+        client.time_env.now += Duration::from_secs(674);
+        assert_matches!(client.transmit(eth_addr()), None);
+        client.time_env.now += Duration::from_secs(1);
+        assert!(client.transmit(eth_addr()).is_some());
+
+        client.time_env.now += Duration::from_secs(675);
+        assert_eq!(client.update(), None);
+        assert_eq!(client.state(), State::Rebinding);
+
+        let (request, dst_addr) = client.transmit(eth_addr()).unwrap();
+        assert_eq!(request.client_ip, Ipv4Address::new([10, 0, 0, 150]));
+        assert_eq!(dst_addr, Ipv4Address::BROADCAST);
+
+        client.time_env.now += Duration::from_secs(450);
+        assert_eq!(client.update(), Some(Change::Deconfigure));
+        assert_eq!(client.state(), State::Selecting);
+        assert_matches!(client.lease(), None);
+    }
+
+    #[test]
+    fn test_renewal_extends_lease() {
+        let mut client = bound_client();
+        client.time_env.now += Duration::from_secs(1800);
+        client.update();
+        client.transmit(eth_addr()).unwrap();
+
+        let mut ack = reply(&client, Dhcpv4MessageType::Ack);
+        ack.server_identifier = None;
+        assert_eq!(client.recv(&ack, eth_addr()), Some(Change::Configure));
+        assert_eq!(client.state(), State::Bound);
+
+        let lease = client.lease().unwrap();
+        assert_eq!(lease.server_id, server_id());
+        assert_eq!(lease.acquired_at, client.time_env.now);
+    }
+}
@@ -0,0 +1,302 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use stack::random::random;
+use stack::repr::{
+    dns_ports,
+    dns_rcodes,
+    dns_types,
+    DnsPacket,
+    DnsRecordData,
+    DnsRepr,
+    Ipv4Address,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketEnv,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    PollAt,
+};
+use {
+    BufferError,
+    Error,
+    RemoteError,
+    Result,
+};
+
+/// Number of times a query is sent before giving up.
+pub static MAX_QUERY_ATTEMPTS: u32 = 3;
+
+/// Handle identifying a query issued via a Resolver.
+pub type QueryHandle = usize;
+
+enum QueryState {
+    Pending {
+        attempts: u32,
+        server: Option<Ipv4Address>,
+        sent_at: Option<Instant>,
+    },
+    Done(Result<Vec<DnsRecordData>>),
+}
+
+struct Query {
+    id: u16,
+    name: String,
+    record_type: u16,
+    state: QueryState,
+}
+
+impl Query {
+    /// Checks if a DNS message is the response to the query from the server
+    /// it was last sent to.
+    fn is_response(&self, dns_repr: &DnsRepr, src_addr: &SocketAddr) -> bool {
+        let from_server = match self.state {
+            QueryState::Pending {
+                server: Some(server),
+                ..
+            } => src_addr.addr == server && src_addr.port == dns_ports::DNS,
+            _ => false,
+        };
+
+        let answers_question = dns_repr.questions.first().is_some_and(|question| {
+            question.record_type == self.record_type
+                && question
+                    .name
+                    .eq_ignore_ascii_case(self.name.trim_end_matches('.'))
+        });
+
+        from_server && dns_repr.is_response() && dns_repr.id == self.id && answers_question
+    }
+}
+
+/// A stub resolver which sends DNS queries to the DNS servers of an interface
+/// over a UDP socket.
+///
+/// Queries are retried with a timeout, rotating through the DNS servers, and
+/// fail with Error::Timeout after MAX_QUERY_ATTEMPTS attempts. Queries time
+/// out if the interface has no DNS servers.
+pub struct Resolver<T = DefaultEnv>
+where
+    T: Env,
+{
+    socket_handle: SocketHandle,
+    queries: BTreeMap<QueryHandle, Query>,
+    next_handle: QueryHandle,
+    timeout: Duration,
+    time_env: T,
+}
+
+impl<T: Env> Resolver<T> {
+    /// Creates a resolver which retries queries after timeout_in_millis
+    /// milliseconds, and adds a UDP socket bound to an ephemeral port to the
+    /// socket set.
+    pub fn new<E>(
+        socket_env: &SocketEnv<E>,
+        socket_set: &mut SocketSet,
+        timeout_in_millis: u64,
+        time_env: T,
+    ) -> Result<Resolver<T>>
+    where
+        E: 'static + Env + Clone,
+    {
+        let socket = socket_env.udp_socket_ephemeral(Ipv4Address::UNSPECIFIED)?;
+
+        let socket_handle = socket_set.add_socket(TaggedSocket::Udp(socket))?;
+
+        Ok(Resolver {
+            socket_handle,
+            queries: BTreeMap::new(),
+            next_handle: 0,
+            timeout: Duration::from_millis(timeout_in_millis),
+            time_env,
+        })
+    }
+
+    /// Returns the handle of the socket used by the resolver.
+    pub fn socket_handle(&self) -> SocketHandle {
+        self.socket_handle
+    }
+
+    /// Starts a query for records of a type for a name, which is sent the next
+    /// time the resolver is polled.
+    ///
+    /// Fails with ParseError::Malformed if the name is not a valid domain name.
+    pub fn query(&mut self, name: &str, record_type: u16) -> Result<QueryHandle> {
+        DnsRepr::query(0, name, record_type).buffer_len()?;
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+
+        self.queries.insert(
+            handle,
+            Query {
+                id: random::<u16>(),
+                name: name.to_string(),
+                record_type,
+                state: QueryState::Pending {
+                    attempts: 0,
+                    server: None,
+                    sent_at: None,
+                },
+            },
+        );
+
+        Ok(handle)
+    }
+
+    /// Removes a query, returning its result if it has completed. The result
+    /// holds the records of the queried type in the answer, or any records if
+    /// the type is ANY.
+    pub fn take_result(&mut self, handle: QueryHandle) -> Option<Result<Vec<DnsRecordData>>> {
+        match self.queries.get(&handle) {
+            Some(&Query {
+                state: QueryState::Done(_),
+                ..
+            }) => {}
+            _ => return None,
+        }
+
+        match self.queries.remove(&handle).unwrap().state {
+            QueryState::Done(result) => Some(result),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Abandons a query, ignoring any response.
+    pub fn cancel(&mut self, handle: QueryHandle) {
+        self.queries.remove(&handle);
+    }
+
+    /// Returns when poll(...) next needs to be called to send or retry a
+    /// pending query.
+    pub fn poll_at(&self) -> PollAt {
+        self.queries
+            .values()
+            .filter_map(|query| match query.state {
+                QueryState::Pending { sent_at: None, .. } => Some(PollAt::Now),
+                QueryState::Pending {
+                    sent_at: Some(sent_at),
+                    ..
+                } => Some(PollAt::Time(sent_at + self.timeout)),
+                QueryState::Done(_) => None,
+            })
+            .min()
+            .unwrap_or(PollAt::Ingress)
+    }
+
+    /// Processes DNS responses received by the socket and sends or retries
+    /// pending queries.
+    ///
+    /// This should be called regularly, before sending packets via the socket
+    /// set.
+    pub fn poll(&mut self, interface: &Interface, socket_set: &mut SocketSet) -> Result<()> {
+        let socket = socket_set.socket(self.socket_handle)?.as_udp_socket();
+
+        loop {
+            let (dns_repr, src_addr) = match socket.recv() {
+                Ok((payload, src_addr)) => {
+                    match DnsPacket::try_new(payload)
+                        .and_then(|packet| DnsRepr::deserialize(&packet))
+                    {
+                        Ok(dns_repr) => (dns_repr, src_addr),
+                        Err(err) => {
+                            debug!("Ignoring DNS message from {} with {:?}.", src_addr, err);
+                            continue;
+                        }
+                    }
+                }
+                Err(Error::Buffer(BufferError::Empty)) => break,
+                Err(err) => {
+                    debug!("Error receiving DNS message with {:?}.", err);
+                    continue;
+                }
+            };
+
+            for query in self.queries.values_mut() {
+                if query.is_response(&dns_repr, &src_addr) {
+                    query.state = QueryState::Done(answer(query.record_type, dns_repr));
+                    break;
+                }
+            }
+        }
+
+        let now = self.time_env.now_instant();
+        let timeout = self.timeout;
+
+        for query in self.queries.values_mut() {
+            let attempts = match query.state {
+                QueryState::Pending {
+                    attempts,
+                    sent_at: None,
+                    ..
+                } => attempts,
+                QueryState::Pending {
+                    attempts,
+                    sent_at: Some(sent_at),
+                    ..
+                } if now.duration_since(sent_at) >= timeout => attempts,
+                _ => continue,
+            };
+
+            if attempts >= MAX_QUERY_ATTEMPTS {
+                debug!("DNS query for {} timed out.", query.name);
+                query.state = QueryState::Done(Err(Error::Timeout));
+                continue;
+            }
+
+            let server = if interface.dns_servers.is_empty() {
+                None
+            } else {
+                Some(interface.dns_servers[attempts as usize % interface.dns_servers.len()])
+            };
+
+            if let Some(server) = server {
+                let dns_repr = DnsRepr::query(query.id, &query.name, query.record_type);
+                let buffer = socket.send(
+                    dns_repr.buffer_len()?,
+                    SocketAddr {
+                        addr: server,
+                        port: dns_ports::DNS,
+                    },
+                )?;
+                dns_repr.serialize(&mut DnsPacket::try_new(buffer)?)?;
+                debug!("Sent DNS query for {} to {}.", query.name, server);
+            }
+
+            query.state = QueryState::Pending {
+                attempts: attempts + 1,
+                server,
+                sent_at: Some(now),
+            };
+        }
+
+        Ok(())
+    }
+}
+
+/// Extracts the result of a query from a response.
+fn answer(record_type: u16, dns_repr: DnsRepr) -> Result<Vec<DnsRecordData>> {
+    if dns_repr.rcode != dns_rcodes::NO_ERROR {
+        return Err(Error::Remote(RemoteError::Dns(dns_repr.rcode)));
+    }
+
+    Ok(dns_repr
+        .answers
+        .into_iter()
+        .map(|record| record.data)
+        .filter(|data| record_type == dns_types::ANY || data.record_type() == record_type)
+        .collect())
+}
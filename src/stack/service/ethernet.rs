@@ -0,0 +1,232 @@
+use alloc::vec::Vec;
+
+use stack::capture::Direction;
+use stack::repr::{
+    eth_types,
+    EthernetAddress,
+    EthernetFrame,
+    Ipv4Address,
+    Ipv6Address,
+};
+use stack::service::{
+    arp,
+    ipv4,
+    ipv6,
+    pppoe,
+    shim,
+    Interface,
+};
+use stack::socket::{
+    RawType,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::storage::PacketBuf;
+use {
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// Maximum number of frames queued while batching before they are sent.
+pub const BATCH_LEN: usize = 32;
+
+/// Send an Ethernet frame via an interface.
+///
+/// If the interface is batching, the frame is queued and sent later along with
+/// other frames. If the interface has a shim, the frame is transformed in
+/// place before it is queued or sent.
+pub fn send_frame<F>(interface: &mut Interface, eth_frame_len: usize, f: F) -> Result<()>
+where
+    F: FnOnce(&mut EthernetFrame<&mut [u8]>),
+{
+    let mut eth_buffer = PacketBuf::from_vec(
+        interface.pool.take(0),
+        shim::headroom(interface),
+        eth_frame_len,
+        shim::tailroom(interface),
+    );
+    {
+        let mut eth_frame = EthernetFrame::try_new(&mut eth_buffer[..])?;
+        f(&mut eth_frame);
+        eth_frame.set_src_addr(interface.ethernet_addr);
+    }
+
+    if let Err(err) = shim::encapsulate(interface, &mut eth_buffer) {
+        interface.pool.give(eth_buffer.into_vec());
+        return Err(err);
+    }
+
+    if let Some(ref mut shaper) = interface.shaper {
+        shaper.consume(eth_buffer.len());
+    }
+
+    // Queue the frame if batching, flushing once the batch is full.
+    if let Some(ref mut tx_batch) = interface.tx_batch {
+        tx_batch.push(eth_buffer);
+        if tx_batch.len() < BATCH_LEN {
+            return Ok(());
+        }
+    } else {
+        let result = interface.dev.send(&eth_buffer);
+        if result.is_ok() {
+            interface.stats.record_tx(eth_buffer.len());
+            interface.capture_frame(Direction::Sent, &eth_buffer);
+        }
+        interface.pool.give(eth_buffer.into_vec());
+        return result;
+    }
+
+    flush_batch(interface)
+}
+
+/// Starts queueing frames sent via an interface so they may be sent with a
+/// single Device::send_batch(...) call.
+///
+/// Frames are queued until BATCH_LEN frames are pending or end_batch(...) is
+/// called.
+pub fn begin_batch(interface: &mut Interface) {
+    if interface.tx_batch.is_none() {
+        interface.tx_batch = Some(Vec::with_capacity(BATCH_LEN));
+    }
+}
+
+/// Sends any frames queued since begin_batch(...) and stops queueing frames.
+pub fn end_batch(interface: &mut Interface) -> Result<()> {
+    let result = flush_batch(interface);
+    interface.tx_batch = None;
+    result
+}
+
+/// Sends frames queued while batching. Frames the device could not send are
+/// dropped, and an error returned so callers stop sending.
+fn flush_batch(interface: &mut Interface) -> Result<()> {
+    let eth_buffers = match interface.tx_batch {
+        Some(ref mut tx_batch) if !tx_batch.is_empty() => tx_batch.split_off(0),
+        _ => return Ok(()),
+    };
+
+    let (sent_len, result) = {
+        let buffers: Vec<&[u8]> = eth_buffers.iter().map(|buffer| &buffer[..]).collect();
+        match interface.dev.send_batch(&buffers) {
+            Ok(sent_len) if sent_len < buffers.len() => {
+                (sent_len, Err(Error::Device(DeviceError::WouldBlock)))
+            }
+            Ok(sent_len) => (sent_len, Ok(())),
+            Err(err) => (0, Err(err)),
+        }
+    };
+
+    for eth_buffer in &eth_buffers[.. sent_len] {
+        interface.stats.record_tx(eth_buffer.len());
+        interface.capture_frame(Direction::Sent, eth_buffer);
+    }
+
+    if sent_len < eth_buffers.len() {
+        debug!(
+            "Dropping {} Ethernet frames the device could not send.",
+            eth_buffers.len() - sent_len
+        );
+    }
+
+    for eth_buffer in eth_buffers {
+        interface.pool.give(eth_buffer.into_vec());
+    }
+
+    result
+}
+
+/// Receives an Ethernet frame from an interface.
+///
+/// The Ethernet frame is parsed, forwarded to any sockets, and propagated up
+/// the network stack.
+pub fn recv_frame(
+    interface: &mut Interface,
+    eth_buffer: &[u8],
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let eth_frame = EthernetFrame::try_new(eth_buffer)?;
+
+    if eth_frame.dst_addr() != interface.ethernet_addr
+        && !eth_frame.dst_addr().is_broadcast()
+        && !is_multicast_member(interface, eth_frame.dst_addr())
+    {
+        debug!(
+            "Ignoring ethernet frame with destination {}.",
+            eth_frame.dst_addr()
+        );
+        return Err(Error::Ignored);
+    }
+
+    socket_set
+        .iter_mut()
+        .filter_map(|socket| match *socket {
+            TaggedSocket::Raw(ref mut socket)
+                if socket.raw_type() == RawType::Ethernet && socket.accepts(eth_frame.as_ref()) =>
+            {
+                Some(socket)
+            }
+            _ => None,
+        })
+        .for_each(|socket| {
+            if let Err(err) = socket.recv_enqueue(eth_frame.as_ref()) {
+                debug!(
+                    "Error enqueueing Ethernet frame for receiving via socket with {:?}.",
+                    err
+                );
+            }
+        });
+
+    match eth_frame.payload_type() {
+        eth_types::ARP => arp::recv_packet(interface, &eth_frame),
+        eth_types::IPV4 => ipv4::recv_packet(interface, &eth_frame, socket_set),
+        eth_types::IPV6 => ipv6::recv_packet(interface, &eth_frame),
+        eth_types::PPPOE_DISCOVERY => pppoe::recv_discovery(interface, &eth_frame),
+        eth_types::PPPOE_SESSION => pppoe::recv_packet(interface, &eth_frame, socket_set),
+        i => {
+            debug!("Ignoring ethernet frame with type {}.", i);
+            Err(Error::Ignored)
+        }
+    }
+}
+
+/// Maps an IPv4 multicast group to an Ethernet multicast address by placing the
+/// low 23 bits of the group address after the 01:00:5E prefix.
+pub fn multicast_addr(ipv4_addr: Ipv4Address) -> EthernetAddress {
+    let ipv4_bytes = ipv4_addr.as_bytes();
+    EthernetAddress::new([
+        0x01,
+        0x00,
+        0x5E,
+        ipv4_bytes[1] & 0x7F,
+        ipv4_bytes[2],
+        ipv4_bytes[3],
+    ])
+}
+
+/// Maps an IPv6 multicast group to an Ethernet multicast address by placing the
+/// low 32 bits of the group address after the 33:33 prefix.
+pub fn ipv6_multicast_addr(ipv6_addr: Ipv6Address) -> EthernetAddress {
+    let ipv6_bytes = ipv6_addr.as_bytes();
+    EthernetAddress::new([
+        0x33,
+        0x33,
+        ipv6_bytes[12],
+        ipv6_bytes[13],
+        ipv6_bytes[14],
+        ipv6_bytes[15],
+    ])
+}
+
+/// Checks if an Ethernet address corresponds to a multicast group joined by the
+/// interface.
+fn is_multicast_member(interface: &Interface, eth_addr: EthernetAddress) -> bool {
+    eth_addr.is_multicast()
+        && (eth_addr == multicast_addr(Ipv4Address::ALL_HOSTS)
+            || eth_addr == ipv6_multicast_addr(Ipv6Address::ALL_NODES)
+            || eth_addr == ipv6_multicast_addr(interface.ipv6_addr.solicited_node())
+            || interface
+                .ipv4_multicast_groups
+                .iter()
+                .any(|group_addr| multicast_addr(*group_addr) == eth_addr))
+}
@@ -0,0 +1,381 @@
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::capture::Direction;
+use stack::service::conntrack::State;
+use stack::repr::{
+    ipv4_protocols,
+    Ipv4AddressCidr,
+    Ipv4Packet,
+    Ipv4Protocol,
+};
+
+/// What happens to a packet matching a rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Accept,
+    /// Silently discards the packet.
+    Drop,
+    /// Discards the packet and notifies the sender of received packets with
+    /// an ICMP Destination Unreachable (Communication Prohibited) error. Sent
+    /// packets are discarded as with Drop.
+    Reject,
+}
+
+/// Bits of the TCP flags for matching via Rule::tcp_flags, in the order of
+/// the TCP header.
+pub mod tcp_flags {
+    pub const FIN: u16 = 0x001;
+
+    pub const SYN: u16 = 0x002;
+
+    pub const RST: u16 = 0x004;
+
+    pub const PSH: u16 = 0x008;
+
+    pub const ACK: u16 = 0x010;
+
+    pub const URG: u16 = 0x020;
+
+    pub const ECE: u16 = 0x040;
+
+    pub const CWR: u16 = 0x080;
+
+    pub const NS: u16 = 0x100;
+}
+
+/// A rule matching IPv4 packets by their headers, where None matches any
+/// value, e.g.
+///
+/// Rule { protocol: Some(Ipv4Protocol::TCP), dst_ports: Some(22 ..= 22),
+/// ..Rule::new(Action::Drop) }
+///
+/// Port and TCP flag matches only apply to UDP and TCP packets carrying the
+/// header, i.e. a rule with them never matches other protocols or fragments
+/// after the first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    pub direction: Option<Direction>,
+    pub protocol: Option<Ipv4Protocol>,
+    pub src_addr: Option<Ipv4AddressCidr>,
+    pub dst_addr: Option<Ipv4AddressCidr>,
+    pub src_ports: Option<RangeInclusive<u16>>,
+    pub dst_ports: Option<RangeInclusive<u16>>,
+    /// A (mask, value) pair of tcp_flags bits, matching TCP packets whose
+    /// flags under the mask equal the value, e.g. (SYN | ACK, SYN) for
+    /// connection attempts.
+    pub tcp_flags: Option<(u16, u16)>,
+    /// Matches packets by the state they put their connection in, e.g.
+    /// Established for replies, if the interface tracks connections.
+    pub state: Option<State>,
+    pub action: Action,
+}
+
+impl Rule {
+    /// Creates a rule matching every packet.
+    pub fn new(action: Action) -> Rule {
+        Rule {
+            direction: None,
+            protocol: None,
+            src_addr: None,
+            dst_addr: None,
+            src_ports: None,
+            dst_ports: None,
+            tcp_flags: None,
+            state: None,
+            action,
+        }
+    }
+
+    /// Checks if a packet passing through the interface in some direction
+    /// matches the rule, where state is the connection state of the packet
+    /// if tracked.
+    pub fn matches(
+        &self,
+        direction: Direction,
+        state: Option<State>,
+        ipv4_packet: &Ipv4Packet<&[u8]>,
+    ) -> bool {
+        if self.direction.is_some_and(|d| d != direction)
+            || self.state.is_some_and(|s| Some(s) != state)
+            || self
+                .protocol
+                .is_some_and(|protocol| protocol as u8 != ipv4_packet.protocol())
+            || self
+                .src_addr
+                .is_some_and(|cidr| !cidr.is_member(ipv4_packet.src_addr()))
+            || self
+                .dst_addr
+                .is_some_and(|cidr| !cidr.is_member(ipv4_packet.dst_addr()))
+        {
+            return false;
+        }
+
+        if self.src_ports.is_none() && self.dst_ports.is_none() && self.tcp_flags.is_none() {
+            return true;
+        }
+
+        let transport = ipv4_packet.payload();
+        let has_ports = match ipv4_packet.protocol() {
+            ipv4_protocols::TCP => transport.len() >= 14,
+            ipv4_protocols::UDP => transport.len() >= 4,
+            _ => false,
+        };
+
+        if !has_ports || ipv4_packet.fragment_offset() != 0 {
+            return false;
+        }
+
+        let src_port = NetworkEndian::read_u16(&transport[0 .. 2]);
+        let dst_port = NetworkEndian::read_u16(&transport[2 .. 4]);
+
+        if self
+            .src_ports
+            .as_ref()
+            .is_some_and(|ports| !ports.contains(&src_port))
+            || self
+                .dst_ports
+                .as_ref()
+                .is_some_and(|ports| !ports.contains(&dst_port))
+        {
+            return false;
+        }
+
+        match self.tcp_flags {
+            Some(_) if ipv4_packet.protocol() != ipv4_protocols::TCP => false,
+            Some((mask, value)) => {
+                let flags = NetworkEndian::read_u16(&transport[12 .. 14]) & 0x1FF;
+                flags & mask == value & mask
+            }
+            None => true,
+        }
+    }
+}
+
+/// Counters for packets matching a rule.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RuleStats {
+    pub packets: u64,
+    pub bytes: u64,
+}
+
+impl RuleStats {
+    fn record(&mut self, packet_len: usize) {
+        self.packets += 1;
+        self.bytes += packet_len as u64;
+    }
+}
+
+/// An ordered list of stateless rules applied to IPv4 packets sent and
+/// received via an interface, before they reach sockets or the device.
+///
+/// The first matching rule decides what happens to a packet, or the default
+/// action if none match.
+#[derive(Clone, Debug)]
+pub struct Filter {
+    rules: Vec<(Rule, RuleStats)>,
+    default_action: Action,
+    default_stats: RuleStats,
+}
+
+impl Filter {
+    /// Creates a filter without rules.
+    pub fn new(default_action: Action) -> Filter {
+        Filter {
+            rules: Vec::new(),
+            default_action,
+            default_stats: RuleStats::default(),
+        }
+    }
+
+    /// Appends a rule, which is evaluated after existing rules.
+    pub fn push(&mut self, rule: Rule) {
+        self.rules.push((rule, RuleStats::default()));
+    }
+
+    /// Inserts a rule at an index, shifting later rules back.
+    pub fn insert(&mut self, index: usize, rule: Rule) {
+        self.rules.insert(index, (rule, RuleStats::default()));
+    }
+
+    /// Removes the rule at an index.
+    pub fn remove(&mut self, index: usize) -> Rule {
+        self.rules.remove(index).0
+    }
+
+    /// Removes all rules.
+    pub fn clear(&mut self) {
+        self.rules.clear();
+    }
+
+    /// Returns the rules in evaluation order and their counters.
+    pub fn rules(&self) -> impl Iterator<Item = (&Rule, &RuleStats)> {
+        self.rules.iter().map(|(rule, stats)| (rule, stats))
+    }
+
+    pub fn default_action(&self) -> Action {
+        self.default_action
+    }
+
+    pub fn set_default_action(&mut self, default_action: Action) {
+        self.default_action = default_action;
+    }
+
+    /// Returns counters for packets which matched no rule.
+    pub fn default_stats(&self) -> RuleStats {
+        self.default_stats
+    }
+
+    /// Returns the action for a packet passing through the interface, and
+    /// updates the counters of the deciding rule. Buffers which aren't IPv4
+    /// packets get the default action.
+    pub fn evaluate(&mut self, direction: Direction, ipv4_buffer: &[u8]) -> Action {
+        self.evaluate_with_state(direction, None, ipv4_buffer)
+    }
+
+    /// Returns the action for a packet like evaluate(...), along with the
+    /// connection state of the packet if tracked.
+    pub fn evaluate_with_state(
+        &mut self,
+        direction: Direction,
+        state: Option<State>,
+        ipv4_buffer: &[u8],
+    ) -> Action {
+        if let Ok(ipv4_packet) = Ipv4Packet::try_new(ipv4_buffer) {
+            for &mut (ref rule, ref mut stats) in self.rules.iter_mut() {
+                if rule.matches(direction, state, &ipv4_packet) {
+                    stats.record(ipv4_buffer.len());
+                    return rule.action;
+                }
+            }
+        }
+
+        self.default_stats.record(ipv4_buffer.len());
+        self.default_action
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::repr::{
+        Ipv4Address,
+        Ipv4Options,
+        Ipv4Repr,
+        PacketBuilder,
+        TcpRepr,
+    };
+
+    fn ipv4_repr(src_addr: [u8; 4], dst_addr: [u8; 4]) -> Ipv4Repr {
+        Ipv4Repr {
+            src_addr: Ipv4Address::new(src_addr),
+            dst_addr: Ipv4Address::new(dst_addr),
+            protocol: Ipv4Protocol::UDP,
+            payload_len: 0,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        }
+    }
+
+    fn udp(src_addr: [u8; 4], dst_addr: [u8; 4], dst_port: u16) -> Vec<u8> {
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr(src_addr, dst_addr))
+            .udp(1024, dst_port);
+        let mut buffer = vec![0; builder.buffer_len()];
+        builder.build(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn tcp(dst_port: u16, syn: bool, ack: bool) -> Vec<u8> {
+        let mut flags = [false; 9];
+        flags[TcpRepr::FLAG_SYN] = syn;
+        flags[TcpRepr::FLAG_ACK] = ack;
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr([10, 0, 0, 2], [10, 0, 0, 1]))
+            .tcp(TcpRepr {
+                src_port: 1024,
+                dst_port,
+                seq_num: 0,
+                ack_num: 0,
+                flags,
+                window_size: 0,
+                urgent_pointer: 0,
+                max_segment_size: None,
+            });
+        let mut buffer = vec![0; builder.buffer_len()];
+        builder.build(&mut buffer).unwrap();
+        buffer
+    }
+
+    fn cidr(addr: [u8; 4], subnet_len: usize) -> Ipv4AddressCidr {
+        Ipv4AddressCidr::new(Ipv4Address::new(addr), subnet_len)
+    }
+
+    #[test]
+    fn test_filter_first_match_wins() {
+        let mut filter = Filter::new(Action::Accept);
+        filter.push(Rule {
+            src_addr: Some(cidr([10, 0, 0, 2], 32)),
+            ..Rule::new(Action::Accept)
+        });
+        filter.push(Rule {
+            src_addr: Some(cidr([10, 0, 0, 0], 24)),
+            dst_ports: Some(53 ..= 53),
+            ..Rule::new(Action::Drop)
+        });
+
+        let packet = udp([10, 0, 0, 2], [10, 0, 0, 1], 53);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Accept);
+        let packet = udp([10, 0, 0, 3], [10, 0, 0, 1], 53);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Drop);
+        let packet = udp([10, 0, 0, 3], [10, 0, 0, 1], 54);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Accept);
+        let packet = udp([10, 0, 1, 3], [10, 0, 0, 1], 53);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Accept);
+
+        let stats: Vec<_> = filter.rules().map(|(_, stats)| stats.packets).collect();
+        assert_eq!(stats, vec![1, 1]);
+        assert_eq!(filter.default_stats().packets, 2);
+        assert_eq!(filter.default_stats().bytes, 2 * packet.len() as u64);
+    }
+
+    #[test]
+    fn test_filter_direction_and_protocol() {
+        let mut filter = Filter::new(Action::Accept);
+        filter.push(Rule {
+            direction: Some(Direction::Sent),
+            protocol: Some(Ipv4Protocol::UDP),
+            ..Rule::new(Action::Reject)
+        });
+
+        let packet = udp([10, 0, 0, 1], [10, 0, 0, 2], 53);
+        assert_eq!(filter.evaluate(Direction::Sent, &packet), Action::Reject);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Accept);
+        assert_eq!(filter.evaluate(Direction::Sent, &tcp(53, false, false)), Action::Accept);
+    }
+
+    #[test]
+    fn test_filter_tcp_flags() {
+        let mut filter = Filter::new(Action::Accept);
+        filter.push(Rule {
+            dst_ports: Some(1 ..= 1023),
+            tcp_flags: Some((tcp_flags::SYN | tcp_flags::ACK, tcp_flags::SYN)),
+            ..Rule::new(Action::Drop)
+        });
+
+        assert_eq!(filter.evaluate(Direction::Received, &tcp(80, true, false)), Action::Drop);
+        assert_eq!(filter.evaluate(Direction::Received, &tcp(80, true, true)), Action::Accept);
+        assert_eq!(filter.evaluate(Direction::Received, &tcp(8080, true, false)), Action::Accept);
+
+        // Flag matches never apply to UDP.
+        let packet = udp([10, 0, 0, 2], [10, 0, 0, 1], 80);
+        assert_eq!(filter.evaluate(Direction::Received, &packet), Action::Accept);
+    }
+}
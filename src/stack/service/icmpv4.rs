@@ -0,0 +1,413 @@
+use core::cmp::min;
+use core::mem::swap;
+use core::time::Duration;
+
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::repr::{
+    ipv4_flags,
+    ipv4_protocols,
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Icmpv4Packet,
+    Icmpv4Repr,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+};
+use stack::service::{
+    ipv4,
+    Interface,
+};
+use stack::socket::{
+    SocketAddr,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+};
+use {
+    Error,
+    Result,
+};
+
+/// Send an ICMP packet via the interface.
+pub fn send_packet<F>(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    icmp_repr: &Icmpv4Repr,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    interface.stack_stats.icmp.out_msgs += 1;
+    match icmp_repr.message {
+        Icmpv4Message::DestinationUnreachable(_) => {
+            interface.stack_stats.icmp.out_dest_unreachs += 1
+        }
+        Icmpv4Message::EchoReply { .. } => interface.stack_stats.icmp.out_echo_reps += 1,
+        _ => {}
+    }
+
+    ipv4::send_packet_with_repr(interface, &ipv4_repr, |ipv4_payload| {
+        let mut icmp_packet = Icmpv4Packet::try_new(ipv4_payload).unwrap();
+        icmp_repr.serialize(&mut icmp_packet).unwrap();
+        f(icmp_packet.payload_mut());
+        icmp_packet.fill_checksum();
+    })
+}
+
+/// Sends an ICMP error message in response to a received IPv4 packet.
+///
+/// The error carries the IP header and first 8 bytes of payload of the
+/// original packet. This may be used for Time Exceeded, Parameter Problem,
+/// Destination Unreachable, etc. errors. No error is sent in response to ICMP
+/// errors, packets to broadcast or multicast addresses, or fragments other
+/// than the first, see RFC 1122. Errors are subject to the rate limiter of
+/// the interface.
+pub fn send_error(
+    interface: &mut Interface,
+    message: Icmpv4Message,
+    ipv4_buffer: &[u8],
+) -> Result<()> {
+    let ipv4_packet = Ipv4Packet::try_new(ipv4_buffer)?;
+    let header_len = (ipv4_packet.header_len() * 4) as usize;
+    let dst_addr = ipv4_packet.dst_addr();
+
+    let is_icmp_error = ipv4_packet.protocol() == ipv4_protocols::ICMP
+        && ipv4_buffer.len() > header_len
+        && ipv4_buffer[header_len] != 0
+        && ipv4_buffer[header_len] != 8;
+
+    if is_icmp_error
+        || ipv4_packet.fragment_offset() != 0
+        || dst_addr.is_multicast()
+        || ipv4::is_broadcast(interface, dst_addr)
+    {
+        debug!("Not sending ICMP {:?} for packet to {}.", message, dst_addr);
+        return Err(Error::Ignored);
+    }
+
+    if !interface.icmpv4_rate_limiter.try_acquire() {
+        debug!("Not sending ICMP {:?}, rate limit exceeded.", message);
+        return Err(Error::Ignored);
+    }
+
+    let copy_len = min(ipv4_buffer.len(), header_len + 8);
+
+    let icmp_repr = Icmpv4Repr {
+        message,
+        payload_len: copy_len,
+    };
+    let ipv4_repr = Ipv4Repr {
+        src_addr: *interface.ipv4_addr,
+        dst_addr: ipv4_packet.src_addr(),
+        protocol: Ipv4Protocol::ICMP,
+        payload_len: icmp_repr.buffer_len() as u16,
+        ttl: Ipv4Repr::DEFAULT_TTL,
+        dscp: 0,
+        flags: ipv4_flags::DONT_FRAGMENT,
+        options: Ipv4Options::new(),
+    };
+
+    send_packet(interface, &ipv4_repr, &icmp_repr, |payload| {
+        payload.copy_from_slice(&ipv4_buffer[.. copy_len]);
+    })
+}
+
+/// Receives an ICMP packet from an interface.
+///
+/// This may result in a response to ICMP echo requests, updates to sockets
+/// for errors, etc.
+pub fn recv_packet(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    icmp_buffer: &[u8],
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    interface.stack_stats.icmp.in_msgs += 1;
+
+    let (icmp_recv_packet, icmp_recv_repr) = match parse_packet(icmp_buffer) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            interface.stack_stats.icmp.in_errors += 1;
+            return Err(err);
+        }
+    };
+
+    match icmp_recv_repr.message {
+        Icmpv4Message::DestinationUnreachable(_) => {
+            interface.stack_stats.icmp.in_dest_unreachs += 1
+        }
+        Icmpv4Message::EchoRequest { .. } => interface.stack_stats.icmp.in_echos += 1,
+        _ => {}
+    }
+
+    socket_set
+        .iter_mut()
+        .filter_map(|socket| match *socket {
+            TaggedSocket::Icmp(ref mut socket) => {
+                if socket.accepts(&icmp_recv_repr, icmp_recv_packet.payload()) {
+                    Some(socket)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        })
+        .for_each(|socket| {
+            if let Err(err) =
+                socket.recv_enqueue(ipv4_repr, &icmp_recv_repr, icmp_recv_packet.payload())
+            {
+                debug!(
+                    "Error enqueueing ICMP packet for receiving via socket with {:?}.",
+                    err
+                );
+            }
+        });
+
+    let (ipv4_send_repr, icmp_send_repr) = match icmp_recv_repr.message {
+        Icmpv4Message::EchoRequest { .. } if ipv4_repr.dst_addr != *interface.ipv4_addr => {
+            debug!(
+                "Ignoring ping from {} to {}.",
+                ipv4_repr.src_addr, ipv4_repr.dst_addr
+            );
+            return Err(Error::Ignored);
+        }
+        Icmpv4Message::EchoRequest { id, seq } => {
+            debug!(
+                "Got a ping from {}; Sending response...",
+                ipv4_repr.src_addr
+            );
+            let mut ipv4_send_repr = ipv4_repr.clone();
+            swap(&mut ipv4_send_repr.src_addr, &mut ipv4_send_repr.dst_addr);
+            ipv4_send_repr.ttl = Ipv4Repr::DEFAULT_TTL;
+            ipv4_send_repr.flags = ipv4_flags::DONT_FRAGMENT;
+            ipv4_send_repr.options = Ipv4Options::new();
+            (
+                ipv4_send_repr,
+                Icmpv4Repr {
+                    message: Icmpv4Message::EchoReply { id, seq },
+                    payload_len: icmp_recv_repr.payload_len,
+                },
+            )
+        }
+        Icmpv4Message::DestinationUnreachable(
+            Icmpv4DestinationUnreachable::FragmentationNeeded { next_hop_mtu },
+        ) => {
+            return recv_fragmentation_needed(next_hop_mtu, icmp_recv_packet.payload(), socket_set);
+        }
+        Icmpv4Message::DestinationUnreachable(_) | Icmpv4Message::TimeExceeded(_) => {
+            return recv_transport_error(&icmp_recv_repr, icmp_recv_packet.payload(), socket_set);
+        }
+        _ => return Err(Error::Ignored),
+    };
+
+    send_packet(interface, &ipv4_send_repr, &icmp_send_repr, |payload| {
+        payload.copy_from_slice(icmp_recv_packet.payload());
+    })
+}
+
+/// Parses and validates an ICMP packet.
+fn parse_packet(icmp_buffer: &[u8]) -> Result<(Icmpv4Packet<&[u8]>, Icmpv4Repr)> {
+    let icmp_packet = Icmpv4Packet::try_new(icmp_buffer)?;
+    icmp_packet.check_encoding()?;
+    let icmp_repr = Icmpv4Repr::deserialize(&icmp_packet)?;
+    Ok((icmp_packet, icmp_repr))
+}
+
+/// Returns the transport protocol and (source, destination) socket addresses
+/// of the original packet quoted by an ICMP error.
+fn original_socket_addrs(original: &[u8]) -> Result<(u8, SocketAddr, SocketAddr)> {
+    // The original packet is truncated, so read the ports by hand.
+    let ipv4_packet = Ipv4Packet::try_new(original)?;
+    let header_len = (ipv4_packet.header_len() * 4) as usize;
+    if original.len() < header_len + 4 {
+        return Err(Error::Ignored);
+    }
+
+    let src_addr = SocketAddr {
+        addr: ipv4_packet.src_addr(),
+        port: NetworkEndian::read_u16(&original[header_len .. header_len + 2]),
+    };
+    let dst_addr = SocketAddr {
+        addr: ipv4_packet.dst_addr(),
+        port: NetworkEndian::read_u16(&original[header_len + 2 .. header_len + 4]),
+    };
+
+    Ok((ipv4_packet.protocol(), src_addr, dst_addr))
+}
+
+/// Delivers an ICMP error to any UDP or TCP sockets owning the flow of the
+/// original packet.
+fn recv_transport_error(
+    icmp_repr: &Icmpv4Repr,
+    original: &[u8],
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let (protocol, src_addr, dst_addr) = original_socket_addrs(original)?;
+
+    debug!(
+        "Got ICMP {:?} for packet from {} to {}.",
+        icmp_repr.message, src_addr, dst_addr
+    );
+
+    for socket in socket_set.iter_mut() {
+        let result = match *socket {
+            TaggedSocket::Udp(ref mut socket) if protocol == ipv4_protocols::UDP => {
+                socket.recv_icmp_error(&src_addr, &dst_addr, icmp_repr)
+            }
+            TaggedSocket::Tcp(ref mut socket) if protocol == ipv4_protocols::TCP => {
+                socket.recv_icmp_error(&src_addr, &dst_addr, icmp_repr)
+            }
+            _ => continue,
+        };
+
+        if result.is_ok() {
+            debug!("Delivered ICMP {:?} to socket.", icmp_repr.message);
+        }
+    }
+
+    Ok(())
+}
+
+/// Feeds the MTU reported by an ICMP Fragmentation Needed message to any TCP
+/// sockets with a connection matching the original packet.
+fn recv_fragmentation_needed(
+    next_hop_mtu: u16,
+    original: &[u8],
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let (protocol, src_addr, dst_addr) = original_socket_addrs(original)?;
+    if protocol != ipv4_protocols::TCP {
+        return Err(Error::Ignored);
+    }
+
+    debug!(
+        "Path MTU to {} is {} according to ICMP Fragmentation Needed.",
+        dst_addr.addr, next_hop_mtu
+    );
+
+    for socket in socket_set.iter_mut() {
+        match *socket {
+            TaggedSocket::Tcp(ref mut socket) => {
+                socket.update_path_mtu(&src_addr, &dst_addr, next_hop_mtu as usize)
+            }
+            TaggedSocket::TcpListener(ref mut socket) => {
+                socket.update_path_mtu(&src_addr, &dst_addr, next_hop_mtu as usize)
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// A token bucket limiting the rate at which ICMP errors are sent, see RFC
+/// 1812 section 4.3.2.8.
+///
+/// The bucket holds up to burst tokens and is refilled at rate_per_sec
+/// tokens per second. Each error sent consumes one token.
+#[derive(Debug)]
+pub struct RateLimiter<T = DefaultEnv>
+where
+    T: Env,
+{
+    rate_per_sec: u32,
+    burst: u32,
+    tokens: u32,
+    refilled_at: Instant,
+    time_env: T,
+}
+
+impl<T: Env> RateLimiter<T> {
+    /// Creates a rate limiter with a full bucket.
+    pub fn new(rate_per_sec: u32, burst: u32, time_env: T) -> RateLimiter<T> {
+        RateLimiter {
+            rate_per_sec,
+            burst,
+            tokens: burst,
+            refilled_at: time_env.now_instant(),
+            time_env,
+        }
+    }
+
+    /// Changes the refill rate and bucket size.
+    pub fn set_rate(&mut self, rate_per_sec: u32, burst: u32) {
+        self.rate_per_sec = rate_per_sec;
+        self.burst = burst;
+        self.tokens = min(self.tokens, burst);
+    }
+
+    /// Tries to consume a token, returning false if the bucket is empty.
+    pub fn try_acquire(&mut self) -> bool {
+        self.refill();
+
+        if self.tokens == 0 {
+            false
+        } else {
+            self.tokens -= 1;
+            true
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = self.time_env.now_instant();
+        if self.rate_per_sec == 0 {
+            self.refilled_at = now;
+            return;
+        }
+
+        // Only advance by whole tokens so partial progress is not lost.
+        let token_nanos = 1_000_000_000 / self.rate_per_sec as u64;
+        let elapsed = now - self.refilled_at;
+        let elapsed_nanos = elapsed.as_secs() * 1_000_000_000 + elapsed.subsec_nanos() as u64;
+        let new_tokens = elapsed_nanos / token_nanos;
+
+        if self.tokens as u64 + new_tokens >= self.burst as u64 {
+            self.tokens = self.burst;
+            self.refilled_at = now;
+        } else {
+            self.tokens += new_tokens as u32;
+            self.refilled_at += Duration::from_nanos(new_tokens * token_nanos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    #[test]
+    fn test_rate_limiter_burst_and_refill() {
+        let mut rate_limiter = RateLimiter::new(10, 3, MockEnv::new());
+
+        for _ in 0 .. 3 {
+            assert!(rate_limiter.try_acquire());
+        }
+        assert!(!rate_limiter.try_acquire());
+
+        rate_limiter.time_env.now += Duration::from_millis(150);
+        assert!(rate_limiter.try_acquire());
+        assert!(!rate_limiter.try_acquire());
+
+        // The remaining 50ms count towards the next token.
+        rate_limiter.time_env.now += Duration::from_millis(50);
+        assert!(rate_limiter.try_acquire());
+
+        rate_limiter.time_env.now += Duration::from_secs(10);
+        for _ in 0 .. 3 {
+            assert!(rate_limiter.try_acquire());
+        }
+        assert!(!rate_limiter.try_acquire());
+    }
+}
@@ -0,0 +1,85 @@
+use stack::repr::{
+    Icmpv6Message,
+    Icmpv6Packet,
+    Icmpv6Repr,
+    Ipv6Repr,
+};
+use stack::service::{
+    ipv6,
+    ndp,
+    Interface,
+};
+use {
+    Error,
+    Result,
+};
+
+/// Sends an ICMPv6 packet via an interface.
+///
+/// The provided closure fills in the payload, which for Neighbor Discovery
+/// messages has already been written by the ICMPv6 header.
+pub fn send_packet<F>(
+    interface: &mut Interface,
+    ipv6_repr: &Ipv6Repr,
+    icmp_repr: &Icmpv6Repr,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    ipv6::send_packet_with_repr(interface, ipv6_repr, |ipv6_payload| {
+        let mut icmp_packet = Icmpv6Packet::try_new(ipv6_payload).unwrap();
+        icmp_repr.serialize(&mut icmp_packet).unwrap();
+        f(icmp_packet.payload_mut());
+        icmp_packet.fill_checksum(ipv6_repr);
+    })
+}
+
+/// Receives an ICMPv6 packet from an interface.
+///
+/// This may result in a response to echo requests, Neighbor Discovery
+/// updates, etc.
+pub fn recv_packet(
+    interface: &mut Interface,
+    ipv6_repr: &Ipv6Repr,
+    icmp_buffer: &[u8],
+) -> Result<()> {
+    let icmp_recv_packet = Icmpv6Packet::try_new(icmp_buffer)?;
+    icmp_recv_packet.check_encoding(ipv6_repr)?;
+
+    let icmp_recv_repr = Icmpv6Repr::deserialize(&icmp_recv_packet)?;
+
+    if icmp_recv_repr.message.is_ndp() {
+        return ndp::recv_message(interface, ipv6_repr, &icmp_recv_repr);
+    }
+
+    match icmp_recv_repr.message {
+        Icmpv6Message::EchoRequest { id, seq } if ipv6_repr.dst_addr == *interface.ipv6_addr => {
+            debug!(
+                "Got a ping from {}; Sending response...",
+                ipv6_repr.src_addr
+            );
+            let ipv6_send_repr = Ipv6Repr {
+                src_addr: ipv6_repr.dst_addr,
+                dst_addr: ipv6_repr.src_addr,
+                hop_limit: Ipv6Repr::DEFAULT_HOP_LIMIT,
+                flow_label: 0,
+                ..*ipv6_repr
+            };
+            let icmp_send_repr = Icmpv6Repr {
+                message: Icmpv6Message::EchoReply { id, seq },
+                payload_len: icmp_recv_repr.payload_len,
+            };
+            send_packet(interface, &ipv6_send_repr, &icmp_send_repr, |payload| {
+                payload.copy_from_slice(icmp_recv_packet.payload());
+            })
+        }
+        _ => {
+            debug!(
+                "Ignoring ICMPv6 message {:?} from {}.",
+                icmp_recv_repr.message, ipv6_repr.src_addr
+            );
+            Err(Error::Ignored)
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use alloc::vec::Vec;
+
+use stack::repr::{
+    ipv4_flags,
+    IgmpMessage,
+    IgmpPacket,
+    IgmpRepr,
+    Ipv4Address,
+    Ipv4Options,
+    Ipv4Protocol,
+    Ipv4Repr,
+};
+use stack::service::{
+    ipv4,
+    Interface,
+};
+use {
+    AddressError,
+    Error,
+    Result,
+};
+
+/// Send an IGMP message via the interface.
+///
+/// IGMP messages are sent with a TTL of 1 so they never leave the link.
+pub fn send_packet(
+    interface: &mut Interface,
+    dst_addr: Ipv4Address,
+    igmp_repr: &IgmpRepr,
+) -> Result<()> {
+    let ipv4_repr = Ipv4Repr {
+        src_addr: *interface.ipv4_addr,
+        dst_addr,
+        protocol: Ipv4Protocol::IGMP,
+        payload_len: igmp_repr.buffer_len() as u16,
+        ttl: 1,
+        dscp: 0,
+        flags: ipv4_flags::DONT_FRAGMENT,
+        options: Ipv4Options::new(),
+    };
+
+    ipv4::send_packet_with_repr(interface, &ipv4_repr, |ipv4_payload| {
+        let mut igmp_packet = IgmpPacket::try_new(ipv4_payload).unwrap();
+        igmp_repr.serialize(&mut igmp_packet).unwrap();
+        igmp_packet.fill_checksum();
+    })
+}
+
+/// Joins an IPv4 multicast group on the interface and sends a membership
+/// report for the group.
+pub fn join_multicast_group(interface: &mut Interface, group_addr: Ipv4Address) -> Result<()> {
+    if !group_addr.is_multicast() {
+        return Err(Error::Address(AddressError::InvalidAddress(group_addr)));
+    }
+
+    if interface.ipv4_multicast_groups.insert(group_addr) {
+        debug!("Joining multicast group {}.", group_addr);
+    }
+
+    send_report(interface, group_addr)
+}
+
+/// Leaves an IPv4 multicast group on the interface and notifies multicast
+/// routers with a leave group message.
+pub fn leave_multicast_group(interface: &mut Interface, group_addr: Ipv4Address) -> Result<()> {
+    if !interface.ipv4_multicast_groups.remove(&group_addr) {
+        return Err(Error::Ignored);
+    }
+
+    debug!("Leaving multicast group {}.", group_addr);
+
+    let igmp_repr = IgmpRepr {
+        message: IgmpMessage::LeaveGroup { group_addr },
+    };
+    send_packet(interface, Ipv4Address::ALL_ROUTERS, &igmp_repr)
+}
+
+/// Checks if the interface is a member of an IPv4 multicast group.
+pub fn is_member(interface: &Interface, group_addr: Ipv4Address) -> bool {
+    group_addr == Ipv4Address::ALL_HOSTS || interface.ipv4_multicast_groups.contains(&group_addr)
+}
+
+/// Receives an IGMP packet from an interface.
+///
+/// Membership queries are answered immediately with a report for each
+/// matching group rather than after a random delay.
+pub fn recv_packet(interface: &mut Interface, igmp_buffer: &[u8]) -> Result<()> {
+    let igmp_packet = IgmpPacket::try_new(igmp_buffer)?;
+    igmp_packet.check_encoding()?;
+
+    let igmp_repr = IgmpRepr::deserialize(&igmp_packet)?;
+
+    let group_addrs: Vec<_> = match igmp_repr.message {
+        IgmpMessage::MembershipQuery { group_addr, .. } => interface
+            .ipv4_multicast_groups
+            .iter()
+            .cloned()
+            .filter(|addr| group_addr == Ipv4Address::new([0, 0, 0, 0]) || group_addr == *addr)
+            .collect(),
+        _ => return Err(Error::Ignored),
+    };
+
+    for group_addr in group_addrs {
+        send_report(interface, group_addr)?;
+    }
+
+    Ok(())
+}
+
+fn send_report(interface: &mut Interface, group_addr: Ipv4Address) -> Result<()> {
+    let igmp_repr = IgmpRepr {
+        message: IgmpMessage::MembershipReport { group_addr },
+    };
+    send_packet(interface, group_addr, &igmp_repr)
+}
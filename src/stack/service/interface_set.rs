@@ -0,0 +1,153 @@
+use alloc::vec::Vec;
+use core::slice::IterMut as SliceIterMut;
+
+use stack::repr::{
+    Ipv4Address,
+    Ipv4AddressCidr,
+};
+use stack::service::{
+    Interface,
+    StackStats,
+};
+
+/// A route directing packets for a subnet out of an interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InterfaceRoute {
+    pub subnet: Ipv4AddressCidr,
+    pub interface: usize,
+}
+
+/// A set of interfaces with stable integral handles, serviced together by
+/// socket::send_all(...) and socket::recv_all(...).
+///
+/// Like ipv4::ipv4_addr_route(...), packets are sent via the interface whose
+/// subnet contains their destination, else the interface of the most specific
+/// route for the destination, else the default interface.
+pub struct InterfaceSet {
+    interfaces: Vec<Interface>,
+    routes: Vec<InterfaceRoute>,
+    default_interface: usize,
+}
+
+impl InterfaceSet {
+    /// Creates an empty interface set.
+    pub fn new() -> InterfaceSet {
+        InterfaceSet {
+            interfaces: Vec::new(),
+            routes: Vec::new(),
+            default_interface: 0,
+        }
+    }
+
+    /// Adds an interface and returns a stable handle. The first interface
+    /// added is the default interface.
+    pub fn add_interface(&mut self, interface: Interface) -> usize {
+        self.interfaces.push(interface);
+        self.interfaces.len() - 1
+    }
+
+    /// Returns a reference to an interface with the specified handle. Causes
+    /// a panic if the handle is not in use.
+    pub fn interface(&mut self, interface_handle: usize) -> &mut Interface {
+        match self.interfaces.get_mut(interface_handle) {
+            Some(interface) => interface,
+            None => panic!("Interface handle is not in use."),
+        }
+    }
+
+    /// Returns the MIB-II style counters of all interfaces in the set combined.
+    pub fn stack_stats(&self) -> StackStats {
+        let mut stack_stats = StackStats::default();
+        for interface in &self.interfaces {
+            stack_stats.merge(interface.stack_stats());
+        }
+        stack_stats
+    }
+
+    /// Returns the number of interfaces in the set.
+    pub fn count(&self) -> usize {
+        self.interfaces.len()
+    }
+
+    /// Returns an iterator over all of the interfaces in the set, in order of
+    /// their handles.
+    pub fn iter_mut(&mut self) -> SliceIterMut<'_, Interface> {
+        self.interfaces.iter_mut()
+    }
+
+    /// Sets the interface for packets which match no route or interface
+    /// subnet, e.g. the interface with a default gateway.
+    pub fn set_default_interface(&mut self, interface_handle: usize) {
+        self.default_interface = interface_handle;
+    }
+
+    /// Adds a route sending packets destined to a subnet via an interface,
+    /// replacing any existing route for the same subnet.
+    pub fn add_route(&mut self, subnet: Ipv4AddressCidr, interface_handle: usize) {
+        let subnet = Ipv4AddressCidr::new(subnet.network(), subnet.subnet_len());
+        self.routes.retain(|route| route.subnet != subnet);
+        self.routes.push(InterfaceRoute {
+            subnet,
+            interface: interface_handle,
+        });
+    }
+
+    /// Removes the route for a subnet, returning the interface of the route if
+    /// there was one.
+    pub fn remove_route(&mut self, subnet: Ipv4AddressCidr) -> Option<usize> {
+        let subnet = Ipv4AddressCidr::new(subnet.network(), subnet.subnet_len());
+        let position = self
+            .routes
+            .iter()
+            .position(|route| route.subnet == subnet)?;
+        Some(self.routes.remove(position).interface)
+    }
+
+    /// Returns the handle of the interface packets to an address should be
+    /// sent via, or None if the set is empty.
+    pub fn route(&self, address: Ipv4Address) -> Option<usize> {
+        let link = self
+            .interfaces
+            .iter()
+            .enumerate()
+            .filter(|&(_, interface)| interface.ipv4_addr.is_member(address))
+            .max_by_key(|&(_, interface)| interface.ipv4_addr.subnet_len())
+            .map(|(i, _)| i);
+
+        let route = || {
+            self.routes
+                .iter()
+                .filter(|route| route.subnet.is_member(address))
+                .max_by_key(|route| route.subnet.subnet_len())
+                .map(|route| route.interface)
+        };
+
+        link.or_else(route).or_else(|| self.default_interface())
+    }
+
+    /// Returns the handle of the default interface, or None if the set is
+    /// empty.
+    pub fn default_interface(&self) -> Option<usize> {
+        if self.default_interface < self.interfaces.len() {
+            Some(self.default_interface)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the largest MTU of the interfaces, e.g. for sizing socket
+    /// buffers.
+    pub fn max_transmission_unit(&self) -> usize {
+        self.interfaces
+            .iter()
+            .map(|interface| interface.dev.max_transmission_unit())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Default for InterfaceSet {
+    fn default() -> InterfaceSet {
+        InterfaceSet::new()
+    }
+}
@@ -0,0 +1,788 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::time::Duration;
+
+use stack::capture::Direction;
+use stack::random::random;
+use stack::repr::{
+    eth_types,
+    ipv4_flags,
+    ipv4_protocols,
+    ppp_protocols,
+    EthernetAddress,
+    EthernetFrame,
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Icmpv4TimeExceeded,
+    Ipv4Address,
+    Ipv4Packet,
+    Ipv4Repr,
+};
+use stack::service::filter::Action;
+use stack::service::{
+    arp,
+    ethernet,
+    icmpv4,
+    igmp,
+    pppoe,
+    shim,
+    tcp,
+    udp,
+    Interface,
+};
+use stack::socket::{
+    RawType,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    TimerQueue,
+};
+use {
+    AddressError,
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Send a raw IPv4 packet via the interface.
+///
+/// The appropriate Ethernet destination address will be inferred by the
+/// network stack, but the callers is responsible for writing an entire well
+/// formatted IPv4 packets to the provided buffer, NOT just the payload! If the
+/// Ethernet address of the next hop is not known yet, the packet is buffered
+/// until it is resolved via ARP.
+///
+/// Packets the interface filter drops or rejects are discarded, without
+/// failing so senders don't retry them, and accepted packets are recorded by
+/// the connection tracker.
+pub fn send_packet_raw<F>(
+    interface: &mut Interface,
+    dst_addr: Ipv4Address,
+    ipv4_packet_len: usize,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    if interface.filter.is_none() && interface.conntrack.is_none() {
+        return send_packet_unfiltered(interface, dst_addr, ipv4_packet_len, f);
+    }
+
+    let mut ipv4_buffer = vec![0; ipv4_packet_len];
+    f(&mut ipv4_buffer[..]);
+
+    let action = filter_packet(interface, Direction::Sent, &ipv4_buffer[..]);
+
+    match action {
+        Action::Accept => send_packet_unfiltered(interface, dst_addr, ipv4_packet_len, |buffer| {
+            buffer.copy_from_slice(&ipv4_buffer[..])
+        }),
+        Action::Drop | Action::Reject => {
+            debug!("Filter discarded IPv4 packet to {} with {:?}.", dst_addr, action);
+            interface.stats.tx_filtered += 1;
+            Ok(())
+        }
+    }
+}
+
+/// Evaluates the interface filter for a packet sent or received via the
+/// interface, passing it the state of the packet's connection, and records
+/// the packet with the connection tracker if accepted.
+fn filter_packet(interface: &mut Interface, direction: Direction, ipv4_buffer: &[u8]) -> Action {
+    let ipv4_packet = Ipv4Packet::try_new(ipv4_buffer).ok();
+
+    let state = match (interface.conntrack.as_ref(), ipv4_packet.as_ref()) {
+        (Some(conntrack), Some(ipv4_packet)) => conntrack.state(ipv4_packet),
+        _ => None,
+    };
+
+    let action = match interface.filter {
+        Some(ref mut filter) => filter.evaluate_with_state(direction, state, ipv4_buffer),
+        None => Action::Accept,
+    };
+
+    if action == Action::Accept {
+        if let (Some(conntrack), Some(ipv4_packet)) = (interface.conntrack.as_mut(), ipv4_packet) {
+            conntrack.track(direction, &ipv4_packet);
+        }
+    }
+
+    action
+}
+
+/// Sends a raw IPv4 packet via the interface, bypassing the filter and
+/// connection tracker. Packets are encapsulated in the interface's PPPoE
+/// session, if any.
+fn send_packet_unfiltered<F>(
+    interface: &mut Interface,
+    dst_addr: Ipv4Address,
+    ipv4_packet_len: usize,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    if interface.pppoe_session.is_some() {
+        return pppoe::send_packet(interface, ppp_protocols::IPV4, ipv4_packet_len, f);
+    }
+
+    let eth_dst_addr = if is_broadcast(interface, dst_addr) {
+        EthernetAddress::BROADCAST
+    } else if dst_addr.is_multicast() {
+        ethernet::multicast_addr(dst_addr)
+    } else {
+        let dst_addr = ipv4_addr_route(interface, dst_addr);
+        match arp::eth_addr_for_ip(interface, dst_addr) {
+            Ok(eth_addr) => eth_addr,
+            Err(Error::Address(AddressError::MacResolution(_))) => {
+                interface.stats.tx_arp_miss += 1;
+                let mut ipv4_buffer = vec![0; ipv4_packet_len];
+                f(&mut ipv4_buffer[..]);
+                return arp::enqueue_packet(interface, dst_addr, ipv4_buffer);
+            }
+            Err(err) => return Err(err),
+        }
+    };
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(ipv4_packet_len);
+
+    ethernet::send_frame(interface, eth_frame_len, |eth_frame| {
+        eth_frame.set_dst_addr(eth_dst_addr);
+        eth_frame.set_payload_type(eth_types::IPV4);
+        f(eth_frame.payload_mut());
+    })
+}
+
+/// Sends an IPv4 packet via ther interface.
+///
+/// This is a "safe" version of send_packet_raw(...) which takes care of
+/// serializing a header, calculating a checksum, etc. so the caller needs to
+/// fill in **only** the payload in the provided buffer. Packets which exceed
+/// the interface MTU are split into fragments.
+pub fn send_packet_with_repr<F>(interface: &mut Interface, ipv4_repr: &Ipv4Repr, f: F) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    let (dst_addr, ipv4_packet_len) = (ipv4_repr.dst_addr, ipv4_repr.buffer_len());
+
+    interface.stack_stats.ip.out_requests += 1;
+
+    let max_packet_len = max_packet_len(interface);
+
+    if ipv4_packet_len > max_packet_len {
+        if ipv4_repr.flags & ipv4_flags::DONT_FRAGMENT != 0 {
+            debug!(
+                "Dropping IPv4 packet to {} exceeding the MTU with DF set.",
+                dst_addr
+            );
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        let mut payload = vec![0; ipv4_repr.payload_len as usize];
+        f(&mut payload[..]);
+        return send_fragments(interface, ipv4_repr, &payload[..], max_packet_len);
+    }
+
+    let checksum_offload = interface.dev.capabilities().checksum;
+
+    send_packet_raw(interface, dst_addr, ipv4_packet_len, |ipv4_buffer| {
+        let mut ipv4_packet = Ipv4Packet::try_new(ipv4_buffer).unwrap();
+        // NOTE: It's important to serialize the Ipv4Repr prior to calling payload_mut()
+        // to ensure the header length is written and used when finding where the
+        // payload is located in the packet!
+        ipv4_repr.serialize_with_checksum(&mut ipv4_packet, !checksum_offload.ipv4);
+        f(ipv4_packet.payload_mut());
+    })
+}
+
+/// Returns the size of the largest IPv4 packet which may be sent without
+/// fragmenting.
+fn max_packet_len(interface: &Interface) -> usize {
+    let overhead = match interface.pppoe_session {
+        Some(_) => pppoe::SESSION_OVERHEAD,
+        None => 0,
+    };
+
+    interface.dev.max_transmission_unit()
+        - EthernetFrame::<&[u8]>::HEADER_LEN
+        - overhead
+        - shim::overhead(interface)
+}
+
+/// Checks if an IPv4 packet exceeds the interface MTU and will be sent as a
+/// series of fragments.
+///
+/// Devices only offload TCP/UDP checksums for unfragmented packets, so the
+/// checksums of fragmented packets must still be computed in software.
+pub fn is_fragmented(interface: &Interface, ipv4_repr: &Ipv4Repr) -> bool {
+    ipv4_repr.buffer_len() > max_packet_len(interface)
+}
+
+/// Sends an IPv4 payload as a series of fragments no larger than
+/// max_packet_len bytes.
+fn send_fragments(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    payload: &[u8],
+    max_packet_len: usize,
+) -> Result<()> {
+    // Fragment offsets are in units of 8 bytes, so all but the last fragment
+    // must carry a multiple of 8 bytes.
+    let fragment_len = (max_packet_len - ipv4_repr.header_len()) & !0x07;
+    let identification = random::<u16>();
+    let checksum_offload = interface.dev.capabilities().checksum;
+
+    debug!(
+        "Fragmenting IPv4 packet to {} with {} bytes payload.",
+        ipv4_repr.dst_addr,
+        payload.len()
+    );
+
+    for (i, fragment) in payload.chunks(fragment_len).enumerate() {
+        let offset = i * fragment_len;
+        let is_last = offset + fragment.len() == payload.len();
+        let fragment_repr = Ipv4Repr {
+            payload_len: fragment.len() as u16,
+            flags: if is_last { 0 } else { ipv4_flags::NOT_LAST },
+            // Only options with the copied flag are repeated after the first
+            // fragment.
+            options: if offset == 0 {
+                ipv4_repr.options
+            } else {
+                ipv4_repr.options.copied()
+            },
+            ..*ipv4_repr
+        };
+
+        send_packet_raw(
+            interface,
+            ipv4_repr.dst_addr,
+            fragment_repr.buffer_len(),
+            |ipv4_buffer| {
+                let mut ipv4_packet = Ipv4Packet::try_new(ipv4_buffer).unwrap();
+                fragment_repr.serialize_with_checksum(&mut ipv4_packet, false);
+                ipv4_packet.set_identification(identification);
+                ipv4_packet.set_fragment_offset((offset / 8) as u16);
+                if !checksum_offload.ipv4 {
+                    ipv4_packet.fill_checksum();
+                }
+                ipv4_packet.payload_mut().copy_from_slice(fragment);
+            },
+        )?;
+        interface.stack_stats.ip.frag_creates += 1;
+    }
+
+    Ok(())
+}
+
+/// Receives an IPv4 packet from an interface.
+///
+/// The IPv4 packet is parsed, reassembled if it is a fragment, forwarded to
+/// any sockets, and propagated up the network stack.
+pub fn recv_packet(
+    interface: &mut Interface,
+    eth_frame: &EthernetFrame<&[u8]>,
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    recv_packet_raw(
+        interface,
+        Some(eth_frame.src_addr()),
+        eth_frame.payload(),
+        socket_set,
+    )
+}
+
+/// Receives a raw IPv4 packet from an interface, e.g. after decapsulating it
+/// from a PPPoE session frame, along with the Ethernet address of the sender
+/// if it is on the link.
+pub fn recv_packet_raw(
+    interface: &mut Interface,
+    eth_src_addr: Option<EthernetAddress>,
+    ipv4_buffer: &[u8],
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    interface.stack_stats.ip.in_receives += 1;
+
+    let ipv4_packet = match Ipv4Packet::try_new(ipv4_buffer) {
+        Ok(ipv4_packet) => ipv4_packet,
+        Err(err) => {
+            interface.stack_stats.ip.in_hdr_errors += 1;
+            return Err(err);
+        }
+    };
+    match ipv4_packet.check_encoding() {
+        Ok(_) => {}
+        Err(Error::Parse(ParseError::Malformed)) => {
+            interface.stack_stats.ip.in_hdr_errors += 1;
+            send_parameter_problem(interface, &ipv4_packet);
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+        Err(err) => {
+            interface.stack_stats.ip.in_hdr_errors += 1;
+            return Err(err);
+        }
+    }
+
+    if ipv4_packet.dst_addr() != *interface.ipv4_addr
+        && !is_broadcast(interface, ipv4_packet.dst_addr())
+        && !igmp::is_member(interface, ipv4_packet.dst_addr())
+    {
+        interface.stack_stats.ip.in_addr_errors += 1;
+        debug!(
+            "Ignoring IPv4 packet with destination {}.",
+            ipv4_packet.dst_addr()
+        );
+        return Err(Error::Ignored);
+    }
+
+    // Update ARP cache! This is important for generating IMMEDIATE (not socket
+    // buffered) ICMP echo replies, errors, etc.
+    if let Some(eth_src_addr) = eth_src_addr.filter(|eth_addr| eth_addr.is_unicast()) {
+        interface
+            .arp_cache
+            .set_eth_addr_for_ip(ipv4_packet.src_addr(), eth_src_addr);
+    }
+
+    if ipv4_packet.flags() & ipv4_flags::NOT_LAST != 0 || ipv4_packet.fragment_offset() != 0 {
+        expire_fragments(interface);
+        return match interface.ipv4_reassembler.reassemble(&ipv4_packet) {
+            Some(ipv4_buffer) => {
+                interface.stack_stats.ip.reasm_oks += 1;
+                recv_reassembled_packet(
+                    interface,
+                    &Ipv4Packet::try_new(&ipv4_buffer[..])?,
+                    socket_set,
+                )
+            }
+            None => Ok(()),
+        };
+    }
+
+    recv_reassembled_packet(interface, &ipv4_packet, socket_set)
+}
+
+/// Sends an ICMP Parameter Problem error in response to a packet addressed to
+/// the interface with an inconsistent total length.
+///
+/// The error is only sent if the rest of the header can be trusted, i.e. the
+/// version, header length and header checksum are valid.
+fn send_parameter_problem(interface: &mut Interface, ipv4_packet: &Ipv4Packet<&[u8]>) {
+    let header_len = (ipv4_packet.header_len() * 4) as usize;
+    if ipv4_packet.ip_version() != 4
+        || header_len < Ipv4Packet::<&[u8]>::MIN_HEADER_LEN
+        || header_len > ipv4_packet.as_ref().len()
+        || ipv4_packet.gen_header_checksum() != 0
+        || ipv4_packet.dst_addr() != *interface.ipv4_addr
+    {
+        return;
+    }
+
+    debug!("Sending ICMP parameter problem for IPv4 packet with a bad total length.");
+    if let Err(err) = icmpv4::send_error(
+        interface,
+        Icmpv4Message::ParameterProblem { pointer: 2 },
+        ipv4_packet.as_ref(),
+    ) {
+        debug!("Error sending ICMP parameter problem with {:?}.", err);
+    }
+}
+
+/// Forwards a complete (unfragmented) IPv4 packet to any sockets and
+/// propagates it up the network stack.
+fn recv_reassembled_packet(
+    interface: &mut Interface,
+    ipv4_packet: &Ipv4Packet<&[u8]>,
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let action = filter_packet(interface, Direction::Received, ipv4_packet.as_ref());
+
+    match action {
+        Action::Accept => {}
+        Action::Drop => {
+            debug!("Filter dropped IPv4 packet from {}.", ipv4_packet.src_addr());
+            interface.stats.rx_filtered += 1;
+            return Err(Error::Ignored);
+        }
+        Action::Reject => {
+            debug!("Filter rejected IPv4 packet from {}.", ipv4_packet.src_addr());
+            interface.stats.rx_filtered += 1;
+            if let Err(err) = icmpv4::send_error(
+                interface,
+                Icmpv4Message::DestinationUnreachable(
+                    Icmpv4DestinationUnreachable::CommunicationProhibited,
+                ),
+                ipv4_packet.as_ref(),
+            ) {
+                debug!("Error sending ICMP communication prohibited with {:?}.", err);
+            }
+            return Err(Error::Ignored);
+        }
+    }
+
+    socket_set
+        .iter_mut()
+        .filter_map(|socket| match *socket {
+            TaggedSocket::Raw(ref mut socket)
+                if socket.raw_type() == RawType::Ipv4 && socket.accepts(ipv4_packet.as_ref()) =>
+            {
+                Some(socket)
+            }
+            _ => None,
+        })
+        .for_each(|socket| {
+            if let Err(err) = socket.recv_enqueue(ipv4_packet.as_ref()) {
+                debug!(
+                    "Error enqueueing IPv4 packet for receiving via socket with {:?}.",
+                    err
+                );
+            }
+        });
+
+    let ipv4_repr = Ipv4Repr::deserialize(ipv4_packet)?;
+
+    match ipv4_packet.protocol() {
+        ipv4_protocols::TCP | ipv4_protocols::UDP | ipv4_protocols::ICMP | ipv4_protocols::IGMP => {
+            interface.stack_stats.ip.in_delivers += 1
+        }
+        _ => interface.stack_stats.ip.in_unknown_protos += 1,
+    }
+
+    match ipv4_packet.protocol() {
+        ipv4_protocols::TCP => tcp::recv_packet(interface, &ipv4_repr, ipv4_packet, socket_set),
+        ipv4_protocols::UDP => udp::recv_packet(interface, &ipv4_repr, ipv4_packet, socket_set),
+        ipv4_protocols::ICMP => {
+            icmpv4::recv_packet(interface, &ipv4_repr, ipv4_packet.payload(), socket_set)
+        }
+        ipv4_protocols::IGMP => igmp::recv_packet(interface, ipv4_packet.payload()),
+        i => {
+            debug!("Ignoring IPv4 packet with type {}.", i);
+            Err(Error::Ignored)
+        }
+    }
+}
+
+/// Discards IPv4 packets which could not be reassembled in time, sending an
+/// ICMP Time Exceeded error for each packet whose first fragment arrived.
+pub fn expire_fragments(interface: &mut Interface) {
+    for ipv4_buffer in interface.ipv4_reassembler.expire() {
+        debug!("Discarding IPv4 packet after reassembly timeout.");
+        if let Err(err) = icmpv4::send_error(
+            interface,
+            Icmpv4Message::TimeExceeded(Icmpv4TimeExceeded::FragmentReassembly),
+            &ipv4_buffer[..],
+        ) {
+            debug!("Error sending ICMP time exceeded with {:?}.", err);
+        }
+    }
+}
+
+/// Checks if an address is the limited broadcast address or the broadcast
+/// address of the interface subnet.
+pub fn is_broadcast(interface: &Interface, address: Ipv4Address) -> bool {
+    address == Ipv4Address::BROADCAST || interface.ipv4_addr.is_broadcast(address)
+}
+
+/// Returns the next hop for a packet destined to a specified address.
+pub fn ipv4_addr_route(interface: &mut Interface, address: Ipv4Address) -> Ipv4Address {
+    if interface.ipv4_addr.is_member(address) {
+        debug!("{} will be routed through link.", address);
+        address
+    } else if let Some(gateway) = interface.routing_table.lookup(address) {
+        debug!("{} will be routed through gateway {}.", address, gateway);
+        gateway
+    } else {
+        debug!("{} will be routed through default gateway.", address);
+        interface.default_gateway
+    }
+}
+
+/// Maximum number of IPv4 packets which may be reassembled concurrently.
+pub static REASSEMBLY_MAX_PACKETS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct FragmentKey {
+    src_addr: Ipv4Address,
+    dst_addr: Ipv4Address,
+    identification: u16,
+    protocol: u8,
+}
+
+struct FragmentedPacket {
+    header: Vec<u8>,
+    payload: Vec<u8>,
+    payload_len: Option<usize>,
+    // Sorted, non-overlapping (start, end) ranges of the payload received.
+    received: Vec<(usize, usize)>,
+}
+
+impl FragmentedPacket {
+    fn add_range(&mut self, start: usize, end: usize) {
+        self.received.push((start, end));
+        self.received.sort();
+
+        let mut merged: Vec<(usize, usize)> = Vec::with_capacity(self.received.len());
+        for &(start, end) in self.received.iter() {
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = end.max(last.1),
+                _ => merged.push((start, end)),
+            }
+        }
+
+        self.received = merged;
+    }
+
+    fn is_complete(&self) -> bool {
+        match self.payload_len {
+            Some(payload_len) => !self.header.is_empty() && self.received == [(0, payload_len)],
+            None => false,
+        }
+    }
+}
+
+/// Buffers IPv4 fragments until all fragments of a packet have arrived.
+///
+/// Fragments are keyed by (source, destination, identification, protocol) and
+/// discarded if the packet is not complete within a timeout.
+pub struct Reassembler<T = DefaultEnv>
+where
+    T: Env,
+{
+    packets: BTreeMap<FragmentKey, FragmentedPacket>,
+    timers: TimerQueue<FragmentKey>,
+    timeout: Duration,
+    time_env: T,
+}
+
+impl<T: Env> Reassembler<T> {
+    /// Creates a reassembler which discards incomplete packets after
+    /// timeout_in_secs seconds.
+    pub fn new(timeout_in_secs: u64, time_env: T) -> Reassembler<T> {
+        Reassembler {
+            packets: BTreeMap::new(),
+            timers: TimerQueue::new(),
+            timeout: Duration::from_secs(timeout_in_secs),
+            time_env,
+        }
+    }
+
+    /// Discards packets which have not been reassembled within the timeout.
+    ///
+    /// For each discarded packet whose first fragment was received, returns
+    /// the IP header and leading 8 bytes of payload of that fragment so the
+    /// caller may send an ICMP Time Exceeded error.
+    pub fn expire(&mut self) -> Vec<Vec<u8>> {
+        let mut expired = Vec::new();
+
+        for key in self.timers.expire(&self.time_env) {
+            let packet = self.packets.remove(&key).unwrap();
+
+            if !packet.header.is_empty() {
+                let payload_len = match packet.received.first() {
+                    Some(&(0, end)) => min(end, 8),
+                    _ => 0,
+                };
+                let mut ipv4_buffer = packet.header;
+                ipv4_buffer.extend_from_slice(&packet.payload[.. payload_len]);
+                expired.push(ipv4_buffer);
+            }
+        }
+
+        expired
+    }
+
+    /// Returns when the oldest packet being reassembled times out, if any.
+    pub fn poll_at(&self) -> Option<Instant> {
+        self.timers.poll_at()
+    }
+
+    /// Adds a fragment to the reassembly buffer, returning the reassembled
+    /// packet once all of its fragments have been received.
+    pub fn reassemble<B>(&mut self, ipv4_packet: &Ipv4Packet<B>) -> Option<Vec<u8>>
+    where
+        B: AsRef<[u8]>,
+    {
+        self.expire();
+
+        let key = FragmentKey {
+            src_addr: ipv4_packet.src_addr(),
+            dst_addr: ipv4_packet.dst_addr(),
+            identification: ipv4_packet.identification(),
+            protocol: ipv4_packet.protocol(),
+        };
+
+        let header_len = (ipv4_packet.header_len() * 4) as usize;
+        let fragment = ipv4_packet.payload();
+        let start = (ipv4_packet.fragment_offset() as usize) * 8;
+        let end = start + fragment.len();
+
+        if header_len + end > 65535 {
+            debug!("Dropping IPv4 fragment exceeding the maximum packet size.");
+            self.packets.remove(&key);
+            self.timers.cancel(&key);
+            return None;
+        }
+
+        if !self.packets.contains_key(&key) && self.packets.len() >= REASSEMBLY_MAX_PACKETS {
+            debug!("Dropping IPv4 fragment, too many packets being reassembled.");
+            return None;
+        }
+
+        if !self.packets.contains_key(&key) {
+            self.timers.schedule(&self.time_env, key, self.timeout);
+        }
+
+        let complete = {
+            let packet = self.packets.entry(key).or_insert_with(|| FragmentedPacket {
+                header: Vec::new(),
+                payload: Vec::new(),
+                payload_len: None,
+                received: Vec::new(),
+            });
+
+            if start == 0 {
+                packet.header = ipv4_packet.as_ref()[.. header_len].to_vec();
+            }
+
+            if ipv4_packet.flags() & ipv4_flags::NOT_LAST == 0 {
+                packet.payload_len = Some(end);
+            }
+
+            if packet.payload.len() < end {
+                packet.payload.resize(end, 0);
+            }
+
+            packet.payload[start .. end].copy_from_slice(fragment);
+            packet.add_range(start, end);
+            packet.is_complete()
+        };
+
+        if !complete {
+            return None;
+        }
+
+        let packet = self.packets.remove(&key).unwrap();
+        self.timers.cancel(&key);
+        let payload_len = packet.payload_len.unwrap();
+
+        let mut ipv4_buffer = packet.header;
+        ipv4_buffer.extend_from_slice(&packet.payload[.. payload_len]);
+
+        {
+            let packet_len = ipv4_buffer.len() as u16;
+            let mut ipv4_packet = Ipv4Packet::try_new(&mut ipv4_buffer[..]).unwrap();
+            let flags = ipv4_packet.flags() & !ipv4_flags::NOT_LAST;
+            ipv4_packet.set_packet_len(packet_len);
+            ipv4_packet.set_fragment_offset(0);
+            ipv4_packet.set_flags(flags);
+            ipv4_packet.fill_checksum();
+        }
+
+        Some(ipv4_buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::repr::{
+        Ipv4Options,
+        Ipv4Protocol,
+    };
+    use stack::time::MockEnv;
+
+    fn fragment(offset: usize, payload: &[u8], is_last: bool) -> Vec<u8> {
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new([10, 0, 0, 1]),
+            dst_addr: Ipv4Address::new([10, 0, 0, 2]),
+            protocol: Ipv4Protocol::UDP,
+            payload_len: payload.len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: if is_last { 0 } else { ipv4_flags::NOT_LAST },
+            options: Ipv4Options::new(),
+        };
+
+        let mut buffer = vec![0; ipv4_repr.buffer_len()];
+        {
+            let mut ipv4_packet = Ipv4Packet::try_new(&mut buffer[..]).unwrap();
+            ipv4_repr.serialize(&mut ipv4_packet);
+            ipv4_packet.set_identification(7);
+            ipv4_packet.set_fragment_offset((offset / 8) as u16);
+            ipv4_packet.fill_checksum();
+            ipv4_packet.payload_mut().copy_from_slice(payload);
+        }
+        buffer
+    }
+
+    #[test]
+    fn test_reassemble_out_of_order() {
+        let mut reassembler = Reassembler::new(30, MockEnv::new());
+        let payload: Vec<u8> = (0 .. 20).collect();
+
+        let fragment_2 = fragment(16, &payload[16 ..], true);
+        let fragment_1 = fragment(8, &payload[8 .. 16], false);
+        let fragment_0 = fragment(0, &payload[.. 8], false);
+
+        assert_matches!(
+            reassembler.reassemble(&Ipv4Packet::try_new(&fragment_2[..]).unwrap()),
+            None
+        );
+        assert_matches!(
+            reassembler.reassemble(&Ipv4Packet::try_new(&fragment_0[..]).unwrap()),
+            None
+        );
+
+        let buffer = reassembler
+            .reassemble(&Ipv4Packet::try_new(&fragment_1[..]).unwrap())
+            .unwrap();
+        let ipv4_packet = Ipv4Packet::try_new(&buffer[..]).unwrap();
+        assert_matches!(ipv4_packet.check_encoding(), Ok(_));
+        assert_eq!(ipv4_packet.flags() & ipv4_flags::NOT_LAST, 0);
+        assert_eq!(ipv4_packet.fragment_offset(), 0);
+        assert_eq!(ipv4_packet.payload(), &payload[..]);
+    }
+
+    #[test]
+    fn test_reassemble_timeout() {
+        let mut reassembler = Reassembler::new(30, MockEnv::new());
+        let payload: Vec<u8> = (0 .. 16).collect();
+
+        let fragment_0 = fragment(0, &payload[.. 8], false);
+        let fragment_1 = fragment(8, &payload[8 ..], true);
+
+        assert_matches!(
+            reassembler.reassemble(&Ipv4Packet::try_new(&fragment_0[..]).unwrap()),
+            None
+        );
+        reassembler.time_env.now += Duration::from_secs(31);
+        assert_matches!(
+            reassembler.reassemble(&Ipv4Packet::try_new(&fragment_1[..]).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_reassemble_expire() {
+        let mut reassembler = Reassembler::new(30, MockEnv::new());
+        let payload: Vec<u8> = (0 .. 24).collect();
+
+        let fragment_0 = fragment(0, &payload[.. 16], false);
+
+        assert_matches!(
+            reassembler.reassemble(&Ipv4Packet::try_new(&fragment_0[..]).unwrap()),
+            None
+        );
+        assert!(reassembler.expire().is_empty());
+
+        reassembler.time_env.now += Duration::from_secs(31);
+        let expired = reassembler.expire();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(&expired[0][..], &fragment_0[.. 28]);
+        assert!(reassembler.expire().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+use stack::repr::{
+    eth_types,
+    EthernetFrame,
+    Ipv6Address,
+    Ipv6Packet,
+    Ipv6Protocol,
+    Ipv6Repr,
+};
+use stack::service::{
+    ethernet,
+    icmpv6,
+    ndp,
+    Interface,
+};
+use {
+    BufferError,
+    Error,
+    ParseError,
+    Result,
+};
+
+/// Sends an IPv6 packet via the interface.
+///
+/// The caller needs to fill in **only** the payload in the provided buffer.
+/// The Ethernet destination is resolved via Neighbor Discovery, so this may
+/// fail with AddressError::NeighborResolution(...) until a neighbor
+/// advertisement is received. IPv6 packets are never fragmented by the stack.
+pub fn send_packet_with_repr<F>(interface: &mut Interface, ipv6_repr: &Ipv6Repr, f: F) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    let max_packet_len = interface.dev.max_transmission_unit() - EthernetFrame::<&[u8]>::HEADER_LEN;
+    if ipv6_repr.buffer_len() > max_packet_len {
+        debug!(
+            "Dropping IPv6 packet to {} exceeding the MTU.",
+            ipv6_repr.dst_addr
+        );
+        return Err(Error::Buffer(BufferError::TooSmall));
+    }
+
+    let eth_dst_addr = if ipv6_repr.dst_addr.is_multicast() {
+        ethernet::ipv6_multicast_addr(ipv6_repr.dst_addr)
+    } else {
+        ndp::eth_addr_for_ip(interface, ipv6_repr.dst_addr)?
+    };
+
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(ipv6_repr.buffer_len());
+
+    ethernet::send_frame(interface, eth_frame_len, |eth_frame| {
+        eth_frame.set_dst_addr(eth_dst_addr);
+        eth_frame.set_payload_type(eth_types::IPV6);
+        let mut ipv6_packet = Ipv6Packet::try_new(eth_frame.payload_mut()).unwrap();
+        ipv6_repr.serialize(&mut ipv6_packet);
+        f(ipv6_packet.payload_mut());
+    })
+}
+
+/// Receives an IPv6 packet from an interface.
+///
+/// The IPv6 packet is parsed, any extension headers are skipped, and the
+/// upper layer payload is propagated up the network stack.
+pub fn recv_packet(interface: &mut Interface, eth_frame: &EthernetFrame<&[u8]>) -> Result<()> {
+    let ipv6_packet = Ipv6Packet::try_new(eth_frame.payload())?;
+    ipv6_packet.check_encoding()?;
+
+    if !is_local(interface, ipv6_packet.dst_addr()) {
+        debug!(
+            "Ignoring IPv6 packet with destination {}.",
+            ipv6_packet.dst_addr()
+        );
+        return Err(Error::Ignored);
+    }
+
+    let ipv6_repr = match Ipv6Repr::deserialize(&ipv6_packet) {
+        Ok(ipv6_repr) => ipv6_repr,
+        Err(Error::Parse(ParseError::Malformed)) => {
+            debug!(
+                "Ignoring IPv6 packet with unsupported next header {}.",
+                ipv6_packet.next_header()
+            );
+            return Err(Error::Ignored);
+        }
+        Err(err) => return Err(err),
+    };
+    let (_, payload) = ipv6_packet.upper_layer()?;
+
+    match ipv6_repr.protocol {
+        Ipv6Protocol::ICMPv6 => icmpv6::recv_packet(interface, &ipv6_repr, payload),
+        protocol => {
+            debug!("Ignoring IPv6 packet with protocol {:?}.", protocol);
+            Err(Error::Ignored)
+        }
+    }
+}
+
+/// Checks if an IPv6 address is the interface address, or a multicast group
+/// the interface implicitly belongs to.
+pub fn is_local(interface: &Interface, ipv6_addr: Ipv6Address) -> bool {
+    ipv6_addr == *interface.ipv6_addr
+        || ipv6_addr == Ipv6Address::ALL_NODES
+        || ipv6_addr == interface.ipv6_addr.solicited_node()
+}
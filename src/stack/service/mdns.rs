@@ -0,0 +1,508 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use stack::repr::{
+    dns_classes,
+    dns_flags,
+    dns_opcodes,
+    dns_ports,
+    dns_types,
+    DnsPacket,
+    DnsRecord,
+    DnsRecordData,
+    DnsRepr,
+    Ipv4Address,
+};
+use stack::service::Interface;
+use stack::socket::{
+    SocketAddr,
+    SocketEnv,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    PollAt,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// TTL in seconds of the address record for the hostname, see RFC 6762.
+pub static HOST_RECORD_TTL: u32 = 120;
+
+/// TTL in seconds of records in responses to legacy unicast queries, see RFC
+/// 6762 section 6.7.
+pub static LEGACY_RECORD_TTL: u32 = 10;
+
+/// Number of times a resolve query is sent before giving up.
+pub static MAX_RESOLVE_ATTEMPTS: u32 = 3;
+
+/// Handle identifying a resolve issued via Mdns.
+pub type ResolveHandle = usize;
+
+struct Resolve {
+    name: String,
+    attempts: u32,
+    sent_at: Option<Instant>,
+    result: Option<Result<Vec<Ipv4Address>>>,
+}
+
+/// A multicast DNS responder and resolver for names in the .local domain.
+///
+/// The responder answers address queries for a hostname with the interface
+/// address, and resolves run one-shot queries for the addresses of peers on
+/// the link. Both share a UDP socket bound to the mDNS port which joins the
+/// mDNS multicast group.
+pub struct Mdns<T = DefaultEnv>
+where
+    T: Env,
+{
+    socket_handle: SocketHandle,
+    hostname: String,
+    resolves: BTreeMap<ResolveHandle, Resolve>,
+    next_handle: ResolveHandle,
+    timeout: Duration,
+    time_env: T,
+}
+
+impl<T: Env> Mdns<T> {
+    /// Creates a responder for a hostname, with .local appended if missing,
+    /// whose resolves retry queries after timeout_in_millis milliseconds.
+    /// Adds a UDP socket bound to the mDNS port to the socket set.
+    ///
+    /// Fails with ParseError::Malformed if the hostname is not a valid domain
+    /// name.
+    pub fn new<E>(
+        socket_env: &SocketEnv<E>,
+        socket_set: &mut SocketSet,
+        hostname: &str,
+        timeout_in_millis: u64,
+        time_env: T,
+    ) -> Result<Mdns<T>>
+    where
+        E: 'static + Env + Clone,
+    {
+        let hostname = local_name(hostname);
+        DnsRepr::query(0, &hostname, dns_types::A).buffer_len()?;
+
+        let mut socket = socket_env.udp_socket(SocketAddr {
+            addr: Ipv4Address::UNSPECIFIED,
+            port: dns_ports::MDNS,
+        })?;
+        socket.join_multicast_group(Ipv4Address::MDNS)?;
+        socket.set_ttl(255);
+
+        let socket_handle = socket_set.add_socket(TaggedSocket::Udp(socket))?;
+
+        Ok(Mdns {
+            socket_handle,
+            hostname,
+            resolves: BTreeMap::new(),
+            next_handle: 0,
+            timeout: Duration::from_millis(timeout_in_millis),
+            time_env,
+        })
+    }
+
+    /// Returns the handle of the socket used for mDNS.
+    pub fn socket_handle(&self) -> SocketHandle {
+        self.socket_handle
+    }
+
+    /// Returns the hostname answered for, including the .local domain.
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Starts resolving the IPv4 addresses of a name, with .local appended if
+    /// missing, which is queried the next time the responder is polled.
+    ///
+    /// Fails with ParseError::Malformed if the name is not a valid domain name.
+    pub fn resolve(&mut self, name: &str) -> Result<ResolveHandle> {
+        let name = local_name(name);
+        DnsRepr::query(0, &name, dns_types::A).buffer_len()?;
+
+        let handle = self.next_handle;
+        self.next_handle += 1;
+
+        self.resolves.insert(
+            handle,
+            Resolve {
+                name,
+                attempts: 0,
+                sent_at: None,
+                result: None,
+            },
+        );
+
+        Ok(handle)
+    }
+
+    /// Removes a resolve, returning its result if it has completed.
+    pub fn take_result(&mut self, handle: ResolveHandle) -> Option<Result<Vec<Ipv4Address>>> {
+        if self
+            .resolves
+            .get(&handle)
+            .is_some_and(|resolve| resolve.result.is_some())
+        {
+            self.resolves.remove(&handle).unwrap().result
+        } else {
+            None
+        }
+    }
+
+    /// Abandons a resolve, ignoring any response.
+    pub fn cancel(&mut self, handle: ResolveHandle) {
+        self.resolves.remove(&handle);
+    }
+
+    /// Returns when poll(...) next needs to be called to send or retry a
+    /// resolve query. Queries for the hostname are answered on ingress.
+    pub fn poll_at(&self) -> PollAt {
+        self.resolves
+            .values()
+            .filter(|resolve| resolve.result.is_none())
+            .map(|resolve| match resolve.sent_at {
+                Some(sent_at) => PollAt::Time(sent_at + self.timeout),
+                None => PollAt::Now,
+            })
+            .min()
+            .unwrap_or(PollAt::Ingress)
+    }
+
+    /// Answers mDNS queries for the hostname, processes responses to
+    /// resolves, and sends or retries resolve queries.
+    ///
+    /// This should be called regularly, before sending packets via the socket
+    /// set.
+    pub fn poll(&mut self, interface: &Interface, socket_set: &mut SocketSet) -> Result<()> {
+        let socket = socket_set.socket(self.socket_handle)?.as_udp_socket();
+
+        loop {
+            let (dns_repr, src_addr) = match socket.recv() {
+                Ok((payload, src_addr)) => {
+                    match DnsPacket::try_new(payload)
+                        .and_then(|packet| DnsRepr::deserialize(&packet))
+                    {
+                        Ok(dns_repr) => (dns_repr, src_addr),
+                        Err(err) => {
+                            debug!("Ignoring mDNS message from {} with {:?}.", src_addr, err);
+                            continue;
+                        }
+                    }
+                }
+                Err(Error::Buffer(BufferError::Empty)) => break,
+                Err(err) => {
+                    debug!("Error receiving mDNS message with {:?}.", err);
+                    continue;
+                }
+            };
+
+            if dns_repr.is_response() {
+                self.recv_response(&dns_repr);
+            } else if let Some((response, dst_addr)) =
+                self.answer(&dns_repr, &src_addr, *interface.ipv4_addr)
+            {
+                debug!(
+                    "Answering mDNS query for {} from {}.",
+                    self.hostname, src_addr
+                );
+                let buffer = socket.send(response.buffer_len()?, dst_addr)?;
+                response.serialize(&mut DnsPacket::try_new(buffer)?)?;
+            }
+        }
+
+        let now = self.time_env.now_instant();
+        let timeout = self.timeout;
+
+        for resolve in self.resolves.values_mut() {
+            match resolve.sent_at {
+                _ if resolve.result.is_some() => continue,
+                Some(sent_at) if now.duration_since(sent_at) < timeout => continue,
+                _ => {}
+            }
+
+            if resolve.attempts >= MAX_RESOLVE_ATTEMPTS {
+                debug!("mDNS resolve for {} timed out.", resolve.name);
+                resolve.result = Some(Err(Error::Timeout));
+                continue;
+            }
+
+            // Ask for a unicast response since one-shot queries are of no
+            // interest to other hosts.
+            let mut dns_repr = DnsRepr::query(0, &resolve.name, dns_types::A);
+            dns_repr.flags = 0;
+            dns_repr.questions[0].class |= dns_classes::UNICAST_RESPONSE;
+
+            let buffer = socket.send(
+                dns_repr.buffer_len()?,
+                SocketAddr {
+                    addr: Ipv4Address::MDNS,
+                    port: dns_ports::MDNS,
+                },
+            )?;
+            dns_repr.serialize(&mut DnsPacket::try_new(buffer)?)?;
+
+            resolve.attempts += 1;
+            resolve.sent_at = Some(now);
+        }
+
+        Ok(())
+    }
+
+    /// Returns a response and its destination if a query asks for the address
+    /// of the hostname.
+    fn answer(
+        &self,
+        dns_repr: &DnsRepr,
+        src_addr: &SocketAddr,
+        ipv4_addr: Ipv4Address,
+    ) -> Option<(DnsRepr, SocketAddr)> {
+        if ipv4_addr.is_unspecified() || dns_repr.opcode != dns_opcodes::QUERY {
+            return None;
+        }
+
+        let question = dns_repr.questions.iter().find(|question| {
+            (question.record_type == dns_types::A || question.record_type == dns_types::ANY)
+                && question.class & !dns_classes::UNICAST_RESPONSE == dns_classes::IN
+                && question.name.eq_ignore_ascii_case(&self.hostname)
+        })?;
+
+        // Queries from other ports come from legacy resolvers, which expect a
+        // conventional unicast DNS response.
+        let legacy = src_addr.port != dns_ports::MDNS;
+
+        let (id, questions, class, ttl) = if legacy {
+            (
+                dns_repr.id,
+                vec![question.clone()],
+                dns_classes::IN,
+                LEGACY_RECORD_TTL,
+            )
+        } else {
+            (
+                0,
+                Vec::new(),
+                dns_classes::IN | dns_classes::CACHE_FLUSH,
+                HOST_RECORD_TTL,
+            )
+        };
+
+        let response = DnsRepr {
+            id,
+            flags: dns_flags::RESPONSE | dns_flags::AUTHORITATIVE,
+            opcode: dns_opcodes::QUERY,
+            rcode: 0,
+            questions,
+            answers: vec![DnsRecord {
+                name: self.hostname.clone(),
+                class,
+                ttl,
+                data: DnsRecordData::A(ipv4_addr),
+            }],
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+        };
+
+        let dst_addr = if legacy || question.class & dns_classes::UNICAST_RESPONSE != 0 {
+            *src_addr
+        } else {
+            SocketAddr {
+                addr: Ipv4Address::MDNS,
+                port: dns_ports::MDNS,
+            }
+        };
+
+        Some((response, dst_addr))
+    }
+
+    /// Completes resolves for names with address records in a response.
+    fn recv_response(&mut self, dns_repr: &DnsRepr) {
+        for resolve in self.resolves.values_mut() {
+            if resolve.result.is_some() {
+                continue;
+            }
+
+            let addrs: Vec<_> = dns_repr
+                .answers
+                .iter()
+                .chain(dns_repr.additionals.iter())
+                .filter(|record| record.name.eq_ignore_ascii_case(&resolve.name))
+                .filter_map(|record| match record.data {
+                    DnsRecordData::A(addr) => Some(addr),
+                    _ => None,
+                })
+                .collect();
+
+            if !addrs.is_empty() {
+                resolve.result = Some(Ok(addrs));
+            }
+        }
+    }
+}
+
+/// Appends the .local domain to a name if missing.
+fn local_name(name: &str) -> String {
+    let name = name.trim_end_matches('.');
+    if name.to_lowercase().ends_with(".local") {
+        name.to_string()
+    } else {
+        format!("{}.local", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    fn mdns() -> Mdns<MockEnv> {
+        Mdns {
+            socket_handle: SocketHandle::new(0, 0),
+            hostname: local_name("usrnet"),
+            resolves: BTreeMap::new(),
+            next_handle: 0,
+            timeout: Duration::from_secs(1),
+            time_env: MockEnv::new(),
+        }
+    }
+
+    fn ipv4_addr() -> Ipv4Address {
+        Ipv4Address::new([10, 0, 0, 102])
+    }
+
+    fn mdns_addr() -> SocketAddr {
+        SocketAddr {
+            addr: Ipv4Address::MDNS,
+            port: dns_ports::MDNS,
+        }
+    }
+
+    #[test]
+    fn test_local_name() {
+        assert_eq!(local_name("usrnet"), "usrnet.local");
+        assert_eq!(local_name("usrnet.local."), "usrnet.local");
+        assert_eq!(local_name("usrnet.LOCAL"), "usrnet.LOCAL");
+    }
+
+    #[test]
+    fn test_answer_multicast() {
+        let mdns = mdns();
+        let mut query = DnsRepr::query(0, "USRNET.local", dns_types::A);
+        query.flags = 0;
+        let src_addr = SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 1]),
+            port: dns_ports::MDNS,
+        };
+
+        let (response, dst_addr) = mdns.answer(&query, &src_addr, ipv4_addr()).unwrap();
+        assert_eq!(dst_addr, mdns_addr());
+        assert!(response.is_response());
+        assert!(response.questions.is_empty());
+        assert_eq!(
+            response.answers,
+            vec![DnsRecord {
+                name: "usrnet.local".to_string(),
+                class: dns_classes::IN | dns_classes::CACHE_FLUSH,
+                ttl: HOST_RECORD_TTL,
+                data: DnsRecordData::A(ipv4_addr()),
+            }]
+        );
+
+        query.questions[0].class |= dns_classes::UNICAST_RESPONSE;
+        let (_, dst_addr) = mdns.answer(&query, &src_addr, ipv4_addr()).unwrap();
+        assert_eq!(dst_addr, src_addr);
+    }
+
+    #[test]
+    fn test_answer_legacy_unicast() {
+        let mdns = mdns();
+        let query = DnsRepr::query(1234, "usrnet.local", dns_types::A);
+        let src_addr = SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 1]),
+            port: 40000,
+        };
+
+        let (response, dst_addr) = mdns.answer(&query, &src_addr, ipv4_addr()).unwrap();
+        assert_eq!(dst_addr, src_addr);
+        assert_eq!(response.id, 1234);
+        assert_eq!(response.questions, query.questions);
+        assert_eq!(response.answers[0].class, dns_classes::IN);
+        assert_eq!(response.answers[0].ttl, LEGACY_RECORD_TTL);
+    }
+
+    #[test]
+    fn test_answer_ignores_other_names() {
+        let mdns = mdns();
+        let src_addr = mdns_addr();
+
+        let query = DnsRepr::query(0, "other.local", dns_types::A);
+        assert_matches!(mdns.answer(&query, &src_addr, ipv4_addr()), None);
+
+        let query = DnsRepr::query(0, "usrnet.local", dns_types::AAAA);
+        assert_matches!(mdns.answer(&query, &src_addr, ipv4_addr()), None);
+
+        let query = DnsRepr::query(0, "usrnet.local", dns_types::A);
+        assert_matches!(
+            mdns.answer(&query, &src_addr, Ipv4Address::UNSPECIFIED),
+            None
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut mdns = mdns();
+        let handle = mdns.resolve("peer").unwrap();
+        assert_matches!(mdns.take_result(handle), None);
+
+        let mut response = DnsRepr::query(0, "other.local", dns_types::A);
+        response.flags = dns_flags::RESPONSE;
+        response.questions.clear();
+        response.answers.push(DnsRecord {
+            name: "other.local".to_string(),
+            class: dns_classes::IN | dns_classes::CACHE_FLUSH,
+            ttl: HOST_RECORD_TTL,
+            data: DnsRecordData::A(Ipv4Address::new([10, 0, 0, 7])),
+        });
+        mdns.recv_response(&response);
+        assert_matches!(mdns.take_result(handle), None);
+
+        response.answers[0].name = "PEER.local".to_string();
+        mdns.recv_response(&response);
+        assert_eq!(
+            mdns.take_result(handle).unwrap().unwrap(),
+            vec![Ipv4Address::new([10, 0, 0, 7])]
+        );
+        assert_matches!(mdns.take_result(handle), None);
+    }
+
+    #[test]
+    fn test_poll_at() {
+        let mut mdns = mdns();
+        assert_eq!(mdns.poll_at(), PollAt::Ingress);
+
+        let handle = mdns.resolve("peer").unwrap();
+        assert_eq!(mdns.poll_at(), PollAt::Now);
+
+        let sent_at = mdns.time_env.now;
+        mdns.resolves.get_mut(&handle).unwrap().sent_at = Some(sent_at);
+        assert_eq!(
+            mdns.poll_at(),
+            PollAt::Time(sent_at + Duration::from_secs(1))
+        );
+
+        mdns.cancel(handle);
+        assert_eq!(mdns.poll_at(), PollAt::Ingress);
+    }
+}
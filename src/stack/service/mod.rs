@@ -0,0 +1,294 @@
+//! Packet processing services for different network layers.
+//!
+//! The `services` module deals with packet transmission and reception logic at
+//! different layers of the network stack.
+
+pub mod arp;
+pub mod conntrack;
+pub mod dhcpv4;
+pub mod dns;
+pub mod ethernet;
+pub mod filter;
+pub mod icmpv4;
+pub mod icmpv6;
+pub mod igmp;
+pub mod interface_set;
+pub mod ipv4;
+pub mod ipv6;
+pub mod mdns;
+pub mod ndp;
+pub mod pppoe;
+pub mod routing;
+pub mod shaper;
+pub mod shim;
+pub mod socket;
+pub mod stats;
+pub mod tcp;
+pub mod udp;
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use log::Level;
+
+use stack::arp_cache::ArpCache;
+use stack::capture::{
+    describe_frame,
+    Capture,
+    Direction,
+};
+use stack::dev::Device;
+use stack::neighbor_cache::NeighborCache;
+use stack::repr::{
+    EthernetAddress,
+    Ipv4Address,
+    Ipv4AddressCidr,
+    Ipv6AddressCidr,
+};
+use stack::service::routing::RoutingTable;
+use stack::socket::{
+    SocketSet,
+    TaggedSocket,
+};
+use stack::storage::{
+    PacketBuf,
+    Pool,
+};
+#[cfg(feature = "std")]
+use stack::time::SystemEnv;
+use stack::time::{
+    Instant,
+    PollAt,
+};
+use Result;
+
+pub use self::interface_set::InterfaceSet;
+pub use self::stats::{
+    InterfaceStats,
+    StackStats,
+};
+
+/// Callback invoked with the conflicting IPv4 address and the Ethernet address
+/// of the offending host when an address conflict is detected.
+pub type ConflictHandler = Box<dyn FnMut(Ipv4Address, EthernetAddress)>;
+
+/// An interface for sending and receiving network packets.
+pub struct Interface {
+    /// Device for sending and receiving raw Ethernet frames.
+    pub dev: Box<Device>,
+    /// Cache for IPv4/Ethernet address translations.
+    pub arp_cache: ArpCache,
+    /// Outstanding ARP requests and packets waiting for them to complete.
+    pub arp_pending: arp::PendingQueue,
+    /// Ethernet address for the interface.
+    pub ethernet_addr: EthernetAddress,
+    /// IPv4 address for the interface.
+    pub ipv4_addr: Ipv4AddressCidr,
+    /// Default gateway for IPv4 packets not on the interface subnet. This
+    /// should be on the same subnet as ipv4_addr!
+    pub default_gateway: Ipv4Address,
+    /// DNS servers for resolving host names, e.g. as configured via DHCP.
+    pub dns_servers: Vec<Ipv4Address>,
+    /// Static routes for IPv4 packets not on the interface subnet, which take
+    /// precedence over the default gateway.
+    pub routing_table: RoutingTable,
+    /// IPv4 multicast groups the interface has joined. Use
+    /// igmp::join_multicast_group(...) rather than modifying this directly so
+    /// membership is reported to multicast routers.
+    pub ipv4_multicast_groups: BTreeSet<Ipv4Address>,
+    /// Buffer for reassembling fragmented IPv4 packets.
+    pub ipv4_reassembler: ipv4::Reassembler,
+    /// Limits the rate at which ICMP error messages are generated.
+    pub icmpv4_rate_limiter: icmpv4::RateLimiter,
+    /// IPv6 address for the interface, typically a link local address.
+    pub ipv6_addr: Ipv6AddressCidr,
+    /// Cache for IPv6/Ethernet address translations.
+    pub neighbor_cache: NeighborCache,
+    /// Invoked when another host on the link claims ipv4_addr, either while
+    /// probing in announce() or at any point afterwards.
+    pub arp_conflict_handler: Option<ConflictHandler>,
+    /// Probes for and announces ipv4_addr after announce(), see
+    /// arp::AddressClaim.
+    pub arp_claim: Option<arp::AddressClaim>,
+    /// Mirrors frames sent and received via dev, e.g. to a pcap file.
+    pub capture: Option<Capture>,
+    /// Frames queued for a single send_batch() on dev while batching, see
+    /// ethernet::begin_batch(...).
+    pub tx_batch: Option<Vec<PacketBuf>>,
+    /// Paces packets sent from sockets via socket::send(...), e.g. to test
+    /// congestion behavior or share a link with other traffic. Every frame
+    /// sent via dev counts towards the rate, but only socket traffic waits.
+    pub shaper: Option<shaper::Shaper>,
+    /// Drops or rejects IPv4 packets sent and received via the interface
+    /// according to a list of rules, before received packets reach sockets.
+    pub filter: Option<filter::Filter>,
+    /// Tracks connections seen in IPv4 packets sent and received via the
+    /// interface, which filter rules may match on the state of.
+    pub conntrack: Option<conntrack::Conntrack>,
+    /// PPPoE session which IPv4 packets are encapsulated in while set, rather
+    /// than being sent directly over Ethernet, e.g. as established by a
+    /// pppoe::PppoeClient.
+    pub pppoe_session: Option<pppoe::Session>,
+    /// Transforms frames sent and received via dev, e.g. to encrypt them.
+    /// Captures and stats see frames as sent to and received from dev.
+    pub shim: Option<Box<dyn shim::Shim>>,
+    /// Buffers reused for frames sent and received via dev.
+    pub pool: Pool,
+    /// Counters for frames sent and received via dev.
+    pub stats: InterfaceStats,
+    /// MIB-II style counters for packets handled by each protocol.
+    pub stack_stats: StackStats,
+}
+
+impl Interface {
+    /// Starts probing for conflicting use of the interface IPv4 address and
+    /// then announcing it with gratuitous ARPs, see arp::announce(...). The
+    /// claim progresses via socket::send(...) and socket::recv(...), and
+    /// arp_claim reports its outcome. This should be called before sending
+    /// other traffic, e.g. on link up or after acquiring an address via DHCP.
+    #[cfg(feature = "std")]
+    pub fn announce(&mut self) -> Result<()> {
+        arp::announce(self, &arp::AnnounceConfig::default(), SystemEnv::new())
+    }
+
+    /// Changes the IPv4 address of the interface.
+    ///
+    /// The ARP cache and packets waiting on ARP resolution are flushed since
+    /// they may belong to the previous subnet, sockets bound to the previous
+    /// address are notified via addr_removed(...), and a gratuitous ARP is
+    /// sent so other hosts learn the new address.
+    pub fn set_ipv4_addr(
+        &mut self,
+        ipv4_addr: Ipv4AddressCidr,
+        socket_set: &mut SocketSet,
+    ) -> Result<()> {
+        let prev_addr = *self.ipv4_addr;
+        self.ipv4_addr = ipv4_addr;
+
+        self.arp_cache.flush();
+        for pending_addr in self.arp_pending.addrs() {
+            self.arp_pending.resolve(pending_addr);
+        }
+
+        if prev_addr != *ipv4_addr && !prev_addr.is_unspecified() {
+            for socket in socket_set.iter_mut() {
+                match *socket {
+                    TaggedSocket::Tcp(ref mut socket) => socket.addr_removed(prev_addr),
+                    TaggedSocket::Udp(ref mut socket) => socket.addr_removed(prev_addr),
+                    _ => {}
+                }
+            }
+        }
+
+        if ipv4_addr.is_unspecified() {
+            Ok(())
+        } else {
+            arp::send_gratuitous(self)
+        }
+    }
+
+    /// Changes the default gateway of the interface, dropping the ARP mapping
+    /// and any packets waiting on ARP resolution for the previous gateway.
+    pub fn set_default_gateway(&mut self, default_gateway: Ipv4Address) {
+        let prev_gateway = self.default_gateway;
+        self.default_gateway = default_gateway;
+
+        if prev_gateway != default_gateway {
+            self.arp_cache.remove(prev_gateway);
+            self.arp_pending.resolve(prev_gateway);
+        }
+    }
+
+    /// Changes the Ethernet address of the interface, sending a gratuitous ARP
+    /// so other hosts update the mapping for the interface IPv4 address.
+    pub fn set_ethernet_addr(&mut self, ethernet_addr: EthernetAddress) -> Result<()> {
+        self.ethernet_addr = ethernet_addr;
+
+        if self.ipv4_addr.is_unspecified() {
+            Ok(())
+        } else {
+            arp::send_gratuitous(self)
+        }
+    }
+
+    /// Blocks until frames may be ready to receive via dev, or the timeout
+    /// elapses if one is provided, so event loops can sleep between calls to
+    /// socket::recv(...) and socket::send(...) rather than busy polling.
+    pub fn poll_wait(&mut self, timeout: Option<Duration>) -> Result<()> {
+        self.dev.wait(timeout)
+    }
+
+    /// Returns when socket::recv(...) and socket::send(...) next need to be
+    /// called to service timers, such as TCP retransmissions and TIME_WAIT
+    /// expiry, ARP retries and probes or the shaper refilling, or None if
+    /// nothing is due until a frame is received.
+    ///
+    /// Event loops can pass poll_delay(...) to poll_wait(...) to sleep until
+    /// the next deadline rather than ticking in a tight loop.
+    ///
+    /// Clients polled alongside the interface, i.e. Dhcpv4Client, Resolver,
+    /// Mdns and PppoeClient, are not included and report their own deadlines
+    /// via poll_at(), which event loops fold in with PollAt::min(...), e.g.
+    /// interface.poll_at(..).map_or(PollAt::Ingress, PollAt::Time).min(client.poll_at()).
+    pub fn poll_at(&self, socket_set: &SocketSet) -> Option<Instant> {
+        let timers = [
+            self.arp_pending.poll_at(),
+            self.arp_claim.as_ref().and_then(|claim| claim.poll_at()),
+            self.ipv4_reassembler.poll_at(),
+        ];
+
+        // Sockets with packets to send wait for the shaper to refill.
+        let mut socket_poll_at = socket_set.poll_at();
+        if let Some(refill_at) = self.shaper.as_ref().and_then(|shaper| shaper.poll_at()) {
+            if socket_poll_at == PollAt::Now {
+                socket_poll_at = PollAt::Time(refill_at);
+            }
+        }
+
+        let poll_at = timers
+            .iter()
+            .filter_map(|timer| timer.map(PollAt::Time))
+            .fold(socket_poll_at, |poll_at, timer| poll_at.min(timer));
+
+        // Instants never precede the epoch, so the epoch is always due.
+        match poll_at {
+            PollAt::Now => Some(Instant::default()),
+            PollAt::Time(instant) => Some(instant),
+            PollAt::Ingress => None,
+        }
+    }
+
+    /// Returns the time until poll_at(...), which is zero if sockets need
+    /// servicing now.
+    #[cfg(feature = "std")]
+    pub fn poll_delay(&self, socket_set: &SocketSet) -> Option<Duration> {
+        self.poll_at(socket_set)
+            .map(|poll_at| poll_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Returns counters for frames sent and received via the interface, and
+    /// packets dropped along the way.
+    pub fn stats(&self) -> &InterfaceStats {
+        &self.stats
+    }
+
+    /// Returns MIB-II style counters for packets handled by each protocol via
+    /// the interface.
+    pub fn stack_stats(&self) -> &StackStats {
+        &self.stack_stats
+    }
+
+    /// Mirrors a frame sent or received via dev to the capture, if any, and
+    /// logs a one-line summary of it at trace level.
+    pub fn capture_frame(&mut self, direction: Direction, frame: &[u8]) {
+        if log_enabled!(Level::Trace) {
+            trace!("{:?} {}", direction, describe_frame(frame));
+        }
+
+        if let Some(ref mut capture) = self.capture {
+            capture.capture(direction, frame);
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use stack::repr::{
+    neighbor_flags,
+    EthernetAddress,
+    Icmpv6Message,
+    Icmpv6Repr,
+    Ipv6Address,
+    Ipv6Protocol,
+    Ipv6Repr,
+};
+use stack::service::{
+    icmpv6,
+    Interface,
+};
+use {
+    AddressError,
+    Error,
+    Result,
+};
+
+/// Hop limit of all Neighbor Discovery messages, which guarantees they
+/// originate on the link, see RFC 4861 section 7.1.
+pub static NDP_HOP_LIMIT: u8 = 255;
+
+/// Tries to retrieve the Ethernet address for an IPv6 address.
+///
+/// The IP address may not have an Ethernet mapping yet, in which case a
+/// neighbor solicitation is sent to the solicited node multicast group of the
+/// address and an error returned. The advertisement (if the IP address exists
+/// on the link) will be processed by `recv_message(...)` and update the
+/// neighbor cache.
+pub fn eth_addr_for_ip(
+    interface: &mut Interface,
+    ipv6_addr: Ipv6Address,
+) -> Result<EthernetAddress> {
+    match interface.neighbor_cache.eth_addr_for_ip(ipv6_addr) {
+        Some(eth_addr) => Ok(eth_addr),
+        None => {
+            debug!("Sending neighbor solicitation for {}.", ipv6_addr);
+            let source_lladdr = Some(interface.ethernet_addr);
+            send_message(
+                interface,
+                ipv6_addr.solicited_node(),
+                Icmpv6Message::NeighborSolicit {
+                    target_addr: ipv6_addr,
+                    source_lladdr,
+                },
+            )?;
+            Err(Error::Address(AddressError::NeighborResolution(ipv6_addr)))
+        }
+    }
+}
+
+/// Receives a Neighbor Discovery message from an interface.
+///
+/// This may result in neighbor advertisements in response to solicitations
+/// for the interface address, updating the neighbor cache, etc.
+pub fn recv_message(
+    interface: &mut Interface,
+    ipv6_repr: &Ipv6Repr,
+    icmp_repr: &Icmpv6Repr,
+) -> Result<()> {
+    if ipv6_repr.hop_limit != NDP_HOP_LIMIT {
+        debug!(
+            "Ignoring NDP message from {} with hop limit {}.",
+            ipv6_repr.src_addr, ipv6_repr.hop_limit
+        );
+        return Err(Error::Ignored);
+    }
+
+    match icmp_repr.message {
+        Icmpv6Message::NeighborSolicit {
+            target_addr,
+            source_lladdr,
+        } => {
+            if target_addr != *interface.ipv6_addr {
+                debug!("Ignoring neighbor solicitation for {}.", target_addr);
+                return Err(Error::Ignored);
+            }
+
+            // Solicitations from an unspecified address are part of duplicate
+            // address detection, and are answered via the all nodes group.
+            let (dst_addr, flags) = if ipv6_repr.src_addr.is_unspecified() {
+                (Ipv6Address::ALL_NODES, neighbor_flags::OVERRIDE)
+            } else {
+                if let Some(eth_addr) = source_lladdr {
+                    learn(interface, ipv6_repr.src_addr, eth_addr);
+                }
+                (
+                    ipv6_repr.src_addr,
+                    neighbor_flags::SOLICITED | neighbor_flags::OVERRIDE,
+                )
+            };
+
+            let target_lladdr = Some(interface.ethernet_addr);
+            send_message(
+                interface,
+                dst_addr,
+                Icmpv6Message::NeighborAdvert {
+                    flags,
+                    target_addr,
+                    target_lladdr,
+                },
+            )
+        }
+        Icmpv6Message::NeighborAdvert {
+            target_addr,
+            target_lladdr: Some(eth_addr),
+            ..
+        } => {
+            learn(interface, target_addr, eth_addr);
+            Ok(())
+        }
+        Icmpv6Message::RouterAdvert {
+            source_lladdr: Some(eth_addr),
+            ..
+        } => {
+            learn(interface, ipv6_repr.src_addr, eth_addr);
+            Ok(())
+        }
+        _ => Err(Error::Ignored),
+    }
+}
+
+fn learn(interface: &mut Interface, ipv6_addr: Ipv6Address, eth_addr: EthernetAddress) {
+    debug!(
+        "Received NDP, adding mapping from {} to {}.",
+        ipv6_addr, eth_addr
+    );
+    interface
+        .neighbor_cache
+        .set_eth_addr_for_ip(ipv6_addr, eth_addr);
+}
+
+fn send_message(
+    interface: &mut Interface,
+    dst_addr: Ipv6Address,
+    message: Icmpv6Message,
+) -> Result<()> {
+    let icmp_repr = Icmpv6Repr::ndp(message);
+    let ipv6_repr = Ipv6Repr {
+        src_addr: *interface.ipv6_addr,
+        dst_addr,
+        protocol: Ipv6Protocol::ICMPv6,
+        payload_len: icmp_repr.buffer_len() as u16,
+        hop_limit: NDP_HOP_LIMIT,
+        traffic_class: 0,
+        flow_label: 0,
+    };
+
+    icmpv6::send_packet(interface, &ipv6_repr, &icmp_repr, |_| {})
+}
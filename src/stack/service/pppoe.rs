@@ -0,0 +1,433 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp;
+use core::time::Duration;
+
+use stack::random::random;
+use stack::repr::{
+    eth_types,
+    ppp_protocols,
+    EthernetAddress,
+    EthernetFrame,
+    PppoeCode,
+    PppoeDiscoveryRepr,
+    PppoePacket,
+    PppoeSessionRepr,
+};
+use stack::service::{
+    ethernet,
+    ipv4,
+    Interface,
+};
+use stack::socket::{
+    RawSocket,
+    RawType,
+    SocketEnv,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+};
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+    PollAt,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+    SocketError,
+};
+
+/// Initial timeout before retransmitting a PADI or PADR, which doubles after
+/// each transmission up to MAX_RETRANSMIT_TIMEOUT_SECS, see RFC 2516.
+pub static RETRANSMIT_TIMEOUT_SECS: u64 = 2;
+
+/// Maximum timeout before retransmitting a PADI or PADR.
+pub static MAX_RETRANSMIT_TIMEOUT_SECS: u64 = 32;
+
+/// Number of PADRs sent for an offer before restarting discovery.
+pub static MAX_REQUESTS: u32 = 4;
+
+/// Bytes PPPoE adds to each IPv4 packet, i.e. the PPPoE and PPP headers.
+pub const SESSION_OVERHEAD: usize =
+    PppoePacket::<&[u8]>::HEADER_LEN + PppoeSessionRepr::PPP_HEADER_LEN;
+
+/// A PPPoE session with an access concentrator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Session {
+    pub ac_addr: EthernetAddress,
+    pub session_id: u16,
+}
+
+/// Sends a PPP frame of some protocol over the PPPoE session of an interface,
+/// e.g. for LCP or IPCP negotiation.
+///
+/// Fails with SocketError::NotConnected if the interface has no session.
+pub fn send_packet<F>(interface: &mut Interface, protocol: u16, payload_len: usize, f: F) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    let session = match interface.pppoe_session {
+        Some(session) => session,
+        None => return Err(Error::Socket(SocketError::NotConnected)),
+    };
+
+    let session_repr = PppoeSessionRepr {
+        session_id: session.session_id,
+        protocol,
+        payload_len,
+    };
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(session_repr.buffer_len());
+
+    ethernet::send_frame(interface, eth_frame_len, |eth_frame| {
+        eth_frame.set_dst_addr(session.ac_addr);
+        eth_frame.set_payload_type(eth_types::PPPOE_SESSION);
+        let mut pppoe_packet = PppoePacket::try_new(eth_frame.payload_mut()).unwrap();
+        session_repr.serialize(&mut pppoe_packet).unwrap();
+        f(pppoe_packet.ppp_payload_mut());
+    })
+}
+
+/// Receives a PPPoE session frame from an interface.
+///
+/// IPv4 packets from the access concentrator of the interface's session are
+/// decapsulated and propagated up the network stack. Other PPP protocols are
+/// left to raw Ethernet sockets.
+pub fn recv_packet(
+    interface: &mut Interface,
+    eth_frame: &EthernetFrame<&[u8]>,
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let pppoe_packet = PppoePacket::try_new(eth_frame.payload())?;
+    pppoe_packet.check_encoding()?;
+    let session_repr = PppoeSessionRepr::deserialize(&pppoe_packet)?;
+
+    let is_session = |session: Session| {
+        session.ac_addr == eth_frame.src_addr() && session.session_id == session_repr.session_id
+    };
+
+    if !interface.pppoe_session.is_some_and(is_session) {
+        debug!(
+            "Ignoring PPPoE session frame for unknown session {} from {}.",
+            session_repr.session_id,
+            eth_frame.src_addr()
+        );
+        return Err(Error::Ignored);
+    }
+
+    match session_repr.protocol {
+        ppp_protocols::IPV4 => {
+            ipv4::recv_packet_raw(interface, None, pppoe_packet.ppp_payload(), socket_set)
+        }
+        protocol => {
+            debug!("Ignoring PPP frame with protocol 0x{:04X}.", protocol);
+            Err(Error::Ignored)
+        }
+    }
+}
+
+/// Receives a PPPoE discovery frame from an interface, ending the interface's
+/// session if the frame is a PADT for it.
+///
+/// Other discovery frames are left to a PppoeClient.
+pub fn recv_discovery(interface: &mut Interface, eth_frame: &EthernetFrame<&[u8]>) -> Result<()> {
+    let pppoe_packet = PppoePacket::try_new(eth_frame.payload())?;
+    pppoe_packet.check_encoding()?;
+    let discovery_repr = PppoeDiscoveryRepr::deserialize(&pppoe_packet)?;
+
+    let session = Session {
+        ac_addr: eth_frame.src_addr(),
+        session_id: discovery_repr.session_id,
+    };
+
+    if discovery_repr.code == PppoeCode::Padt && interface.pppoe_session == Some(session) {
+        debug!("PPPoE session {} terminated by {}.", session.session_id, session.ac_addr);
+        interface.pppoe_session = None;
+        Ok(())
+    } else {
+        Err(Error::Ignored)
+    }
+}
+
+/// States of a PPPoE client, see RFC 2516 section 5.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// Waiting to start discovery.
+    Init,
+    /// Broadcasting PADIs and waiting for a PADO.
+    Discovering,
+    /// Sending PADRs to an access concentrator and waiting for a PADS.
+    Requesting,
+    /// Holding a session which has been applied to the interface.
+    Session,
+}
+
+/// A PADO from the access concentrator the client chose.
+struct Offer {
+    ac_addr: EthernetAddress,
+    ac_cookie: Option<Vec<u8>>,
+    relay_session_id: Option<Vec<u8>>,
+}
+
+/// A PPPoE client which discovers an access concentrator offering a service
+/// and establishes a session with it, via a raw Ethernet socket.
+///
+/// Once the session is applied to the interface, IPv4 packets are sent and
+/// received encapsulated in session frames. PPP link and address negotiation
+/// (LCP and IPCP) is left to the application, see send_packet(...).
+pub struct PppoeClient<T = DefaultEnv>
+where
+    T: Env,
+{
+    socket_handle: SocketHandle,
+    state: State,
+    service_name: Vec<u8>,
+    host_uniq: Vec<u8>,
+    offer: Option<Offer>,
+    transmit_at: Instant,
+    transmissions: u32,
+    time_env: T,
+}
+
+impl<T: Env> PppoeClient<T> {
+    /// Creates a client for a service, where an empty name accepts any
+    /// service, and adds a raw Ethernet socket for discovery frames to the
+    /// socket set.
+    pub fn new<E>(
+        socket_env: &SocketEnv<E>,
+        socket_set: &mut SocketSet,
+        service_name: &[u8],
+        time_env: T,
+    ) -> Result<PppoeClient<T>>
+    where
+        E: 'static + Env + Clone,
+    {
+        let mut socket = socket_env.raw_socket(RawType::Ethernet);
+        socket.set_filter(|eth_buffer| {
+            EthernetFrame::try_new(eth_buffer)
+                .is_ok_and(|eth_frame| eth_frame.payload_type() == eth_types::PPPOE_DISCOVERY)
+        });
+
+        let socket_handle = socket_set.add_socket(TaggedSocket::Raw(socket))?;
+
+        let transmit_at = time_env.now_instant();
+
+        Ok(PppoeClient {
+            socket_handle,
+            state: State::Init,
+            service_name: service_name.to_vec(),
+            host_uniq: random::<u32>().to_be_bytes().to_vec(),
+            offer: None,
+            transmit_at,
+            transmissions: 0,
+            time_env,
+        })
+    }
+
+    /// Returns the handle of the socket used by the client.
+    pub fn socket_handle(&self) -> SocketHandle {
+        self.socket_handle
+    }
+
+    /// Returns the current state of the client.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Returns when poll(...) next needs to be called to send or retransmit a
+    /// PADI or PADR. A session is only serviced on ingress.
+    pub fn poll_at(&self) -> PollAt {
+        match self.state {
+            State::Init => PollAt::Now,
+            State::Discovering | State::Requesting => PollAt::Time(self.transmit_at),
+            State::Session => PollAt::Ingress,
+        }
+    }
+
+    /// Processes discovery frames received by the socket and enqueues any
+    /// PADI or PADR due to be sent. The session is applied to the interface
+    /// once confirmed, and discovery restarts if the interface loses it.
+    ///
+    /// This should be called regularly, before sending packets via the socket
+    /// set.
+    pub fn poll(&mut self, interface: &mut Interface, socket_set: &mut SocketSet) -> Result<()> {
+        let socket = socket_set.socket(self.socket_handle)?.as_raw_socket();
+
+        loop {
+            let (ac_addr, discovery_repr) = match socket.recv() {
+                Ok(eth_buffer) => match parse(eth_buffer) {
+                    Ok(parsed) => parsed,
+                    Err(err) => {
+                        debug!("Ignoring PPPoE discovery frame with {:?}.", err);
+                        continue;
+                    }
+                },
+                Err(Error::Buffer(BufferError::Empty)) => break,
+                Err(err) => {
+                    debug!("Error receiving PPPoE discovery frame with {:?}.", err);
+                    continue;
+                }
+            };
+
+            self.recv(interface, ac_addr, &discovery_repr);
+        }
+
+        let now = self.time_env.now_instant();
+
+        if self.state == State::Session && interface.pppoe_session.is_none() {
+            debug!("PPPoE session lost, restarting discovery.");
+            self.state = State::Init;
+        }
+
+        if self.state == State::Requesting && self.transmissions >= MAX_REQUESTS && now >= self.transmit_at {
+            debug!("PPPoE request timed out, restarting discovery.");
+            self.state = State::Init;
+        }
+
+        if self.state == State::Init {
+            self.offer = None;
+            self.start(State::Discovering, now);
+        }
+
+        if let Some((discovery_repr, dst_addr)) = self.transmit() {
+            debug!(
+                "Sending PPPoE {:?} to {} in state {:?}.",
+                discovery_repr.code, dst_addr, self.state
+            );
+            send_discovery(interface.ethernet_addr, socket, dst_addr, &discovery_repr)?;
+        }
+
+        Ok(())
+    }
+
+    /// Ends the session with a PADT and removes it from the interface. The
+    /// next poll restarts discovery.
+    pub fn terminate(&mut self, interface: &mut Interface, socket_set: &mut SocketSet) -> Result<()> {
+        self.state = State::Init;
+
+        let session = match interface.pppoe_session.take() {
+            Some(session) => session,
+            None => return Ok(()),
+        };
+
+        let socket = socket_set.socket(self.socket_handle)?.as_raw_socket();
+        let discovery_repr = PppoeDiscoveryRepr::new(PppoeCode::Padt, session.session_id);
+        send_discovery(interface.ethernet_addr, socket, session.ac_addr, &discovery_repr)
+    }
+
+    /// Handles a discovery frame from an access concentrator.
+    fn recv(&mut self, interface: &mut Interface, ac_addr: EthernetAddress, discovery_repr: &PppoeDiscoveryRepr) {
+        if discovery_repr.host_uniq.as_ref() != Some(&self.host_uniq) {
+            return;
+        }
+
+        if let Some((tag_type, ref value)) = discovery_repr.error {
+            debug!(
+                "Received PPPoE {:?} from {} with error 0x{:04X} {:?}.",
+                discovery_repr.code,
+                ac_addr,
+                tag_type,
+                String::from_utf8_lossy(value)
+            );
+            if self.state == State::Requesting {
+                self.state = State::Init;
+            }
+            return;
+        }
+
+        let now = self.time_env.now_instant();
+
+        match (self.state, discovery_repr.code) {
+            (State::Discovering, PppoeCode::Pado) => {
+                debug!(
+                    "Received PPPoE offer from {} ({:?}).",
+                    ac_addr,
+                    discovery_repr.ac_name.as_ref().map(|name| String::from_utf8_lossy(name))
+                );
+                self.offer = Some(Offer {
+                    ac_addr,
+                    ac_cookie: discovery_repr.ac_cookie.clone(),
+                    relay_session_id: discovery_repr.relay_session_id.clone(),
+                });
+                self.start(State::Requesting, now);
+            }
+            (State::Requesting, PppoeCode::Pads)
+                if discovery_repr.session_id != 0
+                    && self.offer.as_ref().is_some_and(|offer| offer.ac_addr == ac_addr) =>
+            {
+                let session = Session {
+                    ac_addr,
+                    session_id: discovery_repr.session_id,
+                };
+                debug!("Established PPPoE session {:?}.", session);
+                interface.pppoe_session = Some(session);
+                self.state = State::Session;
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns a discovery frame and destination address if one is due to be
+    /// sent in the current state.
+    fn transmit(&mut self) -> Option<(PppoeDiscoveryRepr, EthernetAddress)> {
+        let now = self.time_env.now_instant();
+        if now < self.transmit_at {
+            return None;
+        }
+
+        let mut discovery_repr = PppoeDiscoveryRepr::new(PppoeCode::Padi, 0);
+        discovery_repr.service_name = Some(self.service_name.clone());
+        discovery_repr.host_uniq = Some(self.host_uniq.clone());
+
+        let dst_addr = match (self.state, self.offer.as_ref()) {
+            (State::Discovering, _) => EthernetAddress::BROADCAST,
+            (State::Requesting, Some(offer)) => {
+                discovery_repr.code = PppoeCode::Padr;
+                discovery_repr.ac_cookie = offer.ac_cookie.clone();
+                discovery_repr.relay_session_id = offer.relay_session_id.clone();
+                offer.ac_addr
+            }
+            _ => return None,
+        };
+
+        let timeout = RETRANSMIT_TIMEOUT_SECS << cmp::min(self.transmissions, 4);
+        self.transmissions += 1;
+        self.transmit_at = now + Duration::from_secs(cmp::min(timeout, MAX_RETRANSMIT_TIMEOUT_SECS));
+
+        Some((discovery_repr, dst_addr))
+    }
+
+    /// Moves to a state which sends frames, starting immediately.
+    fn start(&mut self, state: State, now: Instant) {
+        self.state = state;
+        self.transmissions = 0;
+        self.transmit_at = now;
+    }
+}
+
+/// Parses a discovery frame into the source address and discovery packet.
+fn parse(eth_buffer: &[u8]) -> Result<(EthernetAddress, PppoeDiscoveryRepr)> {
+    let eth_frame = EthernetFrame::try_new(eth_buffer)?;
+    let pppoe_packet = PppoePacket::try_new(eth_frame.payload())?;
+    pppoe_packet.check_encoding()?;
+    Ok((eth_frame.src_addr(), PppoeDiscoveryRepr::deserialize(&pppoe_packet)?))
+}
+
+/// Enqueues a discovery frame on a raw Ethernet socket.
+fn send_discovery(
+    eth_addr: EthernetAddress,
+    socket: &mut RawSocket,
+    dst_addr: EthernetAddress,
+    discovery_repr: &PppoeDiscoveryRepr,
+) -> Result<()> {
+    let eth_frame_len = EthernetFrame::<&[u8]>::buffer_len(discovery_repr.buffer_len());
+    let eth_buffer = socket.send(eth_frame_len)?;
+
+    let mut eth_frame = EthernetFrame::try_new(eth_buffer)?;
+    eth_frame.set_src_addr(eth_addr);
+    eth_frame.set_dst_addr(dst_addr);
+    eth_frame.set_payload_type(eth_types::PPPOE_DISCOVERY);
+    discovery_repr.serialize(&mut PppoePacket::try_new(eth_frame.payload_mut())?)
+}
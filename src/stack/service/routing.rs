@@ -0,0 +1,118 @@
+use alloc::vec::Vec;
+
+use stack::repr::{
+    Ipv4Address,
+    Ipv4AddressCidr,
+};
+
+/// A route directing packets for a subnet through a gateway.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub subnet: Ipv4AddressCidr,
+    pub gateway: Ipv4Address,
+}
+
+/// A set of static IPv4 routes.
+///
+/// Routes are matched by longest prefix, so a route for a /24 subnet takes
+/// precedence over a route for an enclosing /16 subnet.
+#[derive(Clone, Debug, Default)]
+pub struct RoutingTable {
+    routes: Vec<Route>,
+}
+
+impl RoutingTable {
+    /// Creates an empty routing table.
+    pub fn new() -> RoutingTable {
+        RoutingTable { routes: Vec::new() }
+    }
+
+    /// Adds a route for packets destined to a subnet, replacing any existing
+    /// route for the same subnet.
+    pub fn add_route(&mut self, subnet: Ipv4AddressCidr, gateway: Ipv4Address) {
+        let subnet = Ipv4AddressCidr::new(subnet.network(), subnet.subnet_len());
+        self.routes.retain(|route| route.subnet != subnet);
+        self.routes.push(Route { subnet, gateway });
+    }
+
+    /// Removes the route for a subnet, returning the gateway of the route if
+    /// there was one.
+    pub fn remove_route(&mut self, subnet: Ipv4AddressCidr) -> Option<Ipv4Address> {
+        let subnet = Ipv4AddressCidr::new(subnet.network(), subnet.subnet_len());
+        let position = self
+            .routes
+            .iter()
+            .position(|route| route.subnet == subnet)?;
+        Some(self.routes.remove(position).gateway)
+    }
+
+    /// Returns the gateway of the most specific route for an address.
+    pub fn lookup(&self, address: Ipv4Address) -> Option<Ipv4Address> {
+        self.routes
+            .iter()
+            .filter(|route| route.subnet.is_member(address))
+            .max_by_key(|route| route.subnet.subnet_len())
+            .map(|route| route.gateway)
+    }
+
+    /// Returns the routes in the table.
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ipv4(a: u8, b: u8, c: u8, d: u8) -> Ipv4Address {
+        Ipv4Address::new([a, b, c, d])
+    }
+
+    #[test]
+    fn test_lookup_longest_prefix() {
+        let mut routing_table = RoutingTable::new();
+        routing_table.add_route(
+            Ipv4AddressCidr::new(ipv4(10, 1, 0, 0), 16),
+            ipv4(10, 0, 0, 2),
+        );
+        routing_table.add_route(
+            Ipv4AddressCidr::new(ipv4(10, 1, 2, 0), 24),
+            ipv4(10, 0, 0, 3),
+        );
+
+        assert_eq!(
+            routing_table.lookup(ipv4(10, 1, 2, 1)),
+            Some(ipv4(10, 0, 0, 3))
+        );
+        assert_eq!(
+            routing_table.lookup(ipv4(10, 1, 3, 1)),
+            Some(ipv4(10, 0, 0, 2))
+        );
+        assert_eq!(routing_table.lookup(ipv4(10, 2, 0, 1)), None);
+    }
+
+    #[test]
+    fn test_add_and_remove_route() {
+        let mut routing_table = RoutingTable::new();
+        routing_table.add_route(
+            Ipv4AddressCidr::new(ipv4(10, 1, 2, 3), 16),
+            ipv4(10, 0, 0, 2),
+        );
+        routing_table.add_route(
+            Ipv4AddressCidr::new(ipv4(10, 1, 0, 0), 16),
+            ipv4(10, 0, 0, 3),
+        );
+        assert_eq!(routing_table.routes().len(), 1);
+
+        assert_eq!(
+            routing_table.remove_route(Ipv4AddressCidr::new(ipv4(10, 1, 0, 0), 16)),
+            Some(ipv4(10, 0, 0, 3))
+        );
+        assert_eq!(routing_table.lookup(ipv4(10, 1, 2, 1)), None);
+        assert_eq!(
+            routing_table.remove_route(Ipv4AddressCidr::new(ipv4(10, 1, 0, 0), 16)),
+            None
+        );
+    }
+}
@@ -0,0 +1,144 @@
+use core::cmp::min;
+use core::time::Duration;
+
+use stack::time::{
+    DefaultEnv,
+    Env,
+    Instant,
+};
+
+const NANOS_PER_SEC: u128 = 1_000_000_000;
+
+/// A token bucket pacing packets sent from sockets via an interface.
+///
+/// The bucket holds up to burst bytes worth of tokens and is refilled at
+/// bytes_per_sec. Sockets may send while the bucket holds any tokens, and
+/// every frame sent via the interface consumes its length in tokens. The
+/// bucket may thus go into debt by up to a frame, which is paid back before
+/// sockets may send again.
+#[derive(Debug)]
+pub struct Shaper<T = DefaultEnv>
+where
+    T: Env,
+{
+    bytes_per_sec: u64,
+    burst: u64,
+    tokens: i64,
+    refilled_at: Instant,
+    time_env: T,
+}
+
+impl<T: Env> Shaper<T> {
+    /// Creates a shaper with a full bucket.
+    pub fn new(bytes_per_sec: u64, burst: u64, time_env: T) -> Shaper<T> {
+        Shaper {
+            bytes_per_sec,
+            burst,
+            tokens: burst as i64,
+            refilled_at: time_env.now_instant(),
+            time_env,
+        }
+    }
+
+    /// Changes the refill rate and bucket size.
+    pub fn set_rate(&mut self, bytes_per_sec: u64, burst: u64) {
+        self.refill();
+        self.bytes_per_sec = bytes_per_sec;
+        self.burst = burst;
+        self.tokens = min(self.tokens, burst as i64);
+    }
+
+    /// Checks if sockets may send, i.e. the bucket is not empty.
+    pub fn can_send(&mut self) -> bool {
+        self.refill();
+        self.tokens > 0
+    }
+
+    /// Consumes tokens for a frame sent via the interface.
+    pub fn consume(&mut self, len: usize) {
+        self.refill();
+        self.tokens -= len as i64;
+    }
+
+    /// Returns when the bucket will next hold tokens, or None if it already
+    /// does or is never refilled.
+    pub fn poll_at(&self) -> Option<Instant> {
+        if self.tokens > 0 || self.bytes_per_sec == 0 {
+            return None;
+        }
+
+        let deficit = (1 - self.tokens) as u128;
+        let nanos = (deficit * NANOS_PER_SEC).div_ceil(self.bytes_per_sec as u128);
+        Some(self.refilled_at + Duration::from_nanos(nanos as u64))
+    }
+
+    fn refill(&mut self) {
+        let now = self.time_env.now_instant();
+        if self.bytes_per_sec == 0 {
+            self.refilled_at = now;
+            return;
+        }
+
+        // Only advance by whole tokens so partial progress is not lost.
+        let elapsed = now - self.refilled_at;
+        let elapsed_nanos = elapsed.as_secs() as u128 * NANOS_PER_SEC + elapsed.subsec_nanos() as u128;
+        let new_tokens = elapsed_nanos * self.bytes_per_sec as u128 / NANOS_PER_SEC;
+
+        if self.tokens as i128 + new_tokens as i128 >= self.burst as i128 {
+            self.tokens = self.burst as i64;
+            self.refilled_at = now;
+        } else {
+            self.tokens += new_tokens as i64;
+            let nanos = new_tokens * NANOS_PER_SEC / self.bytes_per_sec as u128;
+            self.refilled_at += Duration::from_nanos(nanos as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stack::time::MockEnv;
+
+    #[test]
+    fn test_shaper_burst_and_refill() {
+        let mut shaper = Shaper::new(1000, 1500, MockEnv::new());
+
+        assert!(shaper.can_send());
+        shaper.consume(1000);
+        assert!(shaper.can_send());
+        shaper.consume(1000);
+        assert!(!shaper.can_send());
+
+        // 500 bytes in debt, so the next token arrives after 501ms.
+        assert_eq!(
+            shaper.poll_at(),
+            Some(shaper.time_env.now + Duration::from_millis(501))
+        );
+
+        shaper.time_env.now += Duration::from_millis(500);
+        assert!(!shaper.can_send());
+        shaper.time_env.now += Duration::from_millis(1);
+        assert!(shaper.can_send());
+        assert_eq!(shaper.poll_at(), None);
+
+        // Tokens never exceed the burst.
+        shaper.time_env.now += Duration::from_secs(10);
+        shaper.consume(1500);
+        assert!(!shaper.can_send());
+    }
+
+    #[test]
+    fn test_shaper_set_rate() {
+        let mut shaper = Shaper::new(0, 100, MockEnv::new());
+        shaper.consume(100);
+
+        shaper.time_env.now += Duration::from_secs(1);
+        assert!(!shaper.can_send());
+        assert_eq!(shaper.poll_at(), None);
+
+        shaper.set_rate(100, 100);
+        shaper.time_env.now += Duration::from_millis(10);
+        assert!(shaper.can_send());
+    }
+}
@@ -0,0 +1,72 @@
+use core::ops::Range;
+
+use stack::service::Interface;
+use stack::storage::PacketBuf;
+use {
+    Error,
+    ParseError,
+    Result,
+};
+
+/// A transform applied to whole Ethernet frames between the Ethernet service
+/// and the device of an interface, e.g. encrypting and authenticating frames
+/// when prototyping a VPN.
+///
+/// Frames are sent from buffers which reserve headroom() bytes before and
+/// tailroom() bytes after the frame, so shims may push(...) headers and
+/// put(...) trailers in place rather than copying the frame.
+pub trait Shim {
+    /// Returns the bytes reserved before each frame sent.
+    fn headroom(&self) -> usize;
+
+    /// Returns the bytes reserved after each frame sent.
+    fn tailroom(&self) -> usize;
+
+    /// Transforms a frame in place before it is sent.
+    ///
+    /// Errors drop the frame and are returned to the sender.
+    fn encapsulate(&mut self, frame: &mut PacketBuf) -> Result<()>;
+
+    /// Transforms a buffer received from the device, returning the range of
+    /// the buffer holding the frame.
+    ///
+    /// Errors drop the buffer, e.g. if it fails authentication.
+    fn decapsulate(&mut self, buffer: &mut [u8]) -> Result<Range<usize>>;
+}
+
+/// Returns the bytes the shim of an interface adds to each frame, if any.
+pub fn overhead(interface: &Interface) -> usize {
+    headroom(interface) + tailroom(interface)
+}
+
+/// Returns the bytes reserved before each frame sent via an interface.
+pub fn headroom(interface: &Interface) -> usize {
+    interface.shim.as_ref().map_or(0, |shim| shim.headroom())
+}
+
+/// Returns the bytes reserved after each frame sent via an interface.
+pub fn tailroom(interface: &Interface) -> usize {
+    interface.shim.as_ref().map_or(0, |shim| shim.tailroom())
+}
+
+/// Transforms a frame with the shim of an interface before it is sent. See
+/// Shim::encapsulate(...).
+pub fn encapsulate(interface: &mut Interface, frame: &mut PacketBuf) -> Result<()> {
+    match interface.shim {
+        Some(ref mut shim) => shim.encapsulate(frame),
+        None => Ok(()),
+    }
+}
+
+/// Transforms a buffer received via an interface with its shim, returning the
+/// frame. See Shim::decapsulate(...).
+pub fn decapsulate<'a>(interface: &mut Interface, buffer: &'a mut [u8]) -> Result<&'a mut [u8]> {
+    let range = match interface.shim {
+        Some(ref mut shim) => shim.decapsulate(buffer)?,
+        None => return Ok(buffer),
+    };
+
+    buffer
+        .get_mut(range)
+        .ok_or(Error::Parse(ParseError::Malformed))
+}
@@ -0,0 +1,503 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use stack::capture::Direction;
+use stack::check::checksum_adjust;
+use stack::repr::{
+    ipv4_protocols,
+    EthernetFrame,
+    Ipv4Address,
+    Ipv4Packet,
+    TcpPacket,
+    TcpRepr,
+    UdpPacket,
+};
+use stack::service::{
+    arp,
+    ethernet,
+    icmpv4,
+    igmp,
+    ipv4,
+    shim,
+    tcp,
+    udp,
+    Interface,
+    InterfaceSet,
+};
+use stack::socket::bindings::flow_hash;
+use stack::socket::{
+    IcmpSocket,
+    RawSocket,
+    RawType,
+    SocketAddr,
+    SocketSet,
+    TaggedSocket,
+    TaggedSocketAddr,
+    TcpListener,
+    TcpSocket,
+    UdpSocket,
+};
+use {
+    AddressError,
+    BufferError,
+    DeviceError,
+    Error,
+    Result,
+};
+
+/// Maximum number of frames read from a device at once.
+const RECV_BATCH_LEN: usize = 16;
+
+/// Interfaces packets from sockets may be sent via.
+trait Egress {
+    /// Invokes a callback with each interface.
+    fn for_each_interface<F: FnMut(&mut Interface)>(&mut self, f: F);
+
+    /// Returns the interface to send a packet via from a socket which may be
+    /// pinned to an interface, by destination if the packet has one.
+    fn egress(
+        &mut self,
+        pinned_interface: Option<usize>,
+        dst_addr: Option<Ipv4Address>,
+    ) -> Option<&mut Interface>;
+}
+
+impl Egress for Interface {
+    fn for_each_interface<F: FnMut(&mut Interface)>(&mut self, mut f: F) {
+        f(self)
+    }
+
+    fn egress(&mut self, _: Option<usize>, _: Option<Ipv4Address>) -> Option<&mut Interface> {
+        Some(self)
+    }
+}
+
+impl Egress for InterfaceSet {
+    fn for_each_interface<F: FnMut(&mut Interface)>(&mut self, f: F) {
+        self.iter_mut().for_each(f)
+    }
+
+    fn egress(
+        &mut self,
+        pinned_interface: Option<usize>,
+        dst_addr: Option<Ipv4Address>,
+    ) -> Option<&mut Interface> {
+        let interface_handle = match (pinned_interface, dst_addr) {
+            (Some(interface_handle), _) => interface_handle,
+            (None, Some(dst_addr)) => self.route(dst_addr)?,
+            (None, None) => self.default_interface()?,
+        };
+
+        self.iter_mut().nth(interface_handle)
+    }
+}
+
+/// Sends out as many socket enqueued packets as possible via an interface.
+///
+/// Sockets pinned to an interface are ignored, use send_all(...) with an
+/// InterfaceSet to respect pinning.
+pub fn send(interface: &mut Interface, socket_set: &mut SocketSet) {
+    send_via(interface, socket_set);
+}
+
+/// Sends out as many socket enqueued packets as possible via a set of
+/// interfaces.
+///
+/// Packets from sockets pinned to an interface are sent via that interface,
+/// and all other packets are sent via the interface chosen by
+/// InterfaceSet::route(...) for their destination.
+pub fn send_all(interfaces: &mut InterfaceSet, socket_set: &mut SocketSet) {
+    send_via(interfaces, socket_set);
+}
+
+fn send_via<E: Egress>(egress: &mut E, socket_set: &mut SocketSet) {
+    egress.for_each_interface(|interface| {
+        // Queue frames so the device can send them together.
+        ethernet::begin_batch(interface);
+
+        arp::poll(interface);
+    });
+
+    // Iterate over the sockets in round robin fashion (to avoid starvation) and
+    // try to send a packet for each socket. Stop sending packets once we encounter
+    // an error for each socket. This implies either (1) all the sockets have been
+    // exhausted or (2) the device is busy.
+    loop {
+        let sockets = socket_set.len();
+        let mut errors = 0;
+
+        for (pinned, socket) in socket_set.iter_mut_pinned() {
+            let ok_or_err = match *socket {
+                TaggedSocket::Raw(ref mut socket) => send_raw_socket(egress, pinned, socket),
+                TaggedSocket::Icmp(ref mut socket) => send_icmp_socket(egress, pinned, socket),
+                TaggedSocket::Tcp(ref mut socket) => send_tcp_socket(egress, pinned, socket),
+                TaggedSocket::TcpListener(ref mut socket) => {
+                    send_tcp_listener(egress, pinned, socket)
+                }
+                TaggedSocket::Udp(ref mut socket) => send_udp_socket(egress, pinned, socket),
+            };
+
+            match ok_or_err {
+                Ok(_) => {}
+                Err(Error::Device(err)) => {
+                    debug!(
+                        "Device has encountered an error, probably exhausted {:?}.",
+                        err
+                    );
+                    // Force exit from outer loop.
+                    errors = sockets;
+                    break;
+                }
+                Err(Error::Buffer(BufferError::Empty)) => {
+                    // These occur when the sockets are empty, let's not make our log useless
+                    // with a flood of these errors.
+                    errors += 1;
+                }
+                Err(err) => {
+                    warn!("Error sending packet with {:?}.", err);
+                    errors += 1;
+                }
+            }
+        }
+
+        if errors >= sockets {
+            break;
+        }
+    }
+
+    egress.for_each_interface(|interface| {
+        if let Err(err) = ethernet::end_batch(interface) {
+            debug!(
+                "Device has encountered an error, probably exhausted {:?}.",
+                err
+            );
+        }
+    });
+}
+
+/// Returns the interface to send a packet via, see Egress::egress(...).
+///
+/// Fails with DeviceError::WouldBlock while the shaper of the interface is
+/// empty, so the socket keeps the packet until the next call to send(...).
+fn egress_interface<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    dst_addr: Option<Ipv4Address>,
+) -> Result<&mut Interface> {
+    let interface = egress
+        .egress(pinned, dst_addr)
+        .ok_or(Error::Device(DeviceError::NoInterface))?;
+
+    if let Some(ref mut shaper) = interface.shaper {
+        if !shaper.can_send() {
+            return Err(Error::Device(DeviceError::WouldBlock));
+        }
+    }
+
+    Ok(interface)
+}
+
+fn send_raw_socket<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    socket: &mut RawSocket,
+) -> Result<()> {
+    match socket.raw_type() {
+        RawType::Ethernet => {
+            socket.send_dequeue(|eth_buffer| {
+                let interface = egress_interface(egress, pinned, None)?;
+                ethernet::send_frame(interface, eth_buffer.len(), |eth_frame| {
+                    // NOTE: We overwrite the MAC source address so the socket user should
+                    // ensure this is set correctly in the frame they are writing.
+                    eth_frame.as_mut().copy_from_slice(eth_buffer);
+                })
+            })
+        }
+        RawType::Ipv4 => {
+            let spoofing = socket.spoofing();
+            socket.send_dequeue(|ipv4_buffer| {
+                let (src_addr, dst_addr) = match Ipv4Packet::try_new(ipv4_buffer) {
+                    Ok(ipv4_packet) => (ipv4_packet.src_addr(), ipv4_packet.dst_addr()),
+                    Err(_) => {
+                        warn!("Raw socket attempted to send a malformed IPv4 packet.");
+                        return Ok(());
+                    }
+                };
+
+                let interface = egress_interface(egress, pinned, Some(dst_addr))?;
+
+                // Dropping rather than failing the packet keeps it from
+                // wedging the head of the send buffer.
+                if !spoofing && src_addr != *interface.ipv4_addr && !src_addr.is_unspecified() {
+                    warn!(
+                        "Dropping IPv4 packet from {} sent by a raw socket without spoofing.",
+                        src_addr
+                    );
+                    return Ok(());
+                }
+
+                let ipv4_addr = *interface.ipv4_addr;
+                ipv4::send_packet_raw(interface, dst_addr, ipv4_buffer.len(), |ipv4_packet| {
+                    ipv4_packet.copy_from_slice(ipv4_buffer);
+                    if !spoofing && src_addr != ipv4_addr {
+                        let mut ipv4_packet = Ipv4Packet::try_new(ipv4_packet).unwrap();
+                        ipv4_packet.set_src_addr(ipv4_addr);
+                        ipv4_packet.fill_checksum();
+                        adjust_transport_checksum(&mut ipv4_packet, src_addr);
+                    }
+                })
+            })
+        }
+    }
+}
+
+/// Adjusts the TCP or UDP checksum of a packet after its source address
+/// changed from old_addr, since the checksum covers the address via the
+/// pseudo-header.
+fn adjust_transport_checksum(ipv4_packet: &mut Ipv4Packet<&mut [u8]>, old_addr: Ipv4Address) {
+    // Only the first fragment carries the transport header.
+    if ipv4_packet.fragment_offset() != 0 {
+        return;
+    }
+
+    let (protocol, new_addr) = (ipv4_packet.protocol(), ipv4_packet.src_addr());
+    let payload = ipv4_packet.payload_mut();
+    match protocol {
+        ipv4_protocols::TCP => {
+            if let Ok(mut tcp_packet) = TcpPacket::try_new(payload) {
+                let checksum = checksum_adjust(
+                    tcp_packet.checksum(),
+                    old_addr.as_bytes(),
+                    new_addr.as_bytes(),
+                );
+                tcp_packet.set_checksum(checksum);
+            }
+        }
+        ipv4_protocols::UDP => {
+            if let Ok(mut udp_packet) = UdpPacket::try_new(payload) {
+                // A zero checksum means the sender did not compute one, and a
+                // computed checksum of zero is sent as all ones, see RFC 768.
+                if udp_packet.checksum() != 0 {
+                    let checksum = checksum_adjust(
+                        udp_packet.checksum(),
+                        old_addr.as_bytes(),
+                        new_addr.as_bytes(),
+                    );
+                    udp_packet.set_checksum(if checksum == 0 { 0xFFFF } else { checksum });
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn send_icmp_socket<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    socket: &mut IcmpSocket,
+) -> Result<()> {
+    socket.send_dequeue(|ipv4_repr, icmp_repr, payload| {
+        let interface = egress_interface(egress, pinned, Some(ipv4_repr.dst_addr))?;
+        icmpv4::send_packet(interface, ipv4_repr, icmp_repr, |payload_| {
+            payload_.copy_from_slice(payload);
+        })
+    })
+}
+
+fn send_tcp_socket<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    socket: &mut TcpSocket,
+) -> Result<()> {
+    let retransmits = socket.stats().retransmits;
+    let mut sent_syn = false;
+
+    let dst_addr = socket.send_dequeue(|ipv4_repr, tcp_repr, payload| {
+        let interface = egress_interface(egress, pinned, Some(ipv4_repr.dst_addr))?;
+        tcp::send_packet(interface, ipv4_repr, tcp_repr, |payload_| {
+            payload_.copy_from_slice(payload);
+        })?;
+
+        sent_syn = tcp_repr.flags[TcpRepr::FLAG_SYN] && !tcp_repr.flags[TcpRepr::FLAG_ACK];
+        Ok(ipv4_repr.dst_addr)
+    })?;
+
+    // Only the socket knows if a segment was a retransmission.
+    let retransmitted = socket.stats().retransmits.saturating_sub(retransmits);
+    if let Some(interface) = egress.egress(pinned, Some(dst_addr)) {
+        interface.stack_stats.tcp.retrans_segs += retransmitted;
+        if sent_syn && retransmitted == 0 {
+            interface.stack_stats.tcp.active_opens += 1;
+        }
+    }
+
+    Ok(())
+}
+
+fn send_tcp_listener<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    socket: &mut TcpListener,
+) -> Result<()> {
+    socket.send_dequeue(|ipv4_repr, tcp_repr, payload| {
+        let interface = egress_interface(egress, pinned, Some(ipv4_repr.dst_addr))?;
+        tcp::send_packet(interface, ipv4_repr, tcp_repr, |payload_| {
+            payload_.copy_from_slice(payload);
+        })
+    })
+}
+
+fn send_udp_socket<E: Egress>(
+    egress: &mut E,
+    pinned: Option<usize>,
+    socket: &mut UdpSocket,
+) -> Result<()> {
+    // Multicast groups are joined via the pinned or default interface.
+    if let Some(interface) = egress.egress(pinned, None) {
+        for group_addr in socket.multicast_groups() {
+            if !interface.ipv4_multicast_groups.contains(group_addr) {
+                igmp::join_multicast_group(interface, *group_addr)?;
+            }
+        }
+    }
+
+    // Drain as many packets as possible per call to spare the send loop a
+    // round trip per packet.
+    let broadcast = socket.broadcast();
+    socket.send_dequeue_many(|ipv4_repr, udp_repr, payload| {
+        let interface = egress_interface(egress, pinned, Some(ipv4_repr.dst_addr))?;
+        if !broadcast && ipv4::is_broadcast(interface, ipv4_repr.dst_addr) {
+            warn!(
+                "Dropping UDP packet to {} from a socket without broadcast enabled.",
+                ipv4_repr.dst_addr
+            );
+            return Ok(());
+        }
+
+        // Sockets bound to 0.0.0.0 send from the interface address, which is
+        // itself 0.0.0.0 while the interface is unconfigured, e.g. for DHCP.
+        let mut ipv4_repr = *ipv4_repr;
+        if ipv4_repr.src_addr.is_unspecified() {
+            ipv4_repr.src_addr = *interface.ipv4_addr;
+        }
+
+        udp::send_packet(interface, &ipv4_repr, udp_repr, |payload_| {
+            payload_.copy_from_slice(payload);
+        })
+    })?;
+
+    Ok(())
+}
+
+/// Reads frames from each interface of a set and forwards packets to the
+/// appropriate sockets, skipping sockets pinned to other interfaces.
+pub fn recv_all(interfaces: &mut InterfaceSet, socket_set: &mut SocketSet) {
+    for (interface_handle, interface) in interfaces.iter_mut().enumerate() {
+        socket_set.set_receiving_interface(Some(interface_handle));
+        recv(interface, socket_set);
+    }
+
+    socket_set.set_receiving_interface(None);
+}
+
+/// Reads frames from an interface and forwards packets to the appropriate
+/// sockets.
+pub fn recv(interface: &mut Interface, socket_set: &mut SocketSet) {
+    // Leave room for an Ethernet header on top of the MTU since links such as
+    // TAP devices report the MTU of the IP layer, and peers will fill it with
+    // full sized packets (or fragments).
+    let eth_buffer_len = interface.dev.max_transmission_unit() + EthernetFrame::<&[u8]>::HEADER_LEN;
+    let mut eth_buffers = interface.pool.take(eth_buffer_len * RECV_BATCH_LEN);
+    let mut eth_buffer_lens = [0; RECV_BATCH_LEN];
+
+    loop {
+        let frames = {
+            let mut buffers: Vec<&mut [u8]> = eth_buffers.chunks_mut(eth_buffer_len).collect();
+            match interface.dev.recv_batch(&mut buffers, &mut eth_buffer_lens) {
+                Ok(frames) => frames,
+                Err(Error::Device(_)) => break,
+                Err(err) => {
+                    warn!("Error receiving Ethernet frames with {:?}.", err);
+                    break;
+                }
+            }
+        };
+
+        let eth_buffers = eth_buffers
+            .chunks_mut(eth_buffer_len)
+            .zip(&eth_buffer_lens[.. frames])
+            .map(|(eth_buffer, &buffer_len)| &mut eth_buffer[.. buffer_len]);
+
+        for eth_buffer in eth_buffers {
+            interface.stats.record_rx(eth_buffer.len());
+            interface.capture_frame(Direction::Received, eth_buffer);
+
+            let eth_buffer = match shim::decapsulate(interface, eth_buffer) {
+                Ok(eth_buffer) => eth_buffer,
+                Err(err) => {
+                    interface.stats.record_rx_error(&err);
+                    warn!("Error decapsulating Ethernet frame with {:?}", err);
+                    continue;
+                }
+            };
+
+            match ethernet::recv_frame(interface, eth_buffer, socket_set) {
+                Ok(_) => continue,
+                Err(Error::Ignored) => continue,
+                Err(Error::Address(AddressError::MacResolution(_))) => continue,
+                Err(err) => {
+                    interface.stats.record_rx_error(&err);
+                    warn!("Error processing Ethernet frame with {:?}", err);
+                }
+            }
+        }
+    }
+
+    interface.pool.give(eth_buffers);
+    ipv4::expire_fragments(interface);
+}
+
+/// Picks which of the sockets bound to the destination of a packet receive
+/// it, given the binding each socket shares with other sockets via
+/// reuse_port, or None for sockets which receive packets as usual.
+///
+/// Of the sockets sharing each binding, only one picked by hashing the
+/// (source, destination) addresses of the packet receives it, so flows are
+/// spread across the sockets while each flow sticks to one of them. Returns
+/// whether each socket yielded by SocketSet::iter_mut_bound(...) receives the
+/// packet, or None if no socket shares its binding and all of them do.
+pub fn reuse_port_receivers<F>(
+    socket_set: &mut SocketSet,
+    src_addr: &SocketAddr,
+    dst_addr: &TaggedSocketAddr,
+    shared_binding: F,
+) -> Option<Vec<bool>>
+where
+    F: Fn(&TaggedSocket) -> Option<TaggedSocketAddr>,
+{
+    if !socket_set
+        .iter_mut_bound(dst_addr, src_addr)
+        .any(|socket| shared_binding(socket).is_some())
+    {
+        return None;
+    }
+
+    let mut receivers = Vec::new();
+    let mut groups: BTreeMap<TaggedSocketAddr, Vec<usize>> = BTreeMap::new();
+    for (position, socket) in socket_set.iter_mut_bound(dst_addr, src_addr).enumerate() {
+        match shared_binding(socket) {
+            Some(binding) => {
+                groups.entry(binding).or_default().push(position);
+                receivers.push(false);
+            }
+            None => receivers.push(true),
+        }
+    }
+
+    let hash = flow_hash(src_addr, dst_addr);
+    for group in groups.values() {
+        receivers[group[(hash % group.len() as u64) as usize]] = true;
+    }
+
+    Some(receivers)
+}
@@ -0,0 +1,253 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+use {
+    Error,
+    ParseError,
+};
+
+/// Counters for frames sent and received via an interface, and packets
+/// dropped along the way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InterfaceStats {
+    /// Number of frames received via the device.
+    pub rx_frames: u64,
+    /// Number of bytes in frames received via the device.
+    pub rx_bytes: u64,
+    /// Number of frames sent via the device.
+    pub tx_frames: u64,
+    /// Number of bytes in frames sent via the device.
+    pub tx_bytes: u64,
+    /// Number of received packets dropped due to a bad checksum.
+    pub rx_bad_checksum: u64,
+    /// Number of received packets dropped because they are truncated or
+    /// otherwise malformed.
+    pub rx_malformed: u64,
+    /// Number of received UDP and TCP packets no socket accepted.
+    pub rx_no_socket: u64,
+    /// Number of packets sent before the Ethernet address of their next hop
+    /// was in the ARP cache, which are buffered until ARP resolves it.
+    pub tx_arp_miss: u64,
+    /// Number of received packets dropped or rejected by the filter.
+    pub rx_filtered: u64,
+    /// Number of packets dropped or rejected by the filter before sending.
+    pub tx_filtered: u64,
+}
+
+impl InterfaceStats {
+    /// Records a frame received via the device.
+    pub fn record_rx(&mut self, frame_len: usize) {
+        self.rx_frames += 1;
+        self.rx_bytes += frame_len as u64;
+    }
+
+    /// Records a frame sent via the device.
+    pub fn record_tx(&mut self, frame_len: usize) {
+        self.tx_frames += 1;
+        self.tx_bytes += frame_len as u64;
+    }
+
+    /// Records the error processing a received frame failed with, counting
+    /// it as a drop if it is caused by the contents of the frame.
+    pub fn record_rx_error(&mut self, err: &Error) {
+        match *err {
+            Error::Parse(ParseError::Checksum) => self.rx_bad_checksum += 1,
+            Error::Parse(ParseError::Truncated) | Error::Parse(ParseError::Malformed) => {
+                self.rx_malformed += 1
+            }
+            _ => {}
+        }
+    }
+}
+
+/// IPv4 counters from the IP group of MIB-II, see RFC 1213.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IpCounters {
+    /// Number of packets received, including those with errors.
+    pub in_receives: u64,
+    /// Number of packets dropped due to errors in their headers.
+    pub in_hdr_errors: u64,
+    /// Number of packets dropped because they were not addressed to the
+    /// interface.
+    pub in_addr_errors: u64,
+    /// Number of packets dropped due to an unsupported protocol.
+    pub in_unknown_protos: u64,
+    /// Number of packets delivered to a supported protocol.
+    pub in_delivers: u64,
+    /// Number of packets supplied by protocols for sending.
+    pub out_requests: u64,
+    /// Number of packets successfully reassembled from fragments.
+    pub reasm_oks: u64,
+    /// Number of fragments sent.
+    pub frag_creates: u64,
+}
+
+/// ICMP counters from the ICMP group of MIB-II, see RFC 1213.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IcmpCounters {
+    /// Number of messages received, including those with errors.
+    pub in_msgs: u64,
+    /// Number of messages dropped due to bad checksums, lengths, etc.
+    pub in_errors: u64,
+    /// Number of Destination Unreachable messages received.
+    pub in_dest_unreachs: u64,
+    /// Number of Echo Request messages received.
+    pub in_echos: u64,
+    /// Number of messages sent.
+    pub out_msgs: u64,
+    /// Number of Destination Unreachable messages sent.
+    pub out_dest_unreachs: u64,
+    /// Number of Echo Reply messages sent.
+    pub out_echo_reps: u64,
+}
+
+/// UDP counters from the UDP group of MIB-II, see RFC 1213.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UdpCounters {
+    /// Number of datagrams delivered to sockets.
+    pub in_datagrams: u64,
+    /// Number of datagrams no socket was bound to the port of.
+    pub no_ports: u64,
+    /// Number of datagrams dropped due to bad checksums, lengths, etc.
+    pub in_errors: u64,
+    /// Number of datagrams sent.
+    pub out_datagrams: u64,
+}
+
+/// TCP counters from the TCP group of MIB-II, see RFC 1213.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpCounters {
+    /// Number of connections initiated with a SYN.
+    pub active_opens: u64,
+    /// Number of segments received, including those with errors.
+    pub in_segs: u64,
+    /// Number of segments dropped due to bad checksums, lengths, etc.
+    pub in_errs: u64,
+    /// Number of segments sent, including retransmissions.
+    pub out_segs: u64,
+    /// Number of segments retransmitted.
+    pub retrans_segs: u64,
+}
+
+/// Counters for each protocol of the stack in the style of the MIB-II groups,
+/// so they can be exported to monitoring systems.
+///
+/// Each interface tracks the packets it handles, and the counters of several
+/// interfaces may be combined via merge(...). The Display implementation
+/// renders a netstat-like report with one MIB-II counter per line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StackStats {
+    pub ip: IpCounters,
+    pub icmp: IcmpCounters,
+    pub udp: UdpCounters,
+    pub tcp: TcpCounters,
+}
+
+impl StackStats {
+    /// Adds the counters of another instance, e.g. from another interface.
+    pub fn merge(&mut self, other: &StackStats) {
+        self.ip.in_receives += other.ip.in_receives;
+        self.ip.in_hdr_errors += other.ip.in_hdr_errors;
+        self.ip.in_addr_errors += other.ip.in_addr_errors;
+        self.ip.in_unknown_protos += other.ip.in_unknown_protos;
+        self.ip.in_delivers += other.ip.in_delivers;
+        self.ip.out_requests += other.ip.out_requests;
+        self.ip.reasm_oks += other.ip.reasm_oks;
+        self.ip.frag_creates += other.ip.frag_creates;
+
+        self.icmp.in_msgs += other.icmp.in_msgs;
+        self.icmp.in_errors += other.icmp.in_errors;
+        self.icmp.in_dest_unreachs += other.icmp.in_dest_unreachs;
+        self.icmp.in_echos += other.icmp.in_echos;
+        self.icmp.out_msgs += other.icmp.out_msgs;
+        self.icmp.out_dest_unreachs += other.icmp.out_dest_unreachs;
+        self.icmp.out_echo_reps += other.icmp.out_echo_reps;
+
+        self.udp.in_datagrams += other.udp.in_datagrams;
+        self.udp.no_ports += other.udp.no_ports;
+        self.udp.in_errors += other.udp.in_errors;
+        self.udp.out_datagrams += other.udp.out_datagrams;
+
+        self.tcp.active_opens += other.tcp.active_opens;
+        self.tcp.in_segs += other.tcp.in_segs;
+        self.tcp.in_errs += other.tcp.in_errs;
+        self.tcp.out_segs += other.tcp.out_segs;
+        self.tcp.retrans_segs += other.tcp.retrans_segs;
+    }
+
+    /// Returns the (MIB-II name, value) of each counter.
+    pub fn counters(&self) -> Vec<(&'static str, u64)> {
+        vec![
+            ("IpInReceives", self.ip.in_receives),
+            ("IpInHdrErrors", self.ip.in_hdr_errors),
+            ("IpInAddrErrors", self.ip.in_addr_errors),
+            ("IpInUnknownProtos", self.ip.in_unknown_protos),
+            ("IpInDelivers", self.ip.in_delivers),
+            ("IpOutRequests", self.ip.out_requests),
+            ("IpReasmOKs", self.ip.reasm_oks),
+            ("IpFragCreates", self.ip.frag_creates),
+            ("IcmpInMsgs", self.icmp.in_msgs),
+            ("IcmpInErrors", self.icmp.in_errors),
+            ("IcmpInDestUnreachs", self.icmp.in_dest_unreachs),
+            ("IcmpInEchos", self.icmp.in_echos),
+            ("IcmpOutMsgs", self.icmp.out_msgs),
+            ("IcmpOutDestUnreachs", self.icmp.out_dest_unreachs),
+            ("IcmpOutEchoReps", self.icmp.out_echo_reps),
+            ("UdpInDatagrams", self.udp.in_datagrams),
+            ("UdpNoPorts", self.udp.no_ports),
+            ("UdpInErrors", self.udp.in_errors),
+            ("UdpOutDatagrams", self.udp.out_datagrams),
+            ("TcpActiveOpens", self.tcp.active_opens),
+            ("TcpInSegs", self.tcp.in_segs),
+            ("TcpInErrs", self.tcp.in_errs),
+            ("TcpOutSegs", self.tcp.out_segs),
+            ("TcpRetransSegs", self.tcp.retrans_segs),
+        ]
+    }
+}
+
+impl fmt::Display for StackStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (name, value) in self.counters() {
+            writeln!(f, "{:<24}{:>12}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_rx_error() {
+        let mut stats = InterfaceStats::default();
+        stats.record_rx_error(&Error::Parse(ParseError::Checksum));
+        stats.record_rx_error(&Error::Parse(ParseError::Truncated));
+        stats.record_rx_error(&Error::Parse(ParseError::Malformed));
+        stats.record_rx_error(&Error::Ignored);
+        assert_eq!(stats.rx_bad_checksum, 1);
+        assert_eq!(stats.rx_malformed, 2);
+        assert_eq!(stats.rx_no_socket, 0);
+    }
+
+    #[test]
+    fn test_stack_stats_merge_and_report() {
+        let mut stats = StackStats::default();
+        stats.ip.in_receives = 2;
+        stats.udp.no_ports = 1;
+        let mut other = StackStats::default();
+        other.ip.in_receives = 3;
+        other.tcp.retrans_segs = 4;
+        stats.merge(&other);
+
+        assert_eq!(stats.ip.in_receives, 5);
+        assert_eq!(stats.udp.no_ports, 1);
+        assert_eq!(stats.tcp.retrans_segs, 4);
+
+        let report = stats.to_string();
+        assert_eq!(report.lines().count(), stats.counters().len());
+        assert!(report.starts_with(&format!("{:<24}{:>12}\n", "IpInReceives", 5)));
+        assert!(report.contains(&format!("{:<24}{:>12}\n", "TcpRetransSegs", 4)));
+    }
+}
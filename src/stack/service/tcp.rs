@@ -0,0 +1,138 @@
+use stack::repr::{
+    Ipv4Packet,
+    Ipv4Repr,
+    TcpPacket,
+    TcpRepr,
+};
+use stack::service::{
+    ipv4,
+    socket,
+    Interface,
+};
+use stack::socket::{
+    SocketAddr,
+    SocketSet,
+    TaggedSocket,
+    TaggedSocketAddr,
+};
+use Result;
+
+/// Sends a TCP packet via the interface.
+///
+/// This function takes care of serializing a header, calculating a checksum,
+/// etc. so the caller needs to fill in **only** the payload in the provided
+/// buffer.
+pub fn send_packet<F>(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    tcp_repr: &TcpRepr,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    // Leave the checksum zeroed for the device to fill in if it can.
+    let checksum_offload =
+        interface.dev.capabilities().checksum.tcp && !ipv4::is_fragmented(interface, ipv4_repr);
+
+    interface.stack_stats.tcp.out_segs += 1;
+
+    ipv4::send_packet_with_repr(interface, ipv4_repr, |ipv4_payload| {
+        let mut tcp_packet = TcpPacket::try_new(ipv4_payload).unwrap();
+        tcp_repr.serialize(&mut tcp_packet).unwrap();
+        f(tcp_packet.payload_mut());
+        if !checksum_offload {
+            tcp_packet.fill_checksum(ipv4_repr);
+        }
+    })
+}
+
+/// Receives a TCP packet from an interface.
+///
+/// The TCP packet is parsed, forwarded to any socket, and any necessary TCP
+/// reset messages sent.
+pub fn recv_packet(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    ipv4_packet: &Ipv4Packet<&[u8]>,
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    interface.stack_stats.tcp.in_segs += 1;
+
+    let tcp_packet = TcpPacket::try_new(ipv4_packet.payload())?;
+    if let Err(err) = tcp_packet.check_encoding(ipv4_repr) {
+        interface.stack_stats.tcp.in_errs += 1;
+        return Err(err);
+    }
+
+    let tcp_repr = TcpRepr::deserialize(&tcp_packet);
+    let src_socket_addr = SocketAddr {
+        addr: ipv4_repr.src_addr,
+        port: tcp_repr.src_port,
+    };
+    let dst_socket_addr = TaggedSocketAddr::Tcp(SocketAddr {
+        addr: ipv4_repr.dst_addr,
+        port: tcp_repr.dst_port,
+    });
+
+    // A SYN opening a new connection to listeners sharing a binding via
+    // reuse_port is received by just one of them, which then owns the
+    // connection. Other segments are only accepted by the listener (or
+    // socket) owning their connection.
+    let is_syn = tcp_repr.flags[TcpRepr::FLAG_SYN] && !tcp_repr.flags[TcpRepr::FLAG_ACK];
+    let receivers = if is_syn {
+        socket::reuse_port_receivers(
+            socket_set,
+            &src_socket_addr,
+            &dst_socket_addr,
+            |socket| match *socket {
+                TaggedSocket::TcpListener(ref socket) if socket.is_reuse_port() => {
+                    Some(socket.binding().clone())
+                }
+                _ => None,
+            },
+        )
+    } else {
+        None
+    };
+
+    let mut unreachable = true;
+
+    let sockets = socket_set
+        .iter_mut_bound(&dst_socket_addr, &src_socket_addr)
+        .enumerate();
+
+    for (position, socket) in sockets {
+        if receivers
+            .as_ref()
+            .is_some_and(|receivers| !receivers[position])
+        {
+            continue;
+        }
+
+        let ok_or_err = match *socket {
+            TaggedSocket::Tcp(ref mut socket) => {
+                socket.recv_enqueue(ipv4_repr, &tcp_repr, tcp_packet.payload())
+            }
+            TaggedSocket::TcpListener(ref mut socket) => {
+                socket.recv_enqueue(ipv4_repr, &tcp_repr, tcp_packet.payload())
+            }
+            _ => continue,
+        };
+
+        unreachable = false;
+        if let Err(err) = ok_or_err {
+            debug!(
+                "Error enqueueing TCP packet for receiving via socket with {:?}.",
+                err
+            );
+        }
+    }
+
+    if unreachable {
+        interface.stats.rx_no_socket += 1;
+    }
+
+    // TODO: Send RST message if SYN packet was not accepted by any sockets.
+    Ok(())
+}
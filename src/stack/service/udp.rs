@@ -0,0 +1,162 @@
+use stack::repr::{
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Ipv4Packet,
+    Ipv4Repr,
+    UdpPacket,
+    UdpRepr,
+};
+use stack::service::{
+    icmpv4,
+    ipv4,
+    socket,
+    Interface,
+};
+use stack::socket::{
+    SocketAddr,
+    SocketSet,
+    TaggedSocket,
+    TaggedSocketAddr,
+};
+use Result;
+
+/// Sends a UDP packet via ther interface.
+///
+/// This function takes care of serializing a header, calculating a checksum,
+/// etc. so the caller needs to fill in **only** the payload in the provided
+/// buffer.
+pub fn send_packet<F>(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    udp_repr: &UdpRepr,
+    f: F,
+) -> Result<()>
+where
+    F: FnOnce(&mut [u8]),
+{
+    // Leave the checksum zeroed for the device to fill in if it can.
+    let checksum_offload =
+        interface.dev.capabilities().checksum.udp && !ipv4::is_fragmented(interface, ipv4_repr);
+    let udp_repr = UdpRepr {
+        checksum: udp_repr.checksum && !checksum_offload,
+        ..*udp_repr
+    };
+
+    interface.stack_stats.udp.out_datagrams += 1;
+
+    ipv4::send_packet_with_repr(interface, ipv4_repr, |ipv4_payload| {
+        let mut udp_packet = UdpPacket::try_new(ipv4_payload).unwrap();
+        f(udp_packet.payload_mut());
+        // NOTE: It's important that the UDP serialization happens after the payload
+        // is written to ensure a correct checksum.
+        udp_repr.serialize(&mut udp_packet, ipv4_repr);
+    })
+}
+
+/// Receives a UDP packet from an interface.
+///
+/// The UDP packet is parsed, forwarded to any socket, and any necessary ICMP
+/// messages sent.
+pub fn recv_packet(
+    interface: &mut Interface,
+    ipv4_repr: &Ipv4Repr,
+    ipv4_packet: &Ipv4Packet<&[u8]>,
+    socket_set: &mut SocketSet,
+) -> Result<()> {
+    let udp_packet = UdpPacket::try_new(ipv4_packet.payload())?;
+    if let Err(err) = udp_packet.check_encoding(ipv4_repr) {
+        interface.stack_stats.udp.in_errors += 1;
+        return Err(err);
+    }
+
+    let udp_repr = UdpRepr::deserialize(&udp_packet);
+
+    let src_socket_addr = SocketAddr {
+        addr: ipv4_repr.src_addr,
+        port: udp_repr.src_port,
+    };
+    let dst_socket_addr = SocketAddr {
+        addr: ipv4_repr.dst_addr,
+        port: udp_repr.dst_port,
+    };
+    let is_broadcast = ipv4::is_broadcast(interface, ipv4_repr.dst_addr);
+    let mut unreachable = true;
+
+    let mut deliver = |socket: &mut TaggedSocket| {
+        let socket = match *socket {
+            TaggedSocket::Udp(ref mut socket) => socket,
+            _ => return,
+        };
+
+        let accepts = socket.accepts(&src_socket_addr, &dst_socket_addr)
+            || (is_broadcast && socket.accepts_broadcast(&src_socket_addr, udp_repr.dst_port));
+        if !accepts {
+            return;
+        }
+
+        unreachable = false;
+        if let Err(err) = socket.recv_enqueue(ipv4_repr, &udp_repr, udp_packet.payload()) {
+            debug!(
+                "Error enqueueing UDP packet for receiving via socket with {:?}.",
+                err
+            );
+        }
+    };
+
+    // Broadcasts and multicasts may be received by sockets bound to other
+    // addresses, e.g. multicast group members, so only unicasts are
+    // demultiplexed by binding. Unicasts to unconnected sockets sharing a
+    // binding via reuse_port are received by just one of them.
+    if is_broadcast || ipv4_repr.dst_addr.is_multicast() {
+        socket_set.iter_mut().for_each(deliver);
+    } else {
+        let local_addr = TaggedSocketAddr::Udp(dst_socket_addr);
+        let receivers = socket::reuse_port_receivers(
+            socket_set,
+            &src_socket_addr,
+            &local_addr,
+            |socket| match *socket {
+                TaggedSocket::Udp(ref socket)
+                    if socket.is_reuse_port() && socket.peer_addr().is_err() =>
+                {
+                    Some(socket.binding().clone())
+                }
+                _ => None,
+            },
+        );
+
+        socket_set
+            .iter_mut_bound(&local_addr, &src_socket_addr)
+            .enumerate()
+            .filter(|&(position, _)| {
+                receivers
+                    .as_ref()
+                    .is_none_or(|receivers| receivers[position])
+            })
+            .for_each(|(_, socket)| deliver(socket));
+    }
+
+    // Send an ICMP message indicating packet has been ignored because no
+    // UDP sockets are bound to the specified port. Broadcasts and multicasts
+    // must never cause ICMP errors, see RFC 1122.
+    if unreachable {
+        interface.stats.rx_no_socket += 1;
+        interface.stack_stats.udp.no_ports += 1;
+    } else {
+        interface.stack_stats.udp.in_datagrams += 1;
+    }
+
+    if unreachable && !is_broadcast && !ipv4_repr.dst_addr.is_multicast() {
+        debug!(
+            "Sending ICMP port unreachable in response to a UDP {:?}.",
+            udp_repr
+        );
+        icmpv4::send_error(
+            interface,
+            Icmpv4Message::DestinationUnreachable(Icmpv4DestinationUnreachable::PortUnreachable),
+            ipv4_packet.as_ref(),
+        )
+    } else {
+        Ok(())
+    }
+}
@@ -0,0 +1,444 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use core::cell::{
+    Cell,
+    RefCell,
+};
+use core::fmt::{
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use core::hash::{
+    Hash,
+    Hasher,
+};
+use core::net::SocketAddrV4;
+use core::ops::Deref;
+
+use stack::hash::SipHasher;
+use stack::random::random;
+use stack::repr::Ipv4Address;
+use {
+    Error,
+    Result,
+    SocketError,
+};
+
+/// Lowest port of the dynamic range ephemeral ports are allocated from, see
+/// RFC 6335.
+pub const EPHEMERAL_PORT_MIN: u16 = 49152;
+
+/// Highest port of the dynamic range ephemeral ports are allocated from.
+pub const EPHEMERAL_PORT_MAX: u16 = 65535;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+/// An IPv4 + port socket address.
+pub struct SocketAddr {
+    pub addr: Ipv4Address,
+    pub port: u16,
+}
+
+impl Display for SocketAddr {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}:{}", self.addr, self.port)
+    }
+}
+
+impl<'a> From<&'a SocketAddrV4> for SocketAddr {
+    fn from(socket_addr: &'a SocketAddrV4) -> SocketAddr {
+        SocketAddr {
+            addr: Ipv4Address::from(socket_addr.ip()),
+            port: socket_addr.port(),
+        }
+    }
+}
+
+impl Into<SocketAddrV4> for SocketAddr {
+    fn into(self) -> SocketAddrV4 {
+        SocketAddrV4::new(self.addr.into(), self.port)
+    }
+}
+
+/// A socket address corresponding to different socket types.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum TaggedSocketAddr {
+    Udp(SocketAddr),
+    Tcp(SocketAddr),
+    /// An ICMP socket address, where the port is the ICMP identifier.
+    Icmp(SocketAddr),
+}
+
+impl Deref for TaggedSocketAddr {
+    type Target = SocketAddr;
+
+    fn deref(&self) -> &SocketAddr {
+        match *self {
+            TaggedSocketAddr::Tcp(ref addr) => addr,
+            TaggedSocketAddr::Udp(ref addr) => addr,
+            TaggedSocketAddr::Icmp(ref addr) => addr,
+        }
+    }
+}
+
+impl Display for TaggedSocketAddr {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match *self {
+            TaggedSocketAddr::Tcp(ref addr) => write!(f, "{} (TCP)", addr),
+            TaggedSocketAddr::Udp(ref addr) => write!(f, "{} (UDP)", addr),
+            TaggedSocketAddr::Icmp(ref addr) => write!(f, "{} (ICMP)", addr),
+        }
+    }
+}
+
+impl PartialEq<SocketAddr> for TaggedSocketAddr {
+    fn eq(&self, socket_addr: &SocketAddr) -> bool {
+        socket_addr == self.deref()
+    }
+}
+
+/// Number of leases held on a socket address.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct LeaseCount {
+    leases: usize,
+    lingering: usize,
+    reuse_port: usize,
+}
+
+/// A socket address which has been reserved, and is freed for reallocation by
+/// the owning Bindings instance once dropped.
+#[derive(Debug, Eq, PartialEq)]
+pub struct SocketAddrLease {
+    addr: TaggedSocketAddr,
+    lingering: Cell<bool>,
+    reuse_port: bool,
+    socket_addrs: Rc<RefCell<BTreeMap<TaggedSocketAddr, LeaseCount>>>,
+}
+
+impl SocketAddrLease {
+    /// Marks the lease as held only to linger after a connection has closed,
+    /// e.g. in TIME_WAIT. Bindings with reuse_addr set may lease the socket
+    /// address again while all existing leases on it are lingering.
+    pub fn set_lingering(&self, lingering: bool) {
+        if self.lingering.replace(lingering) == lingering {
+            return;
+        }
+
+        let mut socket_addrs = self.socket_addrs.borrow_mut();
+        let count = socket_addrs.get_mut(&self.addr).unwrap();
+        if lingering {
+            count.lingering += 1;
+        } else {
+            count.lingering -= 1;
+        }
+    }
+
+    /// Checks if the lease is lingering after a connection has closed.
+    pub fn is_lingering(&self) -> bool {
+        self.lingering.get()
+    }
+
+    /// Checks if the lease was taken with reuse_port set, and may share its
+    /// socket address with other such leases.
+    pub fn is_reuse_port(&self) -> bool {
+        self.reuse_port
+    }
+}
+
+impl Deref for SocketAddrLease {
+    type Target = TaggedSocketAddr;
+
+    fn deref(&self) -> &TaggedSocketAddr {
+        &self.addr
+    }
+}
+
+impl Display for SocketAddrLease {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} (Lease)", self.addr)
+    }
+}
+
+impl Drop for SocketAddrLease {
+    fn drop(&mut self) {
+        self.set_lingering(false);
+
+        let mut socket_addrs = self.socket_addrs.borrow_mut();
+        let is_unused = {
+            let count = socket_addrs.get_mut(&self.addr).unwrap();
+            count.leases -= 1;
+            if self.reuse_port {
+                count.reuse_port -= 1;
+            }
+            count.leases == 0
+        };
+
+        if is_unused {
+            socket_addrs.remove(&self.addr);
+        }
+    }
+}
+
+impl PartialEq<SocketAddr> for SocketAddrLease {
+    fn eq(&self, socket_addr: &SocketAddr) -> bool {
+        &self.addr == socket_addr
+    }
+}
+
+/// An allocator for socket address leases.
+#[derive(Debug)]
+pub struct Bindings {
+    socket_addrs: Rc<RefCell<BTreeMap<TaggedSocketAddr, LeaseCount>>>,
+    reuse_addr: bool,
+    reuse_port: bool,
+}
+
+impl Bindings {
+    /// Creates a set of socket bindings.
+    pub fn new() -> Bindings {
+        Bindings {
+            socket_addrs: Rc::new(RefCell::new(BTreeMap::new())),
+            reuse_addr: false,
+            reuse_port: false,
+        }
+    }
+
+    /// Permits leasing socket addresses whose existing leases are all
+    /// lingering, similar to SO_REUSEADDR. Disabled by default.
+    pub fn set_reuse_addr(&mut self, reuse_addr: bool) {
+        self.reuse_addr = reuse_addr;
+    }
+
+    /// Permits leasing socket addresses whose existing leases were all taken
+    /// with reuse_port set, similar to SO_REUSEPORT. Packets for new flows
+    /// are spread across the sockets sharing an address, see flow_hash(...).
+    /// Disabled by default.
+    pub fn set_reuse_port(&mut self, reuse_port: bool) {
+        self.reuse_port = reuse_port;
+    }
+
+    /// Tries to reserve the specified UDP socket address, returning
+    /// SocketError::BindingInUse if the socket address is already in use.
+    pub fn bind_udp(&self, socket_addr: SocketAddr) -> Result<SocketAddrLease> {
+        self.bind(TaggedSocketAddr::Udp(socket_addr))
+    }
+
+    /// Tries to reserve the specified TCP socket address, returning
+    /// SocketError::BindingInUse if the socket address is already in use.
+    pub fn bind_tcp(&self, socket_addr: SocketAddr) -> Result<SocketAddrLease> {
+        self.bind(TaggedSocketAddr::Tcp(socket_addr))
+    }
+
+    /// Tries to reserve the specified ICMP identifier on an address, returning
+    /// SocketError::BindingInUse if the identifier is already in use.
+    pub fn bind_icmp(&self, socket_addr: SocketAddr) -> Result<SocketAddrLease> {
+        self.bind(TaggedSocketAddr::Icmp(socket_addr))
+    }
+
+    /// Tries to reserve an unused UDP port in the dynamic range on an address,
+    /// returning SocketError::PortsExhausted if every port is in use.
+    pub fn bind_udp_ephemeral(&self, addr: Ipv4Address) -> Result<SocketAddrLease> {
+        self.bind_ephemeral(addr, TaggedSocketAddr::Udp)
+    }
+
+    /// Tries to reserve an unused TCP port in the dynamic range on an address,
+    /// returning SocketError::PortsExhausted if every port is in use.
+    pub fn bind_tcp_ephemeral(&self, addr: Ipv4Address) -> Result<SocketAddrLease> {
+        self.bind_ephemeral(addr, TaggedSocketAddr::Tcp)
+    }
+
+    fn bind_ephemeral<F>(&self, addr: Ipv4Address, tag: F) -> Result<SocketAddrLease>
+    where
+        F: Fn(SocketAddr) -> TaggedSocketAddr,
+    {
+        // Start from a random port to make ports harder to guess, see RFC 6056,
+        // then search sequentially for one without any leases, even lingering.
+        let num_ports = u32::from(EPHEMERAL_PORT_MAX - EPHEMERAL_PORT_MIN) + 1;
+        let offset = random::<u32>() % num_ports;
+
+        for i in 0 .. num_ports {
+            let port = EPHEMERAL_PORT_MIN + ((offset + i) % num_ports) as u16;
+            let socket_addr = tag(SocketAddr { addr, port });
+
+            if !self.socket_addrs.borrow().contains_key(&socket_addr) {
+                return self.bind(socket_addr);
+            }
+        }
+
+        Err(Error::Socket(SocketError::PortsExhausted))
+    }
+
+    fn bind(&self, socket_addr: TaggedSocketAddr) -> Result<SocketAddrLease> {
+        let mut socket_addrs = self.socket_addrs.borrow_mut();
+        let count = socket_addrs.entry(socket_addr.clone()).or_default();
+
+        let is_shared = self.reuse_port && count.leases == count.reuse_port;
+        if count.leases > 0 && !is_shared && !(self.reuse_addr && count.leases == count.lingering)
+        {
+            return Err(Error::Socket(SocketError::BindingInUse(*socket_addr)));
+        }
+
+        count.leases += 1;
+        if self.reuse_port {
+            count.reuse_port += 1;
+        }
+
+        Ok(SocketAddrLease {
+            addr: socket_addr,
+            lingering: Cell::new(false),
+            reuse_port: self.reuse_port,
+            socket_addrs: self.socket_addrs.clone(),
+        })
+    }
+}
+
+/// Returns a hash of the (source, destination) addresses of a flow, which
+/// picks the socket receiving the flow among sockets sharing a binding via
+/// reuse_port. The hash is stable, so every packet of a flow picks the same
+/// socket while the sockets sharing the binding do not change.
+pub fn flow_hash(src_addr: &SocketAddr, dst_addr: &SocketAddr) -> u64 {
+    let mut hasher = SipHasher::new();
+    src_addr.hash(&mut hasher);
+    dst_addr.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use stack::repr::Ipv4Address;
+
+    use super::*;
+
+    #[test]
+    fn test_bind_udp_ok() {
+        let bindings = Bindings::new();
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new([0, 1, 2, 3]),
+            port: 1024,
+        };
+        assert_eq!(*bindings.bind_udp(socket_addr).unwrap(), socket_addr);
+    }
+
+    #[test]
+    fn test_bind_udp_err() {
+        let bindings = Bindings::new();
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new([0, 1, 2, 3]),
+            port: 1024,
+        };
+        let _addr_lease = bindings.bind_udp(socket_addr).unwrap();
+        assert_matches!(
+            bindings.bind_udp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+    }
+
+    #[test]
+    fn test_bind_icmp_err() {
+        let bindings = Bindings::new();
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new([0, 1, 2, 3]),
+            port: 1024,
+        };
+        let _udp_lease = bindings.bind_udp(socket_addr).unwrap();
+        let _icmp_lease = bindings.bind_icmp(socket_addr).unwrap();
+        assert_matches!(
+            bindings.bind_icmp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+    }
+
+    #[test]
+    fn test_bind_tcp_lingering() {
+        let mut bindings = Bindings::new();
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new([0, 1, 2, 3]),
+            port: 1024,
+        };
+        let addr_lease = bindings.bind_tcp(socket_addr).unwrap();
+        addr_lease.set_lingering(true);
+        assert_matches!(
+            bindings.bind_tcp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+
+        bindings.set_reuse_addr(true);
+        let reused_addr_lease = bindings.bind_tcp(socket_addr).unwrap();
+        assert_matches!(
+            bindings.bind_tcp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+
+        drop(reused_addr_lease);
+        drop(addr_lease);
+        bindings.set_reuse_addr(false);
+        assert!(bindings.bind_tcp(socket_addr).is_ok());
+    }
+
+    #[test]
+    fn test_bind_reuse_port() {
+        let mut bindings = Bindings::new();
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new([0, 1, 2, 3]),
+            port: 1024,
+        };
+        let addr_lease = bindings.bind_udp(socket_addr).unwrap();
+        bindings.set_reuse_port(true);
+        assert_matches!(
+            bindings.bind_udp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+
+        drop(addr_lease);
+        let addr_leases: Vec<SocketAddrLease> = (0 .. 2)
+            .map(|_| bindings.bind_udp(socket_addr).unwrap())
+            .collect();
+        assert!(addr_leases.iter().all(|lease| lease.is_reuse_port()));
+
+        bindings.set_reuse_port(false);
+        assert_matches!(
+            bindings.bind_udp(socket_addr),
+            Err(Error::Socket(SocketError::BindingInUse(_)))
+        );
+
+        drop(addr_leases);
+        assert!(bindings.bind_udp(socket_addr).is_ok());
+    }
+
+    #[test]
+    fn test_bind_ephemeral() {
+        let bindings = Bindings::new();
+        let addr = Ipv4Address::new([0, 1, 2, 3]);
+
+        let leases: Vec<SocketAddrLease> = (0 .. 64)
+            .map(|_| bindings.bind_udp_ephemeral(addr).unwrap())
+            .collect();
+        for lease in leases.iter() {
+            let socket_addr = ***lease;
+            assert_eq!(socket_addr.addr, addr);
+            assert!(socket_addr.port >= EPHEMERAL_PORT_MIN);
+            assert_matches!(
+                bindings.bind_udp(socket_addr),
+                Err(Error::Socket(SocketError::BindingInUse(_)))
+            );
+            assert!(bindings.bind_tcp(socket_addr).is_ok());
+        }
+
+        let tcp_lease = bindings.bind_tcp_ephemeral(addr).unwrap();
+        assert_matches!(*tcp_lease, TaggedSocketAddr::Tcp(_));
+    }
+
+    #[test]
+    fn test_bind_ephemeral_exhausted() {
+        let bindings = Bindings::new();
+        let addr = Ipv4Address::new([0, 1, 2, 3]);
+
+        let _leases: Vec<SocketAddrLease> = (EPHEMERAL_PORT_MIN ..= EPHEMERAL_PORT_MAX)
+            .map(|port| bindings.bind_udp(SocketAddr { addr, port }).unwrap())
+            .collect();
+        assert_matches!(
+            bindings.bind_udp_ephemeral(addr),
+            Err(Error::Socket(SocketError::PortsExhausted))
+        );
+    }
+}
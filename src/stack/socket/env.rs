@@ -0,0 +1,303 @@
+use alloc::rc::Rc;
+use core::cmp::min;
+
+use stack::repr::{
+    EthernetFrame,
+    Icmpv4Message,
+    Icmpv4Packet,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv4Packet,
+};
+use stack::service::{
+    Interface,
+    InterfaceSet,
+};
+use stack::socket::{
+    Bindings,
+    HashedIsnGenerator,
+    IcmpSocket,
+    IsnGenerator,
+    RawSocket,
+    RawType,
+    SocketAddr,
+    SocketAddrLease,
+    TcpListener,
+    TcpSocket,
+    UdpSocket,
+};
+use stack::storage::{
+    ByteRing,
+    Ring,
+    Slice,
+};
+use stack::time::Env as TimeEnv;
+use Result;
+
+/// Default number of packets a raw socket can buffer.
+pub static RAW_SOCKET_PACKETS: usize = 128;
+
+/// Default number of packets an ICMP socket can buffer.
+pub static ICMP_SOCKET_PACKETS: usize = 16;
+
+/// Default number of packets a UDP socket can buffer.
+pub static UDP_SOCKET_PACKETS: usize = 128;
+
+/// Default maximum payload size of the UDP packets a socket can buffer. This
+/// may exceed the interface MTU since large packets are fragmented.
+pub static UDP_SOCKET_PAYLOAD_LEN: usize = 8192;
+
+/// Default payload size each buffered UDP packet is allocated for, which
+/// grows on demand up to the maximum payload size of the socket.
+pub static UDP_SOCKET_INITIAL_PAYLOAD_LEN: usize = 512;
+
+/// Default number of bytes a TCP socket can buffer in each direction.
+pub static TCP_SOCKET_BUFFER_LEN: usize = 16384;
+
+/// An environment for creating sockets configured for a particular interface.
+pub struct SocketEnv<T: 'static + TimeEnv + Clone> {
+    bindings: Bindings,
+    interface_mtu: usize,
+    time_env: T,
+    isn_generator: Rc<dyn IsnGenerator>,
+}
+
+impl<T: 'static + TimeEnv + Clone> SocketEnv<T> {
+    /// Creates a new socket environment.
+    pub fn new(interface: &Interface, time_env: T) -> SocketEnv<T> {
+        SocketEnv::with_mtu(interface.dev.max_transmission_unit(), time_env)
+    }
+
+    /// Creates a new socket environment for sockets serviced by a set of
+    /// interfaces, sizing buffers for the largest interface MTU.
+    pub fn with_interfaces(interfaces: &InterfaceSet, time_env: T) -> SocketEnv<T> {
+        SocketEnv::with_mtu(interfaces.max_transmission_unit(), time_env)
+    }
+
+    fn with_mtu(interface_mtu: usize, time_env: T) -> SocketEnv<T> {
+        SocketEnv {
+            bindings: Bindings::new(),
+            interface_mtu,
+            isn_generator: Rc::new(HashedIsnGenerator::new(Rc::new(time_env.clone()))),
+            time_env,
+        }
+    }
+
+    /// Permits binding socket addresses which are only held by connections
+    /// lingering in TIME_WAIT, similar to SO_REUSEADDR.
+    pub fn set_reuse_addr(&mut self, reuse_addr: bool) {
+        self.bindings.set_reuse_addr(reuse_addr);
+    }
+
+    /// Permits several UDP sockets or TCP listeners to bind the same socket
+    /// address if all of them are created with reuse_port set, similar to
+    /// SO_REUSEPORT. Datagrams and new connections are spread across them by
+    /// hashing the addresses of each flow, e.g. to balance load across
+    /// workers.
+    pub fn set_reuse_port(&mut self, reuse_port: bool) {
+        self.bindings.set_reuse_port(reuse_port);
+    }
+
+    /// Replaces the generator used for initial sequence numbers of TCP
+    /// sockets created from this environment.
+    pub fn set_isn_generator<G: 'static + IsnGenerator>(&mut self, isn_generator: G) {
+        self.isn_generator = Rc::new(isn_generator);
+    }
+
+    /// Creates a new raw socket.
+    pub fn raw_socket(&self, raw_type: RawType) -> RawSocket {
+        let header_len = match raw_type {
+            RawType::Ethernet => EthernetFrame::<&[u8]>::HEADER_LEN,
+            RawType::Ipv4 => {
+                EthernetFrame::<&[u8]>::HEADER_LEN + Ipv4Packet::<&[u8]>::MIN_HEADER_LEN
+            }
+        };
+
+        let payload_len = self.interface_mtu.checked_sub(header_len).unwrap();
+
+        let buffer = || {
+            let payload = Slice::from(vec![0; payload_len]);
+            Ring::from(vec![payload; RAW_SOCKET_PACKETS])
+        };
+
+        RawSocket::new(raw_type, buffer(), buffer())
+    }
+
+    /// Creates a new ICMP socket bound to an identifier on the specified
+    /// address.
+    pub fn icmp_socket(&self, addr: Ipv4Address, ident: u16) -> Result<IcmpSocket> {
+        let binding = self.bindings.bind_icmp(SocketAddr { addr, port: ident })?;
+
+        let header_len = EthernetFrame::<&[u8]>::HEADER_LEN + Ipv4Packet::<&[u8]>::MIN_HEADER_LEN
+            + Icmpv4Packet::<&[u8]>::HEADER_LEN;
+
+        let payload_len = self.interface_mtu.checked_sub(header_len).unwrap();
+
+        let send_buffer = {
+            let payload = Slice::from(vec![0; payload_len]);
+            let addr = Ipv4Address::new([0, 0, 0, 0]);
+            Ring::from(vec![(payload, addr, 0); ICMP_SOCKET_PACKETS])
+        };
+
+        let recv_buffer = {
+            let payload = Slice::from(vec![0; payload_len]);
+            let addr = Ipv4Address::new([0, 0, 0, 0]);
+            let icmp_repr = Icmpv4Repr {
+                message: Icmpv4Message::EchoReply { id: 0, seq: 0 },
+                payload_len: 0,
+            };
+            Ring::from(vec![(payload, addr, icmp_repr); ICMP_SOCKET_PACKETS])
+        };
+
+        Ok(IcmpSocket::new(binding, send_buffer, recv_buffer))
+    }
+
+    /// Creates a new UDP socket.
+    pub fn udp_socket(&self, socket_addr: SocketAddr) -> Result<UdpSocket> {
+        self.udp_socket_with(
+            socket_addr,
+            UDP_SOCKET_PACKETS,
+            UDP_SOCKET_PACKETS,
+            UDP_SOCKET_PAYLOAD_LEN,
+        )
+    }
+
+    /// Creates a new UDP socket which buffers up to send_capacity and
+    /// recv_capacity packets with payloads of up to max_payload bytes, e.g.
+    /// tiny buffers for telemetry or large ones for bulk transfers.
+    pub fn udp_socket_with(
+        &self,
+        socket_addr: SocketAddr,
+        send_capacity: usize,
+        recv_capacity: usize,
+        max_payload: usize,
+    ) -> Result<UdpSocket> {
+        let binding = self.bindings.bind_udp(socket_addr)?;
+        Ok(UdpSocket::new(
+            binding,
+            udp_buffer(send_capacity, max_payload),
+            udp_buffer(recv_capacity, max_payload),
+        ))
+    }
+
+    /// Creates a new UDP socket bound to an unused ephemeral port on an
+    /// address, e.g. for clients which do not care about their port.
+    pub fn udp_socket_ephemeral(&self, addr: Ipv4Address) -> Result<UdpSocket> {
+        let binding = self.bindings.bind_udp_ephemeral(addr)?;
+        Ok(UdpSocket::new(
+            binding,
+            udp_buffer(UDP_SOCKET_PACKETS, UDP_SOCKET_PAYLOAD_LEN),
+            udp_buffer(UDP_SOCKET_PACKETS, UDP_SOCKET_PAYLOAD_LEN),
+        ))
+    }
+
+    /// Creates a new TCP socket.
+    pub fn tcp_socket(&self, socket_addr: SocketAddr) -> Result<TcpSocket> {
+        self.tcp_socket_with(socket_addr, TCP_SOCKET_BUFFER_LEN, TCP_SOCKET_BUFFER_LEN)
+    }
+
+    /// Creates a new TCP socket which buffers up to send_buffer_len bytes for
+    /// sending and advertises a receive window of up to recv_buffer_len bytes.
+    pub fn tcp_socket_with(
+        &self,
+        socket_addr: SocketAddr,
+        send_buffer_len: usize,
+        recv_buffer_len: usize,
+    ) -> Result<TcpSocket> {
+        let binding = self.bindings.bind_tcp(socket_addr)?;
+        Ok(self.tcp_socket_with_binding(binding, send_buffer_len, recv_buffer_len))
+    }
+
+    /// Creates a new TCP socket like tcp_socket_with(...) whose connections
+    /// use send_buffer and recv_buffer, e.g. backed by static storage, rather
+    /// than allocating buffers.
+    pub fn tcp_socket_with_buffers(
+        &self,
+        socket_addr: SocketAddr,
+        send_buffer: ByteRing,
+        recv_buffer: ByteRing,
+    ) -> Result<TcpSocket> {
+        let binding = self.bindings.bind_tcp(socket_addr)?;
+        Ok(TcpSocket::with_buffers(
+            binding,
+            self.interface_mtu,
+            send_buffer,
+            recv_buffer,
+            self.time_env.clone(),
+            self.isn_generator.clone(),
+        ))
+    }
+
+    /// Creates a new TCP socket bound to an unused ephemeral port on an
+    /// address, e.g. for connecting to a server.
+    pub fn tcp_socket_ephemeral(&self, addr: Ipv4Address) -> Result<TcpSocket> {
+        let binding = self.bindings.bind_tcp_ephemeral(addr)?;
+        Ok(self.tcp_socket_with_binding(binding, TCP_SOCKET_BUFFER_LEN, TCP_SOCKET_BUFFER_LEN))
+    }
+
+    fn tcp_socket_with_binding(
+        &self,
+        binding: SocketAddrLease,
+        send_buffer_len: usize,
+        recv_buffer_len: usize,
+    ) -> TcpSocket {
+        TcpSocket::new(
+            binding,
+            self.interface_mtu,
+            send_buffer_len,
+            recv_buffer_len,
+            self.time_env.clone(),
+            self.isn_generator.clone(),
+        )
+    }
+
+    /// Creates a new TCP listener with SYN and ESTABLISHED queues of the
+    /// specified lengths.
+    pub fn tcp_listener(
+        &self,
+        socket_addr: SocketAddr,
+        syn_queue_len: usize,
+        est_queue_len: usize,
+    ) -> Result<TcpListener> {
+        self.tcp_listener_with(
+            socket_addr,
+            syn_queue_len,
+            est_queue_len,
+            TCP_SOCKET_BUFFER_LEN,
+            TCP_SOCKET_BUFFER_LEN,
+        )
+    }
+
+    /// Creates a new TCP listener like tcp_listener(...) whose accepted
+    /// connections buffer send_buffer_len and recv_buffer_len bytes.
+    pub fn tcp_listener_with(
+        &self,
+        socket_addr: SocketAddr,
+        syn_queue_len: usize,
+        est_queue_len: usize,
+        send_buffer_len: usize,
+        recv_buffer_len: usize,
+    ) -> Result<TcpListener> {
+        let binding = self.bindings.bind_tcp(socket_addr)?;
+        Ok(TcpListener::new(
+            binding,
+            self.interface_mtu,
+            send_buffer_len,
+            recv_buffer_len,
+            self.time_env.clone(),
+            self.isn_generator.clone(),
+            syn_queue_len,
+            est_queue_len,
+        ))
+    }
+}
+
+fn udp_buffer(capacity: usize, max_payload: usize) -> Ring<(Slice<u8>, SocketAddr)> {
+    let initial_payload = min(UDP_SOCKET_INITIAL_PAYLOAD_LEN, max_payload);
+    let payload = Slice::with_max_len(vec![0; initial_payload], max_payload);
+    let addr = SocketAddr {
+        addr: Ipv4Address::new([0, 0, 0, 0]),
+        port: 0,
+    };
+    Ring::from(vec![(payload, addr); capacity])
+}
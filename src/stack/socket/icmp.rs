@@ -0,0 +1,216 @@
+use byteorder::{
+    ByteOrder,
+    NetworkEndian,
+};
+
+use stack::repr::{
+    ipv4_flags,
+    ipv4_protocols,
+    Icmpv4Message,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+};
+use stack::socket::{
+    SocketAddrLease,
+    TaggedSocketAddr,
+};
+use stack::storage::{
+    Ring,
+    Slice,
+};
+use stack::time::PollAt;
+use {
+    Error,
+    Result,
+};
+
+/// An ICMP socket for sending echo requests and receiving the corresponding
+/// echo replies and errors.
+///
+/// The socket is bound to an ICMP identifier and only receives echo replies
+/// with a matching identifier, or destination unreachable errors in response
+/// to echo requests with a matching identifier.
+pub struct IcmpSocket {
+    binding: SocketAddrLease,
+    send_buffer: Ring<(Slice<u8>, Ipv4Address, u16)>,
+    recv_buffer: Ring<(Slice<u8>, Ipv4Address, Icmpv4Repr)>,
+    recv_dropped: u64,
+}
+
+impl IcmpSocket {
+    /// Creates a new ICMP socket. The port of the binding is used as the ICMP
+    /// identifier.
+    pub fn new(
+        binding: SocketAddrLease,
+        send_buffer: Ring<(Slice<u8>, Ipv4Address, u16)>,
+        recv_buffer: Ring<(Slice<u8>, Ipv4Address, Icmpv4Repr)>,
+    ) -> IcmpSocket {
+        IcmpSocket {
+            binding,
+            send_buffer,
+            recv_buffer,
+            recv_dropped: 0,
+        }
+    }
+
+    /// Returns the ICMP identifier the socket is bound to.
+    pub fn ident(&self) -> u16 {
+        self.binding.port
+    }
+
+    /// Returns the local address and identifier the socket is bound to.
+    pub fn binding(&self) -> &TaggedSocketAddr {
+        &self.binding
+    }
+
+    /// Checks if the socket is interested in receiving an ICMP message with
+    /// the specified payload.
+    pub fn accepts(&self, icmp_repr: &Icmpv4Repr, payload: &[u8]) -> bool {
+        match icmp_repr.message {
+            Icmpv4Message::EchoReply { id, .. } => id == self.ident(),
+            Icmpv4Message::DestinationUnreachable(_) => {
+                // The payload holds the IP header and at least 8 bytes of the
+                // original echo request.
+                let ipv4_packet = match Ipv4Packet::try_new(payload) {
+                    Ok(ipv4_packet) => ipv4_packet,
+                    Err(_) => return false,
+                };
+                let header_len = (ipv4_packet.header_len() * 4) as usize;
+                if ipv4_packet.protocol() != ipv4_protocols::ICMP
+                    || ipv4_packet.src_addr() != self.binding.addr
+                    || payload.len() < header_len + 8
+                {
+                    return false;
+                }
+
+                let icmp_header = &payload[header_len .. header_len + 8];
+                let id = NetworkEndian::read_u16(&icmp_header[4 .. 6]);
+                icmp_header[0] == 8 && id == self.ident()
+            }
+            _ => false,
+        }
+    }
+
+    /// Enqueues an echo request with a payload_len bytes payload for sending
+    /// to the specified address.
+    pub fn send_echo(
+        &mut self,
+        payload_len: usize,
+        addr: Ipv4Address,
+        seq: u16,
+    ) -> Result<&mut [u8]> {
+        self.send_buffer
+            .enqueue_maybe(|&mut (ref mut buffer, ref mut addr_, ref mut seq_)| {
+                buffer.try_resize(payload_len, 0)?;
+
+                for i in 0 .. payload_len {
+                    buffer[i] = 0;
+                }
+
+                *addr_ = addr;
+                *seq_ = seq;
+
+                Ok(&mut buffer[.. payload_len])
+            })
+    }
+
+    /// Dequeues a received ICMP message, returning its payload along with the
+    /// source address and header.
+    ///
+    /// For echo replies the payload is the echoed data, while for errors it
+    /// is the IP header and leading bytes of the original echo request.
+    pub fn recv(&mut self) -> Result<(&[u8], Ipv4Address, Icmpv4Repr)> {
+        self.recv_buffer
+            .dequeue_with(|&mut (ref buffer, addr, icmp_repr)| (&buffer[..], addr, icmp_repr))
+    }
+
+    /// Dequeues a packet enqueued for sending via a function f.
+    ///
+    /// The packet is only dequeued if f does not return an error.
+    pub fn send_dequeue<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&Ipv4Repr, &Icmpv4Repr, &[u8]) -> Result<R>,
+    {
+        let (src_addr, ident) = (self.binding.addr, self.ident());
+        self.send_buffer
+            .dequeue_maybe(|&mut (ref mut buffer, addr, seq)| {
+                let icmp_repr = Icmpv4Repr {
+                    message: Icmpv4Message::EchoRequest { id: ident, seq },
+                    payload_len: buffer.len(),
+                };
+
+                let ipv4_repr = Ipv4Repr {
+                    src_addr,
+                    dst_addr: addr,
+                    protocol: Ipv4Protocol::ICMP,
+                    payload_len: icmp_repr.buffer_len() as u16,
+                    ttl: Ipv4Repr::DEFAULT_TTL,
+                    dscp: 0,
+                    flags: ipv4_flags::DONT_FRAGMENT,
+                    options: Ipv4Options::new(),
+                };
+
+                f(&ipv4_repr, &icmp_repr, &buffer[..])
+            })
+    }
+
+    /// Enqueues an ICMP message for receiving.
+    pub fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        icmp_repr: &Icmpv4Repr,
+        payload: &[u8],
+    ) -> Result<()> {
+        let accepts = self.accepts(icmp_repr, payload);
+
+        let res = self.recv_buffer.enqueue_maybe(
+            |&mut (ref mut buffer, ref mut addr, ref mut icmp_repr_)| {
+                if !accepts {
+                    Err(Error::Ignored)
+                } else {
+                    buffer.try_resize(payload.len(), 0)?;
+                    buffer.copy_from_slice(payload);
+                    *addr = ipv4_repr.src_addr;
+                    *icmp_repr_ = *icmp_repr;
+                    Ok(())
+                }
+            },
+        );
+
+        if let Err(Error::Buffer(_)) = res {
+            self.recv_dropped += 1;
+        }
+
+        res
+    }
+
+    /// Returns when the socket next needs servicing, i.e. now if packets are
+    /// enqueued for sending.
+    pub fn poll_at(&self) -> PollAt {
+        if self.send_buffer.len() > 0 {
+            PollAt::Now
+        } else {
+            PollAt::Ingress
+        }
+    }
+
+    /// Returns the number of packets enqueued for sending.
+    pub fn send_enqueued(&self) -> usize {
+        self.send_buffer.len()
+    }
+
+    /// Returns the number of packets enqueued for receiving.
+    pub fn recv_enqueued(&self) -> usize {
+        self.recv_buffer.len()
+    }
+
+    /// Returns the number of received packets dropped because the receive
+    /// buffer was full or a packet did not fit in it.
+    pub fn recv_dropped(&self) -> u64 {
+        self.recv_dropped
+    }
+}
@@ -0,0 +1,9 @@
+use stack::time::Instant;
+
+/// Information about a received packet besides its contents.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecvMeta {
+    /// When the packet was enqueued into the socket, if the socket had
+    /// timestamping enabled at the time.
+    pub timestamp: Option<Instant>,
+}
@@ -0,0 +1,77 @@
+//! Communication between endpoints.
+//!
+//! The `socket` module provides abstractions for buffering, sending, and
+//! receiving data between network endpoints.
+
+pub mod bindings;
+pub mod env;
+pub mod icmp;
+pub mod meta;
+pub mod observer;
+pub mod raw;
+pub mod set;
+pub mod tagged;
+pub mod tcp;
+pub mod udp;
+pub mod watermark;
+
+pub use self::bindings::{
+    Bindings,
+    SocketAddr,
+    SocketAddrLease,
+    TaggedSocketAddr,
+};
+pub use self::env::SocketEnv;
+pub use self::icmp::IcmpSocket;
+pub use self::meta::RecvMeta;
+pub use self::observer::{
+    Observer,
+    SocketEvent,
+};
+pub use self::raw::{
+    RawFilter,
+    RawSocket,
+    RawType,
+};
+pub use self::set::{
+    SocketHandle,
+    SocketSet,
+};
+pub use self::tagged::TaggedSocket;
+pub use self::tcp::{
+    HashedIsnGenerator,
+    IsnGenerator,
+    SequentialIsnGenerator,
+    Shutdown,
+    Tcp,
+    TcpCloseWait,
+    TcpClosed,
+    TcpClosing,
+    TcpConnection,
+    TcpContext,
+    TcpEstablished,
+    TcpFinWait1,
+    TcpFinWait2,
+    TcpLastAck,
+    TcpListen,
+    TcpListener,
+    TcpListenerStats,
+    TcpOverflowPolicy,
+    TcpSocket,
+    TcpState,
+    TcpStateKind,
+    TcpStats,
+    TcpSynRecv,
+    TcpSynSent,
+    TcpTimeWait,
+};
+#[cfg(feature = "std")]
+pub use self::udp::{
+    UdpRecvHalf,
+    UdpSendHalf,
+};
+pub use self::udp::UdpSocket;
+pub use self::watermark::{
+    WatermarkState,
+    Watermarks,
+};
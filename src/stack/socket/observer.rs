@@ -0,0 +1,63 @@
+use alloc::boxed::Box;
+use core::fmt;
+
+/// A change to a socket which applications may react to rather than polling
+/// the socket for it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SocketEvent {
+    /// A TCP connection was established.
+    Established,
+    /// A TCP connection closed, e.g. because it finished closing, timed out
+    /// or was aborted.
+    Closed,
+    /// A TCP connection was reset by the remote endpoint.
+    Reset,
+    /// Data was received and can be dequeued.
+    Recv,
+    /// The remote endpoint of a TCP connection closed its sending half, so
+    /// recv(...) indicates the end of the stream once buffered data is
+    /// dequeued.
+    PeerClosed,
+}
+
+/// A callback notified of the events of a socket, if one is set.
+#[derive(Default)]
+pub struct Observer {
+    callback: Option<Box<dyn FnMut(SocketEvent)>>,
+}
+
+impl fmt::Debug for Observer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.callback {
+            Some(_) => write!(f, "Observer(Some(..))"),
+            None => write!(f, "Observer(None)"),
+        }
+    }
+}
+
+impl Observer {
+    /// Creates an observer without a callback.
+    pub fn new() -> Observer {
+        Observer::default()
+    }
+
+    /// Sets the callback, replacing any previous one.
+    pub fn set<F>(&mut self, callback: F)
+    where
+        F: 'static + FnMut(SocketEvent),
+    {
+        self.callback = Some(Box::new(callback));
+    }
+
+    /// Removes the callback.
+    pub fn clear(&mut self) {
+        self.callback = None;
+    }
+
+    /// Calls the callback, if any, with an event.
+    pub fn notify(&mut self, event: SocketEvent) {
+        if let Some(ref mut callback) = self.callback {
+            callback(event);
+        }
+    }
+}
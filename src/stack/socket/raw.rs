@@ -0,0 +1,218 @@
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use core::fmt::{
+    Debug,
+    Formatter,
+    Result as FmtResult,
+};
+
+use stack::socket::RecvMeta;
+
+use stack::storage::{
+    Ring,
+    Slice,
+};
+use stack::time::{
+    Env as TimeEnv,
+    Instant,
+    PollAt,
+};
+use Result;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RawType {
+    Ethernet,
+    Ipv4,
+}
+
+/// A predicate over the bytes of a received packet.
+pub type RawFilter = Box<dyn Fn(&[u8]) -> bool>;
+
+/// Socket for sending and receiving raw ethernet or IP packets.
+pub struct RawSocket {
+    raw_type: RawType,
+    spoofing: bool,
+    filter: Option<RawFilter>,
+    send_buffer: Ring<Slice<u8>>,
+    recv_buffer: Ring<Slice<u8>>,
+    // Timestamps of packets in the receive buffer, in the same order.
+    recv_timestamps: VecDeque<Option<Instant>>,
+    timestamp_env: Option<Rc<dyn TimeEnv>>,
+    recv_dropped: u64,
+}
+
+impl RawSocket {
+    /// Creates a socket with the provided send and receive buffers.
+    pub fn new(
+        raw_type: RawType,
+        send_buffer: Ring<Slice<u8>>,
+        recv_buffer: Ring<Slice<u8>>,
+    ) -> RawSocket {
+        RawSocket {
+            raw_type,
+            spoofing: false,
+            filter: None,
+            send_buffer,
+            recv_buffer,
+            recv_timestamps: VecDeque::new(),
+            timestamp_env: None,
+            recv_dropped: 0,
+        }
+    }
+
+    /// Enqueues a packet with buffer_len bytes for sending.
+    pub fn send(&mut self, buffer_len: usize) -> Result<&mut [u8]> {
+        self.send_buffer.enqueue_maybe(|buffer| {
+            buffer.try_resize(buffer_len, 0)?;
+
+            for i in 0 .. buffer_len {
+                buffer[i] = 0;
+            }
+
+            return Ok(&mut buffer[.. buffer_len]);
+        })
+    }
+
+    /// Dequeues a received packet from the socket.
+    pub fn recv(&mut self) -> Result<&[u8]> {
+        self.recv_with_meta().map(|(buffer, _)| buffer)
+    }
+
+    /// Dequeues a received packet like recv(), along with information such as
+    /// when it was received.
+    pub fn recv_with_meta(&mut self) -> Result<(&[u8], RecvMeta)> {
+        let timestamps = &mut self.recv_timestamps;
+        self.recv_buffer.dequeue_with(|buffer| {
+            let meta = RecvMeta {
+                timestamp: timestamps.pop_front().and_then(|timestamp| timestamp),
+            };
+            (&buffer[..], meta)
+        })
+    }
+
+    /// Records when each packet is received so it can be retrieved via
+    /// recv_with_meta(), e.g. for measuring latency.
+    pub fn enable_timestamps<T: 'static + TimeEnv>(&mut self, time_env: T) {
+        self.timestamp_env = Some(Rc::new(time_env));
+    }
+
+    /// Stops recording when packets are received.
+    pub fn disable_timestamps(&mut self) {
+        self.timestamp_env = None;
+    }
+
+    /// Dequeues a packet enqueued for sending via a function f.
+    ///
+    /// The packet is only dequeued if f does not return an error.
+    pub fn send_dequeue<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&[u8]) -> Result<R>,
+    {
+        self.send_buffer.dequeue_maybe(|buffer| f(&buffer[..]))
+    }
+
+    /// Enqueues a packet for receiving.
+    pub fn recv_enqueue(&mut self, packet: &[u8]) -> Result<()> {
+        let res = self.recv_buffer.enqueue_maybe(|buffer| {
+            buffer.try_resize(packet.len(), 0)?;
+            buffer.copy_from_slice(packet);
+            Ok(())
+        });
+
+        if res.is_err() {
+            self.recv_dropped += 1;
+            return res;
+        }
+
+        let timestamp = self
+            .timestamp_env
+            .as_ref()
+            .map(|time_env| time_env.now_instant());
+        self.recv_timestamps.push_back(timestamp);
+        Ok(())
+    }
+
+    /// Sets whether IPv4 packets are sent exactly as written.
+    ///
+    /// By default the source address of IPv4 packets is filled in with the
+    /// interface address if unspecified (0.0.0.0), updating the header and
+    /// TCP or UDP checksums, and packets with a source address other than the
+    /// interface address are dropped. Enabling spoofing disables these checks
+    /// for callers which intentionally forge packets.
+    pub fn set_spoofing(&mut self, spoofing: bool) {
+        self.spoofing = spoofing;
+    }
+
+    /// Checks if IPv4 packets are sent exactly as written.
+    pub fn spoofing(&self) -> bool {
+        self.spoofing
+    }
+
+    /// Attaches a filter so only received packets for which the filter returns
+    /// true are enqueued, e.g. to avoid buffering traffic the application would
+    /// parse and discard anyway. Replaces any previously attached filter.
+    pub fn set_filter<F>(&mut self, filter: F)
+    where
+        F: 'static + Fn(&[u8]) -> bool,
+    {
+        self.filter = Some(Box::new(filter));
+    }
+
+    /// Detaches the filter so all received packets are enqueued.
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+    }
+
+    /// Checks if a received packet passes the filter of the socket.
+    pub fn accepts(&self, packet: &[u8]) -> bool {
+        match self.filter {
+            Some(ref filter) => filter(packet),
+            None => true,
+        }
+    }
+
+    /// Returns when the socket next needs servicing, i.e. now if packets are
+    /// enqueued for sending.
+    pub fn poll_at(&self) -> PollAt {
+        if self.send_buffer.len() > 0 {
+            PollAt::Now
+        } else {
+            PollAt::Ingress
+        }
+    }
+
+    /// Returns the type of raw packets this socket contains.
+    pub fn raw_type(&self) -> RawType {
+        self.raw_type
+    }
+
+    /// Returns the number of packets enqueued for sending.
+    pub fn send_enqueued(&self) -> usize {
+        self.send_buffer.len()
+    }
+
+    /// Returns the number of packets enqueued for receiving.
+    pub fn recv_enqueued(&self) -> usize {
+        self.recv_buffer.len()
+    }
+
+    /// Returns the number of received packets dropped because the receive
+    /// buffer was full or a packet did not fit in it.
+    pub fn recv_dropped(&self) -> u64 {
+        self.recv_dropped
+    }
+}
+
+impl Debug for RawSocket {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.debug_struct("RawSocket")
+            .field("raw_type", &self.raw_type)
+            .field("spoofing", &self.spoofing)
+            .field("filtered", &self.filter.is_some())
+            .field("timestamping", &self.timestamp_env.is_some())
+            .field("send_buffer", &self.send_buffer)
+            .field("recv_buffer", &self.recv_buffer)
+            .finish()
+    }
+}
@@ -0,0 +1,923 @@
+use alloc::collections::BTreeMap;
+use alloc::string::{
+    String,
+    ToString,
+};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::iter::{
+    Enumerate,
+    Zip,
+};
+use core::mem;
+use core::slice::{
+    Iter as SliceIter,
+    IterMut as SliceIterMut,
+};
+
+use stack::repr::Ipv4Address;
+use stack::socket::{
+    RawType,
+    SocketAddr,
+    TaggedSocket,
+    TaggedSocketAddr,
+};
+use stack::storage::Managed;
+use stack::time::PollAt;
+use {
+    Error,
+    Result,
+    SocketError,
+};
+
+/// A handle to a socket in a SocketSet.
+///
+/// Handles carry the generation of the slot they refer to, so a handle to a
+/// removed socket is rejected rather than aliasing a socket added later in
+/// the same slot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SocketHandle {
+    index: usize,
+    generation: usize,
+}
+
+impl SocketHandle {
+    #[cfg(test)]
+    pub fn new(index: usize, generation: usize) -> SocketHandle {
+        SocketHandle { index, generation }
+    }
+
+    /// Returns the index of the slot the handle refers to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+/// The key sockets are indexed by, i.e. the local address a socket is bound
+/// to and the remote endpoint it is connected to, if any.
+type BindingKey = (TaggedSocketAddr, Option<SocketAddr>);
+
+/// A set of sockets with stable handles.
+///
+/// Sockets are indexed by the local address they are bound to and the remote
+/// endpoint they are connected to, so inbound packets can be demultiplexed to
+/// the few sockets matching their (source, destination) addresses via
+/// iter_mut_bound(...) rather than iterating over every socket, e.g. every
+/// connection accepted from a listener. The index is updated when sockets are
+/// added or removed, and sockets accessed via socket(...) are marked stale
+/// and reindexed before the next lookup, since they may connect or be
+/// replaced.
+///
+/// Sockets are held in slots which are either allocated or borrowed from
+/// static storage, see From<&'static mut [Option<TaggedSocket>]>.
+pub struct SocketSet {
+    growable: bool,
+    sockets: Managed<Option<TaggedSocket>>,
+    generations: Vec<usize>,
+    pinned_interfaces: Vec<Option<usize>>,
+    receiving_interface: Option<usize>,
+    count: usize,
+    bindings: Vec<Option<BindingKey>>,
+    /// Ascending indices of the sockets with each key.
+    bound_sockets: BTreeMap<BindingKey, Vec<usize>>,
+    /// Indicates a socket is queued in stale_bindings, so each socket is
+    /// queued at most once.
+    is_stale: Vec<bool>,
+    stale_bindings: Vec<usize>,
+}
+
+impl From<&'static mut [Option<TaggedSocket>]> for SocketSet {
+    /// Creates a socket set holding sockets in static storage, which supports
+    /// as many sockets as there are slots. Any sockets in the slots are
+    /// dropped.
+    fn from(sockets: &'static mut [Option<TaggedSocket>]) -> SocketSet {
+        for socket in sockets.iter_mut() {
+            *socket = None;
+        }
+
+        SocketSet::from(Managed::from(sockets))
+    }
+}
+
+impl From<Managed<Option<TaggedSocket>>> for SocketSet {
+    fn from(sockets: Managed<Option<TaggedSocket>>) -> SocketSet {
+        let socket_capacity = sockets.len();
+        SocketSet {
+            growable: false,
+            sockets,
+            generations: vec![0; socket_capacity],
+            pinned_interfaces: vec![None; socket_capacity],
+            receiving_interface: None,
+            count: 0,
+            bindings: vec![None; socket_capacity],
+            bound_sockets: BTreeMap::new(),
+            is_stale: vec![false; socket_capacity],
+            stale_bindings: Vec::new(),
+        }
+    }
+}
+
+impl SocketSet {
+    /// Creates a socket set supporting a maximum number of sockets.
+    pub fn new(socket_capacity: usize) -> SocketSet {
+        let sockets: Vec<_> = (0 .. socket_capacity).map(|_| None).collect();
+        SocketSet::from(Managed::from(sockets))
+    }
+
+    /// Creates a socket set with space for socket_capacity sockets, which
+    /// grows on demand when more sockets are added.
+    pub fn with_capacity(socket_capacity: usize) -> SocketSet {
+        SocketSet {
+            growable: true,
+            ..SocketSet::new(socket_capacity)
+        }
+    }
+
+    /// Adds a socket and returns a stable handle, or SocketError::SetFull if
+    /// the set is full and cannot grow.
+    pub fn add_socket(&mut self, socket: TaggedSocket) -> Result<SocketHandle> {
+        let index = match self.sockets.iter().position(|socket| socket.is_none()) {
+            Some(index) => index,
+            None if self.growable => {
+                let index = self.capacity();
+                self.grow();
+                index
+            }
+            None => return Err(Error::Socket(SocketError::SetFull)),
+        };
+
+        self.sockets[index] = Some(socket);
+        self.pinned_interfaces[index] = None;
+        self.count += 1;
+        self.index_binding(index);
+
+        Ok(SocketHandle {
+            index,
+            generation: self.generations[index],
+        })
+    }
+
+    /// Removes a socket from the set and returns it. Dropping the socket
+    /// releases its buffers and bindings.
+    ///
+    /// The handle, and any copies of it, are invalidated even if another
+    /// socket is later added in the same slot.
+    pub fn remove(&mut self, socket_handle: SocketHandle) -> Result<TaggedSocket> {
+        let index = self.index(socket_handle)?;
+
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.pinned_interfaces[index] = None;
+        self.count -= 1;
+
+        let socket = self.sockets[index].take().unwrap();
+        self.index_binding(index);
+        Ok(socket)
+    }
+
+    /// Returns a reference to a socket with the specified handle, or
+    /// SocketError::InvalidHandle if the handle is not in use.
+    pub fn socket(&mut self, socket_handle: SocketHandle) -> Result<&mut TaggedSocket> {
+        let index = self.index(socket_handle)?;
+        if !self.is_stale[index] {
+            self.is_stale[index] = true;
+            self.stale_bindings.push(index);
+        }
+        Ok(self.sockets[index].as_mut().unwrap())
+    }
+
+    /// Returns the number of sockets in the set.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Checks if the set contains no sockets.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the number of sockets the set can hold before it is full, or
+    /// must grow if created via with_capacity(...).
+    pub fn capacity(&self) -> usize {
+        self.sockets.len()
+    }
+
+    /// Returns a netstat-like table of the sockets in the set with the index
+    /// of their handle, type, local and remote addresses, TCP state, queue
+    /// lengths and received packets dropped. Queue lengths are in bytes for
+    /// TCP sockets and packets for other sockets.
+    pub fn dump(&self) -> String {
+        let mut dump = String::new();
+        writeln!(
+            dump,
+            "{:<6} {:<8} {:<21} {:<21} {:<12} {:>8} {:>8} {:>8}",
+            "INDEX", "TYPE", "LOCAL", "REMOTE", "STATE", "SEND-Q", "RECV-Q", "DROPS"
+        )
+        .unwrap();
+
+        for (index, socket) in self.sockets.iter().enumerate() {
+            if let Some(ref socket) = *socket {
+                let summary = SocketSummary::from(socket);
+                writeln!(
+                    dump,
+                    "{:<6} {:<8} {:<21} {:<21} {:<12} {:>8} {:>8} {:>8}",
+                    index,
+                    summary.socket_type,
+                    summary.local_addr,
+                    summary.remote_addr,
+                    summary.state,
+                    summary.send_queued,
+                    summary.recv_queued,
+                    summary.recv_dropped
+                )
+                .unwrap();
+            }
+        }
+
+        dump
+    }
+
+    /// Returns when the earliest of the sockets in the set next needs
+    /// servicing.
+    pub fn poll_at(&self) -> PollAt {
+        self.sockets
+            .iter()
+            .filter_map(|socket| socket.as_ref().map(|socket| socket.poll_at()))
+            .min()
+            .unwrap_or(PollAt::Ingress)
+    }
+
+    /// Pins a socket to an interface of an InterfaceSet, so its packets are
+    /// only sent and received via that interface, or unpins it with None.
+    pub fn pin_socket(
+        &mut self,
+        socket_handle: SocketHandle,
+        interface_handle: Option<usize>,
+    ) -> Result<()> {
+        let index = self.index(socket_handle)?;
+        self.pinned_interfaces[index] = interface_handle;
+        Ok(())
+    }
+
+    /// Returns the interface a socket is pinned to, if any.
+    pub fn pinned_interface(&self, socket_handle: SocketHandle) -> Result<Option<usize>> {
+        let index = self.index(socket_handle)?;
+        Ok(self.pinned_interfaces[index])
+    }
+
+    /// Sets the interface packets are being received from, so sockets pinned
+    /// to other interfaces are skipped when iterating, or None to iterate over
+    /// all sockets. Used by socket::recv_all(...).
+    pub fn set_receiving_interface(&mut self, interface_handle: Option<usize>) {
+        self.receiving_interface = interface_handle;
+    }
+
+    /// Returns an iterator over all of the sockets in the set.
+    pub fn iter_mut(&mut self) -> SocketIter<'_> {
+        SocketIter {
+            inner: self.iter_mut_pinned(),
+        }
+    }
+
+    /// Returns an iterator over all of the sockets in the set along with their
+    /// handles.
+    pub fn iter_mut_with_handles(&mut self) -> HandleSocketIter<'_> {
+        HandleSocketIter {
+            inner: self
+                .sockets
+                .iter_mut()
+                .zip(self.generations.iter())
+                .enumerate(),
+        }
+    }
+
+    /// Returns an iterator over all of the sockets in the set along with the
+    /// interface each socket is pinned to.
+    pub fn iter_mut_pinned(&mut self) -> PinnedSocketIter<'_> {
+        PinnedSocketIter {
+            inner: self.sockets.iter_mut().zip(self.pinned_interfaces.iter()),
+            receiving_interface: self.receiving_interface,
+        }
+    }
+
+    /// Returns an iterator over the sockets which may accept a unicast packet
+    /// from a remote endpoint to a local address, i.e. the sockets bound to
+    /// the local address, or to the unspecified address and the same port,
+    /// which are connected to the remote endpoint or not connected at all.
+    ///
+    /// Sockets may still reject packets, e.g. listeners receiving segments for
+    /// connections they do not own, so callers should check if each socket
+    /// accepts the packet.
+    pub fn iter_mut_bound(
+        &mut self,
+        local_addr: &TaggedSocketAddr,
+        remote_addr: &SocketAddr,
+    ) -> BoundSocketIter<'_> {
+        for index in mem::take(&mut self.stale_bindings) {
+            self.is_stale[index] = false;
+            self.index_binding(index);
+        }
+
+        let wildcard_addr = with_addr(local_addr, Ipv4Address::UNSPECIFIED);
+        let is_wildcard = local_addr.addr.is_unspecified();
+        let bound_sockets = &self.bound_sockets;
+        let bucket = |local_addr: &TaggedSocketAddr, remote_addr: Option<SocketAddr>| {
+            bound_sockets
+                .get(&(local_addr.clone(), remote_addr))
+                .map_or(&[][..], |indices| &indices[..])
+        };
+        let wildcard_bucket = |remote_addr: Option<SocketAddr>| {
+            if is_wildcard {
+                &[][..]
+            } else {
+                bucket(&wildcard_addr, remote_addr)
+            }
+        };
+
+        BoundSocketIter {
+            buckets: [
+                bucket(local_addr, Some(*remote_addr)),
+                wildcard_bucket(Some(*remote_addr)),
+                bucket(local_addr, None),
+                wildcard_bucket(None),
+            ],
+            sockets: &mut self.sockets[..],
+            pinned_interfaces: &self.pinned_interfaces[..],
+            receiving_interface: self.receiving_interface,
+            offset: 0,
+        }
+    }
+
+    /// Doubles the capacity of the set.
+    fn grow(&mut self) {
+        let socket_capacity = self.capacity().max(1) * 2;
+        if let Managed::Owned(ref mut sockets) = self.sockets {
+            sockets.resize_with(socket_capacity, || None);
+        }
+        self.generations.resize(socket_capacity, 0);
+        self.pinned_interfaces.resize(socket_capacity, None);
+        self.bindings.resize(socket_capacity, None);
+        self.is_stale.resize(socket_capacity, false);
+    }
+
+    /// Updates the key a socket is indexed by, e.g. after it is added,
+    /// removed or connected.
+    fn index_binding(&mut self, index: usize) {
+        let binding = self.sockets[index].as_ref().and_then(|socket| {
+            let remote_addr = match *socket {
+                TaggedSocket::Udp(ref socket) => socket.peer_addr().ok(),
+                TaggedSocket::Tcp(ref socket) => socket.peer_addr(),
+                _ => None,
+            };
+            socket
+                .binding()
+                .map(|binding| (binding.clone(), remote_addr))
+        });
+
+        if self.bindings[index] == binding {
+            return;
+        }
+
+        if let Some(prev_binding) = self.bindings[index].take() {
+            let is_unused = {
+                let indices = self.bound_sockets.get_mut(&prev_binding).unwrap();
+                indices.retain(|i| *i != index);
+                indices.is_empty()
+            };
+
+            if is_unused {
+                self.bound_sockets.remove(&prev_binding);
+            }
+        }
+
+        if let Some(ref binding) = binding {
+            let indices = self.bound_sockets.entry(binding.clone()).or_default();
+            if let Err(position) = indices.binary_search(&index) {
+                indices.insert(position, index);
+            }
+        }
+
+        self.bindings[index] = binding;
+    }
+
+    fn index(&self, socket_handle: SocketHandle) -> Result<usize> {
+        let index = socket_handle.index;
+
+        match self.sockets.get(index) {
+            Some(&Some(_)) if self.generations[index] == socket_handle.generation => Ok(index),
+            _ => Err(Error::Socket(SocketError::InvalidHandle)),
+        }
+    }
+}
+
+fn with_addr(tagged_addr: &TaggedSocketAddr, addr: Ipv4Address) -> TaggedSocketAddr {
+    let socket_addr = SocketAddr {
+        addr,
+        port: tagged_addr.port,
+    };
+
+    match *tagged_addr {
+        TaggedSocketAddr::Udp(_) => TaggedSocketAddr::Udp(socket_addr),
+        TaggedSocketAddr::Tcp(_) => TaggedSocketAddr::Tcp(socket_addr),
+        TaggedSocketAddr::Icmp(_) => TaggedSocketAddr::Icmp(socket_addr),
+    }
+}
+
+fn is_receiving(pinned_interface: Option<usize>, receiving_interface: Option<usize>) -> bool {
+    match (pinned_interface, receiving_interface) {
+        (Some(pinned_interface), Some(receiving_interface)) => {
+            pinned_interface == receiving_interface
+        }
+        _ => true,
+    }
+}
+
+/// A row of SocketSet::dump().
+struct SocketSummary {
+    socket_type: &'static str,
+    local_addr: String,
+    remote_addr: String,
+    state: &'static str,
+    send_queued: usize,
+    recv_queued: usize,
+    recv_dropped: u64,
+}
+
+impl<'a> From<&'a TaggedSocket> for SocketSummary {
+    fn from(socket: &'a TaggedSocket) -> SocketSummary {
+        let local_addr = socket
+            .binding()
+            .map(|binding| (**binding).to_string())
+            .unwrap_or_else(|| "*".to_string());
+        let unconnected = |remote_addr: Option<SocketAddr>| {
+            remote_addr
+                .map(|remote_addr| remote_addr.to_string())
+                .unwrap_or_else(|| "*".to_string())
+        };
+
+        match *socket {
+            TaggedSocket::Raw(ref socket) => SocketSummary {
+                socket_type: match socket.raw_type() {
+                    RawType::Ethernet => "RAW/ETH",
+                    RawType::Ipv4 => "RAW/IPV4",
+                },
+                local_addr,
+                remote_addr: unconnected(None),
+                state: "-",
+                send_queued: socket.send_enqueued(),
+                recv_queued: socket.recv_enqueued(),
+                recv_dropped: socket.recv_dropped(),
+            },
+            TaggedSocket::Icmp(ref socket) => SocketSummary {
+                socket_type: "ICMP",
+                local_addr,
+                remote_addr: unconnected(None),
+                state: "-",
+                send_queued: socket.send_enqueued(),
+                recv_queued: socket.recv_enqueued(),
+                recv_dropped: socket.recv_dropped(),
+            },
+            TaggedSocket::Udp(ref socket) => SocketSummary {
+                socket_type: "UDP",
+                local_addr,
+                remote_addr: unconnected(socket.peer_addr().ok()),
+                state: "-",
+                send_queued: socket.send_enqueued(),
+                recv_queued: socket.recv_enqueued(),
+                recv_dropped: socket.recv_dropped(),
+            },
+            TaggedSocket::Tcp(ref socket) => SocketSummary {
+                socket_type: "TCP",
+                local_addr,
+                remote_addr: unconnected(socket.peer_addr()),
+                state: socket.state_name(),
+                send_queued: socket.send_queued(),
+                recv_queued: socket.recv_queued(),
+                recv_dropped: 0,
+            },
+            TaggedSocket::TcpListener(ref socket) => SocketSummary {
+                socket_type: "TCP",
+                local_addr,
+                remote_addr: unconnected(None),
+                state: "LISTEN",
+                send_queued: 0,
+                recv_queued: 0,
+                recv_dropped: socket.stats().dropped,
+            },
+        }
+    }
+}
+
+/// An iterator over the sockets in a SocketSet.
+pub struct SocketIter<'a> {
+    inner: PinnedSocketIter<'a>,
+}
+
+impl<'a> Iterator for SocketIter<'a> {
+    type Item = &'a mut TaggedSocket;
+
+    fn next(&mut self) -> Option<&'a mut TaggedSocket> {
+        self.inner.next().map(|(_, socket)| socket)
+    }
+}
+
+/// An iterator over the sockets in a SocketSet and the interfaces they are
+/// pinned to.
+pub struct PinnedSocketIter<'a> {
+    inner: Zip<SliceIterMut<'a, Option<TaggedSocket>>, SliceIter<'a, Option<usize>>>,
+    receiving_interface: Option<usize>,
+}
+
+impl<'a> Iterator for PinnedSocketIter<'a> {
+    type Item = (Option<usize>, &'a mut TaggedSocket);
+
+    fn next(&mut self) -> Option<(Option<usize>, &'a mut TaggedSocket)> {
+        for (socket, &pinned_interface) in &mut self.inner {
+            if !is_receiving(pinned_interface, self.receiving_interface) {
+                continue;
+            }
+
+            if let Some(ref mut socket) = *socket {
+                return Some((pinned_interface, socket));
+            }
+        }
+
+        None
+    }
+}
+
+/// An iterator over the sockets in a SocketSet and their handles.
+pub struct HandleSocketIter<'a> {
+    inner: Enumerate<Zip<SliceIterMut<'a, Option<TaggedSocket>>, SliceIter<'a, usize>>>,
+}
+
+impl<'a> Iterator for HandleSocketIter<'a> {
+    type Item = (SocketHandle, &'a mut TaggedSocket);
+
+    fn next(&mut self) -> Option<(SocketHandle, &'a mut TaggedSocket)> {
+        for (index, (socket, &generation)) in &mut self.inner {
+            if let Some(ref mut socket) = *socket {
+                return Some((SocketHandle { index, generation }, socket));
+            }
+        }
+
+        None
+    }
+}
+
+/// An iterator over the sockets in a SocketSet bound to a local address.
+pub struct BoundSocketIter<'a> {
+    /// Ascending indices of the sockets with each key matching the address,
+    /// which are disjoint since each socket has one key.
+    buckets: [&'a [usize]; 4],
+    sockets: &'a mut [Option<TaggedSocket>],
+    pinned_interfaces: &'a [Option<usize>],
+    receiving_interface: Option<usize>,
+    offset: usize,
+}
+
+impl<'a> BoundSocketIter<'a> {
+    /// Removes and returns the lowest index at the head of any bucket.
+    fn next_index(&mut self) -> Option<usize> {
+        let bucket = self
+            .buckets
+            .iter_mut()
+            .filter(|bucket| !bucket.is_empty())
+            .min_by_key(|bucket| bucket[0])?;
+        let index = bucket[0];
+        *bucket = &bucket[1 ..];
+        Some(index)
+    }
+}
+
+impl<'a> Iterator for BoundSocketIter<'a> {
+    type Item = &'a mut TaggedSocket;
+
+    fn next(&mut self) -> Option<&'a mut TaggedSocket> {
+        while let Some(index) = self.next_index() {
+            // Indices are ascending, so split off each socket in turn...
+            let sockets = mem::take(&mut self.sockets);
+            let (socket, sockets) = sockets[index - self.offset ..].split_first_mut().unwrap();
+            self.sockets = sockets;
+            self.offset = index + 1;
+
+            if !is_receiving(self.pinned_interfaces[index], self.receiving_interface) {
+                continue;
+            }
+
+            if let Some(ref mut socket) = *socket {
+                return Some(socket);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::time::Duration;
+
+    use super::*;
+    use stack::socket::{
+        Bindings,
+        RawSocket,
+        RawType,
+        SequentialIsnGenerator,
+        TcpSocket,
+        UdpSocket,
+    };
+    use stack::storage::{
+        Ring,
+        Slice,
+    };
+    use stack::time::MockEnv;
+
+    fn socket() -> TaggedSocket {
+        let buffer = || Ring::from(vec![Slice::from(vec![0; 64]); 1]);
+        TaggedSocket::Raw(RawSocket::new(RawType::Ethernet, buffer(), buffer()))
+    }
+
+    fn udp_socket(bindings: &Bindings, addr: [u8; 4], port: u16) -> TaggedSocket {
+        let socket_addr = SocketAddr {
+            addr: Ipv4Address::new(addr),
+            port,
+        };
+        let binding = bindings.bind_udp(socket_addr).unwrap();
+        let buffer = || Ring::from(vec![(Slice::from(vec![0; 64]), socket_addr); 1]);
+        TaggedSocket::Udp(UdpSocket::new(binding, buffer(), buffer()))
+    }
+
+    fn bound_ports(socket_set: &mut SocketSet, addr: [u8; 4], port: u16) -> Vec<u16> {
+        let local_addr = TaggedSocketAddr::Udp(SocketAddr {
+            addr: Ipv4Address::new(addr),
+            port,
+        });
+        let remote_addr = SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 9]),
+            port: 9,
+        };
+        socket_set
+            .iter_mut_bound(&local_addr, &remote_addr)
+            .map(|socket| socket.binding().unwrap().port)
+            .collect()
+    }
+
+    #[test]
+    fn test_add_and_remove() {
+        let mut socket_set = SocketSet::new(1);
+        let handle = socket_set.add_socket(socket()).unwrap();
+        match socket_set.add_socket(socket()) {
+            Err(Error::Socket(SocketError::SetFull)) => {}
+            _ => panic!("Added socket to full set."),
+        }
+        assert_eq!(socket_set.len(), 1);
+
+        assert!(socket_set.remove(handle).is_ok());
+        assert_eq!(socket_set.len(), 0);
+        assert!(socket_set.iter_mut().next().is_none());
+
+        match socket_set.remove(handle) {
+            Err(Error::Socket(SocketError::InvalidHandle)) => {}
+            _ => panic!("Removed socket twice."),
+        }
+    }
+
+    #[test]
+    fn test_stale_handle() {
+        let mut socket_set = SocketSet::new(1);
+        let stale_handle = socket_set.add_socket(socket()).unwrap();
+        socket_set.pin_socket(stale_handle, Some(1)).unwrap();
+        socket_set.remove(stale_handle).unwrap();
+
+        // The slot is reused, but the stale handle does not alias the socket.
+        let handle = socket_set.add_socket(socket()).unwrap();
+        assert_eq!(handle.index(), stale_handle.index());
+        assert_ne!(handle, stale_handle);
+        assert!(socket_set.socket(handle).is_ok());
+        assert_eq!(socket_set.pinned_interface(handle).unwrap(), None);
+
+        match socket_set.socket(stale_handle) {
+            Err(Error::Socket(SocketError::InvalidHandle)) => {}
+            _ => panic!("Stale handle aliased a new socket."),
+        }
+        assert!(socket_set.pin_socket(stale_handle, None).is_err());
+    }
+
+    #[test]
+    fn test_iter_mut_bound() {
+        let bindings = Bindings::new();
+        let mut socket_set = SocketSet::new(4);
+        let handle = socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 1))
+            .unwrap();
+        socket_set
+            .add_socket(udp_socket(&bindings, [0, 0, 0, 0], 1))
+            .unwrap();
+        socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 2))
+            .unwrap();
+        socket_set.add_socket(socket()).unwrap();
+
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 1), vec![1, 1]);
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 2], 1), vec![1]);
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 3), vec![]);
+
+        // Sockets replaced via their handle are reindexed...
+        *socket_set.socket(handle).unwrap() = udp_socket(&bindings, [10, 0, 0, 1], 3);
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 1), vec![1]);
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 3), vec![3]);
+
+        // As are removed sockets.
+        socket_set.remove(handle).unwrap();
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 3), vec![]);
+    }
+
+    #[test]
+    fn test_iter_mut_bound_connected() {
+        let bindings = Bindings::new();
+        let mut socket_set = SocketSet::new(4);
+        socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 1))
+            .unwrap();
+        let handle = socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 2))
+            .unwrap();
+
+        // Sockets connected to other endpoints are skipped...
+        socket_set
+            .socket(handle)
+            .unwrap()
+            .as_udp_socket()
+            .connect(SocketAddr {
+                addr: Ipv4Address::new([10, 0, 0, 8]),
+                port: 8,
+            });
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 2), vec![]);
+
+        // While sockets connected to the remote endpoint are found.
+        socket_set
+            .socket(handle)
+            .unwrap()
+            .as_udp_socket()
+            .connect(SocketAddr {
+                addr: Ipv4Address::new([10, 0, 0, 9]),
+                port: 9,
+            });
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 2), vec![2]);
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 1), vec![1]);
+    }
+
+    #[test]
+    fn test_socket_queues_stale_binding_once() {
+        let bindings = Bindings::new();
+        let mut socket_set = SocketSet::new(4);
+        let handle = socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 1))
+            .unwrap();
+
+        for _ in 0 .. 16 {
+            socket_set.socket(handle).unwrap();
+        }
+        assert_eq!(socket_set.stale_bindings, vec![0]);
+
+        assert_eq!(bound_ports(&mut socket_set, [10, 0, 0, 1], 1), vec![1]);
+        assert!(socket_set.stale_bindings.is_empty());
+        socket_set.socket(handle).unwrap();
+        assert_eq!(socket_set.stale_bindings, vec![0]);
+    }
+
+    #[test]
+    fn test_with_capacity_grows() {
+        let mut socket_set = SocketSet::with_capacity(1);
+        let handles: Vec<SocketHandle> = (0 .. 3)
+            .map(|_| socket_set.add_socket(socket()).unwrap())
+            .collect();
+        assert_eq!(socket_set.len(), 3);
+        assert_eq!(socket_set.capacity(), 4);
+
+        socket_set.remove(handles[1]).unwrap();
+        let iter_handles: Vec<SocketHandle> = socket_set
+            .iter_mut_with_handles()
+            .map(|(handle, _)| handle)
+            .collect();
+        assert_eq!(iter_handles, vec![handles[0], handles[2]]);
+    }
+
+    #[test]
+    fn test_static() {
+        let sockets: &'static mut [Option<TaggedSocket>] =
+            Box::leak(Box::new([Some(socket()), None]));
+        let mut socket_set = SocketSet::from(sockets);
+        assert!(socket_set.is_empty());
+        assert_eq!(socket_set.capacity(), 2);
+
+        let handle = socket_set.add_socket(socket()).unwrap();
+        socket_set.add_socket(socket()).unwrap();
+        match socket_set.add_socket(socket()) {
+            Err(Error::Socket(SocketError::SetFull)) => {}
+            _ => panic!("Added socket to full set."),
+        }
+
+        socket_set.remove(handle).unwrap();
+        assert_eq!(socket_set.iter_mut().count(), 1);
+    }
+
+    #[test]
+    fn test_poll_at() {
+        let mut socket_set = SocketSet::new(2);
+        assert_eq!(socket_set.poll_at(), PollAt::Ingress);
+
+        let raw_handle = socket_set.add_socket(socket()).unwrap();
+        assert_eq!(socket_set.poll_at(), PollAt::Ingress);
+        {
+            let raw_socket = socket_set.socket(raw_handle).unwrap().as_raw_socket();
+            raw_socket.send(1).unwrap();
+        }
+        assert_eq!(socket_set.poll_at(), PollAt::Now);
+        socket_set.remove(raw_handle).unwrap();
+
+        // A SYN is sent right away and retransmitted after a backed off timeout.
+        let bindings = Bindings::new();
+        let binding = bindings
+            .bind_tcp(SocketAddr {
+                addr: Ipv4Address::new([10, 0, 0, 1]),
+                port: 1024,
+            })
+            .unwrap();
+        let time_env = MockEnv::new();
+        let mut tcp_socket = TcpSocket::new(
+            binding,
+            1500,
+            64,
+            64,
+            time_env.clone(),
+            Rc::new(SequentialIsnGenerator::new(0)),
+        );
+        assert_eq!(tcp_socket.poll_at(), PollAt::Ingress);
+        tcp_socket.connect(SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 2]),
+            port: 80,
+        });
+
+        let tcp_handle = socket_set
+            .add_socket(TaggedSocket::Tcp(tcp_socket))
+            .unwrap();
+        assert_eq!(socket_set.poll_at(), PollAt::Now);
+        let tcp_socket = socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+        tcp_socket.send_dequeue(|_, _, _| Ok(())).unwrap();
+        assert_eq!(
+            socket_set.poll_at(),
+            PollAt::Time(time_env.now + Duration::from_secs(2))
+        );
+    }
+
+    #[test]
+    fn test_dump() {
+        let bindings = Bindings::new();
+        let mut socket_set = SocketSet::new(4);
+        socket_set.add_socket(socket()).unwrap();
+        socket_set
+            .add_socket(udp_socket(&bindings, [10, 0, 0, 1], 53))
+            .unwrap();
+
+        let binding = bindings
+            .bind_tcp(SocketAddr {
+                addr: Ipv4Address::new([10, 0, 0, 1]),
+                port: 1024,
+            })
+            .unwrap();
+        let mut tcp_socket = TcpSocket::new(
+            binding,
+            1500,
+            64,
+            64,
+            MockEnv::new(),
+            Rc::new(SequentialIsnGenerator::new(0)),
+        );
+        tcp_socket.connect(SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 2]),
+            port: 80,
+        });
+        socket_set
+            .add_socket(TaggedSocket::Tcp(tcp_socket))
+            .unwrap();
+
+        let dump = socket_set.dump();
+        let rows: Vec<Vec<&str>> = dump
+            .lines()
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0][0], "INDEX");
+        assert_eq!(rows[1], ["0", "RAW/ETH", "*", "*", "-", "0", "0", "0"]);
+        assert_eq!(
+            rows[2],
+            ["1", "UDP", "10.0.0.1:53", "*", "-", "0", "0", "0"]
+        );
+        assert_eq!(
+            rows[3][.. 5],
+            ["2", "TCP", "10.0.0.1:1024", "10.0.0.2:80", "SYN_SENT"]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use stack::socket::{
+    IcmpSocket,
+    RawSocket,
+    TaggedSocketAddr,
+    TcpListener,
+    TcpSocket,
+    UdpSocket,
+};
+use stack::time::PollAt;
+
+/// One of many types of sockets.
+#[allow(clippy::large_enum_variant)]
+pub enum TaggedSocket {
+    Raw(RawSocket),
+    Icmp(IcmpSocket),
+    Udp(UdpSocket),
+    Tcp(TcpSocket),
+    TcpListener(TcpListener),
+}
+
+impl TaggedSocket {
+    /// Returns the local address the socket is bound to, or None for raw
+    /// sockets which are not bound to an address.
+    pub fn binding(&self) -> Option<&TaggedSocketAddr> {
+        match *self {
+            TaggedSocket::Raw(_) => None,
+            TaggedSocket::Icmp(ref socket) => Some(socket.binding()),
+            TaggedSocket::Udp(ref socket) => Some(socket.binding()),
+            TaggedSocket::Tcp(ref socket) => Some(socket.binding()),
+            TaggedSocket::TcpListener(ref socket) => Some(socket.binding()),
+        }
+    }
+
+    /// Returns when the socket next needs servicing.
+    pub fn poll_at(&self) -> PollAt {
+        match *self {
+            TaggedSocket::Raw(ref socket) => socket.poll_at(),
+            TaggedSocket::Icmp(ref socket) => socket.poll_at(),
+            TaggedSocket::Udp(ref socket) => socket.poll_at(),
+            TaggedSocket::Tcp(ref socket) => socket.poll_at(),
+            TaggedSocket::TcpListener(ref socket) => socket.poll_at(),
+        }
+    }
+
+    /// Returns a reference to the underlying raw socket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying socket is not a raw socket.
+    pub fn as_raw_socket(&mut self) -> &mut RawSocket {
+        match *self {
+            TaggedSocket::Raw(ref mut socket) => socket,
+            _ => panic!("Not a raw socket!"),
+        }
+    }
+
+    /// Returns a reference to the underlying ICMP socket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying socket is not an ICMP socket.
+    pub fn as_icmp_socket(&mut self) -> &mut IcmpSocket {
+        match *self {
+            TaggedSocket::Icmp(ref mut socket) => socket,
+            _ => panic!("Not an ICMP socket!"),
+        }
+    }
+
+    /// Returns a reference to the underlying TCP socket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying socket is not a TCP socket.
+    pub fn as_tcp_socket(&mut self) -> &mut TcpSocket {
+        match *self {
+            TaggedSocket::Tcp(ref mut socket) => socket,
+            _ => panic!("Not a TCP socket!"),
+        }
+    }
+
+    /// Returns a reference to the underlying TCP listener.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying socket is not a TCP listener.
+    pub fn as_tcp_listener(&mut self) -> &mut TcpListener {
+        match *self {
+            TaggedSocket::TcpListener(ref mut socket) => socket,
+            _ => panic!("Not a TCP listener!"),
+        }
+    }
+
+    /// Returns a reference to the underlying UDP socket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the underlying socket is not a UDP socket.
+    pub fn as_udp_socket(&mut self) -> &mut UdpSocket {
+        match *self {
+            TaggedSocket::Udp(ref mut socket) => socket,
+            _ => panic!("Not a UDP socket!"),
+        }
+    }
+}
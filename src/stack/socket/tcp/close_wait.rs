@@ -0,0 +1,78 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpLastAck,
+    TcpState,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP CLOSE_WAIT state.
+///
+/// The remote endpoint has finished sending, but we may continue sending until
+/// the sending half of the connection is shut down.
+#[derive(Debug)]
+pub struct TcpCloseWait {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpCloseWait {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "CLOSE_WAIT @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        (
+            None,
+            self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload),
+        )
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpCloseWait {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from CLOSE_WAIT to CLOSED in response to a RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+
+    /// Transitions from CLOSE_WAIT to LAST_ACK when the sending half of the
+    /// connection is shut down.
+    pub fn to_last_ack(&mut self) -> TcpLastAck {
+        self.connection.shutdown_send();
+        TcpLastAck {
+            connection: self.connection.take(),
+        }
+    }
+}
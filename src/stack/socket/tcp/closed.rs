@@ -0,0 +1,44 @@
+use core::time::Duration;
+
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpContext,
+    TcpStats,
+    TcpSynSent,
+};
+use stack::time::Timer;
+
+/// The TCP CLOSED state.
+#[derive(Debug)]
+pub struct TcpClosed {
+    /// Statistics of the last connection, if any.
+    pub stats: TcpStats,
+    pub context: TcpContext,
+}
+
+impl Tcp for TcpClosed {}
+
+impl TcpClosed {
+    /// Transitions from CLOSED to SYN_SENT in an attempt to establish a
+    /// connection with the specified endpoint within a timeout.
+    pub fn to_syn_sent(
+        &mut self,
+        socket_addr: SocketAddr,
+        connect_timeout: Duration,
+    ) -> TcpSynSent {
+        self.context.binding.set_lingering(false);
+        let mut connect_timer = Timer::new();
+        connect_timer.start(&*self.context.time_env, connect_timeout);
+        TcpSynSent {
+            retransmit_timer: Timer::new(),
+            seq_num: self.context.gen_isn(&socket_addr),
+            connecting_to: socket_addr,
+            retransmit_timeout: Duration::from_secs(1),
+            retries: 0,
+            connect_timer,
+            stats: TcpStats::default(),
+            context: self.context.clone(),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpState,
+    TcpTimeWait,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP CLOSING state.
+///
+/// Both endpoints sent a FIN simultaneously and we are waiting for our FIN to
+/// be acknowledged.
+#[derive(Debug)]
+pub struct TcpClosing {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpClosing {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "CLOSING @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        let ok_or_err = self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        if self.connection.fin_acked {
+            debug!(
+                "CLOSING @ ({}, {}) received ACK for FIN, transition to TIME_WAIT.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::TimeWait(self.to_time_wait())), ok_or_err);
+        }
+
+        (None, ok_or_err)
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpClosing {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from CLOSING to CLOSED in response to a RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+
+    /// Transitions from CLOSING to TIME_WAIT in response to an ACK for our FIN.
+    pub fn to_time_wait(&mut self) -> TcpTimeWait {
+        TcpTimeWait::new(self.connection.take())
+    }
+}
@@ -0,0 +1,899 @@
+use core::cmp::{
+    max,
+    min,
+};
+use core::mem;
+use core::time::Duration;
+
+use stack::repr::{
+    ipv4_flags,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpPacket,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    TcpClosed,
+    TcpContext,
+    TcpStats,
+};
+use stack::storage::{
+    Assembler,
+    ByteRing,
+};
+use stack::time::{
+    Instant,
+    PollAt,
+    Timer,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// MSS to assume when the remote endpoint does not specify one.
+pub const DEFAULT_MSS: usize = 536;
+
+/// Initial timeout before unacknowledged data is retransmitted.
+pub const INITIAL_RTO: Duration = Duration::from_secs(1);
+
+/// Smallest MTU a path may report, see RFC 1191.
+pub const MIN_PATH_MTU: usize = 68;
+
+/// Lower bound for the retransmission timeout, as used by Linux.
+pub const MIN_RTO: Duration = Duration::from_millis(200);
+
+/// Upper bound for the retransmission timeout as it backs off.
+pub const MAX_RTO: Duration = Duration::from_secs(60);
+
+/// Number of consecutive retransmissions of a SYN, SYN + ACK or data after
+/// which the remote endpoint is considered unreachable and the connection is
+/// closed with Error::Timeout.
+pub const MAX_RETRIES: usize = 8;
+
+/// A synchronized connection with a remote endpoint.
+///
+/// This holds the sequence numbers, buffers, and retransmission state shared
+/// by ESTABLISHED and the states a connection moves through while closing.
+#[derive(Debug)]
+pub struct TcpConnection {
+    pub connected_to: SocketAddr,
+    /// Sequence number of the oldest unacknowledged byte we have sent.
+    pub seq_num: u32,
+    /// Sequence number of the next byte we expect to receive.
+    pub ack_num: u32,
+    /// Indicates if ack_num has been acknowledged to the remote endpoint.
+    pub ack_sent: bool,
+    /// Bytes at the head of the send buffer which have been sent since the
+    /// last retransmission timeout.
+    pub sent_len: usize,
+    /// Sequence space (including a FIN) which has ever been sent, relative to
+    /// seq_num.
+    pub sent_max: usize,
+    pub send_buffer: ByteRing,
+    /// Received data, followed by any data received ahead of ack_num in the
+    /// unallocated space past its end.
+    pub recv_buffer: ByteRing,
+    /// Ranges of data received ahead of ack_num, relative to ack_num.
+    pub out_of_order: Assembler,
+    /// Receive window advertised by the remote endpoint.
+    pub send_window: u16,
+    pub max_segment_size: usize,
+    /// Runs while data or a FIN is unacknowledged and expires when the oldest
+    /// of it needs retransmitting.
+    pub retransmit_timer: Timer,
+    pub retransmit_timeout: Duration,
+    /// Number of retransmission timeouts since the remote endpoint last
+    /// acknowledged anything.
+    pub retries: usize,
+    /// End of the segment being timed for an RTT sample and when it was sent.
+    pub rtt_timed: Option<(u32, Instant)>,
+    pub smoothed_rtt: Option<Duration>,
+    pub rtt_variance: Duration,
+    pub congestion_window: usize,
+    pub slow_start_threshold: usize,
+    /// Number of consecutive duplicate ACKs received.
+    pub duplicate_acks: usize,
+    /// Sequence number which ends fast recovery once acknowledged, if the
+    /// connection is recovering from a loss, see RFC 6582.
+    pub recovery_point: Option<u32>,
+    /// Indicates the oldest unacknowledged segment should be resent before
+    /// anything else.
+    pub retransmit_front: bool,
+    /// Indicates a FIN should be sent once the send buffer is drained.
+    pub fin_queued: bool,
+    pub fin_sent: bool,
+    pub fin_acked: bool,
+    pub fin_received: bool,
+    /// End of urgent data in the send buffer, relative to seq_num.
+    pub send_urgent_end: Option<usize>,
+    /// Bytes in the receive buffer in front of the end of urgent data.
+    pub recv_urgent_mark: Option<usize>,
+    /// Indicates received data should be discarded rather than buffered.
+    pub recv_shutdown: bool,
+    pub stats: TcpStats,
+    pub context: TcpContext,
+}
+
+impl TcpConnection {
+    /// Creates a connection once a handshake has completed.
+    pub fn new(
+        connected_to: SocketAddr,
+        seq_num: u32,
+        ack_num: u32,
+        send_window: u16,
+        remote_mss: Option<u16>,
+        stats: TcpStats,
+        context: TcpContext,
+    ) -> TcpConnection {
+        let local_mss = context.max_segment_size();
+        let remote_mss = remote_mss.map(|mss| mss as usize).unwrap_or(DEFAULT_MSS);
+        let max_segment_size = min(local_mss, remote_mss);
+
+        // Initial window from RFC 5681.
+        let congestion_window = if max_segment_size > 2190 {
+            2 * max_segment_size
+        } else if max_segment_size > 1095 {
+            3 * max_segment_size
+        } else {
+            4 * max_segment_size
+        };
+
+        let (send_buffer, recv_buffer) = context.take_buffers();
+        let out_of_order = Assembler::new(recv_buffer.capacity());
+
+        TcpConnection {
+            connected_to,
+            seq_num,
+            ack_num,
+            ack_sent: false,
+            sent_len: 0,
+            sent_max: 0,
+            send_buffer,
+            recv_buffer,
+            out_of_order,
+            send_window,
+            max_segment_size,
+            retransmit_timer: Timer::new(),
+            retransmit_timeout: INITIAL_RTO,
+            retries: 0,
+            rtt_timed: None,
+            smoothed_rtt: None,
+            rtt_variance: Duration::from_secs(0),
+            congestion_window,
+            slow_start_threshold: usize::MAX,
+            duplicate_acks: 0,
+            recovery_point: None,
+            retransmit_front: false,
+            fin_queued: false,
+            fin_sent: false,
+            fin_acked: false,
+            fin_received: false,
+            send_urgent_end: None,
+            recv_urgent_mark: None,
+            recv_shutdown: false,
+            stats,
+            context,
+        }
+    }
+
+    /// Returns statistics for the connection.
+    pub fn stats(&self) -> TcpStats {
+        TcpStats {
+            rtt_estimate: self.smoothed_rtt,
+            congestion_window: self.congestion_window,
+            ..self.stats
+        }
+    }
+
+    /// Moves the connection out of a state which is being transitioned from.
+    /// The connection left behind has empty buffers.
+    pub fn take(&mut self) -> TcpConnection {
+        TcpConnection {
+            send_buffer: mem::take(&mut self.send_buffer),
+            recv_buffer: mem::take(&mut self.recv_buffer),
+            out_of_order: mem::replace(&mut self.out_of_order, Assembler::new(0)),
+            context: self.context.clone(),
+            ..*self
+        }
+    }
+
+    /// Checks if the connection accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        (&self.connected_to == src_addr) && (self.context.binding.as_ref() == dst_addr)
+    }
+
+    /// Transitions to CLOSED, e.g. in response to a RST or completing a close.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        TcpClosed {
+            stats: self.stats(),
+            context: self.context.clone(),
+        }
+    }
+
+    /// Checks if data or a FIN has been retransmitted MAX_RETRIES times
+    /// without being acknowledged and the connection should be given up on.
+    pub fn is_timed_out(&self) -> bool {
+        self.retries >= MAX_RETRIES && self.retransmit_timer.is_expired(&*self.context.time_env)
+    }
+
+    /// Returns a RST which aborts the connection, see RFC 793.
+    pub fn reset(&self) -> (Ipv4Repr, TcpRepr) {
+        let mut tcp_repr = TcpRepr {
+            src_port: self.context.binding.port,
+            dst_port: self.connected_to.port,
+            seq_num: self.seq_num.wrapping_add(self.sent_max as u32),
+            ack_num: 0,
+            flags: [false; 9],
+            window_size: 0,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+        tcp_repr.flags[TcpRepr::FLAG_RST] = true;
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: self.context.binding.addr,
+            dst_addr: self.connected_to.addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: tcp_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        (ipv4_repr, tcp_repr)
+    }
+
+    /// Returns the number of bytes which can be enqueued for sending.
+    pub fn send_capacity(&self) -> usize {
+        self.send_buffer.window()
+    }
+
+    /// Enqueues as much of the buffer as possible for sending and returns the
+    /// number of bytes enqueued.
+    pub fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        let capacity = self.send_capacity();
+        if capacity == 0 && !buffer.is_empty() {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        let send_len = min(capacity, buffer.len());
+        self.send_buffer.enqueue_slice(&buffer[.. send_len]);
+        Ok(send_len)
+    }
+
+    /// Enqueues as much of the buffer as possible for sending as urgent data
+    /// and returns the number of bytes enqueued.
+    ///
+    /// The urgent pointer of segments we send points past the last byte
+    /// enqueued until it has been sent.
+    pub fn send_urgent(&mut self, buffer: &[u8]) -> Result<usize> {
+        let send_len = self.send(buffer)?;
+        if send_len > 0 {
+            self.send_urgent_end = Some(self.send_buffer.len());
+        }
+        Ok(send_len)
+    }
+
+    /// Dequeues received data into the buffer and returns the number of bytes
+    /// dequeued.
+    ///
+    /// Data is never dequeued across the urgent mark, so a call which dequeues
+    /// urgent_mark() bytes ends with the last byte of urgent data.
+    ///
+    /// Returns 0 once the remote endpoint has closed the connection (or the
+    /// receiving half was shut down) and all buffered data has been dequeued.
+    pub fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if self.recv_buffer.is_empty() {
+            return if self.fin_received || self.recv_shutdown {
+                Ok(0)
+            } else {
+                Err(Error::Buffer(BufferError::Empty))
+            };
+        }
+
+        // Let the remote endpoint know we have space again if the window was
+        // closed.
+        if self.recv_window() == 0 {
+            self.ack_sent = false;
+        }
+
+        let recv_len = match self.recv_urgent_mark {
+            Some(mark) => min(min(buffer.len(), self.recv_buffer.len()), mark),
+            None => min(buffer.len(), self.recv_buffer.len()),
+        };
+        self.recv_buffer.dequeue_slice(&mut buffer[.. recv_len]);
+
+        self.recv_urgent_mark = match self.recv_urgent_mark {
+            Some(mark) if mark > recv_len => Some(mark - recv_len),
+            _ => None,
+        };
+
+        Ok(recv_len)
+    }
+
+    /// Returns the number of buffered bytes in front of the end of urgent data
+    /// sent by the remote endpoint, if any.
+    pub fn urgent_mark(&self) -> Option<usize> {
+        self.recv_urgent_mark
+    }
+
+    /// Stops buffering received data. Any data received afterwards is
+    /// acknowledged and discarded.
+    pub fn shutdown_recv(&mut self) {
+        self.recv_shutdown = true;
+        self.recv_buffer.clear();
+        self.recv_urgent_mark = None;
+    }
+
+    /// Queues a FIN to be sent after any data in the send buffer.
+    pub fn shutdown_send(&mut self) {
+        self.fin_queued = true;
+    }
+
+    /// Shrinks the segment size after a router reports a smaller MTU on the
+    /// path to the remote endpoint. Anything in flight is resent immediately
+    /// since it was likely dropped.
+    pub fn update_path_mtu(&mut self, path_mtu: usize) {
+        if path_mtu < MIN_PATH_MTU {
+            return;
+        }
+
+        let header_len = Ipv4Packet::<&[u8]>::MIN_HEADER_LEN + TcpPacket::<&[u8]>::MIN_HEADER_LEN;
+        let max_segment_size = path_mtu - header_len;
+        if max_segment_size >= self.max_segment_size {
+            return;
+        }
+
+        debug!(
+            "TCP @ ({}, {}) reducing MSS from {} to {} for path MTU {}.",
+            self.context.binding,
+            self.connected_to,
+            self.max_segment_size,
+            max_segment_size,
+            path_mtu
+        );
+
+        self.max_segment_size = max_segment_size;
+        self.go_back();
+        self.retransmit_timer.stop();
+    }
+
+    /// Resends everything in flight, starting from the oldest unacknowledged
+    /// byte.
+    fn go_back(&mut self) {
+        self.sent_len = 0;
+        self.fin_sent = false;
+        self.retransmit_front = false;
+        // Karn's algorithm, ACKs for retransmitted segments are ambiguous.
+        self.rtt_timed = None;
+    }
+
+    /// Returns the number of bytes we can currently receive.
+    pub fn recv_window(&self) -> usize {
+        self.recv_buffer.window()
+    }
+
+    /// Returns when the connection next needs to send a segment, either now if
+    /// there is an ACK, data or a FIN to send, or once the retransmission
+    /// timer expires.
+    pub fn poll_at(&self) -> PollAt {
+        let send_window = min(max(self.send_window as usize, 1), self.congestion_window);
+        let unsent_len = self.send_buffer.len() - self.sent_len;
+        let can_send_data = unsent_len > 0 && self.sent_len < send_window;
+        let can_send_fin = self.fin_queued && !self.fin_sent && !self.fin_acked && unsent_len == 0;
+
+        if !self.ack_sent || self.retransmit_front || can_send_data || can_send_fin {
+            return PollAt::Now;
+        }
+
+        self.retransmit_timer.poll_at()
+    }
+
+    /// Dequeues a segment with data, a FIN, and/or an ACK for sending via
+    /// function f.
+    pub fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        let now = self.context.time_env.now_instant();
+
+        // Go back and resend everything in flight if the oldest segment has not
+        // been acknowledged in time.
+        if self.retransmit_timer.is_expired(&*self.context.time_env) {
+            debug!(
+                "TCP @ ({}, {}) retransmitting from SEQ_NUM {} after {:?}.",
+                self.context.binding, self.connected_to, self.seq_num, self.retransmit_timeout
+            );
+            self.go_back();
+            self.retransmit_timer.stop();
+            self.retransmit_timeout = min(self.retransmit_timeout * 2, MAX_RTO);
+            self.retries += 1;
+
+            // Collapse the congestion window, see RFC 5681.
+            self.slow_start_threshold = max(self.sent_max / 2, 2 * self.max_segment_size);
+            self.congestion_window = self.max_segment_size;
+            self.duplicate_acks = 0;
+            self.recovery_point = None;
+        }
+
+        if self.send_buffer.is_empty() && !self.fin_sent {
+            self.retransmit_front = false;
+        }
+
+        let (offset, mut payload_len, mut send_fin) = if self.retransmit_front {
+            // Fast retransmit of the segment the remote endpoint is missing, which
+            // is not limited by the congestion window.
+            let payload_len = min(self.send_buffer.len(), self.max_segment_size);
+            let send_fin = self.fin_sent && payload_len == self.send_buffer.len();
+            (0, payload_len, send_fin)
+        } else {
+            // A zero window still lets us probe with a single byte so we find out
+            // when the remote endpoint has space again.
+            let send_window = min(max(self.send_window as usize, 1), self.congestion_window);
+            let unsent_len = self.send_buffer.len() - self.sent_len;
+            let payload_len = min(
+                min(unsent_len, self.max_segment_size),
+                send_window.saturating_sub(self.sent_len),
+            );
+            let send_fin =
+                self.fin_queued && !self.fin_sent && !self.fin_acked && payload_len == unsent_len;
+            (self.sent_len, payload_len, send_fin)
+        };
+
+        if payload_len == 0 && !send_fin && self.ack_sent {
+            return Err(Error::Buffer(BufferError::Empty));
+        }
+
+        // Segments end where the send buffer wraps around, so the payload can
+        // be sent without copying it.
+        let payload = self.send_buffer.get_allocated(offset, payload_len);
+        if payload.len() < payload_len {
+            payload_len = payload.len();
+            send_fin = false;
+        }
+
+        let mut tcp_repr = TcpRepr {
+            src_port: self.context.binding.port,
+            dst_port: self.connected_to.port,
+            seq_num: self.seq_num.wrapping_add(offset as u32),
+            ack_num: self.ack_num,
+            flags: [false; 9],
+            window_size: min(self.recv_window(), 65535) as u16,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+
+        tcp_repr.flags[TcpRepr::FLAG_ACK] = true;
+        tcp_repr.flags[TcpRepr::FLAG_PSH] = payload_len > 0;
+        tcp_repr.flags[TcpRepr::FLAG_FIN] = send_fin;
+
+        // The urgent pointer is an offset from the sequence number of the segment
+        // to the byte following the urgent data, see RFC 6093.
+        if let Some(urgent_end) = self.send_urgent_end {
+            if urgent_end > offset {
+                tcp_repr.flags[TcpRepr::FLAG_URG] = true;
+                tcp_repr.urgent_pointer = min(urgent_end - offset, 65535) as u16;
+            }
+        }
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: self.context.binding.addr,
+            dst_addr: self.connected_to.addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: (tcp_repr.header_len() + payload_len) as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        match f(&ipv4_repr, &tcp_repr, payload) {
+            Ok(res) => {
+                debug!(
+                    "TCP @ ({}, {}) sent {} bytes from SEQ_NUM {} with ACK_NUM {}{}.",
+                    self.context.binding,
+                    self.connected_to,
+                    payload_len,
+                    tcp_repr.seq_num,
+                    self.ack_num,
+                    if send_fin { " and FIN" } else { "" }
+                );
+                let seq_len = payload_len + if send_fin { 1 } else { 0 };
+                let seq_end = offset + seq_len;
+                if seq_len > 0 && seq_end <= self.sent_max {
+                    self.stats.retransmits += 1;
+                } else if seq_len > 0 && self.rtt_timed.is_none() {
+                    self.rtt_timed = Some((self.seq_num.wrapping_add(seq_end as u32), now));
+                }
+
+                self.stats.segments_sent += 1;
+                self.sent_len = max(self.sent_len, offset + payload_len);
+                self.retransmit_front = false;
+                self.sent_max = max(self.sent_max, seq_end);
+                self.fin_sent |= send_fin;
+                self.ack_sent = true;
+                if seq_len > 0 && !self.retransmit_timer.is_running() {
+                    self.retransmit_timer
+                        .start(&*self.context.time_env, self.retransmit_timeout);
+                }
+                Ok(res)
+            }
+            Err(err) => {
+                debug!(
+                    "TCP @ ({}, {}) encountered {:?} when sending segment.",
+                    self.context.binding, self.connected_to, err
+                );
+                Err(err)
+            }
+        }
+    }
+
+    /// Enqueues a segment for receiving, processing any acknowledgement, data,
+    /// and FIN it carries.
+    ///
+    /// The caller should check fin_acked and fin_received afterwards to decide
+    /// on state transitions.
+    pub fn recv_enqueue(
+        &mut self,
+        _ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> Result<()> {
+        self.stats.segments_received += 1;
+
+        if tcp_repr.flags[TcpRepr::FLAG_ACK] {
+            self.recv_ack(tcp_repr, payload);
+        }
+
+        if tcp_repr.flags[TcpRepr::FLAG_URG] && !self.recv_shutdown {
+            self.recv_urgent(tcp_repr);
+        }
+
+        if payload.is_empty() && !tcp_repr.flags[TcpRepr::FLAG_FIN] {
+            return Ok(());
+        }
+
+        // Acknowledge anything with a sequence number so the remote endpoint learns
+        // what we have (or are missing) via duplicate ACKs.
+        self.ack_sent = false;
+
+        let offset = self.ack_num.wrapping_sub(tcp_repr.seq_num) as usize;
+        if offset > payload.len() {
+            return self.recv_out_of_order(tcp_repr, payload);
+        }
+
+        let payload = &payload[offset ..];
+        let recv_len = if self.recv_shutdown {
+            payload.len()
+        } else {
+            min(payload.len(), self.recv_window())
+        };
+
+        if !self.recv_shutdown {
+            self.recv_buffer.enqueue_slice(&payload[.. recv_len]);
+        }
+
+        self.ack_num = self.ack_num.wrapping_add(recv_len as u32);
+        self.out_of_order.advance(recv_len);
+
+        // The segment may have filled a gap in front of data received out of order.
+        let front_len = self.out_of_order.front_len();
+        if front_len > 0 {
+            debug!(
+                "TCP @ ({}, {}) reassembled {} bytes received out of order.",
+                self.context.binding, self.connected_to, front_len
+            );
+            if !self.recv_shutdown {
+                self.recv_buffer.enqueue_unallocated(front_len);
+            }
+            self.ack_num = self.ack_num.wrapping_add(front_len as u32);
+            self.out_of_order.advance(front_len);
+        }
+
+        if tcp_repr.flags[TcpRepr::FLAG_FIN]
+            && recv_len == payload.len()
+            && front_len == 0
+            && !self.fin_received
+        {
+            debug!(
+                "TCP @ ({}, {}) received FIN.",
+                self.context.binding, self.connected_to
+            );
+            self.fin_received = true;
+            self.ack_num = self.ack_num.wrapping_add(1);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the urgent mark forward if a segment points to urgent data beyond
+    /// the current mark.
+    fn recv_urgent(&mut self, tcp_repr: &TcpRepr) {
+        let urgent_end = tcp_repr
+            .seq_num
+            .wrapping_add(tcp_repr.urgent_pointer as u32);
+        // The urgent data may end in data which is still buffered.
+        let offset = urgent_end.wrapping_sub(self.ack_num) as i32 as isize;
+        let mark = self.recv_buffer.len() as isize + offset;
+        if mark <= 0 {
+            return;
+        }
+
+        let mark = mark as usize;
+        if Some(mark) > self.recv_urgent_mark {
+            debug!(
+                "TCP @ ({}, {}) received urgent data ending at SEQ_NUM {}.",
+                self.context.binding, self.connected_to, urgent_end
+            );
+            self.recv_urgent_mark = Some(mark);
+        }
+    }
+
+    /// Stores a segment which arrived ahead of ack_num until the gap in front
+    /// of it is filled.
+    ///
+    /// A FIN on such a segment is not remembered, the remote endpoint will
+    /// retransmit it once the gap is acknowledged.
+    fn recv_out_of_order(&mut self, tcp_repr: &TcpRepr, payload: &[u8]) -> Result<()> {
+        let offset = tcp_repr.seq_num.wrapping_sub(self.ack_num) as usize;
+        let window = self.recv_window();
+
+        if offset >= window {
+            debug!(
+                "TCP @ ({}, {}) ignoring segment with SEQ_NUM {} outside the receive window.",
+                self.context.binding, self.connected_to, tcp_repr.seq_num
+            );
+            return Err(Error::Ignored);
+        }
+
+        let payload = &payload[.. min(payload.len(), window - offset)];
+        debug!(
+            "TCP @ ({}, {}) buffering {} bytes with SEQ_NUM {}, expected {}.",
+            self.context.binding,
+            self.connected_to,
+            payload.len(),
+            tcp_repr.seq_num,
+            self.ack_num
+        );
+        // Store the data past the end of the receive buffer where it will sit once
+        // the gap is filled, so it is not copied again.
+        let payload_len = self.recv_buffer.write_unallocated(offset, payload);
+        self.out_of_order.add(offset, payload_len)
+    }
+
+    /// Processes the acknowledgement number and window of a segment.
+    fn recv_ack(&mut self, tcp_repr: &TcpRepr, payload: &[u8]) {
+        let acked = tcp_repr.ack_num.wrapping_sub(self.seq_num) as usize;
+
+        if acked > self.sent_max {
+            // Either an old duplicate or acknowledging something we never sent.
+            return;
+        }
+
+        // The remote endpoint is still responding, e.g. to zero window probes.
+        self.retries = 0;
+
+        if acked == 0 {
+            if self.sent_max > 0
+                && payload.is_empty()
+                && !tcp_repr.flags[TcpRepr::FLAG_FIN]
+                && tcp_repr.window_size == self.send_window
+            {
+                self.stats.duplicate_acks += 1;
+                self.recv_duplicate_ack();
+            }
+            self.send_window = tcp_repr.window_size;
+            return;
+        }
+
+        self.send_window = tcp_repr.window_size;
+
+        let now = self.context.time_env.now_instant();
+
+        if let Some((rtt_seq_num, sent_at)) = self.rtt_timed {
+            if rtt_seq_num.wrapping_sub(self.seq_num) as usize <= acked {
+                self.update_rtt(now - sent_at);
+                self.rtt_timed = None;
+            }
+        }
+
+        let acked_len = min(acked, self.send_buffer.len());
+        self.send_buffer.dequeue_allocated(acked_len);
+        self.sent_len = self.sent_len.saturating_sub(acked_len);
+        self.sent_max -= acked;
+        self.seq_num = self.seq_num.wrapping_add(acked as u32);
+        self.send_urgent_end = match self.send_urgent_end {
+            Some(urgent_end) if urgent_end > acked_len => Some(urgent_end - acked_len),
+            _ => None,
+        };
+
+        if acked > acked_len {
+            debug!(
+                "TCP @ ({}, {}) received ACK for FIN.",
+                self.context.binding, self.connected_to
+            );
+            self.fin_sent = false;
+            self.fin_acked = true;
+        }
+
+        self.duplicate_acks = 0;
+
+        if let Some(recovery_point) = self.recovery_point {
+            if recovery_point.wrapping_sub(self.seq_num) as i32 <= 0 {
+                // Everything in flight when the loss was detected has been
+                // acknowledged, deflate the window and leave fast recovery.
+                debug!(
+                    "TCP @ ({}, {}) leaving fast recovery.",
+                    self.context.binding, self.connected_to
+                );
+                self.congestion_window = self.slow_start_threshold;
+                self.recovery_point = None;
+            } else {
+                // A partial acknowledgement means the next segment was lost too,
+                // see RFC 6582.
+                self.congestion_window =
+                    self.congestion_window.saturating_sub(acked) + self.max_segment_size;
+                self.retransmit_front = true;
+                self.rtt_timed = None;
+            }
+        } else if self.congestion_window < self.slow_start_threshold {
+            // Slow start and congestion avoidance from RFC 5681.
+            self.congestion_window += min(acked, self.max_segment_size);
+        } else {
+            let increase = self.max_segment_size * self.max_segment_size / self.congestion_window;
+            self.congestion_window += max(increase, 1);
+        }
+
+        // Restart the retransmission timer for whatever is still in flight.
+        self.retransmit_timeout = self.rto();
+        if self.sent_max > 0 {
+            self.retransmit_timer
+                .start(&*self.context.time_env, self.retransmit_timeout);
+        } else {
+            self.retransmit_timer.stop();
+        }
+    }
+
+    /// Counts a duplicate ACK, performing a fast retransmit and entering fast
+    /// recovery on the third one, see RFC 5681.
+    fn recv_duplicate_ack(&mut self) {
+        self.duplicate_acks += 1;
+
+        if self.recovery_point.is_some() {
+            // Each duplicate ACK means a segment has left the network.
+            self.congestion_window += self.max_segment_size;
+        } else if self.duplicate_acks == 3 {
+            debug!(
+                "TCP @ ({}, {}) received 3 duplicate ACKs, fast retransmitting SEQ_NUM {}.",
+                self.context.binding, self.connected_to, self.seq_num
+            );
+            self.slow_start_threshold = max(self.sent_max / 2, 2 * self.max_segment_size);
+            self.congestion_window = self.slow_start_threshold + 3 * self.max_segment_size;
+            self.recovery_point = Some(self.seq_num.wrapping_add(self.sent_max as u32));
+            self.retransmit_front = true;
+            self.rtt_timed = None;
+            self.stats.fast_retransmits += 1;
+        }
+    }
+
+    /// Updates the smoothed RTT and its variance with a sample, see RFC 6298.
+    fn update_rtt(&mut self, rtt: Duration) {
+        match self.smoothed_rtt {
+            None => {
+                self.smoothed_rtt = Some(rtt);
+                self.rtt_variance = rtt / 2;
+            }
+            Some(smoothed_rtt) => {
+                let delta = smoothed_rtt.abs_diff(rtt);
+                self.rtt_variance = (self.rtt_variance * 3 + delta) / 4;
+                self.smoothed_rtt = Some((smoothed_rtt * 7 + rtt) / 8);
+            }
+        }
+    }
+
+    /// Returns the retransmission timeout based on the RTT estimate.
+    fn rto(&self) -> Duration {
+        match self.smoothed_rtt {
+            None => INITIAL_RTO,
+            Some(smoothed_rtt) => min(max(smoothed_rtt + self.rtt_variance * 4, MIN_RTO), MAX_RTO),
+        }
+    }
+}
+
+impl Drop for TcpConnection {
+    fn drop(&mut self) {
+        let send_buffer = mem::take(&mut self.send_buffer);
+        let recv_buffer = mem::take(&mut self.recv_buffer);
+        self.context.give_buffers(send_buffer, recv_buffer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    use stack::repr::Ipv4Address;
+    use stack::socket::{
+        Bindings,
+        SequentialIsnGenerator,
+    };
+    use stack::time::MockEnv;
+
+    use super::*;
+
+    fn connection(recv_buffer_len: usize) -> TcpConnection {
+        let local_addr = SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 1]),
+            port: 80,
+        };
+        let remote_addr = SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 2]),
+            port: 1024,
+        };
+        let context = TcpContext {
+            binding: Rc::new(Bindings::new().bind_tcp(local_addr).unwrap()),
+            interface_mtu: 1500,
+            send_buffer_len: 64,
+            recv_buffer_len,
+            spare_buffers: Rc::new(RefCell::new(Vec::new())),
+            time_env: Rc::new(MockEnv::new()),
+            isn_generator: Rc::new(SequentialIsnGenerator::new(0)),
+        };
+        TcpConnection::new(remote_addr, 0, 100, 64, None, TcpStats::default(), context)
+    }
+
+    fn recv_segment(connection: &mut TcpConnection, seq_num: u32, payload: &[u8]) {
+        let ipv4_repr = Ipv4Repr {
+            src_addr: connection.connected_to.addr,
+            dst_addr: connection.context.binding.addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: (TcpPacket::<&[u8]>::MIN_HEADER_LEN + payload.len()) as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::default(),
+        };
+        let tcp_repr = TcpRepr {
+            src_port: connection.connected_to.port,
+            dst_port: connection.context.binding.port,
+            seq_num,
+            ack_num: 0,
+            flags: [false; 9],
+            window_size: 64,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+        connection
+            .recv_enqueue(&ipv4_repr, &tcp_repr, payload)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_recv_out_of_order() {
+        let mut connection = connection(8);
+        recv_segment(&mut connection, 104, &[4, 5]);
+        recv_segment(&mut connection, 107, &[7, 8, 9]);
+        assert_eq!(connection.ack_num, 100);
+        assert_matches!(
+            connection.recv(&mut [0; 8]),
+            Err(Error::Buffer(BufferError::Empty))
+        );
+
+        recv_segment(&mut connection, 100, &[0, 1, 2, 3]);
+        assert_eq!(connection.ack_num, 106);
+        recv_segment(&mut connection, 106, &[6]);
+        assert_eq!(connection.ack_num, 108);
+
+        let mut buffer = [0; 8];
+        assert_eq!(connection.recv(&mut buffer).unwrap(), 8);
+        assert_eq!(buffer, [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+}
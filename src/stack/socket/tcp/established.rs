@@ -0,0 +1,90 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpCloseWait,
+    TcpClosed,
+    TcpConnection,
+    TcpFinWait1,
+    TcpState,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP ESTABLISHED state.
+#[derive(Debug)]
+pub struct TcpEstablished {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpEstablished {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "ESTABLISHED @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        let ok_or_err = self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        if self.connection.fin_received {
+            debug!(
+                "ESTABLISHED @ ({}, {}) received FIN, transition to CLOSE_WAIT.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::CloseWait(self.to_close_wait())), ok_or_err);
+        }
+
+        (None, ok_or_err)
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpEstablished {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from ESTABLISHED to CLOSED in response to a RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+
+    /// Transitions from ESTABLISHED to FIN_WAIT_1 when the sending half of the
+    /// connection is shut down.
+    pub fn to_fin_wait_1(&mut self) -> TcpFinWait1 {
+        self.connection.shutdown_send();
+        TcpFinWait1 {
+            connection: self.connection.take(),
+        }
+    }
+
+    /// Transitions from ESTABLISHED to CLOSE_WAIT in response to a FIN.
+    pub fn to_close_wait(&mut self) -> TcpCloseWait {
+        TcpCloseWait {
+            connection: self.connection.take(),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpClosing,
+    TcpConnection,
+    TcpFinWait2,
+    TcpState,
+    TcpTimeWait,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP FIN_WAIT_1 state.
+///
+/// The sending half of the connection has been shut down and our FIN has not
+/// been acknowledged yet.
+#[derive(Debug)]
+pub struct TcpFinWait1 {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpFinWait1 {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "FIN_WAIT_1 @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        let ok_or_err = self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        match (self.connection.fin_acked, self.connection.fin_received) {
+            (true, true) => {
+                debug!(
+                    "FIN_WAIT_1 @ ({}, {}) received FIN + ACK, transition to TIME_WAIT.",
+                    self.connection.context.binding, self.connection.connected_to
+                );
+                (Some(TcpState::TimeWait(self.to_time_wait())), ok_or_err)
+            }
+            (true, false) => {
+                debug!(
+                    "FIN_WAIT_1 @ ({}, {}) received ACK for FIN, transition to FIN_WAIT_2.",
+                    self.connection.context.binding, self.connection.connected_to
+                );
+                (Some(TcpState::FinWait2(self.to_fin_wait_2())), ok_or_err)
+            }
+            (false, true) => {
+                debug!(
+                    "FIN_WAIT_1 @ ({}, {}) received FIN, transition to CLOSING.",
+                    self.connection.context.binding, self.connection.connected_to
+                );
+                (Some(TcpState::Closing(self.to_closing())), ok_or_err)
+            }
+            (false, false) => (None, ok_or_err),
+        }
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpFinWait1 {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from FIN_WAIT_1 to CLOSED in response to a RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+
+    /// Transitions from FIN_WAIT_1 to FIN_WAIT_2 in response to an ACK for our
+    /// FIN.
+    pub fn to_fin_wait_2(&mut self) -> TcpFinWait2 {
+        TcpFinWait2 {
+            connection: self.connection.take(),
+        }
+    }
+
+    /// Transitions from FIN_WAIT_1 to CLOSING in response to a FIN.
+    pub fn to_closing(&mut self) -> TcpClosing {
+        TcpClosing {
+            connection: self.connection.take(),
+        }
+    }
+
+    /// Transitions from FIN_WAIT_1 to TIME_WAIT in response to a FIN which
+    /// also acknowledges our FIN.
+    pub fn to_time_wait(&mut self) -> TcpTimeWait {
+        TcpTimeWait::new(self.connection.take())
+    }
+}
@@ -0,0 +1,81 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpState,
+    TcpTimeWait,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP FIN_WAIT_2 state.
+///
+/// Our FIN has been acknowledged, but the remote endpoint may continue sending
+/// data until it sends a FIN.
+#[derive(Debug)]
+pub struct TcpFinWait2 {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpFinWait2 {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "FIN_WAIT_2 @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        let ok_or_err = self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        if self.connection.fin_received {
+            debug!(
+                "FIN_WAIT_2 @ ({}, {}) received FIN, transition to TIME_WAIT.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::TimeWait(self.to_time_wait())), ok_or_err);
+        }
+
+        (None, ok_or_err)
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpFinWait2 {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from FIN_WAIT_2 to CLOSED in response to a RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+
+    /// Transitions from FIN_WAIT_2 to TIME_WAIT in response to a FIN.
+    pub fn to_time_wait(&mut self) -> TcpTimeWait {
+        TcpTimeWait::new(self.connection.take())
+    }
+}
@@ -0,0 +1,144 @@
+use alloc::rc::Rc;
+use core::cell::Cell;
+use core::fmt::Debug;
+use core::hash::Hasher;
+
+use stack::hash::SipHasher;
+use stack::random::random;
+use stack::socket::SocketAddr;
+use stack::time::{
+    Env as TimeEnv,
+    Instant,
+};
+
+/// A source of initial sequence numbers for new TCP connections.
+pub trait IsnGenerator: Debug {
+    /// Returns an initial sequence number for a connection between the local
+    /// and remote endpoints.
+    fn gen_isn(&self, local_addr: &SocketAddr, remote_addr: &SocketAddr) -> u32;
+}
+
+/// An RFC 6528 initial sequence number generator.
+///
+/// Sequence numbers are computed as ISN = M + F(localip, localport, remoteip,
+/// remoteport, secretkey) where M is a timer ticking every 4 microseconds and
+/// F is a keyed hash of the connection 4-tuple. This keeps sequence numbers
+/// for a 4-tuple monotonic while making them hard to guess off path.
+#[derive(Debug)]
+pub struct HashedIsnGenerator {
+    secret_key: [u8; 16],
+    started_at: Instant,
+    time_env: Rc<dyn TimeEnv>,
+}
+
+impl HashedIsnGenerator {
+    /// Creates an ISN generator with a random secret key.
+    pub fn new(time_env: Rc<dyn TimeEnv>) -> HashedIsnGenerator {
+        HashedIsnGenerator::with_secret_key(time_env, random::<[u8; 16]>())
+    }
+
+    /// Creates an ISN generator with the specified secret key.
+    pub fn with_secret_key(time_env: Rc<dyn TimeEnv>, secret_key: [u8; 16]) -> HashedIsnGenerator {
+        HashedIsnGenerator {
+            secret_key,
+            started_at: time_env.now_instant(),
+            time_env,
+        }
+    }
+}
+
+impl IsnGenerator for HashedIsnGenerator {
+    fn gen_isn(&self, local_addr: &SocketAddr, remote_addr: &SocketAddr) -> u32 {
+        let mut hasher = SipHasher::with_key(&self.secret_key);
+        hasher.write(local_addr.addr.as_bytes());
+        hasher.write_u16(local_addr.port);
+        hasher.write(remote_addr.addr.as_bytes());
+        hasher.write_u16(remote_addr.port);
+        let hash = hasher.finish() as u32;
+
+        let elapsed = self.time_env.now_instant() - self.started_at;
+        let ticks = elapsed.as_secs() * 250_000 + (elapsed.subsec_nanos() / 4_000) as u64;
+
+        hash.wrapping_add(ticks as u32)
+    }
+}
+
+/// A deterministic initial sequence number generator which hands out
+/// sequence numbers from a counter, intended for testing.
+#[derive(Debug)]
+pub struct SequentialIsnGenerator {
+    next_isn: Cell<u32>,
+}
+
+impl SequentialIsnGenerator {
+    /// Creates an ISN generator starting at the specified sequence number.
+    pub fn new(first_isn: u32) -> SequentialIsnGenerator {
+        SequentialIsnGenerator {
+            next_isn: Cell::new(first_isn),
+        }
+    }
+}
+
+impl IsnGenerator for SequentialIsnGenerator {
+    fn gen_isn(&self, _local_addr: &SocketAddr, _remote_addr: &SocketAddr) -> u32 {
+        let isn = self.next_isn.get();
+        self.next_isn.set(isn.wrapping_add(1));
+        isn
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use stack::repr::Ipv4Address;
+    use stack::time::MockEnv;
+
+    use super::*;
+
+    fn socket_addr(port: u16) -> SocketAddr {
+        SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 1]),
+            port,
+        }
+    }
+
+    #[derive(Debug)]
+    struct SharedMockEnv(Rc<Cell<Instant>>);
+
+    impl TimeEnv for SharedMockEnv {
+        fn now_instant(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn test_hashed_isn_advances_with_time() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let isn_gen =
+            HashedIsnGenerator::with_secret_key(Rc::new(SharedMockEnv(now.clone())), [7; 16]);
+
+        let isn_1 = isn_gen.gen_isn(&socket_addr(1024), &socket_addr(80));
+        assert_eq!(isn_gen.gen_isn(&socket_addr(1024), &socket_addr(80)), isn_1);
+
+        now.set(now.get() + Duration::from_millis(1));
+        let isn_2 = isn_gen.gen_isn(&socket_addr(1024), &socket_addr(80));
+        assert_eq!(isn_2.wrapping_sub(isn_1), 250);
+    }
+
+    #[test]
+    fn test_hashed_isn_depends_on_secret_key() {
+        let isn_1 = HashedIsnGenerator::with_secret_key(Rc::new(MockEnv::new()), [1; 16])
+            .gen_isn(&socket_addr(1024), &socket_addr(80));
+        let isn_2 = HashedIsnGenerator::with_secret_key(Rc::new(MockEnv::new()), [2; 16])
+            .gen_isn(&socket_addr(1024), &socket_addr(80));
+        assert_ne!(isn_1, isn_2);
+    }
+
+    #[test]
+    fn test_sequential_isn() {
+        let isn_gen = SequentialIsnGenerator::new(u32::MAX);
+        assert_eq!(isn_gen.gen_isn(&socket_addr(1), &socket_addr(2)), u32::MAX);
+        assert_eq!(isn_gen.gen_isn(&socket_addr(1), &socket_addr(2)), 0);
+    }
+}
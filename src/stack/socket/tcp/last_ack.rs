@@ -0,0 +1,75 @@
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpState,
+};
+use stack::time::PollAt;
+use Result;
+
+/// The TCP LAST_ACK state.
+///
+/// Both endpoints have finished sending and we are waiting for our FIN to be
+/// acknowledged.
+#[derive(Debug)]
+pub struct TcpLastAck {
+    pub connection: TcpConnection,
+}
+
+impl Tcp for TcpLastAck {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "LAST_ACK @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        let ok_or_err = self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        if self.connection.fin_acked {
+            debug!(
+                "LAST_ACK @ ({}, {}) received ACK for FIN, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), ok_or_err);
+        }
+
+        (None, ok_or_err)
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at()
+    }
+}
+
+impl TcpLastAck {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Transitions from LAST_ACK to CLOSED once our FIN is acknowledged.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+}
@@ -0,0 +1,392 @@
+use alloc::collections::VecDeque;
+use core::time::Duration;
+
+use stack::repr::{
+    ipv4_flags,
+    Ipv4Options,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpContext,
+    TcpListenerStats,
+    TcpState,
+    TcpStats,
+    TcpSynRecv,
+};
+use stack::time::{
+    PollAt,
+    Timer,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// What a listener does with connections it has no space for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TcpOverflowPolicy {
+    /// Silently drop the SYN (or the ACK completing the handshake) so the
+    /// remote endpoint retries later.
+    Drop,
+    /// Drop the connection and send a RST so the remote endpoint fails fast.
+    Reset,
+}
+
+/// The TCP LISTENING state.
+#[derive(Debug)]
+pub struct TcpListen {
+    pub syn_queue: VecDeque<TcpSynRecv>,
+    pub syn_queue_len: usize,
+    /// Connections which have been established but not yet accepted. These may
+    /// have moved on to CLOSE_WAIT if the remote endpoint closed early.
+    pub est_queue: VecDeque<TcpState>,
+    pub est_queue_len: usize,
+    pub overflow_policy: TcpOverflowPolicy,
+    /// RSTs waiting to be sent to connections which were dropped.
+    pub reset_queue: VecDeque<(Ipv4Repr, TcpRepr)>,
+    pub stats: TcpListenerStats,
+    pub context: TcpContext,
+}
+
+impl Tcp for TcpListen {
+    #[allow(unused_must_use)]
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        while let Some(&(ipv4_repr, tcp_repr)) = self.reset_queue.front() {
+            if f(&ipv4_repr, &tcp_repr, &[]).is_err() {
+                break;
+            }
+            self.reset_queue.pop_front();
+            self.stats.resets_sent += 1;
+        }
+
+        // Forget handshakes and connections which have run out of retries, e.g.
+        // SYNs from spoofed addresses which never complete a handshake.
+        let binding = &self.context.binding;
+        self.syn_queue.retain(|syn_recv| {
+            let timed_out = syn_recv.is_timed_out();
+            if timed_out {
+                debug!(
+                    "SYN_RECV @ ({}, {}) timed out, dropping.",
+                    binding, syn_recv.connecting_to
+                );
+            }
+            !timed_out
+        });
+        self.est_queue.retain(|est| {
+            let timed_out = est.connection().is_some_and(|connection| connection.is_timed_out());
+            if timed_out {
+                debug!(
+                    "{} @ ({}, {}) timed out, dropping.",
+                    est.as_str(),
+                    binding,
+                    est.connection().unwrap().connected_to
+                );
+            }
+            !timed_out
+        });
+
+        // Any enqueued states may have something to send as well.
+        for syn_recv in self.syn_queue.iter_mut() {
+            syn_recv.send_dequeue(f);
+        }
+
+        for est in self.est_queue.iter_mut() {
+            est.send_dequeue(f);
+        }
+
+        Err(Error::Buffer(BufferError::Empty))
+    }
+
+    fn poll_at(&self) -> PollAt {
+        if !self.reset_queue.is_empty() {
+            return PollAt::Now;
+        }
+
+        let syn_queue = self.syn_queue.iter().map(|syn_recv| syn_recv.poll_at());
+        let est_queue = self.est_queue.iter().map(|est| est.poll_at());
+        syn_queue.chain(est_queue).min().unwrap_or(PollAt::Ingress)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        // Check if the packet is destined for this socket (or any children).
+        if ipv4_repr.dst_addr != self.context.binding.addr
+            || tcp_repr.dst_port != self.context.binding.port
+        {
+            return (None, Err(Error::Ignored));
+        }
+
+        // Forward the packet to any establishing/established connections first.
+        if self.recv_enqueue_syn(ipv4_repr, tcp_repr, payload) {
+            debug!(
+                "LISTEN @ {} ignoring packet, accepted by SYN_RECV socket.",
+                self.context.binding
+            );
+            return (None, Ok(()));
+        }
+
+        if self.recv_enqueue_est(ipv4_repr, tcp_repr, payload) {
+            debug!(
+                "LISTEN @ {} ignoring packet, accepted by ESTABLISHED socket.",
+                self.context.binding
+            );
+            return (None, Ok(()));
+        }
+
+        // See if we can establish a new connection. None of the existing sockets want
+        // to accept the packet so this is our only option left.
+        if !tcp_repr.flags[TcpRepr::FLAG_SYN] || tcp_repr.flags[TcpRepr::FLAG_ACK]
+            || tcp_repr.flags[TcpRepr::FLAG_RST]
+        {
+            // Check if the packet is a valid SYN.
+            return (None, Err(Error::Ignored));
+        }
+
+        if self.syn_queue.len() >= self.syn_queue_len {
+            // Check if we have space on our SYN queue.
+            debug!(
+                "LISTEN @ {} dropping SYN, no capacity in SYN queue.",
+                self.context.binding
+            );
+            self.overflow(ipv4_repr, tcp_repr);
+            return (None, Err(Error::Buffer(BufferError::Full)));
+        }
+
+        let connecting_to = SocketAddr {
+            addr: ipv4_repr.src_addr,
+            port: tcp_repr.src_port,
+        };
+        let syn_recv = self.to_syn_recv(connecting_to, tcp_repr.seq_num, tcp_repr.max_segment_size);
+        debug!(
+            "LISTEN @ {} enqueueing SYN_RECV socket with connection to {}.",
+            self.context.binding, connecting_to
+        );
+        self.syn_queue.push_back(syn_recv);
+        (None, Ok(()))
+    }
+}
+
+impl TcpListen {
+    /// Dequeues an established connection and the address of the remote
+    /// endpoint if one exists.
+    pub fn accept(&mut self) -> Option<(TcpState, SocketAddr)> {
+        let stats = &mut self.stats;
+        self.est_queue.pop_front().map(|tcp| {
+            stats.accepted += 1;
+            let connected_to = tcp.connection().unwrap().connected_to;
+            (tcp, connected_to)
+        })
+    }
+
+    /// Handles a packet for a connection which does not fit in a queue
+    /// according to the overflow policy.
+    fn overflow(&mut self, ipv4_repr: &Ipv4Repr, tcp_repr: &TcpRepr) {
+        self.stats.dropped += 1;
+
+        if self.overflow_policy == TcpOverflowPolicy::Drop {
+            return;
+        } else if self.reset_queue.len() >= self.syn_queue_len {
+            debug!(
+                "LISTEN @ {} not sending RST to {}:{}, too many RSTs pending.",
+                self.context.binding, ipv4_repr.src_addr, tcp_repr.src_port
+            );
+            return;
+        }
+
+        // A RST must be acceptable to the remote endpoint, see RFC 793.
+        let mut rst_repr = TcpRepr {
+            src_port: tcp_repr.dst_port,
+            dst_port: tcp_repr.src_port,
+            seq_num: 0,
+            ack_num: 0,
+            flags: [false; 9],
+            window_size: 0,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+        rst_repr.flags[TcpRepr::FLAG_RST] = true;
+        if tcp_repr.flags[TcpRepr::FLAG_ACK] {
+            rst_repr.seq_num = tcp_repr.ack_num;
+        } else {
+            rst_repr.ack_num = tcp_repr.seq_num.wrapping_add(1);
+            rst_repr.flags[TcpRepr::FLAG_ACK] = true;
+        }
+
+        let rst_ipv4_repr = Ipv4Repr {
+            src_addr: ipv4_repr.dst_addr,
+            dst_addr: ipv4_repr.src_addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: rst_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        debug!(
+            "LISTEN @ {} queueing RST to {}:{}.",
+            self.context.binding, ipv4_repr.src_addr, tcp_repr.src_port
+        );
+        self.reset_queue.push_back((rst_ipv4_repr, rst_repr));
+    }
+
+    /// Forwards a packet to an ESTABLISHED state.
+    ///
+    /// Returns a boolean indicating if the packet was acceptable by any
+    /// sockets.
+    pub fn recv_enqueue_syn(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> bool {
+        let src_addr = SocketAddr {
+            addr: ipv4_repr.src_addr,
+            port: tcp_repr.src_port,
+        };
+        let dst_addr = SocketAddr {
+            addr: ipv4_repr.dst_addr,
+            port: tcp_repr.dst_port,
+        };
+
+        for i in 0 .. self.syn_queue.len() {
+            if !self.syn_queue[i].accepts(&src_addr, &dst_addr) {
+                continue;
+            }
+
+            match self.syn_queue[i].recv_enqueue(ipv4_repr, tcp_repr, payload) {
+                (None, Ok(())) => {
+                    // Not an interesting event, don't log. (Might flood the log as well)
+                }
+                (Some(tcp @ TcpState::Established(_)), _)
+                | (Some(tcp @ TcpState::CloseWait(_)), _) => {
+                    if self.est_queue.len() >= self.est_queue_len {
+                        warn!(
+                            "ESTABLISHED queue of LISTEN @ {} does not have \
+                             capacity for another connection.",
+                            self.context.binding
+                        );
+                        self.overflow(ipv4_repr, tcp_repr);
+                        if self.overflow_policy == TcpOverflowPolicy::Reset {
+                            self.syn_queue.remove(i);
+                        }
+                    } else {
+                        debug!(
+                            "Moving SYN_RECV @ ({}, {}) to ESTABLISHED.",
+                            self.syn_queue[i].context.binding, self.syn_queue[i].connecting_to
+                        );
+                        self.syn_queue.remove(i);
+                        self.est_queue.push_back(tcp);
+                    }
+                }
+                (Some(tcp), _) => {
+                    // TODO: Handle special transitions like closes which lead to timed waits.
+                    debug!(
+                        "SYN_RECV @ ({}, {}) is transitioning to {}, dropping.",
+                        self.syn_queue[i].context.binding,
+                        self.syn_queue[i].connecting_to,
+                        tcp.as_str()
+                    );
+                    self.syn_queue.remove(i);
+                }
+                (None, _) => {}
+            };
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Forwards a packet to a SYN_RECV state.
+    ///
+    /// Returns a boolean indicating if the packet was acceptable by any
+    /// sockets.
+    pub fn recv_enqueue_est(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> bool {
+        let src_addr = SocketAddr {
+            addr: ipv4_repr.src_addr,
+            port: tcp_repr.src_port,
+        };
+        let dst_addr = SocketAddr {
+            addr: ipv4_repr.dst_addr,
+            port: tcp_repr.dst_port,
+        };
+
+        for i in 0 .. self.est_queue.len() {
+            if !self.est_queue[i].accepts(&src_addr, &dst_addr) {
+                continue;
+            }
+
+            match self.est_queue[i].recv_enqueue(ipv4_repr, tcp_repr, payload) {
+                (None, Ok(())) => {
+                    // Not an interesting event, don't log. (Might flood the log as well)
+                }
+                (Some(TcpState::Closed(_)), _) => {
+                    debug!(
+                        "{} @ ({}, {}) is transitioning to CLOSED, dropping.",
+                        self.est_queue[i].as_str(),
+                        self.context.binding,
+                        src_addr
+                    );
+                    self.est_queue.remove(i);
+                }
+                (Some(tcp), _) => {
+                    debug!(
+                        "{} @ ({}, {}) is transitioning to {}.",
+                        self.est_queue[i].as_str(),
+                        self.context.binding,
+                        src_addr,
+                        tcp.as_str()
+                    );
+                    self.est_queue[i] = tcp;
+                }
+                (None, _) => {}
+            };
+
+            return true;
+        }
+
+        false
+    }
+
+    /// Transitions from LISTEN to SYN_RECV in order to establish a new
+    /// connection.
+    pub fn to_syn_recv(
+        &mut self,
+        connecting_to: SocketAddr,
+        remote_seq_num: u32,
+        max_segment_size: Option<u16>,
+    ) -> TcpSynRecv {
+        TcpSynRecv {
+            retransmit_timer: Timer::new(),
+            seq_num: self.context.gen_isn(&connecting_to),
+            ack_num: remote_seq_num.wrapping_add(1),
+            max_segment_size,
+            connecting_to,
+            retransmit_timeout: Duration::from_secs(1),
+            retries: 0,
+            stats: TcpStats {
+                segments_received: 1,
+                ..TcpStats::default()
+            },
+            context: self.context.clone(),
+        }
+    }
+}
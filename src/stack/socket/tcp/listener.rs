@@ -0,0 +1,175 @@
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::task::{
+    Context,
+    Poll,
+    Waker,
+};
+
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    IsnGenerator,
+    SocketAddr,
+    SocketAddrLease,
+    TaggedSocketAddr,
+    Tcp,
+    TcpContext,
+    TcpListen,
+    TcpListenerStats,
+    TcpOverflowPolicy,
+    TcpSocket,
+};
+use stack::time::{
+    Env as TimeEnv,
+    PollAt,
+};
+use Result;
+
+/// A TCP socket which listens for and establishes incoming connections.
+/// Established connections are dequeued as TCP sockets with accept().
+#[derive(Debug)]
+pub struct TcpListener {
+    inner: TcpListen,
+    accept_waker: Option<Waker>,
+}
+
+impl TcpListener {
+    /// Creates a new TCP listener with SYN and ESTABLISHED queues of the
+    /// specified lengths.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<T: 'static + TimeEnv>(
+        binding: SocketAddrLease,
+        interface_mtu: usize,
+        send_buffer_len: usize,
+        recv_buffer_len: usize,
+        time_env: T,
+        isn_generator: Rc<dyn IsnGenerator>,
+        syn_queue_len: usize,
+        est_queue_len: usize,
+    ) -> TcpListener {
+        let context = TcpContext {
+            binding: Rc::new(binding),
+            interface_mtu,
+            send_buffer_len,
+            recv_buffer_len,
+            spare_buffers: Rc::new(RefCell::new(Vec::new())),
+            time_env: Rc::new(time_env),
+            isn_generator,
+        };
+        let listen = TcpListen {
+            syn_queue: VecDeque::with_capacity(syn_queue_len),
+            syn_queue_len,
+            est_queue: VecDeque::with_capacity(est_queue_len),
+            est_queue_len,
+            overflow_policy: TcpOverflowPolicy::Drop,
+            reset_queue: VecDeque::new(),
+            stats: TcpListenerStats::default(),
+            context,
+        };
+        TcpListener {
+            inner: listen,
+            accept_waker: None,
+        }
+    }
+
+    /// Dequeues zero or more packets enqueued for sending via function f.
+    ///
+    /// One packet per establishing or established connection is dequeued
+    /// until f returns an error.
+    pub fn send_dequeue<F, R>(&mut self, mut f: F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        self.inner.send_dequeue(&mut f)
+    }
+
+    /// Enqueues a packet for receiving.
+    pub fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> Result<()> {
+        let (_, ok_or_err) = self.inner.recv_enqueue(ipv4_repr, tcp_repr, payload);
+
+        if !self.inner.est_queue.is_empty() {
+            if let Some(waker) = self.accept_waker.take() {
+                waker.wake();
+            }
+        }
+
+        ok_or_err
+    }
+
+    /// Updates the path MTU of any connection waiting to be accepted which
+    /// matches the (source, destination) addresses of a packet a router could
+    /// not forward without fragmentation.
+    pub fn update_path_mtu(
+        &mut self,
+        src_addr: &SocketAddr,
+        dst_addr: &SocketAddr,
+        path_mtu: usize,
+    ) {
+        for tcp in self.inner.est_queue.iter_mut() {
+            if let Some(connection) = tcp.connection_mut() {
+                if connection.accepts(dst_addr, src_addr) {
+                    connection.update_path_mtu(path_mtu);
+                }
+            }
+        }
+    }
+
+    /// Sets what happens to connections which arrive while the SYN or
+    /// ESTABLISHED queue is full. Connections are dropped silently by default.
+    pub fn set_overflow_policy(&mut self, overflow_policy: TcpOverflowPolicy) {
+        self.inner.overflow_policy = overflow_policy;
+    }
+
+    /// Returns the local address the listener is bound to.
+    pub fn binding(&self) -> &TaggedSocketAddr {
+        &self.inner.context.binding
+    }
+
+    /// Checks if the listener shares its binding with other listeners via
+    /// reuse_port, see SocketEnv::set_reuse_port(...).
+    pub fn is_reuse_port(&self) -> bool {
+        self.inner.context.binding.is_reuse_port()
+    }
+
+    /// Returns when the listener next needs servicing, e.g. to retransmit a
+    /// SYN + ACK or send a RST.
+    pub fn poll_at(&self) -> PollAt {
+        self.inner.poll_at()
+    }
+
+    /// Returns counters for the connections handled by the listener.
+    pub fn stats(&self) -> TcpListenerStats {
+        self.inner.stats
+    }
+
+    /// Dequeues an established connection if one has been established and
+    /// returns it along with the address of the remote endpoint.
+    pub fn accept(&mut self) -> Option<(TcpSocket, SocketAddr)> {
+        self.inner
+            .accept()
+            .map(|(tcp, socket_addr)| (TcpSocket::from(tcp), socket_addr))
+    }
+
+    /// Dequeues an established connection like accept(), but registers the
+    /// task of a context to be woken once a connection is established if
+    /// there is none.
+    pub fn poll_accept(&mut self, cx: &mut Context) -> Poll<(TcpSocket, SocketAddr)> {
+        match self.accept() {
+            Some(accepted) => Poll::Ready(accepted),
+            None => {
+                self.accept_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
@@ -0,0 +1,53 @@
+mod close_wait;
+mod closed;
+mod closing;
+mod connection;
+mod established;
+mod fin_wait_1;
+mod fin_wait_2;
+mod isn;
+mod last_ack;
+mod listen;
+mod listener;
+mod socket;
+mod state;
+mod stats;
+mod syn_recv;
+mod syn_sent;
+mod time_wait;
+
+pub use self::close_wait::TcpCloseWait;
+pub use self::closed::TcpClosed;
+pub use self::closing::TcpClosing;
+pub use self::connection::TcpConnection;
+pub use self::established::TcpEstablished;
+pub use self::fin_wait_1::TcpFinWait1;
+pub use self::fin_wait_2::TcpFinWait2;
+pub use self::isn::{
+    HashedIsnGenerator,
+    IsnGenerator,
+    SequentialIsnGenerator,
+};
+pub use self::last_ack::TcpLastAck;
+pub use self::listen::{
+    TcpListen,
+    TcpOverflowPolicy,
+};
+pub use self::listener::TcpListener;
+pub use self::socket::{
+    Shutdown,
+    TcpSocket,
+};
+pub use self::state::{
+    Tcp,
+    TcpContext,
+    TcpState,
+    TcpStateKind,
+};
+pub use self::stats::{
+    TcpListenerStats,
+    TcpStats,
+};
+pub use self::syn_recv::TcpSynRecv;
+pub use self::syn_sent::TcpSynSent;
+pub use self::time_wait::TcpTimeWait;
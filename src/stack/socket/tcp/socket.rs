@@ -0,0 +1,1149 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::task::{
+    Context,
+    Poll,
+    Waker,
+};
+use core::time::Duration;
+#[cfg(feature = "std")]
+pub use std::net::Shutdown;
+
+use stack::repr::{
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    IsnGenerator,
+    Observer,
+    SocketAddr,
+    SocketAddrLease,
+    SocketEvent,
+    TaggedSocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpContext,
+    TcpState,
+    TcpStateKind,
+    TcpStats,
+    WatermarkState,
+    Watermarks,
+};
+use stack::storage::ByteRing;
+use stack::time::{
+    Env as TimeEnv,
+    PollAt,
+};
+use {
+    AddressError,
+    Error,
+    RemoteError,
+    Result,
+    SocketError,
+};
+
+/// The halves of a connection to close via TcpSocket::shutdown(...), which is
+/// std::net::Shutdown with the std feature.
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shutdown {
+    Read,
+    Write,
+    Both,
+}
+
+/// Default time after which we give up establishing a connection.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(75);
+
+/// A TCP socket for reliable stream transfers. Sockets can be created by (1)
+/// opening client connections to a server or (2) dequeueing established
+/// connections from a TcpListener with accept().
+#[derive(Debug)]
+pub struct TcpSocket {
+    inner: TcpState,
+    error: Option<Error>,
+    icmp_error: Option<Icmpv4Repr>,
+    send_watermark: WatermarkState,
+    recv_watermark: WatermarkState,
+    send_waker: Option<Waker>,
+    recv_waker: Option<Waker>,
+    /// RST queued by abort() for the connection which was aborted.
+    reset: Option<(Ipv4Repr, TcpRepr)>,
+    observer: Observer,
+}
+
+impl From<TcpState> for TcpSocket {
+    fn from(tcp: TcpState) -> TcpSocket {
+        TcpSocket {
+            inner: tcp,
+            error: None,
+            icmp_error: None,
+            send_watermark: WatermarkState::new(),
+            recv_watermark: WatermarkState::new(),
+            send_waker: None,
+            recv_waker: None,
+            reset: None,
+            observer: Observer::new(),
+        }
+    }
+}
+
+impl TcpSocket {
+    /// Creates a new TCP socket.
+    pub fn new<T: 'static + TimeEnv>(
+        binding: SocketAddrLease,
+        interface_mtu: usize,
+        send_buffer_len: usize,
+        recv_buffer_len: usize,
+        time_env: T,
+        isn_generator: Rc<dyn IsnGenerator>,
+    ) -> TcpSocket {
+        let context = TcpContext {
+            binding: Rc::new(binding),
+            interface_mtu,
+            send_buffer_len,
+            recv_buffer_len,
+            spare_buffers: Rc::new(RefCell::new(Vec::new())),
+            time_env: Rc::new(time_env),
+            isn_generator,
+        };
+        let closed = TcpClosed {
+            stats: TcpStats::default(),
+            context,
+        };
+        TcpSocket {
+            inner: TcpState::Closed(closed),
+            error: None,
+            icmp_error: None,
+            send_watermark: WatermarkState::new(),
+            recv_watermark: WatermarkState::new(),
+            send_waker: None,
+            recv_waker: None,
+            reset: None,
+            observer: Observer::new(),
+        }
+    }
+
+    /// Creates a new TCP socket whose connections use send_buffer and
+    /// recv_buffer, e.g. backed by static storage, rather than allocating
+    /// buffers. The buffers are reused by later connections once a connection
+    /// closes.
+    pub fn with_buffers<T: 'static + TimeEnv>(
+        binding: SocketAddrLease,
+        interface_mtu: usize,
+        send_buffer: ByteRing,
+        recv_buffer: ByteRing,
+        time_env: T,
+        isn_generator: Rc<dyn IsnGenerator>,
+    ) -> TcpSocket {
+        let socket = TcpSocket::new(
+            binding,
+            interface_mtu,
+            send_buffer.capacity(),
+            recv_buffer.capacity(),
+            time_env,
+            isn_generator,
+        );
+        socket
+            .inner
+            .context()
+            .spare_buffers
+            .borrow_mut()
+            .push((send_buffer, recv_buffer));
+        socket
+    }
+
+    /// Dequeues a packet enqueued for sending via function f.
+    pub fn send_dequeue<F, R>(&mut self, mut f: F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        if let Some((ipv4_repr, tcp_repr)) = self.reset {
+            let res = f(&ipv4_repr, &tcp_repr, &[]);
+            if res.is_ok() {
+                self.reset = None;
+            }
+            return res;
+        }
+
+        // Timeouts are the only transitions which are not caused by receiving a
+        // packet, so check for them before sending.
+        let closed = match self.inner {
+            TcpState::SynSent(ref mut syn_sent) if syn_sent.is_timed_out() => {
+                debug!(
+                    "SYN_SENT @ ({}, {}) timed out, transition to CLOSED.",
+                    syn_sent.context.binding, syn_sent.connecting_to
+                );
+                self.error = Some(Error::Timeout);
+                Some(syn_sent.to_closed())
+            }
+            TcpState::SynRecv(ref mut syn_recv) if syn_recv.is_timed_out() => {
+                debug!(
+                    "SYN_RECV @ ({}, {}) timed out, transition to CLOSED.",
+                    syn_recv.context.binding, syn_recv.connecting_to
+                );
+                self.error = Some(Error::Timeout);
+                Some(syn_recv.to_closed())
+            }
+            TcpState::TimeWait(ref mut time_wait) if time_wait.is_expired() => {
+                Some(time_wait.to_closed())
+            }
+            ref mut tcp
+                if tcp
+                    .connection()
+                    .is_some_and(|connection| connection.is_timed_out()) =>
+            {
+                let connection = tcp.connection_mut().unwrap();
+                debug!(
+                    "TCP @ ({}, {}) retransmitted {} times without an ACK, transition to CLOSED.",
+                    connection.context.binding, connection.connected_to, connection.retries
+                );
+                self.error = Some(Error::Timeout);
+                Some(connection.to_closed())
+            }
+            _ => None,
+        };
+
+        if let Some(closed) = closed {
+            self.inner = TcpState::Closed(closed);
+            self.update_watermarks();
+            self.wake();
+            self.notify_closed();
+        }
+
+        self.inner.send_dequeue(&mut f)
+    }
+
+    /// Enqueues a packet for receiving.
+    pub fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> Result<()> {
+        let src_addr = SocketAddr {
+            addr: ipv4_repr.src_addr,
+            port: tcp_repr.src_port,
+        };
+        let dst_addr = SocketAddr {
+            addr: ipv4_repr.dst_addr,
+            port: tcp_repr.dst_port,
+        };
+
+        // Connections accepted from a listener share its binding, make sure we only
+        // process packets for our own connection.
+        if self.inner.connection().is_some() && !self.inner.accepts(&src_addr, &dst_addr) {
+            return Err(Error::Ignored);
+        }
+
+        let (was_connected, was_closed) = (self.is_connected(), self.is_closed());
+        let (recv_queued, fin_received) = (self.recv_queued(), self.fin_received());
+        let (tcp, ok_or_err) = self.inner.recv_enqueue(ipv4_repr, tcp_repr, payload);
+        if let Some(tcp) = tcp {
+            if let TcpState::Closed(_) = tcp {
+                if tcp_repr.flags[TcpRepr::FLAG_RST] {
+                    self.error = Some(Error::Socket(SocketError::ConnectionReset));
+                }
+            }
+            self.inner = tcp;
+        }
+        self.update_watermarks();
+
+        // Packets may carry data, acknowledge sent data or change the state of
+        // the connection, so let pending tasks check.
+        self.wake();
+
+        if !was_connected && self.is_connected() {
+            self.observer.notify(SocketEvent::Established);
+        }
+        if self.recv_queued() > recv_queued {
+            self.observer.notify(SocketEvent::Recv);
+        }
+        if !fin_received && self.fin_received() {
+            self.observer.notify(SocketEvent::PeerClosed);
+        }
+        if !was_closed && self.is_closed() {
+            self.notify_closed();
+        }
+
+        ok_or_err
+    }
+
+    /// Initiates a connection to a TCP endpoint.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the connection is not in the closed state!
+    pub fn connect(&mut self, socket_addr: SocketAddr) {
+        self.connect_with_timeout(socket_addr, DEFAULT_CONNECT_TIMEOUT)
+    }
+
+    /// Initiates a connection to a TCP endpoint. The socket is closed and
+    /// take_error() returns Error::Timeout if the connection is not
+    /// established within the timeout.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the connection is not in the closed state!
+    pub fn connect_with_timeout(&mut self, socket_addr: SocketAddr, timeout: Duration) {
+        self.error = None;
+        self.icmp_error = None;
+        self.inner = match self.inner {
+            TcpState::Closed(ref mut closed) => {
+                TcpState::SynSent(closed.to_syn_sent(socket_addr, timeout))
+            }
+            _ => panic!("TcpSocket::connect(...) requires a closed socket!"),
+        }
+    }
+
+    /// Registers the task of a context to be woken once a connection initiated
+    /// by connect(...) is established or fails, and returns the outcome once
+    /// it does.
+    pub fn poll_connect(&mut self, cx: &mut Context) -> Poll<Result<()>> {
+        if self.is_establishing() {
+            self.send_waker = Some(cx.waker().clone());
+            Poll::Pending
+        } else if self.is_connected() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Ready(Err(self
+                .take_error()
+                .unwrap_or(Error::Socket(SocketError::NotConnected))))
+        }
+    }
+
+    /// Enqueues data for sending like send(...), but registers the task of a
+    /// context to be woken once data can be enqueued rather than failing or
+    /// enqueueing nothing while the connection is being established or the
+    /// send buffer is full.
+    pub fn poll_send(&mut self, cx: &mut Context, buffer: &[u8]) -> Poll<Result<usize>> {
+        if self.icmp_error.is_none()
+            && (self.is_establishing() || (self.is_connected() && !self.can_send()))
+        {
+            self.send_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        match self.take_error() {
+            Some(err) => Poll::Ready(Err(err)),
+            None => Poll::Ready(self.send(buffer)),
+        }
+    }
+
+    /// Dequeues received data like recv(...), but registers the task of a
+    /// context to be woken once data is received rather than dequeueing
+    /// nothing while the connection is open.
+    pub fn poll_recv(&mut self, cx: &mut Context, buffer: &mut [u8]) -> Poll<Result<usize>> {
+        if self.icmp_error.is_none()
+            && !self.can_recv()
+            && (self.is_establishing() || self.inner.connection().is_some())
+        {
+            self.recv_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        match self.take_error() {
+            Some(err) => Poll::Ready(Err(err)),
+            None => Poll::Ready(self.recv(buffer)),
+        }
+    }
+
+    /// Wakes tasks waiting to send or receive.
+    fn wake(&mut self) {
+        if let Some(waker) = self.send_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.recv_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Sets a callback notified once the connection is established, closes or
+    /// is reset, whenever data is received and once the remote endpoint
+    /// closes its sending half, e.g. to wake a task or
+    /// schedule work rather than polling is_connected() or recv(...). The
+    /// callback runs while the socket is serviced, so it must not access the
+    /// socket itself.
+    pub fn set_observer<F>(&mut self, callback: F)
+    where
+        F: 'static + FnMut(SocketEvent),
+    {
+        self.observer.set(callback);
+    }
+
+    /// Removes the callback set by set_observer(...).
+    pub fn clear_observer(&mut self) {
+        self.observer.clear();
+    }
+
+    /// Checks if the remote endpoint of the connection has sent a FIN.
+    fn fin_received(&self) -> bool {
+        self.inner
+            .connection()
+            .is_some_and(|connection| connection.fin_received)
+    }
+
+    /// Notifies the observer that the socket closed, or was reset if it
+    /// closed because of a RST.
+    fn notify_closed(&mut self) {
+        let event = match self.error {
+            Some(Error::Socket(SocketError::ConnectionReset)) => SocketEvent::Reset,
+            _ => SocketEvent::Closed,
+        };
+        self.observer.notify(event);
+    }
+
+    /// Sets low and high watermarks, in bytes, on the send buffer. Once as
+    /// many bytes as the high watermark are enqueued, send_congested() is
+    /// true until the buffer drains to the low watermark as data is
+    /// acknowledged, so applications can pause sending before send() enqueues
+    /// nothing.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the low watermark exceeds the high watermark!
+    pub fn set_send_watermarks(&mut self, watermarks: Option<Watermarks>) {
+        let send_queued = self.send_queued();
+        self.send_watermark.set_watermarks(watermarks, send_queued);
+    }
+
+    /// Sets low and high watermarks, in bytes, on the receive buffer. Once as
+    /// many bytes as the high watermark are received, recv_congested() is
+    /// true until the buffer drains to the low watermark, so applications can
+    /// catch up before the receive window closes.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the low watermark exceeds the high watermark!
+    pub fn set_recv_watermarks(&mut self, watermarks: Option<Watermarks>) {
+        let recv_queued = self.recv_queued();
+        self.recv_watermark.set_watermarks(watermarks, recv_queued);
+    }
+
+    /// Checks if the send buffer filled to its high watermark and has not yet
+    /// drained to its low watermark.
+    pub fn send_congested(&self) -> bool {
+        self.send_watermark.is_congested()
+    }
+
+    /// Checks if the receive buffer filled to its high watermark and has not
+    /// yet drained to its low watermark.
+    pub fn recv_congested(&self) -> bool {
+        self.recv_watermark.is_congested()
+    }
+
+    /// Updates the watermark states after the send or receive buffer changed.
+    fn update_watermarks(&mut self) {
+        let (send_queued, recv_queued) = (self.send_queued(), self.recv_queued());
+        self.send_watermark.update(send_queued);
+        self.recv_watermark.update(recv_queued);
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn binding(&self) -> &TaggedSocketAddr {
+        &self.inner.context().binding
+    }
+
+    /// Returns the local address of the connection, which is the address the
+    /// socket is bound to.
+    pub fn local_addr(&self) -> SocketAddr {
+        **self.binding()
+    }
+
+    /// Returns and clears the error which caused the socket to close, if any.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+
+    /// Returns and clears the error which caused the socket to close, if it
+    /// is still closed.
+    fn take_closed_error(&mut self) -> Option<Error> {
+        if self.is_closed() {
+            self.error.take()
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the socket is closed. The socket may be closed for reasons
+    /// including an explicit close, timeout, reset, etc.
+    pub fn is_closed(&self) -> bool {
+        match self.inner {
+            TcpState::Closed(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if the socket is connecting to an endpoint.
+    pub fn is_establishing(&self) -> bool {
+        match self.inner {
+            TcpState::SynSent(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if the socket has connected to an endpoint and can send data.
+    pub fn is_connected(&self) -> bool {
+        match self.inner {
+            TcpState::Established(_) | TcpState::CloseWait(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the number of bytes which can be enqueued for sending, or 0 if
+    /// the socket can not send.
+    pub fn send_capacity(&self) -> usize {
+        match self.inner {
+            TcpState::Established(ref tcp) => tcp.connection.send_capacity(),
+            TcpState::CloseWait(ref tcp) => tcp.connection.send_capacity(),
+            _ => 0,
+        }
+    }
+
+    /// Checks if send() would enqueue data rather than fail.
+    pub fn can_send(&self) -> bool {
+        self.send_capacity() > 0
+    }
+
+    /// Returns the TCP state the socket is in.
+    pub fn state(&self) -> TcpStateKind {
+        self.inner.kind()
+    }
+
+    /// Returns the name of the TCP state the socket is in, e.g. ESTABLISHED.
+    pub fn state_name(&self) -> &'static str {
+        self.inner.as_str()
+    }
+
+    /// Returns the maximum segment size of the connection, i.e. the smaller
+    /// of the MSS of each endpoint reduced to fit the path MTU, or None if no
+    /// connection is synchronized.
+    pub fn mss(&self) -> Option<usize> {
+        self.inner
+            .connection()
+            .map(|connection| connection.max_segment_size)
+    }
+
+    /// Returns the smoothed round trip time of the current (or last)
+    /// connection, or None if no segments have been timed.
+    pub fn rtt_estimate(&self) -> Option<Duration> {
+        self.stats().rtt_estimate
+    }
+
+    /// Returns the address of the remote endpoint the socket is connected or
+    /// connecting to, if any.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self.inner {
+            TcpState::Closed(_) => None,
+            TcpState::SynSent(ref tcp) => Some(tcp.connecting_to),
+            TcpState::SynRecv(ref tcp) => Some(tcp.connecting_to),
+            _ => self
+                .inner
+                .connection()
+                .map(|connection| connection.connected_to),
+        }
+    }
+
+    /// Returns the number of bytes enqueued for sending which have not been
+    /// acknowledged yet.
+    pub fn send_queued(&self) -> usize {
+        self.inner
+            .connection()
+            .map(|connection| connection.send_buffer.len())
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of received bytes which can be dequeued.
+    pub fn recv_queued(&self) -> usize {
+        self.inner
+            .connection()
+            .map(|connection| connection.recv_buffer.len())
+            .unwrap_or(0)
+    }
+
+    /// Checks if recv() would dequeue data or indicate the end of the stream
+    /// rather than fail.
+    pub fn can_recv(&self) -> bool {
+        match self.inner.connection() {
+            Some(connection) => {
+                !connection.recv_buffer.is_empty()
+                    || connection.fin_received
+                    || connection.recv_shutdown
+            }
+            None => false,
+        }
+    }
+
+    /// Enqueues data for sending and returns the number of bytes enqueued.
+    ///
+    /// Fails with SocketError::NotConnected if the socket is not connected or
+    /// the sending half of the connection has been shut down, and with
+    /// RemoteError::Icmp if an ICMP error was received for the connection.
+    /// Once the socket closes because of an error, e.g. Error::Timeout or
+    /// SocketError::ConnectionReset, the next call fails with that error.
+    pub fn send(&mut self, buffer: &[u8]) -> Result<usize> {
+        if let Some(icmp_repr) = self.icmp_error.take() {
+            return Err(Error::Remote(RemoteError::Icmp(icmp_repr)));
+        }
+
+        if let Some(err) = self.take_closed_error() {
+            return Err(err);
+        }
+
+        let res = match self.inner {
+            TcpState::Established(ref mut tcp) => tcp.connection.send(buffer),
+            TcpState::CloseWait(ref mut tcp) => tcp.connection.send(buffer),
+            _ => Err(Error::Socket(SocketError::NotConnected)),
+        };
+        self.update_watermarks();
+        res
+    }
+
+    /// Enqueues data for sending as urgent data and returns the number of bytes
+    /// enqueued.
+    ///
+    /// Urgent data is delivered in line with the rest of the stream, but the
+    /// remote endpoint is notified of it ahead of time. Fails like send() if
+    /// an ICMP error was received for the connection, or once the socket
+    /// closes because of an error.
+    pub fn send_urgent(&mut self, buffer: &[u8]) -> Result<usize> {
+        if let Some(icmp_repr) = self.icmp_error.take() {
+            return Err(Error::Remote(RemoteError::Icmp(icmp_repr)));
+        }
+
+        if let Some(err) = self.take_closed_error() {
+            return Err(err);
+        }
+
+        let res = match self.inner {
+            TcpState::Established(ref mut tcp) => tcp.connection.send_urgent(buffer),
+            TcpState::CloseWait(ref mut tcp) => tcp.connection.send_urgent(buffer),
+            _ => Err(Error::Socket(SocketError::NotConnected)),
+        };
+        self.update_watermarks();
+        res
+    }
+
+    /// Dequeues received data and returns the number of bytes dequeued.
+    ///
+    /// Returns 0 once the remote endpoint has finished sending (or the
+    /// receiving half of the connection has been shut down) and all buffered
+    /// data has been dequeued. Fails with RemoteError::Icmp if an ICMP error
+    /// was received for the connection, and like send() once the socket
+    /// closes because of an error.
+    pub fn recv(&mut self, buffer: &mut [u8]) -> Result<usize> {
+        if let Some(icmp_repr) = self.icmp_error.take() {
+            return Err(Error::Remote(RemoteError::Icmp(icmp_repr)));
+        }
+
+        if let Some(err) = self.take_closed_error() {
+            return Err(err);
+        }
+
+        let res = match self.inner.connection_mut() {
+            Some(connection) => connection.recv(buffer),
+            None => Err(Error::Socket(SocketError::NotConnected)),
+        };
+        self.update_watermarks();
+        res
+    }
+
+    /// Returns the number of bytes which can be dequeued before reaching the
+    /// end of urgent data sent by the remote endpoint, if any.
+    ///
+    /// recv() does not dequeue data across the mark, so a call which dequeues
+    /// this many bytes ends with the last byte of urgent data.
+    pub fn urgent_mark(&self) -> Option<usize> {
+        self.inner
+            .connection()
+            .and_then(|connection| connection.urgent_mark())
+    }
+
+    /// Returns when the socket next needs servicing, e.g. to retransmit
+    /// unacknowledged data or time out.
+    pub fn poll_at(&self) -> PollAt {
+        if self.reset.is_some() {
+            return PollAt::Now;
+        }
+
+        self.inner.poll_at()
+    }
+
+    /// Returns statistics for the current (or last) connection.
+    pub fn stats(&self) -> TcpStats {
+        match self.inner {
+            TcpState::Closed(ref tcp) => tcp.stats,
+            TcpState::SynSent(ref tcp) => tcp.stats,
+            TcpState::SynRecv(ref tcp) => tcp.stats,
+            _ => self.inner.connection().unwrap().stats(),
+        }
+    }
+
+    /// Updates the path MTU of the connection if it matches the (source,
+    /// destination) addresses of a packet a router could not forward without
+    /// fragmentation.
+    pub fn update_path_mtu(
+        &mut self,
+        src_addr: &SocketAddr,
+        dst_addr: &SocketAddr,
+        path_mtu: usize,
+    ) {
+        if let Some(connection) = self.inner.connection_mut() {
+            if connection.accepts(dst_addr, src_addr) {
+                connection.update_path_mtu(path_mtu);
+            }
+        }
+    }
+
+    /// Handles an ICMP error received in response to a packet sent with the
+    /// specified (source, destination) addresses.
+    ///
+    /// Protocol and port unreachable errors abort a connection being
+    /// established, see RFC 1122 section 4.2.3.9. Other errors are soft and
+    /// returned by the next call to send() or recv().
+    pub fn recv_icmp_error(
+        &mut self,
+        src_addr: &SocketAddr,
+        dst_addr: &SocketAddr,
+        icmp_repr: &Icmpv4Repr,
+    ) -> Result<()> {
+        let closed = match self.inner {
+            TcpState::SynSent(ref mut syn_sent)
+                if *syn_sent.context.binding == *src_addr
+                    && syn_sent.connecting_to == *dst_addr =>
+            {
+                match icmp_repr.message {
+                    Icmpv4Message::DestinationUnreachable(
+                        Icmpv4DestinationUnreachable::ProtocolUnreachable,
+                    )
+                    | Icmpv4Message::DestinationUnreachable(
+                        Icmpv4DestinationUnreachable::PortUnreachable,
+                    ) => {
+                        debug!(
+                            "SYN_SENT @ ({}, {}) got {:?}, transition to CLOSED.",
+                            syn_sent.context.binding, syn_sent.connecting_to, icmp_repr
+                        );
+                        Some(syn_sent.to_closed())
+                    }
+                    _ => None,
+                }
+            }
+            TcpState::SynSent(_) => return Err(Error::Ignored),
+            _ if self.inner.accepts(dst_addr, src_addr) => None,
+            _ => return Err(Error::Ignored),
+        };
+
+        let is_closed = closed.is_some();
+        match closed {
+            Some(closed) => {
+                self.inner = TcpState::Closed(closed);
+                self.error = Some(Error::Remote(RemoteError::Icmp(*icmp_repr)));
+            }
+            None => self.icmp_error = Some(*icmp_repr),
+        }
+
+        self.wake();
+        if is_closed {
+            self.notify_closed();
+        }
+
+        Ok(())
+    }
+
+    /// Closes the socket if it is bound to an address which has been removed
+    /// from the interface, e.g. by Interface::set_ipv4_addr(...), since the
+    /// connection can no longer send or receive packets. The error is
+    /// returned by take_error().
+    pub fn addr_removed(&mut self, addr: Ipv4Address) {
+        if self.is_closed() || self.inner.context().binding.addr != addr {
+            return;
+        }
+
+        let closed = match self.inner {
+            TcpState::SynRecv(ref mut tcp) => tcp.to_closed(),
+            TcpState::SynSent(ref mut tcp) => tcp.to_closed(),
+            ref mut tcp => tcp.connection_mut().unwrap().to_closed(),
+        };
+
+        debug!(
+            "{} @ {} lost address {}, transition to CLOSED.",
+            self.inner.as_str(),
+            self.inner.context().binding,
+            addr
+        );
+
+        self.inner = TcpState::Closed(closed);
+        self.error = Some(Error::Address(AddressError::InvalidAddress(addr)));
+        self.wake();
+        self.notify_closed();
+    }
+
+    /// Aborts the connection, discarding any buffered data and closing the
+    /// socket immediately rather than waiting for the remote endpoint as
+    /// shutdown(...) does. A RST is sent to the remote endpoint with the next
+    /// send_dequeue(...), unless the connection was still being established
+    /// by connect(...) or had already closed, see RFC 793.
+    pub fn abort(&mut self) {
+        let (reset, closed) = match self.inner {
+            TcpState::Closed(_) => return,
+            TcpState::SynSent(ref mut tcp) => (None, tcp.to_closed()),
+            TcpState::SynRecv(ref mut tcp) => (Some(tcp.reset()), tcp.to_closed()),
+            TcpState::TimeWait(ref mut tcp) => (None, tcp.to_closed()),
+            ref mut tcp => {
+                let connection = tcp.connection_mut().unwrap();
+                (Some(connection.reset()), connection.to_closed())
+            }
+        };
+
+        debug!(
+            "{} @ {} aborted, transition to CLOSED.",
+            self.inner.as_str(),
+            self.inner.context().binding
+        );
+
+        self.inner = TcpState::Closed(closed);
+        self.reset = reset;
+        self.icmp_error = None;
+        self.update_watermarks();
+        self.wake();
+        self.notify_closed();
+    }
+
+    /// Shuts down the receiving and/or sending halves of the connection.
+    ///
+    /// Shutting down the sending half sends a FIN once all enqueued data has
+    /// been sent, while the remote endpoint may continue sending. Shutting
+    /// down the receiving half discards buffered and future data. Has no
+    /// effect on halves which are already shut down or sockets which are not
+    /// connected.
+    pub fn shutdown(&mut self, how: Shutdown) {
+        if how != Shutdown::Write {
+            if let Some(connection) = self.inner.connection_mut() {
+                connection.shutdown_recv();
+            }
+        }
+
+        self.update_watermarks();
+
+        if how == Shutdown::Read {
+            return;
+        }
+
+        let tcp = match self.inner {
+            TcpState::Established(ref mut tcp) => TcpState::FinWait1(tcp.to_fin_wait_1()),
+            TcpState::CloseWait(ref mut tcp) => TcpState::LastAck(tcp.to_last_ack()),
+            _ => return,
+        };
+
+        debug!(
+            "{} @ {} shutting down, transition to {}.",
+            self.inner.as_str(),
+            self.inner.connection().unwrap().connected_to,
+            tcp.as_str()
+        );
+
+        self.inner = tcp;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::{
+        Cell,
+        RefCell,
+    };
+
+    use stack::repr::{
+        Ipv4Options,
+        Ipv4Protocol,
+    };
+    use stack::socket::{
+        Bindings,
+        SequentialIsnGenerator,
+    };
+    use stack::time::{
+        Instant,
+        MockEnv,
+    };
+
+    use super::super::connection::{
+        MAX_RETRIES,
+        MAX_RTO,
+    };
+    use super::*;
+
+    #[derive(Debug)]
+    struct SharedMockEnv(Rc<Cell<Instant>>);
+
+    impl TimeEnv for SharedMockEnv {
+        fn now_instant(&self) -> Instant {
+            self.0.get()
+        }
+    }
+
+    fn socket_addr(host: u8, port: u16) -> SocketAddr {
+        SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, host]),
+            port,
+        }
+    }
+
+    fn socket(now: &Rc<Cell<Instant>>) -> TcpSocket {
+        let binding = Bindings::new().bind_tcp(socket_addr(1, 1024)).unwrap();
+        TcpSocket::new(
+            binding,
+            1500,
+            64,
+            64,
+            SharedMockEnv(now.clone()),
+            Rc::new(SequentialIsnGenerator::new(0)),
+        )
+    }
+
+    fn dequeue(socket: &mut TcpSocket) -> Option<TcpRepr> {
+        socket.send_dequeue(|_, tcp_repr, _| Ok(*tcp_repr)).ok()
+    }
+
+    fn enqueue(socket: &mut TcpSocket, seq_num: u32, flags: &[usize]) {
+        enqueue_ack(socket, seq_num, 1, flags)
+    }
+
+    fn enqueue_ack(socket: &mut TcpSocket, seq_num: u32, ack_num: u32, flags: &[usize]) {
+        let mut tcp_repr = TcpRepr {
+            src_port: 80,
+            dst_port: 1024,
+            seq_num,
+            ack_num,
+            flags: [false; 9],
+            window_size: 64,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+        for &flag in flags {
+            tcp_repr.flags[flag] = true;
+        }
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new([10, 0, 0, 2]),
+            dst_addr: Ipv4Address::new([10, 0, 0, 1]),
+            protocol: Ipv4Protocol::TCP,
+            payload_len: tcp_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        };
+        let _ = socket.recv_enqueue(&ipv4_repr, &tcp_repr, &[]);
+    }
+
+    fn established(now: &Rc<Cell<Instant>>) -> TcpSocket {
+        let mut socket = socket(now);
+        socket.connect_with_timeout(socket_addr(2, 80), Duration::from_secs(3600));
+        assert!(dequeue(&mut socket).unwrap().flags[TcpRepr::FLAG_SYN]);
+        enqueue(&mut socket, 1000, &[TcpRepr::FLAG_SYN, TcpRepr::FLAG_ACK]);
+        assert!(socket.is_connected());
+        socket
+    }
+
+    #[test]
+    fn test_connection_metadata() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = socket(&now);
+        assert_eq!(socket.state(), TcpStateKind::Closed);
+        assert_eq!(socket.local_addr(), socket_addr(1, 1024));
+        assert_eq!(socket.peer_addr(), None);
+        assert_eq!(socket.mss(), None);
+
+        socket.connect_with_timeout(socket_addr(2, 80), Duration::from_secs(3600));
+        assert_eq!(socket.state(), TcpStateKind::SynSent);
+        assert_eq!(socket.peer_addr(), Some(socket_addr(2, 80)));
+        assert!(dequeue(&mut socket).is_some());
+
+        enqueue(&mut socket, 1000, &[TcpRepr::FLAG_SYN, TcpRepr::FLAG_ACK]);
+        assert_eq!(socket.state(), TcpStateKind::Established);
+        assert_eq!(socket.state().to_string(), "ESTABLISHED");
+        assert_eq!(socket.mss(), Some(536));
+        assert_eq!(socket.rtt_estimate(), None);
+
+        assert_eq!(socket.send(b"data").unwrap(), 4);
+        assert!(dequeue(&mut socket).is_some());
+        now.set(now.get() + Duration::from_millis(20));
+        enqueue_ack(&mut socket, 1001, 5, &[TcpRepr::FLAG_ACK]);
+        assert_eq!(socket.rtt_estimate(), Some(Duration::from_millis(20)));
+
+        socket.abort();
+        assert_eq!(socket.state(), TcpStateKind::Closed);
+        assert_eq!(socket.mss(), None);
+        assert_eq!(socket.rtt_estimate(), Some(Duration::from_millis(20)));
+    }
+
+    #[test]
+    fn test_syn_sent_max_retries() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = socket(&now);
+        socket.connect_with_timeout(socket_addr(2, 80), Duration::from_secs(3600));
+
+        for _ in 0 ..= MAX_RETRIES {
+            assert!(dequeue(&mut socket).unwrap().flags[TcpRepr::FLAG_SYN]);
+            now.set(now.get() + MAX_RTO);
+        }
+
+        assert!(dequeue(&mut socket).is_none());
+        assert!(socket.is_closed());
+        assert_matches!(socket.send(&[0]), Err(Error::Timeout));
+        assert_matches!(
+            socket.send(&[0]),
+            Err(Error::Socket(SocketError::NotConnected))
+        );
+    }
+
+    #[test]
+    fn test_established_max_retries() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = established(&now);
+        assert_eq!(socket.send(b"data").unwrap(), 4);
+        assert_eq!(dequeue(&mut socket).unwrap().seq_num, 1);
+
+        for _ in 0 .. MAX_RETRIES {
+            now.set(now.get() + MAX_RTO);
+            assert_eq!(dequeue(&mut socket).unwrap().seq_num, 1);
+        }
+
+        now.set(now.get() + MAX_RTO);
+        assert!(dequeue(&mut socket).is_none());
+        assert!(socket.is_closed());
+        assert_matches!(socket.recv(&mut [0; 4]), Err(Error::Timeout));
+    }
+
+    #[test]
+    fn test_recv_rst() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = established(&now);
+        enqueue(&mut socket, 1001, &[TcpRepr::FLAG_RST]);
+        assert!(socket.is_closed());
+        assert_matches!(
+            socket.recv(&mut [0; 4]),
+            Err(Error::Socket(SocketError::ConnectionReset))
+        );
+    }
+
+    #[test]
+    fn test_send_urgent_after_rst() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = established(&now);
+        enqueue(&mut socket, 1001, &[TcpRepr::FLAG_RST]);
+        assert_matches!(
+            socket.send_urgent(b"!"),
+            Err(Error::Socket(SocketError::ConnectionReset))
+        );
+        assert_matches!(
+            socket.send_urgent(b"!"),
+            Err(Error::Socket(SocketError::NotConnected))
+        );
+    }
+
+    #[test]
+    fn test_send_urgent_icmp_error() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = established(&now);
+        let icmp_repr = Icmpv4Repr {
+            message: Icmpv4Message::DestinationUnreachable(
+                Icmpv4DestinationUnreachable::HostUnreachable,
+            ),
+            payload_len: 28,
+        };
+        assert_matches!(
+            socket.recv_icmp_error(&socket_addr(1, 1024), &socket_addr(2, 80), &icmp_repr),
+            Ok(())
+        );
+        assert!(socket.is_connected());
+
+        assert_matches!(
+            socket.send_urgent(b"!"),
+            Err(Error::Remote(RemoteError::Icmp(_)))
+        );
+        assert_eq!(socket.send_urgent(b"!").unwrap(), 1);
+    }
+
+    #[test]
+    fn test_observer() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut socket = socket(&now);
+        let observed = events.clone();
+        socket.set_observer(move |event| observed.borrow_mut().push(event));
+
+        socket.connect_with_timeout(socket_addr(2, 80), Duration::from_secs(3600));
+        assert!(dequeue(&mut socket).unwrap().flags[TcpRepr::FLAG_SYN]);
+        enqueue(&mut socket, 1000, &[TcpRepr::FLAG_SYN, TcpRepr::FLAG_ACK]);
+        enqueue(&mut socket, 1001, &[TcpRepr::FLAG_RST]);
+        assert_eq!(
+            *events.borrow(),
+            vec![SocketEvent::Established, SocketEvent::Reset]
+        );
+
+        let mut socket = established(&now);
+        let observed = events.clone();
+        socket.set_observer(move |event| observed.borrow_mut().push(event));
+        socket.abort();
+        socket.abort();
+        assert_eq!(events.borrow().last(), Some(&SocketEvent::Closed));
+        assert_eq!(events.borrow().len(), 3);
+    }
+
+    #[test]
+    fn test_abort() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let mut socket = established(&now);
+        assert_eq!(socket.send(b"data").unwrap(), 4);
+        assert!(dequeue(&mut socket).is_some());
+
+        socket.abort();
+        assert!(socket.is_closed());
+        assert_eq!(socket.send_queued(), 0);
+        assert_eq!(socket.poll_at(), PollAt::Now);
+
+        let rst = dequeue(&mut socket).unwrap();
+        assert!(rst.flags[TcpRepr::FLAG_RST]);
+        assert_eq!(rst.seq_num, 5);
+        assert!(dequeue(&mut socket).is_none());
+        assert!(socket.take_error().is_none());
+    }
+
+    #[test]
+    fn test_with_buffers() {
+        let now = Rc::new(Cell::new(MockEnv::new().now));
+        let send_buffer: &'static mut [u8] = Box::leak(Box::new([0; 4]));
+        let recv_buffer: &'static mut [u8] = Box::leak(Box::new([0; 8]));
+        let binding = Bindings::new().bind_tcp(socket_addr(1, 1024)).unwrap();
+        let mut socket = TcpSocket::with_buffers(
+            binding,
+            1500,
+            ByteRing::from(send_buffer),
+            ByteRing::from(recv_buffer),
+            SharedMockEnv(now.clone()),
+            Rc::new(SequentialIsnGenerator::new(0)),
+        );
+
+        // Each connection borrows the buffers, which are reused once it closes.
+        for _ in 0 .. 2 {
+            socket.connect_with_timeout(socket_addr(2, 80), Duration::from_secs(3600));
+            let syn = dequeue(&mut socket).unwrap();
+            assert_eq!(syn.window_size, 8);
+            let flags = [TcpRepr::FLAG_SYN, TcpRepr::FLAG_ACK];
+            enqueue_ack(&mut socket, 1000, syn.seq_num.wrapping_add(1), &flags);
+            assert!(socket.is_connected());
+            assert!(socket.inner.context().spare_buffers.borrow().is_empty());
+            assert_eq!(socket.send(b"urgent data").unwrap(), 4);
+
+            socket.abort();
+            assert!(socket.is_closed());
+            assert_eq!(socket.inner.context().spare_buffers.borrow().len(), 1);
+            assert!(dequeue(&mut socket).unwrap().flags[TcpRepr::FLAG_RST]);
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
+
+use stack::repr::{
+    EthernetFrame,
+    Ipv4Packet,
+    Ipv4Repr,
+    TcpPacket,
+    TcpRepr,
+};
+use stack::socket::{
+    IsnGenerator,
+    SocketAddr,
+    SocketAddrLease,
+    TcpCloseWait,
+    TcpClosed,
+    TcpClosing,
+    TcpConnection,
+    TcpEstablished,
+    TcpFinWait1,
+    TcpFinWait2,
+    TcpLastAck,
+    TcpSynRecv,
+    TcpSynSent,
+    TcpTimeWait,
+};
+use stack::storage::ByteRing;
+use stack::time::{
+    Env as TimeEnv,
+    PollAt,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// A generic interface for implementing TCP state behavior and transitions.
+pub trait Tcp {
+    /// Dequeues a packet enqueued for sending via function f.
+    ///
+    /// The packet is only dequeued if f does not return an error. States
+    /// transitions are not permitted when sending packets, but the current
+    /// state may be updated.
+    fn send_dequeue<F, R>(&mut self, _f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        Err(Error::Buffer(BufferError::Empty))
+    }
+
+    /// Enqueues a packet for receiving.
+    ///
+    /// In addition, the current TCP state is updated and/or the next TCP state
+    /// the socket should transition to is returned. In certain cases, the
+    /// transition suggestion may be ignored.
+    fn recv_enqueue(
+        &mut self,
+        _ipv4_repr: &Ipv4Repr,
+        _tcp_repr: &TcpRepr,
+        _payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        (None, Err(Error::Ignored))
+    }
+
+    /// Returns when the state next needs to send a packet, e.g. to retransmit
+    /// unacknowledged data, or time out.
+    fn poll_at(&self) -> PollAt {
+        PollAt::Ingress
+    }
+}
+
+/// The kinds of TCP states a socket can be in, without the state they hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TcpStateKind {
+    Closed,
+    SynRecv,
+    SynSent,
+    Established,
+    FinWait1,
+    FinWait2,
+    Closing,
+    TimeWait,
+    CloseWait,
+    LastAck,
+}
+
+impl TcpStateKind {
+    /// Returns a string label for the state, e.g. ESTABLISHED.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            TcpStateKind::Closed => "CLOSED",
+            TcpStateKind::SynRecv => "SYN_RECV",
+            TcpStateKind::SynSent => "SYN_SENT",
+            TcpStateKind::Established => "ESTABLISHED",
+            TcpStateKind::FinWait1 => "FIN_WAIT_1",
+            TcpStateKind::FinWait2 => "FIN_WAIT_2",
+            TcpStateKind::Closing => "CLOSING",
+            TcpStateKind::TimeWait => "TIME_WAIT",
+            TcpStateKind::CloseWait => "CLOSE_WAIT",
+            TcpStateKind::LastAck => "LAST_ACK",
+        }
+    }
+}
+
+impl fmt::Display for TcpStateKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One of several TCP states.
+#[derive(Debug)]
+pub enum TcpState {
+    Closed(TcpClosed),
+    SynRecv(TcpSynRecv),
+    SynSent(TcpSynSent),
+    Established(TcpEstablished),
+    FinWait1(TcpFinWait1),
+    FinWait2(TcpFinWait2),
+    Closing(TcpClosing),
+    TimeWait(TcpTimeWait),
+    CloseWait(TcpCloseWait),
+    LastAck(TcpLastAck),
+}
+
+impl Tcp for TcpState {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        match *self {
+            TcpState::Closed(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::SynRecv(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::SynSent(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::Established(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::FinWait1(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::FinWait2(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::Closing(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::TimeWait(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::CloseWait(ref mut tcp) => tcp.send_dequeue(f),
+            TcpState::LastAck(ref mut tcp) => tcp.send_dequeue(f),
+        }
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        match *self {
+            TcpState::Closed(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::SynRecv(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::SynSent(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::Established(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::FinWait1(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::FinWait2(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::Closing(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::TimeWait(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::CloseWait(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+            TcpState::LastAck(ref mut tcp) => tcp.recv_enqueue(ipv4_repr, tcp_repr, payload),
+        }
+    }
+
+    fn poll_at(&self) -> PollAt {
+        match *self {
+            TcpState::Closed(ref tcp) => tcp.poll_at(),
+            TcpState::SynRecv(ref tcp) => tcp.poll_at(),
+            TcpState::SynSent(ref tcp) => tcp.poll_at(),
+            TcpState::Established(ref tcp) => tcp.poll_at(),
+            TcpState::FinWait1(ref tcp) => tcp.poll_at(),
+            TcpState::FinWait2(ref tcp) => tcp.poll_at(),
+            TcpState::Closing(ref tcp) => tcp.poll_at(),
+            TcpState::TimeWait(ref tcp) => tcp.poll_at(),
+            TcpState::CloseWait(ref tcp) => tcp.poll_at(),
+            TcpState::LastAck(ref tcp) => tcp.poll_at(),
+        }
+    }
+}
+
+impl TcpState {
+    /// Returns the kind of the state.
+    pub fn kind(&self) -> TcpStateKind {
+        match *self {
+            TcpState::Closed(_) => TcpStateKind::Closed,
+            TcpState::SynRecv(_) => TcpStateKind::SynRecv,
+            TcpState::SynSent(_) => TcpStateKind::SynSent,
+            TcpState::Established(_) => TcpStateKind::Established,
+            TcpState::FinWait1(_) => TcpStateKind::FinWait1,
+            TcpState::FinWait2(_) => TcpStateKind::FinWait2,
+            TcpState::Closing(_) => TcpStateKind::Closing,
+            TcpState::TimeWait(_) => TcpStateKind::TimeWait,
+            TcpState::CloseWait(_) => TcpStateKind::CloseWait,
+            TcpState::LastAck(_) => TcpStateKind::LastAck,
+        }
+    }
+
+    /// Returns a string label for the state.
+    pub fn as_str(&self) -> &'static str {
+        self.kind().as_str()
+    }
+
+    /// Returns the synchronized connection if the state has one.
+    pub fn connection(&self) -> Option<&TcpConnection> {
+        match *self {
+            TcpState::Established(ref tcp) => Some(&tcp.connection),
+            TcpState::FinWait1(ref tcp) => Some(&tcp.connection),
+            TcpState::FinWait2(ref tcp) => Some(&tcp.connection),
+            TcpState::Closing(ref tcp) => Some(&tcp.connection),
+            TcpState::TimeWait(ref tcp) => Some(&tcp.connection),
+            TcpState::CloseWait(ref tcp) => Some(&tcp.connection),
+            TcpState::LastAck(ref tcp) => Some(&tcp.connection),
+            _ => None,
+        }
+    }
+
+    /// Returns the synchronized connection if the state has one.
+    pub fn connection_mut(&mut self) -> Option<&mut TcpConnection> {
+        match *self {
+            TcpState::Established(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::FinWait1(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::FinWait2(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::Closing(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::TimeWait(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::CloseWait(ref mut tcp) => Some(&mut tcp.connection),
+            TcpState::LastAck(ref mut tcp) => Some(&mut tcp.connection),
+            _ => None,
+        }
+    }
+
+    /// Returns the context shared across states.
+    pub fn context(&self) -> &TcpContext {
+        match *self {
+            TcpState::Closed(ref tcp) => &tcp.context,
+            TcpState::SynRecv(ref tcp) => &tcp.context,
+            TcpState::SynSent(ref tcp) => &tcp.context,
+            _ => &self.connection().unwrap().context,
+        }
+    }
+
+    /// Checks if the state is a synchronized connection which accepts packets
+    /// with particular (source, destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        match self.connection() {
+            Some(connection) => connection.accepts(src_addr, dst_addr),
+            None => false,
+        }
+    }
+}
+
+/// Shared information across TCP states.
+#[derive(Clone, Debug)]
+pub struct TcpContext {
+    // This is an Rc because we only release the binding once all sockets
+    // are dropped. A situation with many sockets sharing a binding occurs
+    // when a server accepts client connections.
+    pub binding: Rc<SocketAddrLease>,
+    pub interface_mtu: usize,
+    pub send_buffer_len: usize,
+    pub recv_buffer_len: usize,
+    /// Static send and receive buffers which are not in use by a connection.
+    pub spare_buffers: Rc<RefCell<Vec<(ByteRing, ByteRing)>>>,
+    pub time_env: Rc<TimeEnv>,
+    pub isn_generator: Rc<dyn IsnGenerator>,
+}
+
+impl TcpContext {
+    /// Returns the largest segment which can be received without
+    /// fragmentation on the local link.
+    pub fn max_segment_size(&self) -> usize {
+        let header_len = EthernetFrame::<&[u8]>::HEADER_LEN + Ipv4Packet::<&[u8]>::MIN_HEADER_LEN
+            + TcpPacket::<&[u8]>::MIN_HEADER_LEN;
+        self.interface_mtu.saturating_sub(header_len)
+    }
+
+    /// Returns an initial sequence number for a connection with the remote
+    /// endpoint.
+    pub fn gen_isn(&self, remote_addr: &SocketAddr) -> u32 {
+        self.isn_generator.gen_isn(&self.binding, remote_addr)
+    }
+
+    /// Returns send and receive buffers for a new connection, reusing spare
+    /// static buffers before allocating buffers of send_buffer_len and
+    /// recv_buffer_len bytes.
+    pub fn take_buffers(&self) -> (ByteRing, ByteRing) {
+        self.spare_buffers.borrow_mut().pop().unwrap_or_else(|| {
+            (
+                ByteRing::new(self.send_buffer_len),
+                ByteRing::new(self.recv_buffer_len),
+            )
+        })
+    }
+
+    /// Returns the buffers of a closed connection to the spare buffers if they
+    /// are static, or drops them otherwise.
+    pub fn give_buffers(&self, mut send_buffer: ByteRing, mut recv_buffer: ByteRing) {
+        if send_buffer.is_static() || recv_buffer.is_static() {
+            send_buffer.clear();
+            recv_buffer.clear();
+            self.spare_buffers
+                .borrow_mut()
+                .push((send_buffer, recv_buffer));
+        }
+    }
+}
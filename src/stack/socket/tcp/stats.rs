@@ -0,0 +1,34 @@
+use core::time::Duration;
+
+/// Counters and estimates describing the performance of a TCP connection.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpStats {
+    /// Number of segments sent, including retransmissions.
+    pub segments_sent: u64,
+    /// Number of segments received for the connection.
+    pub segments_received: u64,
+    /// Number of segments which were sent more than once.
+    pub retransmits: u64,
+    /// Number of ACKs received which did not acknowledge anything new while
+    /// data was in flight.
+    pub duplicate_acks: u64,
+    /// Number of times a segment was retransmitted after three duplicate ACKs
+    /// rather than a retransmission timeout.
+    pub fast_retransmits: u64,
+    /// Smoothed round trip time, if any segments have been timed.
+    pub rtt_estimate: Option<Duration>,
+    /// Current congestion window in bytes.
+    pub congestion_window: usize,
+}
+
+/// Counters describing the connections handled by a TCP listener.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpListenerStats {
+    /// Number of established connections dequeued with accept().
+    pub accepted: u64,
+    /// Number of SYNs or completed handshakes dropped because the SYN or
+    /// ESTABLISHED queue was full.
+    pub dropped: u64,
+    /// Number of RSTs sent in response to dropped connections.
+    pub resets_sent: u64,
+}
@@ -0,0 +1,230 @@
+use core::cmp::min;
+use core::time::Duration;
+
+use stack::repr::{
+    ipv4_flags,
+    Ipv4Options,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpContext,
+    TcpEstablished,
+    TcpState,
+    TcpStats,
+};
+use stack::time::{
+    PollAt,
+    Timer,
+};
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+use super::connection::{
+    MAX_RETRIES,
+    MAX_RTO,
+};
+
+/// The TCP SYN_RECV state.
+#[derive(Debug)]
+pub struct TcpSynRecv {
+    pub connecting_to: SocketAddr,
+    /// Runs from the last SYN + ACK sent until it is retransmitted.
+    pub retransmit_timer: Timer,
+    pub seq_num: u32,
+    pub ack_num: u32,
+    /// MSS requested by the remote endpoint in its SYN.
+    pub max_segment_size: Option<u16>,
+    pub retransmit_timeout: Duration,
+    /// Number of times the SYN + ACK has been retransmitted.
+    pub retries: usize,
+    pub stats: TcpStats,
+    pub context: TcpContext,
+}
+
+impl Tcp for TcpSynRecv {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        let time_env = &*self.context.time_env;
+        let send_syn =
+            !self.retransmit_timer.is_running() || self.retransmit_timer.is_expired(time_env);
+
+        if !send_syn {
+            return Err(Error::Buffer(BufferError::Empty));
+        }
+
+        let mut tcp_repr = TcpRepr {
+            src_port: self.context.binding.port,
+            dst_port: self.connecting_to.port,
+            seq_num: self.seq_num,
+            ack_num: self.ack_num,
+            flags: [false; 9],
+            window_size: min(self.context.recv_buffer_len, 65535) as u16,
+            urgent_pointer: 0,
+            max_segment_size: Some(min(self.context.max_segment_size(), 65535) as u16),
+        };
+
+        tcp_repr.flags[TcpRepr::FLAG_ACK] = true;
+        tcp_repr.flags[TcpRepr::FLAG_SYN] = true;
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: self.context.binding.addr,
+            dst_addr: self.connecting_to.addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: tcp_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        match f(&ipv4_repr, &tcp_repr, &[0; 0]) {
+            Ok(res) => {
+                debug!(
+                    "SYN_RECV @ ({}, {}) sent SYN + ACK.",
+                    self.context.binding, self.connecting_to
+                );
+                if self.retransmit_timer.is_running() {
+                    self.stats.retransmits += 1;
+                    self.retries += 1;
+                }
+                self.stats.segments_sent += 1;
+                self.retransmit_timeout = min(self.retransmit_timeout * 2, MAX_RTO);
+                self.retransmit_timer
+                    .start(time_env, self.retransmit_timeout);
+                Ok(res)
+            }
+            Err(err) => {
+                debug!(
+                    "SYN_RECV @ ({}, {}) encountered {:?} when sending SYN during active open.",
+                    self.context.binding, self.connecting_to, err
+                );
+                Err(err)
+            }
+        }
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if ipv4_repr.dst_addr != self.context.binding.addr
+            || tcp_repr.dst_port != self.context.binding.port
+            || ipv4_repr.src_addr != self.connecting_to.addr
+            || tcp_repr.src_port != self.connecting_to.port
+            || tcp_repr.ack_num != self.seq_num.wrapping_add(1)
+        {
+            return (None, Err(Error::Ignored));
+        }
+
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            self.stats.segments_received += 1;
+            debug!(
+                "SYN_RECV @ ({}, {}) received RST, transition to CLOSED.",
+                self.context.binding, self.connecting_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        if tcp_repr.flags[TcpRepr::FLAG_ACK] {
+            debug!(
+                "SYN_RECV @ ({}, {}) received ACK, transition to ESTABLISHED.",
+                self.context.binding, self.connecting_to
+            );
+            // The ACK may carry data (or even a FIN) which ESTABLISHED should process.
+            let mut established = self.to_established(tcp_repr);
+            return match established.recv_enqueue(ipv4_repr, tcp_repr, payload) {
+                (Some(tcp), _) => (Some(tcp), Ok(())),
+                (None, _) => (Some(TcpState::Established(established)), Ok(())),
+            };
+        }
+
+        self.stats.segments_received += 1;
+        (None, Err(Error::Ignored))
+    }
+
+    fn poll_at(&self) -> PollAt {
+        if self.retransmit_timer.is_running() {
+            self.retransmit_timer.poll_at()
+        } else {
+            PollAt::Now
+        }
+    }
+}
+
+impl TcpSynRecv {
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        (&self.connecting_to == src_addr) && (self.context.binding.as_ref() == dst_addr)
+    }
+
+    /// Checks if the SYN + ACK has been retransmitted MAX_RETRIES times
+    /// without a response and the connection should be given up on.
+    pub fn is_timed_out(&self) -> bool {
+        self.retries >= MAX_RETRIES && self.retransmit_timer.is_expired(&*self.context.time_env)
+    }
+
+    /// Returns a RST which aborts the connection, see RFC 793.
+    pub fn reset(&self) -> (Ipv4Repr, TcpRepr) {
+        let mut tcp_repr = TcpRepr {
+            src_port: self.context.binding.port,
+            dst_port: self.connecting_to.port,
+            seq_num: self.seq_num.wrapping_add(1),
+            ack_num: 0,
+            flags: [false; 9],
+            window_size: 0,
+            urgent_pointer: 0,
+            max_segment_size: None,
+        };
+        tcp_repr.flags[TcpRepr::FLAG_RST] = true;
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: self.context.binding.addr,
+            dst_addr: self.connecting_to.addr,
+            protocol: Ipv4Protocol::TCP,
+            payload_len: tcp_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        (ipv4_repr, tcp_repr)
+    }
+
+    /// Transitions from SYN_RECV to CLOSED in response to a RST + ACK.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        TcpClosed {
+            stats: self.stats,
+            context: self.context.clone(),
+        }
+    }
+
+    /// Transitions from SYN_RECV to ESTABLISHED in response to an ACK.
+    pub fn to_established(&mut self, tcp_repr: &TcpRepr) -> TcpEstablished {
+        TcpEstablished {
+            connection: TcpConnection::new(
+                self.connecting_to,
+                self.seq_num.wrapping_add(1),
+                self.ack_num,
+                tcp_repr.window_size,
+                self.max_segment_size,
+                self.stats,
+                self.context.clone(),
+            ),
+        }
+    }
+}
@@ -1,33 +1,51 @@
-use std::time::{
-    Duration,
-    Instant,
-};
+use core::cmp::min;
+use core::time::Duration;
 
-use core::repr::{
+use stack::repr::{
+    ipv4_flags,
+    Ipv4Options,
     Ipv4Protocol,
     Ipv4Repr,
     TcpRepr,
 };
-use core::socket::{
+use stack::socket::{
     SocketAddr,
     Tcp,
     TcpClosed,
+    TcpConnection,
     TcpContext,
     TcpEstablished,
     TcpState,
+    TcpStats,
+};
+use stack::time::{
+    PollAt,
+    Timer,
 };
 use {
+    BufferError,
     Error,
     Result,
 };
 
+use super::connection::{
+    MAX_RETRIES,
+    MAX_RTO,
+};
+
 /// The TCP SYN_SENT state.
 #[derive(Debug)]
 pub struct TcpSynSent {
     pub connecting_to: SocketAddr,
-    pub sent_syn_at: Option<Instant>,
+    /// Runs from the last SYN sent until it is retransmitted.
+    pub retransmit_timer: Timer,
     pub seq_num: u32,
     pub retransmit_timeout: Duration,
+    /// Number of times the SYN has been retransmitted.
+    pub retries: usize,
+    pub stats: TcpStats,
+    /// Expires when we give up establishing the connection.
+    pub connect_timer: Timer,
     pub context: TcpContext,
 }
 
@@ -36,15 +54,12 @@ impl Tcp for TcpSynSent {
     where
         F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
     {
-        let now = self.context.time_env.now_instant();
-
-        let send_syn = match self.sent_syn_at {
-            None => true,
-            Some(instant) => (now - instant) >= self.retransmit_timeout,
-        };
+        let time_env = &*self.context.time_env;
+        let send_syn =
+            !self.retransmit_timer.is_running() || self.retransmit_timer.is_expired(time_env);
 
         if !send_syn {
-            return Err(Error::Exhausted);
+            return Err(Error::Buffer(BufferError::Empty));
         }
 
         let mut tcp_repr = TcpRepr {
@@ -53,11 +68,9 @@ impl Tcp for TcpSynSent {
             seq_num: self.seq_num,
             ack_num: 0,
             flags: [false; 9],
-            // TODO: Set this to the size of our receive buffer?
-            window_size: 128,
+            window_size: min(self.context.recv_buffer_len, 65535) as u16,
             urgent_pointer: 0,
-            // TODO: Path MTU discovery to determine MSS.
-            max_segment_size: Some(536),
+            max_segment_size: Some(min(self.context.max_segment_size(), 65535) as u16),
         };
 
         tcp_repr.flags[TcpRepr::FLAG_SYN] = true;
@@ -67,6 +80,10 @@ impl Tcp for TcpSynSent {
             dst_addr: self.connecting_to.addr,
             protocol: Ipv4Protocol::TCP,
             payload_len: tcp_repr.header_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
         };
 
         // Caution, consider send failures! This can happen if the destination IP is
@@ -78,8 +95,14 @@ impl Tcp for TcpSynSent {
                     "SYN_SENT @ ({}, {}) sent SYN during active open.",
                     self.context.binding, self.connecting_to
                 );
-                self.sent_syn_at = Some(now);
-                self.retransmit_timeout *= 2;
+                if self.retransmit_timer.is_running() {
+                    self.stats.retransmits += 1;
+                    self.retries += 1;
+                }
+                self.stats.segments_sent += 1;
+                self.retransmit_timeout = min(self.retransmit_timeout * 2, MAX_RTO);
+                self.retransmit_timer
+                    .start(time_env, self.retransmit_timeout);
                 Ok(res)
             }
             Err(err) => {
@@ -102,11 +125,13 @@ impl Tcp for TcpSynSent {
             || tcp_repr.dst_port != self.context.binding.port
             || ipv4_repr.src_addr != self.connecting_to.addr
             || tcp_repr.src_port != self.connecting_to.port
-            || tcp_repr.ack_num != self.seq_num + 1
+            || tcp_repr.ack_num != self.seq_num.wrapping_add(1)
         {
             return (None, Err(Error::Ignored));
         }
 
+        self.stats.segments_received += 1;
+
         if tcp_repr.flags[TcpRepr::FLAG_RST] {
             debug!(
                 "SYN_SENT @ ({}, {}) received RST, transition to CLOSED.",
@@ -125,31 +150,53 @@ impl Tcp for TcpSynSent {
                 self.context.binding, self.connecting_to
             );
             return (
-                Some(TcpState::Established(self.to_established(tcp_repr.seq_num))),
+                Some(TcpState::Established(self.to_established(tcp_repr))),
                 Ok(()),
             );
         }
 
         (None, Err(Error::Ignored))
     }
+
+    fn poll_at(&self) -> PollAt {
+        let retransmit_at = if self.retransmit_timer.is_running() {
+            self.retransmit_timer.poll_at()
+        } else {
+            PollAt::Now
+        };
+        retransmit_at.min(self.connect_timer.poll_at())
+    }
 }
 
 impl TcpSynSent {
+    /// Checks if the connection has failed to be established in time, or the
+    /// SYN has been retransmitted MAX_RETRIES times without a response.
+    pub fn is_timed_out(&self) -> bool {
+        let time_env = &*self.context.time_env;
+        self.connect_timer.is_expired(time_env)
+            || (self.retries >= MAX_RETRIES && self.retransmit_timer.is_expired(time_env))
+    }
+
     /// Transitions from SYN_SENT to CLOSED in response to a RST + ACK.
     pub fn to_closed(&mut self) -> TcpClosed {
         TcpClosed {
+            stats: self.stats,
             context: self.context.clone(),
         }
     }
 
     /// Transitions from SYN_SENT to ESTABLISHED in response to a SYN + ACK.
-    pub fn to_established(&mut self, remote_seq_num: u32) -> TcpEstablished {
+    pub fn to_established(&mut self, tcp_repr: &TcpRepr) -> TcpEstablished {
         TcpEstablished {
-            connected_to: self.connecting_to,
-            ack_num: remote_seq_num + 1,
-            ack_sent: false,
-            seq_num: self.seq_num + 1,
-            context: self.context.clone(),
+            connection: TcpConnection::new(
+                self.connecting_to,
+                self.seq_num.wrapping_add(1),
+                tcp_repr.seq_num.wrapping_add(1),
+                tcp_repr.window_size,
+                tcp_repr.max_segment_size,
+                self.stats,
+                self.context.clone(),
+            ),
         }
     }
 }
@@ -0,0 +1,103 @@
+use alloc::rc::Rc;
+use core::time::Duration;
+
+use stack::repr::{
+    Ipv4Repr,
+    TcpRepr,
+};
+use stack::socket::{
+    SocketAddr,
+    Tcp,
+    TcpClosed,
+    TcpConnection,
+    TcpState,
+};
+use stack::time::{
+    PollAt,
+    Timer,
+};
+use Result;
+
+/// Time to linger in TIME_WAIT before closing, i.e. 2 * MSL.
+pub const TIME_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The TCP TIME_WAIT state.
+///
+/// Both endpoints have finished sending. We linger to acknowledge any
+/// retransmitted FINs before closing.
+#[derive(Debug)]
+pub struct TcpTimeWait {
+    pub connection: TcpConnection,
+    /// Expires TIME_WAIT_TIMEOUT after entering the state.
+    pub expiry_timer: Timer,
+}
+
+impl Tcp for TcpTimeWait {
+    fn send_dequeue<F, R>(&mut self, f: &mut F) -> Result<R>
+    where
+        F: FnMut(&Ipv4Repr, &TcpRepr, &[u8]) -> Result<R>,
+    {
+        // The binding only lingers once no other sockets, e.g. a listener or
+        // connections it accepted, are using it.
+        let binding = &self.connection.context.binding;
+        if Rc::strong_count(binding) == 1 {
+            binding.set_lingering(true);
+        }
+
+        self.connection.send_dequeue(f)
+    }
+
+    fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        tcp_repr: &TcpRepr,
+        payload: &[u8],
+    ) -> (Option<TcpState>, Result<()>) {
+        if tcp_repr.flags[TcpRepr::FLAG_RST] {
+            debug!(
+                "TIME_WAIT @ ({}, {}) received RST, transition to CLOSED.",
+                self.connection.context.binding, self.connection.connected_to
+            );
+            return (Some(TcpState::Closed(self.to_closed())), Ok(()));
+        }
+
+        (
+            None,
+            self.connection.recv_enqueue(ipv4_repr, tcp_repr, payload),
+        )
+    }
+
+    fn poll_at(&self) -> PollAt {
+        self.connection.poll_at().min(self.expiry_timer.poll_at())
+    }
+}
+
+impl TcpTimeWait {
+    /// Creates a TIME_WAIT state for a connection which has just finished
+    /// closing.
+    pub fn new(connection: TcpConnection) -> TcpTimeWait {
+        let mut expiry_timer = Timer::new();
+        expiry_timer.start(&*connection.context.time_env, TIME_WAIT_TIMEOUT);
+        TcpTimeWait {
+            connection,
+            expiry_timer,
+        }
+    }
+
+    /// Checks if the state accepts packets with particular (source,
+    /// destination) addresses.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        self.connection.accepts(src_addr, dst_addr)
+    }
+
+    /// Checks if the state has lingered long enough to close.
+    pub fn is_expired(&self) -> bool {
+        self.expiry_timer
+            .is_expired(&*self.connection.context.time_env)
+    }
+
+    /// Transitions from TIME_WAIT to CLOSED after a timeout or RST.
+    pub fn to_closed(&mut self) -> TcpClosed {
+        self.connection.to_closed()
+    }
+}
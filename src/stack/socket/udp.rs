@@ -0,0 +1,877 @@
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cmp;
+#[cfg(feature = "std")]
+use core::mem;
+use core::task::{
+    Context,
+    Poll,
+    Waker,
+};
+#[cfg(feature = "std")]
+use std::sync::{
+    Arc,
+    Mutex,
+};
+
+use stack::repr::{
+    ipv4_flags,
+    Icmpv4Repr,
+    Ipv4Address,
+    Ipv4Options,
+    Ipv4Protocol,
+    Ipv4Repr,
+    UdpPacket,
+    UdpRepr,
+};
+use stack::socket::{
+    Observer,
+    RecvMeta,
+    SocketAddr,
+    SocketAddrLease,
+    SocketEvent,
+    TaggedSocketAddr,
+    WatermarkState,
+    Watermarks,
+};
+use stack::storage::{
+    Ring,
+    Slice,
+};
+use stack::time::{
+    Env as TimeEnv,
+    Instant,
+    PollAt,
+};
+use {
+    AddressError,
+    Error,
+    ParseError,
+    RemoteError,
+    Result,
+    SocketError,
+};
+
+type Packet = (Slice<u8>, SocketAddr);
+
+/// The send or receive buffer of a socket, which is shared with a half of the
+/// socket once split.
+enum Buffer {
+    Owned(Ring<Packet>),
+    #[cfg(feature = "std")]
+    Shared(Arc<Mutex<Ring<Packet>>>),
+}
+
+impl Buffer {
+    /// Calls f with the ring, locking it if shared.
+    fn with<F, R>(&mut self, f: F) -> R
+    where
+        F: FnOnce(&mut Ring<Packet>) -> R,
+    {
+        match *self {
+            Buffer::Owned(ref mut ring) => f(ring),
+            #[cfg(feature = "std")]
+            Buffer::Shared(ref ring) => f(&mut ring.lock().unwrap()),
+        }
+    }
+
+    /// Returns the ring, or SocketError::Split if it is shared.
+    fn owned(&mut self) -> Result<&mut Ring<Packet>> {
+        match *self {
+            Buffer::Owned(ref mut ring) => Ok(ring),
+            #[cfg(feature = "std")]
+            Buffer::Shared(_) => Err(Error::Socket(SocketError::Split)),
+        }
+    }
+
+    fn is_shared(&self) -> bool {
+        match *self {
+            Buffer::Owned(_) => false,
+            #[cfg(feature = "std")]
+            Buffer::Shared(_) => true,
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            Buffer::Owned(ref ring) => ring.len(),
+            #[cfg(feature = "std")]
+            Buffer::Shared(ref ring) => ring.lock().unwrap().len(),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        match *self {
+            Buffer::Owned(ref ring) => ring.capacity(),
+            #[cfg(feature = "std")]
+            Buffer::Shared(ref ring) => ring.lock().unwrap().capacity(),
+        }
+    }
+
+    /// Moves the ring behind a lock and returns it, leaving the buffer shared.
+    #[cfg(feature = "std")]
+    fn share(&mut self) -> Arc<Mutex<Ring<Packet>>> {
+        let ring = match mem::replace(self, Buffer::Owned(Ring::from(Vec::new()))) {
+            Buffer::Owned(ring) => Arc::new(Mutex::new(ring)),
+            Buffer::Shared(ring) => ring,
+        };
+
+        *self = Buffer::Shared(ring.clone());
+        ring
+    }
+}
+
+/// A UDP socket.
+pub struct UdpSocket {
+    binding: SocketAddrLease,
+    connected_to: Option<SocketAddr>,
+    broadcast: bool,
+    multicast_groups: Vec<Ipv4Address>,
+    ttl: u8,
+    dscp: u8,
+    dont_fragment: bool,
+    checksum: bool,
+    require_checksum: bool,
+    error: Option<Error>,
+    icmp_error: Option<Icmpv4Repr>,
+    send_buffer: Buffer,
+    recv_buffer: Buffer,
+    // Timestamps of packets in the receive buffer, in the same order.
+    recv_timestamps: VecDeque<Option<Instant>>,
+    timestamp_env: Option<Rc<dyn TimeEnv>>,
+    send_watermark: WatermarkState,
+    recv_watermark: WatermarkState,
+    send_waker: Option<Waker>,
+    recv_waker: Option<Waker>,
+    recv_dropped: u64,
+    observer: Observer,
+}
+
+impl UdpSocket {
+    /// Creates a new UDP socket.
+    pub fn new(
+        binding: SocketAddrLease,
+        send_buffer: Ring<(Slice<u8>, SocketAddr)>,
+        recv_buffer: Ring<(Slice<u8>, SocketAddr)>,
+    ) -> UdpSocket {
+        UdpSocket {
+            binding,
+            connected_to: None,
+            broadcast: false,
+            multicast_groups: Vec::new(),
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            dont_fragment: false,
+            checksum: true,
+            require_checksum: false,
+            error: None,
+            icmp_error: None,
+            send_buffer: Buffer::Owned(send_buffer),
+            recv_timestamps: VecDeque::new(),
+            recv_buffer: Buffer::Owned(recv_buffer),
+            timestamp_env: None,
+            send_watermark: WatermarkState::new(),
+            recv_watermark: WatermarkState::new(),
+            send_waker: None,
+            recv_waker: None,
+            recv_dropped: 0,
+            observer: Observer::new(),
+        }
+    }
+
+    /// Checks if the socket is interested in receiving packets with the
+    /// specified (source, destination) addresses. Sockets bound to 0.0.0.0
+    /// receive packets sent to any address of the interface.
+    pub fn accepts(&self, src_addr: &SocketAddr, dst_addr: &SocketAddr) -> bool {
+        let from_peer = match self.connected_to {
+            Some(ref connected_to) => connected_to == src_addr,
+            None => true,
+        };
+
+        let to_binding = &(*self.binding) == dst_addr
+            || (self.binding.port == dst_addr.port
+                && (self.binding.addr.is_unspecified()
+                    || self.multicast_groups.contains(&dst_addr.addr)));
+
+        from_peer && to_binding
+    }
+
+    /// Checks if the socket is interested in receiving broadcast packets with
+    /// the specified source address and destination port.
+    pub fn accepts_broadcast(&self, src_addr: &SocketAddr, dst_port: u16) -> bool {
+        let from_peer = match self.connected_to {
+            Some(ref connected_to) => connected_to == src_addr,
+            None => true,
+        };
+
+        self.broadcast && from_peer && self.binding.port == dst_port
+    }
+
+    /// Permits sending packets to and receiving packets from broadcast
+    /// addresses, similar to SO_BROADCAST. Disabled by default, in which case
+    /// packets enqueued for a broadcast address are dropped.
+    pub fn set_broadcast(&mut self, broadcast: bool) {
+        self.broadcast = broadcast;
+    }
+
+    /// Checks if the socket may send and receive broadcast packets.
+    pub fn broadcast(&self) -> bool {
+        self.broadcast
+    }
+
+    /// Joins an IPv4 multicast group so packets sent to the group and the
+    /// bound port are received by the socket. The interface joins the group
+    /// and sends a membership report the next time sockets are serviced.
+    pub fn join_multicast_group(&mut self, group_addr: Ipv4Address) -> Result<()> {
+        if !group_addr.is_multicast() {
+            return Err(Error::Address(AddressError::InvalidAddress(group_addr)));
+        }
+
+        if !self.multicast_groups.contains(&group_addr) {
+            self.multicast_groups.push(group_addr);
+        }
+
+        Ok(())
+    }
+
+    /// Stops receiving packets sent to an IPv4 multicast group. The interface
+    /// remains a member of the group since other sockets may have joined it.
+    pub fn leave_multicast_group(&mut self, group_addr: Ipv4Address) {
+        self.multicast_groups.retain(|addr| *addr != group_addr);
+    }
+
+    /// Returns the IPv4 multicast groups joined by the socket.
+    pub fn multicast_groups(&self) -> &[Ipv4Address] {
+        &self.multicast_groups
+    }
+
+    /// Sets the TTL of outgoing packets.
+    pub fn set_ttl(&mut self, ttl: u8) {
+        self.ttl = ttl;
+    }
+
+    /// Returns the TTL of outgoing packets.
+    pub fn ttl(&self) -> u8 {
+        self.ttl
+    }
+
+    /// Sets the differentiated services code point of outgoing packets.
+    pub fn set_dscp(&mut self, dscp: u8) {
+        self.dscp = dscp;
+    }
+
+    /// Returns the differentiated services code point of outgoing packets.
+    pub fn dscp(&self) -> u8 {
+        self.dscp
+    }
+
+    /// Sets the DF flag on outgoing packets. Disabled by default, so packets
+    /// exceeding the interface MTU are fragmented rather than dropped.
+    pub fn set_dont_fragment(&mut self, dont_fragment: bool) {
+        self.dont_fragment = dont_fragment;
+    }
+
+    /// Checks if the DF flag is set on outgoing packets.
+    pub fn dont_fragment(&self) -> bool {
+        self.dont_fragment
+    }
+
+    /// Sets whether checksums are computed for packets sent via the socket.
+    /// Skipping checksums saves some work, but leaves corruption undetected.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
+    /// Checks if checksums are computed for packets sent via the socket.
+    pub fn checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// Sets whether packets received without a checksum are dropped.
+    pub fn set_require_checksum(&mut self, require_checksum: bool) {
+        self.require_checksum = require_checksum;
+    }
+
+    /// Checks if packets received without a checksum are dropped.
+    pub fn require_checksum(&self) -> bool {
+        self.require_checksum
+    }
+
+    /// Connects the socket to a remote endpoint. Only packets from the
+    /// endpoint are received afterwards, and send_connected() sends to it.
+    pub fn connect(&mut self, addr: SocketAddr) {
+        self.connected_to = Some(addr);
+    }
+
+    /// Returns the local address the socket is bound to.
+    pub fn binding(&self) -> &TaggedSocketAddr {
+        &self.binding
+    }
+
+    /// Checks if the socket shares its binding with other sockets via
+    /// reuse_port, see SocketEnv::set_reuse_port(...).
+    pub fn is_reuse_port(&self) -> bool {
+        self.binding.is_reuse_port()
+    }
+
+    /// Returns the address of the remote endpoint the socket is connected to.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.connected_to
+            .ok_or(Error::Socket(SocketError::NotConnected))
+    }
+
+    /// Enqueues a packet with a payload_len bytes payload for sending to the
+    /// specified address.
+    ///
+    /// Fails with RemoteError::Icmp if an ICMP error was received in response
+    /// to a previously sent packet, or BufferError::TooSmall if the payload
+    /// exceeds the max payload of the send buffer. Fails with
+    /// SocketError::Split once the socket is split, see split().
+    pub fn send(&mut self, buffer_len: usize, addr: SocketAddr) -> Result<&mut [u8]> {
+        if let Some(icmp_repr) = self.icmp_error.take() {
+            return Err(Error::Remote(RemoteError::Icmp(icmp_repr)));
+        }
+
+        let send_enqueued = self.send_buffer.len() + 1;
+        let res = self
+            .send_buffer
+            .owned()?
+            .enqueue_maybe(|&mut (ref mut buffer, ref mut addr_)| {
+                buffer.try_resize(buffer_len, 0)?;
+
+                for i in 0 .. buffer_len {
+                    buffer[i] = 0;
+                }
+
+                *addr_ = addr;
+
+                return Ok(&mut buffer[.. buffer_len]);
+            });
+
+        if res.is_ok() {
+            self.send_watermark.update(send_enqueued);
+        }
+
+        res
+    }
+
+    /// Enqueues a packet with a payload_len bytes payload for sending to the
+    /// endpoint the socket is connected to.
+    pub fn send_connected(&mut self, buffer_len: usize) -> Result<&mut [u8]> {
+        let addr = self.peer_addr()?;
+        self.send(buffer_len, addr)
+    }
+
+    /// Dequeues a received packet along with it's source address from the
+    /// socket.
+    ///
+    /// Fails with RemoteError::Icmp if an ICMP error was received in response
+    /// to a previously sent packet, or SocketError::Split once the socket is
+    /// split, see split().
+    pub fn recv(&mut self) -> Result<(&[u8], SocketAddr)> {
+        self.recv_with_meta()
+            .map(|(buffer, addr, _)| (buffer, addr))
+    }
+
+    /// Dequeues a received packet like recv(), along with information such as
+    /// when it was received.
+    pub fn recv_with_meta(&mut self) -> Result<(&[u8], SocketAddr, RecvMeta)> {
+        if let Some(icmp_repr) = self.icmp_error.take() {
+            return Err(Error::Remote(RemoteError::Icmp(icmp_repr)));
+        }
+
+        let recv_enqueued = self.recv_buffer.len().saturating_sub(1);
+        let timestamps = &mut self.recv_timestamps;
+        let res = self
+            .recv_buffer
+            .owned()?
+            .dequeue_with(|&mut (ref buffer, addr)| {
+                let meta = RecvMeta {
+                    timestamp: timestamps.pop_front().and_then(|timestamp| timestamp),
+                };
+                (&buffer[..], addr, meta)
+            });
+
+        if res.is_ok() {
+            self.recv_watermark.update(recv_enqueued);
+        }
+
+        res
+    }
+
+    /// Records when each packet is received so it can be retrieved via
+    /// recv_with_meta(), e.g. for measuring latency.
+    pub fn enable_timestamps<T: 'static + TimeEnv>(&mut self, time_env: T) {
+        self.timestamp_env = Some(Rc::new(time_env));
+    }
+
+    /// Stops recording when packets are received.
+    pub fn disable_timestamps(&mut self) {
+        self.timestamp_env = None;
+    }
+
+    /// Sets a callback notified with SocketEvent::Recv whenever a packet is
+    /// received, e.g. to wake a task rather than polling recv(). The callback
+    /// runs while the socket is serviced, so it must not access the socket
+    /// itself.
+    pub fn set_observer<F>(&mut self, callback: F)
+    where
+        F: 'static + FnMut(SocketEvent),
+    {
+        self.observer.set(callback);
+    }
+
+    /// Removes the callback set by set_observer(...).
+    pub fn clear_observer(&mut self) {
+        self.observer.clear();
+    }
+
+    /// Sets low and high watermarks, in packets, on the send buffer. Once as
+    /// many packets as the high watermark are enqueued, send_congested() is
+    /// true until the buffer drains to the low watermark, so applications can
+    /// pause sending before send() fails with BufferError::Full.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the low watermark exceeds the high watermark!
+    pub fn set_send_watermarks(&mut self, watermarks: Option<Watermarks>) {
+        let send_enqueued = self.send_buffer.len();
+        self.send_watermark
+            .set_watermarks(watermarks, send_enqueued);
+    }
+
+    /// Sets low and high watermarks, in packets, on the receive buffer. Once
+    /// as many packets as the high watermark are received, recv_congested()
+    /// is true until the buffer drains to the low watermark, so applications
+    /// can catch up before packets are dropped.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the low watermark exceeds the high watermark!
+    pub fn set_recv_watermarks(&mut self, watermarks: Option<Watermarks>) {
+        let recv_enqueued = self.recv_buffer.len();
+        self.recv_watermark
+            .set_watermarks(watermarks, recv_enqueued);
+    }
+
+    /// Checks if the send buffer filled to its high watermark and has not yet
+    /// drained to its low watermark.
+    pub fn send_congested(&self) -> bool {
+        self.send_watermark.is_congested()
+    }
+
+    /// Checks if the receive buffer filled to its high watermark and has not
+    /// yet drained to its low watermark.
+    pub fn recv_congested(&self) -> bool {
+        self.recv_watermark.is_congested()
+    }
+
+    /// Enqueues a packet for sending like send(...), but registers the task of
+    /// a context to be woken once a packet is dequeued for sending rather than
+    /// failing when the send buffer is full.
+    pub fn poll_send(
+        &mut self,
+        cx: &mut Context,
+        buffer_len: usize,
+        addr: SocketAddr,
+    ) -> Poll<Result<&mut [u8]>> {
+        if !self.can_send() && self.icmp_error.is_none() {
+            self.send_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        Poll::Ready(self.send(buffer_len, addr))
+    }
+
+    /// Dequeues a received packet like recv(), but registers the task of a
+    /// context to be woken once a packet is received rather than failing when
+    /// the receive buffer is empty.
+    pub fn poll_recv(&mut self, cx: &mut Context) -> Poll<Result<(&[u8], SocketAddr)>> {
+        if !self.can_recv() && self.icmp_error.is_none() {
+            self.recv_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        Poll::Ready(self.recv())
+    }
+
+    /// Shrinks the payload buffers of packets which are not enqueued for
+    /// sending or receiving to payload_len bytes, e.g. once a burst of large
+    /// packets is over. Buffers grow back on demand.
+    pub fn shrink_buffers(&mut self, payload_len: usize) {
+        self.send_buffer.with(|ring| {
+            ring.for_each_free(|&mut (ref mut buffer, _)| buffer.shrink_to(payload_len))
+        });
+        self.recv_buffer.with(|ring| {
+            ring.for_each_free(|&mut (ref mut buffer, _)| buffer.shrink_to(payload_len))
+        });
+    }
+
+    /// Splits off the send and receive buffers of the socket into halves
+    /// which may be moved to other threads, e.g. so one thread enqueues
+    /// packets while another drains received ones.
+    ///
+    /// The socket keeps sending and receiving packets via the halves while it
+    /// is serviced, but send() and recv() fail with SocketError::Split and
+    /// received packets are no longer timestamped. Halves copy payloads in
+    /// and out, as the buffers are locked while in use.
+    #[cfg(feature = "std")]
+    pub fn split(&mut self) -> Result<(UdpSendHalf, UdpRecvHalf)> {
+        if self.send_buffer.is_shared() {
+            return Err(Error::Socket(SocketError::Split));
+        }
+
+        self.recv_timestamps.clear();
+        let send_half = UdpSendHalf {
+            send_buffer: self.send_buffer.share(),
+            connected_to: self.connected_to,
+        };
+        let recv_half = UdpRecvHalf {
+            recv_buffer: self.recv_buffer.share(),
+        };
+
+        Ok((send_half, recv_half))
+    }
+
+    /// Dequeues a packet enqueued for sending via function f.
+    ///
+    /// The packet is only dequeued if f does not return an error.
+    pub fn send_dequeue<F, R>(&mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&Ipv4Repr, &UdpRepr, &[u8]) -> Result<R>,
+    {
+        let (ipv4_repr, udp_repr) = self.send_reprs();
+        let res = self.send_buffer.with(|ring| {
+            ring.dequeue_maybe(|&mut (ref mut buffer, addr)| {
+                let (ipv4_repr, udp_repr) = address_reprs(ipv4_repr, udp_repr, buffer.len(), addr);
+                f(&ipv4_repr, &udp_repr, &buffer[..])
+            })
+        });
+
+        if res.is_ok() {
+            self.sent();
+        }
+
+        res
+    }
+
+    /// Dequeues several packets enqueued for sending via function f, stopping
+    /// at the first packet f returns an error for or once the contiguous
+    /// packets at the head of the send buffer are exhausted.
+    ///
+    /// # Returns
+    ///
+    /// The number of packets dequeued, or the error returned by f if no
+    /// packet was dequeued.
+    pub fn send_dequeue_many<F>(&mut self, mut f: F) -> Result<usize>
+    where
+        F: FnMut(&Ipv4Repr, &UdpRepr, &[u8]) -> Result<()>,
+    {
+        let (ipv4_repr, udp_repr) = self.send_reprs();
+        let (sent, res) = self.send_buffer.with(|ring| {
+            ring.dequeue_many_with(|packets| {
+                for (i, &mut (ref mut buffer, addr)) in packets.iter_mut().enumerate() {
+                    let (ipv4_repr, udp_repr) =
+                        address_reprs(ipv4_repr, udp_repr, buffer.len(), addr);
+                    if let Err(err) = f(&ipv4_repr, &udp_repr, &buffer[..]) {
+                        return (i, (i, Err(err)));
+                    }
+                }
+
+                (packets.len(), (packets.len(), Ok(())))
+            })
+        })?;
+
+        if sent > 0 {
+            self.sent();
+            Ok(sent)
+        } else {
+            res.map(|_| sent)
+        }
+    }
+
+    /// Returns the IPv4 and UDP headers for packets sent by the socket, with
+    /// destination and lengths to be filled in via address_reprs(...).
+    fn send_reprs(&self) -> (Ipv4Repr, UdpRepr) {
+        let flags = if self.dont_fragment {
+            ipv4_flags::DONT_FRAGMENT
+        } else {
+            0
+        };
+
+        let udp_repr = UdpRepr {
+            src_port: self.binding.port,
+            dst_port: 0,
+            length: 0,
+            checksum: self.checksum,
+        };
+
+        let ipv4_repr = Ipv4Repr {
+            src_addr: self.binding.addr,
+            dst_addr: Ipv4Address::UNSPECIFIED,
+            protocol: Ipv4Protocol::UDP,
+            payload_len: 0,
+            ttl: self.ttl,
+            dscp: self.dscp,
+            flags,
+            options: Ipv4Options::new(),
+        };
+
+        (ipv4_repr, udp_repr)
+    }
+
+    fn sent(&mut self) {
+        self.send_watermark.update(self.send_buffer.len());
+        if let Some(waker) = self.send_waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Enqueues a packet for receiving.
+    ///
+    /// Packets with a destination address other than the binding are taken to
+    /// be broadcasts, which the caller should have checked.
+    pub fn recv_enqueue(
+        &mut self,
+        ipv4_repr: &Ipv4Repr,
+        udp_repr: &UdpRepr,
+        payload: &[u8],
+    ) -> Result<()> {
+        let src_addr = SocketAddr {
+            addr: ipv4_repr.src_addr,
+            port: udp_repr.src_port,
+        };
+        let dst_addr = SocketAddr {
+            addr: ipv4_repr.dst_addr,
+            port: udp_repr.dst_port,
+        };
+        let accepts = self.accepts(&src_addr, &dst_addr)
+            || (!dst_addr.addr.is_multicast() && self.accepts_broadcast(&src_addr, dst_addr.port));
+
+        if accepts && self.require_checksum && !udp_repr.checksum {
+            debug!("Dropping UDP packet without a checksum from {}.", src_addr);
+            return Err(Error::Parse(ParseError::Checksum));
+        }
+
+        let res = self.recv_buffer.with(|ring| {
+            ring.enqueue_maybe(|&mut (ref mut buffer, ref mut addr)| {
+                if !accepts {
+                    Err(Error::Ignored)
+                } else {
+                    buffer.try_resize(payload.len(), 0)?;
+                    buffer.copy_from_slice(payload);
+                    addr.addr = ipv4_repr.src_addr;
+                    addr.port = udp_repr.src_port;
+                    Ok(())
+                }
+            })
+        });
+
+        if res.is_ok() {
+            // Packets dequeued via a UdpRecvHalf can not pop their timestamps.
+            if !self.recv_buffer.is_shared() {
+                let timestamp = self
+                    .timestamp_env
+                    .as_ref()
+                    .map(|time_env| time_env.now_instant());
+                self.recv_timestamps.push_back(timestamp);
+            }
+            self.recv_watermark.update(self.recv_buffer.len());
+            if let Some(waker) = self.recv_waker.take() {
+                waker.wake();
+            }
+            self.observer.notify(SocketEvent::Recv);
+        } else if let Err(Error::Buffer(_)) = res {
+            self.recv_dropped += 1;
+        }
+
+        res
+    }
+
+    /// Records an ICMP error received in response to a packet sent with the
+    /// specified (source, destination) addresses. The error is returned by
+    /// the next call to send() or recv().
+    pub fn recv_icmp_error(
+        &mut self,
+        src_addr: &SocketAddr,
+        dst_addr: &SocketAddr,
+        icmp_repr: &Icmpv4Repr,
+    ) -> Result<()> {
+        let to_peer = match self.connected_to {
+            Some(ref connected_to) => connected_to == dst_addr,
+            None => true,
+        };
+
+        if &(*self.binding) != src_addr || !to_peer {
+            return Err(Error::Ignored);
+        }
+
+        self.icmp_error = Some(*icmp_repr);
+
+        // Both send() and recv() return the error.
+        if let Some(waker) = self.send_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.recv_waker.take() {
+            waker.wake();
+        }
+
+        Ok(())
+    }
+
+    /// Marks the socket with an error if it is bound to an address which has
+    /// been removed from the interface, e.g. by Interface::set_ipv4_addr(...).
+    /// The socket should be rebound since packets to the address will no
+    /// longer be received.
+    pub fn addr_removed(&mut self, addr: Ipv4Address) {
+        if self.binding.addr == addr {
+            self.error = Some(Error::Address(AddressError::InvalidAddress(addr)));
+        }
+    }
+
+    /// Returns and clears the pending error, if any, such as an ICMP error.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take().or_else(|| {
+            self.icmp_error
+                .take()
+                .map(|icmp_repr| Error::Remote(RemoteError::Icmp(icmp_repr)))
+        })
+    }
+
+    /// Returns when the socket next needs servicing, i.e. now if packets are
+    /// enqueued for sending.
+    pub fn poll_at(&self) -> PollAt {
+        if self.send_buffer.len() > 0 {
+            PollAt::Now
+        } else {
+            PollAt::Ingress
+        }
+    }
+
+    /// Returns the number of packets enqueued for sending.
+    pub fn send_enqueued(&self) -> usize {
+        self.send_buffer.len()
+    }
+
+    /// Returns the number of packets enqueued for receiving.
+    pub fn recv_enqueued(&self) -> usize {
+        self.recv_buffer.len()
+    }
+
+    /// Returns the number of packets which can be enqueued for sending.
+    pub fn send_capacity(&self) -> usize {
+        self.send_buffer.capacity() - self.send_buffer.len()
+    }
+
+    /// Checks if send() would enqueue a packet rather than fail.
+    pub fn can_send(&self) -> bool {
+        self.send_capacity() > 0
+    }
+
+    /// Checks if recv() would dequeue a packet rather than fail.
+    pub fn can_recv(&self) -> bool {
+        self.recv_enqueued() > 0
+    }
+
+    /// Returns the number of received packets dropped because the receive
+    /// buffer was full or a packet did not fit in it.
+    pub fn recv_dropped(&self) -> u64 {
+        self.recv_dropped
+    }
+}
+
+/// The send half of a UdpSocket, see UdpSocket::split().
+#[cfg(feature = "std")]
+pub struct UdpSendHalf {
+    send_buffer: Arc<Mutex<Ring<Packet>>>,
+    connected_to: Option<SocketAddr>,
+}
+
+#[cfg(feature = "std")]
+impl UdpSendHalf {
+    /// Enqueues a packet with a copy of a payload for sending to the
+    /// specified address.
+    ///
+    /// Fails with BufferError::Full if the send buffer is full, or
+    /// BufferError::TooSmall if the payload exceeds the max payload of the
+    /// send buffer.
+    pub fn send(&mut self, payload: &[u8], addr: SocketAddr) -> Result<()> {
+        self.send_buffer
+            .lock()
+            .unwrap()
+            .enqueue_maybe(|&mut (ref mut buffer, ref mut addr_)| {
+                buffer.try_resize(payload.len(), 0)?;
+                buffer.copy_from_slice(payload);
+                *addr_ = addr;
+                Ok(())
+            })
+    }
+
+    /// Enqueues a packet for sending to the endpoint the socket was connected
+    /// to when it was split.
+    pub fn send_connected(&mut self, payload: &[u8]) -> Result<()> {
+        let addr = self.peer_addr()?;
+        self.send(payload, addr)
+    }
+
+    /// Returns the address of the remote endpoint the socket was connected to
+    /// when it was split.
+    pub fn peer_addr(&self) -> Result<SocketAddr> {
+        self.connected_to
+            .ok_or(Error::Socket(SocketError::NotConnected))
+    }
+
+    /// Checks if send(...) would enqueue a packet rather than fail.
+    pub fn can_send(&self) -> bool {
+        let send_buffer = self.send_buffer.lock().unwrap();
+        send_buffer.len() < send_buffer.capacity()
+    }
+}
+
+/// The receive half of a UdpSocket, see UdpSocket::split().
+#[cfg(feature = "std")]
+pub struct UdpRecvHalf {
+    recv_buffer: Arc<Mutex<Ring<Packet>>>,
+}
+
+#[cfg(feature = "std")]
+impl UdpRecvHalf {
+    /// Dequeues a received packet, copying its payload to a buffer.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes copied along with the source address of the
+    /// packet. Payloads larger than the buffer are truncated.
+    pub fn recv(&mut self, buffer: &mut [u8]) -> Result<(usize, SocketAddr)> {
+        self.recv_buffer
+            .lock()
+            .unwrap()
+            .dequeue_with(|&mut (ref payload, addr)| {
+                let len = cmp::min(payload.len(), buffer.len());
+                buffer[.. len].copy_from_slice(&payload[.. len]);
+                (len, addr)
+            })
+    }
+
+    /// Checks if recv(...) would dequeue a packet rather than fail.
+    pub fn can_recv(&self) -> bool {
+        self.recv_buffer.lock().unwrap().len() > 0
+    }
+}
+
+/// Fills in the destination and lengths of headers from send_reprs(...) for a
+/// packet with payload_len bytes of payload.
+fn address_reprs(
+    mut ipv4_repr: Ipv4Repr,
+    mut udp_repr: UdpRepr,
+    payload_len: usize,
+    addr: SocketAddr,
+) -> (Ipv4Repr, UdpRepr) {
+    udp_repr.dst_port = addr.port;
+    udp_repr.length = UdpPacket::<&[u8]>::buffer_len(payload_len) as u16;
+    ipv4_repr.dst_addr = addr.addr;
+    ipv4_repr.payload_len = udp_repr.buffer_len() as u16;
+    (ipv4_repr, udp_repr)
+}
@@ -0,0 +1,94 @@
+/// Low and high watermarks on the number of bytes or packets in a socket
+/// buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Watermarks {
+    pub low: usize,
+    pub high: usize,
+}
+
+/// Tracks whether a socket buffer is congested, i.e. has filled up to its high
+/// watermark and not yet drained down to its low watermark.
+///
+/// The gap between the watermarks keeps applications from flip-flopping
+/// between pausing and resuming as single packets come and go.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WatermarkState {
+    watermarks: Option<Watermarks>,
+    congested: bool,
+}
+
+impl WatermarkState {
+    /// Creates a state without watermarks, which is never congested.
+    pub fn new() -> WatermarkState {
+        WatermarkState::default()
+    }
+
+    /// Sets or clears the watermarks and re-evaluates congestion for a buffer
+    /// with len bytes or packets.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if the low watermark exceeds the high watermark!
+    pub fn set_watermarks(&mut self, watermarks: Option<Watermarks>, len: usize) {
+        if let Some(watermarks) = watermarks {
+            assert!(watermarks.low <= watermarks.high);
+        }
+
+        self.watermarks = watermarks;
+        self.congested = false;
+        self.update(len);
+    }
+
+    /// Returns the watermarks, if set.
+    pub fn watermarks(&self) -> Option<Watermarks> {
+        self.watermarks
+    }
+
+    /// Updates congestion after the buffer changed to hold len bytes or
+    /// packets, returning true if the buffer crossed a watermark.
+    pub fn update(&mut self, len: usize) -> bool {
+        let congested = match self.watermarks {
+            Some(Watermarks { high, .. }) if len >= high => true,
+            Some(Watermarks { low, .. }) if len <= low => false,
+            Some(_) => self.congested,
+            None => false,
+        };
+
+        let crossed = congested != self.congested;
+        self.congested = congested;
+        crossed
+    }
+
+    /// Checks if the buffer reached the high watermark and has not yet
+    /// drained to the low watermark since.
+    pub fn is_congested(&self) -> bool {
+        self.congested
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watermark_hysteresis() {
+        let mut state = WatermarkState::new();
+        assert!(!state.update(100));
+        assert!(!state.is_congested());
+
+        state.set_watermarks(Some(Watermarks { low: 2, high: 4 }), 0);
+        assert!(!state.update(3));
+        assert!(!state.is_congested());
+        assert!(state.update(4));
+        assert!(state.is_congested());
+        assert!(!state.update(3));
+        assert!(state.is_congested());
+        assert!(state.update(2));
+        assert!(!state.is_congested());
+
+        state.set_watermarks(Some(Watermarks { low: 2, high: 4 }), 5);
+        assert!(state.is_congested());
+        state.set_watermarks(None, 5);
+        assert!(!state.is_congested());
+    }
+}
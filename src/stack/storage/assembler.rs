@@ -0,0 +1,162 @@
+use alloc::vec::Vec;
+use core::cmp::{
+    max,
+    min,
+};
+use core::ops::Range;
+
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Tracks data which arrives out of order in a fixed size window.
+///
+/// Data is added at offsets relative to the start of the window, and may be
+/// consumed from the front once there are no gaps before it. The assembler
+/// only tracks which ranges hold data, the data itself lives wherever the
+/// owner stored it, e.g. past the end of a ByteRing.
+#[derive(Clone, Debug)]
+pub struct Assembler {
+    capacity: usize,
+    // Sorted, non-overlapping, and non-adjacent ranges of the window which
+    // hold data.
+    ranges: Vec<Range<usize>>,
+}
+
+impl Assembler {
+    /// Creates an assembler with a window of the specified capacity.
+    pub fn new(capacity: usize) -> Assembler {
+        Assembler {
+            capacity,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Returns the size of the window.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Checks if the assembler does not hold any data.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns the ranges of the window which hold data.
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Marks len bytes at an offset in the window as holding data.
+    ///
+    /// Returns an error if the data does not fit inside the window, in which
+    /// case nothing is added.
+    pub fn add(&mut self, offset: usize, len: usize) -> Result<()> {
+        let end = offset + len;
+        if end > self.capacity {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        } else if len == 0 {
+            return Ok(());
+        }
+
+        // Merge with any ranges which overlap or touch the new one.
+        let mut range = offset .. end;
+        let mut i = 0;
+        while i < self.ranges.len() {
+            if self.ranges[i].end < range.start {
+                i += 1;
+            } else if self.ranges[i].start > range.end {
+                break;
+            } else {
+                let other = self.ranges.remove(i);
+                range = min(other.start, range.start) .. max(other.end, range.end);
+            }
+        }
+
+        self.ranges.insert(i, range);
+        Ok(())
+    }
+
+    /// Returns the number of contiguous bytes at the front of the window.
+    pub fn front_len(&self) -> usize {
+        match self.ranges.first() {
+            Some(range) if range.start == 0 => range.end,
+            _ => 0,
+        }
+    }
+
+    /// Slides the window forward, discarding the first len bytes of the
+    /// window whether or not they hold data.
+    pub fn advance(&mut self, len: usize) {
+        let len = min(len, self.capacity);
+        if len == 0 {
+            return;
+        }
+
+        self.ranges = self
+            .ranges
+            .iter()
+            .filter(|range| range.end > len)
+            .map(|range| (max(range.start, len) - len) .. (range.end - len))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+// The expected ranges are single ranges, not ranges of array lengths.
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_in_order() {
+        let mut assembler = Assembler::new(8);
+        assert!(assembler.is_empty());
+        assert_matches!(assembler.add(0, 2), Ok(()));
+        assert_matches!(assembler.add(2, 2), Ok(()));
+        assert_eq!(assembler.ranges(), &[0 .. 4]);
+        assert_eq!(assembler.front_len(), 4);
+    }
+
+    #[test]
+    fn test_add_out_of_order() {
+        let mut assembler = Assembler::new(8);
+        assert_matches!(assembler.add(6, 2), Ok(()));
+        assert_matches!(assembler.add(2, 2), Ok(()));
+        assert_eq!(assembler.ranges(), &[2 .. 4, 6 .. 8]);
+        assert_eq!(assembler.front_len(), 0);
+        assert_matches!(assembler.add(0, 2), Ok(()));
+        assert_eq!(assembler.ranges(), &[0 .. 4, 6 .. 8]);
+        assert_eq!(assembler.front_len(), 4);
+        assert_matches!(assembler.add(3, 3), Ok(()));
+        assert_eq!(assembler.ranges(), &[0 .. 8]);
+        assert_eq!(assembler.front_len(), 8);
+    }
+
+    #[test]
+    fn test_add_too_big() {
+        let mut assembler = Assembler::new(4);
+        assert_matches!(
+            assembler.add(2, 3),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+        assert!(assembler.is_empty());
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut assembler = Assembler::new(8);
+        assert_matches!(assembler.add(0, 3), Ok(()));
+        assert_matches!(assembler.add(5, 2), Ok(()));
+        assembler.advance(3);
+        assert_eq!(assembler.ranges(), &[2 .. 4]);
+        assert_eq!(assembler.front_len(), 0);
+        assembler.advance(3);
+        assert_eq!(assembler.ranges(), &[0 .. 1]);
+        assert_eq!(assembler.front_len(), 1);
+        assembler.advance(8);
+        assert!(assembler.is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+use core::cmp::min;
+
+use stack::storage::Managed;
+
+/// Ring buffer of bytes with a fixed capacity.
+///
+/// Besides enqueueing at the end, data may be written into the unallocated
+/// space past the end and enqueued later without copying it again, e.g. for
+/// data which arrives out of order.
+#[derive(Clone, Debug, Default)]
+pub struct ByteRing {
+    buffer: Managed<u8>,
+    begin: usize,
+    len: usize,
+}
+
+impl From<&'static mut [u8]> for ByteRing {
+    fn from(buffer: &'static mut [u8]) -> ByteRing {
+        ByteRing::from(Managed::from(buffer))
+    }
+}
+
+impl From<Managed<u8>> for ByteRing {
+    fn from(buffer: Managed<u8>) -> ByteRing {
+        ByteRing {
+            buffer,
+            begin: 0,
+            len: 0,
+        }
+    }
+}
+
+impl ByteRing {
+    /// Creates an empty ring which can hold capacity bytes.
+    pub fn new(capacity: usize) -> ByteRing {
+        ByteRing::from(Managed::from(vec![0; capacity]))
+    }
+
+    /// Checks if the ring is backed by static storage.
+    pub fn is_static(&self) -> bool {
+        self.buffer.is_static()
+    }
+
+    /// Returns the maximum number of bytes the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Returns the number of bytes in the ring.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks if the ring holds no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes which may still be enqueued.
+    pub fn window(&self) -> usize {
+        self.buffer.len() - self.len
+    }
+
+    /// Discards all bytes in the ring.
+    pub fn clear(&mut self) {
+        self.begin = 0;
+        self.len = 0;
+    }
+
+    /// Enqueues as much of data as fits, returning the number of bytes
+    /// enqueued.
+    pub fn enqueue_slice(&mut self, data: &[u8]) -> usize {
+        let written = self.write_unallocated(0, data);
+        self.enqueue_unallocated(written);
+        written
+    }
+
+    /// Writes as much of data as fits into the unallocated space at offset
+    /// past the end of the ring, returning the number of bytes written. The
+    /// bytes are not enqueued until enqueue_unallocated(...) is called.
+    pub fn write_unallocated(&mut self, offset: usize, data: &[u8]) -> usize {
+        if offset >= self.window() {
+            return 0;
+        }
+
+        let data = &data[.. min(data.len(), self.window() - offset)];
+        let capacity = self.buffer.len();
+        let start = (self.begin + self.len + offset) % capacity;
+        let head_len = min(data.len(), capacity - start);
+        self.buffer[start .. start + head_len].copy_from_slice(&data[.. head_len]);
+        self.buffer[.. data.len() - head_len].copy_from_slice(&data[head_len ..]);
+        data.len()
+    }
+
+    /// Enqueues up to len bytes previously written past the end of the ring
+    /// via write_unallocated(...), returning the number of bytes enqueued.
+    pub fn enqueue_unallocated(&mut self, len: usize) -> usize {
+        let len = min(len, self.window());
+        self.len += len;
+        len
+    }
+
+    /// Returns up to len bytes at offset past the front of the ring without
+    /// dequeueing them. Fewer bytes are returned where they wrap around the
+    /// end of the buffer, so they can be read without copying.
+    pub fn get_allocated(&self, offset: usize, len: usize) -> &[u8] {
+        if offset >= self.len {
+            return &[];
+        }
+
+        let capacity = self.buffer.len();
+        let start = (self.begin + offset) % capacity;
+        let len = min(min(len, self.len - offset), capacity - start);
+        &self.buffer[start .. start + len]
+    }
+
+    /// Dequeues up to len bytes without copying them, returning the number of
+    /// bytes dequeued.
+    pub fn dequeue_allocated(&mut self, len: usize) -> usize {
+        let len = min(len, self.len);
+        if len == 0 {
+            return 0;
+        }
+
+        self.begin = (self.begin + len) % self.buffer.len();
+        self.len -= len;
+        len
+    }
+
+    /// Dequeues bytes into data until it is full or the ring is empty,
+    /// returning the number of bytes dequeued.
+    pub fn dequeue_slice(&mut self, data: &mut [u8]) -> usize {
+        let len = min(data.len(), self.len);
+        if len == 0 {
+            return 0;
+        }
+
+        let capacity = self.buffer.len();
+        let head_len = min(len, capacity - self.begin);
+        data[.. head_len].copy_from_slice(&self.buffer[self.begin .. self.begin + head_len]);
+        data[head_len .. len].copy_from_slice(&self.buffer[.. len - head_len]);
+
+        self.begin = (self.begin + len) % capacity;
+        self.len -= len;
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enqueue_and_dequeue_wrapping() {
+        let mut ring = ByteRing::new(4);
+        assert_eq!(ring.enqueue_slice(&[0, 1, 2]), 3);
+        let mut data = [0; 2];
+        assert_eq!(ring.dequeue_slice(&mut data), 2);
+        assert_eq!(data, [0, 1]);
+
+        assert_eq!(ring.enqueue_slice(&[3, 4, 5, 6]), 3);
+        assert_eq!(ring.window(), 0);
+        let mut data = [0; 8];
+        assert_eq!(ring.dequeue_slice(&mut data), 4);
+        assert_eq!(&data[.. 4], &[2, 3, 4, 5]);
+        assert!(ring.is_empty());
+    }
+
+    #[test]
+    fn test_write_unallocated() {
+        let mut ring = ByteRing::new(6);
+        assert_eq!(ring.enqueue_slice(&[0, 1]), 2);
+        assert_eq!(ring.write_unallocated(2, &[4, 5, 6, 7]), 2);
+        assert_eq!(ring.write_unallocated(4, &[6]), 0);
+        assert_eq!(ring.len(), 2);
+
+        assert_eq!(ring.enqueue_slice(&[2, 3]), 2);
+        assert_eq!(ring.enqueue_unallocated(8), 2);
+        let mut data = [0; 6];
+        assert_eq!(ring.dequeue_slice(&mut data), 6);
+        assert_eq!(data, [0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_get_and_dequeue_allocated() {
+        let mut ring = ByteRing::new(4);
+        assert_eq!(ring.enqueue_slice(&[0, 1, 2]), 3);
+        assert_eq!(ring.dequeue_allocated(2), 2);
+        assert_eq!(ring.enqueue_slice(&[3, 4, 5]), 3);
+
+        assert_eq!(ring.get_allocated(0, 8), &[2, 3]);
+        assert_eq!(ring.get_allocated(1, 1), &[3]);
+        assert_eq!(ring.get_allocated(2, 8), &[4, 5]);
+        assert_eq!(ring.get_allocated(4, 8), &[] as &[u8]);
+
+        assert_eq!(ring.dequeue_allocated(8), 4);
+        assert!(ring.is_empty());
+        assert_eq!(ring.dequeue_allocated(1), 0);
+    }
+
+    #[test]
+    fn test_static() {
+        let buffer: &'static mut [u8] = Box::leak(Box::new([0; 2]));
+        let mut ring = ByteRing::from(buffer);
+        assert!(ring.is_static());
+        assert_eq!(ring.capacity(), 2);
+        assert_eq!(ring.enqueue_slice(&[0, 1, 2]), 2);
+        let mut data = [0; 2];
+        assert_eq!(ring.dequeue_slice(&mut data), 2);
+        assert_eq!(data, [0, 1]);
+    }
+}
@@ -0,0 +1,69 @@
+use alloc::vec::Vec;
+use core::ops::{
+    Deref,
+    DerefMut,
+};
+
+/// Storage which is either owned on the heap or borrowed from caller-provided
+/// static memory, so buffers can run without any heap allocation.
+#[derive(Debug)]
+pub enum Managed<T: 'static> {
+    Owned(Vec<T>),
+    Static(&'static mut [T]),
+}
+
+impl<T> Managed<T> {
+    /// Checks if the storage is borrowed from static memory.
+    pub fn is_static(&self) -> bool {
+        match *self {
+            Managed::Owned(_) => false,
+            Managed::Static(_) => true,
+        }
+    }
+}
+
+impl<T: Clone> Clone for Managed<T> {
+    /// Clones the storage, copying static storage onto the heap since it can
+    /// not be borrowed twice.
+    fn clone(&self) -> Managed<T> {
+        Managed::Owned(self.to_vec())
+    }
+}
+
+impl<T> Default for Managed<T> {
+    fn default() -> Managed<T> {
+        Managed::Owned(Vec::new())
+    }
+}
+
+impl<T> From<Vec<T>> for Managed<T> {
+    fn from(buffer: Vec<T>) -> Managed<T> {
+        Managed::Owned(buffer)
+    }
+}
+
+impl<T> From<&'static mut [T]> for Managed<T> {
+    fn from(buffer: &'static mut [T]) -> Managed<T> {
+        Managed::Static(buffer)
+    }
+}
+
+impl<T> Deref for Managed<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match *self {
+            Managed::Owned(ref buffer) => buffer,
+            Managed::Static(ref buffer) => buffer,
+        }
+    }
+}
+
+impl<T> DerefMut for Managed<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        match *self {
+            Managed::Owned(ref mut buffer) => buffer,
+            Managed::Static(ref mut buffer) => buffer,
+        }
+    }
+}
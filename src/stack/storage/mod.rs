@@ -0,0 +1,17 @@
+//! Storage/buffers for packets, frames, etc.
+
+pub mod assembler;
+pub mod byte_ring;
+pub mod managed;
+pub mod packet_buf;
+pub mod pool;
+pub mod ring;
+pub mod slice;
+
+pub use self::assembler::Assembler;
+pub use self::byte_ring::ByteRing;
+pub use self::managed::Managed;
+pub use self::packet_buf::PacketBuf;
+pub use self::pool::Pool;
+pub use self::ring::Ring;
+pub use self::slice::Slice;
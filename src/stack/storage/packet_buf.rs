@@ -0,0 +1,171 @@
+use alloc::vec::Vec;
+use core::ops::{
+    Deref,
+    DerefMut,
+};
+
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Owned buffer holding a packet with headroom before it and tailroom after
+/// it.
+///
+/// Upper layers write their payload first, then lower layers push(...) their
+/// headers into the headroom and put(...) trailers into the tailroom in place,
+/// rather than serializing the packet inside out or copying it per layer.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PacketBuf {
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+}
+
+impl Deref for PacketBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.start .. self.end]
+    }
+}
+
+impl DerefMut for PacketBuf {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.buffer[self.start .. self.end]
+    }
+}
+
+impl From<Vec<u8>> for PacketBuf {
+    fn from(buffer: Vec<u8>) -> Self {
+        let end = buffer.len();
+        PacketBuf {
+            buffer,
+            start: 0,
+            end,
+        }
+    }
+}
+
+impl PacketBuf {
+    /// Creates a zeroed packet of len bytes with headroom and tailroom bytes
+    /// reserved around it.
+    pub fn new(headroom: usize, len: usize, tailroom: usize) -> PacketBuf {
+        PacketBuf::from_vec(vec![], headroom, len, tailroom)
+    }
+
+    /// Creates a zeroed packet like new(...), reusing the allocation of a
+    /// buffer, e.g. one taken from a Pool.
+    pub fn from_vec(mut buffer: Vec<u8>, headroom: usize, len: usize, tailroom: usize) -> PacketBuf {
+        buffer.clear();
+        buffer.resize(headroom + len + tailroom, 0);
+        PacketBuf {
+            buffer,
+            start: headroom,
+            end: headroom + len,
+        }
+    }
+
+    /// Returns the number of bytes which can be pushed before the packet.
+    pub fn headroom(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the number of bytes which can be put after the packet.
+    pub fn tailroom(&self) -> usize {
+        self.buffer.len() - self.end
+    }
+
+    /// Extends the start of the packet by len bytes of headroom, returning
+    /// the new bytes, e.g. to write a header.
+    pub fn push(&mut self, len: usize) -> Result<&mut [u8]> {
+        if len > self.headroom() {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        self.start -= len;
+        Ok(&mut self.buffer[self.start .. self.start + len])
+    }
+
+    /// Removes len bytes from the start of the packet, returning them, e.g. to
+    /// strip a header.
+    pub fn pull(&mut self, len: usize) -> Result<&mut [u8]> {
+        if len > self.len() {
+            return Err(Error::Buffer(BufferError::Empty));
+        }
+
+        self.start += len;
+        Ok(&mut self.buffer[self.start - len .. self.start])
+    }
+
+    /// Extends the end of the packet by len bytes of tailroom, returning the
+    /// new bytes, e.g. to write a trailer.
+    pub fn put(&mut self, len: usize) -> Result<&mut [u8]> {
+        if len > self.tailroom() {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        self.end += len;
+        Ok(&mut self.buffer[self.end - len .. self.end])
+    }
+
+    /// Shortens the packet to len bytes, returning the removed bytes to the
+    /// tailroom. Has no effect if the packet is already shorter.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            self.end = self.start + len;
+        }
+    }
+
+    /// Returns the underlying buffer, including headroom and tailroom, e.g. to
+    /// give it back to a Pool.
+    pub fn into_vec(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_and_put() {
+        let mut packet = PacketBuf::new(4, 2, 1);
+        packet.copy_from_slice(&[2, 3]);
+        assert_eq!((packet.headroom(), packet.tailroom()), (4, 1));
+
+        packet.push(2).unwrap().copy_from_slice(&[0, 1]);
+        packet.put(1).unwrap()[0] = 4;
+        assert_eq!(&packet[..], &[0, 1, 2, 3, 4]);
+        assert_eq!((packet.headroom(), packet.tailroom()), (2, 0));
+
+        assert_matches!(packet.push(3), Err(Error::Buffer(BufferError::Full)));
+        assert_matches!(packet.put(1), Err(Error::Buffer(BufferError::Full)));
+        assert_eq!(packet.len(), 5);
+    }
+
+    #[test]
+    fn test_pull_and_truncate() {
+        let mut packet = PacketBuf::from(vec![0, 1, 2, 3, 4]);
+        assert_eq!(packet.pull(2).unwrap(), &[0, 1]);
+        packet.truncate(2);
+        assert_eq!(&packet[..], &[2, 3]);
+        assert_eq!((packet.headroom(), packet.tailroom()), (2, 1));
+
+        assert_matches!(packet.pull(3), Err(Error::Buffer(BufferError::Empty)));
+        packet.truncate(3);
+        assert_eq!(packet.len(), 2);
+        assert_eq!(packet.into_vec(), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_from_vec_reuses_buffer() {
+        let buffer = vec![1; 64];
+        let ptr = buffer.as_ptr();
+        let packet = PacketBuf::from_vec(buffer, 8, 16, 8);
+        assert_eq!(&packet[..], &[0; 16][..]);
+        let buffer = packet.into_vec();
+        assert_eq!(buffer.as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,76 @@
+use alloc::vec::Vec;
+
+/// Pool of byte buffers which are reused rather than allocated per frame.
+///
+/// Buffers keep their capacity while in the pool, so once the pool holds
+/// buffers as large as the largest frames sent and received, taking a buffer
+/// does not allocate.
+#[derive(Clone, Debug, Default)]
+pub struct Pool {
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Pool {
+    /// Creates an empty pool.
+    pub fn new() -> Pool {
+        Pool::default()
+    }
+
+    /// Creates a pool holding count buffers with capacity for buffer_len bytes
+    /// each, e.g. to avoid allocating when the first frames are sent.
+    pub fn with_buffers(count: usize, buffer_len: usize) -> Pool {
+        Pool {
+            buffers: (0 .. count)
+                .map(|_| Vec::with_capacity(buffer_len))
+                .collect(),
+        }
+    }
+
+    /// Takes a zeroed buffer of buffer_len bytes from the pool, allocating a
+    /// new buffer if the pool is empty.
+    pub fn take(&mut self, buffer_len: usize) -> Vec<u8> {
+        let mut buffer = self.buffers.pop().unwrap_or_default();
+        buffer.clear();
+        buffer.resize(buffer_len, 0);
+        buffer
+    }
+
+    /// Returns a buffer to the pool for reuse.
+    pub fn give(&mut self, buffer: Vec<u8>) {
+        self.buffers.push(buffer);
+    }
+
+    /// Returns the number of buffers in the pool.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Checks if the pool holds no buffers.
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_reuses_buffers() {
+        let mut pool = Pool::with_buffers(1, 64);
+        assert_eq!(pool.len(), 1);
+
+        let mut buffer = pool.take(32);
+        assert!(pool.is_empty());
+        assert_eq!(buffer, vec![0; 32]);
+        buffer[0] = 1;
+        let ptr = buffer.as_ptr();
+        pool.give(buffer);
+
+        let buffer = pool.take(64);
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert_eq!(buffer, vec![0; 64]);
+
+        assert_eq!(pool.take(16), vec![0; 16]);
+    }
+}
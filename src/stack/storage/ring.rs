@@ -0,0 +1,348 @@
+use alloc::vec::Vec;
+use core::cmp::min;
+
+use stack::storage::Managed;
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Ring/bounded buffer of T's.
+#[derive(Clone, Debug)]
+pub struct Ring<T: 'static> {
+    buffer: Managed<T>,
+    begin: usize,
+    len: usize,
+}
+
+impl<T> From<Vec<T>> for Ring<T> {
+    fn from(buffer: Vec<T>) -> Ring<T> {
+        Ring::from(Managed::from(buffer))
+    }
+}
+
+impl<T> From<&'static mut [T]> for Ring<T> {
+    fn from(buffer: &'static mut [T]) -> Ring<T> {
+        Ring::from(Managed::from(buffer))
+    }
+}
+
+impl<T> From<Managed<T>> for Ring<T> {
+    fn from(buffer: Managed<T>) -> Ring<T> {
+        Ring {
+            buffer,
+            begin: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<T> Ring<T> {
+    /// Applies f on the head of the buffer or returns an error if the buffer
+    /// is empty. Dequeue's the element f was applied on.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    pub fn dequeue_with<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut T) -> R,
+    {
+        self.dequeue_maybe(|x| Ok(f(x)))
+    }
+
+    /// Similar to dequeue_with(...) except cancels the dequeue operation if
+    /// f returns an error.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    pub fn dequeue_maybe<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut T) -> Result<R>,
+    {
+        if self.len == 0 {
+            return Err(Error::Buffer(BufferError::Empty));
+        }
+
+        let buffer_len = self.buffer.len();
+
+        match f(&mut self.buffer[self.begin]) {
+            Err(err) => Err(err),
+            Ok(res) => {
+                self.begin = (self.begin + 1) % buffer_len;
+                self.len -= 1;
+                Ok(res)
+            }
+        }
+    }
+
+    /// Applies f on the head of the buffer (so that f can mutate the T as
+    /// desired) or returns an error if the buffer is full. Enqueue's the
+    /// element f was applied on.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    pub fn enqueue_with<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut T) -> R,
+    {
+        self.enqueue_maybe(|x| Ok(f(x)))
+    }
+
+    /// Similar to dequeue_with(...) except cancels the enqueue operation if
+    /// f returns an error.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    pub fn enqueue_maybe<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut T) -> Result<R>,
+    {
+        if self.len == self.buffer.len() {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        let idx = (self.begin + self.len) % self.buffer.len();
+
+        match f(&mut self.buffer[idx]) {
+            Err(err) => Err(err),
+            Ok(res) => {
+                self.len += 1;
+                Ok(res)
+            }
+        }
+    }
+
+    /// Applies f on the longest contiguous run of elements at the head of the
+    /// buffer or returns an error if the buffer is empty. f returns how many
+    /// elements from the start of the run to dequeue along with a result, so
+    /// several elements can be dequeued with a single call.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if f returns a count larger than the run it was applied
+    /// on!
+    pub fn dequeue_many_with<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut [T]) -> (usize, R),
+    {
+        if self.len == 0 {
+            return Err(Error::Buffer(BufferError::Empty));
+        }
+
+        let buffer_len = self.buffer.len();
+        let run_len = min(self.len, buffer_len - self.begin);
+
+        let (count, res) = f(&mut self.buffer[self.begin .. self.begin + run_len]);
+        assert!(count <= run_len);
+        self.begin = (self.begin + count) % buffer_len;
+        self.len -= count;
+        Ok(res)
+    }
+
+    /// Applies f on the longest contiguous run of free elements at the tail
+    /// of the buffer or returns an error if the buffer is full. f returns how
+    /// many elements from the start of the run to enqueue along with a
+    /// result, so several elements can be enqueued with a single call.
+    ///
+    /// # Returns
+    ///
+    /// An error or the result of f.
+    ///
+    /// # Panics
+    ///
+    /// Causes a panic if f returns a count larger than the run it was applied
+    /// on!
+    pub fn enqueue_many_with<'a, F, R>(&'a mut self, f: F) -> Result<R>
+    where
+        F: FnOnce(&'a mut [T]) -> (usize, R),
+    {
+        let buffer_len = self.buffer.len();
+        if self.len == buffer_len {
+            return Err(Error::Buffer(BufferError::Full));
+        }
+
+        let idx = (self.begin + self.len) % buffer_len;
+        let run_len = min(buffer_len - self.len, buffer_len - idx);
+
+        let (count, res) = f(&mut self.buffer[idx .. idx + run_len]);
+        assert!(count <= run_len);
+        self.len += count;
+        Ok(res)
+    }
+
+    /// Applies f on each element of the buffer which is not enqueued, e.g. to
+    /// release storage held by elements which are not in use.
+    pub fn for_each_free<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut T),
+    {
+        let buffer_len = self.buffer.len();
+        for i in self.len .. buffer_len {
+            f(&mut self.buffer[(self.begin + i) % buffer_len]);
+        }
+    }
+
+    /// Returns the current number of items in the ring.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns the maximum number of items the ring can hold.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dequeue_when_empty() {
+        let mut ring = Ring::from(vec![0; 1]);
+        assert_eq!(ring.len(), 0);
+        assert_eq!(ring.capacity(), 1);
+        assert_matches!(
+            ring.dequeue_with(|_| {}),
+            Err(Error::Buffer(BufferError::Empty))
+        );
+    }
+
+    #[test]
+    fn test_dequeue_maybe_with_error() {
+        let mut ring = Ring::from(vec![0; 1]);
+        assert_eq!(ring.len(), 0);
+        ring.enqueue_with(|i| *i = 1).unwrap();
+        assert_eq!(ring.len(), 1);
+        assert_matches!(
+            ring.dequeue_maybe(|i| if *i == 1 { Err(Error::Ignored) } else { Ok(()) }),
+            Err(Error::Ignored)
+        );
+        assert_eq!(ring.len(), 1);
+    }
+
+    #[test]
+    fn test_enqueue_when_full() {
+        let mut ring = Ring::from(vec![0; 1]);
+        assert_eq!(ring.len(), 0);
+        assert_matches!(ring.enqueue_with(|_| {}), Ok(()));
+        assert_eq!(ring.len(), 1);
+        assert_matches!(
+            ring.enqueue_with(|_| {}),
+            Err(Error::Buffer(BufferError::Full))
+        );
+        assert_eq!(ring.len(), 1);
+    }
+
+    #[test]
+    fn test_enqueue_maybe_with_error() {
+        let mut ring = Ring::from(vec![0; 1]);
+        assert_eq!(ring.len(), 0);
+        assert_matches!(
+            ring.enqueue_maybe(|i| if *i == 0 { Err(Error::Ignored) } else { Ok(()) }),
+            Err(Error::Ignored)
+        );
+        assert_eq!(ring.len(), 0);
+        assert_matches!(
+            ring.dequeue_with(|_| {}),
+            Err(Error::Buffer(BufferError::Empty))
+        );
+    }
+
+    #[test]
+    fn test_enqueue_and_dequeue_static() {
+        let buffer: &'static mut [u32] = Box::leak(Box::new([0; 2]));
+        let mut ring = Ring::from(buffer);
+        assert_eq!(ring.capacity(), 2);
+        assert_matches!(ring.enqueue_with(|i| *i = 1), Ok(()));
+        assert_matches!(ring.enqueue_with(|i| *i = 2), Ok(()));
+        assert_matches!(
+            ring.enqueue_with(|_| {}),
+            Err(Error::Buffer(BufferError::Full))
+        );
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 1);
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_enqueue_and_dequeue() {
+        let mut ring = Ring::from(vec![0; 4]);
+        assert_matches!(ring.enqueue_with(|i| *i = 1), Ok(()));
+        assert_matches!(ring.enqueue_with(|i| *i = 2), Ok(()));
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 1);
+        assert_matches!(ring.enqueue_with(|i| *i = 3), Ok(()));
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 2);
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 3);
+        assert_matches!(
+            ring.dequeue_with(|_| {}),
+            Err(Error::Buffer(BufferError::Empty))
+        );
+    }
+
+    #[test]
+    fn test_enqueue_many_and_dequeue_many() {
+        let mut ring = Ring::from(vec![0; 4]);
+        assert_matches!(ring.enqueue_with(|i| *i = 1), Ok(()));
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 1);
+
+        // The free run stops at the end of the buffer.
+        let enqueued = ring.enqueue_many_with(|items| {
+            assert_eq!(items.len(), 3);
+            items[0] = 2;
+            items[1] = 3;
+            (2, ())
+        });
+        assert_matches!(enqueued, Ok(()));
+        assert_eq!(ring.len(), 2);
+        let enqueued = ring.enqueue_many_with(|items| {
+            assert_eq!(items.len(), 1);
+            items[0] = 4;
+            (1, ())
+        });
+        assert_matches!(enqueued, Ok(()));
+        let enqueued = ring.enqueue_many_with(|items| {
+            assert_eq!(items.len(), 1);
+            items[0] = 5;
+            (1, ())
+        });
+        assert_matches!(enqueued, Ok(()));
+        assert_matches!(
+            ring.enqueue_many_with(|_| (0, ())),
+            Err(Error::Buffer(BufferError::Full))
+        );
+
+        // The occupied run also stops at the end of the buffer.
+        let dequeued = ring.dequeue_many_with(|items| (items.len(), items.to_vec()));
+        assert_eq!(dequeued.unwrap(), vec![2, 3, 4]);
+        let dequeued = ring.dequeue_many_with(|items| (0, items.to_vec()));
+        assert_eq!(dequeued.unwrap(), vec![5]);
+        assert_eq!(ring.len(), 1);
+        let dequeued = ring.dequeue_many_with(|items| (1, items[0]));
+        assert_eq!(dequeued.unwrap(), 5);
+        assert_matches!(
+            ring.dequeue_many_with(|_| (0, ())),
+            Err(Error::Buffer(BufferError::Empty))
+        );
+    }
+
+    #[test]
+    fn test_for_each_free() {
+        let mut ring = Ring::from(vec![0; 4]);
+        assert_matches!(ring.enqueue_with(|i| *i = 1), Ok(()));
+        assert_matches!(ring.enqueue_with(|i| *i = 2), Ok(()));
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 1);
+        ring.for_each_free(|i| *i = 9);
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 2);
+        assert_matches!(ring.enqueue_with(|_| {}), Ok(()));
+        assert_eq!(ring.dequeue_with(|i| *i).unwrap(), 9);
+    }
+}
@@ -0,0 +1,209 @@
+use alloc::vec::Vec;
+use core::cmp::{
+    max,
+    min,
+};
+use core::ops::{
+    Deref,
+    DerefMut,
+};
+
+use stack::storage::Managed;
+use {
+    BufferError,
+    Error,
+    Result,
+};
+
+/// Owned slice which acts a resizable view over a buffer.
+///
+/// By default the slice can not grow past the length of the buffer it was
+/// created from. Slices created via with_max_len(...) instead grow owned
+/// buffers on demand, doubling the buffer on each growth up to a max length.
+#[derive(Clone, Debug)]
+pub struct Slice<T: 'static> {
+    buffer: Managed<T>,
+    len: usize,
+    max_len: usize,
+}
+
+impl<T> From<Vec<T>> for Slice<T> {
+    fn from(buffer: Vec<T>) -> Self {
+        Slice::from(Managed::from(buffer))
+    }
+}
+
+impl<T> From<&'static mut [T]> for Slice<T> {
+    fn from(buffer: &'static mut [T]) -> Self {
+        Slice::from(Managed::from(buffer))
+    }
+}
+
+impl<T> From<Managed<T>> for Slice<T> {
+    fn from(buffer: Managed<T>) -> Self {
+        let len = buffer.len();
+        Slice {
+            buffer,
+            len,
+            max_len: len,
+        }
+    }
+}
+
+impl<T> Deref for Slice<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.buffer[0 .. self.len]
+    }
+}
+
+impl<T> DerefMut for Slice<T> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        &mut self.buffer[0 .. self.len]
+    }
+}
+
+impl<T> Slice<T> {
+    /// Creates a slice over an owned buffer which grows on demand to hold up
+    /// to max_len elements.
+    pub fn with_max_len(buffer: Vec<T>, max_len: usize) -> Slice<T> {
+        let mut slice = Slice::from(buffer);
+        slice.max_len = max(slice.len, max_len);
+        slice
+    }
+
+    /// Returns the maximum length the slice can be resized to.
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Returns the length the slice can be resized to without growing the
+    /// underlying buffer.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Shrinks an owned buffer which has grown past buffer_len elements, e.g.
+    /// once a burst of large packets is over, without truncating the slice.
+    pub fn shrink_to(&mut self, buffer_len: usize) {
+        let buffer_len = max(self.len, buffer_len);
+        if let Managed::Owned(ref mut buffer) = self.buffer {
+            if buffer.len() > buffer_len {
+                buffer.truncate(buffer_len);
+                buffer.shrink_to_fit();
+            }
+        }
+    }
+}
+
+impl<T: Clone> Slice<T> {
+    /// Attempts to resize the slice, assigning fresh values to the tail end
+    /// of the buffer in an upsizing operation.
+    ///
+    /// Fails with BufferError::TooSmall if buffer_len exceeds max_len(), or
+    /// the buffer is static and can not grow.
+    pub fn try_resize(&mut self, buffer_len: usize, value: T) -> Result<()> {
+        if buffer_len > self.buffer.len() {
+            self.grow(buffer_len, value.clone())?;
+        }
+
+        for i in self.len .. buffer_len {
+            self.buffer[i] = value.clone();
+        }
+        self.len = buffer_len;
+        Ok(())
+    }
+
+    fn grow(&mut self, buffer_len: usize, value: T) -> Result<()> {
+        if buffer_len > self.max_len {
+            return Err(Error::Buffer(BufferError::TooSmall));
+        }
+
+        match self.buffer {
+            Managed::Owned(ref mut buffer) => {
+                // Double the buffer to amortize the cost of growing it.
+                let grow_len = min(max(buffer_len, 2 * buffer.len()), self.max_len);
+                buffer.resize(grow_len, value);
+                Ok(())
+            }
+            Managed::Static(_) => Err(Error::Buffer(BufferError::TooSmall)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_too_big() {
+        let mut slice = Slice::from(vec![0, 1, 2, 3]);
+        assert_eq!(&slice[..], &[0, 1, 2, 3]);
+        assert_matches!(
+            slice.try_resize(8, 0),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+        assert_eq!(&slice[..], &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_resize_static() {
+        let buffer: &'static mut [u8] = Box::leak(Box::new([0, 1, 2, 3]));
+        let mut slice = Slice::from(buffer);
+        assert_matches!(slice.try_resize(2, 0), Ok(_));
+        assert_eq!(&slice[..], &[0, 1]);
+        assert_matches!(slice.try_resize(4, 9), Ok(_));
+        assert_eq!(&slice[..], &[0, 1, 9, 9]);
+        assert_matches!(
+            slice.try_resize(5, 0),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+    }
+
+    #[test]
+    fn test_resize_with_capacity() {
+        let mut slice = Slice::from(vec![0, 1, 2, 3]);
+        assert_eq!(&slice[..], &[0, 1, 2, 3]);
+        assert_matches!(slice.try_resize(0, 0), Ok(_));
+        assert_eq!(&slice[..], &[]);
+        assert_matches!(slice.try_resize(1, 0), Ok(_));
+        assert_eq!(&slice[..], &[0]);
+        assert_matches!(slice.try_resize(2, 0), Ok(_));
+        assert_eq!(&slice[..], &[0, 0]);
+        assert_matches!(slice.try_resize(3, 0), Ok(_));
+        assert_eq!(&slice[..], &[0, 0, 0]);
+        assert_matches!(slice.try_resize(4, 0), Ok(_));
+        assert_eq!(&slice[..], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_resize_grows_up_to_max_len() {
+        let mut slice = Slice::with_max_len(vec![0, 1], 6);
+        assert_eq!(slice.max_len(), 6);
+        assert_matches!(slice.try_resize(3, 9), Ok(_));
+        assert_eq!(&slice[..], &[0, 1, 9]);
+        assert_eq!(slice.capacity(), 4);
+        assert_matches!(slice.try_resize(5, 8), Ok(_));
+        assert_eq!(&slice[..], &[0, 1, 9, 8, 8]);
+        assert_eq!(slice.capacity(), 6);
+        assert_matches!(
+            slice.try_resize(7, 0),
+            Err(Error::Buffer(BufferError::TooSmall))
+        );
+        assert_eq!(&slice[..], &[0, 1, 9, 8, 8]);
+    }
+
+    #[test]
+    fn test_shrink_to() {
+        let mut slice = Slice::with_max_len(vec![0; 2], 8);
+        assert_matches!(slice.try_resize(8, 0), Ok(_));
+        slice.shrink_to(2);
+        assert_eq!(slice.capacity(), 8);
+
+        assert_matches!(slice.try_resize(1, 0), Ok(_));
+        slice.shrink_to(2);
+        assert_eq!(slice.capacity(), 2);
+        assert_eq!(&slice[..], &[0]);
+    }
+}
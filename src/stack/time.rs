@@ -0,0 +1,484 @@
+//! Abstractions for providing the current time and tracking deadlines.
+//!
+//! Timers do not read the clock themselves, instead taking the Env of their
+//! owner whenever the current time is needed, so MockEnv tests control when
+//! they expire.
+//!
+//! The stack measures time with its own Instant rather than
+//! std::time::Instant, which can only be read from the OS clock. Targets
+//! without an OS create Instants from whatever clock they have, e.g. a tick
+//! counter, via Instant::from_millis(...) in their Env, while Instant::now()
+//! and SystemEnv require the std feature.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::fmt::{
+    Debug,
+    Display,
+    Formatter,
+    Result as FmtResult,
+};
+use core::ops::{
+    Add,
+    AddAssign,
+    Sub,
+    SubAssign,
+};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+#[cfg(feature = "std")]
+use std::time::{
+    Instant as StdInstant,
+    SystemTime,
+    UNIX_EPOCH,
+};
+
+/// A point in time, measured in microseconds since an arbitrary epoch.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant {
+    micros: u64,
+}
+
+// Pairs the monotonic clock with the system clock once so Instant::now() counts
+// from the UNIX epoch without ever going backwards.
+#[cfg(feature = "std")]
+static CLOCK_ANCHOR: OnceLock<(StdInstant, Instant)> = OnceLock::new();
+
+impl Instant {
+    /// Creates an instant from microseconds since the epoch.
+    pub fn from_micros(micros: u64) -> Instant {
+        Instant { micros }
+    }
+
+    /// Creates an instant from milliseconds since the epoch.
+    pub fn from_millis(millis: u64) -> Instant {
+        Instant::from_micros(millis * 1_000)
+    }
+
+    /// Creates an instant from seconds since the epoch.
+    pub fn from_secs(secs: u64) -> Instant {
+        Instant::from_micros(secs * 1_000_000)
+    }
+
+    /// Returns an instant corresponding to "now" according to the system
+    /// clock.
+    #[cfg(feature = "std")]
+    pub fn now() -> Instant {
+        let &(anchor, instant) = CLOCK_ANCHOR.get_or_init(|| {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            (StdInstant::now(), Instant::from_micros(0) + since_epoch)
+        });
+        instant + anchor.elapsed()
+    }
+
+    /// Returns the microseconds since the epoch.
+    pub fn total_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Returns the milliseconds since the epoch.
+    pub fn total_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    /// Returns the time elapsed from an earlier instant, or zero if the
+    /// instant is in fact later.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.saturating_duration_since(earlier)
+    }
+
+    /// Returns the time elapsed from an earlier instant, or zero if the
+    /// instant is in fact later.
+    pub fn saturating_duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_micros(self.micros.saturating_sub(earlier.micros))
+    }
+
+    /// Returns the time elapsed from an earlier instant, or None if the
+    /// instant is in fact later.
+    pub fn checked_duration_since(&self, earlier: Instant) -> Option<Duration> {
+        self.micros
+            .checked_sub(earlier.micros)
+            .map(Duration::from_micros)
+    }
+
+    /// Returns the instant a duration later, or None on overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<Instant> {
+        self.micros
+            .checked_add(duration_micros(duration))
+            .map(Instant::from_micros)
+    }
+
+    /// Returns the instant a duration earlier, or None if it would precede
+    /// the epoch.
+    pub fn checked_sub(&self, duration: Duration) -> Option<Instant> {
+        self.micros
+            .checked_sub(duration_micros(duration))
+            .map(Instant::from_micros)
+    }
+
+    /// Returns the time elapsed since the instant according to the system
+    /// clock.
+    #[cfg(feature = "std")]
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+fn duration_micros(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000_000 + u64::from(duration.subsec_micros())
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding duration to instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting duration from instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl Display for Instant {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let (secs, micros) = (self.micros / 1_000_000, self.micros % 1_000_000);
+        write!(f, "{}.{:06}s", secs, micros)
+    }
+}
+
+/// When a socket or other part of the stack next needs servicing.
+///
+/// Variants are ordered by urgency, so the earliest of several is their
+/// minimum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PollAt {
+    /// As soon as possible, e.g. packets are enqueued for sending.
+    Now,
+    /// Once an instant has passed, e.g. a timer expires.
+    Time(Instant),
+    /// Only once packets are received.
+    Ingress,
+}
+
+/// An environment that provides the current time.
+pub trait Env: Debug {
+    /// Returns an instance corresponding to "now".
+    fn now_instant(&self) -> Instant;
+}
+
+impl<T: Env + ?Sized> Env for Rc<T> {
+    fn now_instant(&self) -> Instant {
+        (**self).now_instant()
+    }
+}
+
+/// The environment of timers in stack types such as ArpCache which are not
+/// given one, i.e. SystemEnv, or an Env shared by the application without
+/// the std feature since there is no system clock.
+#[cfg(feature = "std")]
+pub type DefaultEnv = SystemEnv;
+#[cfg(not(feature = "std"))]
+pub type DefaultEnv = Rc<dyn Env>;
+
+/// An environment that provides system based time.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct SystemEnv;
+
+#[cfg(feature = "std")]
+impl SystemEnv {
+    pub fn new() -> SystemEnv {
+        SystemEnv {}
+    }
+}
+
+#[cfg(feature = "std")]
+impl Env for SystemEnv {
+    fn now_instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// An environment that provides a configurable time.
+#[derive(Clone, Debug)]
+pub struct MockEnv {
+    pub now: Instant,
+}
+
+impl MockEnv {
+    /// Creates an environment starting at the current time, or at the epoch
+    /// without the std feature.
+    pub fn new() -> MockEnv {
+        #[cfg(feature = "std")]
+        let now = Instant::now();
+        #[cfg(not(feature = "std"))]
+        let now = Instant::default();
+
+        MockEnv { now }
+    }
+}
+
+impl Env for MockEnv {
+    fn now_instant(&self) -> Instant {
+        self.now
+    }
+}
+
+/// A one-shot timer which expires at a deadline once started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Timer {
+    deadline: Option<Instant>,
+}
+
+impl Timer {
+    /// Creates a timer which is not running.
+    pub fn new() -> Timer {
+        Timer { deadline: None }
+    }
+
+    /// Starts (or restarts) the timer to expire after a timeout.
+    pub fn start<T: Env + ?Sized>(&mut self, time_env: &T, timeout: Duration) {
+        self.deadline = Some(time_env.now_instant() + timeout);
+    }
+
+    /// Stops the timer.
+    pub fn stop(&mut self) {
+        self.deadline = None;
+    }
+
+    /// Checks if the timer has been started and not stopped.
+    pub fn is_running(&self) -> bool {
+        self.deadline.is_some()
+    }
+
+    /// Checks if the timer is running and its deadline has passed.
+    pub fn is_expired<T: Env + ?Sized>(&self, time_env: &T) -> bool {
+        match self.deadline {
+            Some(deadline) => time_env.now_instant() >= deadline,
+            None => false,
+        }
+    }
+
+    /// Returns the deadline of the timer if it is running.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Returns when the owner of the timer needs servicing for it to expire.
+    pub fn poll_at(&self) -> PollAt {
+        match self.deadline {
+            Some(deadline) => PollAt::Time(deadline),
+            None => PollAt::Ingress,
+        }
+    }
+}
+
+/// A set of timers identified by keys, ordered by deadline so the earliest
+/// deadline and expired timers are found without scanning every timer.
+#[derive(Debug)]
+pub struct TimerQueue<K> {
+    deadlines: BTreeMap<K, (Instant, u64)>,
+    // Sequence numbers order timers with the same deadline by when they were
+    // scheduled.
+    queue: BTreeMap<(Instant, u64), K>,
+    next_seq: u64,
+}
+
+impl<K: Clone + Ord> Default for TimerQueue<K> {
+    fn default() -> TimerQueue<K> {
+        TimerQueue::new()
+    }
+}
+
+impl<K: Clone + Ord> TimerQueue<K> {
+    /// Creates an empty timer queue.
+    pub fn new() -> TimerQueue<K> {
+        TimerQueue {
+            deadlines: BTreeMap::new(),
+            queue: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    /// Schedules (or reschedules) the timer for a key to expire after a
+    /// timeout.
+    pub fn schedule<T: Env + ?Sized>(&mut self, time_env: &T, key: K, timeout: Duration) {
+        self.schedule_at(key, time_env.now_instant() + timeout);
+    }
+
+    /// Schedules (or reschedules) the timer for a key to expire at a deadline.
+    pub fn schedule_at(&mut self, key: K, deadline: Instant) {
+        self.cancel(&key);
+
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.deadlines.insert(key.clone(), (deadline, seq));
+        self.queue.insert((deadline, seq), key);
+    }
+
+    /// Cancels the timer for a key, returning its deadline if it was
+    /// scheduled.
+    pub fn cancel(&mut self, key: &K) -> Option<Instant> {
+        let (deadline, seq) = self.deadlines.remove(key)?;
+        self.queue.remove(&(deadline, seq));
+        Some(deadline)
+    }
+
+    /// Returns the deadline of the timer for a key, if it is scheduled.
+    pub fn deadline(&self, key: &K) -> Option<Instant> {
+        self.deadlines.get(key).map(|&(deadline, _)| deadline)
+    }
+
+    /// Returns the earliest deadline of any timer.
+    pub fn poll_at(&self) -> Option<Instant> {
+        self.queue.keys().next().map(|&(deadline, _)| deadline)
+    }
+
+    /// Removes the timers whose deadlines have passed and returns their keys,
+    /// earliest deadline first.
+    pub fn expire<T: Env + ?Sized>(&mut self, time_env: &T) -> Vec<K> {
+        let now = time_env.now_instant();
+        let mut expired = Vec::new();
+
+        while let Some((&(deadline, seq), _)) = self.queue.iter().next() {
+            if deadline > now {
+                break;
+            }
+
+            let key = self.queue.remove(&(deadline, seq)).unwrap();
+            self.deadlines.remove(&key);
+            expired.push(key);
+        }
+
+        expired
+    }
+
+    /// Returns the number of scheduled timers.
+    pub fn len(&self) -> usize {
+        self.deadlines.len()
+    }
+
+    /// Checks if no timers are scheduled.
+    pub fn is_empty(&self) -> bool {
+        self.deadlines.is_empty()
+    }
+
+    /// Cancels all timers.
+    pub fn clear(&mut self) {
+        self.deadlines.clear();
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instant_arithmetic() {
+        let instant = Instant::from_millis(1_500);
+        assert_eq!(instant.total_micros(), 1_500_000);
+        assert_eq!(instant + Duration::from_millis(500), Instant::from_secs(2));
+        assert_eq!(instant - Duration::from_secs(1), Instant::from_millis(500));
+        assert_eq!(Instant::from_secs(2) - instant, Duration::from_millis(500));
+        assert_eq!(instant - Instant::from_secs(2), Duration::from_secs(0));
+        assert_eq!(instant.checked_sub(Duration::from_secs(2)), None);
+        assert_eq!(instant.to_string(), "1.500000s");
+    }
+
+    #[test]
+    fn test_timer() {
+        let mut time_env = MockEnv::new();
+        let mut timer = Timer::new();
+        assert!(!timer.is_running());
+        assert!(!timer.is_expired(&time_env));
+        assert_eq!(timer.poll_at(), PollAt::Ingress);
+
+        timer.start(&time_env, Duration::from_secs(1));
+        assert_eq!(timer.poll_at(), PollAt::Time(time_env.now + Duration::from_secs(1)));
+        time_env.now += Duration::from_millis(999);
+        assert!(!timer.is_expired(&time_env));
+        time_env.now += Duration::from_millis(1);
+        assert!(timer.is_expired(&time_env));
+
+        timer.stop();
+        assert!(!timer.is_expired(&time_env));
+        assert_eq!(timer.deadline(), None);
+    }
+
+    #[test]
+    fn test_timer_queue_expires_in_order() {
+        let mut time_env = MockEnv::new();
+        let mut timers = TimerQueue::new();
+        let start = time_env.now;
+
+        timers.schedule(&time_env, 'c', Duration::from_secs(3));
+        timers.schedule(&time_env, 'a', Duration::from_secs(1));
+        timers.schedule(&time_env, 'b', Duration::from_secs(1));
+        assert_eq!(timers.len(), 3);
+        assert_eq!(timers.poll_at(), Some(start + Duration::from_secs(1)));
+        assert_eq!(timers.expire(&time_env), vec![]);
+
+        time_env.now += Duration::from_secs(2);
+        assert_eq!(timers.expire(&time_env), vec!['a', 'b']);
+        assert_eq!(timers.poll_at(), Some(start + Duration::from_secs(3)));
+
+        time_env.now += Duration::from_secs(1);
+        assert_eq!(timers.expire(&time_env), vec!['c']);
+        assert!(timers.is_empty());
+        assert_eq!(timers.poll_at(), None);
+    }
+
+    #[test]
+    fn test_timer_queue_reschedule_and_cancel() {
+        let mut time_env = MockEnv::new();
+        let mut timers = TimerQueue::new();
+        let start = time_env.now;
+
+        timers.schedule(&time_env, 1, Duration::from_secs(1));
+        timers.schedule(&time_env, 2, Duration::from_secs(2));
+        timers.schedule(&time_env, 1, Duration::from_secs(5));
+        assert_eq!(timers.len(), 2);
+        assert_eq!(timers.deadline(&1), Some(start + Duration::from_secs(5)));
+        assert_eq!(timers.poll_at(), Some(start + Duration::from_secs(2)));
+
+        assert_eq!(timers.cancel(&2), Some(start + Duration::from_secs(2)));
+        assert_eq!(timers.cancel(&2), None);
+
+        time_env.now += Duration::from_secs(4);
+        assert_eq!(timers.expire(&time_env), vec![]);
+        time_env.now += Duration::from_secs(1);
+        assert_eq!(timers.expire(&time_env), vec![1]);
+    }
+}
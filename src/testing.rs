@@ -0,0 +1,236 @@
+//! Generators for random but valid headers, and helpers to serialize and
+//! reparse them, for testing code built on top of usrnet.
+//!
+//! Generators take any rand::Rng, see rng() for a seeded one so failures
+//! can be reproduced.
+
+use alloc::vec::Vec;
+
+use rand::{
+    Rng,
+    SeedableRng,
+    XorShiftRng,
+};
+
+use stack::repr::{
+    ipv4_flags,
+    Arp,
+    ArpOp,
+    EthernetAddress,
+    Ipv4Address,
+    Ipv4Option,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    TcpPacket,
+    TcpRepr,
+    UdpPacket,
+    UdpRepr,
+};
+use Result;
+
+/// Maximum payload length generated for packets, keeping them within a
+/// standard Ethernet MTU.
+pub const MAX_PAYLOAD_LEN: usize = 1400;
+
+/// Creates a random number generator from a seed.
+pub fn rng(seed: u32) -> XorShiftRng {
+    // XorShiftRng panics on an all zero seed.
+    XorShiftRng::from_seed([seed, 0x9E37_79B9, 0x7F4A_7C15, 0xF39C_C060])
+}
+
+/// Generates random payload bytes of a random length up to max_len.
+pub fn payload<R: Rng>(rng: &mut R, max_len: usize) -> Vec<u8> {
+    let len = rng.gen_range(0, max_len + 1);
+    rng.gen_iter().take(len).collect()
+}
+
+pub fn eth_addr<R: Rng>(rng: &mut R) -> EthernetAddress {
+    EthernetAddress::new(rng.gen())
+}
+
+pub fn ipv4_addr<R: Rng>(rng: &mut R) -> Ipv4Address {
+    Ipv4Address::new(rng.gen())
+}
+
+/// Generates IPv4 options padded with NoOps to a multiple of 32 bits, so they
+/// reparse as the same options.
+pub fn ipv4_options<R: Rng>(rng: &mut R) -> Ipv4Options {
+    let mut options = Ipv4Options::new();
+
+    if rng.gen() {
+        options.push(&Ipv4Option::RouterAlert(0)).unwrap();
+    }
+
+    if rng.gen() {
+        let route = [0; 32];
+        let slots = rng.gen_range(1, 9);
+        options
+            .push(&Ipv4Option::RecordRoute {
+                pointer: 4,
+                route: &route[.. slots * 4],
+            })
+            .unwrap();
+    }
+
+    while options.as_bytes().len() != options.padded_len() {
+        options.push(&Ipv4Option::NoOp).unwrap();
+    }
+
+    options
+}
+
+/// Generates an IPv4 header for a payload of some protocol and length.
+pub fn ipv4_repr<R: Rng>(rng: &mut R, protocol: Ipv4Protocol, payload_len: usize) -> Ipv4Repr {
+    Ipv4Repr {
+        src_addr: ipv4_addr(rng),
+        dst_addr: ipv4_addr(rng),
+        protocol,
+        payload_len: payload_len as u16,
+        ttl: rng.gen_range(1, 256) as u8,
+        dscp: rng.gen_range(0, 64),
+        flags: if rng.gen() {
+            ipv4_flags::DONT_FRAGMENT
+        } else {
+            0
+        },
+        options: ipv4_options(rng),
+    }
+}
+
+pub fn tcp_repr<R: Rng>(rng: &mut R) -> TcpRepr {
+    let mut flags = [false; 9];
+    for flag in flags.iter_mut() {
+        *flag = rng.gen();
+    }
+
+    TcpRepr {
+        src_port: rng.gen(),
+        dst_port: rng.gen(),
+        seq_num: rng.gen(),
+        ack_num: rng.gen(),
+        flags,
+        window_size: rng.gen(),
+        urgent_pointer: rng.gen(),
+        max_segment_size: if rng.gen() { Some(rng.gen()) } else { None },
+    }
+}
+
+/// Generates a UDP header for a payload of some length.
+pub fn udp_repr<R: Rng>(rng: &mut R, payload_len: usize) -> UdpRepr {
+    UdpRepr {
+        src_port: rng.gen(),
+        dst_port: rng.gen(),
+        length: (UdpPacket::<&[u8]>::buffer_len(payload_len)) as u16,
+        checksum: rng.gen(),
+    }
+}
+
+pub fn arp<R: Rng>(rng: &mut R) -> Arp {
+    Arp {
+        op: if rng.gen() {
+            ArpOp::Request
+        } else {
+            ArpOp::Reply
+        },
+        source_hw_addr: eth_addr(rng),
+        source_proto_addr: ipv4_addr(rng),
+        target_hw_addr: eth_addr(rng),
+        target_proto_addr: ipv4_addr(rng),
+    }
+}
+
+/// Serializes an IPv4 packet, with the header's payload length set to the
+/// payload's.
+pub fn ipv4_packet(ipv4_repr: &Ipv4Repr, payload: &[u8]) -> Vec<u8> {
+    let ipv4_repr = Ipv4Repr {
+        payload_len: payload.len() as u16,
+        ..*ipv4_repr
+    };
+
+    let mut buffer = vec![0; ipv4_repr.buffer_len()];
+    {
+        let mut ipv4_packet = Ipv4Packet::try_new(&mut buffer[..]).unwrap();
+        ipv4_repr.serialize(&mut ipv4_packet);
+        ipv4_packet.payload_mut().copy_from_slice(payload);
+    }
+    buffer
+}
+
+/// Serializes a TCP segment with a checksum over the pseudo header of an IPv4
+/// header, which is adjusted to carry the segment.
+pub fn tcp_packet(tcp_repr: &TcpRepr, payload: &[u8], ipv4_repr: &Ipv4Repr) -> Vec<u8> {
+    let ipv4_repr = transport_ipv4_repr(ipv4_repr, Ipv4Protocol::TCP, tcp_repr.header_len() + payload.len());
+
+    let mut buffer = vec![0; ipv4_repr.payload_len as usize];
+    {
+        let mut tcp_packet = TcpPacket::try_new(&mut buffer[..]).unwrap();
+        tcp_repr.serialize(&mut tcp_packet).unwrap();
+        tcp_packet.payload_mut().copy_from_slice(payload);
+        tcp_packet.fill_checksum(&ipv4_repr);
+    }
+    buffer
+}
+
+/// Serializes a UDP packet with a checksum over the pseudo header of an IPv4
+/// header, which is adjusted to carry the packet.
+pub fn udp_packet(udp_repr: &UdpRepr, payload: &[u8], ipv4_repr: &Ipv4Repr) -> Vec<u8> {
+    let ipv4_repr = transport_ipv4_repr(ipv4_repr, Ipv4Protocol::UDP, udp_repr.buffer_len());
+
+    let mut buffer = vec![0; udp_repr.buffer_len()];
+    {
+        let mut udp_packet = UdpPacket::try_new(&mut buffer[..]).unwrap();
+        udp_packet.payload_mut().copy_from_slice(payload);
+        udp_repr.serialize(&mut udp_packet, &ipv4_repr);
+    }
+    buffer
+}
+
+pub fn arp_packet(arp: &Arp) -> Vec<u8> {
+    let mut buffer = vec![0; arp.buffer_len()];
+    arp.serialize(&mut buffer).unwrap();
+    buffer
+}
+
+/// Serializes and reparses an IPv4 packet, checking its encoding, and returns
+/// the reparsed header and payload.
+pub fn reparse_ipv4(ipv4_repr: &Ipv4Repr, payload: &[u8]) -> Result<(Ipv4Repr, Vec<u8>)> {
+    let buffer = ipv4_packet(ipv4_repr, payload);
+    let ipv4_packet = Ipv4Packet::try_new(&buffer[..])?;
+    ipv4_packet.check_encoding()?;
+    Ok((Ipv4Repr::deserialize(&ipv4_packet)?, ipv4_packet.payload().to_vec()))
+}
+
+/// Serializes and reparses a TCP segment, checking its encoding, and returns
+/// the reparsed header and payload.
+pub fn reparse_tcp(tcp_repr: &TcpRepr, payload: &[u8], ipv4_repr: &Ipv4Repr) -> Result<(TcpRepr, Vec<u8>)> {
+    let buffer = tcp_packet(tcp_repr, payload, ipv4_repr);
+    let ipv4_repr = transport_ipv4_repr(ipv4_repr, Ipv4Protocol::TCP, buffer.len());
+    let tcp_packet = TcpPacket::try_new(&buffer[..])?;
+    tcp_packet.check_encoding(&ipv4_repr)?;
+    Ok((TcpRepr::deserialize(&tcp_packet), tcp_packet.payload().to_vec()))
+}
+
+/// Serializes and reparses a UDP packet, checking its encoding, and returns
+/// the reparsed header and payload.
+pub fn reparse_udp(udp_repr: &UdpRepr, payload: &[u8], ipv4_repr: &Ipv4Repr) -> Result<(UdpRepr, Vec<u8>)> {
+    let buffer = udp_packet(udp_repr, payload, ipv4_repr);
+    let ipv4_repr = transport_ipv4_repr(ipv4_repr, Ipv4Protocol::UDP, buffer.len());
+    let udp_packet = UdpPacket::try_new(&buffer[..])?;
+    udp_packet.check_encoding(&ipv4_repr)?;
+    Ok((UdpRepr::deserialize(&udp_packet), udp_packet.payload().to_vec()))
+}
+
+/// Serializes and reparses an ARP message.
+pub fn reparse_arp(arp: &Arp) -> Result<Arp> {
+    Arp::deserialize(&arp_packet(arp))
+}
+
+fn transport_ipv4_repr(ipv4_repr: &Ipv4Repr, protocol: Ipv4Protocol, payload_len: usize) -> Ipv4Repr {
+    Ipv4Repr {
+        protocol,
+        payload_len: payload_len as u16,
+        ..*ipv4_repr
+    }
+}
@@ -6,17 +6,27 @@ extern crate usrnet;
 
 mod context;
 
+use std::cell::Cell;
+use std::rc::Rc;
 use std::time::Duration;
 
-use usrnet::core::repr::{
+use usrnet::examples::*;
+use usrnet::stack::repr::{
     EthernetAddress,
     Ipv4Address,
+    Ipv4AddressCidr,
+};
+use usrnet::stack::service::arp::{
+    self,
+    AnnounceConfig,
+    ClaimState,
 };
-use usrnet::core::socket::{
+use usrnet::stack::service::socket;
+use usrnet::stack::socket::{
     RawType,
     TaggedSocket,
 };
-use usrnet::examples::*;
+use usrnet::stack::time::SystemEnv;
 
 fn arping_addr(
     context: &mut context::Context,
@@ -51,3 +61,57 @@ fn arping_unknown_ip() {
         assert!(arping_addr(context, Ipv4Address::new([10, 0, 0, 128])).is_none());
     });
 }
+
+lazy_static! {
+    static ref ANNOUNCE_CONFIG: AnnounceConfig = AnnounceConfig {
+        probe_num: 3,
+        probe_interval: Duration::from_millis(200),
+        announce_wait: Duration::from_millis(200),
+        announce_num: 2,
+        announce_interval: Duration::from_millis(200),
+    };
+}
+
+/// Claims the interface address, servicing the interface until the claim
+/// completes.
+fn announce(context: &mut context::Context) -> ClaimState {
+    arp::announce(&mut context.interface, &*ANNOUNCE_CONFIG, SystemEnv::new()).unwrap();
+
+    loop {
+        socket::send(&mut context.interface, &mut context.socket_set);
+        socket::recv(&mut context.interface, &mut context.socket_set);
+
+        match context.interface.arp_claim.as_ref().unwrap().state() {
+            ClaimState::Probing | ClaimState::Announcing => {}
+            state => return state,
+        }
+
+        let delay = context.interface.poll_delay(&context.socket_set);
+        context.interface.poll_wait(delay).unwrap();
+    }
+}
+
+#[test]
+fn arp_announce() {
+    context::run(|context| {
+        assert_eq!(announce(context), ClaimState::Claimed);
+    });
+}
+
+#[test]
+fn arp_announce_conflict() {
+    context::run(|context| {
+        let conflicts = Rc::new(Cell::new(0));
+        let conflicts_ = conflicts.clone();
+        context.interface.arp_conflict_handler = Some(Box::new(move |_, _| {
+            conflicts_.set(conflicts_.get() + 1);
+        }));
+        context.interface.ipv4_addr = Ipv4AddressCidr::new(*env::DEFAULT_IPV4_GATEWAY, 24);
+
+        match announce(context) {
+            ClaimState::Conflict(_) => {}
+            state => panic!("Expected an address conflict, got {:?}.", state),
+        }
+        assert_eq!(conflicts.get(), 1);
+    });
+}
@@ -0,0 +1,2361 @@
+extern crate usrnet;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{
+    self,
+    Read,
+    Write,
+};
+use std::net::Shutdown;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use usrnet::stack::bridge::{
+    Bridge,
+    MacTable,
+    Port,
+};
+use usrnet::stack::capture::{
+    Capture,
+    Direction,
+};
+use usrnet::stack::dev::{
+    ChannelConfig,
+    ChannelDevice,
+    ChecksumCapabilities,
+    Device,
+    DeviceCapabilities,
+    FaultConfig,
+    FaultyDevice,
+};
+use usrnet::stack::repr::{
+    dhcpv4_ports,
+    eth_types,
+    ipv4_protocols,
+    tftp_error_codes,
+    EthernetAddress,
+    EthernetFrame,
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Ipv4Address,
+    Ipv4AddressCidr,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    PacketBuilder,
+    PppoeCode,
+    PppoeDiscoveryRepr,
+    PppoePacket,
+    UdpPacket,
+};
+use usrnet::stack::service::arp::{
+    self,
+    AnnounceConfig,
+    ClaimState,
+};
+use usrnet::stack::service::conntrack::{
+    Conntrack,
+    State as ConntrackState,
+};
+use usrnet::stack::service::dhcpv4::{
+    Dhcpv4Client,
+    State as Dhcpv4State,
+};
+use usrnet::stack::service::filter::{
+    Action,
+    Filter,
+    Rule,
+};
+use usrnet::stack::service::pppoe::{
+    PppoeClient,
+    Session as PppoeSession,
+    State as PppoeState,
+};
+use usrnet::stack::service::shaper::Shaper;
+use usrnet::stack::service::shim::Shim;
+use usrnet::stack::service::{
+    socket,
+    Interface,
+    InterfaceSet,
+};
+use usrnet::stack::socket::bindings::EPHEMERAL_PORT_MIN;
+use usrnet::stack::socket::{
+    RawType,
+    SocketAddr,
+    SocketEnv,
+    SocketEvent,
+    SocketHandle,
+    SocketSet,
+    TaggedSocket,
+    TcpStateKind,
+    UdpSocket,
+    Watermarks,
+};
+use usrnet::stack::storage::PacketBuf;
+use usrnet::stack::time::{
+    MockEnv,
+    SystemEnv,
+};
+use usrnet::examples::*;
+use usrnet::netapi;
+use usrnet::reactor::Reactor;
+use usrnet::{
+    AddressError,
+    Error,
+    ParseError,
+    RemoteError,
+    Result,
+    SocketError,
+};
+
+struct Stack {
+    interface: Interface,
+    socket_set: SocketSet,
+    udp_handle: SocketHandle,
+    socket_addr: SocketAddr,
+}
+
+impl Stack {
+    fn new(dev: ChannelDevice, host: u8) -> Stack {
+        Stack::with_subnet(dev, 0, host)
+    }
+
+    fn with_subnet(dev: ChannelDevice, subnet: u8, host: u8) -> Stack {
+        let mut interface = env::interface(
+            Box::new(dev),
+            EthernetAddress::new([0x06, 0, 0, 0, subnet, host]),
+            Ipv4AddressCidr::new(Ipv4Address::new([10, 0, subnet, host]), 24),
+        );
+        let socket_env = env::socket_env(&mut interface);
+        let mut socket_set = env::socket_set();
+
+        let socket_addr = SocketAddr {
+            addr: *interface.ipv4_addr,
+            port: 4096,
+        };
+        let udp_socket = socket_env.udp_socket(socket_addr).unwrap();
+        let udp_handle = socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        Stack {
+            interface,
+            socket_set,
+            udp_handle,
+            socket_addr,
+        }
+    }
+
+    fn send(&mut self, payload: &[u8], addr: SocketAddr) {
+        self.socket_set
+            .socket(self.udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(payload.len(), addr)
+            .unwrap()
+            .copy_from_slice(payload);
+    }
+
+    fn recv(&mut self) -> Option<(Vec<u8>, SocketAddr)> {
+        self.socket_set
+            .socket(self.udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .recv()
+            .ok()
+            .map(|(payload, addr)| (payload.to_vec(), addr))
+    }
+
+    fn tick(&mut self) {
+        env::tick(&mut self.interface, &mut self.socket_set);
+    }
+
+    fn udp_socket(&mut self) -> &mut UdpSocket {
+        self.socket_set
+            .socket(self.udp_handle)
+            .unwrap()
+            .as_udp_socket()
+    }
+}
+
+/// Ticks both stacks until a receives a packet, or a second passes.
+fn exchange(a: &mut Stack, b: &mut Stack) -> Option<(Vec<u8>, SocketAddr)> {
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < Duration::from_secs(1) {
+        a.tick();
+        b.tick();
+
+        if let Some(packet) = a.recv() {
+            return Some(packet);
+        }
+    }
+
+    None
+}
+
+fn udp_round_trip(config: ChannelConfig) {
+    let (dev_a, dev_b) = ChannelDevice::pair_with_config(config, SystemEnv::new());
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+}
+
+fn udp_round_trip_with(a: &mut Stack, b: &mut Stack) {
+    let b_addr = b.socket_addr;
+    a.send(b"ping", b_addr);
+    let (payload, addr) = exchange(b, a).unwrap();
+    assert_eq!(payload, b"ping");
+    assert_eq!(addr, a.socket_addr);
+
+    b.send(b"pong", addr);
+    let (payload, addr) = exchange(a, b).unwrap();
+    assert_eq!(payload, b"pong");
+    assert_eq!(addr, b_addr);
+}
+
+#[test]
+fn channel_udp_round_trip() {
+    udp_round_trip(ChannelConfig::default());
+}
+
+#[test]
+fn channel_udp_round_trip_with_latency() {
+    udp_round_trip(ChannelConfig {
+        latency: Duration::from_millis(50),
+        ..ChannelConfig::default()
+    });
+}
+
+/// Ticks both stacks while a claims its address, returning the outcome once
+/// the claim completes or a second passes, and any packet a receives while
+/// still probing.
+fn claim(a: &mut Stack, b: &mut Stack) -> (ClaimState, Option<Vec<u8>>) {
+    let config = AnnounceConfig {
+        probe_num: 2,
+        probe_interval: Duration::from_millis(100),
+        announce_wait: Duration::from_millis(100),
+        announce_num: 1,
+        announce_interval: Duration::from_millis(100),
+    };
+    arp::announce(&mut a.interface, &config, SystemEnv::new()).unwrap();
+
+    let start = Instant::now();
+    let mut received = None;
+
+    loop {
+        a.tick();
+        b.tick();
+
+        let state = a.interface.arp_claim.as_ref().unwrap().state();
+        if let Some((payload, _)) = a.recv() {
+            if state == ClaimState::Probing {
+                received = Some(payload);
+            }
+        }
+
+        match state {
+            ClaimState::Probing | ClaimState::Announcing
+                if Instant::now().duration_since(start) < Duration::from_secs(1) => {}
+            state => return (state, received),
+        }
+    }
+}
+
+#[test]
+fn channel_arp_announce() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    // Other traffic keeps flowing while probing.
+    b.send(b"ping", a.socket_addr);
+    let (state, received) = claim(&mut a, &mut b);
+    assert_eq!(state, ClaimState::Claimed);
+    assert_eq!(received, Some(b"ping".to_vec()));
+    assert_eq!(a.interface.poll_delay(&a.socket_set), None);
+}
+
+#[test]
+fn channel_arp_announce_conflict() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    a.interface.ipv4_addr = b.interface.ipv4_addr;
+
+    let (state, _) = claim(&mut a, &mut b);
+    assert_eq!(state, ClaimState::Conflict(b.interface.ethernet_addr));
+}
+
+#[test]
+fn channel_shaper() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    // 20 frames of ~1KB take ~190ms at 100KB/s once the burst is used up.
+    a.interface.shaper = Some(Shaper::new(100_000, 2_000, SystemEnv::new()));
+    for _ in 0 .. 20 {
+        a.send(&[0; 1000], b.socket_addr);
+    }
+
+    let start = Instant::now();
+    a.tick();
+    assert!(a.socket_set.socket(a.udp_handle).unwrap().as_udp_socket().send_enqueued() > 0);
+    assert!(a.interface.poll_delay(&a.socket_set).unwrap() > Duration::from_millis(0));
+
+    let mut received = 0;
+    while received < 20 && Instant::now().duration_since(start) < Duration::from_secs(2) {
+        a.tick();
+        b.tick();
+        while b.recv().is_some() {
+            received += 1;
+        }
+    }
+
+    assert_eq!(received, 20);
+    assert!(Instant::now().duration_since(start) >= Duration::from_millis(150));
+}
+
+#[test]
+fn channel_interface_stats() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    // The first packet waits for ARP to resolve b.
+    let stats = *a.interface.stats();
+    assert_eq!(stats.tx_arp_miss, 1);
+    assert!(stats.tx_frames >= 2 && stats.rx_frames >= 2);
+    assert!(stats.tx_bytes > stats.tx_frames && stats.rx_bytes > stats.rx_frames);
+
+    let unbound_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 5000,
+    };
+    a.send(b"ping", unbound_addr);
+    assert!(exchange(&mut b, &mut a).is_none());
+    assert_eq!(b.interface.stats().rx_no_socket, 1);
+    assert_eq!(b.interface.stats().rx_bad_checksum, 0);
+}
+
+#[test]
+fn channel_filter() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let mut filter = Filter::new(Action::Accept);
+    filter.push(Rule {
+        direction: Some(Direction::Received),
+        protocol: Some(Ipv4Protocol::UDP),
+        dst_ports: Some(5000 ..= 5000),
+        ..Rule::new(Action::Drop)
+    });
+    filter.push(Rule {
+        protocol: Some(Ipv4Protocol::UDP),
+        dst_ports: Some(5001 ..= 5001),
+        ..Rule::new(Action::Reject)
+    });
+    b.interface.filter = Some(filter);
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    let dropped_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 5000,
+    };
+    a.send(b"ping", dropped_addr);
+    assert!(exchange(&mut b, &mut a).is_none());
+    assert_eq!(b.interface.stats().rx_filtered, 1);
+    assert_eq!(b.interface.stack_stats().icmp.out_dest_unreachs, 0);
+
+    let rejected_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 5001,
+    };
+    a.send(b"ping", rejected_addr);
+    let start = Instant::now();
+    let err = loop {
+        assert!(Instant::now().duration_since(start) < Duration::from_secs(1));
+        a.tick();
+        b.tick();
+        if let Err(Error::Remote(RemoteError::Icmp(icmp_repr))) = a.udp_socket().recv() {
+            break icmp_repr.message;
+        }
+    };
+    assert_eq!(
+        err,
+        Icmpv4Message::DestinationUnreachable(Icmpv4DestinationUnreachable::CommunicationProhibited)
+    );
+    assert_eq!(b.interface.stats().rx_filtered, 2);
+
+    // Rejected sends are discarded without notifying the socket.
+    b.send(b"pong", rejected_addr);
+    assert!(exchange(&mut a, &mut b).is_none());
+    assert_eq!(b.udp_socket().send_enqueued(), 0);
+    assert_eq!(b.interface.stats().tx_filtered, 1);
+
+    let filter = b.interface.filter.as_ref().unwrap();
+    let packets: Vec<_> = filter.rules().map(|(_, stats)| stats.packets).collect();
+    assert_eq!(packets, vec![1, 2]);
+}
+
+#[test]
+fn channel_conntrack() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    // Only accept replies to connections a initiated.
+    let mut filter = Filter::new(Action::Accept);
+    filter.push(Rule {
+        direction: Some(Direction::Received),
+        protocol: Some(Ipv4Protocol::UDP),
+        state: Some(ConntrackState::New),
+        ..Rule::new(Action::Drop)
+    });
+    a.interface.filter = Some(filter);
+    a.interface.conntrack = Some(Conntrack::new(SystemEnv::new()));
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    let unsolicited_addr = SocketAddr {
+        addr: a.socket_addr.addr,
+        port: 5000,
+    };
+    b.send(b"ping", unsolicited_addr);
+    assert!(exchange(&mut a, &mut b).is_none());
+    assert_eq!(a.interface.stats().rx_filtered, 1);
+
+    let entries = a.interface.conntrack.as_mut().unwrap().dump();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].tuple.src_port, a.socket_addr.port);
+    assert_eq!(entries[0].tuple.dst_port, b.socket_addr.port);
+    assert_eq!(entries[0].origin, Direction::Sent);
+    assert_eq!(entries[0].state, ConntrackState::Established);
+    assert_eq!(entries[0].original.packets, 1);
+    assert_eq!(entries[0].reply.packets, 1);
+}
+
+#[test]
+fn channel_stack_stats() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    let unbound_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 5000,
+    };
+    a.send(b"ping", unbound_addr);
+    assert!(exchange(&mut b, &mut a).is_none());
+
+    let a_stats = *a.interface.stack_stats();
+    assert_eq!(a_stats.udp.out_datagrams, 2);
+    assert_eq!(a_stats.udp.in_datagrams, 1);
+    assert_eq!(a_stats.icmp.in_dest_unreachs, 1);
+
+    let b_stats = *b.interface.stack_stats();
+    assert_eq!(b_stats.ip.in_receives, 2);
+    assert_eq!(b_stats.ip.in_delivers, 2);
+    assert_eq!(b_stats.udp.in_datagrams, 1);
+    assert_eq!(b_stats.udp.no_ports, 1);
+    assert_eq!(b_stats.icmp.out_dest_unreachs, 1);
+    assert!(b_stats.to_string().contains("UdpNoPorts"));
+}
+
+#[test]
+fn channel_capture() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let frames = Rc::new(RefCell::new(vec![]));
+    let frames_clone = frames.clone();
+    a.interface.capture = Some(Capture::new(Box::new(move |direction, frame: &[u8]| {
+        let payload_type = EthernetFrame::try_new(frame).unwrap().payload_type();
+        frames_clone.borrow_mut().push((direction, payload_type));
+    })));
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    // ARP resolution of b, then the UDP packets.
+    assert_eq!(
+        *frames.borrow(),
+        vec![
+            (Direction::Sent, eth_types::ARP),
+            (Direction::Received, eth_types::ARP),
+            (Direction::Sent, eth_types::IPV4),
+            (Direction::Received, eth_types::IPV4),
+        ]
+    );
+
+    a.interface.capture.as_mut().unwrap().set_enabled(false);
+    udp_round_trip_with(&mut a, &mut b);
+    assert_eq!(frames.borrow().len(), 4);
+}
+
+/// Prepends a magic header to frames and XORs their bytes with a key.
+struct XorShim {
+    key: u8,
+}
+
+impl XorShim {
+    const MAGIC: [u8; 4] = [0x57, 0x47, 0x00, 0x01];
+}
+
+impl Shim for XorShim {
+    fn headroom(&self) -> usize {
+        XorShim::MAGIC.len()
+    }
+
+    fn tailroom(&self) -> usize {
+        0
+    }
+
+    fn encapsulate(&mut self, frame: &mut PacketBuf) -> Result<()> {
+        for byte in frame.iter_mut() {
+            *byte ^= self.key;
+        }
+        frame.push(XorShim::MAGIC.len())?.copy_from_slice(&XorShim::MAGIC);
+        Ok(())
+    }
+
+    fn decapsulate(&mut self, buffer: &mut [u8]) -> Result<Range<usize>> {
+        if !buffer.starts_with(&XorShim::MAGIC) {
+            return Err(Error::Parse(ParseError::Malformed));
+        }
+        for byte in buffer[XorShim::MAGIC.len() ..].iter_mut() {
+            *byte ^= self.key;
+        }
+        Ok(XorShim::MAGIC.len() .. buffer.len())
+    }
+}
+
+#[test]
+fn channel_shim() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let frames = Rc::new(RefCell::new(vec![]));
+    let frames_clone = frames.clone();
+    a.interface.capture = Some(Capture::new(Box::new(move |direction, frame: &[u8]| {
+        frames_clone.borrow_mut().push((direction, frame.to_vec()));
+    })));
+    a.interface.shim = Some(Box::new(XorShim { key: 0xA5 }));
+    b.interface.shim = Some(Box::new(XorShim { key: 0xA5 }));
+
+    udp_round_trip_with(&mut a, &mut b);
+
+    // Captures see frames as sent via the device.
+    for (_, frame) in frames.borrow().iter() {
+        assert!(frame.starts_with(&XorShim::MAGIC));
+        assert!(!frame[XorShim::MAGIC.len() ..].windows(4).any(|w| w == b"ping" || w == b"pong"));
+    }
+
+    // Frames which fail to decapsulate are dropped.
+    a.interface.shim = None;
+    let b_addr = b.socket_addr;
+    a.send(b"ping", b_addr);
+    assert!(exchange(&mut b, &mut a).is_none());
+    assert!(b.interface.stats().rx_malformed > 0);
+}
+
+#[test]
+fn channel_poll_wait() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let start = Instant::now();
+    b.interface
+        .poll_wait(Some(Duration::from_millis(50)))
+        .unwrap();
+    assert!(Instant::now().duration_since(start) >= Duration::from_millis(50));
+
+    // The ARP request for b wakes it up.
+    let b_addr = b.socket_addr;
+    a.send(b"ping", b_addr);
+    a.tick();
+
+    let start = Instant::now();
+    b.interface.poll_wait(Some(Duration::from_secs(5))).unwrap();
+    assert!(Instant::now().duration_since(start) < Duration::from_secs(1));
+}
+
+#[test]
+fn channel_udp_burst() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    // Spans several send and receive batches.
+    let b_addr = b.socket_addr;
+    for i in 0 .. 100u8 {
+        a.send(&[i], b_addr);
+    }
+    a.tick();
+    b.tick();
+
+    for i in 0 .. 100u8 {
+        assert_eq!(b.recv().unwrap().0, vec![i]);
+    }
+    assert!(b.recv().is_none());
+}
+
+#[test]
+fn channel_udp_observer() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let observed = events.clone();
+    b.udp_socket()
+        .set_observer(move |event| observed.borrow_mut().push(event));
+
+    let b_addr = b.socket_addr;
+    for _ in 0 .. 3 {
+        a.send(b"ping", b_addr);
+    }
+    for _ in 0 .. 10 {
+        a.tick();
+        b.tick();
+    }
+    assert_eq!(*events.borrow(), vec![SocketEvent::Recv; 3]);
+
+    b.udp_socket().clear_observer();
+    a.send(b"ping", b_addr);
+    exchange(&mut b, &mut a);
+    assert_eq!(events.borrow().len(), 3);
+}
+
+#[test]
+fn channel_tcp_observer() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let a_socket_env = env::socket_env(&mut a.interface);
+    let b_socket_env = env::socket_env(&mut b.interface);
+    let b_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 80,
+    };
+    let listener = b_socket_env.tcp_listener(b_addr, 1, 1).unwrap();
+    let listener_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::TcpListener(listener))
+        .unwrap();
+
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let observed = events.clone();
+    let mut tcp_socket = a_socket_env.tcp_socket_ephemeral(a.socket_addr.addr).unwrap();
+    tcp_socket.set_observer(move |event| observed.borrow_mut().push(event));
+    tcp_socket.connect(b_addr);
+    let tcp_handle = a.socket_set.add_socket(TaggedSocket::Tcp(tcp_socket)).unwrap();
+
+    let mut accepted = None;
+    for _ in 0 .. 10 {
+        a.tick();
+        b.tick();
+        if accepted.is_none() {
+            accepted = b
+                .socket_set
+                .socket(listener_handle)
+                .unwrap()
+                .as_tcp_listener()
+                .accept()
+                .map(|(tcp_socket, _)| tcp_socket);
+        }
+    }
+
+    // The peer sends data, then closes its sending half.
+    let mut accepted = accepted.unwrap();
+    assert_eq!(accepted.send(b"bye").unwrap(), 3);
+    accepted.shutdown(Shutdown::Write);
+    let accepted_handle = b.socket_set.add_socket(TaggedSocket::Tcp(accepted)).unwrap();
+    for _ in 0 .. 10 {
+        a.tick();
+        b.tick();
+    }
+
+    assert_eq!(
+        *events.borrow(),
+        vec![
+            SocketEvent::Established,
+            SocketEvent::Recv,
+            SocketEvent::PeerClosed,
+        ]
+    );
+    let tcp_socket = a.socket_set.socket(tcp_handle).unwrap().as_tcp_socket();
+    assert_eq!(tcp_socket.recv(&mut [0; 8]).unwrap(), 3);
+    assert_eq!(tcp_socket.recv(&mut [0; 8]).unwrap(), 0);
+
+    // Closing our half finishes closing the connection.
+    tcp_socket.shutdown(Shutdown::Write);
+    for _ in 0 .. 10 {
+        a.tick();
+        b.tick();
+    }
+    assert_eq!(events.borrow().last(), Some(&SocketEvent::Closed));
+    let accepted = b.socket_set.socket(accepted_handle).unwrap().as_tcp_socket();
+    assert_eq!(accepted.state(), TcpStateKind::TimeWait);
+}
+
+#[test]
+fn channel_udp_split() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let b_addr = b.socket_addr;
+    let (mut a_send, _) = a.udp_socket().split().unwrap();
+    let (_, mut b_recv) = b.udp_socket().split().unwrap();
+    match a.udp_socket().split() {
+        Err(Error::Socket(SocketError::Split)) => {}
+        _ => panic!("Socket is split twice."),
+    }
+    match a.udp_socket().send(4, b_addr) {
+        Err(Error::Socket(SocketError::Split)) => {}
+        _ => panic!("Split socket enqueued a packet."),
+    }
+
+    // One thread enqueues packets while another drains them, and the stacks
+    // are serviced from this one.
+    let producer = thread::spawn(move || {
+        for i in 0 .. 64u8 {
+            while a_send.send(&[i; 32], b_addr).is_err() {
+                thread::yield_now();
+            }
+        }
+    });
+    let consumer = thread::spawn(move || {
+        let mut buffer = [0; 16];
+        (0 .. 64u8)
+            .map(|_| loop {
+                if let Ok((len, _)) = b_recv.recv(&mut buffer) {
+                    break (len, buffer[0]);
+                }
+                thread::yield_now();
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let start = Instant::now();
+    while !consumer.is_finished() {
+        assert!(Instant::now().duration_since(start) < Duration::from_secs(5));
+        a.tick();
+        b.tick();
+    }
+
+    producer.join().unwrap();
+    let received = consumer.join().unwrap();
+    assert_eq!(received.len(), 64);
+    for (i, &(len, value)) in received.iter().enumerate() {
+        assert_eq!((len, value), (16, i as u8));
+    }
+}
+
+#[test]
+fn channel_reuse_port() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let a_socket_env = env::socket_env(&mut a.interface);
+    let mut b_socket_env = env::socket_env(&mut b.interface);
+    b_socket_env.set_reuse_port(true);
+    let b_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 5000,
+    };
+
+    // Each datagram is received by one of the UDP sockets sharing the port.
+    let udp_handles: Vec<SocketHandle> = (0 .. 2)
+        .map(|_| {
+            let udp_socket = b_socket_env.udp_socket(b_addr).unwrap();
+            assert!(udp_socket.is_reuse_port());
+            b.socket_set.add_socket(TaggedSocket::Udp(udp_socket)).unwrap()
+        })
+        .collect();
+
+    for port in 5000 .. 5016 {
+        let a_addr = SocketAddr {
+            addr: a.socket_addr.addr,
+            port,
+        };
+        let mut udp_socket = a_socket_env.udp_socket(a_addr).unwrap();
+        udp_socket.send(4, b_addr).unwrap().copy_from_slice(b"ping");
+        a.socket_set.add_socket(TaggedSocket::Udp(udp_socket)).unwrap();
+    }
+
+    let mut received = [0; 2];
+    let start = Instant::now();
+    while received.iter().sum::<usize>() < 16
+        && Instant::now().duration_since(start) < Duration::from_secs(1)
+    {
+        a.tick();
+        b.tick();
+        for (i, &handle) in udp_handles.iter().enumerate() {
+            let udp_socket = b.socket_set.socket(handle).unwrap().as_udp_socket();
+            while udp_socket.recv().is_ok() {
+                received[i] += 1;
+            }
+        }
+    }
+
+    assert_eq!(received.iter().sum::<usize>(), 16);
+    assert!(received.iter().all(|&received| received > 0));
+
+    // Each connection is accepted by one of the TCP listeners sharing the port.
+    let listener_handles: Vec<SocketHandle> = (0 .. 2)
+        .map(|_| {
+            let listener = b_socket_env.tcp_listener(b_addr, 8, 8).unwrap();
+            assert!(listener.is_reuse_port());
+            b.socket_set
+                .add_socket(TaggedSocket::TcpListener(listener))
+                .unwrap()
+        })
+        .collect();
+
+    let tcp_handles: Vec<SocketHandle> = (5000 .. 5008)
+        .map(|port| {
+            let a_addr = SocketAddr {
+                addr: a.socket_addr.addr,
+                port,
+            };
+            let mut tcp_socket = a_socket_env.tcp_socket(a_addr).unwrap();
+            tcp_socket.connect(b_addr);
+            a.socket_set.add_socket(TaggedSocket::Tcp(tcp_socket)).unwrap()
+        })
+        .collect();
+
+    let mut accepted = [vec![], vec![]];
+    let start = Instant::now();
+    while accepted.iter().map(Vec::len).sum::<usize>() < 8
+        && Instant::now().duration_since(start) < Duration::from_secs(1)
+    {
+        a.tick();
+        b.tick();
+        for (i, &handle) in listener_handles.iter().enumerate() {
+            let listener = b.socket_set.socket(handle).unwrap().as_tcp_listener();
+            while let Some((tcp_socket, _)) = listener.accept() {
+                accepted[i].push(tcp_socket);
+            }
+        }
+    }
+
+    assert_eq!(accepted.iter().map(Vec::len).sum::<usize>(), 8);
+    assert!(accepted.iter().all(|accepted| !accepted.is_empty()));
+    for handle in tcp_handles {
+        assert!(a.socket_set.socket(handle).unwrap().as_tcp_socket().is_connected());
+    }
+}
+
+#[test]
+fn channel_udp_socket_with() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let socket_env = env::socket_env(&mut b.interface);
+    let small_addr = SocketAddr {
+        addr: b.socket_addr.addr,
+        port: 4097,
+    };
+    let mut udp_socket = socket_env.udp_socket_with(small_addr, 1, 2, 4).unwrap();
+    assert!(udp_socket.send(5, a.socket_addr).is_err());
+    assert!(udp_socket.send(4, a.socket_addr).is_ok());
+    assert_eq!(udp_socket.send_capacity(), 0);
+    let small_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    // Only as many packets as the receive buffer holds are queued.
+    for i in 0 .. 4u8 {
+        a.send(&[i], small_addr);
+    }
+    a.tick();
+    b.tick();
+
+    let udp_socket = b.socket_set.socket(small_handle).unwrap().as_udp_socket();
+    assert_eq!(udp_socket.recv().unwrap().0, &[0]);
+    assert_eq!(udp_socket.recv().unwrap().0, &[1]);
+    assert!(udp_socket.recv().is_err());
+}
+
+#[test]
+fn channel_raw_socket_filter() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let socket_env = env::socket_env(&mut b.interface);
+    let mut raw_socket = socket_env.raw_socket(RawType::Ipv4);
+    raw_socket.set_filter(|packet| {
+        Ipv4Packet::try_new(packet)
+            .map(|ipv4_packet| ipv4_packet.protocol() == ipv4_protocols::ICMP)
+            .unwrap_or(false)
+    });
+    let raw_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    // UDP packets are filtered out...
+    udp_round_trip_with(&mut a, &mut b);
+    let raw_socket = b.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+    assert!(raw_socket.recv().is_err());
+
+    // Until the filter is detached.
+    raw_socket.clear_filter();
+    udp_round_trip_with(&mut a, &mut b);
+    let raw_socket = b.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+    let ipv4_packet = Ipv4Packet::try_new(raw_socket.recv().unwrap()).unwrap();
+    assert_eq!(ipv4_packet.protocol(), ipv4_protocols::UDP);
+}
+
+#[test]
+fn channel_raw_socket_src_addr() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let socket_env = env::socket_env(&mut a.interface);
+    let raw_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Raw(socket_env.raw_socket(RawType::Ipv4)))
+        .unwrap();
+
+    let b_addr = b.socket_addr;
+    let send = |a: &mut Stack, src_addr: Ipv4Address, payload: &[u8]| {
+        let ipv4_repr = Ipv4Repr {
+            src_addr,
+            dst_addr: b_addr.addr,
+            protocol: Ipv4Protocol::UDP,
+            payload_len: 0,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: 0,
+            options: Ipv4Options::new(),
+        };
+        let builder = PacketBuilder::new()
+            .ipv4(ipv4_repr)
+            .udp(5000, b_addr.port)
+            .payload(payload);
+        let raw_socket = a.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+        builder
+            .build(raw_socket.send(builder.buffer_len()).unwrap())
+            .unwrap();
+    };
+
+    // A packet from another address is dropped rather than left at the head
+    // of the send buffer...
+    send(&mut a, Ipv4Address::new([10, 0, 0, 104]), b"spoofed");
+    // ...and a packet from 0.0.0.0 is sent from the interface address with a
+    // UDP checksum b accepts.
+    send(&mut a, Ipv4Address::UNSPECIFIED, b"unspecified");
+
+    let (payload, addr) = exchange(&mut b, &mut a).unwrap();
+    assert_eq!(payload, b"unspecified");
+    assert_eq!(
+        addr,
+        SocketAddr {
+            addr: a.socket_addr.addr,
+            port: 5000,
+        }
+    );
+    assert!(exchange(&mut b, &mut a).is_none());
+}
+
+/// Polls a's PPPoE client and ticks both stacks until b's raw socket receives
+/// a PPPoE discovery frame, or a second passes.
+fn pppoe_exchange(
+    a: &mut Stack,
+    client: &mut PppoeClient,
+    b: &mut Stack,
+    raw_handle: SocketHandle,
+) -> Option<PppoeDiscoveryRepr> {
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < Duration::from_secs(1) {
+        client.poll(&mut a.interface, &mut a.socket_set).unwrap();
+        a.tick();
+        b.tick();
+
+        let raw_socket = b.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+        if let Ok(eth_buffer) = raw_socket.recv() {
+            let eth_frame = EthernetFrame::try_new(eth_buffer).unwrap();
+            let pppoe_packet = PppoePacket::try_new(eth_frame.payload()).unwrap();
+            return Some(PppoeDiscoveryRepr::deserialize(&pppoe_packet).unwrap());
+        }
+    }
+
+    None
+}
+
+/// Sends a PPPoE discovery frame from b to a via b's raw socket.
+fn pppoe_reply(a: &Stack, b: &mut Stack, raw_handle: SocketHandle, reply: &PppoeDiscoveryRepr) {
+    let raw_socket = b.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+    let eth_buffer = raw_socket
+        .send(EthernetFrame::<&[u8]>::buffer_len(reply.buffer_len()))
+        .unwrap();
+    let mut eth_frame = EthernetFrame::try_new(eth_buffer).unwrap();
+    eth_frame.set_src_addr(b.interface.ethernet_addr);
+    eth_frame.set_dst_addr(a.interface.ethernet_addr);
+    eth_frame.set_payload_type(eth_types::PPPOE_DISCOVERY);
+    reply
+        .serialize(&mut PppoePacket::try_new(eth_frame.payload_mut()).unwrap())
+        .unwrap();
+}
+
+#[test]
+fn channel_pppoe() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let socket_env = env::socket_env(&mut a.interface);
+    let mut client = PppoeClient::new(&socket_env, &mut a.socket_set, b"", SystemEnv::new()).unwrap();
+
+    // b plays the access concentrator via a raw socket.
+    let socket_env = env::socket_env(&mut b.interface);
+    let mut raw_socket = socket_env.raw_socket(RawType::Ethernet);
+    raw_socket.set_filter(|eth_buffer| {
+        EthernetFrame::try_new(eth_buffer)
+            .map(|eth_frame| eth_frame.payload_type() == eth_types::PPPOE_DISCOVERY)
+            .unwrap_or(false)
+    });
+    let raw_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    let padi = pppoe_exchange(&mut a, &mut client, &mut b, raw_handle).unwrap();
+    assert_eq!(padi.code, PppoeCode::Padi);
+    assert_eq!(padi.service_name, Some(Vec::new()));
+    assert_eq!(client.state(), PppoeState::Discovering);
+
+    let pado = PppoeDiscoveryRepr {
+        service_name: Some(Vec::new()),
+        ac_name: Some(b"usrnet".to_vec()),
+        host_uniq: padi.host_uniq.clone(),
+        ac_cookie: Some(vec![1, 2, 3]),
+        ..PppoeDiscoveryRepr::new(PppoeCode::Pado, 0)
+    };
+    pppoe_reply(&a, &mut b, raw_handle, &pado);
+
+    let padr = pppoe_exchange(&mut a, &mut client, &mut b, raw_handle).unwrap();
+    assert_eq!(padr.code, PppoeCode::Padr);
+    assert_eq!(padr.ac_cookie, Some(vec![1, 2, 3]));
+    assert_eq!(padr.host_uniq, padi.host_uniq);
+
+    let pads = PppoeDiscoveryRepr {
+        service_name: Some(Vec::new()),
+        host_uniq: padi.host_uniq.clone(),
+        ..PppoeDiscoveryRepr::new(PppoeCode::Pads, 0x42)
+    };
+    pppoe_reply(&a, &mut b, raw_handle, &pads);
+    assert!(pppoe_exchange(&mut a, &mut client, &mut b, raw_handle).is_none());
+    assert_eq!(client.state(), PppoeState::Session);
+    assert_eq!(
+        a.interface.pppoe_session,
+        Some(PppoeSession {
+            ac_addr: b.interface.ethernet_addr,
+            session_id: 0x42,
+        })
+    );
+
+    // Both ends encapsulate IPv4 in the session, without resolving addresses
+    // via ARP.
+    b.interface.pppoe_session = Some(PppoeSession {
+        ac_addr: a.interface.ethernet_addr,
+        session_id: 0x42,
+    });
+    udp_round_trip_with(&mut a, &mut b);
+    assert_eq!(a.interface.stats().tx_arp_miss, 0);
+
+    let padt = PppoeDiscoveryRepr::new(PppoeCode::Padt, 0x42);
+    pppoe_reply(&a, &mut b, raw_handle, &padt);
+    let padi = pppoe_exchange(&mut a, &mut client, &mut b, raw_handle).unwrap();
+    assert_eq!(padi.code, PppoeCode::Padi);
+    assert_eq!(a.interface.pppoe_session, None);
+    assert_eq!(client.state(), PppoeState::Discovering);
+}
+
+#[test]
+fn channel_recv_timestamps() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    let time_env = MockEnv::new();
+
+    let socket_env = env::socket_env(&mut b.interface);
+    let mut raw_socket = socket_env.raw_socket(RawType::Ipv4);
+    raw_socket.enable_timestamps(time_env.clone());
+    let raw_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    udp_round_trip_with(&mut a, &mut b);
+    let raw_socket = b.socket_set.socket(raw_handle).unwrap().as_raw_socket();
+    assert_eq!(
+        raw_socket.recv_with_meta().unwrap().1.timestamp,
+        Some(time_env.now)
+    );
+
+    // Packets received before timestamping is enabled have no timestamp.
+    a.send(b"early", b.socket_addr);
+    a.tick();
+    b.tick();
+    b.udp_socket().enable_timestamps(time_env.clone());
+    a.send(b"late", b.socket_addr);
+    a.tick();
+    b.tick();
+
+    let udp_socket = b.udp_socket();
+    let (payload, addr, meta) = udp_socket.recv_with_meta().unwrap();
+    assert_eq!(payload, b"early");
+    assert_eq!(addr, a.socket_addr);
+    assert_eq!(meta.timestamp, None);
+    let (payload, _, meta) = udp_socket.recv_with_meta().unwrap();
+    assert_eq!(payload, b"late");
+    assert_eq!(meta.timestamp, Some(time_env.now));
+}
+
+#[test]
+fn channel_udp_watermarks() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let watermarks = Some(Watermarks { low: 1, high: 3 });
+    a.udp_socket().set_send_watermarks(watermarks);
+    b.udp_socket().set_recv_watermarks(watermarks);
+
+    // Sending becomes congested at the high watermark...
+    let b_addr = b.socket_addr;
+    for i in 0 .. 3u8 {
+        assert!(!a.udp_socket().send_congested());
+        a.send(&[i], b_addr);
+    }
+    assert!(a.udp_socket().send_congested());
+
+    // And clears once the buffer drains, at which point receiving is congested.
+    a.tick();
+    b.tick();
+    assert!(!a.udp_socket().send_congested());
+    assert!(b.udp_socket().recv_congested());
+    b.udp_socket().recv().unwrap();
+    assert!(b.udp_socket().recv_congested());
+    b.udp_socket().recv().unwrap();
+    assert!(!b.udp_socket().recv_congested());
+}
+
+#[test]
+fn channel_checksum_offload() {
+    let config = ChannelConfig {
+        capabilities: DeviceCapabilities {
+            checksum: ChecksumCapabilities {
+                ipv4: true,
+                tcp: true,
+                udp: true,
+            },
+        },
+        ..ChannelConfig::default()
+    };
+    let (dev_a, dev_b) = ChannelDevice::pair_with_config(config, SystemEnv::new());
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+
+    let checksums = Rc::new(RefCell::new(vec![]));
+    let checksums_clone = checksums.clone();
+    a.interface.capture = Some(Capture::new(Box::new(move |direction, frame: &[u8]| {
+        let eth_frame = EthernetFrame::try_new(frame).unwrap();
+        if eth_frame.payload_type() != eth_types::IPV4 {
+            return;
+        }
+        let ipv4_packet = Ipv4Packet::try_new(eth_frame.payload()).unwrap();
+        let udp_packet = UdpPacket::try_new(ipv4_packet.payload()).unwrap();
+        checksums_clone.borrow_mut().push((
+            direction,
+            ipv4_packet.header_checksum(),
+            udp_packet.checksum(),
+        ));
+    })));
+
+    // Packets are only accepted by the peer if the device filled checksums in.
+    udp_round_trip_with(&mut a, &mut b);
+
+    let checksums = checksums.borrow();
+    assert_eq!(checksums.len(), 2);
+    assert_eq!(checksums[0], (Direction::Sent, 0, 0));
+    assert_eq!(checksums[1].0, Direction::Received);
+    assert!(checksums[1].1 != 0 && checksums[1].2 != 0);
+}
+
+/// Ticks an interface set and peer stacks for 100 ms.
+fn tick_multi(interfaces: &mut InterfaceSet, socket_set: &mut SocketSet, peers: &mut [&mut Stack]) {
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < Duration::from_millis(100) {
+        socket::recv_all(interfaces, socket_set);
+        socket::send_all(interfaces, socket_set);
+        for peer in peers.iter_mut() {
+            peer.tick();
+        }
+    }
+}
+
+#[test]
+fn channel_multi_interface() {
+    let (dev_a, dev_peer_a) = ChannelDevice::pair();
+    let (dev_b, dev_peer_b) = ChannelDevice::pair();
+    let mut peer_a = Stack::with_subnet(dev_peer_a, 0, 103);
+    let mut peer_b = Stack::with_subnet(dev_peer_b, 1, 103);
+
+    // A host on both subnets, with one socket for either interface and another
+    // pinned to the second interface.
+    let mut interfaces = InterfaceSet::new();
+    let a = interfaces.add_interface(env::interface(
+        Box::new(dev_a),
+        EthernetAddress::new([0x06, 0, 0, 0, 0, 102]),
+        Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 102]), 24),
+    ));
+    let b = interfaces.add_interface(env::interface(
+        Box::new(dev_b),
+        EthernetAddress::new([0x06, 0, 0, 0, 1, 102]),
+        Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 1, 102]), 24),
+    ));
+
+    let any_addr = |port| SocketAddr {
+        addr: Ipv4Address::new([0, 0, 0, 0]),
+        port,
+    };
+    let socket_env = SocketEnv::with_interfaces(&interfaces, SystemEnv::new());
+    let mut socket_set = env::socket_set();
+    let udp_handle = socket_set
+        .add_socket(TaggedSocket::Udp(
+            socket_env.udp_socket(any_addr(4096)).unwrap(),
+        ))
+        .unwrap();
+    let pinned_handle = socket_set
+        .add_socket(TaggedSocket::Udp(
+            socket_env.udp_socket(any_addr(4097)).unwrap(),
+        ))
+        .unwrap();
+    socket_set.pin_socket(pinned_handle, Some(b)).unwrap();
+
+    // Packets leave via the interface on the destination subnet, else via
+    // routes, else via the default interface.
+    assert_eq!(interfaces.route(Ipv4Address::new([10, 0, 1, 5])), Some(b));
+    assert_eq!(interfaces.route(Ipv4Address::new([8, 8, 8, 8])), Some(a));
+    interfaces.add_route(Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 2, 0]), 24), b);
+    assert_eq!(interfaces.route(Ipv4Address::new([10, 0, 2, 5])), Some(b));
+
+    {
+        let udp_socket = socket_set.socket(udp_handle).unwrap().as_udp_socket();
+        udp_socket
+            .send(1, peer_a.socket_addr)
+            .unwrap()
+            .copy_from_slice(b"a");
+        udp_socket
+            .send(1, peer_b.socket_addr)
+            .unwrap()
+            .copy_from_slice(b"b");
+    }
+    tick_multi(
+        &mut interfaces,
+        &mut socket_set,
+        &mut [&mut peer_a, &mut peer_b],
+    );
+
+    // Each peer sees the address of the interface on its subnet.
+    let (payload, addr) = peer_a.recv().unwrap();
+    assert_eq!(
+        (payload, addr.addr),
+        (b"a".to_vec(), *interfaces.interface(a).ipv4_addr)
+    );
+    let (payload, addr) = peer_b.recv().unwrap();
+    assert_eq!(
+        (payload, addr.addr),
+        (b"b".to_vec(), *interfaces.interface(b).ipv4_addr)
+    );
+
+    // The pinned socket only receives via its interface.
+    let pinned_addr = |interfaces: &mut InterfaceSet, interface_handle| SocketAddr {
+        addr: *interfaces.interface(interface_handle).ipv4_addr,
+        port: 4097,
+    };
+    peer_a.send(b"a", pinned_addr(&mut interfaces, a));
+    peer_b.send(b"b", pinned_addr(&mut interfaces, b));
+    tick_multi(
+        &mut interfaces,
+        &mut socket_set,
+        &mut [&mut peer_a, &mut peer_b],
+    );
+
+    let pinned_socket = socket_set.socket(pinned_handle).unwrap().as_udp_socket();
+    let (payload, addr) = pinned_socket.recv().unwrap();
+    assert_eq!((payload, addr), (&b"b"[..], peer_b.socket_addr));
+    assert!(pinned_socket.recv().is_err());
+}
+
+#[test]
+fn channel_bridge() {
+    let (dev_a, bridge_a) = ChannelDevice::pair();
+    let (bridge_b, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    let mut bridge = Bridge::new(
+        Box::new(bridge_a),
+        Box::new(bridge_b),
+        MacTable::new(60, SystemEnv::new()),
+    );
+
+    let b_addr = b.socket_addr;
+    a.send(b"ping", b_addr);
+
+    let start = Instant::now();
+    let mut received = None;
+    while received.is_none() && Instant::now().duration_since(start) < Duration::from_secs(1) {
+        a.tick();
+        bridge.poll();
+        b.tick();
+        received = b.recv();
+    }
+
+    assert_eq!(received.unwrap(), (b"ping".to_vec(), a.socket_addr));
+    assert_eq!(
+        bridge.mac_table().lookup(a.interface.ethernet_addr),
+        Some(Port::A)
+    );
+    assert_eq!(
+        bridge.mac_table().lookup(b.interface.ethernet_addr),
+        Some(Port::B)
+    );
+}
+
+#[test]
+fn channel_reconfigure() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    udp_round_trip_with(&mut a, &mut b);
+
+    let prev_addr = *b.interface.ipv4_addr;
+    let next_addr = Ipv4Address::new([10, 0, 0, 104]);
+    b.interface
+        .set_ipv4_addr(Ipv4AddressCidr::new(next_addr, 24), &mut b.socket_set)
+        .unwrap();
+    assert_eq!(
+        b.interface
+            .arp_cache
+            .eth_addr_for_ip(*a.interface.ipv4_addr),
+        None
+    );
+
+    // The gratuitous ARP teaches a the new address...
+    a.tick();
+    assert_eq!(
+        a.interface.arp_cache.eth_addr_for_ip(next_addr),
+        Some(b.interface.ethernet_addr)
+    );
+
+    // And the socket bound to the previous address is notified.
+    let error = b
+        .socket_set
+        .socket(b.udp_handle)
+        .unwrap()
+        .as_udp_socket()
+        .take_error();
+    match error {
+        Some(Error::Address(AddressError::InvalidAddress(addr))) => assert_eq!(addr, prev_addr),
+        _ => panic!("Expected InvalidAddress, got {:?}.", error),
+    }
+
+    let next_eth_addr = EthernetAddress::new([0x06, 0, 0, 0, 0, 104]);
+    b.interface.set_ethernet_addr(next_eth_addr).unwrap();
+    a.tick();
+    assert_eq!(
+        a.interface.arp_cache.eth_addr_for_ip(next_addr),
+        Some(next_eth_addr)
+    );
+}
+
+#[test]
+fn channel_remove_socket() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let mut a = Stack::new(dev_a, 102);
+    let mut b = Stack::new(dev_b, 103);
+    let socket_env = env::socket_env(&mut b.interface);
+
+    let udp_socket = socket_env.udp_socket(b.socket_addr).unwrap();
+    b.socket_set.remove(b.udp_handle).unwrap();
+    let stale_handle = b.udp_handle;
+    b.udp_handle = b
+        .socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+    udp_round_trip_with(&mut a, &mut b);
+
+    // Removing the socket releases its binding...
+    assert!(socket_env.udp_socket(b.socket_addr).is_err());
+    b.socket_set.remove(b.udp_handle).unwrap();
+    assert!(socket_env.udp_socket(b.socket_addr).is_ok());
+
+    // And the handles are invalidated.
+    match b.socket_set.socket(stale_handle) {
+        Err(Error::Socket(SocketError::InvalidHandle)) => {}
+        _ => panic!("Stale handle is in use."),
+    }
+    assert_eq!(b.socket_set.len(), 0);
+}
+
+fn netapi_stack<D: Device + 'static>(dev: D, host: u8) -> netapi::Stack {
+    netapi::Stack::new(env::interface(
+        Box::new(dev),
+        EthernetAddress::new([0x06, 0, 0, 0, 0, host]),
+        Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, host]), 24),
+    ))
+}
+
+#[test]
+fn channel_netapi_udp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 7,
+    };
+
+    let echo = thread::spawn(move || {
+        let b = netapi_stack(dev_b, 103);
+        let socket = netapi::UdpSocket::bind(&b, b_addr).unwrap();
+        let mut buffer = [0; 64];
+        let (len, addr) = socket.recv_from(&mut buffer).unwrap();
+        socket.send_to(&buffer[.. len], addr).unwrap();
+    });
+
+    let a = netapi_stack(dev_a, 102);
+    let mut socket = netapi::UdpSocket::bind(
+        &a,
+        SocketAddr {
+            addr: Ipv4Address::new([10, 0, 0, 102]),
+            port: 0,
+        },
+    )
+    .unwrap();
+    assert!(socket.local_addr().port >= EPHEMERAL_PORT_MIN);
+    socket
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    socket.connect(b_addr).unwrap();
+    socket.send(b"ping").unwrap();
+
+    let mut buffer = [0; 64];
+    assert_eq!(socket.recv_from(&mut buffer).unwrap(), (4, b_addr));
+    assert_eq!(&buffer[.. 4], b"ping");
+    echo.join().unwrap();
+
+    // Nothing else arrives.
+    socket
+        .set_read_timeout(Some(Duration::from_millis(50)))
+        .unwrap();
+    assert_eq!(
+        socket.recv(&mut buffer).unwrap_err().kind(),
+        io::ErrorKind::TimedOut
+    );
+}
+
+#[test]
+fn channel_netapi_tcp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 7,
+    };
+
+    let echo = thread::spawn(move || {
+        let b = netapi_stack(dev_b, 103);
+        let listener = netapi::TcpListener::bind(&b, b_addr).unwrap();
+        let (mut stream, addr) = listener.accept().unwrap();
+        assert_eq!(stream.peer_addr(), addr);
+
+        let mut buffer = Vec::new();
+        stream.read_to_end(&mut buffer).unwrap();
+        stream.write_all(&buffer).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+
+        // Finish closing the connection.
+        drop(stream);
+        let start = Instant::now();
+        while Instant::now().duration_since(start) < Duration::from_millis(500) {
+            b.tick();
+        }
+    });
+
+    let a = netapi_stack(dev_a, 102);
+    let mut stream =
+        netapi::TcpStream::connect_timeout(&a, b_addr, Duration::from_secs(5)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    stream.write_all(b"hello world").unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+
+    let mut buffer = Vec::new();
+    stream.read_to_end(&mut buffer).unwrap();
+    assert_eq!(&buffer[..], b"hello world");
+    echo.join().unwrap();
+}
+
+#[test]
+fn channel_netapi_tcp_with_faults() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let config = FaultConfig {
+        loss: 0.05,
+        duplicate: 0.05,
+        reorder: 0.1,
+        latency: Duration::from_millis(1),
+        ..FaultConfig::default()
+    };
+    let dev_a = FaultyDevice::new(dev_a, config);
+    let dev_b = FaultyDevice::new(
+        dev_b,
+        FaultConfig {
+            seed: [1, 2, 3, 4],
+            ..config
+        },
+    );
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 7,
+    };
+
+    // Segments are lost, duplicated and reordered in both directions, so the
+    // data only makes it through intact thanks to retransmission.
+    let echo = thread::spawn(move || {
+        let b = netapi_stack(dev_b, 103);
+        let listener = netapi::TcpListener::bind(&b, b_addr).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        let mut buffer = Vec::new();
+        stream.read_to_end(&mut buffer).unwrap();
+        stream.write_all(&buffer).unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+
+        drop(stream);
+        let start = Instant::now();
+        while Instant::now().duration_since(start) < Duration::from_millis(500) {
+            b.tick();
+        }
+    });
+
+    let data: Vec<u8> = (0 .. 16384).map(|i| (i % 251) as u8).collect();
+    let a = netapi_stack(dev_a, 102);
+    let mut stream =
+        netapi::TcpStream::connect_timeout(&a, b_addr, Duration::from_secs(10)).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .unwrap();
+    stream.write_all(&data).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+
+    let mut buffer = Vec::new();
+    stream.read_to_end(&mut buffer).unwrap();
+    assert!(buffer == data);
+    echo.join().unwrap();
+}
+
+#[cfg(feature = "sync")]
+fn sync_stack(dev: ChannelDevice, host: u8) -> netapi::sync::Stack {
+    netapi::sync::Stack::spawn(move || {
+        env::interface(
+            Box::new(dev),
+            EthernetAddress::new([0x06, 0, 0, 0, 0, host]),
+            Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, host]), 24),
+        )
+    })
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn channel_netapi_sync() {
+    fn is_send_sync<T: Send + Sync>() {}
+    is_send_sync::<netapi::sync::Stack>();
+    is_send_sync::<netapi::sync::TcpStream>();
+    is_send_sync::<netapi::sync::TcpListener>();
+    is_send_sync::<netapi::sync::UdpSocket>();
+
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 7,
+    };
+
+    // Each connection is echoed from a thread of its own while the stack
+    // keeps servicing the others.
+    let b = sync_stack(dev_b, 103);
+    let listener = netapi::sync::TcpListener::bind(&b, b_addr).unwrap();
+    let echo = thread::spawn(move || {
+        let echoes: Vec<_> = (0 .. 4)
+            .map(|_| {
+                let (mut stream, _) = listener.accept().unwrap();
+                thread::spawn(move || {
+                    let mut buffer = Vec::new();
+                    stream.read_to_end(&mut buffer).unwrap();
+                    stream.write_all(&buffer).unwrap();
+                })
+            })
+            .collect();
+
+        for echo in echoes {
+            echo.join().unwrap();
+        }
+    });
+
+    let a = sync_stack(dev_a, 102);
+    let clients: Vec<_> = (0 .. 4u8)
+        .map(|i| {
+            let a = a.clone();
+            thread::spawn(move || {
+                let data: Vec<u8> = (0 .. 4096).map(|j| (j % 251) as u8 ^ i).collect();
+                let mut stream =
+                    netapi::sync::TcpStream::connect_timeout(&a, b_addr, Duration::from_secs(5))
+                        .unwrap();
+                stream
+                    .set_read_timeout(Some(Duration::from_secs(5)))
+                    .unwrap();
+                stream.write_all(&data).unwrap();
+                stream.shutdown(Shutdown::Write).unwrap();
+
+                let mut buffer = Vec::new();
+                stream.read_to_end(&mut buffer).unwrap();
+                assert!(buffer == data);
+            })
+        })
+        .collect();
+
+    for client in clients {
+        client.join().unwrap();
+    }
+    echo.join().unwrap();
+
+    // The interface is configured on the stack thread.
+    let ipv4_addr = a.with_interface(|interface| *interface.ipv4_addr).unwrap();
+    assert_eq!(ipv4_addr, Ipv4Address::new([10, 0, 0, 102]));
+}
+
+#[test]
+fn channel_reactor() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 4096,
+    };
+
+    let echo = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let listener = socket_env.tcp_listener(b_addr, 4, 4).unwrap();
+        let reactor = Reactor::new(b.interface, b.socket_set);
+        let udp_handle = b.udp_handle;
+        let listener_handle = reactor
+            .add_socket(TaggedSocket::TcpListener(listener))
+            .unwrap();
+
+        let mut buffer = [0; 64];
+        let (len, addr) = reactor
+            .block_on(reactor.recv_from(udp_handle, &mut buffer))
+            .unwrap();
+        reactor
+            .block_on(reactor.send_to(udp_handle, &buffer[.. len], addr))
+            .unwrap();
+
+        let (socket, _) = reactor.block_on(reactor.accept(listener_handle)).unwrap();
+        let handle = reactor.add_socket(TaggedSocket::Tcp(socket)).unwrap();
+        loop {
+            let len = reactor.block_on(reactor.recv(handle, &mut buffer)).unwrap();
+            if len == 0 {
+                break;
+            }
+            reactor
+                .block_on(reactor.send(handle, &buffer[.. len]))
+                .unwrap();
+        }
+        reactor.with_socket_set(|socket_set| {
+            socket_set
+                .socket(handle)
+                .unwrap()
+                .as_tcp_socket()
+                .shutdown(Shutdown::Write)
+        });
+
+        // Finish closing the connection.
+        let start = Instant::now();
+        while Instant::now().duration_since(start) < Duration::from_millis(500) {
+            reactor.poll();
+        }
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let mut tcp_socket = socket_env
+        .tcp_socket_ephemeral(*a.interface.ipv4_addr)
+        .unwrap();
+    let reactor = Reactor::new(a.interface, a.socket_set);
+
+    let mut buffer = [0; 64];
+    reactor
+        .block_on(reactor.send_to(a.udp_handle, b"ping", b_addr))
+        .unwrap();
+    assert_eq!(
+        reactor
+            .block_on(reactor.recv_from(a.udp_handle, &mut buffer))
+            .unwrap(),
+        (4, b_addr)
+    );
+    assert_eq!(&buffer[.. 4], b"ping");
+
+    tcp_socket.connect(b_addr);
+    let handle = reactor.add_socket(TaggedSocket::Tcp(tcp_socket)).unwrap();
+    reactor.block_on(reactor.connect(handle)).unwrap();
+    reactor
+        .block_on(reactor.send(handle, b"hello world"))
+        .unwrap();
+    reactor.with_socket_set(|socket_set| {
+        socket_set
+            .socket(handle)
+            .unwrap()
+            .as_tcp_socket()
+            .shutdown(Shutdown::Write)
+    });
+
+    let mut received = Vec::new();
+    loop {
+        let len = reactor.block_on(reactor.recv(handle, &mut buffer)).unwrap();
+        if len == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[.. len]);
+    }
+    assert_eq!(&received[..], b"hello world");
+    echo.join().unwrap();
+}
+
+#[test]
+fn channel_perf_tcp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 5201,
+    };
+
+    let server = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let listener = socket_env.tcp_listener(b_addr, 4, 4).unwrap();
+        let listener_handle = b
+            .socket_set
+            .add_socket(TaggedSocket::TcpListener(listener))
+            .unwrap();
+        perf_tcp_server(&mut b.interface, &mut b.socket_set, listener_handle).unwrap()
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let tcp_socket = socket_env
+        .tcp_socket_ephemeral(*a.interface.ipv4_addr)
+        .unwrap();
+    let tcp_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Tcp(tcp_socket))
+        .unwrap();
+    let client = perf_tcp_client(
+        &mut a.interface,
+        &mut a.socket_set,
+        tcp_handle,
+        b_addr,
+        Duration::from_millis(200),
+    ).unwrap();
+
+    let server = server.join().unwrap();
+    assert!(client.bytes > 0);
+    assert_eq!(server.bytes, client.bytes);
+    assert!(server.bits_per_sec() > 0.0);
+}
+
+#[test]
+fn channel_perf_udp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 4096,
+    };
+
+    let server = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let udp_handle = b.udp_handle;
+        perf_udp_server(&mut b.interface, &mut b.socket_set, udp_handle).unwrap()
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let udp_handle = a.udp_handle;
+    let client = perf_udp_client(
+        &mut a.interface,
+        &mut a.socket_set,
+        udp_handle,
+        b_addr,
+        Duration::from_millis(200),
+        512,
+    ).unwrap();
+
+    let server = server.join().unwrap();
+    assert!(server.bytes > 0);
+    assert!(server.bytes + server.lost * 512 <= client.bytes);
+}
+
+#[test]
+fn channel_nc_tcp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 4097,
+    };
+
+    let listener = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let listener = socket_env.tcp_listener(b_addr, 4, 4).unwrap();
+        let listener_handle = b
+            .socket_set
+            .add_socket(TaggedSocket::TcpListener(listener))
+            .unwrap();
+
+        let (send, recv) = mpsc::channel();
+        send.send(b"pong".to_vec()).unwrap();
+        drop(send);
+
+        let mut output = Vec::new();
+        nc_listen(
+            &mut b.interface,
+            &mut b.socket_set,
+            listener_handle,
+            &recv,
+            &mut output,
+        ).unwrap();
+        output
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let tcp_socket = socket_env
+        .tcp_socket_ephemeral(*a.interface.ipv4_addr)
+        .unwrap();
+    let tcp_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Tcp(tcp_socket))
+        .unwrap();
+
+    let (send, recv) = mpsc::channel();
+    send.send(b"ping".to_vec()).unwrap();
+    drop(send);
+
+    let mut output = Vec::new();
+    nc_connect(
+        &mut a.interface,
+        &mut a.socket_set,
+        tcp_handle,
+        b_addr,
+        &recv,
+        &mut output,
+    ).unwrap();
+
+    assert_eq!(&output[..], b"pong");
+    assert_eq!(&listener.join().unwrap()[..], b"ping");
+}
+
+#[test]
+fn channel_nc_udp() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 4096,
+    };
+
+    // b replies to whoever sends to it first.
+    let listener = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let (send, recv) = mpsc::channel();
+        send.send(b"pong".to_vec()).unwrap();
+        drop(send);
+
+        let mut output = Vec::new();
+        let udp_handle = b.udp_handle;
+        nc_udp(
+            &mut b.interface,
+            &mut b.socket_set,
+            udp_handle,
+            None,
+            &recv,
+            &mut output,
+        ).unwrap();
+        output
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let (send, recv) = mpsc::channel();
+    send.send(b"ping".to_vec()).unwrap();
+    drop(send);
+    let udp_handle = a.udp_handle;
+    nc_udp(
+        &mut a.interface,
+        &mut a.socket_set,
+        udp_handle,
+        Some(b_addr),
+        &recv,
+        &mut Vec::new(),
+    ).unwrap();
+
+    let start = Instant::now();
+    let reply = loop {
+        assert!(Instant::now().duration_since(start) < Duration::from_secs(1));
+        a.tick();
+        if let Some(reply) = a.recv() {
+            break reply;
+        }
+    };
+
+    assert_eq!(reply, (b"pong".to_vec(), b_addr));
+    assert_eq!(&listener.join().unwrap()[..], b"ping");
+}
+
+#[test]
+fn channel_http_server() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 80,
+    };
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let server = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let listener = socket_env.tcp_listener(b_addr, 4, 4).unwrap();
+        let listener_handle = b
+            .socket_set
+            .add_socket(TaggedSocket::TcpListener(listener))
+            .unwrap();
+
+        http_server(
+            &mut b.interface,
+            &mut b.socket_set,
+            listener_handle,
+            |request| match request.path.as_str() {
+                "/" => HttpResponse::ok("text/plain", b"hello".to_vec()),
+                _ => HttpResponse::error(404, "Not Found"),
+            },
+            || stopped.try_recv().is_err(),
+        );
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+
+    let mut get = |request: &[u8]| {
+        let tcp_socket = socket_env
+            .tcp_socket_ephemeral(*a.interface.ipv4_addr)
+            .unwrap();
+        let tcp_handle = a
+            .socket_set
+            .add_socket(TaggedSocket::Tcp(tcp_socket))
+            .unwrap();
+
+        let (send, recv) = mpsc::channel();
+        send.send(request.to_vec()).unwrap();
+        drop(send);
+
+        let mut output = Vec::new();
+        nc_connect(
+            &mut a.interface,
+            &mut a.socket_set,
+            tcp_handle,
+            b_addr,
+            &recv,
+            &mut output,
+        ).unwrap();
+        a.socket_set.remove(tcp_handle).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    let response = get(b"GET / HTTP/1.0\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.0 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nhello"));
+
+    let response = get(b"GET /missing HTTP/1.0\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.0 404 Not Found\r\n"));
+
+    let response = get(b"POST / HTTP/1.0\r\n\r\n");
+    assert!(response.starts_with("HTTP/1.0 501 Not Implemented\r\n"));
+
+    stop.send(()).unwrap();
+    server.join().unwrap();
+}
+
+fn tftp_transfers(config: ChannelConfig) {
+    let (dev_a, dev_b) = ChannelDevice::pair_with_config(config, SystemEnv::new());
+    let b_addr = SocketAddr {
+        addr: Ipv4Address::new([10, 0, 0, 103]),
+        port: 4096,
+    };
+    let tftp_config = TftpConfig {
+        timeout: Duration::from_millis(100),
+        retries: 20,
+    };
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let server = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let mut files = BTreeMap::new();
+        files.insert("hello.txt".to_string(), b"hello".to_vec());
+        // Fills its last block, so is terminated by an empty one.
+        files.insert("blocks.bin".to_string(), vec![7; 1024]);
+
+        let udp_handle = b.udp_handle;
+        tftp_server(
+            &mut b.interface,
+            &mut b.socket_set,
+            udp_handle,
+            &mut files,
+            tftp_config,
+            || stopped.try_recv().is_err(),
+        );
+        files
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let udp_handle = a.udp_handle;
+
+    let get = |a: &mut Stack, filename: &str| {
+        tftp_get(
+            &mut a.interface,
+            &mut a.socket_set,
+            udp_handle,
+            b_addr,
+            filename,
+            tftp_config,
+        )
+    };
+
+    assert_eq!(get(&mut a, "hello.txt").unwrap(), b"hello");
+    assert_eq!(get(&mut a, "blocks.bin").unwrap(), vec![7; 1024]);
+    match get(&mut a, "missing.txt") {
+        Err(Error::Remote(RemoteError::Tftp(code))) => {
+            assert_eq!(code, tftp_error_codes::FILE_NOT_FOUND)
+        }
+        _ => panic!("Missing file was read."),
+    }
+
+    let file: Vec<u8> = (0 .. 1500).map(|i| i as u8).collect();
+    tftp_put(
+        &mut a.interface,
+        &mut a.socket_set,
+        udp_handle,
+        b_addr,
+        "upload.bin",
+        &file,
+        tftp_config,
+    ).unwrap();
+    assert_eq!(get(&mut a, "upload.bin").unwrap(), file);
+
+    stop.send(()).unwrap();
+    assert_eq!(server.join().unwrap()["upload.bin"], file);
+}
+
+#[test]
+fn channel_tftp() {
+    tftp_transfers(ChannelConfig::default());
+}
+
+#[test]
+fn channel_tftp_with_loss() {
+    tftp_transfers(ChannelConfig {
+        loss: 0.2,
+        ..ChannelConfig::default()
+    });
+}
+
+#[test]
+fn channel_dhcp_server() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let (ready, is_ready) = mpsc::channel::<()>();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    let server = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let mut udp_socket = socket_env
+            .udp_socket(SocketAddr {
+                addr: *b.interface.ipv4_addr,
+                port: dhcpv4_ports::SERVER,
+            })
+            .unwrap();
+        udp_socket.set_broadcast(true);
+        let udp_handle = b
+            .socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        let mut server = DhcpServer::new(DhcpServerConfig {
+            pool_start: Ipv4Address::new([10, 0, 0, 150]),
+            pool_len: 10,
+            subnet_mask: Ipv4Address::new([255, 255, 255, 0]),
+            router: Some(*b.interface.ipv4_addr),
+            dns_servers: vec![*b.interface.ipv4_addr],
+            lease_duration: Duration::from_secs(3600),
+        });
+
+        ready.send(()).unwrap();
+        dhcp_server(
+            &mut b.interface,
+            &mut b.socket_set,
+            udp_handle,
+            &mut server,
+            || stopped.try_recv().is_err(),
+        );
+        server
+    });
+
+    is_ready.recv().unwrap();
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let mut client = Dhcpv4Client::new(&socket_env, &mut a.socket_set, SystemEnv::new()).unwrap();
+
+    let start = Instant::now();
+    while client.state() != Dhcpv4State::Bound {
+        assert!(Instant::now().duration_since(start) < Duration::from_secs(1));
+        client.poll(&mut a.interface, &mut a.socket_set).unwrap();
+        a.tick();
+    }
+
+    let b_addr = Ipv4Address::new([10, 0, 0, 103]);
+    assert_eq!(
+        a.interface.ipv4_addr,
+        Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 150]), 24)
+    );
+    assert_eq!(a.interface.default_gateway, b_addr);
+    assert_eq!(a.interface.dns_servers, vec![b_addr]);
+
+    stop.send(()).unwrap();
+    let server = server.join().unwrap();
+    let leases = server.leases(Instant::now());
+    assert_eq!(leases.len(), 1);
+    assert_eq!(leases[0].client_hw_addr, a.interface.ethernet_addr);
+    assert!(leases[0].bound);
+}
+
+#[test]
+fn channel_pinger() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    // b answers echo requests while ticking.
+    let responder = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        while stopped.try_recv().is_err() {
+            b.tick();
+        }
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let icmp_socket = socket_env.icmp_socket(*a.interface.ipv4_addr, 7).unwrap();
+    let icmp_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Icmp(icmp_socket))
+        .unwrap();
+
+    let config = PingConfig {
+        count: 4,
+        interval: Duration::from_millis(20),
+        timeout: Duration::from_millis(500),
+        payload_len: 100,
+        pattern: vec![0xAB, 0xCD],
+    };
+    let mut pinger = Pinger::new(icmp_handle, Ipv4Address::new([10, 0, 0, 103]), config);
+
+    let start = Instant::now();
+    let mut seqs = Vec::new();
+    let stats = pinger.run(&mut a.interface, &mut a.socket_set, |seq, _| {
+        seqs.push(seq)
+    });
+
+    // The interval is waited out between requests, but not after the last.
+    assert!(Instant::now().duration_since(start) >= Duration::from_millis(60));
+    assert_eq!(seqs, vec![0, 1, 2, 3]);
+    assert_eq!(stats.transmitted(), 4);
+    assert_eq!(stats.received(), 4);
+    assert_eq!(stats.loss_percent(), 0.0);
+    assert!(stats.min().unwrap() <= stats.avg().unwrap());
+    assert!(stats.avg().unwrap() <= stats.max().unwrap());
+
+    // Nothing answers for an unassigned address.
+    let config = PingConfig {
+        count: 2,
+        interval: Duration::from_secs(0),
+        timeout: Duration::from_millis(50),
+        ..PingConfig::default()
+    };
+    let mut pinger = Pinger::new(icmp_handle, Ipv4Address::new([10, 0, 0, 104]), config);
+    let stats = pinger.run(&mut a.interface, &mut a.socket_set, |_, _| {});
+    assert_eq!(stats.received(), 0);
+    assert_eq!(stats.loss_percent(), 100.0);
+
+    stop.send(()).unwrap();
+    responder.join().unwrap();
+}
+
+#[test]
+fn channel_traceroute_modes() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    // b answers probes while ticking, with a TCP listener on port 80.
+    let responder = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        let socket_env = env::socket_env(&mut b.interface);
+        let tcp_listener = socket_env
+            .tcp_listener(
+                SocketAddr {
+                    addr: *b.interface.ipv4_addr,
+                    port: 80,
+                },
+                4,
+                4,
+            )
+            .unwrap();
+        b.socket_set
+            .add_socket(TaggedSocket::TcpListener(tcp_listener))
+            .unwrap();
+
+        while stopped.try_recv().is_err() {
+            b.tick();
+        }
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let raw_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Raw(socket_env.raw_socket(RawType::Ipv4)))
+        .unwrap();
+    let b_addr = Ipv4Address::new([10, 0, 0, 103]);
+
+    for mode in [TraceMode::Udp, TraceMode::Icmp, TraceMode::TcpSyn(80)].iter() {
+        let config = TraceConfig {
+            mode: *mode,
+            probes: 2,
+            payload_len: 32,
+            max_ttl: 3,
+            timeout: Duration::from_millis(500),
+        };
+
+        let mut hops = Vec::new();
+        let reached = traceroute_with(
+            &mut a.interface,
+            &mut a.socket_set,
+            raw_handle,
+            b_addr,
+            &config,
+            |ttl, probes| hops.push((ttl, probes.to_vec())),
+        );
+
+        assert!(reached.is_some(), "{:?} did not reach {}", mode, b_addr);
+        assert_eq!(hops.len(), 1);
+        assert_eq!(hops[0].0, 1);
+        assert_eq!(hops[0].1.len(), 2);
+        for probe in hops[0].1.iter() {
+            assert_eq!(probe.unwrap().1, b_addr);
+        }
+    }
+
+    stop.send(()).unwrap();
+    responder.join().unwrap();
+}
+
+#[test]
+fn channel_arpscan() {
+    let (dev_a, dev_b) = ChannelDevice::pair();
+    let (stop, stopped) = mpsc::channel::<()>();
+
+    // b answers ARP requests while ticking.
+    let responder = thread::spawn(move || {
+        let mut b = Stack::new(dev_b, 103);
+        while stopped.try_recv().is_err() {
+            b.tick();
+        }
+    });
+
+    let mut a = Stack::new(dev_a, 102);
+    let socket_env = env::socket_env(&mut a.interface);
+    let raw_socket = socket_env.raw_socket(RawType::Ethernet);
+    let raw_handle = a
+        .socket_set
+        .add_socket(TaggedSocket::Raw(raw_socket))
+        .unwrap();
+
+    // Sweeping a /24 sends more requests than the socket buffers at once.
+    let config = ArpScanConfig {
+        timeout: Duration::from_millis(200),
+        retries: 0,
+    };
+    let mut replies = 0;
+    let hosts = arpscan(
+        &mut a.interface,
+        &mut a.socket_set,
+        raw_handle,
+        Ipv4AddressCidr::new(Ipv4Address::new([10, 0, 0, 0]), 24),
+        config,
+        |_| replies += 1,
+    );
+
+    stop.send(()).unwrap();
+    responder.join().unwrap();
+
+    assert_eq!(replies, 1);
+    assert_eq!(hosts.len(), 1);
+    assert_eq!(hosts[0].ipv4_addr, Ipv4Address::new([10, 0, 0, 103]));
+    assert_eq!(
+        hosts[0].eth_addr,
+        EthernetAddress::new([0x06, 0, 0, 0, 0, 103])
+    );
+    assert_eq!(hosts[0].vendor, None);
+}
@@ -7,23 +7,23 @@ use std::process::{
 use std::sync::{
     Mutex,
     Once,
-    ONCE_INIT,
 };
 use std::thread;
 use std::time::Duration;
 
 use rand;
 
-use usrnet::core::service::Interface;
-use usrnet::core::socket::{
+use usrnet::examples::*;
+use usrnet::stack::service::Interface;
+use usrnet::stack::socket::{
     SocketEnv,
     SocketSet,
 };
-use usrnet::core::time::SystemEnv;
-use usrnet::examples::*;
+use usrnet::stack::time::SystemEnv;
+
+static INIT: Once = Once::new();
 
 lazy_static! {
-    static ref INIT: Once = ONCE_INIT;
     static ref TEST: Mutex<()> = { Mutex::new(()) };
     static ref PORT: Mutex<u16> = { Mutex::new(rand::random::<u16>()) };
     pub static ref ONE_SEC: Duration = { Duration::from_secs(1) };
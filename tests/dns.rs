@@ -0,0 +1,131 @@
+extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate usrnet;
+
+mod context;
+
+use std::net::UdpSocket;
+use std::sync::Once;
+use std::thread;
+
+use usrnet::stack::repr::{
+    dns_classes,
+    dns_flags,
+    dns_rcodes,
+    dns_types,
+    DnsPacket,
+    DnsRecord,
+    DnsRecordData,
+    DnsRepr,
+    Ipv4Address,
+};
+use usrnet::stack::service::dns::Resolver;
+use usrnet::stack::time::SystemEnv;
+use usrnet::examples::*;
+use usrnet::{
+    Error,
+    RemoteError,
+};
+
+static DNS_SERVER: Once = Once::new();
+
+/// Runs a DNS server on the host which knows a single name, answering via a
+/// CNAME, and responds with a name error for any other name.
+fn dns_server() {
+    DNS_SERVER.call_once(|| {
+        let socket = UdpSocket::bind((env::DEFAULT_IPV4_GATEWAY.to_string().as_str(), 53)).unwrap();
+
+        thread::spawn(move || loop {
+            let mut buffer = [0; 512];
+            let (len, addr) = socket.recv_from(&mut buffer).unwrap();
+
+            let mut dns_repr =
+                DnsRepr::deserialize(&DnsPacket::try_new(&buffer[.. len]).unwrap()).unwrap();
+            dns_repr.flags |= dns_flags::RESPONSE;
+
+            if dns_repr.questions[0].name == "www.example.com" {
+                dns_repr.answers.push(DnsRecord {
+                    name: "www.example.com".to_string(),
+                    class: dns_classes::IN,
+                    ttl: 60,
+                    data: DnsRecordData::Cname("host.example.com".to_string()),
+                });
+                dns_repr.answers.push(DnsRecord {
+                    name: "host.example.com".to_string(),
+                    class: dns_classes::IN,
+                    ttl: 60,
+                    data: DnsRecordData::A(Ipv4Address::new([10, 0, 0, 200])),
+                });
+            } else {
+                dns_repr.rcode = dns_rcodes::NAME_ERROR;
+            }
+
+            let mut response = vec![0; dns_repr.buffer_len().unwrap()];
+            dns_repr
+                .serialize(&mut DnsPacket::try_new(&mut response[..]).unwrap())
+                .unwrap();
+            socket.send_to(&response, addr).unwrap();
+        });
+    });
+}
+
+fn lookup(
+    context: &mut context::Context,
+    server_addr: Ipv4Address,
+    name: &str,
+) -> usrnet::Result<Vec<DnsRecordData>> {
+    dns_server();
+
+    context.interface.dns_servers = vec![server_addr];
+
+    let mut resolver = Resolver::new(
+        &context.socket_env,
+        &mut context.socket_set,
+        500,
+        SystemEnv::new(),
+    )
+    .unwrap();
+
+    dns_lookup(
+        &mut context.interface,
+        &mut context.socket_set,
+        &mut resolver,
+        name,
+        dns_types::A,
+    )
+}
+
+#[test]
+fn dns_lookup_address() {
+    context::run(|context| {
+        let records = lookup(context, *env::DEFAULT_IPV4_GATEWAY, "www.example.com").unwrap();
+        assert_eq!(
+            records,
+            vec![DnsRecordData::A(Ipv4Address::new([10, 0, 0, 200]))]
+        );
+    });
+}
+
+#[test]
+fn dns_lookup_name_error() {
+    context::run(|context| {
+        match lookup(context, *env::DEFAULT_IPV4_GATEWAY, "missing.example.com") {
+            Err(Error::Remote(RemoteError::Dns(rcode))) => {
+                assert_eq!(rcode, dns_rcodes::NAME_ERROR)
+            }
+            result => panic!("Unexpected result {:?}!", result),
+        }
+    });
+}
+
+#[test]
+fn dns_lookup_timeout() {
+    context::run(
+        |context| match lookup(context, *env::NO_HOST_IPV4_ADDR, "www.example.com") {
+            Err(Error::Timeout) => {}
+            result => panic!("Unexpected result {:?}!", result),
+        },
+    );
+}
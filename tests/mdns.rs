@@ -0,0 +1,161 @@
+extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate usrnet;
+
+mod context;
+
+use std::net::{
+    Ipv4Addr as StdIpv4Addr,
+    UdpSocket,
+};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use usrnet::stack::repr::{
+    dns_classes,
+    dns_flags,
+    dns_ports,
+    dns_types,
+    DnsPacket,
+    DnsRecord,
+    DnsRecordData,
+    DnsRepr,
+    Ipv4Address,
+};
+use usrnet::stack::service::mdns::Mdns;
+use usrnet::stack::time::SystemEnv;
+use usrnet::examples::*;
+
+fn send_dns(socket: &UdpSocket, dns_repr: &DnsRepr, addr: (StdIpv4Addr, u16)) {
+    let mut buffer = vec![0; dns_repr.buffer_len().unwrap()];
+    dns_repr
+        .serialize(&mut DnsPacket::try_new(&mut buffer[..]).unwrap())
+        .unwrap();
+    socket.send_to(&buffer, addr).unwrap();
+}
+
+#[test]
+fn mdns_answer_legacy_query() {
+    context::run(|context| {
+        let mut mdns = Mdns::new(
+            &context.socket_env,
+            &mut context.socket_set,
+            "usrnet",
+            1000,
+            SystemEnv::new(),
+        )
+        .unwrap();
+
+        let (send, recv) = mpsc::channel();
+        let interface_addr: StdIpv4Addr = (*context.interface.ipv4_addr).into();
+
+        thread::spawn(move || {
+            let socket =
+                UdpSocket::bind((env::DEFAULT_IPV4_GATEWAY.to_string().as_str(), 0)).unwrap();
+            socket
+                .set_read_timeout(Some(Duration::from_millis(250)))
+                .unwrap();
+            let query = DnsRepr::query(4321, "usrnet.local", dns_types::A);
+            for _ in 0 .. 4 {
+                send_dns(&socket, &query, (interface_addr, dns_ports::MDNS));
+                let mut buffer = [0; 512];
+                if let Ok((len, _)) = socket.recv_from(&mut buffer) {
+                    let packet = DnsPacket::try_new(&buffer[.. len]).unwrap();
+                    send.send(DnsRepr::deserialize(&packet).unwrap()).unwrap();
+                    return;
+                }
+            }
+        });
+
+        let start_at = Instant::now();
+        let response = loop {
+            assert!(Instant::now().duration_since(start_at) < *context::ONE_SEC * 2);
+            mdns.poll(&context.interface, &mut context.socket_set)
+                .unwrap();
+            env::tick(&mut context.interface, &mut context.socket_set);
+            if let Ok(response) = recv.try_recv() {
+                break response;
+            }
+        };
+
+        assert_eq!(response.id, 4321);
+        assert_eq!(
+            response.answers,
+            vec![DnsRecord {
+                name: "usrnet.local".to_string(),
+                class: dns_classes::IN,
+                ttl: 10,
+                data: DnsRecordData::A(*context.interface.ipv4_addr),
+            }]
+        );
+    });
+}
+
+#[test]
+fn mdns_resolve_peer() {
+    context::run(|context| {
+        let mut mdns = Mdns::new(
+            &context.socket_env,
+            &mut context.socket_set,
+            "usrnet",
+            250,
+            SystemEnv::new(),
+        )
+        .unwrap();
+
+        // Respond to queries for peer.local on the host side of the link.
+        let gateway_addr: StdIpv4Addr = (*env::DEFAULT_IPV4_GATEWAY).into();
+        let socket = UdpSocket::bind(("0.0.0.0", dns_ports::MDNS)).unwrap();
+        socket
+            .join_multicast_v4(&Ipv4Address::MDNS.into(), &gateway_addr)
+            .unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+
+        let handle = mdns.resolve("peer").unwrap();
+        let start_at = Instant::now();
+
+        let addrs = loop {
+            assert!(Instant::now().duration_since(start_at) < *context::ONE_SEC * 2);
+            mdns.poll(&context.interface, &mut context.socket_set)
+                .unwrap();
+            env::tick(&mut context.interface, &mut context.socket_set);
+
+            if let Some(result) = mdns.take_result(handle) {
+                break result.unwrap();
+            }
+
+            let mut buffer = [0; 512];
+            if let Ok((len, src_addr)) = socket.recv_from(&mut buffer) {
+                let query =
+                    DnsRepr::deserialize(&DnsPacket::try_new(&buffer[.. len]).unwrap()).unwrap();
+                if query.is_response() || query.questions[0].name != "peer.local" {
+                    continue;
+                }
+                let mut response = query.clone();
+                response.flags = dns_flags::RESPONSE | dns_flags::AUTHORITATIVE;
+                response.questions.clear();
+                response.answers.push(DnsRecord {
+                    name: "peer.local".to_string(),
+                    class: dns_classes::IN | dns_classes::CACHE_FLUSH,
+                    ttl: 120,
+                    data: DnsRecordData::A(*env::DEFAULT_IPV4_GATEWAY),
+                });
+                let dst_addr = match src_addr {
+                    ::std::net::SocketAddr::V4(addr) => (*addr.ip(), addr.port()),
+                    _ => unreachable!(),
+                };
+                send_dns(&socket, &response, dst_addr);
+            }
+        };
+
+        assert_eq!(addrs, vec![*env::DEFAULT_IPV4_GATEWAY]);
+    });
+}
@@ -0,0 +1,64 @@
+extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate usrnet;
+
+mod context;
+
+use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use usrnet::stack::repr::{
+    EthernetAddress,
+    Ipv6Address,
+};
+use usrnet::stack::service::{
+    arp,
+    ndp,
+    Interface,
+};
+use usrnet::stack::socket::SocketSet;
+use usrnet::examples::*;
+use usrnet::Result;
+
+fn resolve<F>(interface: &mut Interface, socket_set: &mut SocketSet, mut f: F) -> EthernetAddress
+where
+    F: FnMut(&mut Interface) -> Result<EthernetAddress>,
+{
+    let start_at = Instant::now();
+
+    while Instant::now().duration_since(start_at) < *context::ONE_SEC {
+        if let Ok(eth_addr) = f(interface) {
+            return eth_addr;
+        }
+        env::tick(interface, socket_set);
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    panic!("Timeout resolving Ethernet address!");
+}
+
+#[test]
+fn ndp_resolve_default_gateway() {
+    context::run(|context| {
+        let eth_addr = resolve(
+            &mut context.interface,
+            &mut context.socket_set,
+            |interface| arp::eth_addr_for_ip(interface, *env::DEFAULT_IPV4_GATEWAY),
+        );
+
+        // Linux derives the link local address of the bridge from its MAC.
+        let ipv6_addr = Ipv6Address::link_local(eth_addr);
+        let ndp_eth_addr = resolve(
+            &mut context.interface,
+            &mut context.socket_set,
+            |interface| ndp::eth_addr_for_ip(interface, ipv6_addr),
+        );
+
+        assert_eq!(ndp_eth_addr, eth_addr);
+    });
+}
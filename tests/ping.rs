@@ -13,18 +13,18 @@ use std::time::{
     Instant,
 };
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
-    RawType,
-    TaggedSocket,
-};
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::TaggedSocket;
 use usrnet::examples::*;
 
 fn ping_addr(context: &mut context::Context, addr: Ipv4Address) -> Option<Duration> {
-    let raw_socket = context.socket_env.raw_socket(RawType::Ipv4);
-    let raw_handle = context
+    let icmp_socket = context
+        .socket_env
+        .icmp_socket(*context.interface.ipv4_addr, rand::random::<u16>())
+        .unwrap();
+    let icmp_handle = context
         .socket_set
-        .add_socket(TaggedSocket::Raw(raw_socket))
+        .add_socket(TaggedSocket::Icmp(icmp_socket))
         .unwrap();
 
     let mut payload = [0; 64];
@@ -35,9 +35,8 @@ fn ping_addr(context: &mut context::Context, addr: Ipv4Address) -> Option<Durati
     ping(
         &mut context.interface,
         &mut context.socket_set,
-        raw_handle,
+        icmp_handle,
         addr,
-        rand::random::<u16>(),
         0,
         &payload,
         *context::ONE_SEC,
@@ -0,0 +1,120 @@
+#![cfg(target_os = "linux")]
+
+extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
+extern crate libc;
+extern crate rand;
+extern crate usrnet;
+
+mod context;
+
+use std::time::{
+    Duration,
+    Instant,
+};
+
+use usrnet::stack::dev::Device;
+use usrnet::stack::repr::{
+    EthernetAddress,
+    EthernetFrame,
+};
+use usrnet::linux::raw_socket::RawSocketDev;
+
+/// Local experimental Ethertype which the host ignores.
+static TEST_ETH_TYPE: u16 = 0x88B5;
+
+/// Host side of the bridge the TAP is attached to. See tap.sh for more info.
+static BRIDGE_NAME: &str = "br0";
+
+fn test_frame(payload: &[u8]) -> Vec<u8> {
+    let mut buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(payload.len())];
+    {
+        let mut frame = EthernetFrame::try_new(&mut buffer[..]).unwrap();
+        frame.set_dst_addr(EthernetAddress::BROADCAST);
+        frame.set_src_addr(EthernetAddress::new([0x06, 0, 0, 0, 0, 0x01]));
+        frame.set_payload_type(TEST_ETH_TYPE);
+        frame.payload_mut().copy_from_slice(payload);
+    }
+    buffer
+}
+
+/// Waits up to a second for a device to receive a frame with a payload.
+fn recv_frame(dev: &mut dyn Device, payload: &[u8]) -> bool {
+    let mut buffer = vec![0; 2048];
+    let start = Instant::now();
+
+    while Instant::now().duration_since(start) < *context::ONE_SEC {
+        if let Ok(len) = dev.recv(&mut buffer) {
+            let frame = EthernetFrame::try_new(&buffer[.. len]).unwrap();
+            if frame.payload_type() == TEST_ETH_TYPE && frame.payload().starts_with(payload) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+#[test]
+fn raw_socket_recv() {
+    context::run(|context| {
+        let mut raw_dev = RawSocketDev::new(BRIDGE_NAME, false);
+        let payload = rand::random::<[u8; 32]>();
+        context.interface.dev.send(&test_frame(&payload)).unwrap();
+        assert!(recv_frame(&mut raw_dev, &payload));
+    });
+}
+
+#[test]
+fn raw_socket_send() {
+    context::run(|context| {
+        let mut raw_dev = RawSocketDev::new(BRIDGE_NAME, true);
+        let payload = rand::random::<[u8; 32]>();
+        raw_dev.send(&test_frame(&payload)).unwrap();
+        assert!(recv_frame(&mut *context.interface.dev, &payload));
+        // Frames sent via the socket are not received back.
+        assert!(!recv_frame(&mut raw_dev, &payload));
+    });
+}
+
+#[test]
+fn raw_socket_and_tap_wait() {
+    context::run(|context| {
+        let mut raw_dev = RawSocketDev::new(BRIDGE_NAME, false);
+        let payload = rand::random::<[u8; 32]>();
+
+        raw_dev.send(&test_frame(&payload)).unwrap();
+        let start = Instant::now();
+        context
+            .interface
+            .poll_wait(Some(Duration::from_secs(5)))
+            .unwrap();
+        assert!(Instant::now().duration_since(start) < *context::ONE_SEC);
+
+        context.interface.dev.send(&test_frame(&payload)).unwrap();
+        let start = Instant::now();
+        raw_dev.wait(Some(Duration::from_secs(5))).unwrap();
+        assert!(Instant::now().duration_since(start) < *context::ONE_SEC);
+    });
+}
+
+#[test]
+fn tap_raw_fd() {
+    context::run(|context| {
+        let mut raw_dev = RawSocketDev::new(BRIDGE_NAME, false);
+        let payload = rand::random::<[u8; 32]>();
+
+        let mut pollfd = libc::pollfd {
+            fd: context.interface.dev.raw_fd().unwrap(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+
+        // Wait like an external event loop would.
+        raw_dev.send(&test_frame(&payload)).unwrap();
+        let ready = unsafe { libc::poll(&mut pollfd as *mut libc::pollfd, 1, 5000) };
+        assert_eq!(ready, 1);
+        assert!(recv_frame(&mut *context.interface.dev, &payload));
+    });
+}
@@ -0,0 +1,105 @@
+extern crate usrnet;
+
+use usrnet::stack::repr::{
+    eth_types,
+    EthernetFrame,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    UdpPacket,
+    UdpRepr,
+};
+use usrnet::testing;
+
+/// Number of random cases per property, each run with its own seed so a
+/// failure can be reproduced via testing::rng(seed).
+const CASES: u32 = 500;
+
+#[test]
+fn roundtrip_ipv4() {
+    for seed in 0 .. CASES {
+        let mut rng = testing::rng(seed);
+        let payload = testing::payload(&mut rng, testing::MAX_PAYLOAD_LEN);
+        let ipv4_repr = testing::ipv4_repr(&mut rng, Ipv4Protocol::UDP, payload.len());
+
+        let (reparsed, reparsed_payload) = testing::reparse_ipv4(&ipv4_repr, &payload).unwrap();
+        assert_eq!(reparsed, ipv4_repr, "seed {}", seed);
+        assert_eq!(reparsed_payload, payload, "seed {}", seed);
+    }
+}
+
+#[test]
+fn roundtrip_tcp() {
+    for seed in 0 .. CASES {
+        let mut rng = testing::rng(seed);
+        let payload = testing::payload(&mut rng, testing::MAX_PAYLOAD_LEN);
+        let tcp_repr = testing::tcp_repr(&mut rng);
+        let ipv4_repr = testing::ipv4_repr(&mut rng, Ipv4Protocol::TCP, 0);
+
+        let (reparsed, reparsed_payload) =
+            testing::reparse_tcp(&tcp_repr, &payload, &ipv4_repr).unwrap();
+        assert_eq!(reparsed, tcp_repr, "seed {}", seed);
+        assert_eq!(reparsed_payload, payload, "seed {}", seed);
+    }
+}
+
+#[test]
+fn roundtrip_udp() {
+    for seed in 0 .. CASES {
+        let mut rng = testing::rng(seed);
+        let payload = testing::payload(&mut rng, testing::MAX_PAYLOAD_LEN);
+        let udp_repr = testing::udp_repr(&mut rng, payload.len());
+        let ipv4_repr = testing::ipv4_repr(&mut rng, Ipv4Protocol::UDP, 0);
+
+        let (reparsed, reparsed_payload) =
+            testing::reparse_udp(&udp_repr, &payload, &ipv4_repr).unwrap();
+        assert_eq!(reparsed, udp_repr, "seed {}", seed);
+        assert_eq!(reparsed_payload, payload, "seed {}", seed);
+    }
+}
+
+#[test]
+fn roundtrip_arp() {
+    for seed in 0 .. CASES {
+        let mut rng = testing::rng(seed);
+        let arp = testing::arp(&mut rng);
+
+        assert_eq!(testing::reparse_arp(&arp).unwrap(), arp, "seed {}", seed);
+    }
+}
+
+#[test]
+fn roundtrip_udp_over_ipv4_over_ethernet() {
+    for seed in 0 .. CASES {
+        let mut rng = testing::rng(seed);
+        let payload = testing::payload(&mut rng, testing::MAX_PAYLOAD_LEN);
+        let udp_repr = testing::udp_repr(&mut rng, payload.len());
+        let ipv4_repr = testing::ipv4_repr(&mut rng, Ipv4Protocol::UDP, udp_repr.buffer_len());
+        let (src_addr, dst_addr) = (testing::eth_addr(&mut rng), testing::eth_addr(&mut rng));
+
+        let udp_buffer = testing::udp_packet(&udp_repr, &payload, &ipv4_repr);
+        let ipv4_buffer = testing::ipv4_packet(&ipv4_repr, &udp_buffer);
+
+        let mut eth_buffer = vec![0; EthernetFrame::<&[u8]>::buffer_len(ipv4_buffer.len())];
+        {
+            let mut eth_frame = EthernetFrame::try_new(&mut eth_buffer[..]).unwrap();
+            eth_frame.set_src_addr(src_addr);
+            eth_frame.set_dst_addr(dst_addr);
+            eth_frame.set_payload_type(eth_types::IPV4);
+            eth_frame.payload_mut().copy_from_slice(&ipv4_buffer);
+        }
+
+        let eth_frame = EthernetFrame::try_new(&eth_buffer[..]).unwrap();
+        assert_eq!(eth_frame.src_addr(), src_addr, "seed {}", seed);
+        assert_eq!(eth_frame.dst_addr(), dst_addr, "seed {}", seed);
+
+        let ipv4_packet = Ipv4Packet::try_new(eth_frame.payload()).unwrap();
+        ipv4_packet.check_encoding().unwrap();
+        assert_eq!(Ipv4Repr::deserialize(&ipv4_packet).unwrap(), ipv4_repr, "seed {}", seed);
+
+        let udp_packet = UdpPacket::try_new(ipv4_packet.payload()).unwrap();
+        udp_packet.check_encoding(&ipv4_repr).unwrap();
+        assert_eq!(UdpRepr::deserialize(&udp_packet), udp_repr, "seed {}", seed);
+        assert_eq!(udp_packet.payload(), &payload[..], "seed {}", seed);
+    }
+}
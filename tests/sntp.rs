@@ -0,0 +1,130 @@
+extern crate env_logger;
+#[macro_use]
+extern crate lazy_static;
+extern crate rand;
+extern crate usrnet;
+
+mod context;
+
+use std::net::UdpSocket;
+use std::sync::Once;
+use std::thread;
+use std::time::{
+    Duration,
+    SystemTime,
+};
+
+use usrnet::stack::repr::{
+    ntp_leap_indicators,
+    ntp_modes,
+    ntp_ports,
+    Ipv4Address,
+    NtpPacket,
+    NtpRepr,
+    NtpTimestamp,
+};
+use usrnet::stack::socket::{
+    SocketAddr,
+    TaggedSocket,
+};
+use usrnet::examples::*;
+use usrnet::Error;
+
+/// Port of a time server which only responds with kiss-o'-death messages.
+static KOD_PORT: u16 = 1123;
+
+/// Offset of the time server clock relative to the host clock.
+static SERVER_OFFSET_SECS: u64 = 5;
+
+static NTP_SERVER: Once = Once::new();
+
+/// Runs time servers on the host, one with a clock ahead of the host's by
+/// SERVER_OFFSET_SECS and one refusing service.
+fn ntp_server() {
+    NTP_SERVER.call_once(|| {
+        for &(port, stratum) in [(ntp_ports::NTP, 2), (KOD_PORT, 0)].iter() {
+            let socket =
+                UdpSocket::bind((env::DEFAULT_IPV4_GATEWAY.to_string().as_str(), port)).unwrap();
+
+            thread::spawn(move || loop {
+                let mut buffer = [0; 48];
+                let (len, addr) = socket.recv_from(&mut buffer).unwrap();
+                let now = || {
+                    NtpTimestamp::from_system_time(
+                        SystemTime::now() + Duration::from_secs(SERVER_OFFSET_SECS),
+                    )
+                };
+
+                let request =
+                    NtpRepr::deserialize(&NtpPacket::try_new(&buffer[.. len]).unwrap()).unwrap();
+
+                let mut response = NtpRepr::request(request.transmit_timestamp);
+                response.leap_indicator = ntp_leap_indicators::NO_WARNING;
+                response.mode = ntp_modes::SERVER;
+                response.stratum = stratum;
+                response.originate_timestamp = request.transmit_timestamp;
+                response.receive_timestamp = now();
+                response.transmit_timestamp = now();
+
+                response.serialize(&mut NtpPacket::try_new(&mut buffer[..]).unwrap());
+                socket.send_to(&buffer, addr).unwrap();
+            });
+        }
+    });
+}
+
+fn query(
+    context: &mut context::Context,
+    addr: Ipv4Address,
+    port: u16,
+) -> usrnet::Result<SntpResult> {
+    ntp_server();
+
+    let udp_socket = context
+        .socket_env
+        .udp_socket_ephemeral(*context.interface.ipv4_addr)
+        .unwrap();
+    let udp_handle = context
+        .socket_set
+        .add_socket(TaggedSocket::Udp(udp_socket))
+        .unwrap();
+
+    sntp(
+        &mut context.interface,
+        &mut context.socket_set,
+        udp_handle,
+        SocketAddr { addr, port },
+        Duration::from_millis(500),
+    )
+}
+
+#[test]
+fn sntp_offset() {
+    context::run(|context| {
+        let result = query(context, *env::DEFAULT_IPV4_GATEWAY, ntp_ports::NTP).unwrap();
+        let expected_nanos = (SERVER_OFFSET_SECS * 1_000_000_000) as i64;
+        assert!((result.offset_nanos - expected_nanos).abs() < 100_000_000);
+        assert!(result.delay < Duration::from_millis(500));
+        assert_eq!(result.stratum, 2);
+    });
+}
+
+#[test]
+fn sntp_kiss_of_death() {
+    context::run(
+        |context| match query(context, *env::DEFAULT_IPV4_GATEWAY, KOD_PORT) {
+            Err(Error::Timeout) => {}
+            result => panic!("Unexpected result {:?}!", result),
+        },
+    );
+}
+
+#[test]
+fn sntp_timeout() {
+    context::run(
+        |context| match query(context, *env::NO_HOST_IPV4_ADDR, ntp_ports::NTP) {
+            Err(Error::Timeout) => {}
+            result => panic!("Unexpected result {:?}!", result),
+        },
+    );
+}
@@ -6,6 +6,11 @@ extern crate usrnet;
 
 mod context;
 
+use std::io::{
+    ErrorKind,
+    Read,
+    Write,
+};
 use std::net::{
     Shutdown,
     SocketAddr as StdSocketAddr,
@@ -18,12 +23,19 @@ use std::time::{
     Instant,
 };
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
     SocketAddr,
     TaggedSocket,
+    TcpListenerStats,
+    TcpOverflowPolicy,
 };
 use usrnet::examples::env;
+use usrnet::{
+    BufferError,
+    Error,
+    SocketError,
+};
 
 fn std_socket_addr(socket_addr: StdSocketAddr) -> Option<SocketAddr> {
     match socket_addr {
@@ -78,12 +90,14 @@ fn tcp_active_open(context: &mut context::Context, with_server: bool) {
     context
         .socket_set
         .socket(tcp_handle)
+        .unwrap()
         .as_tcp_socket()
         .connect(connect_addr);
 
     while context
         .socket_set
         .socket(tcp_handle)
+        .unwrap()
         .as_tcp_socket()
         .is_establishing()
     {
@@ -91,7 +105,11 @@ fn tcp_active_open(context: &mut context::Context, with_server: bool) {
     }
 
     // Check the socket status depending on if we started a server or not.
-    let tcp_socket = context.socket_set.socket(tcp_handle).as_tcp_socket();
+    let tcp_socket = context
+        .socket_set
+        .socket(tcp_handle)
+        .unwrap()
+        .as_tcp_socket();
     match server {
         Some(server) => {
             assert!(tcp_socket.is_connected());
@@ -116,26 +134,71 @@ fn tcp_active_open_reset() {
 }
 
 #[test]
-fn tcp_passive_open() {
+fn tcp_active_open_timeout() {
     context::run(|context| {
-        // Create a TcpSocket.
-        let server_addr = SocketAddr {
+        let client_addr = SocketAddr {
             addr: *env::DEFAULT_IPV4_ADDR,
             port: context::rand_port(),
         };
 
-        let tcp_socket = context.socket_env.tcp_socket(server_addr).unwrap();
+        let connect_addr = SocketAddr {
+            addr: *env::NO_HOST_IPV4_ADDR,
+            port: context::rand_port(),
+        };
+
+        let tcp_socket = context.socket_env.tcp_socket(client_addr).unwrap();
         let tcp_handle = context
             .socket_set
             .add_socket(TaggedSocket::Tcp(tcp_socket))
             .unwrap();
 
-        // Start a server with a tiny connection queue.
         context
             .socket_set
             .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket()
+            .connect_with_timeout(connect_addr, Duration::from_secs(2));
+
+        let begin = Instant::now();
+        while context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
             .as_tcp_socket()
-            .listen(2, 2);
+            .is_establishing()
+        {
+            env::tick(&mut context.interface, &mut context.socket_set);
+        }
+
+        assert!(Instant::now() - begin >= Duration::from_secs(2));
+
+        let tcp_socket = context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket();
+        assert!(tcp_socket.is_closed());
+        match tcp_socket.take_error() {
+            Some(Error::Timeout) => {}
+            err => panic!("Expected a timeout, got {:?}.", err),
+        }
+    });
+}
+
+#[test]
+fn tcp_passive_open() {
+    context::run(|context| {
+        // Start a server with a tiny connection queue.
+        let server_addr = SocketAddr {
+            addr: *env::DEFAULT_IPV4_ADDR,
+            port: context::rand_port(),
+        };
+
+        let tcp_listener = context.socket_env.tcp_listener(server_addr, 2, 2).unwrap();
+        let tcp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::TcpListener(tcp_listener))
+            .unwrap();
 
         // Create a small herd of clients trying to connect to the server.
         let clients: Vec<_> = (0 .. 4)
@@ -152,12 +215,14 @@ fn tcp_passive_open() {
         // Wait for all clients to have been granted a connection.
         let mut connected_clients = 0;
         while connected_clients != 4 {
-            if let Some(_) = context
+            if let Some((_, socket_addr)) = context
                 .socket_set
                 .socket(tcp_handle)
-                .as_tcp_socket()
+                .unwrap()
+                .as_tcp_listener()
                 .accept()
             {
+                assert_eq!(socket_addr.addr, *env::DEFAULT_IPV4_GATEWAY);
                 connected_clients += 1;
             }
             env::tick(&mut context.interface, &mut context.socket_set);
@@ -175,7 +240,8 @@ fn tcp_passive_open() {
                 context
                     .socket_set
                     .socket(tcp_handle)
-                    .as_tcp_socket()
+                    .unwrap()
+                    .as_tcp_listener()
                     .accept()
                     .is_none()
             );
@@ -183,3 +249,183 @@ fn tcp_passive_open() {
         }
     });
 }
+
+#[test]
+fn tcp_listener_overflow_reset() {
+    context::run(|context| {
+        // Start a server which can only hold a single established connection.
+        let server_addr = SocketAddr {
+            addr: *env::DEFAULT_IPV4_ADDR,
+            port: context::rand_port(),
+        };
+
+        let mut tcp_listener = context.socket_env.tcp_listener(server_addr, 1, 1).unwrap();
+        tcp_listener.set_overflow_policy(TcpOverflowPolicy::Reset);
+        let tcp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::TcpListener(tcp_listener))
+            .unwrap();
+
+        // The second client completes a handshake but is reset since the first was
+        // never accepted.
+        let client = thread::spawn(move || {
+            let server_addr = StdSocketAddr::V4(server_addr.into());
+            let _stream = TcpStream::connect(server_addr).unwrap();
+            let mut stream = TcpStream::connect(server_addr).unwrap();
+            stream.read(&mut [0; 1]).unwrap_err().kind()
+        });
+
+        while context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_listener()
+            .stats()
+            .resets_sent
+            == 0
+        {
+            env::tick(&mut context.interface, &mut context.socket_set);
+        }
+
+        assert_eq!(client.join().unwrap(), ErrorKind::ConnectionReset);
+
+        let tcp_listener = context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_listener();
+        assert!(tcp_listener.accept().is_some());
+        assert!(tcp_listener.accept().is_none());
+        assert_eq!(
+            tcp_listener.stats(),
+            TcpListenerStats {
+                accepted: 1,
+                dropped: 1,
+                resets_sent: 1,
+            }
+        );
+    });
+}
+
+#[test]
+fn tcp_half_close() {
+    context::run(|context| {
+        let eth0_addr = env::ifr_addr("eth0");
+
+        let client_addr = SocketAddr {
+            addr: *env::DEFAULT_IPV4_ADDR,
+            port: context::rand_port(),
+        };
+
+        let connect_addr = SocketAddr {
+            addr: Ipv4Address::new(eth0_addr.octets()),
+            port: context::rand_port(),
+        };
+
+        // Start a server which echoes everything back once the client is done sending.
+        let listener = TcpListener::bind(StdSocketAddr::V4(connect_addr.into())).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = Vec::new();
+            stream.read_to_end(&mut buffer).unwrap();
+            stream.write_all(&buffer).unwrap();
+        });
+
+        // Connect a TcpSocket to the server.
+        let tcp_socket = context.socket_env.tcp_socket(client_addr).unwrap();
+        let tcp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Tcp(tcp_socket))
+            .unwrap();
+
+        context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket()
+            .connect(connect_addr);
+
+        while context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket()
+            .is_establishing()
+        {
+            env::tick(&mut context.interface, &mut context.socket_set);
+        }
+
+        // Send some data and shutdown writing, the server only echoes after a FIN.
+        let data: Vec<u8> = (0 .. 4096).map(|i| i as u8).collect();
+        {
+            let tcp_socket = context
+                .socket_set
+                .socket(tcp_handle)
+                .unwrap()
+                .as_tcp_socket();
+            assert!(tcp_socket.can_send());
+            assert!(!tcp_socket.can_recv());
+            let send_capacity = tcp_socket.send_capacity();
+            assert_eq!(tcp_socket.send(&data).unwrap(), data.len());
+            assert_eq!(tcp_socket.send_capacity(), send_capacity - data.len());
+            tcp_socket.shutdown(Shutdown::Write);
+            assert!(!tcp_socket.can_send());
+            match tcp_socket.send(&data) {
+                Err(Error::Socket(SocketError::NotConnected)) => {}
+                res => panic!("Expected NotConnected after shutdown, got {:?}.", res),
+            }
+        }
+
+        // We should still be able to read until the server closes its half.
+        let mut echoed = Vec::new();
+        let mut buffer = [0; 1024];
+        loop {
+            env::tick(&mut context.interface, &mut context.socket_set);
+            match context
+                .socket_set
+                .socket(tcp_handle)
+                .unwrap()
+                .as_tcp_socket()
+                .recv(&mut buffer)
+            {
+                Ok(0) => break,
+                Ok(n) => echoed.extend_from_slice(&buffer[.. n]),
+                Err(Error::Buffer(BufferError::Empty)) => {}
+                Err(err) => panic!("Unexpected error {:?}.", err),
+            }
+        }
+
+        assert_eq!(echoed, data);
+        server.join().unwrap();
+
+        {
+            let tcp_socket = context
+                .socket_set
+                .socket(tcp_handle)
+                .unwrap()
+                .as_tcp_socket();
+            assert_eq!(tcp_socket.recv_queued(), 0);
+            assert!(tcp_socket.can_recv());
+        }
+
+        let stats = context
+            .socket_set
+            .socket(tcp_handle)
+            .unwrap()
+            .as_tcp_socket()
+            .stats();
+        assert!(stats.segments_sent >= 4);
+        assert!(stats.segments_received >= 4);
+        assert!(stats.rtt_estimate.is_some());
+        assert!(stats.congestion_window > 0);
+
+        // The client lingers in TIME_WAIT, so its address can only be reused
+        // when explicitly allowed.
+        match context.socket_env.tcp_socket(client_addr) {
+            Err(Error::Socket(SocketError::BindingInUse(_))) => {}
+            res => panic!("Expected BindingInUse during TIME_WAIT, got {:?}.", res),
+        }
+        context.socket_env.set_reuse_addr(true);
+        assert!(context.socket_env.tcp_socket(client_addr).is_ok());
+    });
+}
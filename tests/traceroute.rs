@@ -13,8 +13,8 @@ use std::time::{
     Instant,
 };
 
-use usrnet::core::repr::Ipv4Address;
-use usrnet::core::socket::{
+use usrnet::stack::repr::Ipv4Address;
+use usrnet::stack::socket::{
     RawType,
     TaggedSocket,
 };
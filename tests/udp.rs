@@ -7,17 +7,41 @@ extern crate usrnet;
 mod context;
 
 use std::net::{
+    IpAddr as StdIpAddr,
     SocketAddr as StdSocketAddr,
+    SocketAddrV4 as StdSocketAddrV4,
     UdpSocket,
 };
 use std::sync::mpsc;
 use std::thread;
+use std::time::{
+    Duration,
+    Instant,
+};
 
-use usrnet::core::socket::{
+use usrnet::stack::repr::{
+    ipv4_flags,
+    Icmpv4DestinationUnreachable,
+    Icmpv4Message,
+    Ipv4Address,
+    Ipv4Options,
+    Ipv4Packet,
+    Ipv4Protocol,
+    Ipv4Repr,
+    UdpPacket,
+    UdpRepr,
+};
+use usrnet::stack::socket::{
+    RawType,
     SocketAddr,
     TaggedSocket,
 };
+use usrnet::examples::env;
 use usrnet::examples::udp_echo as _udp_echo;
+use usrnet::{
+    Error,
+    RemoteError,
+};
 
 pub const PAYLOAD_SIZE: usize = 128;
 
@@ -87,3 +111,319 @@ fn udp_echo() {
         );
     });
 }
+
+#[test]
+fn udp_connected() {
+    context::run(|context| {
+        let server_addr = SocketAddr {
+            addr: *context.interface.ipv4_addr,
+            port: context::rand_port(),
+        };
+        let std_server_addr = StdSocketAddr::V4(server_addr.into());
+
+        let peer = UdpSocket::bind("0:0").unwrap();
+        let stranger = UdpSocket::bind("0:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+
+        let mut udp_socket = context.socket_env.udp_socket(server_addr).unwrap();
+        udp_socket.connect(SocketAddr {
+            addr: *env::DEFAULT_IPV4_GATEWAY,
+            port: peer.local_addr().unwrap().port(),
+        });
+        let udp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        // Only packets from the connected peer should be received. Keep sending in
+        // case the first packets are dropped while the TAP comes up.
+        let mut sent_at = Instant::now() - *context::ONE_SEC;
+        loop {
+            if Instant::now() - sent_at >= Duration::from_millis(100) {
+                stranger.send_to(&[0], std_server_addr).unwrap();
+                peer.send_to(&[1], std_server_addr).unwrap();
+                sent_at = Instant::now();
+            }
+
+            env::tick(&mut context.interface, &mut context.socket_set);
+            let udp_socket = context
+                .socket_set
+                .socket(udp_handle)
+                .unwrap()
+                .as_udp_socket();
+            if let Ok((payload, addr)) = udp_socket.recv() {
+                assert_eq!(payload, &[1]);
+                assert_eq!(addr.port, peer.local_addr().unwrap().port());
+                break;
+            }
+        }
+
+        // Packets can be sent to the peer without specifying an address.
+        context
+            .socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send_connected(1)
+            .unwrap()[0] = 2;
+
+        let mut buffer = [0; 2];
+        loop {
+            env::tick(&mut context.interface, &mut context.socket_set);
+            if let Ok((size, addr)) = peer.recv_from(&mut buffer) {
+                assert_eq!(&buffer[.. size], &[2]);
+                assert_eq!(addr, std_server_addr);
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn udp_broadcast() {
+    context::run(|context| {
+        let server_addr = SocketAddr {
+            addr: *context.interface.ipv4_addr,
+            port: context::rand_port(),
+        };
+
+        let peer = UdpSocket::bind("0:0").unwrap();
+        peer.set_broadcast(true).unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let peer_port = peer.local_addr().unwrap().port();
+
+        let mut udp_socket = context.socket_env.udp_socket(server_addr).unwrap();
+        udp_socket.set_broadcast(true);
+        let udp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        // Receive a packet sent to the subnet broadcast address.
+        let subnet_broadcast = context.interface.ipv4_addr.broadcast();
+        let subnet_broadcast = StdSocketAddrV4::new(subnet_broadcast.into(), server_addr.port);
+        let mut sent_at = Instant::now() - *context::ONE_SEC;
+        loop {
+            if Instant::now() - sent_at >= Duration::from_millis(100) {
+                peer.send_to(&[1], subnet_broadcast).unwrap();
+                sent_at = Instant::now();
+            }
+
+            env::tick(&mut context.interface, &mut context.socket_set);
+            let udp_socket = context
+                .socket_set
+                .socket(udp_handle)
+                .unwrap()
+                .as_udp_socket();
+            if let Ok((payload, addr)) = udp_socket.recv() {
+                assert_eq!(payload, &[1]);
+                assert_eq!(addr.port, peer_port);
+                break;
+            }
+        }
+
+        // Send a packet to the limited broadcast address.
+        let broadcast_addr = SocketAddr {
+            addr: Ipv4Address::BROADCAST,
+            port: peer_port,
+        };
+        context
+            .socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(1, broadcast_addr)
+            .unwrap()[0] = 2;
+
+        let mut buffer = [0; 2];
+        loop {
+            env::tick(&mut context.interface, &mut context.socket_set);
+            if let Ok((size, _)) = peer.recv_from(&mut buffer) {
+                assert_eq!(&buffer[.. size], &[2]);
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn udp_fragmented() {
+    context::run(|context| {
+        let server_addr = SocketAddr {
+            addr: *context.interface.ipv4_addr,
+            port: context::rand_port(),
+        };
+        let std_server_addr = StdSocketAddr::V4(server_addr.into());
+
+        let peer = UdpSocket::bind("0:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+
+        let udp_socket = context.socket_env.udp_socket(server_addr).unwrap();
+        let udp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        // A packet larger than the MTU arrives in fragments which should be
+        // reassembled.
+        let send: Vec<u8> = (0 .. 4000).map(|_| rand::random::<u8>()).collect();
+        let mut sent_at = Instant::now() - *context::ONE_SEC;
+        let peer_addr = loop {
+            if Instant::now() - sent_at >= Duration::from_millis(100) {
+                peer.send_to(&send[..], std_server_addr).unwrap();
+                sent_at = Instant::now();
+            }
+
+            env::tick(&mut context.interface, &mut context.socket_set);
+            let udp_socket = context
+                .socket_set
+                .socket(udp_handle)
+                .unwrap()
+                .as_udp_socket();
+            if let Ok((payload, addr)) = udp_socket.recv() {
+                assert_eq!(payload, &send[..]);
+                break addr;
+            }
+        };
+
+        // Sending the packet back should fragment it.
+        context
+            .socket_set
+            .socket(udp_handle)
+            .unwrap()
+            .as_udp_socket()
+            .send(send.len(), peer_addr)
+            .unwrap()
+            .copy_from_slice(&send[..]);
+
+        let mut buffer = [0; 4001];
+        loop {
+            env::tick(&mut context.interface, &mut context.socket_set);
+            if let Ok((size, addr)) = peer.recv_from(&mut buffer) {
+                assert_eq!(&buffer[.. size], &send[..]);
+                assert_eq!(addr, std_server_addr);
+                break;
+            }
+        }
+    });
+}
+
+#[test]
+fn udp_port_unreachable() {
+    context::run(|context| {
+        let server_addr = SocketAddr {
+            addr: *context.interface.ipv4_addr,
+            port: context::rand_port(),
+        };
+
+        // Find a port which is not in use on the host.
+        let closed_port = UdpSocket::bind("0:0").unwrap().local_addr().unwrap().port();
+
+        let mut udp_socket = context.socket_env.udp_socket(server_addr).unwrap();
+        udp_socket.connect(SocketAddr {
+            addr: *env::DEFAULT_IPV4_GATEWAY,
+            port: closed_port,
+        });
+        let udp_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Udp(udp_socket))
+            .unwrap();
+
+        // The host should respond with an ICMP port unreachable error which is
+        // returned by the socket.
+        let mut sent_at = Instant::now() - *context::ONE_SEC;
+        loop {
+            let udp_socket = context
+                .socket_set
+                .socket(udp_handle)
+                .unwrap()
+                .as_udp_socket();
+            let result = if Instant::now() - sent_at >= Duration::from_millis(100) {
+                sent_at = Instant::now();
+                udp_socket.send_connected(1).map(|_| ())
+            } else {
+                udp_socket.recv().map(|_| ())
+            };
+
+            match result {
+                Err(Error::Remote(RemoteError::Icmp(icmp_repr))) => {
+                    assert_eq!(
+                        icmp_repr.message,
+                        Icmpv4Message::DestinationUnreachable(
+                            Icmpv4DestinationUnreachable::PortUnreachable
+                        )
+                    );
+                    break;
+                }
+                _ => env::tick(&mut context.interface, &mut context.socket_set),
+            }
+        }
+    });
+}
+
+#[test]
+fn udp_raw_socket_fills_source() {
+    context::run(|context| {
+        let peer = UdpSocket::bind("0:0").unwrap();
+        peer.set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        let peer_port = peer.local_addr().unwrap().port();
+
+        let raw_socket = context.socket_env.raw_socket(RawType::Ipv4);
+        let raw_handle = context
+            .socket_set
+            .add_socket(TaggedSocket::Raw(raw_socket))
+            .unwrap();
+
+        // Send a UDP packet without a source address or checksum, the
+        // interface address should be filled in.
+        let udp_repr = UdpRepr {
+            src_port: context::rand_port(),
+            dst_port: peer_port,
+            length: 9,
+            checksum: false,
+        };
+        let ipv4_repr = Ipv4Repr {
+            src_addr: Ipv4Address::new([0, 0, 0, 0]),
+            dst_addr: *env::DEFAULT_IPV4_GATEWAY,
+            protocol: Ipv4Protocol::UDP,
+            payload_len: udp_repr.buffer_len() as u16,
+            ttl: Ipv4Repr::DEFAULT_TTL,
+            dscp: 0,
+            flags: ipv4_flags::DONT_FRAGMENT,
+            options: Ipv4Options::new(),
+        };
+
+        let mut buffer = [0; 2];
+        let mut sent_at = Instant::now() - *context::ONE_SEC;
+        loop {
+            if Instant::now() - sent_at >= Duration::from_millis(100) {
+                let raw_socket = context
+                    .socket_set
+                    .socket(raw_handle)
+                    .unwrap()
+                    .as_raw_socket();
+                let ipv4_buffer = raw_socket.send(ipv4_repr.buffer_len()).unwrap();
+                let mut ipv4_packet = Ipv4Packet::try_new(ipv4_buffer).unwrap();
+                ipv4_repr.serialize(&mut ipv4_packet);
+                let mut udp_packet = UdpPacket::try_new(ipv4_packet.payload_mut()).unwrap();
+                udp_packet.payload_mut()[0] = 7;
+                udp_repr.serialize(&mut udp_packet, &ipv4_repr);
+                sent_at = Instant::now();
+            }
+
+            env::tick(&mut context.interface, &mut context.socket_set);
+            if let Ok((size, addr)) = peer.recv_from(&mut buffer) {
+                assert_eq!(&buffer[.. size], &[7]);
+                assert_eq!(
+                    addr.ip(),
+                    StdIpAddr::V4((*context.interface.ipv4_addr).into())
+                );
+                break;
+            }
+        }
+    });
+}